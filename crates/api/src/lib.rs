@@ -1,4 +1,5 @@
-//! The serializable types for the Warg REST API.
+//! The serializable types for the Warg registry API, shared by its JSON (REST)
+//! and protobuf transports.
 #![deny(missing_docs)]
 
 pub mod v1;
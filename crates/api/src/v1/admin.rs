@@ -0,0 +1,172 @@
+//! Types relating to the admin API.
+
+use crate::Status;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use thiserror::Error;
+use warg_crypto::signing::KeyID;
+use warg_protocol::{
+    registry::{LogId, PackageName, RecordId},
+    ProtoEnvelopeBody,
+};
+
+/// Represents a request to execute administrative commands.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminCommandRequest<'a> {
+    /// The operator record of admin command, advisory, and identity claim
+    /// entries, signed by a key with the admin permission.
+    ///
+    /// The record must build on the current head of the operator log.
+    pub record: Cow<'a, ProtoEnvelopeBody>,
+}
+
+/// Represents the response to an admin command request.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminCommandResponse {
+    /// The identifier of the operator record recording the commands.
+    pub record_id: RecordId,
+    /// A description of the outcome of each entry, in record order.
+    pub results: Vec<String>,
+}
+
+/// Represents a package record rejected by the registry and held in
+/// quarantine for inspection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedRecord {
+    /// The identifier of the record in the quarantine.
+    pub id: u64,
+    /// The package log the record was published to.
+    pub log_id: LogId,
+    /// The name of the package the record was published to.
+    pub package_name: PackageName,
+    /// The identifier of the rejected record.
+    pub record_id: RecordId,
+    /// The key that signed the rejected record.
+    pub submitter: KeyID,
+    /// The reason the registry rejected the record.
+    pub reason: String,
+    /// The time the record was rejected, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The headers of the publish request.
+    ///
+    /// The values of headers that may carry secrets are redacted.
+    pub headers: IndexMap<String, String>,
+    /// The rejected record.
+    pub record: ProtoEnvelopeBody,
+}
+
+/// Represents the response to a request for the quarantined records.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineResponse {
+    /// The quarantined records, oldest first.
+    pub records: Vec<QuarantinedRecord>,
+}
+
+/// Represents an admin API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum AdminError {
+    /// The commands were not authorized by the operator log.
+    #[error("unauthorized operation: {0}")]
+    Unauthorized(String),
+    /// The commands were rejected by the registry.
+    #[error("the admin commands were rejected by the registry: {0}")]
+    Rejection(String),
+    /// A command is not supported by the registry.
+    #[error("the requested operation is not supported: {0}")]
+    NotSupported(String),
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl AdminError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Unauthorized(_) => 401,
+            Self::Rejection(_) => 422,
+            Self::NotSupported(_) => 501,
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    Unauthorized {
+        status: Status<401>,
+        message: Cow<'a, str>,
+    },
+    Rejection {
+        status: Status<422>,
+        message: Cow<'a, str>,
+    },
+    NotSupported {
+        status: Status<501>,
+        message: Cow<'a, str>,
+    },
+    Message {
+        status: u16,
+        message: Cow<'a, str>,
+    },
+}
+
+impl Serialize for AdminError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Unauthorized(message) => RawError::Unauthorized {
+                status: Status::<401>,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+            Self::Rejection(message) => RawError::Rejection {
+                status: Status::<422>,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+            Self::NotSupported(message) => RawError::NotSupported {
+                status: Status::<501>,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AdminError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::Unauthorized { status: _, message } => {
+                Ok(Self::Unauthorized(message.into_owned()))
+            }
+            RawError::Rejection { status: _, message } => Ok(Self::Rejection(message.into_owned())),
+            RawError::NotSupported { status: _, message } => {
+                Ok(Self::NotSupported(message.into_owned()))
+            }
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Types relating to the cold storage layout of an archived registry log.
+//!
+//! An archive holds old ranges of the registry log as plain files so that
+//! they may be kept in any object store:
+//!
+//! ```text
+//! manifest.json                   the signed archive manifest
+//! segments/<start>-<end>.json     the records of a range of the registry log
+//! ```
+//!
+//! The manifest commits to the digest of each segment file; each segment
+//! carries inclusion proofs of its records in the manifest's checkpoint.
+
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use warg_protocol::{
+    registry::{LogId, RegistryIndex},
+    PublishedProtoEnvelopeBody,
+};
+
+/// The path of the signed archive manifest.
+pub fn manifest() -> &'static str {
+    "manifest.json"
+}
+
+/// The path of the segment holding the given range of the registry log.
+pub fn segment(start: RegistryIndex, end: RegistryIndex) -> String {
+    format!("segments/{start}-{end}.json")
+}
+
+/// Represents a record of an archive segment.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentRecord {
+    /// The log the record belongs to.
+    pub log_id: LogId,
+    /// The published record.
+    pub record: PublishedProtoEnvelopeBody,
+}
+
+/// Represents a segment of an archive.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSegment {
+    /// The records of the segment, in registry order.
+    pub records: Vec<SegmentRecord>,
+    /// The bytes of the log proof bundle of the records' inclusion in the
+    /// manifest's checkpoint.
+    #[serde_as(as = "Base64")]
+    pub proof: Vec<u8>,
+}
@@ -0,0 +1,15 @@
+//! Types relating to the feed API.
+
+use serde::{Deserialize, Serialize};
+
+/// The media type of the feed API response, an Atom feed.
+pub const FEED_CONTENT_TYPE: &str = "application/atom+xml";
+
+/// Represents the query parameters of a feed request.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedQuery {
+    /// The maximum number of the latest registry records to include.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
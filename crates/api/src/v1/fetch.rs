@@ -2,16 +2,21 @@
 
 use crate::Status;
 use indexmap::IndexMap;
+use prost::Message;
 use serde::{de::Unexpected, Deserialize, Serialize, Serializer};
 use std::borrow::Cow;
 use std::str::FromStr;
 use thiserror::Error;
-use warg_crypto::hash::AnyHash;
+use warg_crypto::hash::{AnyHash, AnyHashError};
+use warg_protobuf::protocol as protobuf;
 use warg_protocol::{
     registry::{LogId, PackageName, RegistryLen},
-    PublishedProtoEnvelopeBody,
+    ParseEnvelopeError, ProtoEnvelopeBody, PublishedProtoEnvelopeBody,
 };
 
+/// The content type of a streamed fetch logs response.
+pub const FETCH_LOGS_STREAM_CONTENT_TYPE: &str = "application/vnd.warg.fetch-logs-stream";
+
 /// Wraps the PublishedProtoEnvelopeBody with a fetch token.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -83,6 +88,147 @@ pub struct FetchWarning {
     pub message: String,
 }
 
+/// Represents a frame of a streamed fetch logs response.
+///
+/// A streamed fetch logs response is a sequence of frames, each encoded as a
+/// length-delimited protobuf message, ending with a [`FetchLogsFrame::End`]
+/// frame. This allows clients to validate records as they are received
+/// rather than buffering the entire response.
+#[derive(Debug)]
+pub enum FetchLogsFrame {
+    /// An operator record.
+    Operator(PublishedRecord),
+    /// A package record.
+    Package {
+        /// The log of the record.
+        log_id: LogId,
+        /// The record.
+        record: PublishedRecord,
+    },
+    /// The end of the response.
+    End {
+        /// Whether there are more records to fetch.
+        more: bool,
+        /// An optional list of warnings.
+        warnings: Vec<FetchWarning>,
+    },
+}
+
+impl FetchLogsFrame {
+    /// Encodes the frame, prefixed by its length.
+    pub fn encode_length_delimited(&self) -> Vec<u8> {
+        use protobuf::fetch_logs_frame::Contents;
+
+        let record = |log_id: Option<&LogId>, record: &PublishedRecord| {
+            Contents::Record(protobuf::FetchedRecord {
+                log_id: log_id.map(ToString::to_string),
+                envelope: record.envelope.envelope.to_protobuf(),
+                registry_index: record.envelope.registry_index as u64,
+                fetch_token: record.fetch_token.clone(),
+            })
+        };
+
+        let contents = match self {
+            Self::Operator(r) => record(None, r),
+            Self::Package { log_id, record: r } => record(Some(log_id), r),
+            Self::End { more, warnings } => Contents::End(protobuf::FetchLogsEnd {
+                more: *more,
+                warnings: warnings.iter().map(|w| w.message.clone()).collect(),
+            }),
+        };
+
+        protobuf::FetchLogsFrame {
+            contents: Some(contents),
+        }
+        .encode_length_delimited_to_vec()
+    }
+
+    /// Encodes a frame ending the response with the given error, prefixed by
+    /// its length.
+    ///
+    /// This is used when an error occurs after the response has started.
+    pub fn encode_error_length_delimited(error: &FetchError) -> Vec<u8> {
+        protobuf::FetchLogsFrame {
+            contents: Some(protobuf::fetch_logs_frame::Contents::Error(
+                protobuf::FetchLogsError {
+                    error: serde_json::to_string(error)
+                        .expect("fetch error should serialize to JSON"),
+                },
+            )),
+        }
+        .encode_length_delimited_to_vec()
+    }
+
+    /// Decodes a frame from its protobuf representation, without its length
+    /// prefix.
+    ///
+    /// A frame ending the response with an error is returned as
+    /// [`FetchLogsFrameError::Fetch`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, FetchLogsFrameError> {
+        use protobuf::fetch_logs_frame::Contents;
+
+        match protobuf::FetchLogsFrame::decode(bytes)?.contents {
+            Some(Contents::Record(record)) => {
+                let published = PublishedRecord {
+                    envelope: PublishedProtoEnvelopeBody {
+                        envelope: ProtoEnvelopeBody::from_protobuf(&record.envelope)?,
+                        registry_index: record
+                            .registry_index
+                            .try_into()
+                            .map_err(|_| FetchLogsFrameError::RegistryIndex)?,
+                    },
+                    fetch_token: record.fetch_token,
+                };
+                match record.log_id {
+                    Some(log_id) => Ok(Self::Package {
+                        log_id: log_id.parse::<AnyHash>()?.into(),
+                        record: published,
+                    }),
+                    None => Ok(Self::Operator(published)),
+                }
+            }
+            Some(Contents::End(end)) => Ok(Self::End {
+                more: end.more,
+                warnings: end
+                    .warnings
+                    .into_iter()
+                    .map(|message| FetchWarning { message })
+                    .collect(),
+            }),
+            Some(Contents::Error(error)) => Err(FetchLogsFrameError::Fetch(
+                serde_json::from_str(&error.error).map_err(FetchLogsFrameError::Error)?,
+            )),
+            None => Err(FetchLogsFrameError::Empty),
+        }
+    }
+}
+
+/// Represents an error decoding a [`FetchLogsFrame`].
+#[derive(Debug, Error)]
+pub enum FetchLogsFrameError {
+    /// The frame is not a valid protobuf message.
+    #[error("failed to decode fetch logs frame: {0}")]
+    Decode(#[from] prost::DecodeError),
+    /// The frame has no contents.
+    #[error("fetch logs frame has no contents")]
+    Empty,
+    /// The record envelope is invalid.
+    #[error("failed to parse fetched record envelope: {0}")]
+    Envelope(#[from] ParseEnvelopeError),
+    /// The log identifier is invalid.
+    #[error("invalid log id of fetched record: {0}")]
+    LogId(#[from] AnyHashError),
+    /// The registry index is out of range.
+    #[error("registry index of fetched record is out of range")]
+    RegistryIndex,
+    /// The server ended the response with an error.
+    #[error(transparent)]
+    Fetch(FetchError),
+    /// The error ending the response is invalid.
+    #[error("failed to parse fetch logs error frame: {0}")]
+    Error(#[source] serde_json::Error),
+}
+
 /// Represents a fetch API error.
 #[non_exhaustive]
 #[derive(Debug, Error)]
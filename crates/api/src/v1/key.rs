@@ -0,0 +1,127 @@
+//! Types relating to the key activity API.
+
+use crate::Status;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use thiserror::Error;
+use warg_protocol::registry::{LogId, PackageName, RecordId, RegistryIndex};
+
+/// Represents the query parameters of a key activity request.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRecordsQuery {
+    /// Only records with a registry index greater than this index are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<RegistryIndex>,
+    /// The maximum number of records to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a record signed by a key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRecord {
+    /// The log of the record.
+    pub log_id: LogId,
+    /// The name of the package, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<PackageName>,
+    /// The identifier of the record.
+    pub record_id: RecordId,
+    /// The index of the record in the registry log.
+    pub registry_index: RegistryIndex,
+}
+
+/// Represents a key activity response.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRecordsResponse {
+    /// Whether there are more records to fetch.
+    #[serde(default)]
+    pub more: bool,
+    /// The package records signed by the key, in registry order.
+    ///
+    /// A record is signed by a key if the key signed its envelope or any of
+    /// its entries.
+    pub records: Vec<KeyRecord>,
+}
+
+/// Represents a key activity API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum KeyError {
+    /// Key activity queries are not supported by the registry.
+    #[error("key activity queries are not supported by the registry")]
+    NotSupported,
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl KeyError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::NotSupported => 501,
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum EntityType {
+    KeyActivity,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    NotSupported {
+        status: Status<501>,
+        #[serde(rename = "type")]
+        ty: EntityType,
+    },
+    Message {
+        status: u16,
+        message: Cow<'a, str>,
+    },
+}
+
+impl Serialize for KeyError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::NotSupported => RawError::NotSupported {
+                status: Status::<501>,
+                ty: EntityType::KeyActivity,
+            }
+            .serialize(serializer),
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::NotSupported { .. } => Ok(Self::NotSupported),
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
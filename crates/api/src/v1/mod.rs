@@ -1,12 +1,19 @@
 //! Types representing v1 of the Warg REST API.
 
+pub mod admin;
+pub mod archive;
 pub mod content;
+pub mod feed;
 pub mod fetch;
+pub mod key;
 pub mod ledger;
 pub mod monitor;
 pub mod package;
 pub mod paths;
 pub mod proof;
+pub mod protobuf;
+pub mod search;
+pub mod static_site;
 
 use serde::{Deserialize, Serialize};
 
@@ -4,13 +4,14 @@ pub use super::ContentSource;
 use crate::Status;
 use indexmap::IndexMap;
 use serde::{de::Unexpected, Deserialize, Serialize, Serializer};
+use serde_with::{base64::Base64, serde_as};
 use std::borrow::Cow;
 use std::str::FromStr;
 use thiserror::Error;
 use warg_crypto::hash::AnyHash;
 use warg_protocol::{
-    registry::{LogId, PackageName, RecordId, RegistryIndex},
-    ProtoEnvelopeBody,
+    registry::{LogId, PackageName, RecordId, RecordReceipt, RegistryIndex},
+    CoPublication, ProtoEnvelopeBody, SerdeEnvelope, Version,
 };
 
 /// Represents the supported kinds of content upload endpoints.
@@ -52,6 +53,32 @@ pub struct PublishRecordRequest<'a> {
     /// A registry may not support specifying content sources directly.
     #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
     pub content_sources: IndexMap<AnyHash, Vec<ContentSource>>,
+    /// The head of the package log the record was built against.
+    ///
+    /// If present, it must match the `prev` of the record and the registry
+    /// rejects the record if the log's current head differs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_head: Option<RecordId>,
+    /// The signed co-publication the record is a part of.
+    ///
+    /// If present, the registry holds the record until every part of the
+    /// co-publication has been published and sequences all of the parts
+    /// into the same checkpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copublication: Option<SerdeEnvelope<CoPublication>>,
+}
+
+/// Represents a receipt for a published package record.
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageRecordReceipt {
+    /// The receipt, signed by the registry operator.
+    pub receipt: SerdeEnvelope<RecordReceipt>,
+    /// The bytes of the log inclusion proof bundle of the record in the
+    /// checkpoint of the receipt.
+    #[serde_as(as = "Base64")]
+    pub proof: Vec<u8>,
 }
 
 /// Represents a package record API entity in a registry.
@@ -79,6 +106,7 @@ impl PackageRecord {
 
 /// Represents a package record in one of the following states:
 /// * `sourcing` - The record is sourcing content.
+/// * `staged` - The record is awaiting an operator countersignature.
 /// * `processing` - The record is being processed.
 /// * `rejected` - The record was rejected.
 /// * `published` - The record was published to the log.
@@ -92,6 +120,9 @@ pub enum PackageRecordState {
         /// The digests of the missing content.
         missing_content: IndexMap<AnyHash, MissingContent>,
     },
+    /// The package record is staged until an operator countersignature is attached.
+    #[serde(rename_all = "camelCase")]
+    Staged,
     /// The package record is processing.
     #[serde(rename_all = "camelCase")]
     Processing,
@@ -100,6 +131,10 @@ pub enum PackageRecordState {
     Rejected {
         /// The reason the record was rejected.
         reason: String,
+        /// The release the record conflicted with, if it was rejected for
+        /// releasing a version that another record released first.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        conflict: Option<ReleaseConflict>,
     },
     /// The package record was successfully published to the log.
     #[serde(rename_all = "camelCase")]
@@ -109,6 +144,16 @@ pub enum PackageRecordState {
     },
 }
 
+/// Represents a release that conflicted with a rejected package record.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseConflict {
+    /// The version released by both records.
+    pub version: Version,
+    /// The identifier of the record that released the version.
+    pub record_id: RecordId,
+}
+
 /// Represents a package API error.
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -137,6 +182,18 @@ pub enum PackageError {
     /// The package was rejected by the registry, due to a conflict with a pending publish.
     #[error("the package conflicts with pending publish of record `{0}`")]
     ConflictPendingPublish(RecordId),
+    /// The record was built against a head that is not the current head of the log.
+    #[error("{}", match current_head {
+        Some(head) => format!("the package log head has moved to record `{head}`"),
+        None => "the package log has no head".to_string(),
+    })]
+    HeadMismatch {
+        /// The current head of the package log, if the log exists.
+        current_head: Option<RecordId>,
+    },
+    /// The record was already submitted to the registry.
+    #[error("record `{0}` has already been submitted")]
+    RecordAlreadySubmitted(RecordId),
     /// The package was rejected by the registry.
     #[error("the package was rejected by the registry: {0}")]
     Rejection(String),
@@ -156,7 +213,10 @@ impl PackageError {
         match self {
             Self::Unauthorized { .. } => 401,
             Self::LogNotFound(_) | Self::RecordNotFound(_) | Self::NamespaceNotDefined(_) => 404,
-            Self::NamespaceImported(_) | Self::ConflictPendingPublish(_) => 409,
+            Self::NamespaceImported(_)
+            | Self::ConflictPendingPublish(_)
+            | Self::HeadMismatch { .. }
+            | Self::RecordAlreadySubmitted(_) => 409,
             Self::RecordNotSourcing => 405,
             Self::Rejection(_) => 422,
             Self::NotSupported(_) => 501,
@@ -173,6 +233,8 @@ enum EntityType {
     Namespace,
     NamespaceImport,
     Name,
+    Head,
+    SubmittedRecord,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -198,6 +260,13 @@ where
         ty: EntityType,
         id: Cow<'a, T>,
     },
+    HeadMismatch {
+        status: Status<409>,
+        #[serde(rename = "type")]
+        ty: EntityType,
+        #[serde(rename = "currentHead")]
+        current_head: Option<Cow<'a, T>>,
+    },
     RecordNotSourcing {
         status: Status<405>,
     },
@@ -253,6 +322,18 @@ impl Serialize for PackageError {
                 id: Cow::Borrowed(record_id),
             }
             .serialize(serializer),
+            Self::RecordAlreadySubmitted(record_id) => RawError::Conflict {
+                status: Status::<409>,
+                ty: EntityType::SubmittedRecord,
+                id: Cow::Borrowed(record_id),
+            }
+            .serialize(serializer),
+            Self::HeadMismatch { current_head } => RawError::HeadMismatch {
+                status: Status::<409>,
+                ty: EntityType::Head,
+                current_head: current_head.as_ref().map(Cow::Borrowed),
+            }
+            .serialize(serializer),
             Self::RecordNotSourcing => RawError::RecordNotSourcing::<()> {
                 status: Status::<405>,
             }
@@ -321,11 +402,41 @@ impl<'de> Deserialize<'de> for PackageError {
                         })?
                         .into(),
                 )),
+                EntityType::SubmittedRecord => Ok(Self::RecordAlreadySubmitted(
+                    AnyHash::from_str(&id)
+                        .map_err(|_| {
+                            serde::de::Error::invalid_value(
+                                Unexpected::Str(&id),
+                                &"a valid record id",
+                            )
+                        })?
+                        .into(),
+                )),
                 _ => Err(serde::de::Error::invalid_value(
                     Unexpected::Enum,
                     &"a valid entity type",
                 )),
             },
+            RawError::HeadMismatch {
+                status: _,
+                ty: EntityType::Head,
+                current_head,
+            } => Ok(Self::HeadMismatch {
+                current_head: current_head
+                    .map(|id| {
+                        AnyHash::from_str(&id).map(Into::into).map_err(|_| {
+                            serde::de::Error::invalid_value(
+                                Unexpected::Str(&id),
+                                &"a valid record id",
+                            )
+                        })
+                    })
+                    .transpose()?,
+            }),
+            RawError::HeadMismatch { .. } => Err(serde::de::Error::invalid_value(
+                Unexpected::Enum,
+                &"a valid entity type",
+            )),
             RawError::RecordNotSourcing { status: _ } => Ok(Self::RecordNotSourcing),
             RawError::Rejection { status: _, message } => Ok(Self::Rejection(message.into_owned())),
             RawError::NotSupported { status: _, message } => {
@@ -1,23 +1,81 @@
 //! The paths of the Warg REST API.
 
-use warg_crypto::hash::AnyHash;
-use warg_protocol::registry::{LogId, RecordId};
+use warg_crypto::{hash::AnyHash, signing::KeyID};
+use warg_protocol::registry::{LogId, RecordId, RegistryLen};
+
+/// The path of the operator keys discovery document.
+///
+/// The document is not versioned with the API, so that clients can locate
+/// it before anything else about the registry is known.
+pub fn operator_keys() -> &'static str {
+    ".well-known/warg/operator-keys"
+}
+
+/// The path of the "admin command" API.
+pub fn admin_command() -> &'static str {
+    "v1/admin/command"
+}
+
+/// The path of the quarantined records.
+pub fn admin_quarantine() -> &'static str {
+    "v1/admin/quarantine"
+}
+
+/// The path of a quarantined record.
+pub fn admin_quarantined_record(id: u64) -> String {
+    format!("v1/admin/quarantine/{id}")
+}
+
+/// The path for requeuing a quarantined record.
+pub fn admin_requeue_quarantined_record(id: u64) -> String {
+    format!("v1/admin/quarantine/{id}/requeue")
+}
+
+/// The path of the Atom feed of registry activity.
+pub fn feed() -> &'static str {
+    "v1/feed"
+}
 
 /// The path of the "fetch logs" API.
 pub fn fetch_logs() -> &'static str {
     "v1/fetch/logs"
 }
 
+/// The path of the "fetch logs" API streaming a length-delimited protobuf response.
+pub fn fetch_logs_stream() -> &'static str {
+    "v1/fetch/logs/stream"
+}
+
 /// The path of the "fetch checkpoint" API.
 pub fn fetch_checkpoint() -> &'static str {
     "v1/fetch/checkpoint"
 }
 
+/// The path of the "fetch checkpoint" API for the checkpoint at a log length.
+pub fn fetch_checkpoint_at(log_length: RegistryLen) -> String {
+    format!("v1/fetch/checkpoint/{log_length}")
+}
+
+/// The path of the "fetch freshness assertion" API.
+pub fn fetch_freshness() -> &'static str {
+    "v1/fetch/freshness"
+}
+
+/// The path of the "fetch package filter" API.
+pub fn fetch_package_filter() -> &'static str {
+    "v1/fetch/filter"
+}
+
 /// The path of the "fetch package names" API.
 pub fn fetch_package_names() -> &'static str {
     "v1/fetch/names"
 }
 
+/// The path of the records signed by a key.
+pub fn key_records(key_id: &KeyID) -> String {
+    format!("v1/key/{key_id}/records")
+}
+
 /// The path of the get ledger sources.
 pub fn ledger_sources() -> &'static str {
     "v1/ledger"
@@ -38,6 +96,16 @@ pub fn package_record(log_id: &LogId, record_id: &RecordId) -> String {
     format!("v1/package/{log_id}/record/{record_id}")
 }
 
+/// The path for the receipt of a published package record.
+pub fn package_record_receipt(log_id: &LogId, record_id: &RecordId) -> String {
+    format!("v1/package/{log_id}/record/{record_id}/receipt")
+}
+
+/// The path for attaching an operator countersignature to a staged package record.
+pub fn package_record_countersignature(log_id: &LogId, record_id: &RecordId) -> String {
+    format!("v1/package/{log_id}/record/{record_id}/countersignature")
+}
+
 /// The path for proving checkpoint consistency.
 pub fn prove_consistency() -> &'static str {
     "v1/proof/consistency"
@@ -48,6 +116,16 @@ pub fn prove_inclusion() -> &'static str {
     "v1/proof/inclusion"
 }
 
+/// The path for proving the changed map entries between two checkpoints.
+pub fn prove_delta() -> &'static str {
+    "v1/proof/delta"
+}
+
+/// The path for searching packages.
+pub fn search() -> &'static str {
+    "v1/search"
+}
+
 /// The path for verifying a checkpoint.
 pub fn verify_checkpoint() -> &'static str {
     "v1/verify/checkpoint"
@@ -6,7 +6,7 @@ use serde_with::{base64::Base64, serde_as};
 use std::borrow::Cow;
 use thiserror::Error;
 use warg_crypto::hash::AnyHash;
-use warg_protocol::registry::{LogId, RegistryIndex, RegistryLen};
+use warg_protocol::registry::{LogId, LogLeaf, RegistryIndex, RegistryLen};
 
 /// Represents a consistency proof request.
 #[derive(Serialize, Deserialize)]
@@ -51,6 +51,34 @@ pub struct InclusionResponse {
     pub map: Vec<u8>,
 }
 
+/// Represents a checkpoint delta request.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaRequest {
+    /// The log length of the earlier checkpoint.
+    pub from: RegistryLen,
+    /// The log length of the later checkpoint.
+    pub to: RegistryLen,
+}
+
+/// Represents a checkpoint delta response.
+///
+/// The delta describes only the map entries whose values changed between
+/// the two checkpoints, so a monitor can update its view of the map without
+/// re-deriving the entries that did not change.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaResponse {
+    /// The map entries that changed, with their values at the later
+    /// checkpoint, in registry log order of their last change.
+    pub changes: Vec<LogLeaf>,
+    /// The bytes of the map inclusion proof bundle of the changes at the
+    /// later checkpoint.
+    #[serde_as(as = "Base64")]
+    pub map: Vec<u8>,
+}
+
 /// Represents a proof API error.
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -0,0 +1,767 @@
+//! The protobuf representation of the v1 API types.
+//!
+//! The REST API exchanges the types of this crate as JSON; other transports
+//! exchange the same types as the protobuf messages of the `warg.api`
+//! package, converting with the [`Protobuf`] trait.
+
+use super::{
+    fetch::{
+        FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest, FetchPackageNamesResponse,
+        FetchWarning, PublishedRecord,
+    },
+    package::{
+        MissingContent, PackageRecord, PackageRecordState, PublishRecordRequest, ReleaseConflict,
+        UploadEndpoint,
+    },
+    proof::{ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse},
+    ContentSource,
+};
+use indexmap::IndexMap;
+use prost::Message;
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+use thiserror::Error;
+use warg_crypto::hash::AnyHash;
+use warg_protobuf::api as protobuf;
+use warg_protocol::{
+    registry::{Checkpoint, LogId, PackageName, TimestampedCheckpoint},
+    CoPublication, CoPublishedRecord, ProtoEnvelopeBody, PublishedProtoEnvelopeBody, SerdeEnvelope,
+    Version,
+};
+
+/// Represents an error converting a protobuf message to an API type.
+#[derive(Debug, Error)]
+pub enum ProtobufError {
+    /// The bytes are not a valid protobuf message.
+    #[error("failed to decode protobuf message: {0}")]
+    Decode(#[from] prost::DecodeError),
+    /// A required field of the message is not set.
+    #[error("protobuf message is missing field `{0}`")]
+    MissingField(&'static str),
+    /// A field of the message has an invalid value.
+    #[error("protobuf message has an invalid `{field}`: {message}")]
+    InvalidField {
+        /// The name of the field.
+        field: &'static str,
+        /// The reason the value is invalid.
+        message: String,
+    },
+}
+
+/// An API type with a protobuf representation.
+pub trait Protobuf: Sized {
+    /// The protobuf message representing the type.
+    type Message: Message + Default;
+
+    /// Converts the value to its protobuf message.
+    fn to_protobuf(&self) -> Self::Message;
+
+    /// Converts a protobuf message to a value.
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError>;
+
+    /// Encodes the value as protobuf bytes.
+    fn encode_protobuf(&self) -> Vec<u8> {
+        self.to_protobuf().encode_to_vec()
+    }
+
+    /// Decodes a value from protobuf bytes.
+    fn decode_protobuf(bytes: &[u8]) -> Result<Self, ProtobufError> {
+        Self::from_protobuf(Self::Message::decode(bytes)?)
+    }
+}
+
+fn parse<T>(field: &'static str, value: &str) -> Result<T, ProtobufError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e: T::Err| ProtobufError::InvalidField {
+            field,
+            message: e.to_string(),
+        })
+}
+
+fn parse_index(field: &'static str, value: u64) -> Result<usize, ProtobufError> {
+    value.try_into().map_err(|_| ProtobufError::InvalidField {
+        field,
+        message: "value is out of range".into(),
+    })
+}
+
+fn parse_envelope(field: &'static str, bytes: &[u8]) -> Result<ProtoEnvelopeBody, ProtobufError> {
+    ProtoEnvelopeBody::from_protobuf(bytes).map_err(|e| ProtobufError::InvalidField {
+        field,
+        message: e.to_string(),
+    })
+}
+
+impl Protobuf for ContentSource {
+    type Message = protobuf::ContentSource;
+
+    fn to_protobuf(&self) -> Self::Message {
+        use protobuf::content_source::Kind;
+
+        let kind = match self {
+            Self::HttpGet {
+                url,
+                accept_ranges,
+                size,
+            } => Kind::HttpGet(protobuf::HttpGetSource {
+                url: url.clone(),
+                accept_ranges: *accept_ranges,
+                size: *size,
+            }),
+        };
+        protobuf::ContentSource { kind: Some(kind) }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        use protobuf::content_source::Kind;
+
+        match message.kind.ok_or(ProtobufError::MissingField("kind"))? {
+            Kind::HttpGet(source) => Ok(Self::HttpGet {
+                url: source.url,
+                accept_ranges: source.accept_ranges,
+                size: source.size,
+            }),
+        }
+    }
+}
+
+impl Protobuf for PublishRecordRequest<'_> {
+    type Message = protobuf::PublishRecordRequest;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::PublishRecordRequest {
+            package_name: self.package_name.to_string(),
+            record: self.record.to_protobuf(),
+            content_sources: self
+                .content_sources
+                .iter()
+                .map(|(digest, sources)| protobuf::ContentSources {
+                    digest: digest.to_string(),
+                    sources: sources.iter().map(Protobuf::to_protobuf).collect(),
+                })
+                .collect(),
+            expected_head: self.expected_head.as_ref().map(ToString::to_string),
+            copublication: self.copublication.as_ref().map(Protobuf::to_protobuf),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            package_name: Cow::Owned(parse::<PackageName>("package_name", &message.package_name)?),
+            record: Cow::Owned(parse_envelope("record", &message.record)?),
+            content_sources: message
+                .content_sources
+                .into_iter()
+                .map(|sources| {
+                    Ok((
+                        parse::<AnyHash>("content_sources", &sources.digest)?,
+                        sources
+                            .sources
+                            .into_iter()
+                            .map(ContentSource::from_protobuf)
+                            .collect::<Result<_, _>>()?,
+                    ))
+                })
+                .collect::<Result<_, ProtobufError>>()?,
+            expected_head: message
+                .expected_head
+                .map(|head| parse::<AnyHash>("expected_head", &head).map(Into::into))
+                .transpose()?,
+            copublication: message
+                .copublication
+                .map(SerdeEnvelope::from_protobuf)
+                .transpose()?,
+        })
+    }
+}
+
+impl Protobuf for SerdeEnvelope<CoPublication> {
+    type Message = protobuf::SignedCoPublication;
+
+    fn to_protobuf(&self) -> Self::Message {
+        let copublication = self.as_ref();
+        protobuf::SignedCoPublication {
+            copublication: Some(protobuf::CoPublication {
+                key: copublication.key.to_string(),
+                parts: copublication
+                    .parts
+                    .iter()
+                    .map(|part| protobuf::CoPublishedRecord {
+                        package_name: part.package.to_string(),
+                        record_id: part.record_id.to_string(),
+                    })
+                    .collect(),
+                timestamp: copublication.timestamp,
+            }),
+            key_id: self.key_id().to_string(),
+            signature: self.signature().to_string(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        let copublication = message
+            .copublication
+            .ok_or(ProtobufError::MissingField("copublication"))?;
+        Ok(SerdeEnvelope::from_parts_unchecked(
+            CoPublication {
+                key: parse("key", &copublication.key)?,
+                parts: copublication
+                    .parts
+                    .into_iter()
+                    .map(|part| {
+                        Ok(CoPublishedRecord {
+                            package: parse("package_name", &part.package_name)?,
+                            record_id: parse::<AnyHash>("record_id", &part.record_id)?.into(),
+                        })
+                    })
+                    .collect::<Result<_, ProtobufError>>()?,
+                timestamp: copublication.timestamp,
+            },
+            message.key_id.into(),
+            parse("signature", &message.signature)?,
+        ))
+    }
+}
+
+impl Protobuf for UploadEndpoint {
+    type Message = protobuf::UploadEndpoint;
+
+    fn to_protobuf(&self) -> Self::Message {
+        use protobuf::upload_endpoint::Kind;
+
+        let kind = match self {
+            Self::Http {
+                method,
+                url,
+                headers,
+            } => Kind::Http(protobuf::HttpUploadEndpoint {
+                method: method.clone(),
+                url: url.clone(),
+                headers: headers
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            }),
+        };
+        protobuf::UploadEndpoint { kind: Some(kind) }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        use protobuf::upload_endpoint::Kind;
+
+        match message.kind.ok_or(ProtobufError::MissingField("kind"))? {
+            Kind::Http(endpoint) => {
+                // Protobuf maps are unordered; sort the headers for a stable order
+                let mut headers: IndexMap<_, _> = endpoint.headers.into_iter().collect();
+                headers.sort_keys();
+                Ok(Self::Http {
+                    method: endpoint.method,
+                    url: endpoint.url,
+                    headers,
+                })
+            }
+        }
+    }
+}
+
+impl Protobuf for PackageRecord {
+    type Message = protobuf::PackageRecord;
+
+    fn to_protobuf(&self) -> Self::Message {
+        use protobuf::package_record::State;
+
+        let state = match &self.state {
+            PackageRecordState::Sourcing { missing_content } => {
+                State::Sourcing(protobuf::PackageRecordSourcing {
+                    missing_content: missing_content
+                        .iter()
+                        .map(|(digest, missing)| protobuf::MissingContent {
+                            digest: digest.to_string(),
+                            upload: missing.upload.iter().map(Protobuf::to_protobuf).collect(),
+                        })
+                        .collect(),
+                })
+            }
+            PackageRecordState::Staged => State::Staged(protobuf::PackageRecordStaged {}),
+            PackageRecordState::Processing => {
+                State::Processing(protobuf::PackageRecordProcessing {})
+            }
+            PackageRecordState::Rejected { reason, conflict } => {
+                State::Rejected(protobuf::PackageRecordRejected {
+                    reason: reason.clone(),
+                    conflict: conflict.as_ref().map(|c| protobuf::ReleaseConflict {
+                        version: c.version.to_string(),
+                        record_id: c.record_id.to_string(),
+                    }),
+                })
+            }
+            PackageRecordState::Published { registry_index } => {
+                State::Published(protobuf::PackageRecordPublished {
+                    registry_index: *registry_index as u64,
+                })
+            }
+        };
+
+        protobuf::PackageRecord {
+            record_id: self.record_id.to_string(),
+            state: Some(state),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        use protobuf::package_record::State;
+
+        let state = match message.state.ok_or(ProtobufError::MissingField("state"))? {
+            State::Sourcing(sourcing) => PackageRecordState::Sourcing {
+                missing_content: sourcing
+                    .missing_content
+                    .into_iter()
+                    .map(|missing| {
+                        Ok((
+                            parse::<AnyHash>("missing_content", &missing.digest)?,
+                            MissingContent {
+                                upload: missing
+                                    .upload
+                                    .into_iter()
+                                    .map(UploadEndpoint::from_protobuf)
+                                    .collect::<Result<_, _>>()?,
+                            },
+                        ))
+                    })
+                    .collect::<Result<_, ProtobufError>>()?,
+            },
+            State::Staged(_) => PackageRecordState::Staged,
+            State::Processing(_) => PackageRecordState::Processing,
+            State::Rejected(rejected) => PackageRecordState::Rejected {
+                reason: rejected.reason,
+                conflict: rejected
+                    .conflict
+                    .map(|c| {
+                        Ok::<_, ProtobufError>(ReleaseConflict {
+                            version: parse::<Version>("version", &c.version)?,
+                            record_id: parse::<AnyHash>("record_id", &c.record_id)?.into(),
+                        })
+                    })
+                    .transpose()?,
+            },
+            State::Published(published) => PackageRecordState::Published {
+                registry_index: parse_index("registry_index", published.registry_index)?,
+            },
+        };
+
+        Ok(Self {
+            record_id: parse::<AnyHash>("record_id", &message.record_id)?.into(),
+            state,
+        })
+    }
+}
+
+impl Protobuf for FetchLogsRequest<'_> {
+    type Message = protobuf::FetchLogsRequest;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::FetchLogsRequest {
+            log_length: self.log_length as u64,
+            limit: self.limit.map(Into::into),
+            operator: self.operator.as_deref().map(Into::into),
+            packages: self
+                .packages
+                .iter()
+                .map(|(log_id, fetch_token)| protobuf::PackageFetchToken {
+                    log_id: log_id.to_string(),
+                    fetch_token: fetch_token.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            log_length: parse_index("log_length", message.log_length)?,
+            limit: message
+                .limit
+                .map(|limit| {
+                    limit.try_into().map_err(|_| ProtobufError::InvalidField {
+                        field: "limit",
+                        message: "value is out of range".into(),
+                    })
+                })
+                .transpose()?,
+            operator: message.operator.map(Cow::Owned),
+            packages: Cow::Owned(
+                message
+                    .packages
+                    .into_iter()
+                    .map(|p| {
+                        Ok((
+                            LogId::from(parse::<AnyHash>("log_id", &p.log_id)?),
+                            p.fetch_token,
+                        ))
+                    })
+                    .collect::<Result<_, ProtobufError>>()?,
+            ),
+        })
+    }
+}
+
+impl Protobuf for PublishedRecord {
+    type Message = protobuf::PublishedRecord;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::PublishedRecord {
+            envelope: self.envelope.envelope.to_protobuf(),
+            registry_index: self.envelope.registry_index as u64,
+            fetch_token: self.fetch_token.clone(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            envelope: PublishedProtoEnvelopeBody {
+                envelope: parse_envelope("envelope", &message.envelope)?,
+                registry_index: parse_index("registry_index", message.registry_index)?,
+            },
+            fetch_token: message.fetch_token,
+        })
+    }
+}
+
+impl Protobuf for FetchLogsResponse {
+    type Message = protobuf::FetchLogsResponse;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::FetchLogsResponse {
+            more: self.more,
+            operator: self.operator.iter().map(Protobuf::to_protobuf).collect(),
+            packages: self
+                .packages
+                .iter()
+                .map(|(log_id, records)| protobuf::PackageRecords {
+                    log_id: log_id.to_string(),
+                    records: records.iter().map(Protobuf::to_protobuf).collect(),
+                })
+                .collect(),
+            warnings: self.warnings.iter().map(|w| w.message.clone()).collect(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            more: message.more,
+            operator: message
+                .operator
+                .into_iter()
+                .map(PublishedRecord::from_protobuf)
+                .collect::<Result<_, _>>()?,
+            packages: message
+                .packages
+                .into_iter()
+                .map(|p| {
+                    Ok((
+                        LogId::from(parse::<AnyHash>("log_id", &p.log_id)?),
+                        p.records
+                            .into_iter()
+                            .map(PublishedRecord::from_protobuf)
+                            .collect::<Result<_, _>>()?,
+                    ))
+                })
+                .collect::<Result<_, ProtobufError>>()?,
+            warnings: message
+                .warnings
+                .into_iter()
+                .map(|message| FetchWarning { message })
+                .collect(),
+        })
+    }
+}
+
+impl Protobuf for FetchPackageNamesRequest<'_> {
+    type Message = protobuf::FetchPackageNamesRequest;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::FetchPackageNamesRequest {
+            packages: self.packages.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            packages: Cow::Owned(
+                message
+                    .packages
+                    .iter()
+                    .map(|log_id| parse::<AnyHash>("packages", log_id).map(Into::into))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+impl Protobuf for FetchPackageNamesResponse {
+    type Message = protobuf::FetchPackageNamesResponse;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::FetchPackageNamesResponse {
+            packages: self
+                .packages
+                .iter()
+                .map(|(log_id, name)| protobuf::PackageLogName {
+                    log_id: log_id.to_string(),
+                    name: name.as_ref().map(ToString::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            packages: message
+                .packages
+                .into_iter()
+                .map(|p| {
+                    Ok((
+                        LogId::from(parse::<AnyHash>("log_id", &p.log_id)?),
+                        p.name
+                            .map(|name| parse::<PackageName>("name", &name))
+                            .transpose()?,
+                    ))
+                })
+                .collect::<Result<_, ProtobufError>>()?,
+        })
+    }
+}
+
+impl Protobuf for SerdeEnvelope<TimestampedCheckpoint> {
+    type Message = protobuf::SignedCheckpoint;
+
+    fn to_protobuf(&self) -> Self::Message {
+        let checkpoint = self.as_ref();
+        protobuf::SignedCheckpoint {
+            checkpoint: Some(protobuf::Checkpoint {
+                log_root: checkpoint.checkpoint.log_root.to_string(),
+                log_length: checkpoint.checkpoint.log_length as u64,
+                map_root: checkpoint.checkpoint.map_root.to_string(),
+                timestamp: checkpoint.timestamp,
+            }),
+            key_id: self.key_id().to_string(),
+            signature: self.signature().to_string(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        let checkpoint = message
+            .checkpoint
+            .ok_or(ProtobufError::MissingField("checkpoint"))?;
+        Ok(SerdeEnvelope::from_parts_unchecked(
+            TimestampedCheckpoint {
+                checkpoint: Checkpoint {
+                    log_root: parse("log_root", &checkpoint.log_root)?,
+                    log_length: parse_index("log_length", checkpoint.log_length)?,
+                    map_root: parse("map_root", &checkpoint.map_root)?,
+                },
+                timestamp: checkpoint.timestamp,
+            },
+            message.key_id.into(),
+            parse("signature", &message.signature)?,
+        ))
+    }
+}
+
+impl Protobuf for ConsistencyRequest {
+    type Message = protobuf::ConsistencyRequest;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::ConsistencyRequest {
+            from: self.from as u64,
+            to: self.to as u64,
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            from: parse_index("from", message.from)?,
+            to: parse_index("to", message.to)?,
+        })
+    }
+}
+
+impl Protobuf for ConsistencyResponse {
+    type Message = protobuf::ConsistencyResponse;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::ConsistencyResponse {
+            proof: self.proof.clone(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            proof: message.proof,
+        })
+    }
+}
+
+impl Protobuf for InclusionRequest {
+    type Message = protobuf::InclusionRequest;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::InclusionRequest {
+            log_length: self.log_length as u64,
+            leafs: self.leafs.iter().map(|leaf| *leaf as u64).collect(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            log_length: parse_index("log_length", message.log_length)?,
+            leafs: message
+                .leafs
+                .into_iter()
+                .map(|leaf| parse_index("leafs", leaf))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Protobuf for InclusionResponse {
+    type Message = protobuf::InclusionResponse;
+
+    fn to_protobuf(&self) -> Self::Message {
+        protobuf::InclusionResponse {
+            log: self.log.clone(),
+            map: self.map.clone(),
+        }
+    }
+
+    fn from_protobuf(message: Self::Message) -> Result<Self, ProtobufError> {
+        Ok(Self {
+            log: message.log,
+            map: message.map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::hash::{Hash, Sha256};
+    use warg_protocol::registry::RecordId;
+
+    fn roundtrip<T: Protobuf>(value: &T) -> T {
+        T::decode_protobuf(&value.encode_protobuf()).unwrap()
+    }
+
+    #[test]
+    fn package_record_roundtrip() {
+        let record_id: RecordId = AnyHash::from(Hash::<Sha256>::of("record")).into();
+        let digest: AnyHash = Hash::<Sha256>::of("content").into();
+        let record = PackageRecord {
+            record_id: record_id.clone(),
+            state: PackageRecordState::Sourcing {
+                missing_content: IndexMap::from([(
+                    digest,
+                    MissingContent {
+                        upload: vec![UploadEndpoint::Http {
+                            method: "POST".into(),
+                            url: "https://example.com/upload".into(),
+                            headers: IndexMap::from([
+                                ("authorization".into(), "token".into()),
+                                ("content-type".into(), "application/wasm".into()),
+                            ]),
+                        }],
+                    },
+                )]),
+            },
+        };
+        let actual = roundtrip(&record);
+        assert_eq!(actual.record_id, record_id);
+        assert_eq!(
+            serde_json::to_value(&actual).unwrap(),
+            serde_json::to_value(&record).unwrap()
+        );
+
+        let record = PackageRecord {
+            record_id: record_id.clone(),
+            state: PackageRecordState::Rejected {
+                reason: "conflict".into(),
+                conflict: Some(ReleaseConflict {
+                    version: "1.0.0".parse().unwrap(),
+                    record_id,
+                }),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(roundtrip(&record)).unwrap(),
+            serde_json::to_value(&record).unwrap()
+        );
+    }
+
+    #[test]
+    fn fetch_logs_request_roundtrip() {
+        let log_id = LogId::package_log::<Sha256>(&"test:package".parse().unwrap());
+        let packages = IndexMap::from([(log_id, Some("token".to_string()))]);
+        let request = FetchLogsRequest {
+            log_length: 10,
+            limit: Some(100),
+            operator: Some(Cow::Borrowed("operator")),
+            packages: Cow::Borrowed(&packages),
+        };
+        let actual = roundtrip(&request);
+        assert_eq!(actual.log_length, 10);
+        assert_eq!(actual.limit, Some(100));
+        assert_eq!(actual.operator.as_deref(), Some("operator"));
+        assert_eq!(actual.packages.as_ref(), &packages);
+    }
+
+    #[test]
+    fn copublication_roundtrip() {
+        let (public_key, private_key) = warg_crypto::signing::generate_p256_pair();
+        let copublication = CoPublication::new(
+            public_key,
+            ["test:foo", "test:bar"].map(|package| CoPublishedRecord {
+                package: package.parse().unwrap(),
+                record_id: AnyHash::from(Hash::<Sha256>::of(package)).into(),
+            }),
+            std::time::SystemTime::now(),
+        )
+        .unwrap()
+        .sign(&private_key)
+        .unwrap();
+        let actual = roundtrip(&copublication);
+        assert_eq!(actual, copublication);
+        CoPublication::verify_signed(&actual).unwrap();
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        let message = protobuf::ConsistencyRequest { from: 0, to: 1 };
+        assert!(ConsistencyRequest::from_protobuf(message).is_ok());
+
+        let message = protobuf::PackageRecord {
+            record_id: "not-a-hash".into(),
+            state: Some(protobuf::package_record::State::Staged(
+                protobuf::PackageRecordStaged {},
+            )),
+        };
+        assert!(matches!(
+            PackageRecord::from_protobuf(message),
+            Err(ProtobufError::InvalidField {
+                field: "record_id",
+                ..
+            })
+        ));
+
+        let message = protobuf::PackageRecord {
+            record_id: String::new(),
+            state: None,
+        };
+        assert!(matches!(
+            PackageRecord::from_protobuf(message),
+            Err(ProtobufError::MissingField("state"))
+        ));
+    }
+}
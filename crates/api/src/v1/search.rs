@@ -0,0 +1,107 @@
+//! Types relating to the search API.
+
+use crate::Status;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use thiserror::Error;
+use warg_protocol::registry::PackageName;
+
+/// Represents the query parameters of a package search request.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery<'a> {
+    /// The search terms.
+    ///
+    /// Each term must match a word, or the prefix of a word, of the package name.
+    pub q: Cow<'a, str>,
+    /// The maximum number of results to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Represents a package search response.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    /// The matching packages, sorted by name.
+    pub packages: Vec<PackageName>,
+}
+
+/// Represents a search API error.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum SearchError {
+    /// Search is not supported by the registry.
+    #[error("search is not supported by the registry")]
+    NotSupported,
+    /// An error with a message occurred.
+    #[error("{message}")]
+    Message {
+        /// The HTTP status code.
+        status: u16,
+        /// The error message
+        message: String,
+    },
+}
+
+impl SearchError {
+    /// Returns the HTTP status code of the error.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::NotSupported => 501,
+            Self::Message { status, .. } => *status,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum EntityType {
+    Search,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, rename_all = "camelCase")]
+enum RawError<'a> {
+    NotSupported {
+        status: Status<501>,
+        #[serde(rename = "type")]
+        ty: EntityType,
+    },
+    Message {
+        status: u16,
+        message: Cow<'a, str>,
+    },
+}
+
+impl Serialize for SearchError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::NotSupported => RawError::NotSupported {
+                status: Status::<501>,
+                ty: EntityType::Search,
+            }
+            .serialize(serializer),
+            Self::Message { status, message } => RawError::Message {
+                status: *status,
+                message: Cow::Borrowed(message),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SearchError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match RawError::deserialize(deserializer)? {
+            RawError::NotSupported { .. } => Ok(Self::NotSupported),
+            RawError::Message { status, message } => Ok(Self::Message {
+                status,
+                message: message.into_owned(),
+            }),
+        }
+    }
+}
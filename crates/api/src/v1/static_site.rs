@@ -0,0 +1,82 @@
+//! Types relating to the static site layout of a registry.
+//!
+//! A static site is a snapshot of a registry at a single checkpoint, written
+//! as plain files so that it may be served by any static file host:
+//!
+//! ```text
+//! checkpoint.json              the signed checkpoint
+//! index.json                   the package name index
+//! logs/operator.json           the operator log records
+//! logs/<log-id>.json           the records of each package log
+//! proofs/operator.json         the inclusion proof of the operator log head
+//! proofs/<log-id>.json         the inclusion proof of each package log head
+//! content/<digest>             the content referenced by package records
+//! ```
+//!
+//! Log identifiers and digests are written with the `:` separating the
+//! algorithm from the hex value replaced by `-`.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{
+    registry::{LogId, PackageName, RegistryLen},
+    PublishedProtoEnvelopeBody,
+};
+
+/// The path of the signed checkpoint.
+pub fn checkpoint() -> &'static str {
+    "checkpoint.json"
+}
+
+/// The path of the package name index.
+pub fn index() -> &'static str {
+    "index.json"
+}
+
+/// The path of the operator log records.
+pub fn operator_log() -> &'static str {
+    "logs/operator.json"
+}
+
+/// The path of the operator log head inclusion proof.
+pub fn operator_proof() -> &'static str {
+    "proofs/operator.json"
+}
+
+/// The path of a package log's records.
+pub fn package_log(log_id: &LogId) -> String {
+    format!("logs/{id}.json", id = file_name(log_id))
+}
+
+/// The path of a package log head inclusion proof.
+pub fn package_proof(log_id: &LogId) -> String {
+    format!("proofs/{id}.json", id = file_name(log_id))
+}
+
+/// The path of the content with the given digest.
+pub fn content(digest: &AnyHash) -> String {
+    format!("content/{digest}", digest = file_name(digest))
+}
+
+fn file_name(value: impl ToString) -> String {
+    value.to_string().replace(':', "-")
+}
+
+/// Represents the package name index of a static site.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaticIndex {
+    /// The registry log length of the checkpoint the site was exported at.
+    pub log_length: RegistryLen,
+    /// The packages in the registry, sorted by name.
+    pub packages: IndexMap<PackageName, LogId>,
+}
+
+/// Represents the records of a log in a static site.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaticLog {
+    /// The records of the log, in registry order.
+    pub records: Vec<PublishedProtoEnvelopeBody>,
+}
@@ -1,43 +1,72 @@
 //! A module for Warg registry API clients.
 
 use anyhow::{anyhow, Result};
-use bytes::Bytes;
-use futures_util::{future::ready, stream::once, Stream, StreamExt, TryStreamExt};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::{future::ready, ready, stream::once, Stream, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
 use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    Body, IntoUrl, Method, RequestBuilder, Response, StatusCode,
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_NONE_MATCH},
+    IntoUrl, Method, RequestBuilder, Response, StatusCode,
 };
 use secrecy::{ExposeSecret, Secret};
 use serde::de::DeserializeOwned;
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
 use thiserror::Error;
+use url::Url;
 use warg_api::v1::{
+    admin::{
+        AdminCommandRequest, AdminCommandResponse, AdminError, QuarantineResponse,
+        QuarantinedRecord,
+    },
     content::{ContentError, ContentSourcesResponse},
     fetch::{
-        FetchError, FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest,
-        FetchPackageNamesResponse,
+        FetchError, FetchLogsFrame, FetchLogsFrameError, FetchLogsRequest, FetchLogsResponse,
+        FetchPackageNamesRequest, FetchPackageNamesResponse, FETCH_LOGS_STREAM_CONTENT_TYPE,
     },
+    key::{KeyError, KeyRecordsQuery, KeyRecordsResponse},
     ledger::{LedgerError, LedgerSourcesResponse},
     monitor::{CheckpointVerificationResponse, MonitorError},
-    package::{ContentSource, PackageError, PackageRecord, PublishRecordRequest},
+    package::{
+        ContentSource, PackageError, PackageRecord, PackageRecordReceipt, PublishRecordRequest,
+    },
     paths,
     proof::{
-        ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse, ProofError,
+        ConsistencyRequest, ConsistencyResponse, DeltaRequest, DeltaResponse, InclusionRequest,
+        InclusionResponse, ProofError,
     },
+    search::{SearchError, SearchQuery, SearchResponse},
     REGISTRY_HEADER_NAME, REGISTRY_HINT_HEADER_NAME,
 };
-use warg_crypto::hash::{AnyHash, HashError, Sha256};
+use warg_crypto::{
+    hash::{AnyHash, Hash, HashError, Sha256},
+    signing::KeyID,
+};
 use warg_protocol::{
-    registry::{Checkpoint, LogId, LogLeaf, MapLeaf, RecordId, TimestampedCheckpoint},
-    SerdeEnvelope,
+    discovery::OperatorKeys,
+    filter::PackageFilter,
+    registry::{
+        Checkpoint, FreshnessAssertion, LogId, LogLeaf, MapLeaf, RecordId, RecordReceipt,
+        RegistryLen, TimestampedCheckpoint,
+    },
+    Countersignature, SerdeEnvelope,
 };
 use warg_transparency::{
     log::{ConsistencyProofError, InclusionProofError, LogProofBundle, ProofBundle},
     map::MapProofBundle,
 };
 
-use crate::{registry_url::RegistryUrl, storage::RegistryDomain};
+use crate::{
+    auth::{AuthProvider, BearerToken},
+    registry_url::RegistryUrl,
+    storage::RegistryDomain,
+    transport::{HttpTransport, Middleware, Next, RequestBody, Transport, TransportRequest},
+};
 /// Represents an error that occurred while communicating with the registry.
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -59,6 +88,26 @@ pub enum ClientError {
     /// An error was returned from the ledger API.
     #[error(transparent)]
     Ledger(#[from] LedgerError),
+    /// An error was returned from the search API.
+    #[error(transparent)]
+    Search(#[from] SearchError),
+    /// An error was returned from the key API.
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    /// An error was returned from the admin API.
+    #[error(transparent)]
+    Admin(#[from] AdminError),
+    /// A frame of a streamed fetch logs response was invalid.
+    #[error(transparent)]
+    Frame(#[from] FetchLogsFrameError),
+    /// A frame of a streamed fetch logs response exceeded the maximum frame length.
+    #[error("fetch logs frame of {len} bytes exceeds the maximum of {max} bytes")]
+    FrameTooLarge {
+        /// The length of the frame.
+        len: u64,
+        /// The maximum frame length.
+        max: usize,
+    },
     /// An error occurred while communicating with the registry.
     #[error("failed to send request to registry server: {0}")]
     Communication(#[from] reqwest::Error),
@@ -107,6 +156,9 @@ pub enum ClientError {
     /// The provided log was not found with hint header.
     #[error("log `{0}` was not found in this registry, but the registry provided the hint header: `{1:?}`")]
     LogNotFoundWithHint(LogId, HeaderValue),
+    /// Credentials for the registry could not be obtained.
+    #[error("failed to obtain registry credentials: {0:#}")]
+    Authentication(anyhow::Error),
     /// An other error occurred during the requested operation.
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -172,51 +224,104 @@ impl WithWargHeader for RequestBuilder {
     }
 }
 
-trait WithAuth {
-    fn auth(self, auth_token: &Option<Secret<String>>) -> RequestBuilder;
+/// Represents the response to a conditional request.
+#[derive(Debug)]
+pub enum Conditional<T> {
+    /// The response has changed since the given entity tag.
+    Modified {
+        /// The response.
+        value: T,
+        /// The entity tag of the response, if the registry provided one.
+        etag: Option<String>,
+    },
+    /// The response has not changed since the given entity tag.
+    NotModified,
 }
 
-impl WithAuth for RequestBuilder {
-    fn auth(self, auth_token: &Option<Secret<String>>) -> reqwest::RequestBuilder {
-        if let Some(tok) = auth_token {
-            self.bearer_auth(tok.expose_secret())
-        } else {
-            self
-        }
-    }
-}
+/// The latest checkpoints received from a registry, with their entity tags.
+type CheckpointCache =
+    HashMap<Option<RegistryDomain>, (HeaderValue, SerdeEnvelope<TimestampedCheckpoint>)>;
 
 /// Represents a Warg API client for communicating with
 /// a Warg registry server.
 pub struct Client {
     url: RegistryUrl,
     client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    middleware: Vec<Arc<dyn Middleware>>,
     warg_registry_header: Option<RegistryDomain>,
     auth_token: Option<Secret<String>>,
+    auth_provider: Option<Arc<dyn AuthProvider>>,
+    checkpoints: Mutex<CheckpointCache>,
 }
 
 impl Client {
     /// Creates a new API client with the given URL.
+    ///
+    /// If an auth token is given, requests to the registry are authenticated
+    /// with it as a bearer token.
     pub fn new(url: impl IntoUrl, auth_token: Option<Secret<String>>) -> Result<Self> {
         let url = RegistryUrl::new(url)?;
+        let client = reqwest::Client::new();
         Ok(Self {
             url,
-            client: reqwest::Client::new(),
+            transport: Arc::new(HttpTransport::new(client.clone())),
+            client,
+            middleware: Vec::new(),
             warg_registry_header: None,
+            auth_provider: auth_token
+                .clone()
+                .map(|token| Arc::new(BearerToken::from(token)) as Arc<dyn AuthProvider>),
             auth_token,
+            checkpoints: Default::default(),
         })
     }
 
+    /// Sets the transport used to send requests to the registry.
+    ///
+    /// By default, requests are sent over HTTP.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Adds middleware intercepting the requests sent by the client.
+    ///
+    /// Middleware runs in the order it was added, after requests to the
+    /// registry are authorized and before they reach the transport.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the provider of credentials for requests to the registry.
+    ///
+    /// This replaces any auth token the client was created with. Credentials
+    /// are only sent to the registry, not to content sources elsewhere.
+    pub fn with_auth_provider(mut self, provider: impl AuthProvider + 'static) -> Self {
+        self.auth_token = None;
+        self.auth_provider = Some(Arc::new(provider));
+        self
+    }
+
     /// Gets auth token
     pub fn auth_token(&self) -> &Option<Secret<String>> {
         &self.auth_token
     }
 
+    /// Determines if requests to the registry are authenticated.
+    pub fn has_auth(&self) -> bool {
+        self.auth_provider.is_some()
+    }
+
     /// Gets the URL of the API client.
     pub fn url(&self) -> &RegistryUrl {
         &self.url
     }
     /// Gets the latest checkpoint from the registry.
+    ///
+    /// The last checkpoint received is cached; if the registry reports that
+    /// the checkpoint has not changed, the cached checkpoint is returned.
     pub async fn latest_checkpoint(
         &self,
         registry_domain: Option<&RegistryDomain>,
@@ -227,12 +332,101 @@ impl Client {
             registry_header = ?registry_domain,
             "getting latest checkpoint",
         );
+
+        let key = registry_domain.cloned();
+        let cached = self.checkpoints.lock().unwrap().get(&key).cloned();
+        let mut request = self.client.get(url).warg_header(registry_domain)?;
+        if let Some((etag, _)) = &cached {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        let response = self.send(request).await?;
+        if let (StatusCode::NOT_MODIFIED, Some((_, checkpoint))) = (response.status(), cached) {
+            tracing::debug!("latest checkpoint has not changed");
+            return Ok(checkpoint);
+        }
+
+        let etag = response.headers().get(ETAG).cloned();
+        let checkpoint: SerdeEnvelope<TimestampedCheckpoint> =
+            into_result::<_, FetchError>(response).await?;
+        if let Some(etag) = etag {
+            self.checkpoints
+                .lock()
+                .unwrap()
+                .insert(key, (etag, checkpoint.clone()));
+        }
+
+        Ok(checkpoint)
+    }
+
+    /// Gets the checkpoint of the registry at the given log length.
+    pub async fn checkpoint_at(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        log_length: RegistryLen,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, ClientError> {
+        let url = self.url.join(&paths::fetch_checkpoint_at(log_length));
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "getting checkpoint",
+        );
+        into_result::<_, FetchError>(
+            self.send(self.client.get(url).warg_header(registry_domain)?)
+                .await?,
+        )
+        .await
+    }
+
+    /// Gets the operator keys document of the registry.
+    pub async fn operator_keys(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+    ) -> Result<SerdeEnvelope<OperatorKeys>, ClientError> {
+        let url = self.url.join(paths::operator_keys());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "getting operator keys",
+        );
+        into_result::<_, FetchError>(
+            self.send(self.client.get(url).warg_header(registry_domain)?)
+                .await?,
+        )
+        .await
+    }
+
+    /// Gets the latest freshness assertion of the registry.
+    pub async fn latest_freshness(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+    ) -> Result<SerdeEnvelope<FreshnessAssertion>, ClientError> {
+        let url = self.url.join(paths::fetch_freshness());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "getting latest freshness assertion",
+        );
+        into_result::<_, FetchError>(
+            self.send(self.client.get(url).warg_header(registry_domain)?)
+                .await?,
+        )
+        .await
+    }
+
+    /// Gets the latest package filter of the registry.
+    pub async fn latest_package_filter(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+    ) -> Result<SerdeEnvelope<PackageFilter>, ClientError> {
+        let url = self.url.join(paths::fetch_package_filter());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "getting latest package filter",
+        );
         into_result::<_, FetchError>(
-            self.client
-                .get(url)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
+            self.send(self.client.get(url).warg_header(registry_domain)?)
                 .await?,
         )
         .await
@@ -252,12 +446,12 @@ impl Client {
         );
 
         let response = self
-            .client
-            .post(url)
-            .json(&request)
-            .warg_header(registry_domain)?
-            .auth(self.auth_token())
-            .send()
+            .send(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
             .await?;
         into_result::<_, MonitorError>(response).await
     }
@@ -268,24 +462,59 @@ impl Client {
         registry_domain: Option<&RegistryDomain>,
         request: FetchLogsRequest<'_>,
     ) -> Result<FetchLogsResponse, ClientError> {
+        match self
+            .fetch_logs_if_none_match(registry_domain, request, None)
+            .await?
+        {
+            Conditional::Modified { value, .. } => Ok(value),
+            Conditional::NotModified => Err(ClientError::UnexpectedResponse {
+                status: StatusCode::NOT_MODIFIED,
+                message: "the server responded to an unconditional request as not modified".into(),
+            }),
+        }
+    }
+
+    /// Fetches package log entries from the registry unless the response
+    /// matches the given entity tag.
+    ///
+    /// The entity tag of a response is returned by a previous request; the
+    /// records up to a checkpoint never change, so a request may be repeated
+    /// with the tag to check for changes without transferring the records.
+    pub async fn fetch_logs_if_none_match(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        request: FetchLogsRequest<'_>,
+        etag: Option<&str>,
+    ) -> Result<Conditional<FetchLogsResponse>, ClientError> {
         let url = self.url.join(paths::fetch_logs());
         tracing::debug!(
             url,
             registry_header = ?registry_domain,
             "fetching logs",
         );
-        let response = self
+        let mut builder = self
             .client
             .post(&url)
             .json(&request)
-            .warg_header(registry_domain)?
-            .auth(self.auth_token())
-            .send()
-            .await?;
+            .warg_header(registry_domain)?;
+        if let Some(etag) = etag {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+
+        let response = self.send(builder).await?;
+        if etag.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
 
         let header = response.headers().get(REGISTRY_HINT_HEADER_NAME).cloned();
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(ToString::to_string);
         into_result::<_, FetchError>(response)
             .await
+            .map(|value| Conditional::Modified { value, etag })
             .map_err(|err| match err {
                 ClientError::Fetch(FetchError::LogNotFound(log_id)) if header.is_some() => {
                     ClientError::LogNotFoundWithHint(log_id, header.unwrap())
@@ -294,6 +523,64 @@ impl Client {
             })
     }
 
+    /// Fetches package log entries from the registry as a stream of frames.
+    ///
+    /// Unlike [`Client::fetch_logs`], the response is not buffered; records
+    /// are yielded as they are received.
+    ///
+    /// Registries that do not support streaming are fetched from with
+    /// [`Client::fetch_logs`] instead.
+    pub async fn fetch_logs_stream(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        request: FetchLogsRequest<'_>,
+    ) -> Result<EnvelopeStream, ClientError> {
+        let url = self.url.join(paths::fetch_logs_stream());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "streaming logs",
+        );
+        let response = self
+            .send(
+                self.client
+                    .post(&url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
+            .await?;
+
+        let status = response.status();
+        let header = response.headers().get(REGISTRY_HINT_HEADER_NAME).cloned();
+        if !status.is_success() {
+            return match (deserialize::<FetchError>(response).await, header) {
+                // A registry without the streaming endpoint responds with a
+                // generic not found error rather than one for a requested entity
+                (Ok(FetchError::Message { .. }) | Err(_), _) if status == StatusCode::NOT_FOUND => {
+                    tracing::debug!("registry does not support streaming logs; fetching instead");
+                    self.fetch_logs(registry_domain, request)
+                        .await
+                        .map(EnvelopeStream::from)
+                }
+                (Ok(FetchError::LogNotFound(log_id)), Some(header)) => {
+                    Err(ClientError::LogNotFoundWithHint(log_id, header))
+                }
+                (Ok(err), _) => Err(err.into()),
+                (Err(err), _) => Err(err),
+            };
+        }
+
+        match response.headers().get("content-type") {
+            Some(ty) if ty == FETCH_LOGS_STREAM_CONTENT_TYPE => {
+                Ok(EnvelopeStream::new(response.bytes_stream()).with_hint(header))
+            }
+            _ => Err(ClientError::UnexpectedResponse {
+                status,
+                message: "the server did not respond with a fetch logs stream".into(),
+            }),
+        }
+    }
+
     /// Fetches package names from the registry.
     pub async fn fetch_package_names(
         &self,
@@ -307,12 +594,12 @@ impl Client {
             "fetching package names",
         );
         let response = self
-            .client
-            .post(url)
-            .warg_header(registry_domain)?
-            .auth(self.auth_token())
-            .json(&request)
-            .send()
+            .send(
+                self.client
+                    .post(url)
+                    .warg_header(registry_domain)?
+                    .json(&request),
+            )
             .await?;
         into_result::<_, FetchError>(response).await
     }
@@ -329,16 +616,98 @@ impl Client {
             "getting ledger sources",
         );
         into_result::<_, LedgerError>(
-            self.client
-                .get(url)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
+            self.send(self.client.get(url).warg_header(registry_domain)?)
                 .await?,
         )
         .await
     }
 
+    /// Searches the registry for packages matching the given query.
+    pub async fn search(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        query: SearchQuery<'_>,
+    ) -> Result<SearchResponse, ClientError> {
+        let url = self.url.join(paths::search());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            query = ?query.q,
+            "searching packages",
+        );
+        into_result::<_, SearchError>(
+            self.send(
+                self.client
+                    .get(url)
+                    .warg_header(registry_domain)?
+                    .query(&query),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the package records signed by the given key.
+    pub async fn key_records(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        key_id: &KeyID,
+        query: KeyRecordsQuery,
+    ) -> Result<KeyRecordsResponse, ClientError> {
+        let url = self.url.join(&paths::key_records(key_id));
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            since = ?query.since,
+            "getting key records",
+        );
+        into_result::<_, KeyError>(
+            self.send(
+                self.client
+                    .get(url)
+                    .warg_header(registry_domain)?
+                    .query(&query),
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Executes administrative commands recorded in a signed operator record.
+    pub async fn admin_command(
+        &self,
+        request: AdminCommandRequest<'_>,
+    ) -> Result<AdminCommandResponse, ClientError> {
+        let url = self.url.join(paths::admin_command());
+        tracing::debug!(url, "executing admin commands");
+        into_result::<_, AdminError>(self.send(self.client.post(url).json(&request)).await?).await
+    }
+
+    /// Gets the package records rejected by the registry and held in quarantine.
+    pub async fn quarantined_records(&self) -> Result<Vec<QuarantinedRecord>, ClientError> {
+        let url = self.url.join(paths::admin_quarantine());
+        tracing::debug!(url, "getting quarantined records");
+        into_result::<QuarantineResponse, AdminError>(self.send(self.client.get(url)).await?)
+            .await
+            .map(|response| response.records)
+    }
+
+    /// Gets a package record held in quarantine.
+    pub async fn quarantined_record(&self, id: u64) -> Result<QuarantinedRecord, ClientError> {
+        let url = self.url.join(&paths::admin_quarantined_record(id));
+        tracing::debug!(url, "getting quarantined record");
+        into_result::<_, AdminError>(self.send(self.client.get(url)).await?).await
+    }
+
+    /// Publishes a package record held in quarantine again.
+    ///
+    /// The record is removed from the quarantine if the registry accepts it.
+    pub async fn requeue_quarantined_record(&self, id: u64) -> Result<PackageRecord, ClientError> {
+        let url = self.url.join(&paths::admin_requeue_quarantined_record(id));
+        tracing::debug!(url, "requeuing quarantined record");
+        into_result::<_, AdminError>(self.send(self.client.post(url)).await?).await
+    }
+
     /// Publish a new record to a package log.
     pub async fn publish_package_record(
         &self,
@@ -354,12 +723,41 @@ impl Client {
             "publishing to package",
         );
         let response = self
-            .client
-            .post(url)
-            .json(&request)
-            .warg_header(registry_domain)?
-            .auth(self.auth_token())
-            .send()
+            .send(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
+            .await?;
+        into_result::<_, PackageError>(response).await
+    }
+
+    /// Attaches an operator countersignature to a staged package record.
+    pub async fn countersign_package_record(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        log_id: &LogId,
+        record_id: &RecordId,
+        countersignature: &Countersignature,
+    ) -> Result<PackageRecord, ClientError> {
+        let url = self
+            .url
+            .join(&paths::package_record_countersignature(log_id, record_id));
+        tracing::debug!(
+            log_id = log_id.to_string(),
+            record_id = record_id.to_string(),
+            url,
+            registry_header = ?registry_domain,
+            "countersigning package record",
+        );
+        let response = self
+            .send(
+                self.client
+                    .post(url)
+                    .json(countersignature)
+                    .warg_header(registry_domain)?,
+            )
             .await?;
         into_result::<_, PackageError>(response).await
     }
@@ -380,16 +778,67 @@ impl Client {
             "getting package record",
         );
         into_result::<_, PackageError>(
-            self.client
-                .get(url)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
+            self.send(self.client.get(url).warg_header(registry_domain)?)
                 .await?,
         )
         .await
     }
 
+    /// Gets the receipt of a published package record.
+    ///
+    /// The receipt is verified to be for the given record and to be included
+    /// in the checkpoint of the receipt; the signature of the receipt is not
+    /// verified.
+    pub async fn get_package_record_receipt(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<SerdeEnvelope<RecordReceipt>, ClientError> {
+        let url = self
+            .url
+            .join(&paths::package_record_receipt(log_id, record_id));
+        tracing::debug!(
+            log_id = log_id.to_string(),
+            record_id = record_id.to_string(),
+            url,
+            registry_header = ?registry_domain,
+            "getting package record receipt",
+        );
+        let response = into_result::<PackageRecordReceipt, PackageError>(
+            self.send(self.client.get(url).warg_header(registry_domain)?)
+                .await?,
+        )
+        .await?;
+
+        let receipt = response.receipt.as_ref();
+        if &receipt.log_id != log_id || &receipt.record_id != record_id {
+            return Err(ClientError::Other(anyhow!(
+                "registry returned a receipt for record `{found}` instead of `{record_id}`",
+                found = receipt.record_id
+            )));
+        }
+
+        let log_proof_bundle: LogProofBundle<Sha256, LogLeaf> =
+            LogProofBundle::decode(response.proof.as_slice())?;
+        let (log_data, _, log_inclusions) = log_proof_bundle.unbundle();
+        let proof = log_inclusions.first().ok_or_else(|| {
+            ClientError::Proof(ProofError::BundleFailure(
+                "expected an inclusion proof for the record".into(),
+            ))
+        })?;
+        let found = proof.evaluate_value(&log_data, &receipt.leaf())?;
+        let root = receipt.checkpoint.log_root.try_into()?;
+        if found != root {
+            return Err(ClientError::Proof(ProofError::IncorrectProof {
+                root: receipt.checkpoint.log_root,
+                found: found.into(),
+            }));
+        }
+
+        Ok(response.receipt)
+    }
+
     /// Gets a content sources from the registry.
     pub async fn content_sources(
         &self,
@@ -404,11 +853,7 @@ impl Client {
             "getting content sources for digest",
         );
         into_result::<_, ContentError>(
-            self.client
-                .get(url)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
+            self.send(self.client.get(url).warg_header(registry_domain)?)
                 .await?,
         )
         .await
@@ -425,14 +870,14 @@ impl Client {
 
         let sources = content_sources
             .get(digest)
-            .ok_or(ClientError::AllSourcesFailed(digest.clone()))?;
+            .ok_or(ClientError::AllSourcesFailed(*digest))?;
 
         for source in sources {
             let ContentSource::HttpGet { url, .. } = source;
 
             tracing::debug!("downloading content `{digest}` from `{url}`");
 
-            let response = self.client.get(url).send().await?;
+            let response = self.send(self.client.get(url)).await?;
             if !response.status().is_success() {
                 tracing::debug!(
                     "failed to download content `{digest}` from `{url}`: {status}",
@@ -447,7 +892,7 @@ impl Client {
             ));
         }
 
-        Err(ClientError::AllSourcesFailed(digest.clone()))
+        Err(ClientError::AllSourcesFailed(*digest))
     }
 
     /// Set warg-registry header value
@@ -463,24 +908,88 @@ impl Client {
         checkpoint: &Checkpoint,
         leafs: &[LogLeaf],
     ) -> Result<(), ClientError> {
+        let response = self.inclusion_proof(registry_domain, request).await?;
+        Self::validate_inclusion_response(response, checkpoint, leafs)
+    }
+
+    /// Gets the proof of inclusion of the given log heads in the registry
+    /// without validating it.
+    pub async fn inclusion_proof(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        request: InclusionRequest,
+    ) -> Result<InclusionResponse, ClientError> {
         let url = self.url.join(paths::prove_inclusion());
         tracing::debug!(
             url,
             registry_header = ?registry_domain,
             "proving checkpoint inclusion",
         );
-        let response = into_result::<InclusionResponse, ProofError>(
-            self.client
-                .post(url)
-                .json(&request)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+        into_result::<InclusionResponse, ProofError>(
+            self.send(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
+            .await?,
+        )
+        .await
+    }
+
+    /// Gets the map entries that changed between two checkpoints.
+    ///
+    /// The new value of each changed entry is verified against the map root
+    /// of the given later checkpoint.
+    pub async fn prove_delta(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        request: DeltaRequest,
+        checkpoint: &Checkpoint,
+    ) -> Result<Vec<LogLeaf>, ClientError> {
+        let url = self.url.join(paths::prove_delta());
+        tracing::debug!(
+            url,
+            registry_header = ?registry_domain,
+            "proving checkpoint delta",
+        );
+        let response = into_result::<DeltaResponse, ProofError>(
+            self.send(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
+            .await?,
         )
         .await?;
 
-        Self::validate_inclusion_response(response, checkpoint, leafs)
+        let map_proof_bundle: MapProofBundle<Sha256, LogId, MapLeaf> =
+            MapProofBundle::decode(response.map.as_slice())?;
+        let map_inclusions = map_proof_bundle.unbundle();
+        if map_inclusions.len() != response.changes.len() {
+            return Err(ClientError::Proof(ProofError::BundleFailure(
+                "expected a map inclusion proof for each change".into(),
+            )));
+        }
+
+        let root: Hash<Sha256> = checkpoint.map_root.try_into()?;
+        for (leaf, proof) in response.changes.iter().zip(map_inclusions.iter()) {
+            let found = proof.evaluate(
+                &leaf.log_id,
+                &MapLeaf {
+                    record_id: leaf.record_id.clone(),
+                },
+            );
+            if found != root {
+                return Err(ClientError::Proof(ProofError::IncorrectProof {
+                    root: checkpoint.map_root,
+                    found: found.into(),
+                }));
+            }
+        }
+
+        Ok(response.changes)
     }
 
     /// Proves consistency between two log roots.
@@ -493,13 +1002,13 @@ impl Client {
     ) -> Result<(), ClientError> {
         let url = self.url.join(paths::prove_consistency());
         let response = into_result::<ConsistencyResponse, ProofError>(
-            self.client
-                .post(url)
-                .json(&request)
-                .warg_header(registry_domain)?
-                .auth(self.auth_token())
-                .send()
-                .await?,
+            self.send(
+                self.client
+                    .post(url)
+                    .json(&request)
+                    .warg_header(registry_domain)?,
+            )
+            .await?,
         )
         .await?;
 
@@ -546,7 +1055,7 @@ impl Client {
         method: &str,
         url: &str,
         headers: &IndexMap<String, String>,
-        content: impl Into<Body>,
+        content: impl Stream<Item = Result<Bytes>> + Send + Sync + 'static,
     ) -> Result<(), ClientError> {
         // Upload URLs may be relative to the registry URL.
         let url = self.url.join(url);
@@ -557,7 +1066,7 @@ impl Client {
             method => return Err(ClientError::InvalidHttpMethod(method.to_string())),
         };
 
-        let headers = headers
+        let mut headers = headers
             .iter()
             .map(|(k, v)| {
                 let name = match k.as_str() {
@@ -570,15 +1079,18 @@ impl Client {
                 Ok((name, value))
             })
             .collect::<Result<HeaderMap, ClientError>>()?;
+        let url = Url::parse(&url).map_err(|e| anyhow!(e))?;
+        self.authorize(&url, &mut headers).await?;
 
         tracing::debug!("uploading content to `{url}`");
 
         let response = self
-            .client
-            .request(method, url)
-            .headers(headers)
-            .body(content)
-            .send()
+            .dispatch(TransportRequest {
+                method,
+                url,
+                headers,
+                body: RequestBody::Stream(Box::pin(content)),
+            })
             .await?;
         if !response.status().is_success() {
             return Err(ClientError::Package(
@@ -589,7 +1101,48 @@ impl Client {
         Ok(())
     }
 
-    fn validate_inclusion_response(
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        let mut request = request.build()?;
+        let url = request.url().clone();
+        self.authorize(&url, request.headers_mut()).await?;
+        self.dispatch(request.into()).await
+    }
+
+    /// Dispatches a request through the middleware chain to the transport.
+    async fn dispatch(&self, request: TransportRequest) -> Result<Response, ClientError> {
+        Next::new(&self.middleware, self.transport.as_ref())
+            .run(request)
+            .await
+    }
+
+    /// Authorizes a request to the given URL with a token from the auth provider.
+    ///
+    /// Tokens are only sent to the registry itself and never replace an
+    /// authorization header already present on the request.
+    async fn authorize(&self, url: &Url, headers: &mut HeaderMap) -> Result<(), ClientError> {
+        let Some(provider) = &self.auth_provider else {
+            return Ok(());
+        };
+
+        if !self.url.same_origin(url) || headers.contains_key(AUTHORIZATION) {
+            return Ok(());
+        }
+
+        if let Some(token) = provider
+            .token()
+            .await
+            .map_err(ClientError::Authentication)?
+        {
+            let mut value = HeaderValue::try_from(format!("Bearer {}", token.expose_secret()))
+                .map_err(|e| ClientError::Authentication(e.into()))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate_inclusion_response(
         response: InclusionResponse,
         checkpoint: &Checkpoint,
         leafs: &[LogLeaf],
@@ -599,10 +1152,10 @@ impl Client {
         let (log_data, _, log_inclusions) = log_proof_bundle.unbundle();
         for (leaf, proof) in leafs.iter().zip(log_inclusions.iter()) {
             let found = proof.evaluate_value(&log_data, leaf)?;
-            let root = checkpoint.log_root.clone().try_into()?;
+            let root = checkpoint.log_root.try_into()?;
             if found != root {
                 return Err(ClientError::Proof(ProofError::IncorrectProof {
-                    root: checkpoint.log_root.clone(),
+                    root: checkpoint.log_root,
                     found: found.into(),
                 }));
             }
@@ -618,10 +1171,10 @@ impl Client {
                     record_id: leaf.record_id.clone(),
                 },
             );
-            let root = checkpoint.map_root.clone().try_into()?;
+            let root = checkpoint.map_root.try_into()?;
             if found != root {
                 return Err(ClientError::Proof(ProofError::IncorrectProof {
-                    root: checkpoint.map_root.clone(),
+                    root: checkpoint.map_root,
                     found: found.into(),
                 }));
             }
@@ -631,12 +1184,171 @@ impl Client {
     }
 }
 
+/// The default maximum length of a frame of a streamed fetch logs response.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+/// A stream of the frames of a streamed fetch logs response.
+///
+/// Frames are decoded as the response body is received, so that at most one
+/// frame, bounded by the maximum frame length, is buffered at a time.
+///
+/// The stream ends after a [`FetchLogsFrame::End`] frame is received; a
+/// response that ends before that frame, or that the server ends with an
+/// error, results in an error.
+pub struct EnvelopeStream {
+    body: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    buf: BytesMut,
+    max_frame_len: usize,
+    hint: Option<HeaderValue>,
+    done: bool,
+}
+
+impl EnvelopeStream {
+    /// Creates a new stream of the frames of the given response body.
+    pub fn new(body: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static) -> Self {
+        Self {
+            body: Box::pin(body),
+            buf: BytesMut::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            hint: None,
+            done: false,
+        }
+    }
+
+    /// Sets the registry hint returned with the response.
+    ///
+    /// A log that the server reports as not found while streaming is
+    /// returned as [`ClientError::LogNotFoundWithHint`] with the hint.
+    fn with_hint(mut self, hint: Option<HeaderValue>) -> Self {
+        self.hint = hint;
+        self
+    }
+
+    /// Sets the maximum length of a frame.
+    ///
+    /// Defaults to [`DEFAULT_MAX_FRAME_LEN`].
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Decodes the next frame from the buffer, if it has been fully received.
+    fn next_frame(&mut self) -> Result<Option<FetchLogsFrame>, ClientError> {
+        // A length prefix is a varint of at most 10 bytes
+        let Some(prefix_end) = self.buf.iter().take(10).position(|b| b & 0x80 == 0) else {
+            if self.buf.len() >= 10 {
+                return Err(FetchLogsFrameError::Decode(prost::DecodeError::new(
+                    "invalid frame length",
+                ))
+                .into());
+            }
+            return Ok(None);
+        };
+
+        let len = prost::encoding::decode_varint(&mut &self.buf[..=prefix_end])
+            .map_err(FetchLogsFrameError::Decode)?;
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= self.max_frame_len)
+            .ok_or(ClientError::FrameTooLarge {
+                len,
+                max: self.max_frame_len,
+            })?;
+
+        if self.buf.len() <= prefix_end + len {
+            return Ok(None);
+        }
+
+        self.buf.advance(prefix_end + 1);
+        let frame = self.buf.split_to(len);
+        match FetchLogsFrame::decode(&frame) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(FetchLogsFrameError::Fetch(FetchError::LogNotFound(log_id)))
+                if self.hint.is_some() =>
+            {
+                Err(ClientError::LogNotFoundWithHint(
+                    log_id,
+                    self.hint.clone().unwrap(),
+                ))
+            }
+            Err(FetchLogsFrameError::Fetch(e)) => Err(e.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl From<FetchLogsResponse> for EnvelopeStream {
+    /// Creates a stream of the frames of an already received response.
+    fn from(response: FetchLogsResponse) -> Self {
+        let body = response
+            .operator
+            .into_iter()
+            .map(FetchLogsFrame::Operator)
+            .chain(response.packages.into_iter().flat_map(|(log_id, records)| {
+                records
+                    .into_iter()
+                    .map(move |record| FetchLogsFrame::Package {
+                        log_id: log_id.clone(),
+                        record,
+                    })
+            }))
+            .chain([FetchLogsFrame::End {
+                more: response.more,
+                warnings: response.warnings,
+            }])
+            .flat_map(|frame| frame.encode_length_delimited())
+            .collect::<Vec<_>>();
+
+        Self::new(once(ready(Ok(Bytes::from(body)))))
+    }
+}
+
+impl Stream for EnvelopeStream {
+    type Item = Result<FetchLogsFrame, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match this.next_frame() {
+                Ok(Some(frame)) => {
+                    this.done = matches!(frame, FetchLogsFrame::End { .. });
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            match ready!(this.body.as_mut().poll_next(cx)) {
+                Some(Ok(bytes)) => this.buf.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                None => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(ClientError::UnexpectedResponse {
+                        status: StatusCode::OK,
+                        message: "the fetch logs stream ended unexpectedly".into(),
+                    })));
+                }
+            }
+        }
+    }
+}
+
 fn validate_stream(
     digest: &AnyHash,
     stream: impl Stream<Item = Result<Bytes>>,
 ) -> impl Stream<Item = Result<Bytes>> {
     let hasher = Some(digest.algorithm().hasher());
-    let expected = digest.clone();
+    let expected = *digest;
     stream
         .map_ok(Some)
         .chain(once(async { Ok(None) }))
@@ -661,3 +1373,115 @@ fn validate_stream(
             })
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+    use std::time::SystemTime;
+    use warg_api::v1::fetch::PublishedRecord;
+    use warg_crypto::{hash::HashAlgorithm, signing::generate_p256_pair};
+    use warg_protocol::{
+        operator::{OperatorEntry, OperatorRecord},
+        ProtoEnvelope, PublishedProtoEnvelope,
+    };
+
+    fn frames() -> Vec<FetchLogsFrame> {
+        let (public_key, private_key) = generate_p256_pair();
+        let envelope = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key,
+                }],
+            },
+        )
+        .unwrap();
+
+        vec![
+            FetchLogsFrame::Operator(PublishedRecord {
+                envelope: PublishedProtoEnvelope {
+                    envelope,
+                    registry_index: 0,
+                }
+                .into(),
+                fetch_token: "token".to_string(),
+            }),
+            FetchLogsFrame::End {
+                more: true,
+                warnings: Vec::new(),
+            },
+        ]
+    }
+
+    fn body(frames: &[FetchLogsFrame], chunk_len: usize) -> Vec<reqwest::Result<Bytes>> {
+        frames
+            .iter()
+            .flat_map(FetchLogsFrame::encode_length_delimited)
+            .collect::<Vec<_>>()
+            .chunks(chunk_len)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn decodes_frames_as_they_arrive() {
+        let frames = frames();
+        let decoded = EnvelopeStream::new(stream::iter(body(&frames, 1)))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        match (&decoded[0], &frames[0]) {
+            (FetchLogsFrame::Operator(a), FetchLogsFrame::Operator(b)) => {
+                assert_eq!(a.fetch_token, b.fetch_token);
+                assert_eq!(a.envelope.registry_index, b.envelope.registry_index);
+                assert_eq!(
+                    a.envelope.envelope.to_protobuf(),
+                    b.envelope.envelope.to_protobuf()
+                );
+            }
+            _ => panic!("expected an operator record"),
+        }
+        assert!(matches!(decoded[1], FetchLogsFrame::End { more: true, .. }));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_streams() {
+        let frames = frames();
+
+        // Frames larger than the maximum are not buffered
+        let err = EnvelopeStream::new(stream::iter(body(&frames, 16)))
+            .with_max_frame_len(16)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::FrameTooLarge { max: 16, .. }));
+
+        // A stream must end with an end frame
+        let err = EnvelopeStream::new(stream::iter(body(&frames[..1], 16)))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::UnexpectedResponse { .. }));
+
+        // A stream ended with an error results in that error
+        let log_id =
+            LogId::package_log::<warg_crypto::hash::Sha256>(&"test:missing".parse().unwrap());
+        let mut body = body(&frames[..1], 16);
+        body.push(Ok(FetchLogsFrame::encode_error_length_delimited(
+            &FetchError::LogNotFound(log_id.clone()),
+        )
+        .into()));
+        let err = EnvelopeStream::new(stream::iter(body))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::Fetch(FetchError::LogNotFound(id)) if id == log_id));
+    }
+}
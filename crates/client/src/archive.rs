@@ -0,0 +1,248 @@
+//! A client for registry log ranges archived to cold storage.
+//!
+//! See [`warg_api::v1::archive`] for a description of the layout.
+
+use crate::{
+    api,
+    static_site::{StaticSiteClient, StaticSiteError},
+};
+use std::ops::Range;
+use thiserror::Error;
+use url::Url;
+use warg_api::v1::{
+    archive::{self, ArchiveSegment, SegmentRecord},
+    proof::ProofError,
+};
+use warg_crypto::{
+    hash::{AnyHash, Hash, Sha256},
+    signing, Encode, Signable,
+};
+use warg_protocol::{
+    archive::{ArchiveManifest, ArchivedRange},
+    operator, package,
+    registry::{LogId, LogLeaf, RecordId, RegistryIndex},
+    PublishedProtoEnvelope, SerdeEnvelope,
+};
+use warg_transparency::log::{LogProofBundle, Node};
+
+/// Represents an error from an archive client.
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    /// A file of the archive could not be retrieved or deserialized.
+    #[error(transparent)]
+    Fetch(#[from] StaticSiteError),
+    /// The manifest was signed by an unknown key.
+    #[error("archive manifest signed by unknown key `{0}`")]
+    InvalidManifestKeyId(signing::KeyID),
+    /// The manifest signature failed verification.
+    #[error("invalid archive manifest signature")]
+    InvalidManifestSignature,
+    /// The requested range is not archived.
+    #[error("registry log range {start}..{end} is not archived")]
+    NotArchived {
+        /// The start of the requested range.
+        start: RegistryIndex,
+        /// The end of the requested range.
+        end: RegistryIndex,
+    },
+    /// A segment did not match the digest in the manifest.
+    #[error("archive segment {start}..{end} does not match its digest `{digest}`")]
+    SegmentDigestMismatch {
+        /// The start of the segment.
+        start: RegistryIndex,
+        /// The end of the segment.
+        end: RegistryIndex,
+        /// The digest of the segment in the manifest.
+        digest: AnyHash,
+    },
+    /// A segment does not hold the records of its range.
+    #[error("archive segment {start}..{end} is malformed: {reason}")]
+    MalformedSegment {
+        /// The start of the segment.
+        start: RegistryIndex,
+        /// The end of the segment.
+        end: RegistryIndex,
+        /// The reason the segment is malformed.
+        reason: String,
+    },
+    /// A record in a segment could not be decoded.
+    #[error("failed to decode archived record: {0}")]
+    Record(anyhow::Error),
+    /// A record failed its inclusion proof.
+    #[error("failed to prove inclusion of archived record at registry index {index}: {inner}")]
+    InclusionProof {
+        /// The registry index of the record.
+        index: RegistryIndex,
+        /// The proof error.
+        inner: api::ClientError,
+    },
+}
+
+/// A client for an archive of registry log ranges.
+///
+/// Both `http(s)` and `file` base URLs are supported.
+pub struct ArchiveClient {
+    site: StaticSiteClient,
+}
+
+impl ArchiveClient {
+    /// Creates a new client for the archive at the given base URL.
+    pub fn new(base: Url) -> Self {
+        Self {
+            site: StaticSiteClient::new(base),
+        }
+    }
+
+    /// Gets the base URL of the archive.
+    pub fn url(&self) -> &Url {
+        self.site.url()
+    }
+
+    /// Fetches the archive manifest, verifying its signature with the keys
+    /// of the given operator log state.
+    ///
+    /// Callers should check that the manifest's checkpoint is consistent with
+    /// a checkpoint they trust.
+    pub async fn manifest(
+        &self,
+        operator: &operator::LogState,
+    ) -> Result<ArchiveManifest, ArchiveError> {
+        let manifest: SerdeEnvelope<ArchiveManifest> =
+            self.site.get_json(archive::manifest()).await?;
+        let key = operator
+            .public_key(manifest.key_id())
+            .ok_or_else(|| ArchiveError::InvalidManifestKeyId(manifest.key_id().clone()))?;
+        ArchiveManifest::verify(key, &manifest.as_ref().encode(), manifest.signature())
+            .map_err(|_| ArchiveError::InvalidManifestSignature)?;
+
+        Ok(manifest.into_contents())
+    }
+
+    /// Fetches the records of the given range of the registry log.
+    ///
+    /// Each segment overlapping the range is verified against its digest in
+    /// the manifest, and each of its records against the log root of the
+    /// manifest's checkpoint.
+    pub async fn fetch_range(
+        &self,
+        manifest: &ArchiveManifest,
+        range: Range<RegistryIndex>,
+    ) -> Result<Vec<SegmentRecord>, ArchiveError> {
+        if !manifest.covers(range.clone()) {
+            return Err(ArchiveError::NotArchived {
+                start: range.start,
+                end: range.end,
+            });
+        }
+
+        let mut records = Vec::with_capacity(range.len());
+        for segment in manifest.segments_overlapping(range.clone()) {
+            let fetched = self.fetch_segment(manifest, segment).await?;
+            records.extend(
+                fetched
+                    .into_iter()
+                    .filter(|r| range.contains(&r.record.registry_index)),
+            );
+        }
+
+        Ok(records)
+    }
+
+    async fn fetch_segment(
+        &self,
+        manifest: &ArchiveManifest,
+        segment: &ArchivedRange,
+    ) -> Result<Vec<SegmentRecord>, ArchiveError> {
+        let (start, end) = (segment.start, segment.end);
+        let malformed = |reason: String| ArchiveError::MalformedSegment { start, end, reason };
+
+        let bytes = self.site.get(&archive::segment(start, end)).await?;
+        if segment.digest.algorithm().digest(&bytes) != segment.digest {
+            return Err(ArchiveError::SegmentDigestMismatch {
+                start,
+                end,
+                digest: segment.digest,
+            });
+        }
+
+        let fetched: ArchiveSegment =
+            serde_json::from_slice(&bytes).map_err(|e| malformed(e.to_string()))?;
+        if fetched.records.len() != end - start {
+            return Err(malformed(format!(
+                "expected {expected} records but found {found}",
+                expected = end - start,
+                found = fetched.records.len()
+            )));
+        }
+
+        let bundle: LogProofBundle<Sha256, LogLeaf> =
+            LogProofBundle::decode(fetched.proof.as_slice())
+                .map_err(|e| malformed(e.to_string()))?;
+        let (log_data, _, inclusions) = bundle.unbundle();
+        if inclusions.len() != fetched.records.len() {
+            return Err(malformed(
+                "expected an inclusion proof for each record".to_string(),
+            ));
+        }
+
+        let root: Hash<Sha256> = manifest
+            .checkpoint
+            .log_root
+            .try_into()
+            .map_err(|e: warg_crypto::hash::HashError| malformed(e.to_string()))?;
+        for (i, (record, proof)) in fetched.records.iter().zip(inclusions.iter()).enumerate() {
+            let index = start + i;
+            if record.record.registry_index != index {
+                return Err(malformed(format!(
+                    "expected record at registry index {index} but found index {found}",
+                    found = record.record.registry_index
+                )));
+            }
+
+            // The proof must be of this record's position in the manifest's checkpoint
+            if proof.leaf() != Node(index * 2)
+                || proof.log_length() != manifest.checkpoint.log_length
+            {
+                return Err(malformed(format!(
+                    "the inclusion proof of registry index {index} is for a different position"
+                )));
+            }
+
+            let leaf = LogLeaf {
+                log_id: record.log_id.clone(),
+                record_id: record_id(record)?,
+            };
+            let found = proof.evaluate_value(&log_data, &leaf).map_err(|e| {
+                ArchiveError::InclusionProof {
+                    index,
+                    inner: e.into(),
+                }
+            })?;
+            if found != root {
+                return Err(ArchiveError::InclusionProof {
+                    index,
+                    inner: api::ClientError::Proof(ProofError::IncorrectProof {
+                        root: manifest.checkpoint.log_root,
+                        found: found.into(),
+                    }),
+                });
+            }
+        }
+
+        Ok(fetched.records)
+    }
+}
+
+/// Computes the record identifier of an archived record.
+fn record_id(record: &SegmentRecord) -> Result<RecordId, ArchiveError> {
+    let body = record.record.clone();
+    if record.log_id == LogId::operator_log::<Sha256>() {
+        let record: PublishedProtoEnvelope<operator::OperatorRecord> =
+            body.try_into().map_err(ArchiveError::Record)?;
+        Ok(RecordId::operator_record::<Sha256>(&record.envelope))
+    } else {
+        let record: PublishedProtoEnvelope<package::PackageRecord> =
+            body.try_into().map_err(ArchiveError::Record)?;
+        Ok(RecordId::package_record::<Sha256>(&record.envelope))
+    }
+}
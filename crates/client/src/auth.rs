@@ -0,0 +1,229 @@
+//! Authentication with registries.
+//!
+//! Requests to a registry are authenticated with bearer tokens obtained from
+//! an [`AuthProvider`] set with
+//! [`Client::with_auth_provider`](crate::api::Client::with_auth_provider).
+//!
+//! Private registries commonly accept OpenID Connect (OIDC) tokens issued
+//! by an identity provider; [`OidcTokenFile`] and [`OidcClientCredentials`]
+//! obtain such tokens for workloads and services.
+
+use anyhow::{bail, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use url::Url;
+
+/// Tokens are refreshed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// A trait implemented by providers of registry credentials.
+#[async_trait::async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Gets the bearer token to authenticate a request to the registry.
+    ///
+    /// The provider is consulted for every request, so implementations
+    /// should cache tokens that are expensive to obtain.
+    ///
+    /// Returns `None` if the request should not be authenticated.
+    async fn token(&self) -> Result<Option<Secret<String>>>;
+}
+
+/// A provider of a fixed bearer token.
+pub struct BearerToken(Secret<String>);
+
+impl BearerToken {
+    /// Creates a new provider of the given bearer token.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(Secret::new(token.into()))
+    }
+}
+
+impl From<Secret<String>> for BearerToken {
+    fn from(token: Secret<String>) -> Self {
+        Self(token)
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for BearerToken {
+    async fn token(&self) -> Result<Option<Secret<String>>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// A provider of an OIDC identity token read from a file.
+///
+/// Workload identity systems, such as Kubernetes projected service account
+/// tokens, rotate the token file in place; the file is read for every request
+/// so that the current token is always used.
+pub struct OidcTokenFile {
+    path: PathBuf,
+}
+
+impl OidcTokenFile {
+    /// Creates a new provider of the token in the given file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for OidcTokenFile {
+    async fn token(&self) -> Result<Option<Secret<String>>> {
+        let token = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to read token file `{path}`",
+                    path = self.path.display()
+                )
+            })?;
+
+        let token = token.trim();
+        if token.is_empty() {
+            bail!("token file `{path}` is empty", path = self.path.display());
+        }
+
+        Ok(Some(Secret::new(token.to_string())))
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// A provider of OIDC access tokens obtained with the OAuth 2.0 client
+/// credentials grant.
+///
+/// Tokens are cached until shortly before they expire.
+pub struct OidcClientCredentials {
+    client: reqwest::Client,
+    token_url: Url,
+    client_id: String,
+    client_secret: Secret<String>,
+    scope: Option<String>,
+    audience: Option<String>,
+    cached: Mutex<Option<(Secret<String>, Option<Instant>)>>,
+}
+
+impl OidcClientCredentials {
+    /// Creates a new provider that requests tokens from the given token
+    /// endpoint of an identity provider.
+    pub fn new(
+        token_url: Url,
+        client_id: impl Into<String>,
+        client_secret: Secret<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token_url,
+            client_id: client_id.into(),
+            client_secret,
+            scope: None,
+            audience: None,
+            cached: Default::default(),
+        }
+    }
+
+    /// Sets the scope to request tokens for.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets the audience to request tokens for.
+    ///
+    /// Identity providers that issue tokens for many services typically
+    /// require the audience to be the registry URL.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    async fn request_token(&self) -> Result<(Secret<String>, Option<Instant>)> {
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", &self.client_id),
+            ("client_secret", self.client_secret.expose_secret()),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience));
+        }
+
+        let response = self
+            .client
+            .post(self.token_url.clone())
+            .form(&form)
+            .send()
+            .await
+            .with_context(|| format!("failed to request token from `{}`", self.token_url))?;
+        if !response.status().is_success() {
+            bail!(
+                "token endpoint `{url}` returned status {status}",
+                url = self.token_url,
+                status = response.status()
+            );
+        }
+
+        let response: TokenResponse = response
+            .json()
+            .await
+            .with_context(|| format!("invalid token response from `{}`", self.token_url))?;
+        let expires_at = response
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs).saturating_sub(EXPIRY_MARGIN));
+        Ok((Secret::new(response.access_token), expires_at))
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthProvider for OidcClientCredentials {
+    async fn token(&self) -> Result<Option<Secret<String>>> {
+        let mut cached = self.cached.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if expires_at.map_or(true, |at| Instant::now() < at) {
+                return Ok(Some(token.clone()));
+            }
+        }
+
+        let (token, expires_at) = self.request_token().await?;
+        *cached = Some((token.clone(), expires_at));
+        Ok(Some(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_rotated_token_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        let provider = OidcTokenFile::new(&path);
+
+        assert!(provider.token().await.is_err());
+
+        std::fs::write(&path, "first\n").unwrap();
+        let token = provider.token().await.unwrap().unwrap();
+        assert_eq!(token.expose_secret(), "first");
+
+        std::fs::write(&path, "second").unwrap();
+        let token = provider.token().await.unwrap().unwrap();
+        assert_eq!(token.expose_secret(), "second");
+
+        std::fs::write(&path, "  ").unwrap();
+        assert!(provider.token().await.is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! Module for client configuration.
 
-use crate::{ClientError, RegistryUrl};
+use crate::{storage::StorageLayout, ClientError, RegistryUrl};
 use anyhow::{anyhow, Context, Result};
 use indexmap::IndexSet;
 use normpath::PathExt;
@@ -126,6 +126,13 @@ pub struct Config {
     /// Disable interactive prompts.
     #[serde(default)]
     pub disable_interactive: bool,
+
+    /// The layout of the files in the registries and content directories.
+    ///
+    /// Files stored under a previous layout are still found; new files are
+    /// stored under this layout.
+    #[serde(default)]
+    pub storage_layout: StorageLayout,
 }
 
 impl Config {
@@ -207,6 +214,7 @@ impl Config {
             ignore_federation_hints: self.ignore_federation_hints,
             auto_accept_federation_hints: self.auto_accept_federation_hints,
             disable_interactive: self.disable_interactive,
+            storage_layout: self.storage_layout,
         };
 
         serde_json::to_writer_pretty(
@@ -13,8 +13,6 @@ use wasmparser::{Chunk, ComponentImportSectionReader, Parser, Payload};
 use super::Client;
 use crate::storage::{ContentStorage, NamespaceMapStorage, PackageInfo, RegistryStorage};
 use crate::version_util::{DependencyImportParser, Import, ImportKind};
-/// Import Kinds found in components
-
 /// Creates list of dependenies for locking components
 pub struct LockListBuilder {
     /// List of deps to include in locked component
@@ -112,9 +110,9 @@ impl LockListBuilder {
                         .load_package(registry_domain.as_ref(), &id)
                         .await?
                     {
-                        let release = info.state.releases().last();
+                        let release = info.state.stored_releases().last();
                         if let Some(r) = release {
-                            if let Some(bytes) = self.release_bytes(r, client)? {
+                            if let Some(bytes) = self.release_bytes(&r, client)? {
                                 self.parse_package(client, &bytes).await?;
                             }
                         }
@@ -129,9 +127,9 @@ impl LockListBuilder {
                             )
                             .await?
                         {
-                            let release = info.state.releases().last();
+                            let release = info.state.stored_releases().last();
                             if let Some(r) = release {
-                                if let Some(bytes) = self.release_bytes(r, client)? {
+                                if let Some(bytes) = self.release_bytes(&r, client)? {
                                     self.parse_package(client, &bytes).await?;
                                 }
                             }
@@ -172,7 +170,7 @@ impl LockListBuilder {
         C: ContentStorage,
         N: NamespaceMapStorage,
     {
-        let release = info.state.releases().last();
+        let release = info.state.stored_releases().last();
         if let Some(r) = release {
             let state = &r.state;
             if let ReleaseState::Released { content } = state {
@@ -238,11 +236,11 @@ where
                 {
                     let release = if parsed_imp.req != VersionReq::STAR {
                         info.state
-                            .releases()
+                            .stored_releases()
                             .filter(|r| parsed_imp.req.matches(&r.version))
                             .last()
                     } else {
-                        info.state.releases().last()
+                        info.state.stored_releases().last()
                     };
                     if let Some(r) = release {
                         let release_state = &r.state;
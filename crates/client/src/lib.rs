@@ -1,40 +1,59 @@
 //! A client library for Warg component registries.
 
 #![deny(missing_docs)]
+use crate::auth::AuthProvider;
 use crate::storage::PackageInfo;
 
 use anyhow::{anyhow, Context, Result};
 use bytes::Bytes;
 use futures_util::{Stream, StreamExt, TryStreamExt};
-use indexmap::IndexMap;
-use reqwest::{Body, IntoUrl};
+use indexmap::{IndexMap, IndexSet};
+use reqwest::IntoUrl;
 use secrecy::Secret;
 use semver::{Version, VersionReq};
 use std::cmp::Ordering;
 use std::fs;
 use std::str::FromStr;
-use std::{borrow::Cow, path::PathBuf, time::Duration};
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 use storage::{
     ContentStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage,
-    FileSystemRegistryStorage, NamespaceMapStorage, PublishInfo, RegistryDomain, RegistryStorage,
+    FileSystemRegistryStorage, NamespaceMapStorage, OperatorInfo, PublishEntry, PublishInfo,
+    RegistryDomain, RegistryStorage,
 };
 use thiserror::Error;
 use tokio_util::io::ReaderStream;
 use warg_api::v1::{
-    fetch::{FetchError, FetchLogsRequest},
+    admin::{AdminCommandRequest, AdminCommandResponse},
+    fetch::{FetchError, FetchLogsFrame, FetchLogsRequest},
     package::{
         MissingContent, PackageError, PackageRecord, PackageRecordState, PublishRecordRequest,
         UploadEndpoint,
     },
     proof::{ConsistencyRequest, InclusionRequest},
+    search::SearchQuery,
 };
 use warg_crypto::hash::Sha256;
-use warg_crypto::{hash::AnyHash, signing, Encode, Signable};
+use warg_crypto::{
+    encryption::{ContentEncryption, EncryptionError},
+    hash::AnyHash,
+    signing, Encode, Signable,
+};
 use warg_protocol::package::ReleaseState;
 use warg_protocol::{
+    discovery::OperatorKeys,
+    filter::{InclusionFilter, PackageFilter},
     operator, package,
-    registry::{LogId, LogLeaf, PackageName, RecordId, RegistryLen, TimestampedCheckpoint},
-    PublishedProtoEnvelope,
+    policy::{TimeWindow, TimeWindowError},
+    registry::{
+        Checkpoint, FreshnessAssertion, LogId, LogLeaf, PackageName, RecordId, RecordReceipt,
+        RegistryLen, TimestampedCheckpoint,
+    },
+    CoPublication, CoPublicationError, CoPublicationStatus, CoPublishedRecord, Countersignature,
+    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
 };
 use wasm_compose::graph::{CompositionGraph, EncodeOptions, ExportIndex, InstanceId};
 
@@ -42,6 +61,8 @@ use wasm_compose::graph::{CompositionGraph, EncodeOptions, ExportIndex, Instance
 pub mod keyring;
 
 pub mod api;
+pub mod archive;
+pub mod auth;
 mod config;
 /// Tools for locking and bundling components
 pub mod depsolve;
@@ -50,8 +71,15 @@ use depsolve::{Bundler, LockListBuilder};
 pub mod version_util;
 use version_util::{kindless_name, locked_package, versioned_package, Import, ImportKind};
 pub mod lock;
+pub mod monitor;
+pub mod sbom;
+use sbom::{Sbom, SbomPackage};
 mod registry_url;
+pub mod source;
+pub mod static_site;
 pub mod storage;
+pub mod testing;
+pub mod transport;
 pub use self::config::*;
 pub use self::registry_url::RegistryUrl;
 
@@ -71,6 +99,13 @@ where
     ignore_federation_hints: bool,
     auto_accept_federation_hints: bool,
     disable_interactive: bool,
+    freshness_window: Option<TimeWindow>,
+    root_of_trust: Option<signing::PublicKey>,
+    auto_rebase: bool,
+    validation_rules: package::ValidationRules,
+    publish_token: Option<package::PublishToken>,
+    pinned_checkpoints: IndexMap<Option<RegistryDomain>, Checkpoint>,
+    migration_sources: IndexMap<String, signing::PublicKey>,
 }
 
 impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C, N> {
@@ -96,9 +131,134 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
             ignore_federation_hints,
             auto_accept_federation_hints,
             disable_interactive,
+            freshness_window: None,
+            root_of_trust: None,
+            auto_rebase: false,
+            validation_rules: Default::default(),
+            publish_token: None,
+            pinned_checkpoints: IndexMap::new(),
+            migration_sources: IndexMap::new(),
         })
     }
 
+    /// Sets the provider of credentials for requests to the registry.
+    ///
+    /// This replaces any auth token the client was created with.
+    pub fn with_auth_provider(mut self, provider: impl AuthProvider + 'static) -> Self {
+        self.api = self.api.with_auth_provider(provider);
+        self
+    }
+
+    /// Publishes records under the given publish token.
+    ///
+    /// The token is issued by a maintainer key and authorizes the signing key
+    /// used to publish, such as one held by a CI system, to publish releases
+    /// of the packages it names without being granted permission in their logs.
+    pub fn with_publish_token(mut self, token: package::PublishToken) -> Self {
+        self.publish_token = Some(token);
+        self
+    }
+
+    /// Requires the registry's latest checkpoint to be asserted fresh within
+    /// the given window when updating.
+    ///
+    /// Updates fail if the registry (or a mirror of it) serves a checkpoint
+    /// whose freshness assertion is missing, invalid, or older than the window.
+    ///
+    /// Assertions made further in the future than the default clock skew of
+    /// a [`TimeWindow`] are also rejected.
+    pub fn with_freshness_window(mut self, window: Duration) -> Self {
+        self.freshness_window = Some(TimeWindow::default().with_max_past_age(window));
+        self
+    }
+
+    /// Requires the home registry's checkpoints to be signed by an operator
+    /// key listed in its operator keys document, signed by the given root
+    /// of trust.
+    ///
+    /// The document is cached in registry storage and refreshed from the
+    /// registry when it expires or does not list the checkpoint's key.
+    /// Registries of federated namespaces are not checked.
+    pub fn with_root_of_trust(mut self, key: signing::PublicKey) -> Self {
+        self.root_of_trust = Some(key);
+        self
+    }
+
+    /// Pins fetches from a registry to the given historical checkpoint.
+    ///
+    /// Operator and package logs are fetched and proven against the pinned
+    /// checkpoint rather than the registry's latest, so that a build can be
+    /// reproduced exactly against the registry state it was resolved at. A
+    /// registry domain of `None` pins the home registry.
+    ///
+    /// The registry must still serve a checkpoint at the pinned log length
+    /// with the same roots, and that checkpoint must be consistent with the
+    /// latest checkpoint previously seen. Freshness is not checked for pinned
+    /// registries.
+    pub fn with_pinned_checkpoint(
+        mut self,
+        registry_domain: Option<RegistryDomain>,
+        checkpoint: Checkpoint,
+    ) -> Self {
+        self.pinned_checkpoints.insert(registry_domain, checkpoint);
+        self
+    }
+
+    /// Trusts the given operator key of a registry that namespaces were
+    /// migrated from.
+    ///
+    /// Packages of a migrated namespace are only updated once the migration
+    /// is verified to be signed by an operator key of the source registry.
+    /// Without a trusted key, the signing key is checked against the source
+    /// registry's operator log, which is fetched if it is not in registry
+    /// storage; a trusted key allows following migrations from a registry
+    /// that is no longer available.
+    pub fn with_migration_source_key(
+        mut self,
+        registry: impl Into<String>,
+        key: signing::PublicKey,
+    ) -> Self {
+        self.migration_sources.insert(registry.into(), key);
+        self
+    }
+
+    /// Automatically rebases publishes onto the current head of a package log.
+    ///
+    /// When the registry reports that a package log has moved past the head a
+    /// record was built against, the record is rebuilt against the current head
+    /// and re-signed, provided none of its entries conflict with the records
+    /// published in the meantime.
+    pub fn with_auto_rebase(mut self, auto_rebase: bool) -> Self {
+        self.auto_rebase = auto_rebase;
+        self
+    }
+
+    /// Sets the rules the package logs of the registry are validated under.
+    ///
+    /// The rules should be those the registry enforces; by default, package
+    /// logs are validated under the rules every package log follows.
+    pub fn with_validation_rules(mut self, rules: package::ValidationRules) -> Self {
+        self.validation_rules = rules;
+        self
+    }
+
+    /// Sets the transport used to send requests to the registry.
+    ///
+    /// By default, requests are sent over HTTP.
+    pub fn with_transport(mut self, transport: impl transport::Transport + 'static) -> Self {
+        self.api = self.api.with_transport(transport);
+        self
+    }
+
+    /// Adds middleware intercepting the requests sent to the registry.
+    ///
+    /// Middleware runs in the order it was added; see
+    /// [`transport::Middleware`].
+    pub fn with_middleware(mut self, middleware: impl transport::Middleware + 'static) -> Self {
+        self.api = self.api.with_middleware(middleware);
+        self
+    }
+
     /// Gets the URL of the client.
     pub fn url(&self) -> &RegistryUrl {
         self.api.url()
@@ -206,22 +366,21 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
             if let Some(inf) = info {
                 let release = if version != VersionReq::STAR {
                     inf.state
-                        .releases()
+                        .stored_releases()
                         .filter(|r| version.matches(&r.version))
                         .last()
                 } else {
-                    inf.state.releases().last()
+                    inf.state.stored_releases().last()
                 };
 
                 if let Some(r) = release {
                     let state = &r.state;
                     if let ReleaseState::Released { content } = state {
-                        let locked_package = locked_package(&package.name, r, content);
+                        let locked_package = locked_package(&package.name, &r, content);
                         let path = self.content().content_location(content);
                         if let Some(p) = path {
-                            let bytes = fs::read(&p).map_err(|_| ClientError::ContentNotFound {
-                                digest: content.clone(),
-                            })?;
+                            let bytes = fs::read(&p)
+                                .map_err(|_| ClientError::ContentNotFound { digest: *content })?;
 
                             let read_digest =
                                 AnyHash::from_str(&format!("sha256:{}", sha256::digest(bytes)))
@@ -229,7 +388,7 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
                             if content != &read_digest {
                                 return Err(ClientError::IncorrectContent {
                                     digest: read_digest,
-                                    expected: content.clone(),
+                                    expected: *content,
                                 });
                             }
                             let component =
@@ -292,6 +451,84 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
         Ok(bundled.as_slice().to_vec())
     }
 
+    /// Builds a bill of materials for the latest release of a package and
+    /// the dependencies resolved for it.
+    ///
+    /// Dependencies are resolved the same way as by `lock_component`.
+    pub async fn sbom(&self, info: &PackageInfo) -> ClientResult<Sbom> {
+        let mut builder = LockListBuilder::default();
+        builder.build_list(self, info).await?;
+
+        // Advisories are looked up in the operator log of each package's registry
+        let mut operators = IndexMap::new();
+        let package = self
+            .sbom_package(info, &VersionReq::STAR, &mut operators)
+            .await?;
+        let mut dependencies = Vec::with_capacity(builder.lock_list.len());
+        for import in builder.lock_list {
+            let name = PackageName::new(import.name)?;
+            let registry_domain = self.get_warg_registry(name.namespace()).await?;
+            let info = self
+                .registry
+                .load_package(registry_domain.as_ref(), &name)
+                .await?
+                .ok_or_else(|| ClientError::PackageDoesNotExist {
+                    name: name.clone(),
+                    has_auth_token: self.api.auth_token().is_some(),
+                })?;
+            dependencies.push(
+                self.sbom_package(&info, &import.req, &mut operators)
+                    .await?,
+            );
+        }
+
+        Ok(Sbom {
+            package,
+            dependencies,
+            created: SystemTime::now(),
+        })
+    }
+
+    async fn sbom_package(
+        &self,
+        info: &PackageInfo,
+        req: &VersionReq,
+        operators: &mut IndexMap<Option<RegistryDomain>, operator::LogState>,
+    ) -> ClientResult<SbomPackage> {
+        let release = info
+            .state
+            .stored_releases()
+            .filter(|r| req.matches(&r.version))
+            .last()
+            .ok_or_else(|| ClientError::PackageVersionRequirementDoesNotExist {
+                version: req.clone(),
+                name: info.name.clone(),
+            })?;
+
+        let registry_domain = self.get_warg_registry(info.name.namespace()).await?;
+        let operator = match operators.entry(registry_domain) {
+            indexmap::map::Entry::Occupied(entry) => entry.into_mut(),
+            indexmap::map::Entry::Vacant(entry) => {
+                let operator = self.update_operator(entry.key().as_ref()).await?;
+                entry.insert(operator.state)
+            }
+        };
+
+        Ok(SbomPackage {
+            name: info.name.clone(),
+            registry: info
+                .registry
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| self.url().to_string()),
+            advisories: operator
+                .advisories_for(&info.name, &release.version)
+                .cloned()
+                .collect(),
+            release: (&release).into(),
+        })
+    }
+
     /// Submits the publish information in client storage.
     ///
     /// If there's no publishing information in client storage, an error is returned.
@@ -323,6 +560,173 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
         signing_key: &signing::PrivateKey,
         publish_info: PublishInfo,
     ) -> ClientResult<RecordId> {
+        let (record, _) = self.submit_publish(signing_key, publish_info).await?;
+        Ok(record.record_id)
+    }
+
+    /// Submits the provided publish information and waits for the record to
+    /// be published, returning a receipt for the record signed by the
+    /// registry operator.
+    ///
+    /// The receipt is verified and stored in registry storage.
+    pub async fn publish_with_receipt(
+        &self,
+        signing_key: &signing::PrivateKey,
+        publish_info: PublishInfo,
+        interval: Duration,
+    ) -> ClientResult<SerdeEnvelope<RecordReceipt>> {
+        let name = publish_info.name.clone();
+        let record_id = self.publish_with_info(signing_key, publish_info).await?;
+        self.wait_for_publish(&name, &record_id, interval).await?;
+        self.record_receipt(&name, &record_id).await
+    }
+
+    /// Gets the receipt of a published package record from the registry.
+    ///
+    /// The receipt must be signed by a key of the registry operator and its
+    /// checkpoint must be consistent with the last checkpoint seen by the
+    /// client. The verified receipt is stored in registry storage.
+    pub async fn record_receipt(
+        &self,
+        package: &PackageName,
+        record_id: &RecordId,
+    ) -> ClientResult<SerdeEnvelope<RecordReceipt>> {
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+        let log_id = LogId::package_log::<Sha256>(package);
+        let receipt = self
+            .api
+            .get_package_record_receipt(registry_domain.as_ref(), &log_id, record_id)
+            .await?;
+
+        // The receipt may be signed by a key the local operator log doesn't know yet
+        let mut operator = self
+            .registry
+            .load_operator(registry_domain.as_ref())
+            .await?;
+        if operator
+            .as_ref()
+            .and_then(|o| o.state.public_key(receipt.key_id()))
+            .is_none()
+        {
+            operator = Some(self.update_operator(registry_domain.as_ref()).await?);
+        }
+
+        let key = operator
+            .as_ref()
+            .and_then(|o| o.state.public_key(receipt.key_id()))
+            .ok_or(ClientError::InvalidReceiptSignature)?;
+        RecordReceipt::verify(key, &receipt.as_ref().encode(), receipt.signature())
+            .or(Err(ClientError::InvalidReceiptSignature))?;
+
+        // The checkpoint of the receipt must be part of the same log as the
+        // last checkpoint seen
+        if let Some(seen) = self
+            .registry
+            .load_checkpoint(registry_domain.as_ref())
+            .await?
+        {
+            let seen = &seen.as_ref().checkpoint;
+            let checkpoint = &receipt.as_ref().checkpoint;
+            let (from, to) = if seen.log_length <= checkpoint.log_length {
+                (seen, checkpoint)
+            } else {
+                (checkpoint, seen)
+            };
+
+            if from.log_length == to.log_length {
+                if from != to {
+                    return Err(ClientError::CheckpointChangedLogRootOrMapRoot {
+                        log_length: from.log_length,
+                    });
+                }
+            } else {
+                self.api
+                    .prove_log_consistency(
+                        registry_domain.as_ref(),
+                        ConsistencyRequest {
+                            from: from.log_length,
+                            to: to.log_length,
+                        },
+                        Cow::Borrowed(&from.log_root),
+                        Cow::Borrowed(&to.log_root),
+                    )
+                    .await?;
+            }
+        }
+
+        self.registry
+            .store_receipt(registry_domain.as_ref(), &receipt)
+            .await?;
+        Ok(receipt)
+    }
+
+    /// Initializes a new package with a record signed by the given key.
+    ///
+    /// Registries may stage the initialization of packages in reserved
+    /// namespaces until the operator countersigns the init record. If
+    /// `operator_key` is provided, a staged init record is countersigned with
+    /// it; otherwise the returned package remains staged until the operator
+    /// approves it.
+    ///
+    /// Use `wait_for_publish` with the returned package's head to wait for
+    /// the init record to be published.
+    pub async fn init_package(
+        &self,
+        name: PackageName,
+        signing_key: &signing::PrivateKey,
+        operator_key: Option<&signing::PrivateKey>,
+    ) -> ClientResult<NewPackage> {
+        let (record, envelope) = self
+            .submit_publish(
+                signing_key,
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![PublishEntry::Init],
+                },
+            )
+            .await?;
+
+        let staged = match (&record.state, operator_key) {
+            (PackageRecordState::Staged, Some(operator_key)) => {
+                let countersignature =
+                    Countersignature::sign(operator_key, envelope.content_bytes())
+                        .map_err(|e| ClientError::Other(e.into()))?;
+                let registry_domain = self.get_warg_registry(name.namespace()).await?;
+                let record = self
+                    .api
+                    .countersign_package_record(
+                        registry_domain.as_ref(),
+                        &LogId::package_log::<Sha256>(&name),
+                        &record.record_id,
+                        &countersignature,
+                    )
+                    .await
+                    .map_err(|e| match e {
+                        api::ClientError::Package(PackageError::Unauthorized(reason)) => {
+                            ClientError::Unauthorized(reason)
+                        }
+                        e => e.into(),
+                    })?;
+                matches!(record.state, PackageRecordState::Staged)
+            }
+            (state, _) => matches!(state, PackageRecordState::Staged),
+        };
+
+        Ok(NewPackage {
+            name,
+            head: record.record_id,
+            staged,
+        })
+    }
+
+    /// Submits the provided publish information, returning the registry's
+    /// record and the envelope that was submitted.
+    async fn submit_publish(
+        &self,
+        signing_key: &signing::PrivateKey,
+        publish_info: PublishInfo,
+    ) -> ClientResult<(PackageRecord, ProtoEnvelope<package::PackageRecord>)> {
         if publish_info.entries.is_empty() {
             return Err(ClientError::NothingToPublish {
                 name: publish_info.name.clone(),
@@ -344,8 +748,13 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
 
         let mut init_record_id: Option<RecordId> = None;
 
-        let (package, record) = loop {
+        let mut rebase_head: Option<RecordId> = None;
+
+        let (package, record, envelope) = loop {
             let mut info = publish_info.clone();
+            if rebase_head.is_some() {
+                info.head = rebase_head.clone();
+            }
 
             let mut initializing = info.initializing();
 
@@ -361,6 +770,15 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
                         // set to the latest known head.
                         info.head = package.state.head().as_ref().map(|h| h.digest.clone());
                     }
+                    if let Some(head) = &rebase_head {
+                        if let Some(entry) = info.rebase_conflict(&package.state) {
+                            return Err(ClientError::RebaseConflict {
+                                name: package.name,
+                                head: head.clone(),
+                                entry: format!("{entry:?}"),
+                            });
+                        }
+                    }
                     package
                 }
                 Err(ClientError::PackageDoesNotExist {
@@ -377,7 +795,6 @@ impl<R: RegistryStorage, C: ContentStorage, N: NamespaceMapStorage> Client<R, C,
 
                         #[cfg(feature = "cli-interactive")]
                         {
-                            use crate::storage::PublishEntry;
                             use dialoguer::{theme::ColorfulTheme, Confirm};
 
                             if accepted_prompt_to_initialize
@@ -410,8 +827,9 @@ Attempt to create `{package_name}` and publish the release y/N\n",
             let registry_domain = self.get_warg_registry(package.name.namespace()).await?;
 
             let log_id = LogId::package_log::<Sha256>(&package.name);
-            let record = info.finalize(signing_key)?;
-            let record_id = RecordId::package_record::<Sha256>(&record);
+            let expected_head = info.head.clone();
+            let envelope = info.finalize(signing_key, self.publish_token.as_ref())?;
+            let record_id = RecordId::package_record::<Sha256>(&envelope);
             let record = match self
                 .api
                 .publish_package_record(
@@ -419,8 +837,10 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     &log_id,
                     PublishRecordRequest {
                         package_name: Cow::Borrowed(&package.name),
-                        record: Cow::Owned(record.into()),
+                        record: Cow::Owned(envelope.clone().into()),
                         content_sources: Default::default(),
+                        expected_head,
+                        copublication: None,
                     },
                 )
                 .await
@@ -436,6 +856,23 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                 Err(api::ClientError::Package(PackageError::Unauthorized(reason))) => {
                     Err(ClientError::Unauthorized(reason))
                 }
+                Err(api::ClientError::Package(PackageError::HeadMismatch {
+                    current_head: Some(current_head),
+                })) if self.auto_rebase
+                    && !initializing
+                    && rebase_head.as_ref() != Some(&current_head) =>
+                {
+                    tracing::info!("rebasing publish onto package log head `{current_head}`");
+                    rebase_head = Some(current_head);
+                    continue;
+                }
+                Err(api::ClientError::Package(PackageError::HeadMismatch { current_head })) => {
+                    Err(ClientError::HeadMismatch {
+                        name: package.name.clone(),
+                        record_id,
+                        current_head,
+                    })
+                }
                 Err(api::ClientError::Package(PackageError::ConflictPendingPublish(
                     pending_record_id,
                 ))) => {
@@ -468,7 +905,7 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                 }
                 Err(e) => Err(ClientError::translate_log_not_found(
                     e,
-                    self.api.auth_token().is_some(),
+                    self.api.has_auth(),
                     |id| {
                         if id == &log_id {
                             Some(package.name.clone())
@@ -479,9 +916,21 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                 )),
             }?;
 
-            break (package, record);
+            break (package, record, envelope);
         };
 
+        self.upload_missing_content(&package.name, &record).await?;
+
+        Ok((record, envelope))
+    }
+
+    /// Uploads the content a published record is missing, if the registry
+    /// supports uploading it.
+    async fn upload_missing_content(
+        &self,
+        name: &PackageName,
+        record: &PackageRecord,
+    ) -> ClientResult<()> {
         // TODO: parallelize this
         for (digest, MissingContent { upload }) in record.missing_content() {
             // Upload the missing content, if the registry supports it
@@ -499,17 +948,16 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     method,
                     url,
                     headers,
-                    Body::wrap_stream(self.content.load_content(digest).await?.ok_or_else(
-                        || ClientError::ContentNotFound {
-                            digest: digest.clone(),
-                        },
-                    )?),
+                    self.content
+                        .load_content(digest)
+                        .await?
+                        .ok_or_else(|| ClientError::ContentNotFound { digest: *digest })?,
                 )
                 .await
                 .map_err(|e| match e {
                     api::ClientError::Package(PackageError::Rejection(reason)) => {
                         ClientError::PublishRejected {
-                            name: package.name.clone(),
+                            name: name.clone(),
                             record_id: record.record_id.clone(),
                             reason,
                         }
@@ -521,7 +969,140 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                 })?;
         }
 
-        Ok(record.record_id)
+        Ok(())
+    }
+
+    /// Submits the provided publish information for several packages as a
+    /// co-publication signed by the given key.
+    ///
+    /// The registry holds the records until every record of the
+    /// co-publication has been submitted, then sequences them into the same
+    /// checkpoint. Records are built against the current head of each package
+    /// log and are not rebased.
+    ///
+    /// Use `check_copublication` to check that every record landed.
+    pub async fn publish_copublication(
+        &self,
+        signing_key: &signing::PrivateKey,
+        publish_infos: Vec<PublishInfo>,
+    ) -> ClientResult<SerdeEnvelope<CoPublication>> {
+        let mut records = Vec::with_capacity(publish_infos.len());
+        for mut info in publish_infos {
+            if info.entries.is_empty() {
+                return Err(ClientError::NothingToPublish { name: info.name });
+            }
+
+            if info.head.is_none() && !info.initializing() {
+                let package = self.fetch_package(&info.name).await?;
+                info.head = package.state.head().as_ref().map(|h| h.digest.clone());
+            }
+
+            let name = info.name.clone();
+            let expected_head = info.head.clone();
+            let envelope = info.finalize(signing_key, self.publish_token.as_ref())?;
+            records.push((name, expected_head, envelope));
+        }
+
+        let copublication = CoPublication::new(
+            signing_key.public_key(),
+            records.iter().map(|(name, _, envelope)| CoPublishedRecord {
+                package: name.clone(),
+                record_id: RecordId::package_record::<Sha256>(envelope),
+            }),
+            SystemTime::now(),
+        )?
+        .sign(signing_key)?;
+
+        for (name, expected_head, envelope) in records {
+            tracing::info!("publishing package `{name}` as part of a co-publication");
+            let registry_domain = self.get_warg_registry(name.namespace()).await?;
+            let record_id = RecordId::package_record::<Sha256>(&envelope);
+            let record = self
+                .api
+                .publish_package_record(
+                    registry_domain.as_ref(),
+                    &LogId::package_log::<Sha256>(&name),
+                    PublishRecordRequest {
+                        package_name: Cow::Borrowed(&name),
+                        record: Cow::Owned(envelope.into()),
+                        content_sources: Default::default(),
+                        expected_head,
+                        copublication: Some(copublication.clone()),
+                    },
+                )
+                .await
+                .map_err(|e| match e {
+                    api::ClientError::Package(PackageError::Rejection(reason)) => {
+                        ClientError::PublishRejected {
+                            name: name.clone(),
+                            reason,
+                            record_id,
+                        }
+                    }
+                    api::ClientError::Package(PackageError::Unauthorized(reason)) => {
+                        ClientError::Unauthorized(reason)
+                    }
+                    e => e.into(),
+                })?;
+
+            self.upload_missing_content(&name, &record).await?;
+        }
+
+        Ok(copublication)
+    }
+
+    /// Checks which records of a co-publication landed as of the latest
+    /// registry checkpoint.
+    ///
+    /// Returns an error if only some of the records landed, in which case
+    /// none of the records of the co-publication should be trusted.
+    pub async fn check_copublication(
+        &self,
+        copublication: &SerdeEnvelope<CoPublication>,
+    ) -> ClientResult<CoPublicationStatus> {
+        CoPublication::verify_signed(copublication)?;
+        let copublication = copublication.as_ref();
+        let packages = self
+            .fetch_packages(copublication.parts.iter().map(|part| &part.package))
+            .await?;
+        let Some(checkpoint) = packages
+            .iter()
+            .filter_map(|package| package.checkpoint.as_ref())
+            .min_by_key(|checkpoint| checkpoint.log_length)
+        else {
+            return Ok(CoPublicationStatus::Pending);
+        };
+
+        let mut indices = Vec::with_capacity(copublication.parts.len());
+        for part in &copublication.parts {
+            let registry_domain = self.get_warg_registry(part.package.namespace()).await?;
+            let log_id = LogId::package_log::<Sha256>(&part.package);
+            let index = match self
+                .get_package_record(
+                    registry_domain.as_ref(),
+                    &part.package,
+                    &log_id,
+                    &part.record_id,
+                )
+                .await
+            {
+                Ok(PackageRecord {
+                    state: PackageRecordState::Published { registry_index },
+                    ..
+                }) => Some(registry_index),
+                // Parts that were never published or were rejected have not landed
+                Ok(_)
+                | Err(ClientError::PublishRejected { .. })
+                | Err(ClientError::Api(api::ClientError::Package(PackageError::RecordNotFound(
+                    _,
+                )))) => None,
+                Err(e) => return Err(e),
+            };
+            indices.push(index);
+        }
+
+        let mut indices = indices.into_iter();
+        Ok(copublication.check(checkpoint, |_| indices.next().flatten())?)
     }
 
     /// Waits for a package record to transition to the `published` state.
@@ -550,14 +1131,25 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     self.fetch_package(package).await?;
                     return Ok(());
                 }
-                PackageRecordState::Rejected { reason } => {
+                PackageRecordState::Rejected {
+                    conflict: Some(conflict),
+                    ..
+                } => {
+                    return Err(ClientError::DuplicateRelease {
+                        name: package.clone(),
+                        version: conflict.version,
+                        released_by: conflict.record_id,
+                    });
+                }
+                PackageRecordState::Rejected { reason, .. } => {
                     return Err(ClientError::PublishRejected {
                         name: package.clone(),
                         record_id: record_id.clone(),
                         reason,
                     });
                 }
-                PackageRecordState::Processing => {
+                // Staged records are published once the operator countersigns them
+                PackageRecordState::Staged | PackageRecordState::Processing => {
                     tokio::time::sleep(interval).await;
                     current = self
                         .get_package_record(registry_domain.as_ref(), package, &log_id, record_id)
@@ -578,6 +1170,33 @@ Attempt to create `{package_name}` and publish the release y/N\n",
         Ok(())
     }
 
+    /// Updates the operator log of a registry to its latest checkpoint,
+    /// returning the validated operator state.
+    ///
+    /// Package updates refresh the operator log before fetching any package
+    /// log, so this is only needed to inspect the operator state itself.
+    pub async fn update_operator(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+    ) -> Result<OperatorInfo, ClientError> {
+        let ts_checkpoint = self.checkpoint(registry_domain).await?;
+        let operator = self
+            .bootstrap_operator(registry_domain, &ts_checkpoint)
+            .await?;
+
+        if let Some(window) = self.freshness_window {
+            self.verify_freshness(
+                registry_domain,
+                &operator.state,
+                &ts_checkpoint.as_ref().checkpoint,
+                window,
+            )
+            .await?;
+        }
+
+        Ok(operator)
+    }
+
     /// Downloads the latest version of a package into client storage that
     /// satisfies the given version requirement.
     ///
@@ -609,10 +1228,57 @@ Attempt to create `{package_name}` and publish the release y/N\n",
 
         match info.state.find_latest_release(requirement) {
             Some(release) => {
-                let digest = release
+                let digest = *release
+                    .content()
+                    .context("invalid state: not yanked but missing content")?;
+                let path = self
+                    .download_content(registry_domain.as_ref(), &digest)
+                    .await?;
+                Ok(Some(PackageDownload {
+                    version: release.version.clone(),
+                    digest,
+                    path,
+                    encryption: release.encryption.clone(),
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Downloads the version of a package that a channel tag, such as
+    /// `latest`, points at into client storage.
+    ///
+    /// The tag is resolved through the validated package log, so the
+    /// version is the one most recently tagged by a maintainer.
+    ///
+    /// If the requested package log is not present in client storage, it
+    /// will be fetched from the registry first.
+    ///
+    /// An error is returned if the package does not exist.
+    ///
+    /// If the tag has not been assigned or the version it points at has been
+    /// yanked, `None` is returned.
+    pub async fn download_tag(
+        &self,
+        package: &PackageName,
+        tag: &str,
+    ) -> Result<Option<PackageDownload>, ClientError> {
+        let info = self.package(package).await?;
+
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+
+        tracing::debug!(
+            package = package.as_ref(),
+            tag,
+            registry_header = ?registry_domain,
+            "downloading tagged version",
+        );
+
+        match info.state.resolve_tag(tag) {
+            Some(release) => {
+                let digest = *release
                     .content()
-                    .context("invalid state: not yanked but missing content")?
-                    .clone();
+                    .context("invalid state: not yanked but missing content")?;
                 let path = self
                     .download_content(registry_domain.as_ref(), &digest)
                     .await?;
@@ -620,6 +1286,7 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     version: release.version.clone(),
                     digest,
                     path,
+                    encryption: release.encryption.clone(),
                 }))
             }
             None => Ok(None),
@@ -653,10 +1320,9 @@ Attempt to create `{package_name}` and publish the release y/N\n",
 
         match info.state.find_latest_release(requirement) {
             Some(release) => {
-                let digest = release
+                let digest = *release
                     .content()
-                    .context("invalid state: not yanked but missing content")?
-                    .clone();
+                    .context("invalid state: not yanked but missing content")?;
                 let stream = self
                     .download_content_stream(registry_domain.as_ref(), &digest)
                     .await?;
@@ -664,6 +1330,7 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     PackageDownloadInfo {
                         version: release.version.clone(),
                         digest,
+                        encryption: release.encryption.clone(),
                     },
                     stream,
                 )))
@@ -714,10 +1381,11 @@ Attempt to create `{package_name}` and publish the release y/N\n",
 
         Ok(PackageDownload {
             version: version.clone(),
-            digest: digest.clone(),
+            digest: *digest,
             path: self
                 .download_content(registry_domain.as_ref(), digest)
                 .await?,
+            encryption: release.encryption.clone(),
         })
     }
 
@@ -761,20 +1429,155 @@ Attempt to create `{package_name}` and publish the release y/N\n",
         Ok((
             PackageDownloadInfo {
                 version: version.clone(),
-                digest: digest.clone(),
+                digest: *digest,
+                encryption: release.encryption.clone(),
             },
             self.download_content_stream(registry_domain.as_ref(), digest)
                 .await?,
         ))
     }
 
-    async fn update_packages_and_return_federated_packages<'a>(
+    /// Downloads a named artifact from the manifest of the specified version
+    /// of a package into client storage.
+    ///
+    /// If the requested package log is not present in client storage, it
+    /// will be fetched from the registry first.
+    ///
+    /// An error is returned if the package, version, or artifact does not
+    /// exist, or if the version has been yanked.
+    ///
+    /// Returns the path within client storage of the artifact contents.
+    pub async fn download_artifact(
         &self,
-        registry_domain: Option<&RegistryDomain>,
-        packages: impl IntoIterator<Item = &'a mut PackageInfo>,
-    ) -> Result<IndexMap<Option<RegistryDomain>, Vec<&'a mut PackageInfo>>, ClientError> {
-        let ts_checkpoint = self.api.latest_checkpoint(registry_domain).await?;
-        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        package: &PackageName,
+        version: &Version,
+        artifact: &str,
+    ) -> Result<PathBuf, ClientError> {
+        let info = self.package(package).await?;
+
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+
+        tracing::debug!(
+            package = package.as_ref(),
+            version = version.to_string(),
+            artifact,
+            registry_header = ?registry_domain,
+            "downloading release artifact",
+        );
+
+        let release = info
+            .state
+            .release(version)
+            .filter(|release| !release.yanked())
+            .ok_or_else(|| ClientError::PackageVersionDoesNotExist {
+                version: version.clone(),
+                name: package.clone(),
+            })?;
+
+        let digest = release
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.artifact(artifact))
+            .map(|artifact| &artifact.content)
+            .ok_or_else(|| ClientError::PackageArtifactDoesNotExist {
+                artifact: artifact.to_string(),
+                version: version.clone(),
+                name: package.clone(),
+            })?;
+
+        self.download_content(registry_domain.as_ref(), digest)
+            .await
+    }
+
+    /// Downloads the latest version of a package satisfying the given
+    /// requirement to the given destination path.
+    ///
+    /// The package log is first updated to the registry's latest checkpoint,
+    /// validating its records and their inclusion proofs. Yanked versions
+    /// are never selected.
+    ///
+    /// The content is hashed as it is written, and the destination is only
+    /// replaced once the content matches the release's digest.
+    ///
+    /// If a version satisfying the requirement does not exist, `None` is
+    /// returned.
+    pub async fn download_to(
+        &self,
+        package: &PackageName,
+        requirement: &VersionReq,
+        dest: impl Into<PathBuf>,
+    ) -> Result<Option<VerifiedArtifact>, ClientError> {
+        let dest = dest.into();
+        let mut info = self.package(package).await?;
+        self.update_checkpoints([&mut info]).await?;
+
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+
+        tracing::debug!(
+            package = package.as_ref(),
+            version_requirement = requirement.to_string(),
+            dest = %dest.display(),
+            registry_header = ?registry_domain,
+            "downloading to destination",
+        );
+
+        let Some(release) = info.state.find_latest_release(requirement) else {
+            return Ok(None);
+        };
+        let digest = *release
+            .content()
+            .context("invalid state: not yanked but missing content")?;
+
+        let dir = match dest.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => std::path::Path::new("."),
+        };
+        let (file, temp_path) = tempfile::NamedTempFile::new_in(dir)?.into_parts();
+        let mut writer = tokio::io::BufWriter::new(tokio::fs::File::from_std(file));
+        let mut hasher = digest.algorithm().hasher();
+        let mut size = 0;
+
+        let mut stream = self
+            .download_content_stream(registry_domain.as_ref(), &digest)
+            .await?;
+        while let Some(bytes) = stream.next().await.transpose()? {
+            hasher.update(&bytes);
+            size += bytes.len() as u64;
+            tokio::io::AsyncWriteExt::write_all(&mut writer, &bytes).await?;
+        }
+        tokio::io::AsyncWriteExt::shutdown(&mut writer).await?;
+        drop(writer);
+
+        let computed = hasher.finalize();
+        if computed != digest {
+            return Err(ClientError::IncorrectContent {
+                digest: computed,
+                expected: digest,
+            });
+        }
+
+        temp_path.persist(&dest).map_err(|e| e.error)?;
+
+        Ok(Some(VerifiedArtifact {
+            name: package.clone(),
+            version: release.version.clone(),
+            digest,
+            size,
+            path: dest,
+            record_id: release.record_id.clone(),
+            published_at: release.timestamp,
+            checkpoint: info.checkpoint.clone(),
+            encryption: release.encryption.clone(),
+        }))
+    }
+
+    async fn update_packages_and_return_federated_packages<'a>(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        packages: impl IntoIterator<Item = &'a mut PackageInfo>,
+    ) -> Result<IndexMap<Option<RegistryDomain>, Vec<&'a mut PackageInfo>>, ClientError> {
+        let ts_checkpoint = self.checkpoint(registry_domain).await?;
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
 
         tracing::debug!(
             log_length = checkpoint.log_length,
@@ -782,27 +1585,47 @@ Attempt to create `{package_name}` and publish the release y/N\n",
             "updating to checkpoint",
         );
 
-        // operator log info
+        // The operator log is validated before any package log, so that package
+        // records are checked against the operator state at the checkpoint
         let mut operator = self
-            .registry
-            .load_operator(registry_domain)
-            .await?
-            .unwrap_or_default();
+            .bootstrap_operator(registry_domain, &ts_checkpoint)
+            .await?;
 
         // map package names to package logs that need to be updated
         let mut packages = packages
             .into_iter()
-            .filter_map(|p| match &p.checkpoint {
-                // Don't bother updating if the package is already at the specified checkpoint
-                // If `registry` field is not set, then update.
-                Some(c) if p.registry.is_some() && c == checkpoint => None,
-                _ => Some((LogId::package_log::<Sha256>(&p.name), p)),
+            .filter_map(|p| {
+                // Start over if the state was stored by an older client and
+                // must be rebuilt from the log
+                if p.state.needs_replay() {
+                    *p = PackageInfo::new(p.name.clone());
+                }
+
+                match &p.checkpoint {
+                    // Don't bother updating if the package is already at the specified checkpoint
+                    // If `registry` field is not set, then update.
+                    Some(c) if p.registry.is_some() && c == checkpoint => None,
+                    _ => {
+                        // Start over if the package was updated past a pinned checkpoint
+                        if p.head_registry_index
+                            .is_some_and(|index| index >= checkpoint.log_length)
+                        {
+                            *p = PackageInfo::new(p.name.clone());
+                        }
+                        Some((LogId::package_log::<Sha256>(&p.name), p))
+                    }
+                }
             })
             .inspect(|(_, p)| tracing::info!("package `{name}` will be updated", name = p.name))
             .collect::<IndexMap<_, _>>();
 
-        // if operator log and all packages are up to date at the latest checkpoint, then return
-        if operator.checkpoint.is_some_and(|c| &c == checkpoint) && packages.is_empty() {
+        // if all packages are up to date at the latest checkpoint, then return
+        if packages.is_empty() {
+            if let Some(window) = self.freshness_window {
+                self.verify_freshness(registry_domain, &operator.state, checkpoint, window)
+                    .await?;
+            }
+
             return Ok(IndexMap::default());
         }
 
@@ -811,11 +1634,11 @@ Attempt to create `{package_name}` and publish the release y/N\n",
             IndexMap::with_capacity(packages.len());
 
         // loop and fetch logs
-        let has_auth_token = self.api.auth_token().is_some();
+        let has_auth_token = self.api.has_auth();
         loop {
-            let response = match self
+            let mut stream = match self
                 .api
-                .fetch_logs(
+                .fetch_logs_stream(
                     registry_domain,
                     FetchLogsRequest {
                         log_length: checkpoint.log_length,
@@ -834,12 +1657,8 @@ Attempt to create `{package_name}` and publish the release y/N\n",
                     },
                 )
                 .await
-                .inspect(|res| {
-                    for warning in res.warnings.iter() {
-                        tracing::warn!("Fetch warning from registry: {}", warning.message);
-                    }
-                }) {
-                Ok(res) => Ok(res),
+            {
+                Ok(stream) => Ok(stream),
                 Err(err) => match &err {
                     api::ClientError::Fetch(FetchError::LogNotFound(log_id))
                     | api::ClientError::Package(PackageError::LogNotFound(log_id)) => {
@@ -953,50 +1772,80 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
                 },
             }?;
 
-            for record in response.operator {
-                let proto_envelope: PublishedProtoEnvelope<operator::OperatorRecord> =
-                    record.envelope.try_into()?;
-
-                // skip over records that has already seen
-                if operator.head_registry_index.is_none()
-                    || proto_envelope.registry_index > operator.head_registry_index.unwrap()
-                {
-                    operator.state = operator
-                        .state
-                        .validate(&proto_envelope.envelope)
-                        .map_err(|inner| ClientError::OperatorValidationFailed { inner })?;
-                    operator.head_registry_index = Some(proto_envelope.registry_index);
-                    operator.head_fetch_token = Some(record.fetch_token);
-                }
-            }
-
-            for (log_id, records) in response.packages {
-                let package = packages.get_mut(&log_id).ok_or_else(|| {
-                    anyhow!("received records for unknown package log `{log_id}`")
-                })?;
+            // validate records as they are received
+            let mut updated = IndexSet::new();
+            let mut more = false;
+            while let Some(frame) = stream.next().await {
+                match frame? {
+                    FetchLogsFrame::Operator(record) => {
+                        let proto_envelope: PublishedProtoEnvelope<operator::OperatorRecord> =
+                            record.envelope.try_into()?;
+
+                        // skip over records that has already seen
+                        if operator.head_registry_index.is_none()
+                            || proto_envelope.registry_index > operator.head_registry_index.unwrap()
+                        {
+                            let state = std::mem::take(&mut operator.state);
+                            operator.state = operator::LogState::check_operator_record(
+                                &proto_envelope.envelope,
+                                proto_envelope.registry_index,
+                            )
+                            .and_then(|_| state.validate(&proto_envelope.envelope))
+                            .map_err(|inner| ClientError::OperatorValidationFailed { inner })?;
+                            operator.head_registry_index = Some(proto_envelope.registry_index);
+                            operator.head_fetch_token = Some(record.fetch_token);
+                        }
+                    }
+                    FetchLogsFrame::Package { log_id, record } => {
+                        let package = packages.get_mut(&log_id).ok_or_else(|| {
+                            anyhow!("received records for unknown package log `{log_id}`")
+                        })?;
 
-                for record in records {
-                    let proto_envelope: PublishedProtoEnvelope<package::PackageRecord> =
-                        record.envelope.try_into()?;
+                        let proto_envelope: PublishedProtoEnvelope<package::PackageRecord> =
+                            record.envelope.try_into()?;
 
-                    // skip over records that has already seen
-                    if package.head_registry_index.is_none()
-                        || proto_envelope.registry_index > package.head_registry_index.unwrap()
-                    {
-                        let state = std::mem::take(&mut package.state);
-                        package.state =
-                            state.validate(&proto_envelope.envelope).map_err(|inner| {
-                                ClientError::PackageValidationFailed {
+                        // skip over records that has already seen
+                        if package.head_registry_index.is_none()
+                            || proto_envelope.registry_index > package.head_registry_index.unwrap()
+                        {
+                            let state = std::mem::take(&mut package.state);
+                            package.state = operator
+                                .state
+                                .check_package_record(
+                                    &proto_envelope.envelope,
+                                    proto_envelope.registry_index,
+                                )
+                                .and_then(|_| {
+                                    state.validate_with_rules(
+                                        &proto_envelope.envelope,
+                                        &self.validation_rules,
+                                    )
+                                })
+                                .map_err(|inner| ClientError::PackageValidationFailed {
                                     name: package.name.clone(),
-                                    inner,
-                                }
-                            })?;
-                        package.head_registry_index = Some(proto_envelope.registry_index);
-                        package.head_fetch_token = Some(record.fetch_token);
+                                    inner: Box::new(inner),
+                                })?;
+                            package.head_registry_index = Some(proto_envelope.registry_index);
+                            package.head_fetch_token = Some(record.fetch_token);
+                        }
+
+                        updated.insert(log_id);
+                    }
+                    FetchLogsFrame::End {
+                        more: has_more,
+                        warnings,
+                    } => {
+                        for warning in warnings {
+                            tracing::warn!("Fetch warning from registry: {}", warning.message);
+                        }
+                        more = has_more;
                     }
                 }
+            }
 
-                // At this point, the package log should not be empty
+            // At this point, the updated package logs should not be empty
+            for log_id in updated {
+                let package = &packages[&log_id];
                 if package.state.head().is_none() {
                     return Err(ClientError::PackageLogEmpty {
                         name: package.name.clone(),
@@ -1004,40 +1853,22 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
                 }
             }
 
-            if !response.more {
+            if !more {
                 break;
             }
         }
 
-        // verify checkpoint signature
-        TimestampedCheckpoint::verify(
-            operator
-                .state
-                .public_key(ts_checkpoint.to_owned().to_owned().key_id())
-                .ok_or(ClientError::InvalidCheckpointKeyId {
-                    key_id: ts_checkpoint.key_id().clone(),
-                })?,
-            &ts_checkpoint.as_ref().encode(),
-            ts_checkpoint.signature(),
-        )
-        .or(Err(ClientError::InvalidCheckpointSignature))?;
-
-        // Prove inclusion for the current log heads
-        let mut leaf_indices = Vec::with_capacity(packages.len() + 1 /* for operator */);
-        let mut leafs = Vec::with_capacity(leaf_indices.len());
-
-        // operator record inclusion
-        if let Some(index) = operator.head_registry_index {
-            leaf_indices.push(index);
-            leafs.push(LogLeaf {
-                log_id: LogId::operator_log::<Sha256>(),
-                record_id: operator.state.head().as_ref().unwrap().digest.clone(),
-            });
-        } else {
-            return Err(ClientError::NoOperatorRecords);
+        if let Some(window) = self.freshness_window {
+            self.verify_freshness(registry_domain, &operator.state, checkpoint, window)
+                .await?;
         }
 
-        // package records inclusion
+        self.verify_migrations(&operator.state, packages.values().map(|p| &p.name))
+            .await?;
+
+        // Prove inclusion for the current package log heads
+        let mut leaf_indices = Vec::with_capacity(packages.len());
+        let mut leafs = Vec::with_capacity(leaf_indices.len());
         for (log_id, package) in &packages {
             if let Some(index) = package.head_registry_index {
                 leaf_indices.push(index);
@@ -1066,11 +1897,210 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
                 .await?;
         }
 
+        operator.registry = registry_domain
+            .cloned()
+            .or_else(|| Some(self.url().registry_domain()));
+        operator.checkpoint = Some(checkpoint.clone()); // updated to this checkpoint
+        self.registry
+            .store_operator(registry_domain, operator)
+            .await?;
+
+        for package in packages.values_mut() {
+            package.registry = registry_domain
+                .cloned()
+                .or_else(|| Some(self.url().registry_domain()));
+            package.checkpoint = Some(checkpoint.clone()); // updated to this checkpoint
+            self.registry
+                .store_package(registry_domain, package)
+                .await?;
+        }
+
+        // return packages to be retrieved from other registries
+        Ok(federated_packages)
+    }
+
+    /// Gets the checkpoint that fetches and proofs from a registry are
+    /// evaluated against.
+    ///
+    /// This is the pinned checkpoint of the registry, if any; otherwise it is
+    /// the registry's latest checkpoint.
+    async fn checkpoint(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, ClientError> {
+        let Some(pinned) = self.pinned_checkpoints.get(&registry_domain.cloned()) else {
+            return Ok(self.api.latest_checkpoint(registry_domain).await?);
+        };
+
+        let ts_checkpoint = self
+            .api
+            .checkpoint_at(registry_domain, pinned.log_length)
+            .await?;
+        if &ts_checkpoint.as_ref().checkpoint != pinned {
+            return Err(ClientError::PinnedCheckpointMismatch {
+                log_length: pinned.log_length,
+            });
+        }
+
+        Ok(ts_checkpoint)
+    }
+
+    /// Fetches and validates the operator log of a registry up to the given
+    /// checkpoint.
+    ///
+    /// The resulting operator state, including the namespaces, permissions
+    /// and checkpoint keys of the registry, is cached in registry storage. The
+    /// cached state is used as-is if it was validated at the checkpoint;
+    /// otherwise it is refreshed with the operator records sequenced since,
+    /// and the checkpoint is verified with the refreshed state.
+    async fn bootstrap_operator(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<OperatorInfo, ClientError> {
+        let checkpoint = &ts_checkpoint.as_ref().checkpoint;
+        let mut operator = self
+            .registry
+            .load_operator(registry_domain)
+            .await?
+            .unwrap_or_default();
+        if operator.checkpoint.as_ref() == Some(checkpoint) {
+            return Ok(operator);
+        }
+
+        // Start over if the operator log was updated past a pinned checkpoint
+        if operator
+            .head_registry_index
+            .is_some_and(|index| index >= checkpoint.log_length)
+        {
+            operator = OperatorInfo::default();
+        }
+
+        tracing::debug!(
+            log_length = checkpoint.log_length,
+            registry_header = ?registry_domain,
+            "refreshing operator log",
+        );
+
+        loop {
+            let mut stream = self
+                .api
+                .fetch_logs_stream(
+                    registry_domain,
+                    FetchLogsRequest {
+                        log_length: checkpoint.log_length,
+                        operator: operator
+                            .head_fetch_token
+                            .as_ref()
+                            .map(|t| Cow::Borrowed(t.as_str())),
+                        limit: None,
+                        packages: Cow::Owned(IndexMap::new()),
+                    },
+                )
+                .await?;
+
+            let mut more = false;
+            while let Some(frame) = stream.next().await {
+                match frame? {
+                    FetchLogsFrame::Operator(record) => {
+                        let proto_envelope: PublishedProtoEnvelope<operator::OperatorRecord> =
+                            record.envelope.try_into()?;
+
+                        // skip over records that has already seen
+                        if operator.head_registry_index.is_none()
+                            || proto_envelope.registry_index > operator.head_registry_index.unwrap()
+                        {
+                            let state = std::mem::take(&mut operator.state);
+                            operator.state = operator::LogState::check_operator_record(
+                                &proto_envelope.envelope,
+                                proto_envelope.registry_index,
+                            )
+                            .and_then(|_| state.validate(&proto_envelope.envelope))
+                            .map_err(|inner| ClientError::OperatorValidationFailed { inner })?;
+                            operator.head_registry_index = Some(proto_envelope.registry_index);
+                            operator.head_fetch_token = Some(record.fetch_token);
+                        }
+                    }
+                    FetchLogsFrame::Package { log_id, .. } => {
+                        return Err(
+                            anyhow!("received records for unknown package log `{log_id}`").into(),
+                        );
+                    }
+                    FetchLogsFrame::End {
+                        more: has_more,
+                        warnings,
+                    } => {
+                        for warning in warnings {
+                            tracing::warn!("Fetch warning from registry: {}", warning.message);
+                        }
+                        more = has_more;
+                    }
+                }
+            }
+
+            if !more {
+                break;
+            }
+        }
+
+        if let (None, Some(root)) = (registry_domain, &self.root_of_trust) {
+            self.verify_operator_key(root, ts_checkpoint.key_id())
+                .await?;
+        }
+
+        // verify checkpoint signature
+        TimestampedCheckpoint::verify(
+            operator.state.public_key(ts_checkpoint.key_id()).ok_or(
+                ClientError::InvalidCheckpointKeyId {
+                    key_id: ts_checkpoint.key_id().clone(),
+                },
+            )?,
+            &ts_checkpoint.as_ref().encode(),
+            ts_checkpoint.signature(),
+        )
+        .or(Err(ClientError::InvalidCheckpointSignature))?;
+
+        // Prove inclusion for the operator log head
+        let Some(index) = operator.head_registry_index else {
+            return Err(ClientError::NoOperatorRecords);
+        };
+        self.api
+            .prove_inclusion(
+                registry_domain,
+                InclusionRequest {
+                    log_length: checkpoint.log_length,
+                    leafs: vec![index],
+                },
+                checkpoint,
+                &[LogLeaf {
+                    log_id: LogId::operator_log::<Sha256>(),
+                    record_id: operator.state.head().as_ref().unwrap().digest.clone(),
+                }],
+            )
+            .await?;
+
+        let pinned = self
+            .pinned_checkpoints
+            .contains_key(&registry_domain.cloned());
         if let Some(from) = self.registry.load_checkpoint(registry_domain).await? {
             let from_log_length = from.as_ref().checkpoint.log_length;
             let to_log_length = ts_checkpoint.as_ref().checkpoint.log_length;
 
             match from_log_length.cmp(&to_log_length) {
+                // A pinned checkpoint must be a prefix of the latest checkpoint seen
+                Ordering::Greater if pinned => {
+                    self.api
+                        .prove_log_consistency(
+                            registry_domain,
+                            ConsistencyRequest {
+                                from: to_log_length,
+                                to: from_log_length,
+                            },
+                            Cow::Borrowed(&ts_checkpoint.as_ref().checkpoint.log_root),
+                            Cow::Borrowed(&from.as_ref().checkpoint.log_root),
+                        )
+                        .await?
+                }
                 Ordering::Greater => {
                     return Err(ClientError::CheckpointLogLengthRewind {
                         from: from_log_length,
@@ -1109,25 +2139,205 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
             .or_else(|| Some(self.url().registry_domain()));
         operator.checkpoint = Some(checkpoint.clone()); // updated to this checkpoint
         self.registry
-            .store_operator(registry_domain, operator)
+            .store_operator(registry_domain, operator.clone())
             .await?;
 
-        for package in packages.values_mut() {
-            package.registry = registry_domain
-                .cloned()
-                .or_else(|| Some(self.url().registry_domain()));
-            package.checkpoint = Some(checkpoint.clone()); // updated to this checkpoint
+        // The stored checkpoint guards against rewinds, so it is never moved
+        // back to a pinned checkpoint
+        if !pinned {
             self.registry
-                .store_package(registry_domain, package)
+                .store_checkpoint(registry_domain, ts_checkpoint)
                 .await?;
         }
 
-        self.registry
-            .store_checkpoint(registry_domain, &ts_checkpoint)
+        Ok(operator)
+    }
+
+    /// Verifies the migrations of the namespaces of the given packages were
+    /// signed by an operator key of their source registries.
+    ///
+    /// The operator log only proves that the key included in a migration
+    /// signed it, so the key is checked against a trusted key for the source
+    /// registry or against the source registry's operator log.
+    async fn verify_migrations(
+        &self,
+        operator: &operator::LogState,
+        names: impl IntoIterator<Item = &PackageName>,
+    ) -> Result<(), ClientError> {
+        let namespaces = names
+            .into_iter()
+            .map(PackageName::namespace)
+            .collect::<IndexSet<_>>();
+        for namespace in namespaces {
+            let Some(migration) = operator.namespace_migration(namespace) else {
+                continue;
+            };
+
+            let trusted = match self.migration_sources.get(&migration.registry) {
+                Some(key) => key.fingerprint() == migration.key_id,
+                None => {
+                    let domain = RegistryDomain::new(migration.registry.clone());
+                    let source = match self.registry.load_operator(Some(&domain)).await? {
+                        Some(source) => source,
+                        None => self.update_operator(Some(&domain)).await?,
+                    };
+                    migration.verify_source(&source.state).is_ok()
+                }
+            };
+
+            if !trusted {
+                return Err(ClientError::UntrustedMigration {
+                    namespace: namespace.to_string(),
+                    registry: migration.registry.clone(),
+                    key_id: migration.key_id.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the given operator key is listed in the home registry's
+    /// operator keys document signed by the given root of trust.
+    async fn verify_operator_key(
+        &self,
+        root: &signing::PublicKey,
+        key_id: &signing::KeyID,
+    ) -> Result<(), ClientError> {
+        let now = SystemTime::now();
+        let verify = |keys: &SerdeEnvelope<OperatorKeys>| {
+            if keys.key_id() != &root.fingerprint()
+                || OperatorKeys::verify(root, &keys.as_ref().encode(), keys.signature()).is_err()
+            {
+                return Err(ClientError::InvalidOperatorKeysSignature);
+            }
+
+            if keys.as_ref().expired(now) {
+                return Err(ClientError::OperatorKeysExpired);
+            }
+
+            Ok(keys.as_ref().key(key_id).is_some())
+        };
+
+        // Refresh the cached document if it is invalid, expired, or does not list the key
+        if let Some(keys) = self.registry.load_operator_keys(None).await? {
+            if let Ok(true) = verify(&keys) {
+                return Ok(());
+            }
+        }
+
+        let keys = self.api.operator_keys(None).await?;
+        let listed = verify(&keys)?;
+        self.registry.store_operator_keys(None, &keys).await?;
+        if !listed {
+            return Err(ClientError::UnlistedOperatorKey {
+                key_id: key_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the served checkpoint was asserted fresh by the operator within the window.
+    async fn verify_freshness(
+        &self,
+        registry_domain: Option<&RegistryDomain>,
+        operator: &operator::LogState,
+        checkpoint: &Checkpoint,
+        window: TimeWindow,
+    ) -> Result<(), ClientError> {
+        // Only the latest checkpoint is ever asserted fresh
+        if self
+            .pinned_checkpoints
+            .contains_key(&registry_domain.cloned())
+        {
+            return Ok(());
+        }
+
+        let freshness = self.api.latest_freshness(registry_domain).await?;
+        FreshnessAssertion::verify(
+            operator
+                .public_key(freshness.key_id())
+                .ok_or(ClientError::InvalidFreshnessSignature)?,
+            &freshness.as_ref().encode(),
+            freshness.signature(),
+        )
+        .or(Err(ClientError::InvalidFreshnessSignature))?;
+
+        let assertion = freshness.as_ref();
+        if &assertion.checkpoint != checkpoint {
+            return Err(ClientError::FreshnessCheckpointMismatch {
+                asserted: assertion.checkpoint.log_length,
+                served: checkpoint.log_length,
+            });
+        }
+
+        window.check_now(assertion.time()).map_err(|e| match e {
+            TimeWindowError::TooOld { age, max } => {
+                ClientError::StaleCheckpoint { age, window: max }
+            }
+            TimeWindowError::TooFarInFuture { skew, max } => {
+                ClientError::FutureFreshnessAssertion {
+                    skew,
+                    max_skew: max,
+                }
+            }
+        })
+    }
+
+    /// Checks the registry's package filter for the given package.
+    ///
+    /// Returns `false` if the package definitely does not exist as of the
+    /// filter's checkpoint, allowing a client to skip fetching its log. A
+    /// return of `true` may be a false positive.
+    pub async fn package_may_exist(&self, name: &PackageName) -> Result<bool, ClientError> {
+        Ok(self.package_filter(name).await?.may_contain_package(name))
+    }
+
+    /// Checks the registry's package filter for the given release of a package.
+    ///
+    /// Returns `false` if the release definitely does not exist as of the
+    /// filter's checkpoint. A return of `true` may be a false positive.
+    pub async fn release_may_exist(
+        &self,
+        name: &PackageName,
+        version: &Version,
+    ) -> Result<bool, ClientError> {
+        Ok(self
+            .package_filter(name)
+            .await?
+            .may_contain_release(name, version))
+    }
+
+    /// Fetches and verifies the package filter of the registry of the given package.
+    async fn package_filter(&self, name: &PackageName) -> Result<InclusionFilter, ClientError> {
+        let registry_domain = self.get_warg_registry(name.namespace()).await?;
+        let filter = self
+            .api
+            .latest_package_filter(registry_domain.as_ref())
             .await?;
 
-        // return packages to be retrieved from other registries
-        Ok(federated_packages)
+        // The filter may be signed by a key the local operator log doesn't know yet
+        let mut operator = self
+            .registry
+            .load_operator(registry_domain.as_ref())
+            .await?;
+        if operator
+            .as_ref()
+            .and_then(|o| o.state.public_key(filter.key_id()))
+            .is_none()
+        {
+            operator = Some(self.update_operator(registry_domain.as_ref()).await?);
+        }
+
+        let key = operator
+            .as_ref()
+            .and_then(|o| o.state.public_key(filter.key_id()))
+            .ok_or(ClientError::InvalidPackageFilterSignature)?;
+        PackageFilter::verify(key, &filter.as_ref().encode(), filter.signature())
+            .or(Err(ClientError::InvalidPackageFilterSignature))?;
+
+        Ok(filter.into_contents().filter)
     }
 
     /// Update checkpoint for list of packages
@@ -1164,6 +2374,153 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
         Ok(())
     }
 
+    /// Executes administrative commands on the home registry.
+    ///
+    /// The commands are recorded in an operator record signed with the
+    /// given key, which must have the admin permission in the operator log.
+    pub async fn admin(
+        &self,
+        signing_key: &signing::PrivateKey,
+        commands: impl IntoIterator<Item = operator::AdminCommand>,
+    ) -> Result<AdminCommandResponse, ClientError> {
+        self.submit_operator_entries(
+            signing_key,
+            commands.into_iter().map(operator::OperatorEntry::Admin),
+        )
+        .await
+    }
+
+    /// Publishes security advisories to the operator log of the home
+    /// registry.
+    ///
+    /// The advisories are recorded in an operator record signed with the
+    /// given key, which must have the admin permission in the operator log.
+    pub async fn publish_advisories(
+        &self,
+        signing_key: &signing::PrivateKey,
+        advisories: impl IntoIterator<Item = operator::Advisory>,
+    ) -> Result<AdminCommandResponse, ClientError> {
+        self.submit_operator_entries(
+            signing_key,
+            advisories
+                .into_iter()
+                .map(operator::OperatorEntry::Advisory),
+        )
+        .await
+    }
+
+    /// Gets the advisories published by the operator of a package's registry
+    /// that affect the given version of the package.
+    ///
+    /// The operator log is updated first, so that the advisories are as of
+    /// the registry's latest checkpoint.
+    pub async fn advisories_for(
+        &self,
+        package: &PackageName,
+        version: &Version,
+    ) -> Result<Vec<operator::Advisory>, ClientError> {
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+        let operator = self.update_operator(registry_domain.as_ref()).await?;
+        Ok(operator
+            .state
+            .advisories_for(package, version)
+            .cloned()
+            .collect())
+    }
+
+    /// Publishes identity claims to the key directory in the operator log of
+    /// the home registry.
+    ///
+    /// The claims are recorded in an operator record signed with the given
+    /// key, which must have the admin permission in the operator log; each
+    /// claim must be signed by the key it is for.
+    pub async fn publish_identity_claims(
+        &self,
+        signing_key: &signing::PrivateKey,
+        claims: impl IntoIterator<Item = operator::IdentityClaim>,
+    ) -> Result<AdminCommandResponse, ClientError> {
+        self.submit_operator_entries(
+            signing_key,
+            claims
+                .into_iter()
+                .map(operator::OperatorEntry::IdentityClaim),
+        )
+        .await
+    }
+
+    /// Gets the identities claimed for a key in the key directory of a
+    /// package's registry.
+    ///
+    /// The operator log is updated first, so that the identities are as of
+    /// the registry's latest checkpoint.
+    pub async fn identities_for(
+        &self,
+        package: &PackageName,
+        key_id: &signing::KeyID,
+    ) -> Result<Vec<String>, ClientError> {
+        let registry_domain = self.get_warg_registry(package.namespace()).await?;
+        let operator = self.update_operator(registry_domain.as_ref()).await?;
+        Ok(operator
+            .state
+            .identities(key_id)
+            .map(str::to_string)
+            .collect())
+    }
+
+    async fn submit_operator_entries(
+        &self,
+        signing_key: &signing::PrivateKey,
+        entries: impl IntoIterator<Item = operator::OperatorEntry>,
+    ) -> Result<AdminCommandResponse, ClientError> {
+        // The record must build on the latest head of the operator log
+        let head = self
+            .update_operator(None)
+            .await?
+            .state
+            .head()
+            .clone()
+            .ok_or_else(|| anyhow!("the operator log of the registry is empty"))?;
+
+        let record = operator::OperatorRecord {
+            prev: Some(head.digest),
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: head.timestamp.max(SystemTime::now()),
+            entries: entries.into_iter().collect(),
+        };
+        tracing::debug!("signing {record}");
+        let record = ProtoEnvelope::signed_contents(signing_key, record)
+            .map_err(|e| ClientError::Other(e.into()))?;
+
+        Ok(self
+            .api
+            .admin_command(AdminCommandRequest {
+                record: Cow::Owned(record.into()),
+            })
+            .await?)
+    }
+
+    /// Searches the home registry for packages matching the given query.
+    ///
+    /// Returns at most `limit` package names, or the registry's default
+    /// number of results if `limit` is `None`.
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: Option<u16>,
+    ) -> Result<Vec<PackageName>, ClientError> {
+        Ok(self
+            .api
+            .search(
+                None,
+                SearchQuery {
+                    q: Cow::Borrowed(query),
+                    limit,
+                },
+            )
+            .await?
+            .packages)
+    }
+
     /// Fetches package logs without checking local storage first.
     pub async fn fetch_packages(
         &self,
@@ -1210,6 +2567,37 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
         }
     }
 
+    /// Gets the releases of a package made by records signed by any key the
+    /// registry operator has declared compromised.
+    ///
+    /// Records signed by a key before it was declared compromised remain
+    /// valid, so their releases should be audited. A release is included if
+    /// a denied key released it, signed its record, or issued the publish
+    /// token it was released under.
+    pub async fn compromised_releases(
+        &self,
+        package: &PackageInfo,
+    ) -> Result<Vec<package::Release>, ClientError> {
+        let registry_domain = self.get_warg_registry(package.name.namespace()).await?;
+        let Some(operator) = self
+            .registry
+            .load_operator(registry_domain.as_ref())
+            .await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        Ok(package
+            .state
+            .stored_releases()
+            .filter(|release| {
+                release
+                    .signers()
+                    .any(|key_id| operator.state.key_denied_since(key_id).is_some())
+            })
+            .collect())
+    }
+
     async fn get_package_record(
         &self,
         registry_domain: Option<&RegistryDomain>,
@@ -1229,15 +2617,13 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
                         record_id: record_id.clone(),
                     }
                 }
-                e => {
-                    ClientError::translate_log_not_found(e, self.api.auth_token().is_some(), |id| {
-                        if id == log_id {
-                            Some(package.clone())
-                        } else {
-                            None
-                        }
-                    })
-                }
+                e => ClientError::translate_log_not_found(e, self.api.has_auth(), |id| {
+                    if id == log_id {
+                        Some(package.clone())
+                    } else {
+                        None
+                    }
+                }),
             })?;
         Ok(record)
     }
@@ -1266,9 +2652,7 @@ current_registry = registry_domain.map(|d| d.as_str()).unwrap_or(&self.url().saf
 
                 self.content
                     .content_location(digest)
-                    .ok_or_else(|| ClientError::ContentNotFound {
-                        digest: digest.clone(),
-                    })
+                    .ok_or_else(|| ClientError::ContentNotFound { digest: *digest })
             }
         }
     }
@@ -1332,8 +2716,10 @@ impl FileSystemClient {
         } = config.storage_paths_for_url(url)?;
 
         let (packages, content, namespace_map) = match (
-            FileSystemRegistryStorage::try_lock(registries_dir.clone())?,
-            FileSystemContentStorage::try_lock(content_dir.clone())?,
+            FileSystemRegistryStorage::try_lock(registries_dir.clone())?
+                .map(|s| s.with_layout(config.storage_layout)),
+            FileSystemContentStorage::try_lock(content_dir.clone())?
+                .map(|s| s.with_layout(config.storage_layout)),
             FileSystemNamespaceMapStorage::new(namespace_map_path.clone()),
         ) {
             (Some(packages), Some(content), namespace_map) => (packages, content, namespace_map),
@@ -1406,8 +2792,8 @@ impl FileSystemClient {
 
         Self::new(
             registry_url.into_url(),
-            FileSystemRegistryStorage::lock(registries_dir)?,
-            FileSystemContentStorage::lock(content_dir)?,
+            FileSystemRegistryStorage::lock(registries_dir)?.with_layout(config.storage_layout),
+            FileSystemContentStorage::lock(content_dir)?.with_layout(config.storage_layout),
             FileSystemNamespaceMapStorage::new(namespace_map_path),
             auth_token,
             config.ignore_federation_hints,
@@ -1439,6 +2825,94 @@ pub struct PackageDownload {
     pub digest: AnyHash,
     /// The path to the downloaded package contents.
     pub path: PathBuf,
+    /// How the package contents are encrypted, if they are encrypted.
+    pub encryption: Option<ContentEncryption>,
+}
+
+impl PackageDownload {
+    /// Decrypts the downloaded package contents with the given signing key.
+    ///
+    /// The contents were verified against the release's content digest when
+    /// downloaded, so only authentic ciphertext is decrypted.
+    ///
+    /// Returns an error if the contents are not encrypted or the key is not
+    /// a recipient of the contents.
+    pub async fn decrypt(&self, signing_key: &signing::PrivateKey) -> Result<Vec<u8>, ClientError> {
+        let encryption =
+            self.encryption
+                .as_ref()
+                .ok_or_else(|| ClientError::ContentNotEncrypted {
+                    digest: self.digest,
+                })?;
+
+        let ciphertext = tokio::fs::read(&self.path).await?;
+        encryption
+            .decrypt(&ciphertext, signing_key)
+            .map_err(|source| ClientError::ContentDecryptionFailed {
+                digest: self.digest,
+                source,
+            })
+    }
+}
+
+/// Represents package content downloaded with [`Client::download_to`] and
+/// verified against the package log.
+#[derive(Debug, Clone)]
+pub struct VerifiedArtifact {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The package version that was downloaded.
+    pub version: Version,
+    /// The digest of the package contents.
+    pub digest: AnyHash,
+    /// The size of the package contents, in bytes.
+    pub size: u64,
+    /// The path the package contents were written to.
+    pub path: PathBuf,
+    /// The id of the record that released the version.
+    pub record_id: RecordId,
+    /// The timestamp of the release.
+    pub published_at: SystemTime,
+    /// The checkpoint the package log was verified against.
+    pub checkpoint: Option<Checkpoint>,
+    /// How the package contents are encrypted, if they are encrypted.
+    pub encryption: Option<ContentEncryption>,
+}
+
+/// Represents a package initialized with [`Client::init_package`].
+#[derive(Debug, Clone)]
+pub struct NewPackage {
+    name: PackageName,
+    head: RecordId,
+    staged: bool,
+}
+
+impl NewPackage {
+    /// Gets the name of the package.
+    pub fn name(&self) -> &PackageName {
+        &self.name
+    }
+
+    /// Gets the identifier of the init record, the head of the package log.
+    pub fn head(&self) -> &RecordId {
+        &self.head
+    }
+
+    /// Determines if the init record is staged awaiting an operator
+    /// countersignature.
+    pub fn is_staged(&self) -> bool {
+        self.staged
+    }
+
+    /// Creates publish information for the given entries, to be published
+    /// onto the init record.
+    pub fn publish_info(&self, entries: Vec<PublishEntry>) -> PublishInfo {
+        PublishInfo {
+            name: self.name.clone(),
+            head: Some(self.head.clone()),
+            entries,
+        }
+    }
 }
 
 /// Represents information about a downloaded package.
@@ -1447,6 +2921,8 @@ pub struct PackageDownloadInfo {
     pub version: Version,
     /// The digest of the package contents.
     pub digest: AnyHash,
+    /// How the package contents are encrypted, if they are encrypted.
+    pub encryption: Option<ContentEncryption>,
 }
 
 /// Represents an error returned by Warg registry clients.
@@ -1479,10 +2955,77 @@ pub enum ClientError {
         key_id: signing::KeyID,
     },
 
+    /// Freshness assertion signature failed verification
+    #[error("invalid freshness assertion signature")]
+    InvalidFreshnessSignature,
+
+    /// Package filter signature failed verification
+    #[error("invalid package filter signature")]
+    InvalidPackageFilterSignature,
+
+    /// Record receipt signature failed verification
+    #[error("invalid record receipt signature")]
+    InvalidReceiptSignature,
+
+    /// The freshness assertion is for a different checkpoint than the one served.
+    #[error("the freshness assertion is for checkpoint with log length `{asserted}` but the registry served log length `{served}`")]
+    FreshnessCheckpointMismatch {
+        /// The log length of the checkpoint that was asserted fresh.
+        asserted: RegistryLen,
+        /// The log length of the checkpoint that was served.
+        served: RegistryLen,
+    },
+
+    /// The operator keys document was not signed by the root of trust.
+    #[error("the registry's operator keys document is not signed by the root of trust")]
+    InvalidOperatorKeysSignature,
+
+    /// The operator keys document has expired.
+    #[error("the registry's operator keys document has expired")]
+    OperatorKeysExpired,
+
+    /// The registry signed with a key not listed in its operator keys document.
+    #[error("operator key `{key_id}` is not listed in the registry's operator keys document")]
+    UnlistedOperatorKey {
+        /// The key ID of the unlisted key.
+        key_id: signing::KeyID,
+    },
+
+    /// The freshness assertion is older than the freshness window.
+    #[error("the registry checkpoint is stale; it was last asserted fresh {age:?} ago, exceeding the window of {window:?}")]
+    StaleCheckpoint {
+        /// The age of the freshness assertion.
+        age: Duration,
+        /// The freshness window of the client.
+        window: Duration,
+    },
+
+    /// The freshness assertion was made further in the future than the
+    /// allowed clock skew.
+    #[error("the registry checkpoint was asserted fresh {skew:?} in the future, exceeding the allowed clock skew of {max_skew:?}")]
+    FutureFreshnessAssertion {
+        /// How far in the future the assertion was made.
+        skew: Duration,
+        /// The allowed clock skew of the client.
+        max_skew: Duration,
+    },
+
     /// The server did not provide operator records.
     #[error("the server did not provide any operator records")]
     NoOperatorRecords,
 
+    /// A namespace was migrated with a key that is not an operator key of
+    /// its source registry.
+    #[error("namespace `{namespace}` was migrated from registry `{registry}` with key `{key_id}`, which is not an operator key of that registry")]
+    UntrustedMigration {
+        /// The migrated namespace.
+        namespace: String,
+        /// The registry the namespace was migrated from.
+        registry: String,
+        /// The key that signed the migration.
+        key_id: signing::KeyID,
+    },
+
     /// The operator failed validation.
     #[error("operator failed validation: {inner}")]
     OperatorValidationFailed {
@@ -1559,13 +3102,35 @@ pub enum ClientError {
         name: PackageName,
     },
 
+    /// The package version does not have the artifact.
+    #[error(
+        "version `{version}` of package `{name}` does not have an artifact named `{artifact}`"
+    )]
+    PackageArtifactDoesNotExist {
+        /// The name of the missing artifact.
+        artifact: String,
+        /// The version of the package without the artifact.
+        version: Version,
+        /// The package without the artifact.
+        name: PackageName,
+    },
+
+    /// The package channel tag does not resolve to a release.
+    #[error("tag `{tag}` of package `{name}` does not point at an available release")]
+    PackageTagDoesNotExist {
+        /// The unresolved tag of the package.
+        tag: String,
+        /// The package with the unresolved tag.
+        name: PackageName,
+    },
+
     /// The package failed validation.
     #[error("package `{name}` failed validation: {inner}")]
     PackageValidationFailed {
         /// The package that failed validation.
         name: PackageName,
         /// The validation error.
-        inner: package::ValidationError,
+        inner: Box<package::ValidationError>,
     },
 
     /// Content was not found during a publish operation.
@@ -1613,6 +3178,39 @@ pub enum ClientError {
         pending_record_id: RecordId,
     },
 
+    /// A publish operation was rejected because another record released the same version first.
+    #[error("the publishing of package `{name}` was rejected because version {version} was already released by record `{released_by}`")]
+    DuplicateRelease {
+        /// The package that was rejected.
+        name: PackageName,
+        /// The version that was released by both records.
+        version: Version,
+        /// The record identifier for the record that released the version.
+        released_by: RecordId,
+    },
+
+    /// A publish operation was rejected because the package log head moved.
+    #[error("the publishing of package `{name}` was rejected because the package log head has moved{}", current_head.as_ref().map(|h| format!(" to record `{h}`")).unwrap_or_default())]
+    HeadMismatch {
+        /// The package that was rejected.
+        name: PackageName,
+        /// The record identifier for the record that was rejected.
+        record_id: RecordId,
+        /// The current head of the package log.
+        current_head: Option<RecordId>,
+    },
+
+    /// A publish could not be rebased onto the current head of the package log.
+    #[error("the publishing of package `{name}` cannot be rebased onto record `{head}`: entry {entry} conflicts with the package log")]
+    RebaseConflict {
+        /// The package being published.
+        name: PackageName,
+        /// The head of the package log that the publish was being rebased onto.
+        head: RecordId,
+        /// The conflicting entry.
+        entry: String,
+    },
+
     /// The package is still missing content.
     #[error("the package is still missing content after all content was uploaded")]
     PackageMissingContent,
@@ -1635,6 +3233,34 @@ pub enum ClientError {
         log_length: RegistryLen,
     },
 
+    /// The registry served a checkpoint with a different `log_root` or
+    /// `map_root` than the pinned checkpoint.
+    #[error("registry provided a checkpoint at log length `{log_length}` with a different log root or map root than the pinned checkpoint")]
+    PinnedCheckpointMismatch {
+        /// The log length of the pinned checkpoint.
+        log_length: RegistryLen,
+    },
+
+    /// The package content is not encrypted.
+    #[error("content with digest `{digest}` is not encrypted")]
+    ContentNotEncrypted {
+        /// The digest of the content.
+        digest: AnyHash,
+    },
+
+    /// The package content could not be decrypted.
+    #[error("failed to decrypt content with digest `{digest}`: {source}")]
+    ContentDecryptionFailed {
+        /// The digest of the content.
+        digest: AnyHash,
+        /// The decryption error.
+        source: EncryptionError,
+    },
+
+    /// A co-publication is invalid or only some of its records landed.
+    #[error("invalid co-publication: {0}")]
+    CoPublication(#[from] CoPublicationError),
+
     /// An error occurred during an API operation.
     #[error(transparent)]
     Api(#[from] api::ClientError),
@@ -0,0 +1,183 @@
+//! Tools for monitoring registries.
+//!
+//! [`compare`] compares two registry states exported to static sites (see
+//! [`warg_api::v1::static_site`]) that claim the same checkpoint, for
+//! investigating suspected split views of a registry.
+
+use crate::static_site::{StaticSiteClient, StaticSiteError};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use warg_api::v1::static_site::{self, StaticIndex, StaticLog};
+use warg_crypto::hash::Sha256;
+use warg_protocol::{
+    operator, package,
+    registry::{Checkpoint, LogId, LogLeaf, RecordId, RegistryIndex, TimestampedCheckpoint},
+    PublishedProtoEnvelope, SerdeEnvelope,
+};
+
+/// Represents the first record at which two registry logs diverge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordDivergence {
+    /// The registry index of the divergent record.
+    pub registry_index: RegistryIndex,
+    /// The leaf at the index in the first registry state, if any.
+    pub a: Option<LogLeaf>,
+    /// The leaf at the index in the second registry state, if any.
+    pub b: Option<LogLeaf>,
+}
+
+/// Represents the first entry at which two registry maps diverge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapEntryDivergence {
+    /// The log identifier of the divergent map entry.
+    pub log_id: LogId,
+    /// The head of the log in the first registry state, if any.
+    pub a: Option<RecordId>,
+    /// The head of the log in the second registry state, if any.
+    pub b: Option<RecordId>,
+}
+
+/// A report of the differences between two registry states.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergenceReport {
+    /// The checkpoint of the first registry state.
+    pub a: Checkpoint,
+    /// The checkpoint of the second registry state.
+    pub b: Checkpoint,
+    /// The first record at which the registry logs diverge, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<RecordDivergence>,
+    /// The first entry (in log identifier order) at which the registry maps diverge, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_entry: Option<MapEntryDivergence>,
+}
+
+impl DivergenceReport {
+    /// Determines if the registry states are identical.
+    pub fn is_consistent(&self) -> bool {
+        self.a == self.b && self.record.is_none() && self.map_entry.is_none()
+    }
+}
+
+/// The log leafs and map of an exported registry state.
+struct ExportedState {
+    checkpoint: Checkpoint,
+    leafs: Vec<(RegistryIndex, LogLeaf)>,
+    heads: IndexMap<LogId, RecordId>,
+}
+
+impl ExportedState {
+    async fn fetch(site: &StaticSiteClient) -> Result<Self, StaticSiteError> {
+        let checkpoint: SerdeEnvelope<TimestampedCheckpoint> =
+            site.get_json(static_site::checkpoint()).await?;
+        let index: StaticIndex = site.get_json(static_site::index()).await?;
+
+        let mut leafs = Vec::new();
+        let mut heads = IndexMap::new();
+
+        let log_id = LogId::operator_log::<Sha256>();
+        let log: StaticLog = site.get_json(static_site::operator_log()).await?;
+        for record in log.records {
+            let record: PublishedProtoEnvelope<operator::OperatorRecord> =
+                record.try_into().map_err(StaticSiteError::Record)?;
+            let record_id = RecordId::operator_record::<Sha256>(&record.envelope);
+            heads.insert(log_id.clone(), record_id.clone());
+            leafs.push((
+                record.registry_index,
+                LogLeaf {
+                    log_id: log_id.clone(),
+                    record_id,
+                },
+            ));
+        }
+
+        for log_id in index.packages.values() {
+            let log: StaticLog = site.get_json(&static_site::package_log(log_id)).await?;
+            for record in log.records {
+                let record: PublishedProtoEnvelope<package::PackageRecord> =
+                    record.try_into().map_err(StaticSiteError::Record)?;
+                let record_id = RecordId::package_record::<Sha256>(&record.envelope);
+                heads.insert(log_id.clone(), record_id.clone());
+                leafs.push((
+                    record.registry_index,
+                    LogLeaf {
+                        log_id: log_id.clone(),
+                        record_id,
+                    },
+                ));
+            }
+        }
+
+        leafs.sort_by_key(|(index, _)| *index);
+
+        Ok(Self {
+            checkpoint: checkpoint.as_ref().checkpoint.clone(),
+            leafs,
+            heads,
+        })
+    }
+
+    fn leaf(&self, index: RegistryIndex) -> Option<&LogLeaf> {
+        self.leafs
+            .binary_search_by_key(&index, |(i, _)| *i)
+            .ok()
+            .map(|i| &self.leafs[i].1)
+    }
+}
+
+/// Compares two registry states exported to static sites.
+///
+/// The records of every log in each state are merged into registry order and
+/// compared to find the first divergent record; the heads of every log are
+/// compared to find the first divergent map entry.
+///
+/// The states are not validated, so that states with invalid logs or
+/// checkpoint signatures may still be compared; use
+/// [`StaticSiteClient::snapshot`] to verify a state.
+pub async fn compare(
+    a: &StaticSiteClient,
+    b: &StaticSiteClient,
+) -> Result<DivergenceReport, StaticSiteError> {
+    let a = ExportedState::fetch(a).await?;
+    let b = ExportedState::fetch(b).await?;
+
+    let indexes = a
+        .leafs
+        .iter()
+        .chain(&b.leafs)
+        .map(|(index, _)| *index)
+        .collect::<BTreeSet<_>>();
+    let record = indexes.into_iter().find_map(|index| {
+        let (leaf_a, leaf_b) = (a.leaf(index), b.leaf(index));
+        (leaf_a != leaf_b).then(|| RecordDivergence {
+            registry_index: index,
+            a: leaf_a.cloned(),
+            b: leaf_b.cloned(),
+        })
+    });
+
+    let log_ids = a
+        .heads
+        .keys()
+        .chain(b.heads.keys())
+        .collect::<BTreeSet<_>>();
+    let map_entry = log_ids.into_iter().find_map(|log_id| {
+        let (head_a, head_b) = (a.heads.get(log_id), b.heads.get(log_id));
+        (head_a != head_b).then(|| MapEntryDivergence {
+            log_id: log_id.clone(),
+            a: head_a.cloned(),
+            b: head_b.cloned(),
+        })
+    });
+
+    Ok(DivergenceReport {
+        a: a.checkpoint,
+        b: b.checkpoint,
+        record,
+        map_entry,
+    })
+}
@@ -99,6 +99,11 @@ impl RegistryUrl {
         self.0
     }
 
+    /// Determines if the given URL has the same origin as the registry.
+    pub(crate) fn same_origin(&self, url: &Url) -> bool {
+        self.0.origin() == url.origin()
+    }
+
     pub(crate) fn join(&self, path: &str) -> String {
         // Url::join can only fail if the base is relative or if the result is
         // very large (>4GB), neither of which should be possible in this lib.
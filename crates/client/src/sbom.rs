@@ -0,0 +1,431 @@
+//! Software bills of materials for resolved packages.
+//!
+//! An [`Sbom`] describes a package and the set of dependencies resolved for
+//! it by [`Client::sbom`](crate::Client::sbom). It can be rendered as an
+//! SPDX 2.3 or CycloneDX 1.5 JSON document for compliance pipelines.
+
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use warg_crypto::hash::{civil_date, AnyHash, HashAlgorithm};
+use warg_protocol::{operator::Advisory, package::ReleaseInfo, registry::PackageName};
+
+/// The name of the tool recorded as the creator of rendered documents.
+const TOOL_NAME: &str = "warg-client";
+
+/// The document formats an [`Sbom`] can be rendered to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// SPDX 2.3 JSON.
+    #[default]
+    Spdx,
+    /// CycloneDX 1.5 JSON.
+    CycloneDx,
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spdx => write!(f, "spdx"),
+            Self::CycloneDx => write!(f, "cyclonedx"),
+        }
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spdx" => Ok(Self::Spdx),
+            "cyclonedx" => Ok(Self::CycloneDx),
+            _ => Err(format!(
+                "unknown SBOM format `{s}`: expected `spdx` or `cyclonedx`"
+            )),
+        }
+    }
+}
+
+/// A resolved package release described by an [`Sbom`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomPackage {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The identity of the registry the package was resolved from.
+    ///
+    /// This is the registry domain, or the registry URL if the package was
+    /// resolved from the home registry.
+    pub registry: String,
+    /// The resolved release, including its yank status.
+    pub release: ReleaseInfo,
+    /// The advisories published by the registry operator that affect the
+    /// resolved release.
+    pub advisories: Vec<Advisory>,
+}
+
+impl SbomPackage {
+    /// Gets the package URL of the release.
+    ///
+    /// There is no registered package URL type for Warg registries, so the
+    /// generic type is used with the registry as the repository.
+    pub fn purl(&self) -> String {
+        format!(
+            "pkg:generic/{namespace}/{name}@{version}?repository_url={registry}",
+            namespace = self.name.namespace(),
+            name = self.name.name(),
+            version = self.release.version.to_string().replace('+', "%2B"),
+            registry = self.registry,
+        )
+    }
+
+    /// Describes the yank of the release, if it has been yanked.
+    fn yank_comment(&self) -> Option<String> {
+        self.release.yank.as_ref().map(|yank| match &yank.reason {
+            Some(reason) => format!("yanked: {reason}"),
+            None => "yanked".to_string(),
+        })
+    }
+}
+
+/// A bill of materials for a package and its resolved dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbom {
+    /// The package the bill of materials is for.
+    pub package: SbomPackage,
+    /// The dependencies resolved for the package, in resolution order.
+    pub dependencies: Vec<SbomPackage>,
+    /// The time the bill of materials was created.
+    pub created: SystemTime,
+}
+
+impl Sbom {
+    /// Renders the bill of materials in the given format.
+    pub fn render(&self, format: SbomFormat) -> Value {
+        match format {
+            SbomFormat::Spdx => self.to_spdx(),
+            SbomFormat::CycloneDx => self.to_cyclonedx(),
+        }
+    }
+
+    /// Renders the bill of materials as an SPDX 2.3 JSON document.
+    ///
+    /// Advisories are recorded as security references of the affected
+    /// package and yanked releases are noted in the comment of their package.
+    pub fn to_spdx(&self) -> Value {
+        let id = |i: usize| format!("SPDXRef-Package-{i}");
+        let packages = self
+            .packages()
+            .enumerate()
+            .map(|(i, package)| {
+                let mut value = json!({
+                    "SPDXID": id(i),
+                    "name": package.name.to_string(),
+                    "versionInfo": package.release.version.to_string(),
+                    "downloadLocation": "NOASSERTION",
+                    "filesAnalyzed": false,
+                    "checksums": package
+                        .release
+                        .content
+                        .iter()
+                        .filter_map(|digest| Some(json!({
+                            "algorithm": spdx_algorithm(digest)?,
+                            "checksumValue": hex::encode(digest.bytes()),
+                        })))
+                        .collect::<Vec<_>>(),
+                    "externalRefs": std::iter::once(json!({
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": package.purl(),
+                    }))
+                    .chain(package.advisories.iter().map(|advisory| json!({
+                        "referenceCategory": "SECURITY",
+                        "referenceType": "advisory",
+                        "referenceLocator": advisory.id,
+                        "comment": advisory.summary,
+                    })))
+                    .collect::<Vec<_>>(),
+                });
+                if let Some(comment) = package.yank_comment() {
+                    value["comment"] = comment.into();
+                }
+                value
+            })
+            .collect::<Vec<_>>();
+
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": id(0),
+        })];
+        relationships.extend((1..packages.len()).map(|i| {
+            json!({
+                "spdxElementId": id(0),
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": id(i),
+            })
+        }));
+
+        let root = &self.package;
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": format!("{name}@{version}", name = root.name, version = root.release.version),
+            "documentNamespace": format!(
+                "urn:warg:sbom:{registry}:{name}@{version}",
+                registry = root.registry,
+                name = root.name,
+                version = root.release.version,
+            ),
+            "creationInfo": {
+                "created": rfc3339(self.created),
+                "creators": [format!("Tool: {TOOL_NAME}-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    /// Renders the bill of materials as a CycloneDX 1.5 JSON document.
+    ///
+    /// Registry identity and yank status are recorded as `warg:` properties
+    /// of each component and advisories as vulnerabilities.
+    pub fn to_cyclonedx(&self) -> Value {
+        let component = |package: &SbomPackage| {
+            let mut properties = vec![json!({
+                "name": "warg:registry",
+                "value": package.registry,
+            })];
+            if let Some(yank) = &package.release.yank {
+                properties.push(json!({ "name": "warg:yanked", "value": "true" }));
+                if let Some(reason) = &yank.reason {
+                    properties.push(json!({ "name": "warg:yank-reason", "value": reason }));
+                }
+            }
+
+            json!({
+                "type": "library",
+                "bom-ref": package.purl(),
+                "group": package.name.namespace(),
+                "name": package.name.name(),
+                "version": package.release.version.to_string(),
+                "purl": package.purl(),
+                "hashes": package
+                    .release
+                    .content
+                    .iter()
+                    .filter_map(|digest| Some(json!({
+                        "alg": cyclonedx_algorithm(digest)?,
+                        "content": hex::encode(digest.bytes()),
+                    })))
+                    .collect::<Vec<_>>(),
+                "properties": properties,
+            })
+        };
+
+        let mut dependencies = vec![json!({
+            "ref": self.package.purl(),
+            "dependsOn": self.dependencies.iter().map(SbomPackage::purl).collect::<Vec<_>>(),
+        })];
+        dependencies.extend(
+            self.dependencies
+                .iter()
+                .map(|package| json!({ "ref": package.purl() })),
+        );
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": rfc3339(self.created),
+                "tools": {
+                    "components": [{
+                        "type": "application",
+                        "name": TOOL_NAME,
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }],
+                },
+                "component": component(&self.package),
+            },
+            "components": self.dependencies.iter().map(component).collect::<Vec<_>>(),
+            "dependencies": dependencies,
+            "vulnerabilities": self.vulnerabilities(),
+        })
+    }
+
+    /// Gets the CycloneDX vulnerabilities of the advisories affecting any
+    /// package, merging the packages affected by the same advisory.
+    fn vulnerabilities(&self) -> Vec<Value> {
+        let mut vulnerabilities = IndexMap::<&str, (&Advisory, Vec<Value>)>::new();
+        for package in self.packages() {
+            for advisory in &package.advisories {
+                vulnerabilities
+                    .entry(&advisory.id)
+                    .or_insert_with(|| (advisory, Vec::new()))
+                    .1
+                    .push(json!({ "ref": package.purl() }));
+            }
+        }
+
+        vulnerabilities
+            .into_values()
+            .map(|(advisory, affects)| {
+                json!({
+                    "id": advisory.id,
+                    "description": advisory.summary,
+                    "affects": affects,
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the package followed by its dependencies.
+    fn packages(&self) -> impl Iterator<Item = &SbomPackage> {
+        std::iter::once(&self.package).chain(&self.dependencies)
+    }
+}
+
+/// Gets the SPDX name of the algorithm of a digest, if SPDX has one.
+fn spdx_algorithm(digest: &AnyHash) -> Option<&'static str> {
+    match digest.algorithm() {
+        HashAlgorithm::Sha256 => Some("SHA256"),
+        _ => None,
+    }
+}
+
+/// Gets the CycloneDX name of the algorithm of a digest, if CycloneDX has one.
+fn cyclonedx_algorithm(digest: &AnyHash) -> Option<&'static str> {
+    match digest.algorithm() {
+        HashAlgorithm::Sha256 => Some("SHA-256"),
+        _ => None,
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with second precision.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_date(time);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z",
+        hour = secs / 3600 % 24,
+        minute = secs / 60 % 60,
+        second = secs % 60,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use warg_crypto::hash::{Hash, Sha256};
+    use warg_protocol::package::YankInfo;
+
+    fn package(name: &str, version: &str, yank: Option<&str>) -> SbomPackage {
+        let package_name = PackageName::new(name).unwrap();
+        let content: AnyHash = Hash::<Sha256>::of(name).into();
+        SbomPackage {
+            advisories: yank
+                .map(|id| Advisory {
+                    id: id.to_string(),
+                    package: package_name.clone(),
+                    affected: "*".parse().unwrap(),
+                    summary: "a vulnerability".to_string(),
+                })
+                .into_iter()
+                .collect(),
+            name: package_name,
+            registry: "registry.example.com".to_string(),
+            release: ReleaseInfo {
+                version: version.parse().unwrap(),
+                content: Some(content),
+                record_id: content.into(),
+                released_by: "sha256:abc".to_string().into(),
+                timestamp: UNIX_EPOCH,
+                yank: yank.map(|reason| YankInfo {
+                    by: "sha256:abc".to_string().into(),
+                    timestamp: UNIX_EPOCH,
+                    reason: Some(reason.to_string()),
+                }),
+                manifest: None,
+            },
+        }
+    }
+
+    fn sbom() -> Sbom {
+        Sbom {
+            package: package("test:app", "1.0.0", None),
+            dependencies: vec![
+                package("test:lib", "2.1.0+build.1", None),
+                package("test:old", "0.1.0", Some("ADV-1")),
+            ],
+            // 2024-02-29T12:34:56Z
+            created: UNIX_EPOCH + Duration::from_secs(1709210096),
+        }
+    }
+
+    #[test]
+    fn it_renders_spdx() {
+        let sbom = sbom();
+        let doc = sbom.render(SbomFormat::Spdx);
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["creationInfo"]["created"], "2024-02-29T12:34:56Z");
+        assert_eq!(doc["packages"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            doc["packages"][1]["externalRefs"][0]["referenceLocator"],
+            "pkg:generic/test/lib@2.1.0%2Bbuild.1?repository_url=registry.example.com"
+        );
+        assert_eq!(
+            doc["packages"][0]["checksums"][0]["checksumValue"],
+            hex::encode(sbom.package.release.content.unwrap().bytes())
+        );
+        assert_eq!(doc["packages"][2]["comment"], "yanked: ADV-1");
+        assert_eq!(
+            doc["packages"][2]["externalRefs"][1]["referenceLocator"],
+            "ADV-1"
+        );
+        assert_eq!(doc["relationships"].as_array().unwrap().len(), 3);
+        assert_eq!(
+            doc["relationships"][2]["relatedSpdxElement"],
+            "SPDXRef-Package-2"
+        );
+    }
+
+    #[test]
+    fn it_renders_cyclonedx() {
+        let doc = sbom().render(SbomFormat::CycloneDx);
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["metadata"]["component"]["name"], "app");
+        assert_eq!(doc["components"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["components"][1]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(
+            doc["components"][1]["properties"],
+            json!([
+                { "name": "warg:registry", "value": "registry.example.com" },
+                { "name": "warg:yanked", "value": "true" },
+                { "name": "warg:yank-reason", "value": "ADV-1" },
+            ])
+        );
+        assert_eq!(
+            doc["dependencies"][0]["dependsOn"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn it_parses_formats() {
+        assert_eq!("spdx".parse(), Ok(SbomFormat::Spdx));
+        assert_eq!("cyclonedx".parse(), Ok(SbomFormat::CycloneDx));
+        assert!("swid".parse::<SbomFormat>().is_err());
+    }
+}
@@ -0,0 +1,526 @@
+//! Transport-agnostic sources of registry data.
+//!
+//! A [`RegistrySource`] fetches the raw data of a registry: its latest
+//! checkpoint, the records of its logs, inclusion proofs, and content. The
+//! functions of this module verify and resolve packages from any source, so
+//! the same verification applies to a registry served over HTTP
+//! ([`api::Client`]), held in memory ([`MockRegistry`](crate::testing::MockRegistry)),
+//! or exported to the file system ([`StaticSiteClient`]).
+//!
+//! Sources are not trusted: every checkpoint, record, proof, and content
+//! fetched from a source is verified before it is used.
+
+use crate::{
+    api,
+    static_site::{StaticSiteClient, StaticSiteError},
+};
+use bytes::Bytes;
+use futures_util::{StreamExt, TryStreamExt};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use thiserror::Error;
+use warg_api::v1::{
+    fetch::{FetchLogsFrame, FetchLogsRequest},
+    proof::{InclusionRequest, InclusionResponse},
+    static_site::{self, StaticLog},
+};
+use warg_crypto::{
+    hash::{AnyHash, Sha256},
+    signing::KeyID,
+    Encode, Signable,
+};
+use warg_protocol::{
+    operator, package,
+    registry::{
+        Checkpoint, LogId, LogLeaf, PackageName, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    PublishedProtoEnvelope, PublishedProtoEnvelopeBody, SerdeEnvelope, Version, VersionReq,
+};
+
+/// Represents an error from a registry source or from verifying its data.
+#[derive(Debug, Error)]
+pub enum SourceError {
+    /// An error occurred fetching from a registry API.
+    #[error(transparent)]
+    Api(#[from] api::ClientError),
+    /// An error occurred fetching from a static site.
+    #[error(transparent)]
+    StaticSite(#[from] StaticSiteError),
+    /// The log is not in the source.
+    #[error("log `{0}` was not found")]
+    LogNotFound(LogId),
+    /// The content is not in the source.
+    #[error("content `{0}` was not found")]
+    ContentNotFound(AnyHash),
+    /// A record could not be decoded.
+    #[error("failed to decode record: {0}")]
+    Record(anyhow::Error),
+    /// A record is beyond the checkpoint.
+    #[error("record at registry index {index} is beyond the checkpoint log length {log_length}")]
+    BeyondCheckpoint {
+        /// The registry index of the record.
+        index: RegistryIndex,
+        /// The log length of the checkpoint.
+        log_length: RegistryLen,
+    },
+    /// A record is out of registry order.
+    #[error("record at registry index {0} is out of registry order")]
+    OutOfOrder(RegistryIndex),
+    /// The checkpoint was signed by an unknown key.
+    #[error("checkpoint signed by unknown key `{0}`")]
+    InvalidCheckpointKeyId(KeyID),
+    /// The checkpoint signature failed verification.
+    #[error("invalid checkpoint signature")]
+    InvalidCheckpointSignature,
+    /// The operator log is empty.
+    #[error("the source does not contain any operator records")]
+    NoOperatorRecords,
+    /// The operator log failed validation.
+    #[error("operator failed validation: {0}")]
+    OperatorValidationFailed(operator::ValidationError),
+    /// The package log is empty.
+    #[error("package `{0}` does not contain any records")]
+    PackageLogEmpty(PackageName),
+    /// The package log failed validation.
+    #[error("package `{name}` failed validation: {inner}")]
+    PackageValidationFailed {
+        /// The package that failed validation.
+        name: PackageName,
+        /// The validation error.
+        inner: Box<package::ValidationError>,
+    },
+    /// The source returned the wrong number of inclusion proofs.
+    #[error("expected inclusion proofs for {expected} log(s) but the source returned {found}")]
+    ProofCount {
+        /// The number of log heads to prove.
+        expected: usize,
+        /// The number of proofs returned.
+        found: usize,
+    },
+    /// A log head failed an inclusion proof.
+    #[error("failed to prove inclusion of log `{log_id}`: {inner}")]
+    InclusionProof {
+        /// The log that failed the proof.
+        log_id: LogId,
+        /// The proof error.
+        inner: api::ClientError,
+    },
+    /// The content did not match its digest.
+    #[error("content `{0}` does not match its digest")]
+    ContentDigestMismatch(AnyHash),
+}
+
+/// The head of a log to prove the inclusion of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogHead {
+    /// The leaf of the head record in the registry log.
+    pub leaf: LogLeaf,
+    /// The index of the head record in the registry log.
+    pub registry_index: RegistryIndex,
+}
+
+/// Implemented by sources of registry data.
+#[async_trait::async_trait]
+pub trait RegistrySource: Send + Sync {
+    /// Fetches the latest signed checkpoint.
+    async fn fetch_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>, SourceError>;
+
+    /// Fetches every record of a log up to the given checkpoint, in registry
+    /// order.
+    async fn fetch_records(
+        &self,
+        checkpoint: &Checkpoint,
+        log_id: &LogId,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, SourceError>;
+
+    /// Fetches proofs of the inclusion of the given log heads in the given
+    /// checkpoint.
+    ///
+    /// Returns either a single response proving every head, or a response
+    /// for each head in the given order.
+    async fn fetch_proofs(
+        &self,
+        checkpoint: &Checkpoint,
+        heads: &[LogHead],
+    ) -> Result<Vec<InclusionResponse>, SourceError>;
+
+    /// Fetches the content with the given digest.
+    async fn fetch_content(&self, digest: &AnyHash) -> Result<Bytes, SourceError>;
+}
+
+#[async_trait::async_trait]
+impl RegistrySource for api::Client {
+    async fn fetch_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>, SourceError> {
+        Ok(self.latest_checkpoint(None).await?)
+    }
+
+    async fn fetch_records(
+        &self,
+        checkpoint: &Checkpoint,
+        log_id: &LogId,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, SourceError> {
+        let operator_log = *log_id == LogId::operator_log::<Sha256>();
+        let mut operator_token = None;
+        let mut package_token = None;
+        let mut records = Vec::new();
+        loop {
+            let mut packages = IndexMap::new();
+            if !operator_log {
+                packages.insert(log_id.clone(), package_token.clone());
+            }
+
+            let mut stream = self
+                .fetch_logs_stream(
+                    None,
+                    FetchLogsRequest {
+                        log_length: checkpoint.log_length,
+                        limit: None,
+                        operator: operator_token.as_deref().map(Cow::Borrowed),
+                        packages: Cow::Owned(packages),
+                    },
+                )
+                .await?;
+
+            let mut more = false;
+            while let Some(frame) = stream.next().await {
+                match frame? {
+                    FetchLogsFrame::Operator(record) => {
+                        operator_token = Some(record.fetch_token);
+                        if operator_log {
+                            records.push(record.envelope);
+                        }
+                    }
+                    FetchLogsFrame::Package { log_id: id, record } if id == *log_id => {
+                        package_token = Some(record.fetch_token);
+                        records.push(record.envelope);
+                    }
+                    FetchLogsFrame::Package { .. } => {}
+                    FetchLogsFrame::End { more: m, .. } => more = m,
+                }
+            }
+
+            if !more {
+                return Ok(records);
+            }
+        }
+    }
+
+    async fn fetch_proofs(
+        &self,
+        checkpoint: &Checkpoint,
+        heads: &[LogHead],
+    ) -> Result<Vec<InclusionResponse>, SourceError> {
+        let response = self
+            .inclusion_proof(
+                None,
+                InclusionRequest {
+                    log_length: checkpoint.log_length,
+                    leafs: heads.iter().map(|head| head.registry_index).collect(),
+                },
+            )
+            .await?;
+        Ok(vec![response])
+    }
+
+    async fn fetch_content(&self, digest: &AnyHash) -> Result<Bytes, SourceError> {
+        let chunks: Vec<Bytes> = self
+            .download_content(None, digest)
+            .await?
+            .try_collect()
+            .await
+            .map_err(api::ClientError::Other)?;
+        Ok(chunks.concat().into())
+    }
+}
+
+#[async_trait::async_trait]
+impl RegistrySource for StaticSiteClient {
+    async fn fetch_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>, SourceError> {
+        Ok(self.get_json(static_site::checkpoint()).await?)
+    }
+
+    async fn fetch_records(
+        &self,
+        checkpoint: &Checkpoint,
+        log_id: &LogId,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, SourceError> {
+        let path = if *log_id == LogId::operator_log::<Sha256>() {
+            static_site::operator_log().to_string()
+        } else {
+            static_site::package_log(log_id)
+        };
+
+        let log: StaticLog = match self.get_json(&path).await {
+            Ok(log) => log,
+            Err(StaticSiteError::Read { .. }) => {
+                return Err(SourceError::LogNotFound(log_id.clone()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(log
+            .records
+            .into_iter()
+            .filter(|record| record.registry_index < checkpoint.log_length)
+            .collect())
+    }
+
+    async fn fetch_proofs(
+        &self,
+        _checkpoint: &Checkpoint,
+        heads: &[LogHead],
+    ) -> Result<Vec<InclusionResponse>, SourceError> {
+        // A static site only holds a proof of each log head at the checkpoint
+        // it was exported at
+        let mut responses = Vec::with_capacity(heads.len());
+        for head in heads {
+            let path = if head.leaf.log_id == LogId::operator_log::<Sha256>() {
+                static_site::operator_proof().to_string()
+            } else {
+                static_site::package_proof(&head.leaf.log_id)
+            };
+            responses.push(self.get_json(&path).await?);
+        }
+
+        Ok(responses)
+    }
+
+    async fn fetch_content(&self, digest: &AnyHash) -> Result<Bytes, SourceError> {
+        match self.get(&static_site::content(digest)).await {
+            Ok(content) => Ok(content),
+            Err(StaticSiteError::Read { .. }) => Err(SourceError::ContentNotFound(*digest)),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Represents a verified snapshot of a registry source.
+#[derive(Debug)]
+pub struct SourceSnapshot {
+    /// The verified checkpoint of the snapshot.
+    pub checkpoint: TimestampedCheckpoint,
+    /// The validated operator log state.
+    pub operator: operator::LogState,
+}
+
+/// A release resolved from a registry source.
+#[derive(Debug, Clone)]
+pub struct ResolvedRelease {
+    /// The version of the release.
+    pub version: Version,
+    /// The digest of the release content.
+    pub digest: AnyHash,
+    /// The verified release content.
+    pub content: Bytes,
+}
+
+/// Fetches the latest checkpoint and the operator log from a source,
+/// validating the operator log and verifying the checkpoint signature and
+/// the operator log's inclusion in the checkpoint.
+pub async fn verify_operator<S>(source: &S) -> Result<SourceSnapshot, SourceError>
+where
+    S: RegistrySource + ?Sized,
+{
+    let ts_checkpoint = source.fetch_checkpoint().await?;
+    let checkpoint = ts_checkpoint.as_ref().clone();
+    let log_id = LogId::operator_log::<Sha256>();
+
+    let mut operator = operator::LogState::new();
+    let mut head_index = None;
+    for record in source
+        .fetch_records(&checkpoint.checkpoint, &log_id)
+        .await?
+    {
+        let record: PublishedProtoEnvelope<operator::OperatorRecord> =
+            record.try_into().map_err(SourceError::Record)?;
+        check_index(&checkpoint.checkpoint, head_index, record.registry_index)?;
+        operator = operator
+            .validate(&record.envelope)
+            .map_err(SourceError::OperatorValidationFailed)?;
+        head_index = Some(record.registry_index);
+    }
+
+    let key = operator
+        .public_key(ts_checkpoint.key_id())
+        .ok_or_else(|| SourceError::InvalidCheckpointKeyId(ts_checkpoint.key_id().clone()))?;
+    TimestampedCheckpoint::verify(key, &checkpoint.encode(), ts_checkpoint.signature())
+        .map_err(|_| SourceError::InvalidCheckpointSignature)?;
+
+    let (Some(head), Some(registry_index)) = (operator.head(), head_index) else {
+        return Err(SourceError::NoOperatorRecords);
+    };
+    verify_inclusion(
+        source,
+        &checkpoint.checkpoint,
+        &[LogHead {
+            leaf: LogLeaf {
+                log_id,
+                record_id: head.digest.clone(),
+            },
+            registry_index,
+        }],
+    )
+    .await?;
+
+    Ok(SourceSnapshot {
+        checkpoint,
+        operator,
+    })
+}
+
+/// Fetches and validates the log of a package from a source, verifying its
+/// inclusion in the snapshot's checkpoint.
+///
+/// Package records are also checked against the operator policy of the
+/// snapshot, e.g. keys the operator has declared compromised.
+pub async fn verify_package<S>(
+    source: &S,
+    snapshot: &SourceSnapshot,
+    name: &PackageName,
+) -> Result<package::LogState, SourceError>
+where
+    S: RegistrySource + ?Sized,
+{
+    let checkpoint = &snapshot.checkpoint.checkpoint;
+    let log_id = LogId::package_log::<Sha256>(name);
+    let validation_failed = |inner| SourceError::PackageValidationFailed {
+        name: name.clone(),
+        inner: Box::new(inner),
+    };
+
+    let mut state = package::LogState::new();
+    let mut head_index = None;
+    for record in source.fetch_records(checkpoint, &log_id).await? {
+        let record: PublishedProtoEnvelope<package::PackageRecord> =
+            record.try_into().map_err(SourceError::Record)?;
+        check_index(checkpoint, head_index, record.registry_index)?;
+        snapshot
+            .operator
+            .check_package_record(&record.envelope, record.registry_index)
+            .map_err(validation_failed)?;
+        state = state
+            .validate(&record.envelope)
+            .map_err(validation_failed)?;
+        head_index = Some(record.registry_index);
+    }
+
+    let (Some(head), Some(registry_index)) = (state.head(), head_index) else {
+        return Err(SourceError::PackageLogEmpty(name.clone()));
+    };
+    verify_inclusion(
+        source,
+        checkpoint,
+        &[LogHead {
+            leaf: LogLeaf {
+                log_id,
+                record_id: head.digest.clone(),
+            },
+            registry_index,
+        }],
+    )
+    .await?;
+
+    Ok(state)
+}
+
+/// Fetches content from a source, verifying its digest.
+pub async fn fetch_verified_content<S>(source: &S, digest: &AnyHash) -> Result<Bytes, SourceError>
+where
+    S: RegistrySource + ?Sized,
+{
+    let content = source.fetch_content(digest).await?;
+    if digest.algorithm().digest(&content) != *digest {
+        return Err(SourceError::ContentDigestMismatch(*digest));
+    }
+
+    Ok(content)
+}
+
+/// Resolves the latest release of a package matching the given requirement
+/// from a source, verifying the package log and the release content.
+///
+/// Returns `None` if no unyanked release matches the requirement.
+pub async fn resolve<S>(
+    source: &S,
+    snapshot: &SourceSnapshot,
+    name: &PackageName,
+    requirement: &VersionReq,
+) -> Result<Option<ResolvedRelease>, SourceError>
+where
+    S: RegistrySource + ?Sized,
+{
+    let state = verify_package(source, snapshot, name).await?;
+    let Some((version, digest)) = state
+        .find_latest_release(requirement)
+        .and_then(|release| Some((release.version.clone(), *release.content()?)))
+    else {
+        return Ok(None);
+    };
+
+    let content = fetch_verified_content(source, &digest).await?;
+    Ok(Some(ResolvedRelease {
+        version,
+        digest,
+        content,
+    }))
+}
+
+/// Checks that a record fetched from a source follows the previous record of
+/// its log and is covered by the checkpoint.
+fn check_index(
+    checkpoint: &Checkpoint,
+    previous: Option<RegistryIndex>,
+    index: RegistryIndex,
+) -> Result<(), SourceError> {
+    if previous.is_some_and(|previous| index <= previous) {
+        return Err(SourceError::OutOfOrder(index));
+    }
+
+    if index >= checkpoint.log_length {
+        return Err(SourceError::BeyondCheckpoint {
+            index,
+            log_length: checkpoint.log_length,
+        });
+    }
+
+    Ok(())
+}
+
+async fn verify_inclusion<S>(
+    source: &S,
+    checkpoint: &Checkpoint,
+    heads: &[LogHead],
+) -> Result<(), SourceError>
+where
+    S: RegistrySource + ?Sized,
+{
+    if heads.is_empty() {
+        return Ok(());
+    }
+
+    let responses = source.fetch_proofs(checkpoint, heads).await?;
+    let groups: Vec<&[LogHead]> = match responses.len() {
+        1 => vec![heads],
+        n if n == heads.len() => heads.chunks(1).collect(),
+        found => {
+            return Err(SourceError::ProofCount {
+                expected: heads.len(),
+                found,
+            })
+        }
+    };
+
+    for (response, heads) in responses.into_iter().zip(groups) {
+        let leafs = heads
+            .iter()
+            .map(|head| head.leaf.clone())
+            .collect::<Vec<_>>();
+        api::Client::validate_inclusion_response(response, checkpoint, &leafs).map_err(
+            |inner| SourceError::InclusionProof {
+                log_id: leafs[0].log_id.clone(),
+                inner,
+            },
+        )?;
+    }
+
+    Ok(())
+}
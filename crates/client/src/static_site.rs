@@ -0,0 +1,299 @@
+//! A client for registries exported to a static site layout.
+//!
+//! See [`warg_api::v1::static_site`] for a description of the layout.
+
+use crate::api;
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use thiserror::Error;
+use url::Url;
+use warg_api::v1::{
+    proof::InclusionResponse,
+    static_site::{self, StaticIndex, StaticLog},
+};
+use warg_crypto::{
+    hash::{AnyHash, Sha256},
+    signing, Encode, Signable,
+};
+use warg_protocol::{
+    operator, package,
+    registry::{LogId, LogLeaf, PackageName, RegistryLen, TimestampedCheckpoint},
+    PublishedProtoEnvelope, SerdeEnvelope,
+};
+
+/// Represents an error from a static site client.
+#[derive(Debug, Error)]
+pub enum StaticSiteError {
+    /// A file could not be fetched from a static site served over HTTP.
+    #[error("failed to fetch `{url}`: {source}")]
+    Fetch {
+        /// The URL of the file.
+        url: Url,
+        /// The underlying error.
+        source: reqwest::Error,
+    },
+    /// A file could not be read from a static site on the local file system.
+    #[error("failed to read `{path}`: {source}")]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// A file in the static site could not be deserialized.
+    #[error("failed to deserialize `{url}`: {source}")]
+    Deserialize {
+        /// The URL of the file.
+        url: Url,
+        /// The underlying error.
+        source: serde_json::Error,
+    },
+    /// A record in the static site could not be decoded.
+    #[error("failed to decode record: {0}")]
+    Record(anyhow::Error),
+    /// The index was exported at a different checkpoint than the checkpoint file.
+    #[error("index log length {index} does not match checkpoint log length {checkpoint}")]
+    IndexMismatch {
+        /// The log length of the index.
+        index: RegistryLen,
+        /// The log length of the checkpoint.
+        checkpoint: RegistryLen,
+    },
+    /// The checkpoint was signed by an unknown key.
+    #[error("checkpoint signed by unknown key `{0}`")]
+    InvalidCheckpointKeyId(signing::KeyID),
+    /// The checkpoint signature failed verification.
+    #[error("invalid checkpoint signature")]
+    InvalidCheckpointSignature,
+    /// The operator log is empty.
+    #[error("the static site does not contain any operator records")]
+    NoOperatorRecords,
+    /// The operator log failed validation.
+    #[error("operator failed validation: {0}")]
+    OperatorValidationFailed(operator::ValidationError),
+    /// The package is not in the static site.
+    #[error("package `{0}` was not found")]
+    PackageNotFound(PackageName),
+    /// The package log is empty.
+    #[error("package `{0}` does not contain any records")]
+    PackageLogEmpty(PackageName),
+    /// The package log failed validation.
+    #[error("package `{name}` failed validation: {inner}")]
+    PackageValidationFailed {
+        /// The package that failed validation.
+        name: PackageName,
+        /// The validation error.
+        inner: Box<package::ValidationError>,
+    },
+    /// A log head failed an inclusion proof.
+    #[error("failed to prove inclusion of log `{log_id}`: {inner}")]
+    InclusionProof {
+        /// The log that failed the proof.
+        log_id: LogId,
+        /// The proof error.
+        inner: api::ClientError,
+    },
+    /// The content did not match its digest.
+    #[error("content `{0}` does not match its digest")]
+    ContentDigestMismatch(AnyHash),
+}
+
+/// Represents a verified snapshot of a registry exported to a static site.
+#[derive(Debug)]
+pub struct StaticSnapshot {
+    /// The verified checkpoint of the snapshot.
+    pub checkpoint: TimestampedCheckpoint,
+    /// The validated operator log state.
+    pub operator: operator::LogState,
+    /// The package name index.
+    pub index: StaticIndex,
+}
+
+/// A client for a registry exported to a static site.
+///
+/// Both `http(s)` and `file` base URLs are supported.
+pub struct StaticSiteClient {
+    base: Url,
+    client: reqwest::Client,
+}
+
+impl StaticSiteClient {
+    /// Creates a new client for the static site at the given base URL.
+    pub fn new(mut base: Url) -> Self {
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{path}/", path = base.path()));
+        }
+
+        Self {
+            base,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Gets the base URL of the static site.
+    pub fn url(&self) -> &Url {
+        &self.base
+    }
+
+    /// Fetches the checkpoint and operator log, validating the operator log and
+    /// verifying the checkpoint signature and operator log inclusion.
+    pub async fn snapshot(&self) -> Result<StaticSnapshot, StaticSiteError> {
+        let ts_checkpoint: SerdeEnvelope<TimestampedCheckpoint> =
+            self.get_json(static_site::checkpoint()).await?;
+        let index: StaticIndex = self.get_json(static_site::index()).await?;
+        let checkpoint = ts_checkpoint.as_ref().clone();
+        if index.log_length != checkpoint.checkpoint.log_length {
+            return Err(StaticSiteError::IndexMismatch {
+                index: index.log_length,
+                checkpoint: checkpoint.checkpoint.log_length,
+            });
+        }
+
+        let log: StaticLog = self.get_json(static_site::operator_log()).await?;
+        let mut operator = operator::LogState::new();
+        for record in log.records {
+            let record: PublishedProtoEnvelope<operator::OperatorRecord> =
+                record.try_into().map_err(StaticSiteError::Record)?;
+            operator = operator
+                .validate(&record.envelope)
+                .map_err(StaticSiteError::OperatorValidationFailed)?;
+        }
+
+        let key = operator.public_key(ts_checkpoint.key_id()).ok_or_else(|| {
+            StaticSiteError::InvalidCheckpointKeyId(ts_checkpoint.key_id().clone())
+        })?;
+        TimestampedCheckpoint::verify(key, &checkpoint.encode(), ts_checkpoint.signature())
+            .map_err(|_| StaticSiteError::InvalidCheckpointSignature)?;
+
+        let head = operator
+            .head()
+            .as_ref()
+            .ok_or(StaticSiteError::NoOperatorRecords)?;
+        self.verify_inclusion(
+            static_site::operator_proof(),
+            &checkpoint,
+            LogLeaf {
+                log_id: LogId::operator_log::<Sha256>(),
+                record_id: head.digest.clone(),
+            },
+        )
+        .await?;
+
+        Ok(StaticSnapshot {
+            checkpoint,
+            operator,
+            index,
+        })
+    }
+
+    /// Fetches and validates the log of the given package, verifying its
+    /// inclusion in the snapshot's checkpoint.
+    pub async fn package(
+        &self,
+        snapshot: &StaticSnapshot,
+        name: &PackageName,
+    ) -> Result<package::LogState, StaticSiteError> {
+        // The log identifier is derived from the name rather than trusted from the index
+        if !snapshot.index.packages.contains_key(name) {
+            return Err(StaticSiteError::PackageNotFound(name.clone()));
+        }
+        let log_id = &LogId::package_log::<Sha256>(name);
+
+        let log: StaticLog = self.get_json(&static_site::package_log(log_id)).await?;
+        let mut state = package::LogState::new();
+        for record in log.records {
+            let record: PublishedProtoEnvelope<package::PackageRecord> =
+                record.try_into().map_err(StaticSiteError::Record)?;
+            state = state.validate(&record.envelope).map_err(|inner| {
+                StaticSiteError::PackageValidationFailed {
+                    name: name.clone(),
+                    inner: Box::new(inner),
+                }
+            })?;
+        }
+
+        let head = state
+            .head()
+            .as_ref()
+            .ok_or_else(|| StaticSiteError::PackageLogEmpty(name.clone()))?;
+        self.verify_inclusion(
+            &static_site::package_proof(log_id),
+            &snapshot.checkpoint,
+            LogLeaf {
+                log_id: log_id.clone(),
+                record_id: head.digest.clone(),
+            },
+        )
+        .await?;
+
+        Ok(state)
+    }
+
+    /// Downloads the content with the given digest, verifying the digest.
+    pub async fn download(&self, digest: &AnyHash) -> Result<Bytes, StaticSiteError> {
+        let bytes = self.get(&static_site::content(digest)).await?;
+        if digest.algorithm().digest(&bytes) != *digest {
+            return Err(StaticSiteError::ContentDigestMismatch(*digest));
+        }
+
+        Ok(bytes)
+    }
+
+    async fn verify_inclusion(
+        &self,
+        path: &str,
+        checkpoint: &TimestampedCheckpoint,
+        leaf: LogLeaf,
+    ) -> Result<(), StaticSiteError> {
+        let proof: InclusionResponse = self.get_json(path).await?;
+        api::Client::validate_inclusion_response(
+            proof,
+            &checkpoint.checkpoint,
+            std::slice::from_ref(&leaf),
+        )
+        .map_err(|inner| StaticSiteError::InclusionProof {
+            log_id: leaf.log_id,
+            inner,
+        })
+    }
+
+    pub(crate) async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<T, StaticSiteError> {
+        let bytes = self.get(path).await?;
+        serde_json::from_slice(&bytes).map_err(|source| StaticSiteError::Deserialize {
+            url: self.base.join(path).unwrap(),
+            source,
+        })
+    }
+
+    pub(crate) async fn get(&self, path: &str) -> Result<Bytes, StaticSiteError> {
+        let url = self.base.join(path).unwrap();
+        if url.scheme() == "file" {
+            let path = url
+                .to_file_path()
+                .unwrap_or_else(|_| PathBuf::from(url.path()));
+            return tokio::fs::read(&path)
+                .await
+                .map(Bytes::from)
+                .map_err(|source| StaticSiteError::Read { path, source });
+        }
+
+        tracing::debug!(%url, "fetching static site file");
+        let res = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+        match res {
+            Ok(res) => res
+                .bytes()
+                .await
+                .map_err(|source| StaticSiteError::Fetch { url, source }),
+            Err(source) => Err(StaticSiteError::Fetch { url, source }),
+        }
+    }
+}
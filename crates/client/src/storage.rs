@@ -9,18 +9,33 @@ use reqwest::header::HeaderValue;
 use serde::{Deserialize, Serialize};
 use std::{fmt, path::PathBuf, pin::Pin, str::FromStr, time::SystemTime};
 use warg_crypto::{
+    encryption::ContentEncryption,
     hash::{AnyHash, HashAlgorithm},
     signing::{self, KeyID, PublicKey},
 };
 use warg_protocol::{
+    discovery::OperatorKeys,
     operator,
-    package::{self, PackageRecord, Permission, PACKAGE_RECORD_VERSION},
-    registry::{Checkpoint, PackageName, RecordId, RegistryIndex, TimestampedCheckpoint},
-    ProtoEnvelope, SerdeEnvelope, Version,
+    package::{self, PackageRecord, Permission, ReleaseManifest, PACKAGE_RECORD_VERSION},
+    registry::{
+        Checkpoint, PackageName, RecordId, RecordReceipt, RegistryIndex, TimestampedCheckpoint,
+    },
+    ProtoEnvelope, SerdeEnvelope, Version, VersionReq,
 };
 
 mod fs;
 pub use fs::*;
+pub use warg_crypto::hash::StorageLayout;
+
+#[cfg(feature = "ipfs")]
+mod ipfs;
+#[cfg(feature = "ipfs")]
+pub use ipfs::*;
+
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "s3")]
+pub use s3::*;
 
 /// Registry domain used for warg header values
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -102,6 +117,37 @@ pub trait RegistryStorage: Send + Sync {
         operator: OperatorInfo,
     ) -> Result<()>;
 
+    /// Loads the cached operator keys document of the registry.
+    ///
+    /// Returns `Ok(None)` if the document is not present.
+    async fn load_operator_keys(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+    ) -> Result<Option<SerdeEnvelope<OperatorKeys>>>;
+
+    /// Stores the operator keys document of the registry.
+    async fn store_operator_keys(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        operator_keys: &SerdeEnvelope<OperatorKeys>,
+    ) -> Result<()>;
+
+    /// Loads the receipt of a published package record.
+    ///
+    /// Returns `Ok(None)` if the receipt is not present.
+    async fn load_receipt(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        record_id: &RecordId,
+    ) -> Result<Option<SerdeEnvelope<RecordReceipt>>>;
+
+    /// Stores the receipt of a published package record.
+    async fn store_receipt(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        receipt: &SerdeEnvelope<RecordReceipt>,
+    ) -> Result<()>;
+
     /// Loads the package information for all packages.
     async fn load_all_packages(&self) -> Result<IndexMap<RegistryDomain, Vec<PackageInfo>>>;
 
@@ -256,13 +302,35 @@ pub enum PublishEntry {
         /// The version of the release.
         version: Version,
         /// The content digest of the release.
+        ///
+        /// If the content is encrypted, this is the digest of the ciphertext.
         content: AnyHash,
+        /// How the content is encrypted, if it is encrypted.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<ContentEncryption>,
+        /// The artifacts shipped alongside the content, if any.
+        ///
+        /// The artifacts must be in content storage to be uploaded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        manifest: Option<ReleaseManifest>,
     },
     /// A release is being yanked.
     Yank {
         /// The version of the release being yanked.
         version: Version,
     },
+    /// Every unyanked release matching a range is being yanked.
+    YankRange {
+        /// The range of versions being yanked.
+        range: VersionReq,
+    },
+    /// A channel tag is being pointed at a release.
+    Tag {
+        /// The name of the tag.
+        tag: String,
+        /// The version of the release being tagged.
+        version: Version,
+    },
     /// A key is being granted permission(s).
     Grant {
         /// The public key being granted to.
@@ -300,9 +368,28 @@ impl PublishInfo {
         self.entries.iter().any(|e| matches!(e, PublishEntry::Init))
     }
 
+    /// Finds the first entry that cannot be rebased onto the given package log state.
+    ///
+    /// Releases conflict with an existing release of the same version, yanks
+    /// and tags conflict with a missing or already yanked release, and range
+    /// yanks conflict when no unyanked release matches the range.
+    pub(crate) fn rebase_conflict(&self, state: &package::LogState) -> Option<&PublishEntry> {
+        self.entries.iter().find(|entry| match entry {
+            PublishEntry::Init => true,
+            PublishEntry::Release { version, .. } => state.release(version).is_some(),
+            PublishEntry::Yank { version } | PublishEntry::Tag { version, .. } => state
+                .release(version)
+                .map(|release| release.yanked())
+                .unwrap_or(true),
+            PublishEntry::YankRange { range } => state.releases_to_yank(range).is_empty(),
+            PublishEntry::Grant { .. } | PublishEntry::Revoke { .. } => false,
+        })
+    }
+
     pub(crate) fn finalize(
         self,
         signing_key: &signing::PrivateKey,
+        publish_token: Option<&package::PublishToken>,
     ) -> Result<ProtoEnvelope<PackageRecord>> {
         let mut entries = Vec::with_capacity(self.entries.len());
         for entry in self.entries {
@@ -313,14 +400,38 @@ impl PublishInfo {
                         key: signing_key.public_key(),
                     });
                 }
-                PublishEntry::Release { version, content } => {
-                    entries.push(package::PackageEntry::Release { version, content });
+                PublishEntry::Release {
+                    version,
+                    content,
+                    encryption,
+                    manifest,
+                } => {
+                    entries.push(package::PackageEntry::Release {
+                        version,
+                        content,
+                        encryption,
+                        manifest,
+                    });
+                }
+                PublishEntry::Yank { version } => entries.push(package::PackageEntry::Yank {
+                    version,
+                    reason: None,
+                }),
+                PublishEntry::YankRange { range } => {
+                    entries.push(package::PackageEntry::YankRange {
+                        range,
+                        reason: None,
+                    })
                 }
-                PublishEntry::Yank { version } => {
-                    entries.push(package::PackageEntry::Yank { version })
+                PublishEntry::Tag { tag, version } => {
+                    entries.push(package::PackageEntry::Tag { tag, version })
                 }
                 PublishEntry::Grant { key, permissions } => {
-                    entries.push(package::PackageEntry::GrantFlat { key, permissions })
+                    entries.push(package::PackageEntry::GrantFlat {
+                        key,
+                        permissions,
+                        proof: None,
+                    })
                 }
                 PublishEntry::Revoke {
                     key_id,
@@ -340,8 +451,12 @@ impl PublishInfo {
             // Should incrementing timestamps even be a requirement?
             timestamp: SystemTime::now(),
             entries,
+            entry_signatures: Vec::new(),
+            publish_token: publish_token.cloned(),
         };
 
+        tracing::debug!("signing {record}");
+        record.validate_self()?;
         Ok(ProtoEnvelope::signed_contents(signing_key, record)?)
     }
 }
@@ -2,7 +2,7 @@
 
 use super::{
     ContentStorage, NamespaceMapStorage, OperatorInfo, PackageInfo, PublishInfo, RegistryDomain,
-    RegistryStorage,
+    RegistryStorage, StorageLayout,
 };
 use crate::lock::FileLock;
 use anyhow::{anyhow, bail, Context, Result};
@@ -17,6 +17,7 @@ use std::{
     path::{Path, PathBuf},
     pin::Pin,
     str::FromStr,
+    time::SystemTime,
 };
 use tempfile::NamedTempFile;
 use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
@@ -24,7 +25,8 @@ use tokio_util::io::ReaderStream;
 use walkdir::WalkDir;
 use warg_crypto::hash::{AnyHash, Digest, Hash, Sha256};
 use warg_protocol::{
-    registry::{LogId, PackageName, TimestampedCheckpoint},
+    discovery::OperatorKeys,
+    registry::{LogId, PackageName, RecordId, RecordReceipt, TimestampedCheckpoint},
     SerdeEnvelope,
 };
 
@@ -32,12 +34,14 @@ const TEMP_DIRECTORY: &str = "temp";
 const PENDING_PUBLISH_FILE: &str = "pending-publish.json";
 const LOCK_FILE_NAME: &str = ".lock";
 const PACKAGE_LOGS_DIR: &str = "package-logs";
+const RECEIPTS_DIR: &str = "receipts";
 
 /// Represents a package storage using the local file system.
 pub struct FileSystemRegistryStorage {
     _lock: FileLock,
     base_dir: PathBuf,
     registries_dir: PathBuf,
+    layout: StorageLayout,
 }
 
 impl FileSystemRegistryStorage {
@@ -57,6 +61,7 @@ impl FileSystemRegistryStorage {
                 _lock: lock,
                 base_dir,
                 registries_dir: registries_dir.to_path_buf(),
+                layout: StorageLayout::default(),
             })),
             None => Ok(None),
         }
@@ -79,9 +84,18 @@ impl FileSystemRegistryStorage {
             _lock: lock,
             base_dir,
             registries_dir: registries_dir.to_path_buf(),
+            layout: StorageLayout::default(),
         })
     }
 
+    /// Sets the layout of the package log files.
+    ///
+    /// Defaults to [`StorageLayout::Flat`].
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     fn operator_path(&self, namespace_registry: Option<&RegistryDomain>) -> PathBuf {
         if let Some(nm) = namespace_registry {
             return self
@@ -92,27 +106,43 @@ impl FileSystemRegistryStorage {
         self.base_dir.join("operator.log")
     }
 
+    fn operator_keys_path(&self, namespace_registry: Option<&RegistryDomain>) -> PathBuf {
+        if let Some(nm) = namespace_registry {
+            return self
+                .registries_dir
+                .join(nm.to_string())
+                .join("operator-keys.json");
+        }
+        self.base_dir.join("operator-keys.json")
+    }
+
+    fn receipt_path(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        record_id: &RecordId,
+    ) -> PathBuf {
+        let receipts_dir = match namespace_registry {
+            Some(nm) => self.registries_dir.join(nm.to_string()).join(RECEIPTS_DIR),
+            None => self.base_dir.join(RECEIPTS_DIR),
+        };
+        self.layout
+            .locate_or_path(&receipts_dir, &record_id.clone().into())
+    }
+
     fn package_path(
         &self,
         namespace_registry: Option<&RegistryDomain>,
         name: &PackageName,
     ) -> PathBuf {
-        if let Some(nm) = namespace_registry {
-            return self
+        let logs_dir = match namespace_registry {
+            Some(nm) => self
                 .registries_dir
                 .join(nm.to_string())
-                .join(PACKAGE_LOGS_DIR)
-                .join(
-                    LogId::package_log::<Sha256>(name)
-                        .to_string()
-                        .replace(':', "/"),
-                );
-        }
-        self.base_dir.join(PACKAGE_LOGS_DIR).join(
-            LogId::package_log::<Sha256>(name)
-                .to_string()
-                .replace(':', "/"),
-        )
+                .join(PACKAGE_LOGS_DIR),
+            None => self.base_dir.join(PACKAGE_LOGS_DIR),
+        };
+        self.layout
+            .locate_or_path(&logs_dir, &LogId::package_log::<Sha256>(name).into())
     }
 
     fn pending_publish_path(&self) -> PathBuf {
@@ -207,6 +237,41 @@ impl RegistryStorage for FileSystemRegistryStorage {
         store(&self.operator_path(namespace_registry), info).await
     }
 
+    async fn load_operator_keys(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+    ) -> Result<Option<SerdeEnvelope<OperatorKeys>>> {
+        Ok(load(&self.operator_keys_path(namespace_registry)).await?)
+    }
+
+    async fn store_operator_keys(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        operator_keys: &SerdeEnvelope<OperatorKeys>,
+    ) -> Result<()> {
+        store(&self.operator_keys_path(namespace_registry), operator_keys).await
+    }
+
+    async fn load_receipt(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        record_id: &RecordId,
+    ) -> Result<Option<SerdeEnvelope<RecordReceipt>>> {
+        Ok(load(&self.receipt_path(namespace_registry, record_id)).await?)
+    }
+
+    async fn store_receipt(
+        &self,
+        namespace_registry: Option<&RegistryDomain>,
+        receipt: &SerdeEnvelope<RecordReceipt>,
+    ) -> Result<()> {
+        store(
+            &self.receipt_path(namespace_registry, &receipt.as_ref().record_id),
+            receipt,
+        )
+        .await
+    }
+
     async fn load_package(
         &self,
         namespace_registry: Option<&RegistryDomain>,
@@ -243,6 +308,7 @@ pub struct FileSystemContentStorage {
     _lock: FileLock,
     base_dir: PathBuf,
     temp_dir: PathBuf,
+    layout: StorageLayout,
 }
 
 impl FileSystemContentStorage {
@@ -259,6 +325,7 @@ impl FileSystemContentStorage {
                 _lock: lock,
                 base_dir,
                 temp_dir,
+                layout: StorageLayout::default(),
             })),
             None => Ok(None),
         }
@@ -278,9 +345,18 @@ impl FileSystemContentStorage {
             _lock: lock,
             base_dir,
             temp_dir,
+            layout: StorageLayout::default(),
         })
     }
 
+    /// Sets the layout of the content files.
+    ///
+    /// Defaults to [`StorageLayout::Flat`].
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     fn temp_file(&self) -> Result<NamedTempFile> {
         fs::create_dir_all(&self.temp_dir).with_context(|| {
             format!(
@@ -297,8 +373,8 @@ impl FileSystemContentStorage {
         })
     }
 
-    fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.base_dir.join(digest.to_string().replace(':', "/"))
+    fn content_path(&self, digest: &AnyHash) -> Option<PathBuf> {
+        self.layout.locate(&self.base_dir, digest)
     }
 }
 
@@ -309,22 +385,16 @@ impl ContentStorage for FileSystemContentStorage {
     }
 
     fn content_location(&self, digest: &AnyHash) -> Option<PathBuf> {
-        let path = self.content_path(digest);
-        if path.is_file() {
-            Some(path)
-        } else {
-            None
-        }
+        self.content_path(digest)
     }
 
     async fn load_content(
         &self,
         digest: &AnyHash,
     ) -> Result<Option<Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>>> {
-        let path = self.content_path(digest);
-        if !path.is_file() {
+        let Some(path) = self.content_path(digest) else {
             return Ok(None);
-        }
+        };
 
         Ok(Some(Box::pin(
             ReaderStream::new(BufReader::new(
@@ -370,8 +440,8 @@ impl ContentStorage for FileSystemContentStorage {
 
         drop(writer);
 
-        let content_path = self.content_path(&hash);
-        if !content_path.is_file() {
+        if self.content_path(&hash).is_none() {
+            let content_path = self.layout.path(&self.base_dir, &hash, SystemTime::now());
             if let Some(parent) = content_path.parent() {
                 fs::create_dir_all(parent).with_context(|| {
                     format!(
@@ -0,0 +1,242 @@
+//! A module for content storage backed by IPFS.
+
+use super::ContentStorage;
+use crate::lock::FileLock;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use reqwest::{multipart, Url};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+
+const LOCK_FILE_NAME: &str = ".lock";
+
+/// The response of the IPFS `add` API.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AddResponse {
+    hash: String,
+}
+
+/// Represents a content storage using an IPFS node's HTTP API.
+///
+/// Content is pinned on the node when stored and verified against its digest
+/// when loaded. The mapping of content digests to IPFS content identifiers
+/// is kept in an index directory on the local file system.
+pub struct IpfsContentStorage {
+    _lock: FileLock,
+    client: reqwest::Client,
+    api_url: Url,
+    index_dir: PathBuf,
+}
+
+impl IpfsContentStorage {
+    /// Attempts to lock the content storage.
+    ///
+    /// The `api_url` is the base URL of the IPFS HTTP API (e.g.
+    /// `http://127.0.0.1:5001`).
+    ///
+    /// The index directory will be created if it does not exist.
+    ///
+    /// If the lock cannot be acquired, `Ok(None)` is returned.
+    pub fn try_lock(api_url: Url, index_dir: impl Into<PathBuf>) -> Result<Option<Self>> {
+        let index_dir = index_dir.into();
+        match FileLock::try_open_rw(index_dir.join(LOCK_FILE_NAME))? {
+            Some(lock) => Ok(Some(Self::new(lock, api_url, index_dir))),
+            None => Ok(None),
+        }
+    }
+
+    /// Locks a new content storage with the given index directory.
+    ///
+    /// The `api_url` is the base URL of the IPFS HTTP API (e.g.
+    /// `http://127.0.0.1:5001`).
+    ///
+    /// The index directory will be created if it does not exist.
+    ///
+    /// If the lock cannot be immediately acquired, this function
+    /// will block.
+    pub fn lock(api_url: Url, index_dir: impl Into<PathBuf>) -> Result<Self> {
+        let index_dir = index_dir.into();
+        let lock = FileLock::open_rw(index_dir.join(LOCK_FILE_NAME))?;
+        Ok(Self::new(lock, api_url, index_dir))
+    }
+
+    fn new(lock: FileLock, api_url: Url, index_dir: PathBuf) -> Self {
+        Self {
+            _lock: lock,
+            client: reqwest::Client::new(),
+            api_url,
+            index_dir,
+        }
+    }
+
+    fn index_path(&self, digest: &AnyHash) -> PathBuf {
+        self.index_dir.join(digest.to_string().replace(':', "/"))
+    }
+
+    fn load_cid(&self, digest: &AnyHash) -> Result<Option<String>> {
+        let path = self.index_path(digest);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let cid = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+        Ok(Some(cid.trim().to_string()))
+    }
+
+    fn store_cid(&self, digest: &AnyHash, cid: &str) -> Result<()> {
+        let path = self.index_path(digest);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create directory `{path}`",
+                    path = parent.display()
+                )
+            })?;
+        }
+
+        fs::write(&path, cid)
+            .with_context(|| format!("failed to write `{path}`", path = path.display()))
+    }
+
+    /// Invokes an IPFS API command, which always uses the `POST` method.
+    async fn call(
+        &self,
+        command: &str,
+        query: &[(&str, &str)],
+        form: Option<multipart::Form>,
+    ) -> Result<reqwest::Response> {
+        let url = self
+            .api_url
+            .join(&format!("api/v0/{command}"))
+            .with_context(|| format!("invalid IPFS API URL `{url}`", url = self.api_url))?;
+
+        let mut request = self.client.post(url).query(query);
+        if let Some(form) = form {
+            request = request.multipart(form);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("failed to send IPFS `{command}` request"))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!("IPFS `{command}` request failed with status {status}: {body}");
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl ContentStorage for IpfsContentStorage {
+    async fn clear(&self) -> Result<()> {
+        for entry in walkdir::WalkDir::new(&self.index_dir) {
+            let entry = entry?;
+            if !entry.file_type().is_file() || entry.file_name() == LOCK_FILE_NAME {
+                continue;
+            }
+
+            let path = entry.path();
+            let cid = fs::read_to_string(path)
+                .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+            self.call("pin/rm", &[("arg", cid.trim())], None).await?;
+            remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn content_location(&self, _digest: &AnyHash) -> Option<PathBuf> {
+        None
+    }
+
+    async fn load_content(
+        &self,
+        digest: &AnyHash,
+    ) -> Result<Option<Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>>> {
+        let Some(cid) = self.load_cid(digest)? else {
+            return Ok(None);
+        };
+
+        let bytes = self
+            .call("cat", &[("arg", &cid)], None)
+            .await?
+            .bytes()
+            .await
+            .with_context(|| format!("failed to read content `{cid}` from IPFS"))?;
+
+        let actual = digest.algorithm().digest(&bytes);
+        if actual != *digest {
+            bail!("IPFS content `{cid}` has digest `{actual}` but a digest of `{digest}` was expected");
+        }
+
+        Ok(Some(Box::pin(futures_util::stream::once(async move {
+            Ok(bytes)
+        }))))
+    }
+
+    async fn store_content(
+        &self,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>,
+        expected_digest: Option<&AnyHash>,
+    ) -> Result<AnyHash> {
+        // The content is buffered so that it is verified before being pinned
+        let mut content = BytesMut::new();
+        while let Some(bytes) = stream.next().await.transpose()? {
+            content.extend_from_slice(&bytes);
+        }
+        let content = content.freeze();
+
+        let hash = HashAlgorithm::Sha256.digest(&content);
+        if let Some(expected) = expected_digest {
+            if hash != *expected {
+                bail!(
+                    "stored content has digest `{hash}` but a digest of `{expected}` was expected",
+                );
+            }
+        }
+
+        if self.load_cid(&hash)?.is_some() {
+            return Ok(hash);
+        }
+
+        let form = multipart::Form::new().part(
+            "file",
+            multipart::Part::stream(content).file_name(hash.to_string()),
+        );
+        let response: AddResponse = self
+            .call(
+                "add",
+                &[
+                    ("pin", "true"),
+                    ("cid-version", "1"),
+                    ("raw-leaves", "true"),
+                    ("hash", "sha2-256"),
+                ],
+                Some(form),
+            )
+            .await?
+            .json()
+            .await
+            .context("failed to parse IPFS `add` response")?;
+
+        self.store_cid(&hash, &response.hash)?;
+        Ok(hash)
+    }
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    fs::remove_file(path)
+        .with_context(|| format!("failed to remove file `{path}`", path = path.display()))
+}
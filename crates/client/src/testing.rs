@@ -0,0 +1,633 @@
+//! Test doubles for code built on the client.
+//!
+//! A [`MockRegistry`] serves canned operator and package logs, signed
+//! checkpoints, proofs, and content to an API client, without a registry
+//! server. It can be scripted to [misbehave](Misbehavior) so that integrations
+//! can test how they handle a registry that cannot be trusted. It is also an
+//! in-memory [`RegistrySource`].
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use warg_client::{api, testing::{MockRegistry, MOCK_REGISTRY_URL}};
+//! use warg_crypto::signing::PrivateKey;
+//!
+//! let operator_key = PrivateKey::decode(
+//!     "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+//! )?;
+//! let registry = MockRegistry::new(operator_key);
+//! let client = api::Client::new(MOCK_REGISTRY_URL, None)?.with_transport(registry.clone());
+//! let checkpoint = client.latest_checkpoint(None).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    api::ClientError,
+    source::{LogHead, RegistrySource, SourceError},
+    transport::{self, Transport, TransportRequest},
+};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    Method, Response, StatusCode,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use warg_api::v1::{
+    content::{ContentError, ContentSourcesResponse},
+    fetch::{
+        FetchError, FetchLogsFrame, FetchLogsRequest, FetchLogsResponse, PublishedRecord,
+        FETCH_LOGS_STREAM_CONTENT_TYPE,
+    },
+    paths,
+    proof::{
+        ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse, ProofError,
+    },
+    ContentSource,
+};
+use warg_crypto::{
+    hash::{AnyHash, Hash, Sha256},
+    signing::{self, PrivateKey},
+};
+use warg_protocol::{
+    operator, package,
+    registry::{
+        Checkpoint, LogId, LogLeaf, MapKey, MapLeaf, PackageName, RecordId, RegistryIndex,
+        RegistryLen, TimestampedCheckpoint,
+    },
+    ProtoEnvelope, PublishedProtoEnvelopeBody, SerdeEnvelope,
+};
+use warg_transparency::{
+    log::{LogBuilder, LogData, LogProofBundle, Node, VecLog},
+    map::{Map, MapProofBundle},
+};
+
+/// The URL of a [`MockRegistry`].
+///
+/// Requests sent to a mock registry are dispatched by path, so the host of
+/// this URL is never resolved.
+pub const MOCK_REGISTRY_URL: &str = "https://mock.warg.invalid";
+
+/// The ways a [`MockRegistry`] can be scripted to misbehave.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Misbehavior {
+    /// The registry serves the checkpoint preceding its latest checkpoint.
+    StaleCheckpoint,
+    /// The registry serves checkpoints with a signature not made by its
+    /// operator key.
+    ForgedCheckpoint,
+    /// The registry serves inclusion proofs for a tampered registry map.
+    BadInclusionProof,
+    /// The registry serves consistency proofs for a tampered registry log.
+    BadConsistencyProof,
+    /// The registry does not serve streamed fetch logs responses, like
+    /// registries predating them.
+    NoStreaming,
+}
+
+/// A mock registry serving canned logs to an API client.
+///
+/// The mock is a [`Transport`]; use it with
+/// [`Client::with_transport`](crate::api::Client::with_transport) and
+/// [`MOCK_REGISTRY_URL`]. Clones of a mock share its logs.
+///
+/// Records are appended to the registry log as given, without validation,
+/// so that tests can serve invalid logs. Each appended record is covered by
+/// a new checkpoint signed with the operator key.
+#[derive(Clone)]
+pub struct MockRegistry {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockRegistry {
+    /// Creates a mock registry whose operator log is initialized with the
+    /// given operator key.
+    pub fn new(operator_key: PrivateKey) -> Self {
+        let init = operator::OperatorRecord {
+            prev: None,
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![operator::OperatorEntry::Init {
+                hash_algorithm: warg_crypto::hash::HashAlgorithm::Sha256,
+                key: operator_key.public_key(),
+            }],
+        };
+        let init = ProtoEnvelope::signed_contents(&operator_key, init)
+            .expect("failed to sign operator init record");
+
+        let registry = Self {
+            state: Arc::new(Mutex::new(State::new(operator_key))),
+        };
+        registry.append_operator_record(init);
+        registry
+    }
+
+    /// Appends a record to the operator log, returning its registry index.
+    pub fn append_operator_record(
+        &self,
+        record: ProtoEnvelope<operator::OperatorRecord>,
+    ) -> RegistryIndex {
+        let log_id = LogId::operator_log::<Sha256>();
+        let record_id = RecordId::operator_record::<Sha256>(&record);
+        self.state
+            .lock()
+            .unwrap()
+            .append(log_id, record_id, record.into())
+    }
+
+    /// Appends a record to the log of a package, returning its registry index.
+    pub fn append_package_record(
+        &self,
+        name: &PackageName,
+        record: ProtoEnvelope<package::PackageRecord>,
+    ) -> RegistryIndex {
+        let log_id = LogId::package_log::<Sha256>(name);
+        let record_id = RecordId::package_record::<Sha256>(&record);
+        self.state
+            .lock()
+            .unwrap()
+            .append(log_id, record_id, record.into())
+    }
+
+    /// Adds content to the registry, returning its digest.
+    pub fn add_content(&self, content: impl Into<Bytes>) -> AnyHash {
+        let content = content.into();
+        let digest: AnyHash = Hash::<Sha256>::of(content.as_ref()).into();
+        self.state.lock().unwrap().content.insert(digest, content);
+        digest
+    }
+
+    /// Gets the latest checkpoint of the registry, as signed by its operator.
+    pub fn checkpoint(&self) -> SerdeEnvelope<TimestampedCheckpoint> {
+        self.state
+            .lock()
+            .unwrap()
+            .checkpoints
+            .last()
+            .cloned()
+            .expect("registry has a checkpoint")
+    }
+
+    /// Starts misbehaving in the given way.
+    pub fn misbehave(&self, misbehavior: Misbehavior) {
+        self.state.lock().unwrap().misbehaviors.push(misbehavior);
+    }
+
+    /// Stops misbehaving in any way.
+    pub fn behave(&self) {
+        self.state.lock().unwrap().misbehaviors.clear();
+    }
+
+    fn handle(&self, method: &Method, path: &str, body: &[u8]) -> Result<Response, ClientError> {
+        let state = self.state.lock().unwrap();
+        let response = match (method, path) {
+            (&Method::GET, p) if p == paths::fetch_checkpoint() => {
+                json(StatusCode::OK, &state.latest_checkpoint()?)
+            }
+            (&Method::POST, p)
+                if p == paths::fetch_logs_stream()
+                    && !state.misbehaviors.contains(&Misbehavior::NoStreaming) =>
+            {
+                let request: FetchLogsRequest = parse(body)?;
+                match state.fetch_logs(&request) {
+                    Ok(frames) => {
+                        let mut headers = HeaderMap::new();
+                        headers.insert(
+                            CONTENT_TYPE,
+                            HeaderValue::from_static(FETCH_LOGS_STREAM_CONTENT_TYPE),
+                        );
+                        let body = frames
+                            .iter()
+                            .flat_map(FetchLogsFrame::encode_length_delimited)
+                            .collect::<Vec<_>>();
+                        transport::response(StatusCode::OK, headers, body.into())
+                    }
+                    Err(e) => json(StatusCode::from_u16(e.status()).unwrap(), &e),
+                }
+            }
+            (&Method::POST, p) if p == paths::fetch_logs() => {
+                let request: FetchLogsRequest = parse(body)?;
+                match state.fetch_logs(&request) {
+                    Ok(frames) => {
+                        let mut response = FetchLogsResponse {
+                            more: false,
+                            operator: Vec::new(),
+                            packages: IndexMap::new(),
+                            warnings: Vec::new(),
+                        };
+                        for frame in frames {
+                            match frame {
+                                FetchLogsFrame::Operator(record) => response.operator.push(record),
+                                FetchLogsFrame::Package { log_id, record } => {
+                                    response.packages.entry(log_id).or_default().push(record)
+                                }
+                                FetchLogsFrame::End { more, warnings } => {
+                                    response.more = more;
+                                    response.warnings = warnings;
+                                }
+                            }
+                        }
+                        json(StatusCode::OK, &response)
+                    }
+                    Err(e) => json(StatusCode::from_u16(e.status()).unwrap(), &e),
+                }
+            }
+            (&Method::POST, p) if p == paths::prove_inclusion() => {
+                match state.prove_inclusion(&parse(body)?) {
+                    Ok(response) => json(StatusCode::OK, &response),
+                    Err(e) => json(StatusCode::from_u16(e.status()).unwrap(), &e),
+                }
+            }
+            (&Method::POST, p) if p == paths::prove_consistency() => {
+                match state.prove_consistency(&parse(body)?) {
+                    Ok(response) => json(StatusCode::OK, &response),
+                    Err(e) => json(StatusCode::from_u16(e.status()).unwrap(), &e),
+                }
+            }
+            (&Method::GET, p) => match p
+                .strip_prefix("v1/content/")
+                .and_then(|digest| digest.parse::<AnyHash>().ok())
+            {
+                Some(digest) if state.content.contains_key(&digest) => json(
+                    StatusCode::OK,
+                    &ContentSourcesResponse {
+                        content_sources: IndexMap::from([(
+                            digest,
+                            vec![ContentSource::HttpGet {
+                                url: format!("{MOCK_REGISTRY_URL}/content/{digest}"),
+                                accept_ranges: false,
+                                size: None,
+                            }],
+                        )]),
+                    },
+                ),
+                Some(digest) => {
+                    let e = ContentError::ContentDigestNotFound(digest);
+                    json(StatusCode::from_u16(e.status()).unwrap(), &e)
+                }
+                None => match p
+                    .strip_prefix("content/")
+                    .and_then(|digest| digest.parse::<AnyHash>().ok())
+                    .and_then(|digest| state.content.get(&digest))
+                {
+                    Some(content) => {
+                        transport::response(StatusCode::OK, HeaderMap::new(), content.clone())
+                    }
+                    None => not_found(p),
+                },
+            },
+            (_, p) => not_found(p),
+        };
+
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for MockRegistry {
+    async fn send(&self, request: TransportRequest) -> Result<Response, ClientError> {
+        let body = request.body.into_bytes().await?;
+        let path = request.url.path().trim_start_matches('/');
+        tracing::debug!(
+            method = %request.method,
+            path,
+            "dispatching mock registry request"
+        );
+        self.handle(&request.method, path, &body)
+    }
+}
+
+#[async_trait::async_trait]
+impl RegistrySource for MockRegistry {
+    async fn fetch_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>, SourceError> {
+        Ok(self.state.lock().unwrap().latest_checkpoint()?)
+    }
+
+    async fn fetch_records(
+        &self,
+        checkpoint: &Checkpoint,
+        log_id: &LogId,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, SourceError> {
+        let state = self.state.lock().unwrap();
+        let log_length = checkpoint.log_length.min(state.records.len());
+        let records = state.records[..log_length]
+            .iter()
+            .filter(|(id, _)| id == log_id)
+            .map(|(_, record)| record.clone())
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            return Err(SourceError::LogNotFound(log_id.clone()));
+        }
+
+        Ok(records)
+    }
+
+    async fn fetch_proofs(
+        &self,
+        checkpoint: &Checkpoint,
+        heads: &[LogHead],
+    ) -> Result<Vec<InclusionResponse>, SourceError> {
+        let request = InclusionRequest {
+            log_length: checkpoint.log_length,
+            leafs: heads.iter().map(|head| head.registry_index).collect(),
+        };
+        let response = self
+            .state
+            .lock()
+            .unwrap()
+            .prove_inclusion(&request)
+            .map_err(ClientError::Proof)?;
+        Ok(vec![response])
+    }
+
+    async fn fetch_content(&self, digest: &AnyHash) -> Result<Bytes, SourceError> {
+        self.state
+            .lock()
+            .unwrap()
+            .content
+            .get(digest)
+            .cloned()
+            .ok_or(SourceError::ContentNotFound(*digest))
+    }
+}
+
+struct State {
+    operator_key: PrivateKey,
+    // The verifiable log of all log entries, with the node of each entry
+    log: VecLog<Sha256, LogLeaf>,
+    leaf_index: Vec<Node>,
+    // The records of the registry log, in order, with their logs
+    records: Vec<(LogId, PublishedProtoEnvelopeBody)>,
+    // The verifiable map of logs' latest entries, by log length
+    maps: IndexMap<RegistryLen, Map<Sha256, MapKey, MapLeaf>>,
+    checkpoints: Vec<SerdeEnvelope<TimestampedCheckpoint>>,
+    content: HashMap<AnyHash, Bytes>,
+    misbehaviors: Vec<Misbehavior>,
+}
+
+impl State {
+    fn new(operator_key: PrivateKey) -> Self {
+        Self {
+            operator_key,
+            log: Default::default(),
+            leaf_index: Default::default(),
+            records: Default::default(),
+            maps: Default::default(),
+            checkpoints: Default::default(),
+            content: Default::default(),
+            misbehaviors: Default::default(),
+        }
+    }
+
+    fn misbehaves(&self, misbehavior: Misbehavior) -> bool {
+        self.misbehaviors.contains(&misbehavior)
+    }
+
+    fn append(
+        &mut self,
+        log_id: LogId,
+        record_id: RecordId,
+        envelope: warg_protocol::ProtoEnvelopeBody,
+    ) -> RegistryIndex {
+        let registry_index = self.records.len();
+        let leaf = LogLeaf {
+            log_id: log_id.clone(),
+            record_id: record_id.clone(),
+        };
+        self.leaf_index.push(self.log.push(&leaf));
+        self.records.push((
+            log_id.clone(),
+            PublishedProtoEnvelopeBody {
+                envelope,
+                registry_index,
+            },
+        ));
+
+        let map = self
+            .maps
+            .last()
+            .map(|(_, map)| map.clone())
+            .unwrap_or_default()
+            .insert(MapKey::from(log_id), MapLeaf { record_id });
+        let log_checkpoint = self.log.checkpoint();
+        let checkpoint = Checkpoint {
+            log_root: log_checkpoint.root().into(),
+            log_length: log_checkpoint.length(),
+            map_root: (*map.root()).into(),
+        };
+        self.maps.insert(checkpoint.log_length, map);
+
+        let timestamped =
+            TimestampedCheckpoint::now(checkpoint).expect("system time is after the epoch");
+        self.checkpoints.push(
+            SerdeEnvelope::signed_contents(&self.operator_key, timestamped)
+                .expect("failed to sign checkpoint"),
+        );
+
+        registry_index
+    }
+
+    fn log_id(&self, index: RegistryIndex) -> LogId {
+        self.records[index].0.clone()
+    }
+
+    fn latest_checkpoint(&self) -> Result<SerdeEnvelope<TimestampedCheckpoint>, ClientError> {
+        let skip = usize::from(self.misbehaves(Misbehavior::StaleCheckpoint));
+        let checkpoint = self
+            .checkpoints
+            .iter()
+            .rev()
+            .nth(skip)
+            .or(self.checkpoints.first())
+            .cloned()
+            .expect("registry has a checkpoint");
+        if !self.misbehaves(Misbehavior::ForgedCheckpoint) {
+            return Ok(checkpoint);
+        }
+
+        let (_, forger) = signing::generate_p256_pair();
+        let forged = SerdeEnvelope::signed_contents(&forger, checkpoint.as_ref().clone())
+            .map_err(|e| ClientError::Other(e.into()))?;
+        Ok(SerdeEnvelope::from_parts_unchecked(
+            forged.as_ref().clone(),
+            checkpoint.key_id().clone(),
+            forged.signature().clone(),
+        ))
+    }
+
+    fn fetch_logs(&self, request: &FetchLogsRequest) -> Result<Vec<FetchLogsFrame>, FetchError> {
+        // Fetch tokens are the registry indexes of the last fetched records
+        let since = |token: Option<&str>| -> Result<Option<RegistryIndex>, FetchError> {
+            token
+                .map(|t| {
+                    t.parse()
+                        .map_err(|_| FetchError::FetchTokenNotFound(t.to_string()))
+                })
+                .transpose()
+        };
+
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let log_length = request.log_length.min(self.records.len());
+        let operator_since = since(request.operator.as_deref())?;
+        let mut package_since = IndexMap::with_capacity(request.packages.len());
+        for (log_id, token) in request.packages.iter() {
+            if !(0..log_length).any(|index| &self.log_id(index) == log_id) {
+                return Err(FetchError::LogNotFound(log_id.clone()));
+            }
+            package_since.insert(log_id.clone(), since(token.as_deref())?);
+        }
+
+        let mut frames = Vec::new();
+        for (index, (log_id, record)) in self.records[..log_length].iter().enumerate() {
+            let log_id = log_id.clone();
+            let since = if log_id == operator_log_id {
+                operator_since
+            } else {
+                match package_since.get(&log_id) {
+                    Some(since) => *since,
+                    None => continue,
+                }
+            };
+            if since.is_some_and(|since| index <= since) {
+                continue;
+            }
+
+            let record = PublishedRecord {
+                envelope: record.clone(),
+                fetch_token: index.to_string(),
+            };
+            frames.push(if log_id == operator_log_id {
+                FetchLogsFrame::Operator(record)
+            } else {
+                FetchLogsFrame::Package { log_id, record }
+            });
+        }
+
+        frames.push(FetchLogsFrame::End {
+            more: false,
+            warnings: Vec::new(),
+        });
+        Ok(frames)
+    }
+
+    fn prove_inclusion(&self, request: &InclusionRequest) -> Result<InclusionResponse, ProofError> {
+        let map = self
+            .maps
+            .get(&request.log_length)
+            .ok_or(ProofError::CheckpointNotFound(request.log_length))?;
+
+        // A tampered map maps every log to a record that was never published,
+        // and includes a log that was never published so that even the proof
+        // of a single log evaluates to a root other than the checkpoint's
+        let tampered;
+        let map = if self.misbehaves(Misbehavior::BadInclusionProof) {
+            let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("tampered")));
+            let unpublished = LogId::package_log::<Sha256>(
+                &PackageName::new("tampered:log").expect("package name is valid"),
+            );
+            tampered = request
+                .leafs
+                .iter()
+                .filter(|&&index| index < request.log_length)
+                .fold(
+                    map.insert(
+                        MapKey::from(unpublished),
+                        MapLeaf {
+                            record_id: record_id.clone(),
+                        },
+                    ),
+                    |map, &index| {
+                        map.insert(
+                            MapKey::from(self.log_id(index)),
+                            MapLeaf {
+                                record_id: record_id.clone(),
+                            },
+                        )
+                    },
+                );
+            &tampered
+        } else {
+            map
+        };
+
+        let mut log_proofs = Vec::with_capacity(request.leafs.len());
+        let mut map_proofs = Vec::with_capacity(request.leafs.len());
+        for &index in &request.leafs {
+            if index >= request.log_length {
+                return Err(ProofError::LeafNotFound(index));
+            }
+
+            let log_id = self.log_id(index);
+            log_proofs.push(
+                self.log
+                    .prove_inclusion(self.leaf_index[index], request.log_length),
+            );
+            map_proofs.push(
+                map.prove(MapKey::from(log_id.clone()))
+                    .ok_or(ProofError::PackageLogNotIncluded(log_id))?,
+            );
+        }
+
+        Ok(InclusionResponse {
+            log: LogProofBundle::bundle(vec![], log_proofs, &self.log)
+                .map_err(|e| ProofError::BundleFailure(e.to_string()))?
+                .encode(),
+            map: MapProofBundle::bundle(map_proofs).encode(),
+        })
+    }
+
+    fn prove_consistency(
+        &self,
+        request: &ConsistencyRequest,
+    ) -> Result<ConsistencyResponse, ProofError> {
+        if request.to > self.records.len() {
+            return Err(ProofError::CheckpointNotFound(request.to));
+        }
+
+        // A tampered log has a leaf appended that was never published
+        let mut log = self.log.clone();
+        let to = if self.misbehaves(Misbehavior::BadConsistencyProof) {
+            log.push(&LogLeaf {
+                log_id: LogId::operator_log::<Sha256>(),
+                record_id: AnyHash::from(Hash::<Sha256>::of("tampered")).into(),
+            });
+            request.to + 1
+        } else {
+            request.to
+        };
+
+        let proof = log.prove_consistency(request.from, to);
+        Ok(ConsistencyResponse {
+            proof: LogProofBundle::bundle(vec![proof], vec![], &log)
+                .map_err(|e| ProofError::BundleFailure(e.to_string()))?
+                .encode(),
+        })
+    }
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(body: &'a [u8]) -> Result<T, ClientError> {
+    serde_json::from_slice(body).map_err(|e| ClientError::Other(e.into()))
+}
+
+fn json(status: StatusCode, body: &impl Serialize) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let body = serde_json::to_vec(body).expect("failed to serialize response");
+    transport::response(status, headers, body.into())
+}
+
+fn not_found(path: &str) -> Response {
+    json(
+        StatusCode::NOT_FOUND,
+        &serde_json::json!({
+            "status": StatusCode::NOT_FOUND.as_u16(),
+            "message": format!("the mock registry does not serve `{path}`"),
+        }),
+    )
+}
@@ -0,0 +1,172 @@
+//! Transports for sending requests to a registry.
+//!
+//! By default, the API client sends requests over HTTP with [`HttpTransport`].
+//! Other transports, such as one that dispatches requests to a registry
+//! server running in the same process, may be used with
+//! [`Client::with_transport`](crate::api::Client::with_transport).
+//!
+//! Requests pass through any [`Middleware`] added with
+//! [`Client::with_middleware`](crate::api::Client::with_middleware) before
+//! reaching the transport, allowing them to be inspected, modified, or
+//! answered without contacting the registry.
+
+use crate::api::ClientError;
+use anyhow::Result;
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, TryStreamExt};
+use reqwest::{header::HeaderMap, Body, Method, Response, StatusCode};
+use std::{pin::Pin, sync::Arc};
+use url::Url;
+
+/// Represents the body of a request sent through a [`Transport`].
+pub enum RequestBody {
+    /// The request has no body.
+    Empty,
+    /// The request body is buffered in memory.
+    Bytes(Bytes),
+    /// The request body is streamed.
+    Stream(Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>),
+}
+
+impl RequestBody {
+    /// Reads the entire body into memory.
+    pub async fn into_bytes(self) -> Result<Bytes> {
+        match self {
+            Self::Empty => Ok(Bytes::new()),
+            Self::Bytes(bytes) => Ok(bytes),
+            Self::Stream(stream) => Ok(stream
+                .try_fold(BytesMut::new(), |mut buf, bytes| async move {
+                    buf.extend_from_slice(&bytes);
+                    Ok(buf)
+                })
+                .await?
+                .freeze()),
+        }
+    }
+}
+
+/// Represents a request sent through a [`Transport`].
+pub struct TransportRequest {
+    /// The method of the request.
+    pub method: Method,
+    /// The URL of the request.
+    pub url: Url,
+    /// The headers of the request.
+    pub headers: HeaderMap,
+    /// The body of the request.
+    pub body: RequestBody,
+}
+
+impl From<reqwest::Request> for TransportRequest {
+    fn from(request: reqwest::Request) -> Self {
+        // Requests built by the API client only have buffered bodies;
+        // streamed content is sent with `RequestBody::Stream` directly
+        let body = match request.body().and_then(Body::as_bytes) {
+            Some(bytes) => RequestBody::Bytes(Bytes::copy_from_slice(bytes)),
+            None => RequestBody::Empty,
+        };
+
+        Self {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+            body,
+        }
+    }
+}
+
+/// A transport for sending requests to a registry.
+#[async_trait::async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends a request to the registry, returning its response.
+    async fn send(&self, request: TransportRequest) -> Result<Response, ClientError>;
+}
+
+/// Middleware that intercepts the requests sent by an API client.
+///
+/// Middleware may modify a request before passing it to the rest of the chain
+/// with [`Next::run`], inspect or replace the response, or respond to the
+/// request itself without running the rest of the chain.
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync {
+    /// Handles a request, usually by running the rest of the chain.
+    async fn handle(
+        &self,
+        request: TransportRequest,
+        next: Next<'_>,
+    ) -> Result<Response, ClientError>;
+}
+
+/// The rest of a middleware chain, ending with the transport.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn Transport,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(middleware: &'a [Arc<dyn Middleware>], transport: &'a dyn Transport) -> Self {
+        Self {
+            middleware,
+            transport,
+        }
+    }
+
+    /// Sends the request through the rest of the chain.
+    pub async fn run(self, request: TransportRequest) -> Result<Response, ClientError> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => {
+                middleware
+                    .handle(
+                        request,
+                        Next {
+                            middleware: rest,
+                            transport: self.transport,
+                        },
+                    )
+                    .await
+            }
+            None => self.transport.send(request).await,
+        }
+    }
+}
+
+/// A transport that sends requests to a registry over HTTP.
+#[derive(Default, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    /// Creates a new HTTP transport using the given HTTP client.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, request: TransportRequest) -> Result<Response, ClientError> {
+        let builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        let builder = match request.body {
+            RequestBody::Empty => builder,
+            RequestBody::Bytes(bytes) => builder.body(bytes),
+            RequestBody::Stream(stream) => builder.body(Body::wrap_stream(stream)),
+        };
+
+        Ok(builder.send().await?)
+    }
+}
+
+/// Creates a response received through a [`Transport`].
+///
+/// This is used by transports that do not receive responses over HTTP.
+pub fn response(status: StatusCode, headers: HeaderMap, body: Bytes) -> Response {
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
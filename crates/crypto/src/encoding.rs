@@ -32,12 +32,21 @@ where
 pub trait Signable: Encode {
     const PREFIX: &'static [u8];
 
-    fn sign(
-        &self,
-        private_key: &signing::PrivateKey,
-    ) -> Result<signing::Signature, SignatureError> {
-        let prefixed_content = [Self::PREFIX, b":", self.encode().as_slice()].concat();
-        private_key.sign(&prefixed_content)
+    /// Gets the prefixed message that is signed for these contents.
+    fn signing_message(&self) -> Vec<u8> {
+        Self::prefixed_message(&self.encode())
+    }
+
+    /// Gets the prefixed message that is signed for the given encoded contents.
+    fn prefixed_message(msg: &[u8]) -> Vec<u8> {
+        [Self::PREFIX, b":", msg].concat()
+    }
+
+    fn sign<S>(&self, signer: &S) -> Result<signing::Signature, SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        signer.sign(&self.signing_message())
     }
 
     fn verify(
@@ -45,7 +54,6 @@ pub trait Signable: Encode {
         msg: &[u8],
         signature: &signing::Signature,
     ) -> Result<(), SignatureError> {
-        let prefixed_content = [Self::PREFIX, b":", msg].concat();
-        public_key.verify(&prefixed_content, signature)
+        public_key.verify(&Self::prefixed_message(msg), signature)
     }
 }
@@ -0,0 +1,261 @@
+//! Content encryption for private registries.
+//!
+//! Content is encrypted with a random content key using AES-256-GCM.
+//!
+//! The content key is wrapped for each recipient with HPKE (RFC 9180) in
+//! base mode, using DHKEM(P-256, HKDF-SHA256), HKDF-SHA256, and AES-256-GCM,
+//! so that any recipient may decrypt the content with their private key.
+
+use crate::signing::{KeyID, PrivateKey, PublicKey};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use hpke::{
+    aead::AesGcm256, kdf::HkdfSha256, kem::DhP256HkdfSha256, Deserializable, Kem, OpModeR, OpModeS,
+    Serializable,
+};
+use rand_core::{OsRng, RngCore};
+use secrecy::zeroize::Zeroizing;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use thiserror::Error;
+
+const CONTENT_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const WRAP_INFO: &[u8] = b"warg-content-key-wrap-v2";
+
+type WrapKem = DhP256HkdfSha256;
+
+/// Represents an error from content encryption.
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    /// The key is not a recipient of the content.
+    #[error("key `{0}` is not a recipient of the encrypted content")]
+    NotRecipient(KeyID),
+    /// The encrypted content failed authentication.
+    #[error("encrypted content failed authentication")]
+    Authentication,
+    /// The wrapped content key is malformed.
+    #[error("wrapped content key for key `{0}` is malformed")]
+    InvalidWrappedKey(KeyID),
+    /// The key does not support key agreement.
+    #[error("key `{0}` does not support content encryption; only ECDSA P-256 keys do")]
+    UnsupportedKey(KeyID),
+}
+
+/// A content key wrapped for a recipient.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WrappedKey {
+    /// The key id of the recipient.
+    pub key_id: KeyID,
+    /// The HPKE encapsulated key used to wrap the content key.
+    #[serde_as(as = "Base64")]
+    pub encapsulated_key: Vec<u8>,
+    /// The wrapped content key.
+    #[serde_as(as = "Base64")]
+    pub wrapped_key: Vec<u8>,
+}
+
+/// Describes how content is encrypted and who may decrypt it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentEncryption {
+    /// The content key wrapped for each recipient.
+    pub recipients: Vec<WrappedKey>,
+}
+
+impl ContentEncryption {
+    /// Encrypts content for the given recipients.
+    ///
+    /// Returns the ciphertext and the encryption information needed by the
+    /// recipients to decrypt it.
+    ///
+    /// Every recipient must have an ECDSA P-256 key.
+    pub fn encrypt<'a>(
+        content: &[u8],
+        recipients: impl IntoIterator<Item = &'a PublicKey>,
+    ) -> Result<(Vec<u8>, Self), EncryptionError> {
+        let mut content_key = Zeroizing::new([0u8; CONTENT_KEY_LEN]);
+        OsRng.fill_bytes(content_key.as_mut());
+        let ciphertext = seal(&content_key, content);
+
+        let recipients = recipients
+            .into_iter()
+            .map(|recipient| {
+                let key_id = recipient.fingerprint();
+                let PublicKey::EcdsaP256(key) = recipient else {
+                    return Err(EncryptionError::UnsupportedKey(key_id));
+                };
+                let key =
+                    <WrapKem as Kem>::PublicKey::from_bytes(key.to_encoded_point(false).as_bytes())
+                        .map_err(|_| EncryptionError::UnsupportedKey(key_id.clone()))?;
+
+                let (encapsulated_key, wrapped_key) =
+                    hpke::single_shot_seal::<AesGcm256, HkdfSha256, WrapKem, _>(
+                        &OpModeS::Base,
+                        &key,
+                        WRAP_INFO,
+                        content_key.as_ref(),
+                        key_id.as_str().as_bytes(),
+                        &mut OsRng,
+                    )
+                    // Sealing with a valid P-256 key does not fail
+                    .map_err(|_| EncryptionError::UnsupportedKey(key_id.clone()))?;
+
+                Ok(WrappedKey {
+                    key_id,
+                    encapsulated_key: encapsulated_key.to_bytes().to_vec(),
+                    wrapped_key,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok((ciphertext, Self { recipients }))
+    }
+
+    /// Decrypts content with the private key of a recipient.
+    ///
+    /// The ciphertext is authenticated before it is decrypted.
+    pub fn decrypt(
+        &self,
+        ciphertext: &[u8],
+        private_key: &PrivateKey,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let key_id = private_key.public_key().fingerprint();
+        let recipient = self
+            .recipients
+            .iter()
+            .find(|r| r.key_id == key_id)
+            .ok_or_else(|| EncryptionError::NotRecipient(key_id.clone()))?;
+
+        let secret = private_key
+            .p256_secret_bytes()
+            .ok_or_else(|| EncryptionError::UnsupportedKey(key_id.clone()))?;
+        let key = <WrapKem as Kem>::PrivateKey::from_bytes(secret.as_ref())
+            .map_err(|_| EncryptionError::UnsupportedKey(key_id.clone()))?;
+        let encapsulated_key =
+            <WrapKem as Kem>::EncappedKey::from_bytes(&recipient.encapsulated_key)
+                .map_err(|_| EncryptionError::InvalidWrappedKey(key_id.clone()))?;
+
+        let content_key = Zeroizing::new(
+            hpke::single_shot_open::<AesGcm256, HkdfSha256, WrapKem>(
+                &OpModeR::Base,
+                &key,
+                &encapsulated_key,
+                WRAP_INFO,
+                &recipient.wrapped_key,
+                key_id.as_str().as_bytes(),
+            )
+            .map_err(|_| EncryptionError::InvalidWrappedKey(key_id.clone()))?,
+        );
+        let content_key: &[u8; CONTENT_KEY_LEN] = content_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| EncryptionError::InvalidWrappedKey(key_id))?;
+
+        open(content_key, ciphertext)
+    }
+}
+
+/// Encrypts and authenticates the given plaintext with AES-256-GCM.
+///
+/// The output is the random nonce, followed by the ciphertext and the tag.
+pub(crate) fn seal(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut output = Vec::with_capacity(NONCE_LEN + plaintext.len() + TAG_LEN);
+    output.extend_from_slice(&nonce);
+    // Encrypting in memory fails only for plaintexts beyond the GCM limit
+    output.extend(
+        cipher
+            .encrypt(&nonce, plaintext)
+            .expect("plaintext should be within the AES-GCM length limit"),
+    );
+    output
+}
+
+/// Authenticates and decrypts output from [`seal`].
+pub(crate) fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        return Err(EncryptionError::Authentication);
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| EncryptionError::Authentication)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::generate_p256_pair;
+
+    #[test]
+    fn test_round_trip() {
+        let (alice_pub, alice) = generate_p256_pair();
+        let (bob_pub, bob) = generate_p256_pair();
+        let (_, eve) = generate_p256_pair();
+
+        let content = b"proprietary component".repeat(10);
+        let (ciphertext, encryption) =
+            ContentEncryption::encrypt(&content, [&alice_pub, &bob_pub]).unwrap();
+        assert_ne!(
+            &ciphertext[NONCE_LEN..NONCE_LEN + content.len()],
+            content.as_slice()
+        );
+        assert_eq!(encryption.recipients.len(), 2);
+
+        assert_eq!(encryption.decrypt(&ciphertext, &alice).unwrap(), content);
+        assert_eq!(encryption.decrypt(&ciphertext, &bob).unwrap(), content);
+        assert!(matches!(
+            encryption.decrypt(&ciphertext, &eve),
+            Err(EncryptionError::NotRecipient(_))
+        ));
+
+        let json = serde_json::to_string(&encryption).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ContentEncryption>(&json).unwrap(),
+            encryption
+        );
+    }
+
+    #[test]
+    fn test_tampering_fails_authentication() {
+        let (public_key, private_key) = generate_p256_pair();
+        let (mut ciphertext, mut encryption) =
+            ContentEncryption::encrypt(b"secret", [&public_key]).unwrap();
+
+        ciphertext[NONCE_LEN] ^= 1;
+        assert!(matches!(
+            encryption.decrypt(&ciphertext, &private_key),
+            Err(EncryptionError::Authentication)
+        ));
+        ciphertext[NONCE_LEN] ^= 1;
+
+        assert!(matches!(
+            encryption.decrypt(&ciphertext[..TAG_LEN], &private_key),
+            Err(EncryptionError::Authentication)
+        ));
+
+        encryption.recipients[0].wrapped_key[0] ^= 1;
+        assert!(matches!(
+            encryption.decrypt(&ciphertext, &private_key),
+            Err(EncryptionError::InvalidWrappedKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_ed25519_recipient_is_unsupported() {
+        let (public_key, _) = crate::signing::generate_ed25519_pair();
+        assert!(matches!(
+            ContentEncryption::encrypt(b"secret", [&public_key]),
+            Err(EncryptionError::UnsupportedKey(_))
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use super::{Digest, HashAlgorithm, Sha256};
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use std::{fmt, ops::Deref, str::FromStr};
+use std::{cmp::Ordering, fmt, str::FromStr};
 use thiserror::Error;
 
 pub enum Hasher {
@@ -16,11 +16,9 @@ impl Hasher {
     }
 
     pub fn finalize(self) -> AnyHash {
-        let (algo, bytes) = match self {
-            Self::Sha256(d) => (HashAlgorithm::Sha256, d.finalize().deref().into()),
-        };
-
-        AnyHash { algo, bytes }
+        match self {
+            Self::Sha256(d) => AnyHash::new(HashAlgorithm::Sha256, d.finalize()),
+        }
     }
 }
 
@@ -32,30 +30,49 @@ impl HashAlgorithm {
     }
 
     pub fn digest(&self, content_bytes: &[u8]) -> AnyHash {
-        let hash_bytes: Vec<u8> = match self {
-            HashAlgorithm::Sha256 => {
-                let mut d = Sha256::new();
-                d.update(content_bytes);
-                d.finalize().deref().into()
-            }
-        };
-
-        AnyHash {
-            algo: *self,
-            bytes: hash_bytes,
-        }
+        let mut hasher = self.hasher();
+        hasher.update(content_bytes);
+        hasher.finalize()
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+/// The largest digest, in bytes, an [`AnyHash`] can hold.
+///
+/// This is the digest size of the largest supported hash algorithm.
+pub const MAX_DIGEST_SIZE: usize = 32;
+
+/// A digest of any supported hash algorithm.
+///
+/// The digest is stored inline, so hashes can be copied without allocating.
+#[derive(Clone, Copy)]
 pub struct AnyHash {
     pub(crate) algo: HashAlgorithm,
-    pub(crate) bytes: Vec<u8>,
+    len: u8,
+    digest: [u8; MAX_DIGEST_SIZE],
 }
 
 impl AnyHash {
-    pub fn new(algo: HashAlgorithm, bytes: Vec<u8>) -> AnyHash {
-        AnyHash { algo, bytes }
+    /// Creates a hash from the given digest bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the digest is longer than [`MAX_DIGEST_SIZE`] bytes.
+    pub fn new(algo: HashAlgorithm, bytes: impl AsRef<[u8]>) -> AnyHash {
+        Self::try_new(algo, bytes.as_ref()).expect("digest is too long")
+    }
+
+    fn try_new(algo: HashAlgorithm, bytes: &[u8]) -> Result<AnyHash, AnyHashError> {
+        if bytes.len() > MAX_DIGEST_SIZE {
+            return Err(AnyHashError::DigestTooLong(bytes.len()));
+        }
+
+        let mut digest = [0; MAX_DIGEST_SIZE];
+        digest[..bytes.len()].copy_from_slice(bytes);
+        Ok(AnyHash {
+            algo,
+            len: bytes.len() as u8,
+            digest,
+        })
     }
 
     pub fn algorithm(&self) -> HashAlgorithm {
@@ -63,19 +80,46 @@ impl AnyHash {
     }
 
     pub fn bytes(&self) -> &[u8] {
-        &self.bytes
+        &self.digest[..self.len as usize]
+    }
+}
+
+impl PartialEq for AnyHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.algo == other.algo && self.bytes() == other.bytes()
+    }
+}
+
+impl Eq for AnyHash {}
+
+impl PartialOrd for AnyHash {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AnyHash {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.algo, self.bytes()).cmp(&(other.algo, other.bytes()))
+    }
+}
+
+impl std::hash::Hash for AnyHash {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.algo.hash(state);
+        self.bytes().hash(state);
     }
 }
 
 impl fmt::Display for AnyHash {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}:{}", self.algo, hex::encode(self.bytes.as_slice()))
+        write!(f, "{}:{}", self.algo, hex::encode(self.bytes()))
     }
 }
 
 impl fmt::Debug for AnyHash {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}:{}", self.algo, hex::encode(self.bytes.as_slice()))
+        write!(f, "{}:{}", self.algo, hex::encode(self.bytes()))
     }
 }
 
@@ -94,7 +138,7 @@ impl FromStr for AnyHash {
         let algo = algo_part.parse::<HashAlgorithm>()?;
         let bytes = hex::decode(bytes_part)?;
 
-        Ok(AnyHash { algo, bytes })
+        AnyHash::try_new(algo, &bytes)
     }
 }
 
@@ -111,6 +155,9 @@ pub enum AnyHashError {
 
     #[error("hexadecimal decode failed: {0}")]
     InvalidHex(#[from] hex::FromHexError),
+
+    #[error("digest of {0} bytes is longer than the maximum of {MAX_DIGEST_SIZE} bytes")]
+    DigestTooLong(usize),
 }
 
 impl Serialize for AnyHash {
@@ -153,6 +200,28 @@ mod tests {
         assert!(digest_str.parse::<AnyHash>().is_err());
     }
 
+    #[test]
+    fn test_labeled_digest_parse_rejects_long_digest() {
+        let digest_str = format!("sha256:{}", "ab".repeat(MAX_DIGEST_SIZE + 1));
+        assert!(matches!(
+            digest_str.parse::<AnyHash>(),
+            Err(AnyHashError::DigestTooLong(len)) if len == MAX_DIGEST_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn test_digest_is_stored_inline() {
+        assert_eq!(
+            std::mem::size_of::<AnyHash>(),
+            std::mem::size_of::<HashAlgorithm>() + 1 + MAX_DIGEST_SIZE
+        );
+
+        let hash = HashAlgorithm::Sha256.digest(b"content");
+        let copy = hash;
+        assert_eq!(copy, hash);
+        assert_eq!(copy.bytes().len(), 32);
+    }
+
     #[test]
     fn test_labeled_digest_roundtrip() {
         let input = "sha256:7d38b5cd25a2baf85ad3bb5b9311383e671a8a142eb302b324d4a5fba8748c69";
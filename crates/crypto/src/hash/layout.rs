@@ -0,0 +1,250 @@
+use super::AnyHash;
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Represents how a file system storage arranges the files of the objects it
+/// stores, keyed by hash.
+///
+/// A flat layout keeps all files of a hash algorithm in one directory, which
+/// becomes slow on most file systems beyond a few hundred thousand files; the
+/// other layouts spread files across subdirectories.
+///
+/// Files are located regardless of the layout they were stored under, so
+/// the layout of a storage directory may change once files are stored; new
+/// files are stored under the new layout.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageLayout {
+    /// Files are stored at `<algo>/<hex>`.
+    #[default]
+    Flat,
+    /// Files are stored at `<algo>/<hex[0..2]>/<hex[2..4]>/<hex>`.
+    HashPrefix,
+    /// Files are stored at `<algo>/<year>/<month>/<day>/<hex>` by the UTC
+    /// date they were first stored.
+    ///
+    /// Locating a file requires searching the date directories, most
+    /// recent first.
+    Date,
+}
+
+impl StorageLayout {
+    const ALL: [Self; 3] = [Self::Flat, Self::HashPrefix, Self::Date];
+
+    /// Gets the path within the given base directory to store a new file
+    /// for the given hash at the given time.
+    pub fn path(&self, base_dir: &Path, hash: &AnyHash, time: SystemTime) -> PathBuf {
+        let dir = base_dir.join(hash.algorithm().to_string());
+        let hex = hex::encode(hash.bytes());
+        match self {
+            Self::Flat => dir.join(hex),
+            Self::HashPrefix => dir.join(&hex[..2]).join(&hex[2..4]).join(hex),
+            Self::Date => {
+                let (year, month, day) = civil_date(time);
+                dir.join(format!("{year:04}"))
+                    .join(format!("{month:02}"))
+                    .join(format!("{day:02}"))
+                    .join(hex)
+            }
+        }
+    }
+
+    /// Locates the existing file for the given hash within the given base
+    /// directory.
+    ///
+    /// The file is searched for under this layout first, then under the
+    /// other layouts.
+    ///
+    /// Returns `None` if there is no file for the hash.
+    pub fn locate(&self, base_dir: &Path, hash: &AnyHash) -> Option<PathBuf> {
+        std::iter::once(*self)
+            .chain(Self::ALL.into_iter().filter(|layout| layout != self))
+            .find_map(|layout| layout.locate_in(base_dir, hash))
+    }
+
+    /// Gets the path of the file for the given hash, either existing or to
+    /// store a new file at the current time.
+    pub fn locate_or_path(&self, base_dir: &Path, hash: &AnyHash) -> PathBuf {
+        self.locate(base_dir, hash)
+            .unwrap_or_else(|| self.path(base_dir, hash, SystemTime::now()))
+    }
+
+    /// Locates the existing file for the given hash stored under this layout.
+    fn locate_in(&self, base_dir: &Path, hash: &AnyHash) -> Option<PathBuf> {
+        match self {
+            Self::Flat | Self::HashPrefix => {
+                Some(self.path(base_dir, hash, UNIX_EPOCH)).filter(|p| p.is_file())
+            }
+            Self::Date => {
+                // Years are probed by name rather than listed, as the
+                // directory also holds the files of the flat layout
+                let hex = hex::encode(hash.bytes());
+                let dir = base_dir.join(hash.algorithm().to_string());
+                let (current, _, _) = civil_date(SystemTime::now());
+                for year in (1970..=current + 1).rev() {
+                    let year = dir.join(format!("{year:04}"));
+                    if !year.is_dir() {
+                        continue;
+                    }
+
+                    for month in subdirs_descending(&year) {
+                        for day in subdirs_descending(&month) {
+                            let path = day.join(&hex);
+                            if path.is_file() {
+                                return Some(path);
+                            }
+                        }
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Display for StorageLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flat => write!(f, "flat"),
+            Self::HashPrefix => write!(f, "hash-prefix"),
+            Self::Date => write!(f, "date"),
+        }
+    }
+}
+
+impl FromStr for StorageLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Self::Flat),
+            "hash-prefix" => Ok(Self::HashPrefix),
+            "date" => Ok(Self::Date),
+            _ => bail!("invalid storage layout `{s}`: expected `flat`, `hash-prefix` or `date`"),
+        }
+    }
+}
+
+/// Lists the subdirectories of a directory in descending order of name.
+fn subdirs_descending(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort_by(|a, b| b.cmp(a));
+    dirs
+}
+
+/// Converts a time to a UTC (year, month, day) date, as used by the date
+/// layout.
+pub fn civil_date(time: SystemTime) -> (i64, u32, u32) {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default() as i64;
+
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{Hash, Sha256};
+    use std::time::Duration;
+
+    #[test]
+    fn it_computes_paths() {
+        let hash: AnyHash = Hash::<Sha256>::of("content").into();
+        let hex = hex::encode(hash.bytes());
+        let base = Path::new("base");
+        // 2024-02-29T12:00:00Z
+        let time = UNIX_EPOCH + Duration::from_secs(1709208000);
+
+        assert_eq!(
+            StorageLayout::Flat.path(base, &hash, time),
+            base.join("sha256").join(&hex)
+        );
+        assert_eq!(
+            StorageLayout::HashPrefix.path(base, &hash, time),
+            base.join("sha256")
+                .join(&hex[..2])
+                .join(&hex[2..4])
+                .join(&hex)
+        );
+        assert_eq!(
+            StorageLayout::Date.path(base, &hash, time),
+            base.join("sha256/2024/02/29").join(&hex)
+        );
+        assert_eq!(civil_date(UNIX_EPOCH), (1970, 1, 1));
+    }
+
+    #[test]
+    fn it_locates_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let stored: AnyHash = Hash::<Sha256>::of("stored").into();
+        let missing: AnyHash = Hash::<Sha256>::of("missing").into();
+
+        for layout in [
+            StorageLayout::Flat,
+            StorageLayout::HashPrefix,
+            StorageLayout::Date,
+        ] {
+            let base = dir.path().join(layout.to_string());
+            let path = layout.path(
+                &base,
+                &stored,
+                UNIX_EPOCH + Duration::from_secs(86400 * 400),
+            );
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "stored").unwrap();
+
+            assert_eq!(layout.locate(&base, &stored), Some(path.clone()));
+            assert_eq!(layout.locate_or_path(&base, &stored), path);
+            assert_eq!(layout.locate(&base, &missing), None);
+            assert_eq!(layout.to_string().parse::<StorageLayout>().unwrap(), layout);
+        }
+    }
+
+    #[test]
+    fn it_locates_files_after_a_layout_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let time = UNIX_EPOCH + Duration::from_secs(86400 * 400);
+
+        let mut stored = Vec::new();
+        for layout in StorageLayout::ALL {
+            let hash: AnyHash = Hash::<Sha256>::of(layout.to_string().as_str()).into();
+            let path = layout.path(base, &hash, time);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "stored").unwrap();
+            stored.push((hash, path));
+        }
+
+        // Files stored under any layout are found by every layout
+        for layout in StorageLayout::ALL {
+            for (hash, path) in &stored {
+                assert_eq!(layout.locate(base, hash).as_ref(), Some(path));
+                assert_eq!(&layout.locate_or_path(base, hash), path);
+            }
+        }
+    }
+}
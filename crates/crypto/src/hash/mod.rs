@@ -5,10 +5,16 @@ use std::{fmt, str::FromStr};
 use thiserror::Error;
 
 mod dynamic;
+mod layout;
+#[cfg(feature = "multihash")]
+mod multiformats;
 mod r#static;
 
 pub use digest::{Digest, Output};
-pub use dynamic::{AnyHash, AnyHashError};
+pub use dynamic::{AnyHash, AnyHashError, MAX_DIGEST_SIZE};
+pub use layout::{civil_date, StorageLayout};
+#[cfg(feature = "multihash")]
+pub use multiformats::{Multibase, MultihashError};
 pub use r#static::Hash;
 pub use sha2::Sha256;
 
@@ -107,10 +113,7 @@ impl<D: SupportedDigest> From<Hash<D>> for AnyHash {
 
 impl<D: SupportedDigest> From<&Hash<D>> for AnyHash {
     fn from(value: &Hash<D>) -> Self {
-        AnyHash {
-            algo: D::ALGORITHM,
-            bytes: value.digest.to_vec(),
-        }
+        AnyHash::new(D::ALGORITHM, &value.digest)
     }
 }
 
@@ -135,8 +138,8 @@ impl<D: SupportedDigest> TryFrom<AnyHash> for Hash<D> {
 
     fn try_from(value: AnyHash) -> Result<Self, Self::Error> {
         if value.algorithm() == D::ALGORITHM {
-            let len = value.bytes.len();
-            match Hash::try_from(value.bytes) {
+            let len = value.bytes().len();
+            match Hash::try_from(value.bytes()) {
                 Ok(hash) => Ok(hash),
                 Err(IncorrectLengthError) => Err(HashError::IncorrectLength {
                     expected: <D as Digest>::output_size(),
@@ -0,0 +1,411 @@
+//! Encoding of hashes as [multihashes] and [multibase] strings.
+//!
+//! This allows content hashes to interoperate with IPFS/IPLD-based content
+//! stores, which identify content by multihash.
+//!
+//! [multihashes]: https://github.com/multiformats/multihash
+//! [multibase]: https://github.com/multiformats/multibase
+
+use super::{AnyHash, Hash, HashAlgorithm, HashError, SupportedDigest};
+use base64::{
+    engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// The multihash code of SHA-256.
+const SHA2_256_CODE: u64 = 0x12;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Represents an error decoding a multihash or multibase string.
+#[derive(Error, Debug)]
+pub enum MultihashError {
+    #[error("unsupported multihash code {0:#x}")]
+    UnsupportedCode(u64),
+
+    #[error("invalid multihash varint")]
+    InvalidVarint,
+
+    #[error("multihash digest length {actual} does not match declared length {declared}")]
+    IncorrectLength { declared: u64, actual: usize },
+
+    #[error("unsupported multibase prefix `{0}`")]
+    UnsupportedBase(char),
+
+    #[error("empty multibase string")]
+    EmptyMultibase,
+
+    #[error("invalid {0} encoding")]
+    InvalidEncoding(Multibase),
+
+    #[error(transparent)]
+    Hash(#[from] HashError),
+}
+
+/// The multibase encodings supported for hashes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Multibase {
+    /// Lowercase hexadecimal (prefix `f`).
+    Base16,
+    /// Lowercase RFC 4648 base32 without padding (prefix `b`).
+    Base32,
+    /// Bitcoin base58 (prefix `z`).
+    Base58Btc,
+    /// RFC 4648 base64 without padding (prefix `m`).
+    Base64,
+    /// RFC 4648 URL-safe base64 without padding (prefix `u`).
+    Base64Url,
+}
+
+impl Multibase {
+    /// Gets the prefix character of the encoding.
+    pub fn prefix(&self) -> char {
+        match self {
+            Self::Base16 => 'f',
+            Self::Base32 => 'b',
+            Self::Base58Btc => 'z',
+            Self::Base64 => 'm',
+            Self::Base64Url => 'u',
+        }
+    }
+
+    /// Gets the encoding with the given prefix character.
+    pub fn from_prefix(prefix: char) -> Result<Self, MultihashError> {
+        match prefix {
+            'f' => Ok(Self::Base16),
+            'b' => Ok(Self::Base32),
+            'z' => Ok(Self::Base58Btc),
+            'm' => Ok(Self::Base64),
+            'u' => Ok(Self::Base64Url),
+            _ => Err(MultihashError::UnsupportedBase(prefix)),
+        }
+    }
+
+    /// Encodes bytes, including the prefix character.
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        let encoded = match self {
+            Self::Base16 => hex::encode(bytes),
+            Self::Base32 => base32_encode(bytes),
+            Self::Base58Btc => base58_encode(bytes),
+            Self::Base64 => STANDARD_NO_PAD.encode(bytes),
+            Self::Base64Url => URL_SAFE_NO_PAD.encode(bytes),
+        };
+        format!("{prefix}{encoded}", prefix = self.prefix())
+    }
+
+    /// Decodes a string that begins with a prefix character.
+    pub fn decode(s: &str) -> Result<(Self, Vec<u8>), MultihashError> {
+        let mut chars = s.chars();
+        let base = Self::from_prefix(chars.next().ok_or(MultihashError::EmptyMultibase)?)?;
+        let encoded = chars.as_str();
+        let bytes = match base {
+            Self::Base16 => hex::decode(encoded).ok(),
+            Self::Base32 => base32_decode(encoded),
+            Self::Base58Btc => base58_decode(encoded),
+            Self::Base64 => STANDARD_NO_PAD.decode(encoded).ok(),
+            Self::Base64Url => URL_SAFE_NO_PAD.decode(encoded).ok(),
+        }
+        .ok_or(MultihashError::InvalidEncoding(base))?;
+
+        Ok((base, bytes))
+    }
+}
+
+impl fmt::Display for Multibase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base16 => write!(f, "base16"),
+            Self::Base32 => write!(f, "base32"),
+            Self::Base58Btc => write!(f, "base58btc"),
+            Self::Base64 => write!(f, "base64"),
+            Self::Base64Url => write!(f, "base64url"),
+        }
+    }
+}
+
+impl FromStr for Multibase {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base16" => Ok(Self::Base16),
+            "base32" => Ok(Self::Base32),
+            "base58btc" => Ok(Self::Base58Btc),
+            "base64" => Ok(Self::Base64),
+            "base64url" => Ok(Self::Base64Url),
+            _ => Err(anyhow::Error::msg(format!("Illegal multibase '{}'", s))),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// Gets the multihash code of the algorithm.
+    pub fn multihash_code(&self) -> u64 {
+        match self {
+            HashAlgorithm::Sha256 => SHA2_256_CODE,
+        }
+    }
+
+    /// Gets the algorithm with the given multihash code.
+    pub fn from_multihash_code(code: u64) -> Result<Self, MultihashError> {
+        match code {
+            SHA2_256_CODE => Ok(HashAlgorithm::Sha256),
+            _ => Err(MultihashError::UnsupportedCode(code)),
+        }
+    }
+}
+
+impl AnyHash {
+    /// Encodes the hash as a multihash.
+    pub fn to_multihash(&self) -> Vec<u8> {
+        let digest = self.bytes();
+        let mut bytes = Vec::with_capacity(digest.len() + 4);
+        leb128::write::unsigned(&mut bytes, self.algo.multihash_code()).unwrap();
+        leb128::write::unsigned(&mut bytes, digest.len() as u64).unwrap();
+        bytes.extend_from_slice(digest);
+        bytes
+    }
+
+    /// Decodes a hash from a multihash.
+    pub fn from_multihash(mut bytes: &[u8]) -> Result<Self, MultihashError> {
+        let code = read_varint(&mut bytes)?;
+        let algo = HashAlgorithm::from_multihash_code(code)?;
+        let declared = read_varint(&mut bytes)?;
+        if declared != bytes.len() as u64 {
+            return Err(MultihashError::IncorrectLength {
+                declared,
+                actual: bytes.len(),
+            });
+        }
+
+        let expected = algo.digest(&[]).bytes().len();
+        if bytes.len() != expected {
+            return Err(HashError::IncorrectLength {
+                expected,
+                algo,
+                actual: bytes.len(),
+            }
+            .into());
+        }
+
+        Ok(AnyHash::new(algo, bytes))
+    }
+
+    /// Encodes the hash as a multihash in the given multibase encoding.
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        base.encode(&self.to_multihash())
+    }
+
+    /// Decodes a hash from a multihash in any supported multibase encoding.
+    pub fn from_multibase(s: &str) -> Result<Self, MultihashError> {
+        let (_, bytes) = Multibase::decode(s)?;
+        Self::from_multihash(&bytes)
+    }
+}
+
+impl<D: SupportedDigest> Hash<D> {
+    /// Encodes the hash as a multihash.
+    pub fn to_multihash(&self) -> Vec<u8> {
+        AnyHash::from(self).to_multihash()
+    }
+
+    /// Decodes a hash from a multihash.
+    pub fn from_multihash(bytes: &[u8]) -> Result<Self, MultihashError> {
+        Ok(AnyHash::from_multihash(bytes)?.try_into()?)
+    }
+
+    /// Encodes the hash as a multihash in the given multibase encoding.
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        AnyHash::from(self).to_multibase(base)
+    }
+
+    /// Decodes a hash from a multihash in any supported multibase encoding.
+    pub fn from_multibase(s: &str) -> Result<Self, MultihashError> {
+        Ok(AnyHash::from_multibase(s)?.try_into()?)
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, MultihashError> {
+    leb128::read::unsigned(bytes).map_err(|_| MultihashError::InvalidVarint)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u16;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    // Any leftover bits are padding and must be zero
+    (buffer & ((1 << bits) - 1) == 0).then_some(decoded)
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+
+    // Little-endian base58 digits of the number represented by the bytes
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in &bytes[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    std::iter::repeat('1')
+        .take(zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|d| BASE58_ALPHABET[*d as usize] as char),
+        )
+        .collect()
+}
+
+fn base58_decode(s: &str) -> Option<Vec<u8>> {
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+
+    // Little-endian bytes of the number represented by the digits
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+    for c in s[zeros..].bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    Some(
+        std::iter::repeat(0)
+            .take(zeros)
+            .chain(bytes.into_iter().rev())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Sha256;
+
+    const BASES: [Multibase; 5] = [
+        Multibase::Base16,
+        Multibase::Base32,
+        Multibase::Base58Btc,
+        Multibase::Base64,
+        Multibase::Base64Url,
+    ];
+
+    #[test]
+    fn test_multihash_roundtrip() {
+        let hash = HashAlgorithm::Sha256.digest(b"hello world");
+        let multihash = hash.to_multihash();
+        assert_eq!(&multihash[..2], &[0x12, 0x20]);
+        assert_eq!(&multihash[2..], hash.bytes());
+        assert_eq!(AnyHash::from_multihash(&multihash).unwrap(), hash);
+
+        assert!(matches!(
+            AnyHash::from_multihash(&[0x13, 0x20]),
+            Err(MultihashError::UnsupportedCode(0x13))
+        ));
+        assert!(matches!(
+            AnyHash::from_multihash(&multihash[..multihash.len() - 1]),
+            Err(MultihashError::IncorrectLength { declared: 32, .. })
+        ));
+    }
+
+    #[test]
+    fn test_multibase_roundtrip() {
+        let hash = Hash::<Sha256>::of("hello world");
+        for base in BASES {
+            let encoded = hash.to_multibase(base);
+            assert!(encoded.starts_with(base.prefix()));
+            assert_eq!(Hash::<Sha256>::from_multibase(&encoded).unwrap(), hash);
+            assert_eq!(base.to_string().parse::<Multibase>().unwrap(), base);
+        }
+
+        // SHA-256 multihashes are the familiar `Qm...` identifiers in base58btc
+        assert!(hash.to_multibase(Multibase::Base58Btc).starts_with("zQm"));
+        assert_eq!(
+            hash.to_multibase(Multibase::Base16),
+            format!("f1220{}", hex::encode(hash.bytes()))
+        );
+
+        assert!(matches!(
+            AnyHash::from_multibase("x1220"),
+            Err(MultihashError::UnsupportedBase('x'))
+        ));
+        assert!(matches!(
+            AnyHash::from_multibase("z0OIl"),
+            Err(MultihashError::InvalidEncoding(Multibase::Base58Btc))
+        ));
+    }
+
+    #[test]
+    fn test_base_encodings() {
+        // RFC 4648 test vectors
+        for (input, expected) in [
+            ("", ""),
+            ("f", "my"),
+            ("fo", "mzxq"),
+            ("foo", "mzxw6"),
+            ("foob", "mzxw6yq"),
+            ("fooba", "mzxw6ytb"),
+            ("foobar", "mzxw6ytboi"),
+        ] {
+            assert_eq!(base32_encode(input.as_bytes()), expected);
+            assert_eq!(base32_decode(expected).unwrap(), input.as_bytes());
+        }
+
+        for (input, expected) in [
+            (&b""[..], ""),
+            (&b"\0\0hello"[..], "11Cn8eVZg"),
+            (&b"hello world"[..], "StV1DL6CwTryKyV"),
+        ] {
+            assert_eq!(base58_encode(input), expected);
+            assert_eq!(base58_decode(expected).unwrap(), input);
+        }
+    }
+}
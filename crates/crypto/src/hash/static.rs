@@ -81,6 +81,9 @@ impl<D: SupportedDigest> Clone for Hash<D> {
     }
 }
 
+// Digest outputs are fixed-size arrays, which are `Copy` for every supported digest.
+impl<D: SupportedDigest> Copy for Hash<D> where Output<D>: Copy {}
+
 impl<D: SupportedDigest> Eq for Hash<D> {}
 impl<D: SupportedDigest> PartialEq for Hash<D> {
     fn eq(&self, other: &Self) -> bool {
@@ -121,12 +124,26 @@ impl<D: SupportedDigest> TryFrom<Vec<u8>> for Hash<D> {
 
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         let hash = Hash {
-            digest: GenericArray::from_exact_iter(value.into_iter()).ok_or(IncorrectLengthError)?,
+            digest: GenericArray::from_exact_iter(value).ok_or(IncorrectLengthError)?,
         };
         Ok(hash)
     }
 }
 
+impl<D: SupportedDigest> TryFrom<&[u8]> for Hash<D> {
+    type Error = IncorrectLengthError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != <D as digest::Digest>::output_size() {
+            return Err(IncorrectLengthError);
+        }
+
+        Ok(Hash {
+            digest: GenericArray::clone_from_slice(value),
+        })
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 #[error("the provided vector was not the correct length")]
 pub struct IncorrectLengthError;
@@ -1,6 +1,8 @@
 mod encoding;
+pub mod encryption;
 pub mod hash;
 pub mod signing;
+pub mod timestamp;
 
 /// Module for prefix encoding
 pub mod prefix;
@@ -41,4 +41,9 @@ where
         self.visit_unsigned(s.len() as u64);
         self.visit_str_raw(s);
     }
+
+    pub fn visit_bytes(&mut self, bytes: &[u8]) {
+        self.visit_unsigned(bytes.len() as u64);
+        self.inner.visit_bytes(bytes);
+    }
 }
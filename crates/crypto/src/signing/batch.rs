@@ -0,0 +1,288 @@
+use super::{PublicKey, Signature};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// A signature to verify as part of a batch.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchItem<'a> {
+    /// The key expected to have made the signature
+    pub key: &'a PublicKey,
+    /// The signed message
+    pub msg: &'a [u8],
+    /// The signature over the message
+    pub signature: &'a Signature,
+}
+
+impl<'a> BatchItem<'a> {
+    /// Creates a new batch item.
+    pub fn new(key: &'a PublicKey, msg: &'a [u8], signature: &'a Signature) -> Self {
+        Self {
+            key,
+            msg,
+            signature,
+        }
+    }
+}
+
+/// The error returned when signatures of a batch fail to verify.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{count} signature(s) of the batch failed to verify", count = .failed.len())]
+pub struct BatchVerifyError {
+    failed: Vec<usize>,
+}
+
+impl BatchVerifyError {
+    /// Gets the indexes of the items that failed to verify, in ascending order.
+    pub fn failed(&self) -> &[usize] {
+        &self.failed
+    }
+}
+
+/// The indexes of the items of a batch by the same key, by message and
+/// signature.
+type Repeats<'a> = BTreeMap<(&'a [u8], Vec<u8>), Vec<usize>>;
+
+/// Verifies a batch of signatures.
+///
+/// Items are grouped by key, and an item repeated in the batch is verified
+/// once. Distinct Ed25519 signatures are verified together with the Ed25519
+/// batch verification equation, falling back to verifying them individually
+/// only to find the failing items if the batch fails. ECDSA and RSA have no
+/// batch verification equation, so P-256 and RSA-PSS signatures are verified
+/// individually.
+///
+/// Unlike strict individual verification, the Ed25519 batch equation does
+/// not check that the `R` of a signature is canonically encoded, and it may
+/// accept a signature whose `R` has a torsion component, as each signature's
+/// terms are scaled by a random coefficient that may cancel the torsion.
+/// Only signatures with a canonically encoded `R` of prime order, made by
+/// keys of prime order, are therefore batched; the equations agree on these,
+/// so the result is the same as verifying each item with
+/// [`PublicKey::verify`]. Other Ed25519 signatures, including those by weak
+/// keys, are verified individually.
+///
+/// Every item is verified even if an earlier item fails; the error lists
+/// each failing item.
+pub fn batch_verify<'a>(
+    items: impl IntoIterator<Item = BatchItem<'a>>,
+) -> Result<(), BatchVerifyError> {
+    let items = items.into_iter().collect::<Vec<_>>();
+
+    // Group the indexes of repeated items so each distinct item is verified once
+    let mut groups: BTreeMap<&PublicKey, Repeats> = BTreeMap::new();
+    for (index, item) in items.iter().enumerate() {
+        groups
+            .entry(item.key)
+            .or_default()
+            .entry((item.msg, item.signature.bytes()))
+            .or_default()
+            .push(index);
+    }
+
+    let mut failed = Vec::new();
+    let mut ed25519 = Vec::new();
+    for (key, repeats) in groups {
+        let batchable_key = match key {
+            PublicKey::Ed25519(key) => is_prime_order(CompressedEdwardsY(key.to_bytes())),
+            _ => false,
+        };
+
+        for indexes in repeats.into_values() {
+            let item = &items[indexes[0]];
+            match (item.key, item.signature) {
+                (PublicKey::Ed25519(key), Signature::Ed25519(signature))
+                    if batchable_key
+                        && is_prime_order(CompressedEdwardsY(*signature.r_bytes())) =>
+                {
+                    ed25519.push((key, signature, indexes))
+                }
+                _ => {
+                    if item.key.verify(item.msg, item.signature).is_err() {
+                        failed.extend(indexes);
+                    }
+                }
+            }
+        }
+    }
+
+    if !ed25519.is_empty() {
+        let messages = ed25519
+            .iter()
+            .map(|(_, _, indexes)| items[indexes[0]].msg)
+            .collect::<Vec<_>>();
+        let signatures = ed25519
+            .iter()
+            .map(|(_, signature, _)| **signature)
+            .collect::<Vec<_>>();
+        let keys = ed25519.iter().map(|(key, _, _)| **key).collect::<Vec<_>>();
+        if ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_err() {
+            for (key, signature, indexes) in ed25519 {
+                if key.verify_strict(items[indexes[0]].msg, signature).is_err() {
+                    failed.extend(indexes);
+                }
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    failed.sort_unstable();
+    Err(BatchVerifyError { failed })
+}
+
+/// Determines if an encoded Edwards point is canonically encoded and of
+/// prime order.
+///
+/// The batch equation agrees with strict verification on a signature when
+/// both its `R` and the key are such points.
+fn is_prime_order(encoded: CompressedEdwardsY) -> bool {
+    encoded.decompress().is_some_and(|point| {
+        point.compress() == encoded && !point.is_small_order() && point.is_torsion_free()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{generate_ed25519_pair, generate_p256_pair};
+
+    #[test]
+    fn test_batch_verify() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+
+        let messages = [b"one".as_slice(), b"two", b"three"];
+        let alice_sigs = messages
+            .iter()
+            .map(|msg| alice_priv.sign(msg).unwrap())
+            .collect::<Vec<_>>();
+        let bob_sig = bob_priv.sign(b"one").unwrap();
+
+        let mut items = messages
+            .iter()
+            .zip(&alice_sigs)
+            .map(|(msg, sig)| BatchItem::new(&alice_pub, msg, sig))
+            .collect::<Vec<_>>();
+        items.push(BatchItem::new(&bob_pub, b"one", &bob_sig));
+        items.push(items[0]);
+        batch_verify(items.iter().copied()).unwrap();
+        batch_verify([]).unwrap();
+
+        // Failures are reported for every failing item
+        items.push(BatchItem::new(&alice_pub, b"one", &bob_sig));
+        items.push(BatchItem::new(&bob_pub, b"two", &bob_sig));
+        assert_eq!(
+            batch_verify(items.iter().copied()).unwrap_err().failed(),
+            [5, 6]
+        );
+    }
+
+    #[test]
+    fn test_batch_verify_ed25519() {
+        let (alice_pub, alice_priv) = generate_ed25519_pair();
+        let (bob_pub, bob_priv) = generate_ed25519_pair();
+        let (carol_pub, carol_priv) = generate_p256_pair();
+
+        let messages = [b"one".as_slice(), b"two", b"three"];
+        let alice_sigs = messages
+            .iter()
+            .map(|msg| alice_priv.sign(msg).unwrap())
+            .collect::<Vec<_>>();
+        let bob_sig = bob_priv.sign(b"one").unwrap();
+        let carol_sig = carol_priv.sign(b"one").unwrap();
+
+        // Ed25519 signatures of different keys are verified in one batch,
+        // alongside signatures of other algorithms
+        let mut items = messages
+            .iter()
+            .zip(&alice_sigs)
+            .map(|(msg, sig)| BatchItem::new(&alice_pub, msg, sig))
+            .collect::<Vec<_>>();
+        items.push(BatchItem::new(&bob_pub, b"one", &bob_sig));
+        items.push(BatchItem::new(&carol_pub, b"one", &carol_sig));
+        items.push(items[1]);
+        batch_verify(items.iter().copied()).unwrap();
+
+        // A batch with one bad Ed25519 signature is rejected, and the bad
+        // signature and its repetitions are reported
+        let bad = BatchItem::new(&alice_pub, b"four", &alice_sigs[0]);
+        items.insert(2, bad);
+        items.push(bad);
+        assert_eq!(
+            batch_verify(items.iter().copied()).unwrap_err().failed(),
+            [2, 7]
+        );
+
+        // A signature by a key of another algorithm fails as well
+        let items = [BatchItem::new(&alice_pub, b"one", &bob_sig)];
+        assert_eq!(batch_verify(items).unwrap_err().failed(), [0]);
+    }
+
+    #[test]
+    fn test_batch_verify_torsion() {
+        use curve25519_dalek::{
+            constants::{ED25519_BASEPOINT_POINT, EIGHT_TORSION},
+            EdwardsPoint, Scalar,
+        };
+        use sha2::{Digest, Sha512};
+
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let verifying_key = signing_key.verifying_key();
+        let key = PublicKey::from(verifying_key);
+
+        // Signs a message with the nonce point `R`, where `R - [r]B` may have
+        // a torsion component
+        let sign = |msg: &[u8], r: Scalar, point: EdwardsPoint| {
+            let point = point.compress();
+            let k = Scalar::from_hash(
+                Sha512::new()
+                    .chain_update(point.as_bytes())
+                    .chain_update(verifying_key.as_bytes())
+                    .chain_update(msg),
+            );
+            let s = r + k * signing_key.to_scalar();
+            ed25519_dalek::Signature::from_components(point.to_bytes(), s.to_bytes())
+        };
+
+        // The coefficients of the batch equation are derived from its inputs,
+        // so find a message for which the equation cancels the torsion
+        let batched = |r: Scalar, point: EdwardsPoint| {
+            (0..=u8::MAX)
+                .map(|i| vec![i])
+                .find_map(|msg| {
+                    let signature = sign(&msg, r, point);
+                    ed25519_dalek::verify_batch(&[&msg], &[signature], &[verifying_key])
+                        .is_ok()
+                        .then_some((msg, signature))
+                })
+                .unwrap()
+        };
+
+        let r = Scalar::from_bytes_mod_order([3; 32]);
+        let valid = (
+            b"valid".to_vec(),
+            sign(b"valid", r, r * ED25519_BASEPOINT_POINT),
+        );
+        let torsioned = batched(r, r * ED25519_BASEPOINT_POINT + EIGHT_TORSION[4]);
+        let small_order = batched(Scalar::ZERO, EIGHT_TORSION[4]);
+
+        let signatures = [valid, torsioned, small_order]
+            .map(|(msg, signature)| (msg, Signature::Ed25519(signature)));
+        let items = signatures
+            .iter()
+            .map(|(msg, signature)| BatchItem::new(&key, msg, signature))
+            .collect::<Vec<_>>();
+        for (index, item) in items.iter().enumerate() {
+            assert_eq!(
+                batch_verify([*item]).is_ok(),
+                index == 0,
+                "item {index} verified differently in a batch"
+            );
+            assert_eq!(key.verify(item.msg, item.signature).is_ok(), index == 0);
+        }
+        assert_eq!(batch_verify(items).unwrap_err().failed(), [1, 2]);
+    }
+}
@@ -0,0 +1,182 @@
+//! Passphrase-encrypted private keys.
+//!
+//! A private key is encrypted at rest with keys derived from a passphrase
+//! with scrypt, using the same authenticated encryption as content
+//! encryption (AES-256-GCM). The key is encrypted in its
+//! `<algo>:<base64>` form, so any supported key may be encrypted.
+
+use super::{PrivateKey, PrivateKeyParseError};
+use crate::encryption::{open, seal};
+use rand_core::{OsRng, RngCore};
+use secrecy::zeroize::Zeroizing;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use thiserror::Error;
+
+const SALT_LEN: usize = 16;
+
+/// The scrypt cost used to encrypt keys, as the base-2 logarithm of the
+/// number of iterations.
+///
+/// This takes a fraction of a second and 128 MiB of memory on current
+/// hardware.
+const LOG_N: u8 = 17;
+
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// The most memory, in scrypt blocks of 128 bytes, that decrypting a key may
+/// use, so that a crafted key file cannot exhaust memory; this is twice that
+/// used to encrypt keys.
+const MAX_BLOCKS: u64 = (SCRYPT_R as u64) << (LOG_N + 1);
+
+/// The greatest scrypt parallelization accepted when decrypting keys.
+const MAX_P: u32 = 4;
+
+/// Represents an error decrypting a private key.
+#[derive(Debug, Error)]
+pub enum KeyDecryptionError {
+    /// The passphrase is incorrect or the encrypted key was modified.
+    #[error("the passphrase is incorrect or the encrypted key is corrupt")]
+    Authentication,
+    /// The scrypt parameters of the encrypted key are not supported.
+    #[error("the key derivation parameters of the encrypted key are not supported")]
+    UnsupportedParameters,
+    /// The decrypted key could not be parsed.
+    #[error("the decrypted key is invalid: {0}")]
+    InvalidKey(#[from] PrivateKeyParseError),
+}
+
+/// A private key encrypted with a passphrase.
+///
+/// Encrypted keys serialize as JSON objects suitable for storing in a file.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedPrivateKey {
+    /// The base-2 logarithm of the scrypt cost parameter.
+    log_n: u8,
+    /// The scrypt block size parameter.
+    r: u32,
+    /// The scrypt parallelization parameter.
+    p: u32,
+    /// The random salt of the key derivation.
+    #[serde_as(as = "Base64")]
+    salt: Vec<u8>,
+    /// The nonce, encrypted key, and tag.
+    #[serde_as(as = "Base64")]
+    ciphertext: Vec<u8>,
+}
+
+impl PrivateKey {
+    /// Encrypts the key with the given passphrase.
+    pub fn encrypt(&self, passphrase: &str) -> EncryptedPrivateKey {
+        self.encrypt_with_cost(passphrase, LOG_N)
+    }
+
+    fn encrypt_with_cost(&self, passphrase: &str, log_n: u8) -> EncryptedPrivateKey {
+        let mut salt = vec![0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        // The parameters are valid, so deriving keys does not fail
+        let keys = derive_keys(passphrase, &salt, log_n, SCRYPT_R, SCRYPT_P)
+            .expect("scrypt parameters should be valid");
+        let encoded = self.encode();
+        EncryptedPrivateKey {
+            log_n,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            ciphertext: seal(&keys, encoded.as_bytes()),
+            salt,
+        }
+    }
+
+    /// Decrypts a key encrypted with [`PrivateKey::encrypt`] using the given
+    /// passphrase.
+    pub fn decrypt(
+        encrypted: &EncryptedPrivateKey,
+        passphrase: &str,
+    ) -> Result<Self, KeyDecryptionError> {
+        let blocks = u64::from(encrypted.r)
+            .checked_shl(encrypted.log_n.into())
+            .filter(|blocks| blocks >> encrypted.log_n == u64::from(encrypted.r));
+        if !blocks.is_some_and(|blocks| blocks <= MAX_BLOCKS) || encrypted.p > MAX_P {
+            return Err(KeyDecryptionError::UnsupportedParameters);
+        }
+
+        let keys = derive_keys(
+            passphrase,
+            &encrypted.salt,
+            encrypted.log_n,
+            encrypted.r,
+            encrypted.p,
+        )?;
+        let key = Zeroizing::new(
+            open(&keys, &encrypted.ciphertext).map_err(|_| KeyDecryptionError::Authentication)?,
+        );
+        let key = std::str::from_utf8(&key).map_err(|_| KeyDecryptionError::Authentication)?;
+        Ok(PrivateKey::decode(key.to_string())?)
+    }
+}
+
+/// Derives the encryption key from a passphrase.
+fn derive_keys(
+    passphrase: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<Zeroizing<[u8; 32]>, KeyDecryptionError> {
+    let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+        .map_err(|_| KeyDecryptionError::UnsupportedParameters)?;
+    let mut keys = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, keys.as_mut())
+        .map_err(|_| KeyDecryptionError::UnsupportedParameters)?;
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::generate_ed25519_pair;
+
+    #[test]
+    fn test_round_trip() {
+        let (_, key) = generate_ed25519_pair();
+        let encrypted = key.encrypt_with_cost("correct horse", 10);
+        assert_eq!(
+            PrivateKey::decrypt(&encrypted, "correct horse")
+                .unwrap()
+                .encode(),
+            key.encode()
+        );
+        assert!(matches!(
+            PrivateKey::decrypt(&encrypted, "battery staple"),
+            Err(KeyDecryptionError::Authentication)
+        ));
+
+        // Encrypted keys round trip through serde
+        let json = serde_json::to_string(&encrypted).unwrap();
+        let decoded: EncryptedPrivateKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, encrypted);
+
+        // Tampering with the parameters changes the derived keys
+        let mut tampered = encrypted.clone();
+        tampered.log_n = 11;
+        assert!(matches!(
+            PrivateKey::decrypt(&tampered, "correct horse"),
+            Err(KeyDecryptionError::Authentication)
+        ));
+
+        // Excessive costs are rejected before deriving keys
+        for (log_n, r) in [(LOG_N + 2, SCRYPT_R), (LOG_N, SCRYPT_R * 4), (63, u32::MAX)] {
+            let mut expensive = encrypted.clone();
+            expensive.log_n = log_n;
+            expensive.r = r;
+            assert!(matches!(
+                PrivateKey::decrypt(&expensive, "correct horse"),
+                Err(KeyDecryptionError::UnsupportedParameters)
+            ));
+        }
+    }
+}
@@ -5,18 +5,36 @@ use thiserror::Error;
 
 use crate::hash::HashAlgorithm;
 
+mod batch;
+mod encrypted_key;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
 mod private_key;
 mod public_key;
 mod signature;
+mod signer;
+#[cfg(feature = "yubikey")]
+mod yubikey;
 
+pub use self::batch::{batch_verify, BatchItem, BatchVerifyError};
+pub use self::encrypted_key::{EncryptedPrivateKey, KeyDecryptionError};
 pub use self::private_key::{PrivateKey, PrivateKeyParseError, SignatureError};
 pub use self::public_key::{KeyID, PublicKey, PublicKeyParseError};
 pub use self::signature::{Signature, SignatureParseError};
+pub use self::signer::{AsyncSigner, Signer, SignerPrompt};
+
+#[cfg(feature = "pkcs11")]
+pub use self::pkcs11::{Pkcs11Error, Pkcs11Signer};
+#[cfg(feature = "yubikey")]
+pub use self::yubikey::{PivSlot, YubiKeySigner};
 
 /// A signature algorithm supported by WARG
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum SignatureAlgorithm {
     EcdsaP256,
+    Ed25519,
+    RsaPss2048,
+    RsaPss4096,
 }
 
 impl SignatureAlgorithm {
@@ -24,7 +42,31 @@ impl SignatureAlgorithm {
     /// signing algorithm to generate digests.
     pub fn digest_algorithm(&self) -> HashAlgorithm {
         match self {
-            SignatureAlgorithm::EcdsaP256 => HashAlgorithm::Sha256,
+            SignatureAlgorithm::EcdsaP256
+            | SignatureAlgorithm::Ed25519
+            | SignatureAlgorithm::RsaPss2048
+            | SignatureAlgorithm::RsaPss4096 => HashAlgorithm::Sha256,
+        }
+    }
+
+    /// Determine the RSA modulus size, in bits, of keys for this algorithm.
+    ///
+    /// Returns `None` if this is not an RSA algorithm.
+    pub fn rsa_modulus_bits(&self) -> Option<usize> {
+        match self {
+            SignatureAlgorithm::EcdsaP256 | SignatureAlgorithm::Ed25519 => None,
+            SignatureAlgorithm::RsaPss2048 => Some(2048),
+            SignatureAlgorithm::RsaPss4096 => Some(4096),
+        }
+    }
+
+    /// Determine the RSA-PSS algorithm for keys with the given modulus size,
+    /// in bytes.
+    pub(crate) fn rsa_pss_for_size(size: usize) -> Option<Self> {
+        match size {
+            256 => Some(SignatureAlgorithm::RsaPss2048),
+            512 => Some(SignatureAlgorithm::RsaPss4096),
+            _ => None,
         }
     }
 }
@@ -33,6 +75,9 @@ impl fmt::Display for SignatureAlgorithm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SignatureAlgorithm::EcdsaP256 => write!(f, "ecdsa-p256"),
+            SignatureAlgorithm::Ed25519 => write!(f, "ed25519"),
+            SignatureAlgorithm::RsaPss2048 => write!(f, "rsa-pss-2048"),
+            SignatureAlgorithm::RsaPss4096 => write!(f, "rsa-pss-4096"),
         }
     }
 }
@@ -43,6 +88,9 @@ impl FromStr for SignatureAlgorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "ecdsa-p256" => Ok(SignatureAlgorithm::EcdsaP256),
+            "ed25519" => Ok(SignatureAlgorithm::Ed25519),
+            "rsa-pss-2048" => Ok(SignatureAlgorithm::RsaPss2048),
+            "rsa-pss-4096" => Ok(SignatureAlgorithm::RsaPss4096),
             _ => Err(SignatureAlgorithmParseError {
                 value: s.to_owned(),
             }),
@@ -62,6 +110,27 @@ pub fn generate_p256_pair() -> (PublicKey, PrivateKey) {
     (PublicKey::from(public_key), PrivateKey::from(private_key))
 }
 
+pub fn generate_ed25519_pair() -> (PublicKey, PrivateKey) {
+    let private_key = ed25519_dalek::SigningKey::generate(&mut OsRng);
+    let public_key = private_key.verifying_key();
+    (PublicKey::from(public_key), PrivateKey::from(private_key))
+}
+
+/// Generates an RSA-PSS key pair for the given algorithm.
+///
+/// Returns an error if the algorithm is not an RSA-PSS algorithm.
+pub fn generate_rsa_pss_pair(
+    algorithm: SignatureAlgorithm,
+) -> Result<(PublicKey, PrivateKey), SignatureError> {
+    let bits = algorithm
+        .rsa_modulus_bits()
+        .ok_or_else(SignatureError::new)?;
+    let private_key =
+        rsa::RsaPrivateKey::new(&mut OsRng, bits).map_err(|_| SignatureError::new())?;
+    let private_key = PrivateKey::try_from(private_key)?;
+    Ok((private_key.public_key(), private_key))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -86,4 +155,78 @@ pub mod tests {
         assert!(bob_public.verify(&msg, &alice_signature).is_err());
         assert!(alice_public.verify(&msg, &bob_signature).is_err());
     }
+
+    #[test]
+    pub fn test_ed25519_sign_and_verify() {
+        let (public, private) = generate_ed25519_pair();
+        let msg = (0..255u8).collect::<Vec<u8>>();
+        let signature = private.sign(&msg).unwrap();
+        assert_eq!(signature.signature_algorithm(), SignatureAlgorithm::Ed25519);
+        public.verify(&msg, &signature).unwrap();
+        assert!(public.verify(b"other", &signature).is_err());
+
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        assert_eq!(parsed, signature);
+        assert_eq!(signature.encoded_len(), signature.to_string().len());
+
+        let parsed: PublicKey = public.to_string().parse().unwrap();
+        assert_eq!(parsed, public);
+        assert_eq!(
+            PrivateKey::decode(private.encode().to_string())
+                .unwrap()
+                .public_key(),
+            public
+        );
+    }
+
+    #[test]
+    pub fn test_rsa_pss_sign_and_verify() {
+        let (public, private) = generate_rsa_pss_pair(SignatureAlgorithm::RsaPss2048).unwrap();
+        assert_eq!(public.signature_algorithm(), SignatureAlgorithm::RsaPss2048);
+        let msg = (0..255u8).collect::<Vec<u8>>();
+        let signature = private.sign(&msg).unwrap();
+        assert_eq!(
+            signature.signature_algorithm(),
+            SignatureAlgorithm::RsaPss2048
+        );
+        public.verify(&msg, &signature).unwrap();
+        assert!(public.verify(b"other", &signature).is_err());
+
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        assert_eq!(parsed, signature);
+        assert_eq!(signature.encoded_len(), signature.to_string().len());
+
+        let parsed: PublicKey = public.to_string().parse().unwrap();
+        assert_eq!(parsed, public);
+        assert_eq!(parsed.fingerprint(), public.fingerprint());
+        assert_eq!(
+            PrivateKey::decode(private.encode().to_string())
+                .unwrap()
+                .public_key(),
+            public
+        );
+
+        // The algorithm must agree with the key's modulus size
+        let mislabeled = public.to_string().replace("rsa-pss-2048", "rsa-pss-4096");
+        assert!(mislabeled.parse::<PublicKey>().is_err());
+        let mislabeled = signature
+            .to_string()
+            .replace("rsa-pss-2048", "rsa-pss-4096");
+        assert!(mislabeled.parse::<Signature>().is_err());
+        assert!(generate_rsa_pss_pair(SignatureAlgorithm::Ed25519).is_err());
+    }
+
+    #[test]
+    pub fn test_mixed_algorithms_fail_verify() {
+        let (p256_public, p256_private) = generate_p256_pair();
+        let (ed25519_public, ed25519_private) = generate_ed25519_pair();
+
+        let msg = (0..255u8).collect::<Vec<u8>>();
+        assert!(p256_public
+            .verify(&msg, &ed25519_private.sign(&msg).unwrap())
+            .is_err());
+        assert!(ed25519_public
+            .verify(&msg, &p256_private.sign(&msg).unwrap())
+            .is_err());
+    }
 }
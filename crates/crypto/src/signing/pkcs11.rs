@@ -0,0 +1,251 @@
+use super::{PublicKey, Signature, SignatureError, Signer};
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    error::{Error as CryptokiError, RvError},
+    mechanism::{
+        rsa::{PkcsMgfType, PkcsPssParams},
+        Mechanism, MechanismType,
+    },
+    object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    types::AuthPin,
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use std::{path::Path, sync::Mutex};
+use thiserror::Error;
+
+/// Represents an error opening a [`Pkcs11Signer`].
+#[derive(Error, Debug)]
+pub enum Pkcs11Error {
+    #[error("PKCS#11 operation failed: {0}")]
+    Pkcs11(#[from] CryptokiError),
+
+    #[error("no PKCS#11 token with label `{0}` was found")]
+    TokenNotFound(String),
+
+    #[error("no PKCS#11 key pair with label `{0}` was found")]
+    KeyNotFound(String),
+
+    #[error("PKCS#11 key `{0}` is not a supported P-256, Ed25519, or RSA key")]
+    UnsupportedKey(String),
+}
+
+/// The kind of key a [`Pkcs11Signer`] signs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Pkcs11KeyKind {
+    EcdsaP256,
+    Ed25519,
+    RsaPss,
+}
+
+impl Pkcs11KeyKind {
+    /// Decodes a signature as returned by a token signing with this kind of
+    /// key.
+    ///
+    /// ECDSA signatures are the raw concatenation of `r` and `s`, as PKCS#11
+    /// specifies; they are normalized to low-S form, which tokens do not
+    /// necessarily produce.
+    pub(super) fn decode_signature(self, bytes: &[u8]) -> Result<Signature, SignatureError> {
+        match self {
+            Pkcs11KeyKind::EcdsaP256 => {
+                let signature = p256::ecdsa::Signature::from_slice(bytes)?;
+                Ok(Signature::P256(
+                    signature.normalize_s().unwrap_or(signature),
+                ))
+            }
+            Pkcs11KeyKind::Ed25519 => Ok(Signature::Ed25519(ed25519_dalek::Signature::from_slice(
+                bytes,
+            )?)),
+            Pkcs11KeyKind::RsaPss => Ok(Signature::RsaPss(rsa::pss::Signature::try_from(bytes)?)),
+        }
+    }
+}
+
+/// Signs with a private key held by a PKCS#11 token, such as a hardware
+/// security module, so that the key never leaves the token.
+///
+/// The token must hold the public key of the private key as an object with
+/// the same ID as the private key.
+pub struct Pkcs11Signer {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+    kind: Pkcs11KeyKind,
+    public_key: PublicKey,
+}
+
+impl Pkcs11Signer {
+    /// Opens a signer for the key pair with the given label on the token
+    /// with the given label, using the PKCS#11 module at the given path.
+    ///
+    /// The session is logged in as a user with the given PIN.
+    pub fn open(
+        module: impl AsRef<Path>,
+        token_label: &str,
+        pin: &SecretString,
+        key_label: &str,
+    ) -> Result<Self, Pkcs11Error> {
+        Self::open_matching(
+            module,
+            |label| label == token_label,
+            token_label,
+            pin,
+            key_label,
+        )
+    }
+
+    // Opens a signer on the first token whose label matches, describing the
+    // token with the given description when none does.
+    pub(crate) fn open_matching(
+        module: impl AsRef<Path>,
+        token_matches: impl Fn(&str) -> bool,
+        token_description: &str,
+        pin: &SecretString,
+        key_label: &str,
+    ) -> Result<Self, Pkcs11Error> {
+        let pkcs11 = Pkcs11::new(module)?;
+        match pkcs11.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut slot = None;
+        for candidate in pkcs11.get_slots_with_token()? {
+            if token_matches(pkcs11.get_token_info(candidate)?.label()) {
+                slot = Some(candidate);
+                break;
+            }
+        }
+        let slot = slot.ok_or_else(|| Pkcs11Error::TokenNotFound(token_description.to_string()))?;
+
+        let session = pkcs11.open_ro_session(slot)?;
+        session.login(
+            UserType::User,
+            Some(&AuthPin::new(pin.expose_secret().clone())),
+        )?;
+
+        let find = |template: &[Attribute]| {
+            session
+                .find_objects(template)
+                .map(|objects| objects.into_iter().next())
+        };
+        let not_found = || Pkcs11Error::KeyNotFound(key_label.to_string());
+        let key = find(&[
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::Label(key_label.as_bytes().to_vec()),
+        ])?
+        .ok_or_else(not_found)?;
+        let id = session
+            .get_attributes(key, &[AttributeType::Id])?
+            .into_iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Id(id) => Some(id),
+                _ => None,
+            })
+            .ok_or_else(not_found)?;
+        let public = find(&[Attribute::Class(ObjectClass::PUBLIC_KEY), Attribute::Id(id)])?
+            .ok_or_else(not_found)?;
+
+        let unsupported = || Pkcs11Error::UnsupportedKey(key_label.to_string());
+        let attributes = session.get_attributes(
+            public,
+            &[
+                AttributeType::KeyType,
+                AttributeType::EcPoint,
+                AttributeType::Modulus,
+                AttributeType::PublicExponent,
+            ],
+        )?;
+        let mut key_type = None;
+        let mut ec_point = None;
+        let mut modulus = None;
+        let mut exponent = None;
+        for attribute in attributes {
+            match attribute {
+                Attribute::KeyType(value) => key_type = Some(value),
+                Attribute::EcPoint(value) => ec_point = Some(value),
+                Attribute::Modulus(value) => modulus = Some(value),
+                Attribute::PublicExponent(value) => exponent = Some(value),
+                _ => {}
+            }
+        }
+
+        let (kind, public_key) = match (key_type, ec_point, modulus, exponent) {
+            (Some(KeyType::EC), Some(point), _, _) => (
+                Pkcs11KeyKind::EcdsaP256,
+                PublicKey::from(
+                    p256::ecdsa::VerifyingKey::from_sec1_bytes(unwrap_octet_string(&point))
+                        .map_err(|_| unsupported())?,
+                ),
+            ),
+            (Some(KeyType::EC_EDWARDS), Some(point), _, _) => (
+                Pkcs11KeyKind::Ed25519,
+                PublicKey::from(
+                    ed25519_dalek::VerifyingKey::try_from(unwrap_octet_string(&point))
+                        .map_err(|_| unsupported())?,
+                ),
+            ),
+            (Some(KeyType::RSA), _, Some(modulus), Some(exponent)) => (
+                Pkcs11KeyKind::RsaPss,
+                rsa::RsaPublicKey::new(
+                    rsa::BigUint::from_bytes_be(&modulus),
+                    rsa::BigUint::from_bytes_be(&exponent),
+                )
+                .ok()
+                .and_then(|key| PublicKey::try_from(key).ok())
+                .ok_or_else(unsupported)?,
+            ),
+            _ => return Err(unsupported()),
+        };
+
+        Ok(Self {
+            session: Mutex::new(session),
+            key,
+            kind,
+            public_key,
+        })
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
+        let session = self.session.lock().map_err(|_| SignatureError::new())?;
+        let sign = |mechanism: &Mechanism<'_>, data: &[u8]| {
+            session
+                .sign(mechanism, self.key, data)
+                .map_err(SignatureError::from_source)
+        };
+
+        let signature = match self.kind {
+            // The token signs a digest computed here, as not every token
+            // supports hashing with ECDSA
+            Pkcs11KeyKind::EcdsaP256 => sign(&Mechanism::Ecdsa, &Sha256::digest(msg))?,
+            Pkcs11KeyKind::Ed25519 => sign(&Mechanism::Eddsa, msg)?,
+            Pkcs11KeyKind::RsaPss => {
+                // Matches the salt length used by in-memory RSA-PSS keys
+                let params = PkcsPssParams {
+                    hash_alg: MechanismType::SHA256,
+                    mgf: PkcsMgfType::MGF1_SHA256,
+                    s_len: (<Sha256 as Digest>::output_size() as u64).into(),
+                };
+                sign(&Mechanism::Sha256RsaPkcsPss(params), msg)?
+            }
+        };
+
+        self.kind.decode_signature(&signature)
+    }
+}
+
+/// Unwraps a DER octet string, as tokens may encode `CKA_EC_POINT` either
+/// as a raw point or as an octet string containing the point.
+fn unwrap_octet_string(bytes: &[u8]) -> &[u8] {
+    match bytes {
+        [0x04, len, rest @ ..] if *len as usize == rest.len() && *len < 0x80 => rest,
+        [0x04, 0x81, len, rest @ ..] if *len as usize == rest.len() => rest,
+        _ => bytes,
+    }
+}
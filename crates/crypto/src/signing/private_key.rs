@@ -1,6 +1,14 @@
 use super::{PublicKey, Signature, SignatureAlgorithm, SignatureAlgorithmParseError};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use p256;
+use pkcs8::{
+    der::pem::PemLabel, DecodePrivateKey, EncodePrivateKey, LineEnding, ObjectIdentifier,
+    PrivateKeyInfo, SecretDocument,
+};
+use rand_core::OsRng;
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey};
+use rsa::signature::RandomizedSigner;
+use rsa::traits::PublicKeyParts;
 use secrecy::{zeroize::Zeroizing, ExposeSecret, Secret, SecretString, SecretVec, Zeroize};
 use signature::Signer;
 use thiserror::Error;
@@ -12,6 +20,8 @@ pub struct PrivateKey(Secret<PrivateKeyInner>);
 
 pub enum PrivateKeyInner {
     EcdsaP256(p256::ecdsa::SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+    RsaPss(rsa::RsaPrivateKey),
 }
 
 impl PrivateKey {
@@ -30,6 +40,12 @@ impl PrivateKey {
             SignatureAlgorithm::EcdsaP256 => PrivateKeyInner::EcdsaP256(
                 p256::ecdsa::SigningKey::from_slice(bytes.expose_secret())?,
             ),
+            SignatureAlgorithm::Ed25519 => PrivateKeyInner::Ed25519(
+                ed25519_dalek::SigningKey::try_from(bytes.expose_secret().as_slice())?,
+            ),
+            SignatureAlgorithm::RsaPss2048 | SignatureAlgorithm::RsaPss4096 => {
+                PrivateKeyInner::rsa_pss(algo, bytes.expose_secret())?
+            }
         };
 
         Ok(PrivateKey(Secret::from(key)))
@@ -37,31 +53,100 @@ impl PrivateKey {
 
     /// Encode the key as a string in `<algo>:<base64 data>` form.
     pub fn encode(&self) -> Zeroizing<String> {
-        Zeroizing::new(format!(
-            "{algo}:{b64}",
-            algo = self.signature_algorithm(),
-            b64 = STANDARD.encode(self.bytes())
-        ))
+        // The key is encoded in place into a buffer of the exact size, so no
+        // unzeroized copy of it is left behind by intermediate strings or
+        // reallocations
+        let algo = self.signature_algorithm().to_string();
+        let bytes = self.bytes();
+        let b64_len =
+            base64::encoded_len(bytes.len(), true).expect("encoded key length should not overflow");
+        let mut encoded = Zeroizing::new(vec![0; algo.len() + 1 + b64_len]);
+        encoded[..algo.len()].copy_from_slice(algo.as_bytes());
+        encoded[algo.len()] = b':';
+        STANDARD
+            .encode_slice(&*bytes, &mut encoded[algo.len() + 1..])
+            .expect("the buffer should fit the encoded key");
+
+        // Base64 and algorithm names are ASCII; the buffer is moved, not copied
+        Zeroizing::new(
+            String::from_utf8(std::mem::take(&mut *encoded))
+                .expect("the encoded key should be valid UTF-8"),
+        )
+    }
+
+    /// Decode a key from a PEM-encoded, unencrypted PKCS#8 private key, as
+    /// generated by `openssl genpkey`.
+    ///
+    /// P-256 ECDSA, Ed25519, and RSA keys are supported; RSA keys are used
+    /// for RSA-PSS signatures.
+    pub fn from_pkcs8_pem(pem: &str) -> Result<Self, PrivateKeyParseError> {
+        let (label, document) = SecretDocument::from_pem(pem).map_err(pkcs8::Error::from)?;
+        PrivateKeyInfo::validate_pem_label(label).map_err(pkcs8::Error::from)?;
+        Self::from_pkcs8_der(document.as_bytes())
+    }
+
+    /// Decode a key from a DER-encoded, unencrypted PKCS#8 private key.
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Self, PrivateKeyParseError> {
+        let oid = PrivateKeyInfo::try_from(der)?.algorithm.oid;
+        let key = if oid == p256::elliptic_curve::ALGORITHM_OID {
+            PrivateKeyInner::EcdsaP256(p256::ecdsa::SigningKey::from_pkcs8_der(der)?)
+        } else if oid == ed25519_dalek::pkcs8::ALGORITHM_OID {
+            PrivateKeyInner::Ed25519(ed25519_dalek::SigningKey::from_pkcs8_der(der)?)
+        } else if oid == rsa::pkcs1::ALGORITHM_OID {
+            return Ok(PrivateKey::try_from(rsa::RsaPrivateKey::from_pkcs8_der(
+                der,
+            )?)?);
+        } else {
+            return Err(PrivateKeyParseError::UnsupportedAlgorithm(oid));
+        };
+
+        Ok(PrivateKey(Secret::from(key)))
+    }
+
+    /// Encode the key as a PEM-encoded, unencrypted PKCS#8 private key.
+    pub fn to_pkcs8_pem(&self) -> Zeroizing<String> {
+        let pem = match self.0.expose_secret() {
+            PrivateKeyInner::EcdsaP256(key) => key.to_pkcs8_pem(LineEnding::LF),
+            PrivateKeyInner::Ed25519(key) => key.to_pkcs8_pem(LineEnding::LF),
+            PrivateKeyInner::RsaPss(key) => key.to_pkcs8_pem(LineEnding::LF),
+        };
+        // Encoding a valid key as PKCS#8 does not fail
+        pem.unwrap_or_default()
     }
 
     /// Get the signature algorithm used for by this key
     pub fn signature_algorithm(&self) -> SignatureAlgorithm {
         match self.0.expose_secret() {
             PrivateKeyInner::EcdsaP256(_) => SignatureAlgorithm::EcdsaP256,
+            PrivateKeyInner::Ed25519(_) => SignatureAlgorithm::Ed25519,
+            // The modulus size is checked when the key is constructed
+            PrivateKeyInner::RsaPss(key) => SignatureAlgorithm::rsa_pss_for_size(key.size())
+                .unwrap_or(SignatureAlgorithm::RsaPss4096),
         }
     }
 
     /// Get the keys representation as bytes (not including an algorithm specifier)
-    pub fn bytes(&self) -> Vec<u8> {
-        match self.0.expose_secret() {
-            PrivateKeyInner::EcdsaP256(key) => key.to_bytes().to_vec(),
-        }
+    pub fn bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(match self.0.expose_secret() {
+            PrivateKeyInner::EcdsaP256(key) => Zeroizing::new(key.to_bytes()).to_vec(),
+            PrivateKeyInner::Ed25519(key) => Zeroizing::new(key.to_bytes()).to_vec(),
+            // Encoding a valid key as PKCS#1 DER does not fail
+            PrivateKeyInner::RsaPss(key) => key
+                .to_pkcs1_der()
+                .map(|der| der.as_bytes().to_vec())
+                .unwrap_or_default(),
+        })
     }
 
     /// Sign a given message with this key
     pub fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
         match self.0.expose_secret() {
             PrivateKeyInner::EcdsaP256(key) => Ok(Signature::P256(key.try_sign(msg)?)),
+            PrivateKeyInner::Ed25519(key) => Ok(Signature::Ed25519(key.try_sign(msg)?)),
+            PrivateKeyInner::RsaPss(key) => Ok(Signature::RsaPss(
+                rsa::pss::SigningKey::<sha2::Sha256>::new(key.clone())
+                    .try_sign_with_rng(&mut OsRng, msg)?,
+            )),
         }
     }
 
@@ -70,6 +155,16 @@ impl PrivateKey {
             PrivateKeyInner::EcdsaP256(key) => {
                 PublicKey::EcdsaP256(p256::ecdsa::VerifyingKey::from(key))
             }
+            PrivateKeyInner::Ed25519(key) => PublicKey::Ed25519(key.verifying_key()),
+            PrivateKeyInner::RsaPss(key) => PublicKey::RsaPss(key.to_public_key()),
+        }
+    }
+
+    /// Returns the secret scalar of the key, if it is a P-256 key.
+    pub(crate) fn p256_secret_bytes(&self) -> Option<Zeroizing<[u8; 32]>> {
+        match self.0.expose_secret() {
+            PrivateKeyInner::EcdsaP256(key) => Some(Zeroizing::new(key.to_bytes().into())),
+            _ => None,
         }
     }
 }
@@ -93,6 +188,12 @@ impl TryFrom<String> for PrivateKey {
             SignatureAlgorithm::EcdsaP256 => PrivateKeyInner::EcdsaP256(
                 p256::ecdsa::SigningKey::from_bytes(bytes.as_slice().into())?,
             ),
+            SignatureAlgorithm::Ed25519 => {
+                PrivateKeyInner::Ed25519(ed25519_dalek::SigningKey::try_from(bytes.as_slice())?)
+            }
+            SignatureAlgorithm::RsaPss2048 | SignatureAlgorithm::RsaPss4096 => {
+                PrivateKeyInner::rsa_pss(algo, &Zeroizing::new(bytes))?
+            }
         };
 
         Ok(PrivateKey(Secret::from(key)))
@@ -112,6 +213,12 @@ pub enum PrivateKeyParseError {
 
     #[error("private key could not be constructed from bytes")]
     SignatureError(#[from] SignatureError),
+
+    #[error("failed to decode PKCS#8 private key")]
+    Pkcs8(#[from] pkcs8::Error),
+
+    #[error("unsupported private key algorithm `{0}`")]
+    UnsupportedAlgorithm(ObjectIdentifier),
 }
 
 impl Zeroize for PrivateKeyInner {
@@ -125,6 +232,29 @@ impl Zeroize for PrivateKeyInner {
                 );
                 drop(std::mem::replace(sk, mostly_zero));
             }
+            PrivateKeyInner::RsaPss(_) => {
+                // RsaPrivateKey zeroizes on Drop, which immediately follows
+                // zeroization of the secret holding it
+            }
+            PrivateKeyInner::Ed25519(sk) => {
+                // SigningKey zeroizes on Drop
+                drop(std::mem::replace(
+                    sk,
+                    ed25519_dalek::SigningKey::from_bytes(&[0; ed25519_dalek::SECRET_KEY_LENGTH]),
+                ));
+            }
+        }
+    }
+}
+
+impl Clone for PrivateKey {
+    fn clone(&self) -> Self {
+        match self.0.expose_secret() {
+            PrivateKeyInner::EcdsaP256(key) => key.clone().into(),
+            PrivateKeyInner::Ed25519(key) => key.clone().into(),
+            PrivateKeyInner::RsaPss(key) => {
+                PrivateKey(Secret::from(PrivateKeyInner::RsaPss(key.clone())))
+            }
         }
     }
 }
@@ -135,9 +265,39 @@ impl From<p256::ecdsa::SigningKey> for PrivateKey {
     }
 }
 
+impl From<ed25519_dalek::SigningKey> for PrivateKey {
+    fn from(key: ed25519_dalek::SigningKey) -> Self {
+        PrivateKey(Secret::from(PrivateKeyInner::Ed25519(key)))
+    }
+}
+
+impl TryFrom<rsa::RsaPrivateKey> for PrivateKey {
+    type Error = SignatureError;
+
+    /// Converts an RSA key into an RSA-PSS private key.
+    ///
+    /// Fails if the key's modulus size is not supported.
+    fn try_from(key: rsa::RsaPrivateKey) -> Result<Self, Self::Error> {
+        PublicKey::try_from(key.to_public_key())?;
+        Ok(PrivateKey(Secret::from(PrivateKeyInner::RsaPss(key))))
+    }
+}
+
+impl PrivateKeyInner {
+    /// Decodes a PKCS#1 DER encoded RSA-PSS key for the given algorithm.
+    fn rsa_pss(algo: SignatureAlgorithm, der: &[u8]) -> Result<Self, SignatureError> {
+        let key = rsa::RsaPrivateKey::from_pkcs1_der(der).map_err(|_| SignatureError::new())?;
+        if PublicKey::try_from(key.to_public_key())?.signature_algorithm() != algo {
+            return Err(SignatureError::new());
+        }
+        Ok(PrivateKeyInner::RsaPss(key))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::signing::PublicKeyParseError;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -146,4 +306,64 @@ mod tests {
         let key = PrivateKey::decode(key_str.to_string()).unwrap();
         assert_eq!(key_str, &*key.encode());
     }
+
+    #[test]
+    fn test_pkcs8_roundtrip() {
+        for (private_pem, public_pem, algo) in [
+            (
+                include_str!("../../tests/fixtures/keys/p256.pem"),
+                include_str!("../../tests/fixtures/keys/p256.pub.pem"),
+                SignatureAlgorithm::EcdsaP256,
+            ),
+            (
+                include_str!("../../tests/fixtures/keys/ed25519.pem"),
+                include_str!("../../tests/fixtures/keys/ed25519.pub.pem"),
+                SignatureAlgorithm::Ed25519,
+            ),
+            (
+                include_str!("../../tests/fixtures/keys/rsa.pem"),
+                include_str!("../../tests/fixtures/keys/rsa.pub.pem"),
+                SignatureAlgorithm::RsaPss2048,
+            ),
+        ] {
+            let key = PrivateKey::from_pkcs8_pem(private_pem).unwrap();
+            let public_key = PublicKey::from_spki_pem(public_pem).unwrap();
+            assert_eq!(key.signature_algorithm(), algo);
+            assert_eq!(key.public_key(), public_key);
+
+            let signature = key.sign(b"hello").unwrap();
+            public_key.verify(b"hello", &signature).unwrap();
+
+            let decoded = PrivateKey::from_pkcs8_pem(&key.to_pkcs8_pem()).unwrap();
+            assert_eq!(decoded.encode(), key.encode());
+            assert_eq!(
+                PublicKey::from_spki_pem(&public_key.to_spki_pem()).unwrap(),
+                public_key
+            );
+        }
+
+        assert!(matches!(
+            PrivateKey::from_pkcs8_pem(include_str!("../../tests/fixtures/keys/x25519.pem")),
+            Err(PrivateKeyParseError::UnsupportedAlgorithm(_))
+        ));
+        assert!(matches!(
+            PublicKey::from_spki_pem(include_str!("../../tests/fixtures/keys/x25519.pub.pem")),
+            Err(PublicKeyParseError::UnsupportedAlgorithm(_))
+        ));
+        // A public key is not a private key
+        assert!(
+            PrivateKey::from_pkcs8_pem(include_str!("../../tests/fixtures/keys/p256.pub.pem"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_signature_encoded_len() {
+        let key_str = "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=";
+        let key = PrivateKey::decode(key_str.to_string()).unwrap();
+        for message in [&b""[..], b"hello", &[0xff; 1024]] {
+            let signature = key.sign(message).unwrap();
+            assert_eq!(signature.encoded_len(), signature.to_string().len());
+        }
+    }
 }
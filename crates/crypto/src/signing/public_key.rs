@@ -1,14 +1,22 @@
 use super::{Signature, SignatureAlgorithm, SignatureAlgorithmParseError};
 use base64::{engine::general_purpose::STANDARD, Engine};
-use core::fmt;
+use core::{cmp::Ordering, fmt};
+use pkcs8::{
+    der::pem::PemLabel, DecodePublicKey, Document, EncodePublicKey, LineEnding, ObjectIdentifier,
+    SubjectPublicKeyInfoRef,
+};
+use rsa::pkcs1::{DecodeRsaPublicKey, EncodeRsaPublicKey};
+use rsa::traits::PublicKeyParts;
 use serde::{Deserialize, Serialize};
 use signature::{Error as SignatureError, Verifier};
 use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PublicKey {
     EcdsaP256(p256::ecdsa::VerifyingKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+    RsaPss(rsa::RsaPublicKey),
 }
 
 impl PublicKey {
@@ -16,13 +24,65 @@ impl PublicKey {
     pub fn signature_algorithm(&self) -> SignatureAlgorithm {
         match self {
             PublicKey::EcdsaP256(_) => SignatureAlgorithm::EcdsaP256,
+            PublicKey::Ed25519(_) => SignatureAlgorithm::Ed25519,
+            // The modulus size is checked when the key is constructed
+            PublicKey::RsaPss(key) => SignatureAlgorithm::rsa_pss_for_size(key.size())
+                .unwrap_or(SignatureAlgorithm::RsaPss4096),
         }
     }
 
+    /// Decode a key from a PEM-encoded SubjectPublicKeyInfo, as exported by
+    /// `openssl pkey -pubout`.
+    ///
+    /// P-256 ECDSA, Ed25519, and RSA keys are supported; RSA keys are used
+    /// for RSA-PSS signatures.
+    pub fn from_spki_pem(pem: &str) -> Result<Self, PublicKeyParseError> {
+        let (label, document) = Document::from_pem(pem).map_err(pkcs8::spki::Error::from)?;
+        SubjectPublicKeyInfoRef::validate_pem_label(label).map_err(pkcs8::spki::Error::from)?;
+        Self::from_spki_der(document.as_bytes())
+    }
+
+    /// Decode a key from a DER-encoded SubjectPublicKeyInfo.
+    pub fn from_spki_der(der: &[u8]) -> Result<Self, PublicKeyParseError> {
+        let oid = SubjectPublicKeyInfoRef::try_from(der)?.algorithm.oid;
+        if oid == p256::elliptic_curve::ALGORITHM_OID {
+            Ok(PublicKey::EcdsaP256(
+                p256::ecdsa::VerifyingKey::from_public_key_der(der)?,
+            ))
+        } else if oid == ed25519_dalek::pkcs8::ALGORITHM_OID {
+            Ok(PublicKey::Ed25519(
+                ed25519_dalek::VerifyingKey::from_public_key_der(der)?,
+            ))
+        } else if oid == rsa::pkcs1::ALGORITHM_OID {
+            Ok(PublicKey::try_from(
+                rsa::RsaPublicKey::from_public_key_der(der)?,
+            )?)
+        } else {
+            Err(PublicKeyParseError::UnsupportedAlgorithm(oid))
+        }
+    }
+
+    /// Encode the key as a PEM-encoded SubjectPublicKeyInfo.
+    pub fn to_spki_pem(&self) -> String {
+        let pem = match self {
+            PublicKey::EcdsaP256(key) => key.to_public_key_pem(LineEnding::LF),
+            PublicKey::Ed25519(key) => key.to_public_key_pem(LineEnding::LF),
+            PublicKey::RsaPss(key) => key.to_public_key_pem(LineEnding::LF),
+        };
+        // Encoding a valid key as a SubjectPublicKeyInfo does not fail
+        pem.unwrap_or_default()
+    }
+
     /// Get the encoded bytes of this key
     pub fn bytes(&self) -> Vec<u8> {
         match self {
             PublicKey::EcdsaP256(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+            PublicKey::Ed25519(key) => key.to_bytes().to_vec(),
+            // Encoding a valid key as PKCS#1 DER does not fail
+            PublicKey::RsaPss(key) => key
+                .to_pkcs1_der()
+                .map(|der| der.into_vec())
+                .unwrap_or_default(),
         }
     }
 
@@ -30,6 +90,13 @@ impl PublicKey {
     pub fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), SignatureError> {
         match (self, signature) {
             (PublicKey::EcdsaP256(key), Signature::P256(signature)) => key.verify(msg, signature),
+            (PublicKey::Ed25519(key), Signature::Ed25519(signature)) => {
+                key.verify_strict(msg, signature)
+            }
+            (PublicKey::RsaPss(key), Signature::RsaPss(signature)) => {
+                rsa::pss::VerifyingKey::<sha2::Sha256>::new(key.clone()).verify(msg, signature)
+            }
+            _ => Err(SignatureError::new()),
         }
     }
 
@@ -44,6 +111,31 @@ impl PublicKey {
     }
 }
 
+impl PartialOrd for PublicKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PublicKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn rank(key: &PublicKey) -> u8 {
+            match key {
+                PublicKey::EcdsaP256(_) => 0,
+                PublicKey::Ed25519(_) => 1,
+                PublicKey::RsaPss(_) => 2,
+            }
+        }
+
+        match (self, other) {
+            (PublicKey::EcdsaP256(a), PublicKey::EcdsaP256(b)) => a.cmp(b),
+            (PublicKey::Ed25519(a), PublicKey::Ed25519(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (PublicKey::RsaPss(a), PublicKey::RsaPss(b)) => (a.n(), a.e()).cmp(&(b.n(), b.e())),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
 impl fmt::Display for PublicKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -59,7 +151,7 @@ impl FromStr for PublicKey {
     type Err = PublicKeyParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(|c| c == ':').collect();
+        let parts: Vec<&str> = s.split(':').collect();
         if parts.len() != 2 {
             return Err(PublicKeyParseError::IncorrectStructure(parts.len()));
         }
@@ -70,8 +162,20 @@ impl FromStr for PublicKey {
             SignatureAlgorithm::EcdsaP256 => {
                 PublicKey::EcdsaP256(p256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes)?)
             }
+            SignatureAlgorithm::Ed25519 => {
+                PublicKey::Ed25519(ed25519_dalek::VerifyingKey::try_from(bytes.as_slice())?)
+            }
+            SignatureAlgorithm::RsaPss2048 | SignatureAlgorithm::RsaPss4096 => {
+                let key =
+                    rsa::RsaPublicKey::from_pkcs1_der(&bytes).map_err(|_| SignatureError::new())?;
+                PublicKey::try_from(key)?
+            }
         };
 
+        if key.signature_algorithm() != algo {
+            return Err(PublicKeyParseError::SignatureError(SignatureError::new()));
+        }
+
         Ok(key)
     }
 }
@@ -107,6 +211,12 @@ pub enum PublicKeyParseError {
 
     #[error("public key could not be constructed from bytes")]
     SignatureError(#[from] SignatureError),
+
+    #[error("failed to decode SubjectPublicKeyInfo public key")]
+    Spki(#[from] pkcs8::spki::Error),
+
+    #[error("unsupported public key algorithm `{0}`")]
+    UnsupportedAlgorithm(ObjectIdentifier),
 }
 
 impl From<p256::ecdsa::VerifyingKey> for PublicKey {
@@ -115,6 +225,24 @@ impl From<p256::ecdsa::VerifyingKey> for PublicKey {
     }
 }
 
+impl From<ed25519_dalek::VerifyingKey> for PublicKey {
+    fn from(key: ed25519_dalek::VerifyingKey) -> Self {
+        PublicKey::Ed25519(key)
+    }
+}
+
+impl TryFrom<rsa::RsaPublicKey> for PublicKey {
+    type Error = SignatureError;
+
+    /// Converts an RSA key into an RSA-PSS public key.
+    ///
+    /// Fails if the key's modulus size is not supported.
+    fn try_from(key: rsa::RsaPublicKey) -> Result<Self, Self::Error> {
+        SignatureAlgorithm::rsa_pss_for_size(key.size()).ok_or_else(SignatureError::new)?;
+        Ok(PublicKey::RsaPss(key))
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct KeyID(String);
@@ -125,6 +253,13 @@ impl fmt::Display for KeyID {
     }
 }
 
+impl KeyID {
+    /// Gets the key ID as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl From<String> for KeyID {
     fn from(s: String) -> Self {
         KeyID(s)
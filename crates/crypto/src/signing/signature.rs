@@ -2,6 +2,7 @@ use super::{SignatureAlgorithm, SignatureAlgorithmParseError};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use core::fmt;
 use p256;
+use rsa::signature::SignatureEncoding;
 use serde::{Deserialize, Serialize};
 use signature::Error as SignatureError;
 use std::str::FromStr;
@@ -10,6 +11,8 @@ use thiserror::Error;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Signature {
     P256(p256::ecdsa::Signature),
+    Ed25519(ed25519_dalek::Signature),
+    RsaPss(rsa::pss::Signature),
 }
 
 impl Signature {
@@ -17,6 +20,12 @@ impl Signature {
     pub fn signature_algorithm(&self) -> SignatureAlgorithm {
         match self {
             Signature::P256(_) => SignatureAlgorithm::EcdsaP256,
+            Signature::Ed25519(_) => SignatureAlgorithm::Ed25519,
+            // The signature length is checked when the signature is parsed or created
+            Signature::RsaPss(signature) => {
+                SignatureAlgorithm::rsa_pss_for_size(signature.encoded_len())
+                    .unwrap_or(SignatureAlgorithm::RsaPss4096)
+            }
         }
     }
 
@@ -24,8 +33,21 @@ impl Signature {
     pub fn bytes(&self) -> Vec<u8> {
         match self {
             Signature::P256(key) => key.to_der().to_bytes().to_vec(),
+            Signature::Ed25519(signature) => signature.to_bytes().to_vec(),
+            Signature::RsaPss(signature) => signature.to_vec(),
         }
     }
+
+    /// Gets the length of the signature's string representation without
+    /// formatting it.
+    pub fn encoded_len(&self) -> usize {
+        let len = match self {
+            Signature::P256(key) => key.to_der().len(),
+            Signature::Ed25519(_) => ed25519_dalek::SIGNATURE_LENGTH,
+            Signature::RsaPss(signature) => signature.encoded_len(),
+        };
+        self.signature_algorithm().to_string().len() + 1 + len.div_ceil(3) * 4
+    }
 }
 
 impl fmt::Display for Signature {
@@ -43,7 +65,7 @@ impl FromStr for Signature {
     type Err = SignatureParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(|c| c == ':').collect();
+        let parts: Vec<&str> = s.split(':').collect();
         if parts.len() != 2 {
             return Err(SignatureParseError::IncorrectStructure(parts.len()));
         }
@@ -54,6 +76,15 @@ impl FromStr for Signature {
             SignatureAlgorithm::EcdsaP256 => {
                 Signature::P256(p256::ecdsa::Signature::from_der(&bytes)?)
             }
+            SignatureAlgorithm::Ed25519 => {
+                Signature::Ed25519(ed25519_dalek::Signature::from_slice(&bytes)?)
+            }
+            SignatureAlgorithm::RsaPss2048 | SignatureAlgorithm::RsaPss4096 => {
+                if SignatureAlgorithm::rsa_pss_for_size(bytes.len()) != Some(algo) {
+                    return Err(SignatureParseError::SignatureError(SignatureError::new()));
+                }
+                Signature::RsaPss(rsa::pss::Signature::try_from(bytes.as_slice())?)
+            }
         };
 
         Ok(sig)
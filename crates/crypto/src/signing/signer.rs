@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+
+use super::{PrivateKey, PublicKey, Signature, SignatureError};
+
+/// A source of signatures made with a single key.
+///
+/// [`PrivateKey`] signs with a key held in memory; other implementations may
+/// sign with keys that never leave a hardware token or remote service.
+pub trait Signer {
+    /// Gets the public key of the key used to sign.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the given message.
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError>;
+}
+
+impl Signer for PrivateKey {
+    fn public_key(&self) -> PublicKey {
+        PrivateKey::public_key(self)
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
+        PrivateKey::sign(self, msg)
+    }
+}
+
+/// A source of signatures made with a single key that may need to wait on
+/// I/O to sign, such as a key management service.
+///
+/// Every [`Signer`] is also an `AsyncSigner` that signs without waiting.
+#[async_trait]
+pub trait AsyncSigner: Send + Sync {
+    /// Gets the public key of the key used to sign.
+    async fn public_key(&self) -> Result<PublicKey, SignatureError>;
+
+    /// Signs the given message.
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError>;
+}
+
+#[async_trait]
+impl<S: Signer + Send + Sync + ?Sized> AsyncSigner for S {
+    async fn public_key(&self) -> Result<PublicKey, SignatureError> {
+        Ok(Signer::public_key(self))
+    }
+
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
+        Signer::sign(self, msg)
+    }
+}
+
+/// A hook notified when a signer needs the user's attention to sign, such as
+/// a hardware token that signs only once touched.
+///
+/// A command line interface may implement it to ask the user for touch
+/// confirmation. It is implemented for closures called before each signature.
+pub trait SignerPrompt: Send + Sync {
+    /// Called before the signer signs with the given key, which may block
+    /// until the user confirms.
+    fn confirm(&self, key: &PublicKey);
+
+    /// Called once the signer has signed with the given key or failed to.
+    fn done(&self, _key: &PublicKey) {}
+}
+
+impl<F> SignerPrompt for F
+where
+    F: Fn(&PublicKey) + Send + Sync,
+{
+    fn confirm(&self, key: &PublicKey) {
+        self(key)
+    }
+}
@@ -0,0 +1,209 @@
+use super::{
+    Pkcs11Error, Pkcs11Signer, PublicKey, Signature, SignatureError, Signer, SignerPrompt,
+};
+use secrecy::SecretString;
+use std::{fmt, path::Path};
+
+/// The label prefix of the tokens exposed by the YubiKey PIV PKCS#11 module.
+const TOKEN_LABEL_PREFIX: &str = "YubiKey PIV";
+
+/// A PIV slot of a YubiKey holding a signing key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivSlot {
+    /// Slot `9a`, for PIV authentication.
+    Authentication,
+    /// Slot `9c`, for digital signatures.
+    Signature,
+    /// Slot `9d`, for key management.
+    KeyManagement,
+    /// Slot `9e`, for card authentication.
+    CardAuthentication,
+}
+
+impl PivSlot {
+    // The label of the slot's private key in the YubiKey PIV PKCS#11 module.
+    fn key_label(&self) -> &'static str {
+        match self {
+            PivSlot::Authentication => "Private key for PIV Authentication",
+            PivSlot::Signature => "Private key for Digital Signature",
+            PivSlot::KeyManagement => "Private key for Key Management",
+            PivSlot::CardAuthentication => "Private key for Card Authentication",
+        }
+    }
+}
+
+impl fmt::Display for PivSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PivSlot::Authentication => write!(f, "9a"),
+            PivSlot::Signature => write!(f, "9c"),
+            PivSlot::KeyManagement => write!(f, "9d"),
+            PivSlot::CardAuthentication => write!(f, "9e"),
+        }
+    }
+}
+
+// Describes the YubiKey with the given serial number, or any YubiKey, as the
+// label of its token in the YubiKey PIV PKCS#11 module.
+fn token_description(serial: Option<u32>) -> String {
+    match serial {
+        Some(serial) => format!("{TOKEN_LABEL_PREFIX} #{serial}"),
+        None => TOKEN_LABEL_PREFIX.to_string(),
+    }
+}
+
+// Determines if a token label is that of the YubiKey with the given serial
+// number, or of any YubiKey; labels are padded with spaces.
+fn token_matches(serial: Option<u32>, label: &str) -> bool {
+    match serial {
+        Some(_) => label.trim_end() == token_description(serial),
+        None => label.starts_with(TOKEN_LABEL_PREFIX),
+    }
+}
+
+/// Signs with a P-256 or RSA key held in a PIV slot of a YubiKey, using the
+/// YubiKey PIV PKCS#11 module (`ykcs11`) shipped with the Yubico PIV tool.
+///
+/// Keys whose touch policy requires the YubiKey to be touched block each
+/// signature until it is; a [`SignerPrompt`] set with
+/// [`YubiKeySigner::with_prompt`] is notified before each signature so the
+/// user can be asked to touch the key.
+///
+/// FIDO2 credentials are not supported, as a FIDO2 authenticator signs
+/// WebAuthn assertions rather than arbitrary messages.
+pub struct YubiKeySigner {
+    inner: Pkcs11Signer,
+    prompt: Option<Box<dyn SignerPrompt>>,
+}
+
+impl YubiKeySigner {
+    /// Opens a signer for the key in the given PIV slot, logging in with the
+    /// given PIV PIN.
+    ///
+    /// If a serial number is given, the YubiKey with that serial number is
+    /// used; otherwise the first YubiKey found is used.
+    pub fn open(
+        module: impl AsRef<Path>,
+        serial: Option<u32>,
+        slot: PivSlot,
+        pin: &SecretString,
+    ) -> Result<Self, Pkcs11Error> {
+        let inner = Pkcs11Signer::open_matching(
+            module,
+            |label| token_matches(serial, label),
+            &token_description(serial),
+            pin,
+            slot.key_label(),
+        )?;
+
+        Ok(Self {
+            inner,
+            prompt: None,
+        })
+    }
+
+    /// Sets the prompt notified before each signature.
+    pub fn with_prompt(mut self, prompt: impl SignerPrompt + 'static) -> Self {
+        self.prompt = Some(Box::new(prompt));
+        self
+    }
+}
+
+impl Signer for YubiKeySigner {
+    fn public_key(&self) -> PublicKey {
+        self.inner.public_key()
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
+        let Some(prompt) = &self.prompt else {
+            return self.inner.sign(msg);
+        };
+
+        let public_key = self.inner.public_key();
+        prompt.confirm(&public_key);
+        let signature = self.inner.sign(msg);
+        prompt.done(&public_key);
+        signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{generate_p256_pair, pkcs11::Pkcs11KeyKind, PrivateKey};
+    use signature::SignatureEncoding;
+
+    const SLOTS: [PivSlot; 4] = [
+        PivSlot::Authentication,
+        PivSlot::Signature,
+        PivSlot::KeyManagement,
+        PivSlot::CardAuthentication,
+    ];
+
+    #[test]
+    fn test_slot_keys() {
+        let ids = SLOTS.map(|slot| slot.to_string());
+        assert_eq!(ids, ["9a", "9c", "9d", "9e"]);
+
+        let labels = SLOTS.map(|slot| slot.key_label());
+        assert_eq!(labels[1], "Private key for Digital Signature");
+        for (i, label) in labels.iter().enumerate() {
+            assert!(label.starts_with("Private key for "));
+            assert!(!labels[i + 1..].contains(label), "duplicate label {label}");
+        }
+    }
+
+    #[test]
+    fn test_token_matches() {
+        let label = format!("{:<32}", "YubiKey PIV #12345678");
+        assert!(token_matches(Some(12345678), &label));
+        assert!(token_matches(None, &label));
+        assert!(!token_matches(Some(1234567), &label));
+        assert!(!token_matches(Some(123456789), &label));
+        assert!(!token_matches(None, "SoftHSM slot"));
+        assert_eq!(token_description(Some(7)), "YubiKey PIV #7");
+    }
+
+    #[test]
+    fn test_decode_p256_signature() {
+        let (public_key, private_key) = generate_p256_pair();
+        let Signature::P256(signature) = private_key.sign(b"hello").unwrap() else {
+            panic!("expected a P-256 signature");
+        };
+
+        // The token returns `r || s`, which may have a high `s`
+        let (r, s) = signature.split_scalars();
+        let high = p256::ecdsa::Signature::from_scalars(r, -s).unwrap();
+        for bytes in [signature.to_bytes(), high.to_bytes()] {
+            let decoded = Pkcs11KeyKind::EcdsaP256.decode_signature(&bytes).unwrap();
+            assert_eq!(
+                decoded,
+                Signature::P256(signature.normalize_s().unwrap_or(signature))
+            );
+            public_key.verify(b"hello", &decoded).unwrap();
+        }
+
+        // DER encoded signatures are not what PKCS#11 tokens return
+        assert!(Pkcs11KeyKind::EcdsaP256
+            .decode_signature(signature.to_der().as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_decode_rsa_signature() {
+        let private_key =
+            PrivateKey::from_pkcs8_pem(include_str!("../../tests/fixtures/keys/rsa.pem")).unwrap();
+        let public_key = private_key.public_key();
+        let signature = private_key.sign(b"hello").unwrap();
+        let Signature::RsaPss(raw) = &signature else {
+            panic!("expected an RSA-PSS signature");
+        };
+
+        let decoded = Pkcs11KeyKind::RsaPss
+            .decode_signature(&raw.to_vec())
+            .unwrap();
+        assert_eq!(decoded, signature);
+        public_key.verify(b"hello", &decoded).unwrap();
+        assert!(public_key.verify(b"goodbye", &decoded).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+//! RFC 3161 trusted timestamps.
+//!
+//! A time-stamping authority (TSA) countersigns the SHA-256 digest of a
+//! message together with the time it saw the digest, producing a
+//! [`TimestampToken`]. A token proves that the message existed at that time
+//! to anyone who trusts the authority, even if keys that signed the message
+//! are later revoked or compromised.
+//!
+//! Authorities are trusted by pinning their certificates as
+//! [`TimestampAuthority`]s; certificate chains are not built or checked.
+//! Tokens signed with ECDSA P-256 or RSASSA-PKCS1-v1_5, both over SHA-256,
+//! are supported.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use cms::{
+    cert::CertificateChoices,
+    content_info::ContentInfo,
+    signed_data::{SignedData, SignerIdentifier, SignerInfo},
+};
+use der::{
+    asn1::{ObjectIdentifier, OctetString, SetOfVec},
+    oid::db::{rfc5280, rfc5911, rfc5912},
+    Any, Decode, DecodePem, Encode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use signature::Verifier;
+use std::{fmt, time::SystemTime};
+use thiserror::Error;
+use x509_cert::{
+    ext::pkix::{ExtendedKeyUsage, SubjectKeyIdentifier},
+    spki::DecodePublicKey,
+    Certificate,
+};
+use x509_tsp::{MessageImprint, TimeStampReq, TimeStampResp, TspVersion, TstInfo};
+
+/// The content type of the TSTInfo structure signed by an authority.
+const ID_CT_TST_INFO: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+
+/// Represents an error parsing or verifying a timestamp token.
+#[derive(Debug, Error)]
+pub enum TimestampError {
+    /// The token or certificate is not valid DER.
+    #[error("timestamp token is malformed: {0}")]
+    Malformed(#[from] der::Error),
+    /// The token is not a signed TSTInfo structure.
+    #[error("timestamp token is not a signed RFC 3161 TSTInfo structure")]
+    NotTimestamp,
+    /// The authority did not grant the request.
+    #[error("time-stamping authority rejected the request with status {0}")]
+    Rejected(u8),
+    /// The token uses an unsupported digest or signature algorithm.
+    #[error("timestamp token uses unsupported algorithm `{0}`")]
+    UnsupportedAlgorithm(ObjectIdentifier),
+    /// The token is for another message.
+    #[error("timestamp token is for a different message")]
+    ImprintMismatch,
+    /// The token was not signed by a trusted authority.
+    #[error("timestamp token was not signed by a trusted time-stamping authority")]
+    UntrustedAuthority,
+    /// The certificate of the authority does not permit time stamping.
+    #[error("certificate of the time-stamping authority is not valid for time stamping")]
+    NotTimeStamping,
+    /// The signature of the token is invalid.
+    #[error("signature of the timestamp token is invalid")]
+    InvalidSignature,
+}
+
+/// Creates the DER encoding of an RFC 3161 request to timestamp the given
+/// message, asking the authority to include its certificate in the token.
+///
+/// The request may be sent to an authority over HTTP with the content type
+/// `application/timestamp-query`.
+pub fn request(message: &[u8]) -> Vec<u8> {
+    TimeStampReq {
+        version: TspVersion::V1,
+        message_imprint: imprint(message),
+        req_policy: None,
+        nonce: None,
+        cert_req: true,
+        extensions: None,
+    }
+    .to_der()
+    .expect("timestamp requests are encodable")
+}
+
+fn imprint(message: &[u8]) -> MessageImprint {
+    MessageImprint {
+        hash_algorithm: x509_cert::spki::AlgorithmIdentifier {
+            oid: rfc5912::ID_SHA_256,
+            parameters: Some(Any::null()),
+        },
+        hashed_message: OctetString::new(Sha256::digest(message).to_vec())
+            .expect("digests are valid octet strings"),
+    }
+}
+
+/// A time-stamping authority trusted to sign timestamp tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampAuthority {
+    certificate: Certificate,
+}
+
+impl TimestampAuthority {
+    /// Trusts the authority with the given DER-encoded certificate.
+    ///
+    /// The certificate must permit time stamping.
+    pub fn from_der(bytes: &[u8]) -> Result<Self, TimestampError> {
+        Self::new(Certificate::from_der(bytes)?)
+    }
+
+    /// Trusts the authority with the given PEM-encoded certificate.
+    ///
+    /// The certificate must permit time stamping.
+    pub fn from_pem(pem: &str) -> Result<Self, TimestampError> {
+        Self::new(Certificate::from_pem(pem)?)
+    }
+
+    fn new(certificate: Certificate) -> Result<Self, TimestampError> {
+        let usage = certificate.tbs_certificate.get::<ExtendedKeyUsage>()?;
+        match usage {
+            Some((_, usage)) if usage.0.contains(&rfc5280::ID_KP_TIME_STAMPING) => {
+                Ok(Self { certificate })
+            }
+            _ => Err(TimestampError::NotTimeStamping),
+        }
+    }
+
+    // Determines if this authority is the one identified by a signer.
+    fn identifies(&self, sid: &SignerIdentifier) -> bool {
+        let tbs = &self.certificate.tbs_certificate;
+        match sid {
+            SignerIdentifier::IssuerAndSerialNumber(id) => {
+                id.issuer == tbs.issuer && id.serial_number == tbs.serial_number
+            }
+            SignerIdentifier::SubjectKeyIdentifier(id) => {
+                matches!(tbs.get::<SubjectKeyIdentifier>(), Ok(Some((_, ski))) if &ski == id)
+            }
+        }
+    }
+
+    // Verifies a signature by this authority over a message.
+    fn verify(
+        &self,
+        algorithm: ObjectIdentifier,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), TimestampError> {
+        let spki = self
+            .certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()?;
+        match algorithm {
+            rfc5912::ECDSA_WITH_SHA_256 => {
+                let key = p256::ecdsa::VerifyingKey::from_public_key_der(&spki)
+                    .map_err(|_| TimestampError::InvalidSignature)?;
+                let signature = p256::ecdsa::Signature::from_der(signature)
+                    .map_err(|_| TimestampError::InvalidSignature)?;
+                key.verify(message, &signature)
+                    .map_err(|_| TimestampError::InvalidSignature)
+            }
+            rfc5912::RSA_ENCRYPTION | rfc5912::SHA_256_WITH_RSA_ENCRYPTION => {
+                let key = rsa::RsaPublicKey::from_public_key_der(&spki)
+                    .map_err(|_| TimestampError::InvalidSignature)?;
+                let signature = rsa::pkcs1v15::Signature::try_from(signature)
+                    .map_err(|_| TimestampError::InvalidSignature)?;
+                rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key)
+                    .verify(message, &signature)
+                    .map_err(|_| TimestampError::InvalidSignature)
+            }
+            algorithm => Err(TimestampError::UnsupportedAlgorithm(algorithm)),
+        }
+    }
+}
+
+/// An RFC 3161 timestamp token: a TSTInfo structure signed by a
+/// time-stamping authority.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TimestampToken {
+    bytes: Vec<u8>,
+    signed_data: SignedData,
+    info: TstInfo,
+}
+
+impl TimestampToken {
+    /// Parses a DER-encoded timestamp token.
+    ///
+    /// The token's signature is not verified.
+    pub fn from_der(bytes: &[u8]) -> Result<Self, TimestampError> {
+        let content_info = ContentInfo::from_der(bytes)?;
+        if content_info.content_type != rfc5911::ID_SIGNED_DATA {
+            return Err(TimestampError::NotTimestamp);
+        }
+
+        let signed_data: SignedData = content_info.content.decode_as()?;
+        let encap = &signed_data.encap_content_info;
+        if encap.econtent_type != ID_CT_TST_INFO || signed_data.signer_infos.0.len() != 1 {
+            return Err(TimestampError::NotTimestamp);
+        }
+        let info = encap
+            .econtent
+            .as_ref()
+            .ok_or(TimestampError::NotTimestamp)?
+            .decode_as::<OctetString>()?;
+        let info = TstInfo::from_der(info.as_bytes())?;
+
+        Ok(Self {
+            bytes: bytes.to_vec(),
+            signed_data,
+            info,
+        })
+    }
+
+    /// Parses the token of a DER-encoded response from an authority.
+    pub fn from_response(bytes: &[u8]) -> Result<Self, TimestampError> {
+        let response = TimeStampResp::from_der(bytes)?;
+        // The status is 0 (granted) or 1 (granted with modifications)
+        let status = response.status.status as u8;
+        match response.time_stamp_token {
+            Some(token) if status <= 1 => Self::from_der(&token.to_der()?),
+            _ => Err(TimestampError::Rejected(status)),
+        }
+    }
+
+    /// Gets the DER encoding of the token.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Gets the time the authority asserts it saw the message.
+    pub fn time(&self) -> SystemTime {
+        self.info.gen_time.to_system_time()
+    }
+
+    /// Checks that the token is for the given message.
+    ///
+    /// This does not establish that the token was signed by a trusted
+    /// authority; see [`TimestampToken::verify`].
+    pub fn verify_imprint(&self, message: &[u8]) -> Result<(), TimestampError> {
+        let imprint = &self.info.message_imprint;
+        if imprint.hash_algorithm.oid != rfc5912::ID_SHA_256 {
+            return Err(TimestampError::UnsupportedAlgorithm(
+                imprint.hash_algorithm.oid,
+            ));
+        }
+        if imprint.hashed_message.as_bytes() != Sha256::digest(message).as_slice() {
+            return Err(TimestampError::ImprintMismatch);
+        }
+        Ok(())
+    }
+
+    /// Verifies that the token is for the given message and was signed by
+    /// one of the given authorities, returning the time of the token.
+    pub fn verify(
+        &self,
+        message: &[u8],
+        authorities: &[TimestampAuthority],
+    ) -> Result<SystemTime, TimestampError> {
+        self.verify_imprint(message)?;
+
+        let signer = self.signer();
+        let authority = authorities
+            .iter()
+            .find(|a| a.identifies(&signer.sid))
+            .ok_or(TimestampError::UntrustedAuthority)?;
+
+        // An embedded certificate for the signer must be the pinned one
+        let embedded = self
+            .signed_data
+            .certificates
+            .iter()
+            .flat_map(|set| set.0.iter())
+            .filter_map(|choice| match choice {
+                CertificateChoices::Certificate(cert) => Some(cert),
+                CertificateChoices::Other(_) => None,
+            })
+            .find(|cert| {
+                TimestampAuthority {
+                    certificate: (*cert).clone(),
+                }
+                .identifies(&signer.sid)
+            });
+        if embedded.is_some_and(|cert| cert != &authority.certificate) {
+            return Err(TimestampError::UntrustedAuthority);
+        }
+
+        if signer.digest_alg.oid != rfc5912::ID_SHA_256 {
+            return Err(TimestampError::UnsupportedAlgorithm(signer.digest_alg.oid));
+        }
+        let attrs = signer
+            .signed_attrs
+            .as_ref()
+            .ok_or(TimestampError::NotTimestamp)?;
+        let econtent = self
+            .signed_data
+            .encap_content_info
+            .econtent
+            .as_ref()
+            .ok_or(TimestampError::NotTimestamp)?
+            .decode_as::<OctetString>()?;
+
+        // The signed attributes must bind the signature to the TSTInfo
+        let attr = |oid| {
+            attrs
+                .iter()
+                .find(|attr| attr.oid == oid)
+                .and_then(|attr| attr.values.get(0))
+        };
+        let content_type = attr(rfc5911::ID_CONTENT_TYPE)
+            .map(|value| value.decode_as::<ObjectIdentifier>())
+            .transpose()?;
+        let digest = attr(rfc5911::ID_MESSAGE_DIGEST)
+            .map(|value| value.decode_as::<OctetString>())
+            .transpose()?;
+        if content_type != Some(ID_CT_TST_INFO)
+            || digest.as_ref().map(OctetString::as_bytes)
+                != Some(Sha256::digest(econtent.as_bytes()).as_slice())
+        {
+            return Err(TimestampError::InvalidSignature);
+        }
+
+        // The signature is over the DER encoding of the attributes as a SET OF
+        let attrs: &SetOfVec<_> = attrs;
+        authority.verify(
+            signer.signature_algorithm.oid,
+            &attrs.to_der()?,
+            signer.signature.as_bytes(),
+        )?;
+
+        Ok(self.time())
+    }
+
+    fn signer(&self) -> &SignerInfo {
+        self.signed_data
+            .signer_infos
+            .0
+            .get(0)
+            .expect("tokens have one signer")
+    }
+}
+
+impl fmt::Debug for TimestampToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimestampToken")
+            .field("time", &self.time())
+            .field("bytes", &STANDARD.encode(&self.bytes))
+            .finish()
+    }
+}
+
+impl Serialize for TimestampToken {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(&self.bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for TimestampToken {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes = STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)?;
+        Self::from_der(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/timestamp/",
+                $name
+            ))
+        };
+    }
+
+    const MESSAGE: &[u8] = b"hello world";
+
+    fn authority(pem: &[u8]) -> TimestampAuthority {
+        TimestampAuthority::from_pem(std::str::from_utf8(pem).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_request_matches_openssl() {
+        // Created with `openssl ts -query -sha256 -cert -no_nonce`
+        assert_eq!(request(MESSAGE), fixture!("req.tsq"));
+    }
+
+    #[test]
+    fn test_verify_tokens() {
+        let p256 = authority(fixture!("p256.crt"));
+        let rsa = authority(fixture!("rsa.crt"));
+
+        let token = TimestampToken::from_response(fixture!("p256.tsr")).unwrap();
+        let trusted = [rsa, p256];
+        let time = token.verify(MESSAGE, &trusted).unwrap();
+        assert_eq!(time, token.time());
+
+        let token = TimestampToken::from_der(fixture!("rsa.tst")).unwrap();
+        token.verify(MESSAGE, &trusted[..1]).unwrap();
+
+        // The token must be for the message and by a trusted authority
+        assert!(matches!(
+            token.verify(b"goodbye world", &trusted[..1]),
+            Err(TimestampError::ImprintMismatch)
+        ));
+        assert!(matches!(
+            token.verify(MESSAGE, &trusted[1..]),
+            Err(TimestampError::UntrustedAuthority)
+        ));
+
+        // Round trips through serde
+        let json = serde_json::to_string(&token).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TimestampToken>(&json).unwrap(),
+            token
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_tokens() {
+        let rsa = authority(fixture!("rsa.crt"));
+
+        // Authorities must be certified for time stamping
+        assert!(matches!(
+            TimestampAuthority::from_pem(std::str::from_utf8(fixture!("nots.crt")).unwrap()),
+            Err(TimestampError::NotTimeStamping)
+        ));
+
+        // Changing the signature invalidates the token
+        let mut bytes = fixture!("rsa.tst").to_vec();
+        let len = bytes.len();
+        bytes[len - 1] ^= 1;
+        let token = TimestampToken::from_der(&bytes).unwrap();
+        token.verify_imprint(MESSAGE).unwrap();
+        assert!(matches!(
+            token.verify(MESSAGE, &[rsa]),
+            Err(TimestampError::InvalidSignature)
+        ));
+
+        assert!(TimestampToken::from_der(b"not a token").is_err());
+    }
+}
@@ -6,7 +6,7 @@ pub trait ByteVisitor {
     }
 }
 
-impl<'a, BV: ?Sized + ByteVisitor> ByteVisitor for &'a mut BV {
+impl<BV: ?Sized + ByteVisitor> ByteVisitor for &mut BV {
     fn visit_bytes(&mut self, bytes: impl AsRef<[u8]>) {
         (self as &mut BV).visit_bytes(bytes)
     }
@@ -16,7 +16,7 @@ pub trait VisitBytes {
     fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV);
 }
 
-impl<'a, VB: ?Sized + VisitBytes> VisitBytes for &'a VB {
+impl<VB: ?Sized + VisitBytes> VisitBytes for &VB {
     fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
         (self as &VB).visit(visitor)
     }
@@ -28,13 +28,13 @@ impl VisitBytes for u8 {
     }
 }
 
-impl<'a> VisitBytes for &'a [u8] {
+impl VisitBytes for &[u8] {
     fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
         visitor.visit_bytes(self);
     }
 }
 
-impl<'a> VisitBytes for &'a str {
+impl VisitBytes for &str {
     fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
         visitor.visit_bytes(self.as_bytes());
     }
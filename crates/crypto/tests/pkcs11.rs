@@ -0,0 +1,155 @@
+//! Tests of the PKCS#11 signer against a SoftHSMv2 token.
+//!
+//! The SoftHSMv2 module is found at the path in `SOFTHSM2_MODULE` or at the
+//! default install locations; tokens are created in a temporary directory.
+
+#![cfg(feature = "pkcs11")]
+
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    mechanism::Mechanism,
+    object::Attribute,
+    session::UserType,
+    types::AuthPin,
+};
+use secrecy::SecretString;
+use std::{fs, path::PathBuf};
+use warg_crypto::signing::{Pkcs11Error, Pkcs11Signer, Signer};
+
+const TOKEN_LABEL: &str = "warg-test";
+const SO_PIN: &str = "12345678";
+const USER_PIN: &str = "1234";
+
+// DER object identifiers of the P-256 curve and of Ed25519
+const P256_PARAMS: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const ED25519_PARAMS: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+
+fn module() -> PathBuf {
+    if let Some(path) = std::env::var_os("SOFTHSM2_MODULE") {
+        return path.into();
+    }
+
+    [
+        "/usr/lib/softhsm/libsofthsm2.so",
+        "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+        "/usr/local/lib/softhsm/libsofthsm2.so",
+        "/opt/homebrew/lib/softhsm/libsofthsm2.so",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .find(|path| path.exists())
+    .expect("SoftHSMv2 is not installed; set `SOFTHSM2_MODULE` to the path of its module")
+}
+
+/// Initializes a token holding a P-256, an Ed25519, and an RSA key pair.
+fn create_token(module: &PathBuf, dir: &tempfile::TempDir) {
+    let tokens = dir.path().join("tokens");
+    fs::create_dir(&tokens).unwrap();
+    let config = dir.path().join("softhsm2.conf");
+    fs::write(
+        &config,
+        format!(
+            "directories.tokendir = {}\nobjectstore.backend = file\n",
+            tokens.display()
+        ),
+    )
+    .unwrap();
+    std::env::set_var("SOFTHSM2_CONF", &config);
+
+    let pkcs11 = Pkcs11::new(module).unwrap();
+    pkcs11.initialize(CInitializeArgs::OsThreads).unwrap();
+
+    let slot = pkcs11.get_slots_with_token().unwrap()[0];
+    pkcs11
+        .init_token(slot, &AuthPin::new(SO_PIN.into()), TOKEN_LABEL)
+        .unwrap();
+
+    // The initialized token may move to another slot
+    let slot = pkcs11
+        .get_slots_with_initialized_token()
+        .unwrap()
+        .into_iter()
+        .find(|slot| pkcs11.get_token_info(*slot).unwrap().label() == TOKEN_LABEL)
+        .unwrap();
+    let session = pkcs11.open_rw_session(slot).unwrap();
+    session
+        .login(UserType::So, Some(&AuthPin::new(SO_PIN.into())))
+        .unwrap();
+    session.init_pin(&AuthPin::new(USER_PIN.into())).unwrap();
+    session.logout().unwrap();
+    session
+        .login(UserType::User, Some(&AuthPin::new(USER_PIN.into())))
+        .unwrap();
+
+    let generate = |mechanism: &Mechanism, label: &str, public: &[Attribute]| {
+        let id = Attribute::Id(label.as_bytes().to_vec());
+        let mut public = public.to_vec();
+        public.extend([Attribute::Token(true), Attribute::Verify(true), id.clone()]);
+        let private = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Sign(true),
+            Attribute::Label(label.as_bytes().to_vec()),
+            id,
+        ];
+        session
+            .generate_key_pair(mechanism, &public, &private)
+            .unwrap();
+    };
+    generate(
+        &Mechanism::EccKeyPairGen,
+        "p256",
+        &[Attribute::EcParams(P256_PARAMS.to_vec())],
+    );
+    generate(
+        &Mechanism::EccEdwardsKeyPairGen,
+        "ed25519",
+        &[Attribute::EcParams(ED25519_PARAMS.to_vec())],
+    );
+    generate(
+        &Mechanism::RsaPkcsKeyPairGen,
+        "rsa",
+        &[
+            Attribute::ModulusBits(2048.into()),
+            Attribute::PublicExponent(vec![0x01, 0x00, 0x01]),
+        ],
+    );
+
+    // Signers log in themselves, and closing the context finalizes the module
+    session.logout().unwrap();
+}
+
+#[test]
+fn signs_with_softhsm_keys() {
+    let module = module();
+    let dir = tempfile::tempdir().unwrap();
+    create_token(&module, &dir);
+    let pin = SecretString::new(USER_PIN.into());
+
+    for (label, algorithm) in [
+        ("p256", "ecdsa-p256"),
+        ("ed25519", "ed25519"),
+        ("rsa", "rsa-pss-2048"),
+    ] {
+        let signer = Pkcs11Signer::open(&module, TOKEN_LABEL, &pin, label).unwrap();
+        let public_key = signer.public_key();
+        assert!(
+            public_key.to_string().starts_with(algorithm),
+            "unexpected public key for `{label}`: {public_key}"
+        );
+
+        let signature = signer.sign(b"hello world").unwrap();
+        public_key.verify(b"hello world", &signature).unwrap();
+        assert!(public_key.verify(b"goodbye world", &signature).is_err());
+    }
+
+    assert!(matches!(
+        Pkcs11Signer::open(&module, "missing", &pin, "p256"),
+        Err(Pkcs11Error::TokenNotFound(label)) if label == "missing"
+    ));
+    assert!(matches!(
+        Pkcs11Signer::open(&module, TOKEN_LABEL, &pin, "missing"),
+        Err(Pkcs11Error::KeyNotFound(label)) if label == "missing"
+    ));
+}
@@ -0,0 +1,148 @@
+//! Manifests of registry log ranges archived to cold storage.
+//!
+//! Old records of the registry log may be moved out of a registry's data
+//! store into segments held in cold storage. The operator signs an
+//! [`ArchiveManifest`] committing to the digest of each segment as of a
+//! checkpoint, so that clients fetching archived ranges on demand can verify
+//! them.
+
+use crate::registry::{Checkpoint, RegistryIndex};
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, time::SystemTime};
+use warg_crypto::{
+    hash::AnyHash,
+    prefix::{self, VisitPrefixEncode},
+    ByteVisitor, Signable, VisitBytes,
+};
+
+/// A contiguous range of the registry log archived as a single segment.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedRange {
+    /// The registry index of the first record in the segment.
+    pub start: RegistryIndex,
+    /// The registry index after the last record in the segment.
+    pub end: RegistryIndex,
+    /// The digest of the segment.
+    pub digest: AnyHash,
+}
+
+impl ArchivedRange {
+    /// Gets the range of registry indexes of the segment.
+    pub fn range(&self) -> Range<RegistryIndex> {
+        self.start..self.end
+    }
+}
+
+/// A statement by the registry operator of the segments archived as of the
+/// given checkpoint.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub checkpoint: Checkpoint,
+    pub timestamp: u64,
+    /// The archived segments, in registry order.
+    pub segments: Vec<ArchivedRange>,
+}
+
+impl ArchiveManifest {
+    pub fn new(
+        checkpoint: Checkpoint,
+        segments: Vec<ArchivedRange>,
+        time: SystemTime,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            checkpoint,
+            timestamp: time.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+            segments,
+        })
+    }
+
+    pub fn now(checkpoint: Checkpoint, segments: Vec<ArchivedRange>) -> anyhow::Result<Self> {
+        Self::new(checkpoint, segments, SystemTime::now())
+    }
+
+    /// Gets the segments overlapping the given range of registry indexes.
+    pub fn segments_overlapping(
+        &self,
+        range: Range<RegistryIndex>,
+    ) -> impl Iterator<Item = &ArchivedRange> {
+        self.segments
+            .iter()
+            .filter(move |s| s.start < range.end && range.start < s.end)
+    }
+
+    /// Checks if every index in the given range is archived.
+    pub fn covers(&self, range: Range<RegistryIndex>) -> bool {
+        let mut next = range.start;
+        for segment in self.segments_overlapping(range.clone()) {
+            if segment.start > next {
+                return false;
+            }
+            next = next.max(segment.end);
+        }
+        next >= range.end
+    }
+}
+
+impl Signable for ArchiveManifest {
+    const PREFIX: &'static [u8] = b"WARG-ARCHIVE-MANIFEST-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for ArchiveManifest {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-ARCHIVE-MANIFEST-V0");
+        visitor.visit_unsigned(self.checkpoint.log_length as u64);
+        visitor.visit_str(&self.checkpoint.log_root.to_string());
+        visitor.visit_str(&self.checkpoint.map_root.to_string());
+        visitor.visit_unsigned(self.timestamp);
+        visitor.visit_unsigned(self.segments.len() as u64);
+        for segment in &self.segments {
+            visitor.visit_unsigned(segment.start as u64);
+            visitor.visit_unsigned(segment.end as u64);
+            visitor.visit_str(&segment.digest.to_string());
+        }
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for ArchiveManifest {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::hash::{Hash, Sha256};
+
+    #[test]
+    fn finds_overlapping_segments() {
+        let segment = |start, end| ArchivedRange {
+            start,
+            end,
+            digest: Hash::<Sha256>::of(format!("{start}-{end}").as_str()).into(),
+        };
+        let manifest = ArchiveManifest::now(
+            Checkpoint {
+                log_root: Hash::<Sha256>::default().into(),
+                log_length: 10,
+                map_root: Hash::<Sha256>::default().into(),
+            },
+            vec![segment(0, 4), segment(4, 8)],
+        )
+        .unwrap();
+
+        let overlapping: Vec<_> = manifest
+            .segments_overlapping(3..5)
+            .map(ArchivedRange::range)
+            .collect();
+        assert_eq!(overlapping, [0..4, 4..8]);
+        assert_eq!(manifest.segments_overlapping(8..10).count(), 0);
+
+        assert!(manifest.covers(0..8));
+        assert!(manifest.covers(2..6));
+        assert!(!manifest.covers(6..9));
+    }
+}
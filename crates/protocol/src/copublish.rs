@@ -0,0 +1,286 @@
+//! Co-publication of records across packages.
+//!
+//! A release spanning several packages, such as a workspace release, is
+//! published as one record in each package log along with a
+//! [`CoPublication`] listing every part, signed by the publishing key. The
+//! registry sequences all the parts of a co-publication into the same
+//! checkpoint, and a client checks with [`CoPublication::check`] that either
+//! every part landed or none did; a co-publication only some parts of which
+//! landed must not be trusted.
+
+use crate::{
+    registry::{Checkpoint, PackageName, RecordId, RegistryIndex},
+    SerdeEnvelope,
+};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use thiserror::Error;
+use warg_crypto::{
+    prefix::{self, VisitPrefixEncode},
+    signing, ByteVisitor, Encode, Signable, VisitBytes,
+};
+
+/// Represents an error creating or verifying a co-publication.
+#[derive(Debug, Error)]
+pub enum CoPublicationError {
+    /// The co-publication has no parts.
+    #[error("the co-publication must have at least one part")]
+    NoParts,
+    /// The co-publication has more than one part for a package.
+    #[error("the co-publication has more than one part for package `{0}`")]
+    DuplicatePackage(PackageName),
+    /// The co-publication time is before the Unix epoch.
+    #[error("the co-publication time is before the Unix epoch")]
+    InvalidTime,
+    /// The co-publication is signed by a key other than its key.
+    #[error("the co-publication must be signed by its key `{0}`")]
+    KeyMismatch(signing::KeyID),
+    /// The co-publication could not be signed.
+    #[error("failed to sign co-publication: {0}")]
+    Signing(#[from] signing::SignatureError),
+    /// The signature of the co-publication is invalid.
+    #[error("the signature of the co-publication is invalid")]
+    InvalidSignature,
+    /// Some but not all parts of the co-publication landed in the checkpoint.
+    #[error("only some parts of the co-publication landed: missing record(s) for {}", .missing.iter().map(|p| format!("`{p}`")).collect::<Vec<_>>().join(", "))]
+    Partial {
+        /// The packages whose parts landed.
+        landed: Vec<PackageName>,
+        /// The packages whose parts did not land.
+        missing: Vec<PackageName>,
+    },
+}
+
+/// The status of a co-publication as of a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoPublicationStatus {
+    /// No part of the co-publication has landed yet.
+    Pending,
+    /// Every part of the co-publication has landed.
+    Complete,
+}
+
+/// A part of a co-publication: a record in a package log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoPublishedRecord {
+    /// The package of the record.
+    pub package: PackageName,
+    /// The id of the record in the package log.
+    pub record_id: RecordId,
+}
+
+/// A release of records in several package logs that must land together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoPublication {
+    /// The key publishing the records.
+    pub key: signing::PublicKey,
+    /// The records of the co-publication, one per package.
+    pub parts: Vec<CoPublishedRecord>,
+    /// When the co-publication was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl CoPublication {
+    /// Creates a co-publication of the given records, made at the given time.
+    pub fn new(
+        key: signing::PublicKey,
+        parts: impl IntoIterator<Item = CoPublishedRecord>,
+        time: SystemTime,
+    ) -> Result<Self, CoPublicationError> {
+        let mut unique: Vec<CoPublishedRecord> = Vec::new();
+        for part in parts {
+            if unique.iter().any(|p| p.package == part.package) {
+                return Err(CoPublicationError::DuplicatePackage(part.package));
+            }
+            unique.push(part);
+        }
+        if unique.is_empty() {
+            return Err(CoPublicationError::NoParts);
+        }
+
+        Ok(Self {
+            key,
+            parts: unique,
+            timestamp: time
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|_| CoPublicationError::InvalidTime)?
+                .as_secs(),
+        })
+    }
+
+    /// Signs the co-publication with its key.
+    pub fn sign<S>(self, signer: &S) -> Result<SerdeEnvelope<Self>, CoPublicationError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        if signer.public_key() != self.key {
+            return Err(CoPublicationError::KeyMismatch(self.key.fingerprint()));
+        }
+
+        Ok(SerdeEnvelope::signed_contents(signer, self)?)
+    }
+
+    /// Verifies that the given co-publication is signed by its key.
+    pub fn verify_signed(copublication: &SerdeEnvelope<Self>) -> Result<(), CoPublicationError> {
+        let key = &copublication.as_ref().key;
+        if &key.fingerprint() != copublication.key_id() {
+            return Err(CoPublicationError::KeyMismatch(key.fingerprint()));
+        }
+
+        Self::verify(
+            key,
+            &copublication.as_ref().encode(),
+            copublication.signature(),
+        )
+        .map_err(|_| CoPublicationError::InvalidSignature)
+    }
+
+    /// Gets the part of the co-publication for the given package.
+    pub fn part(&self, package: &PackageName) -> Option<&CoPublishedRecord> {
+        self.parts.iter().find(|p| &p.package == package)
+    }
+
+    /// Checks which parts of the co-publication landed in the given
+    /// checkpoint.
+    ///
+    /// `index_of` returns the registry index a part was published at, if it
+    /// has been published at all. Returns an error if only some of the
+    /// parts landed in the checkpoint.
+    pub fn check(
+        &self,
+        checkpoint: &Checkpoint,
+        mut index_of: impl FnMut(&CoPublishedRecord) -> Option<RegistryIndex>,
+    ) -> Result<CoPublicationStatus, CoPublicationError> {
+        let (landed, missing): (Vec<_>, Vec<_>) = self
+            .parts
+            .iter()
+            .partition(|part| index_of(part).is_some_and(|index| index < checkpoint.log_length));
+
+        match (landed.is_empty(), missing.is_empty()) {
+            (_, true) => Ok(CoPublicationStatus::Complete),
+            (true, false) => Ok(CoPublicationStatus::Pending),
+            (false, false) => Err(CoPublicationError::Partial {
+                landed: landed.into_iter().map(|p| p.package.clone()).collect(),
+                missing: missing.into_iter().map(|p| p.package.clone()).collect(),
+            }),
+        }
+    }
+}
+
+impl Signable for CoPublication {
+    const PREFIX: &'static [u8] = b"WARG-CO-PUBLICATION-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for CoPublication {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-CO-PUBLICATION-V0");
+        visitor.visit_str(&self.key.to_string());
+        visitor.visit_unsigned(self.parts.len() as u64);
+        for part in &self.parts {
+            visitor.visit_str(part.package.as_ref());
+            visitor.visit_str(&part.record_id.to_string());
+        }
+        visitor.visit_unsigned(self.timestamp);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for CoPublication {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::{
+        hash::{AnyHash, Hash, Sha256},
+        signing::generate_p256_pair,
+    };
+
+    fn part(package: &str) -> CoPublishedRecord {
+        CoPublishedRecord {
+            package: package.parse().unwrap(),
+            record_id: RecordId::from(AnyHash::from(Hash::<Sha256>::of(package))),
+        }
+    }
+
+    #[test]
+    fn test_copublication() {
+        let (public_key, private_key) = generate_p256_pair();
+        assert!(matches!(
+            CoPublication::new(
+                public_key.clone(),
+                [part("example:foo"), part("example:foo")],
+                SystemTime::now()
+            ),
+            Err(CoPublicationError::DuplicatePackage(_))
+        ));
+        assert!(matches!(
+            CoPublication::new(public_key.clone(), [], SystemTime::now()),
+            Err(CoPublicationError::NoParts)
+        ));
+
+        let copublication = CoPublication::new(
+            public_key,
+            [part("example:foo"), part("example:bar")],
+            SystemTime::now(),
+        )
+        .unwrap();
+        let signed = copublication.clone().sign(&private_key).unwrap();
+        let signed: SerdeEnvelope<CoPublication> =
+            serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        CoPublication::verify_signed(&signed).unwrap();
+
+        // Only the key of the co-publication may sign it
+        let (_, other_key) = generate_p256_pair();
+        assert!(matches!(
+            copublication.clone().sign(&other_key),
+            Err(CoPublicationError::KeyMismatch(_))
+        ));
+
+        // Dropping a part invalidates the signature
+        let mut tampered = copublication.clone();
+        tampered.parts.pop();
+        let tampered = SerdeEnvelope::from_parts_unchecked(
+            tampered,
+            signed.key_id().clone(),
+            signed.signature().clone(),
+        );
+        assert!(matches!(
+            CoPublication::verify_signed(&tampered),
+            Err(CoPublicationError::InvalidSignature)
+        ));
+
+        // The parts land all or nothing
+        let checkpoint = Checkpoint {
+            log_root: Hash::<Sha256>::of("log").into(),
+            log_length: 10,
+            map_root: Hash::<Sha256>::of("map").into(),
+        };
+        let foo: PackageName = "example:foo".parse().unwrap();
+        assert_eq!(copublication.part(&foo), Some(&part("example:foo")));
+        assert_eq!(
+            copublication.check(&checkpoint, |_| None).unwrap(),
+            CoPublicationStatus::Pending
+        );
+        assert_eq!(
+            copublication.check(&checkpoint, |_| Some(12)).unwrap(),
+            CoPublicationStatus::Pending
+        );
+        assert_eq!(
+            copublication.check(&checkpoint, |_| Some(8)).unwrap(),
+            CoPublicationStatus::Complete
+        );
+        match copublication.check(&checkpoint, |p| (p.package == foo).then_some(8)) {
+            Err(CoPublicationError::Partial { landed, missing }) => {
+                assert_eq!(landed, [foo]);
+                assert_eq!(missing, ["example:bar".parse::<PackageName>().unwrap()]);
+            }
+            other => panic!("unexpected status {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Discovery of the keys of a registry operator.
+//!
+//! A new client has no operator log state with which to verify the first
+//! checkpoint it fetches. Instead, a registry may serve an [`OperatorKeys`]
+//! document signed by a root of trust known to the client out of band,
+//! listing the keys the operator signs with.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use warg_crypto::{
+    prefix::{self, VisitPrefixEncode},
+    signing, ByteVisitor, Signable, VisitBytes,
+};
+
+/// A statement by a root of trust of the keys a registry operator signs with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorKeys {
+    /// The operator keys.
+    pub keys: Vec<signing::PublicKey>,
+    /// The time the statement was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The time the statement expires, in seconds since the Unix epoch.
+    pub expires: u64,
+}
+
+impl OperatorKeys {
+    pub fn new(
+        keys: Vec<signing::PublicKey>,
+        time: SystemTime,
+        valid_for: Duration,
+    ) -> anyhow::Result<Self> {
+        let timestamp = time.duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(Self {
+            keys,
+            timestamp,
+            expires: timestamp.saturating_add(valid_for.as_secs()),
+        })
+    }
+
+    pub fn now(keys: Vec<signing::PublicKey>, valid_for: Duration) -> anyhow::Result<Self> {
+        Self::new(keys, SystemTime::now(), valid_for)
+    }
+
+    /// Determines if the statement has expired as of the given time.
+    pub fn expired(&self, now: SystemTime) -> bool {
+        now.duration_since(UNIX_EPOCH)
+            .is_ok_and(|now| now.as_secs() > self.expires)
+    }
+
+    /// Gets the listed key with the given key ID.
+    pub fn key(&self, key_id: &signing::KeyID) -> Option<&signing::PublicKey> {
+        self.keys.iter().find(|key| &key.fingerprint() == key_id)
+    }
+}
+
+impl Signable for OperatorKeys {
+    const PREFIX: &'static [u8] = b"WARG-OPERATOR-KEYS-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for OperatorKeys {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-OPERATOR-KEYS-V0");
+        visitor.visit_unsigned(self.keys.len() as u64);
+        for key in &self.keys {
+            visitor.visit_str(&key.to_string());
+        }
+        visitor.visit_unsigned(self.timestamp);
+        visitor.visit_unsigned(self.expires);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for OperatorKeys {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerdeEnvelope;
+    use warg_crypto::{signing::generate_p256_pair, Encode};
+
+    #[test]
+    fn verifies_operator_keys() {
+        let (root_public, root_private) = generate_p256_pair();
+        let (operator_public, _) = generate_p256_pair();
+        let time = SystemTime::now();
+        let keys = OperatorKeys::new(vec![operator_public.clone()], time, Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(
+            keys.key(&operator_public.fingerprint()),
+            Some(&operator_public)
+        );
+        assert_eq!(keys.key(&root_public.fingerprint()), None);
+        assert!(!keys.expired(time + Duration::from_secs(60)));
+        assert!(keys.expired(time + Duration::from_secs(61)));
+
+        let envelope = SerdeEnvelope::signed_contents(&root_private, keys).unwrap();
+        OperatorKeys::verify(
+            &root_public,
+            &envelope.as_ref().encode(),
+            envelope.signature(),
+        )
+        .unwrap();
+        assert!(OperatorKeys::verify(
+            &operator_public,
+            &envelope.as_ref().encode(),
+            envelope.signature()
+        )
+        .is_err());
+    }
+}
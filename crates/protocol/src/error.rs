@@ -0,0 +1,125 @@
+//! A unified error type for the registry crates.
+//!
+//! Applications that use the protocol, cryptography, and transparency crates
+//! together may convert any of their errors into an [`Error`] and match on its
+//! [`ErrorKind`] rather than on each crate's error types.
+
+use crate::{operator, package, ParseEnvelopeError};
+use thiserror::Error;
+use warg_crypto::{
+    hash::{AnyHashError, HashError},
+    signing::{PrivateKeyParseError, PublicKeyParseError, SignatureError, SignatureParseError},
+};
+use warg_transparency::log::{ConsistencyProofError, InclusionProofError};
+
+/// Represents the category of an [`Error`].
+///
+/// The kind of a given error is stable across releases, though new kinds may
+/// be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A record failed validation against the state of its log.
+    Validation,
+    /// A signature failed verification.
+    Signature,
+    /// A value could not be parsed or decoded.
+    Decode,
+    /// A hash used an unexpected algorithm or had an unexpected length.
+    Hash,
+    /// A transparency proof failed verification.
+    Proof,
+}
+
+/// Represents an error from any of the registry crates.
+///
+/// The original error is preserved: an `Error` displays as the error it was
+/// converted from and reports that error's source chain.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// An operator record failed validation.
+    #[error(transparent)]
+    OperatorValidation(#[from] operator::ValidationError),
+    /// A package record failed validation.
+    #[error(transparent)]
+    PackageValidation(#[from] package::ValidationError),
+    /// An envelope could not be parsed.
+    #[error(transparent)]
+    Envelope(#[from] ParseEnvelopeError),
+    /// A signature failed verification.
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+    /// A signature could not be parsed.
+    #[error(transparent)]
+    SignatureParse(#[from] SignatureParseError),
+    /// A public key could not be parsed.
+    #[error(transparent)]
+    PublicKeyParse(#[from] PublicKeyParseError),
+    /// A private key could not be parsed.
+    #[error(transparent)]
+    PrivateKeyParse(#[from] PrivateKeyParseError),
+    /// A hash could not be parsed.
+    #[error(transparent)]
+    HashParse(#[from] AnyHashError),
+    /// A hash used an unexpected algorithm or had an unexpected length.
+    #[error(transparent)]
+    Hash(#[from] HashError),
+    /// An inclusion proof failed verification.
+    #[error(transparent)]
+    InclusionProof(#[from] InclusionProofError),
+    /// A consistency proof failed verification.
+    #[error(transparent)]
+    ConsistencyProof(#[from] ConsistencyProofError),
+}
+
+impl Error {
+    /// Gets the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::OperatorValidation(operator::ValidationError::SignatureError(_))
+            | Self::PackageValidation(
+                package::ValidationError::SignatureError(_)
+                | package::ValidationError::InvalidEntrySignature { .. }
+                | package::ValidationError::InvalidCountersignature,
+            )
+            | Self::Signature(_) => ErrorKind::Signature,
+            Self::OperatorValidation(_) | Self::PackageValidation(_) => ErrorKind::Validation,
+            Self::Envelope(_)
+            | Self::SignatureParse(_)
+            | Self::PublicKeyParse(_)
+            | Self::PrivateKeyParse(_)
+            | Self::HashParse(_) => ErrorKind::Decode,
+            Self::Hash(_) => ErrorKind::Hash,
+            Self::InclusionProof(_) | Self::ConsistencyProof(_) => ErrorKind::Proof,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+    use warg_crypto::hash::AnyHash;
+
+    #[test]
+    fn error_kinds() {
+        let err = Error::from(package::ValidationError::FirstEntryIsNotInit);
+        assert_eq!(err.kind(), ErrorKind::Validation);
+        assert_eq!(
+            err.to_string(),
+            package::ValidationError::FirstEntryIsNotInit.to_string()
+        );
+
+        let err = Error::from(package::ValidationError::InvalidCountersignature);
+        assert_eq!(err.kind(), ErrorKind::Signature);
+
+        // Source chains of converted errors are preserved
+        let inner = "sha256:zz".parse::<AnyHash>().unwrap_err();
+        let expected = inner.source().map(|s| s.to_string());
+        assert!(expected.is_some());
+        let err = Error::from(inner);
+        assert_eq!(err.kind(), ErrorKind::Decode);
+        assert_eq!(err.source().map(|s| s.to_string()), expected);
+    }
+}
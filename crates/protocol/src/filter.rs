@@ -0,0 +1,214 @@
+//! Inclusion filters over the packages and releases of a registry.
+//!
+//! The registry periodically publishes a signed [`PackageFilter`] so that
+//! clients can rule out packages and releases that definitely do not exist
+//! without requesting a full non-inclusion proof.
+
+use crate::registry::{Checkpoint, PackageName};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use std::time::SystemTime;
+use warg_crypto::{
+    hash::{Hash, Sha256},
+    prefix::{self, VisitPrefixEncode},
+    ByteVisitor, Signable, VisitBytes,
+};
+
+/// The maximum number of hash functions used by an inclusion filter.
+const MAX_HASHES: u32 = 32;
+
+/// A Bloom filter over package names and released versions.
+///
+/// A filter never reports a false negative: if [`InclusionFilter::may_contain`]
+/// returns `false`, the key was not inserted.
+#[serde_as]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionFilter {
+    /// The number of hash functions applied to each key.
+    pub hashes: u32,
+    /// The bits of the filter.
+    #[serde_as(as = "Base64")]
+    pub bits: Vec<u8>,
+}
+
+impl InclusionFilter {
+    /// Creates an empty filter sized for the given number of keys and
+    /// false positive rate.
+    pub fn new(keys: usize, false_positive_rate: f64) -> Self {
+        let keys = keys.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits = (-(keys * rate.ln()) / (ln2 * ln2)).ceil().max(8.0) as usize;
+        let hashes = ((bits as f64 / keys) * ln2).round() as u32;
+        Self {
+            hashes: hashes.clamp(1, MAX_HASHES),
+            bits: vec![0; bits.div_ceil(8)],
+        }
+    }
+
+    /// Creates a filter containing the given packages and their released versions.
+    pub fn from_packages<'a>(
+        packages: impl IntoIterator<Item = (&'a PackageName, &'a [Version])>,
+        false_positive_rate: f64,
+    ) -> Self {
+        let keys: Vec<_> = packages
+            .into_iter()
+            .flat_map(|(name, versions)| {
+                std::iter::once(package_key(name))
+                    .chain(versions.iter().map(|version| release_key(name, version)))
+            })
+            .collect();
+
+        let mut filter = Self::new(keys.len(), false_positive_rate);
+        for key in &keys {
+            filter.insert(key);
+        }
+        filter
+    }
+
+    /// Inserts a key into the filter.
+    pub fn insert(&mut self, key: &str) {
+        for index in self.indexes(key) {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    /// Checks if the filter may contain the given key.
+    pub fn may_contain(&self, key: &str) -> bool {
+        self.indexes(key)
+            .all(|index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Checks if the filter may contain the given package.
+    pub fn may_contain_package(&self, name: &PackageName) -> bool {
+        self.may_contain(&package_key(name))
+    }
+
+    /// Checks if the filter may contain the given release of a package.
+    pub fn may_contain_release(&self, name: &PackageName, version: &Version) -> bool {
+        self.may_contain(&release_key(name, version))
+    }
+
+    // Computes the bit indexes of a key using double hashing.
+    fn indexes(&self, key: &str) -> impl Iterator<Item = usize> {
+        let bits = (self.bits.len() * 8) as u64;
+        let hash = Hash::<Sha256>::of(key);
+        let bytes = hash.bytes();
+        let first = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let second = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) | 1;
+        let hashes = if bits == 0 {
+            0
+        } else {
+            self.hashes.min(MAX_HASHES) as u64
+        };
+        (0..hashes).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % bits) as usize)
+    }
+}
+
+/// Gets the filter key of a package.
+fn package_key(name: &PackageName) -> String {
+    name.to_string()
+}
+
+/// Gets the filter key of a released version of a package.
+fn release_key(name: &PackageName, version: &Version) -> String {
+    format!("{name}@{version}", name = package_key(name))
+}
+
+/// A statement by the registry operator that the given filter contains the
+/// packages and releases of the registry as of the given checkpoint.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageFilter {
+    pub checkpoint: Checkpoint,
+    pub timestamp: u64,
+    pub filter: InclusionFilter,
+}
+
+impl PackageFilter {
+    pub fn new(
+        checkpoint: Checkpoint,
+        filter: InclusionFilter,
+        time: SystemTime,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            checkpoint,
+            timestamp: time.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+            filter,
+        })
+    }
+
+    pub fn now(checkpoint: Checkpoint, filter: InclusionFilter) -> anyhow::Result<Self> {
+        Self::new(checkpoint, filter, SystemTime::now())
+    }
+}
+
+impl Signable for PackageFilter {
+    const PREFIX: &'static [u8] = b"WARG-PACKAGE-FILTER-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for PackageFilter {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-PACKAGE-FILTER-V0");
+        visitor.visit_unsigned(self.checkpoint.log_length as u64);
+        visitor.visit_str(&self.checkpoint.log_root.to_string());
+        visitor.visit_str(&self.checkpoint.map_root.to_string());
+        visitor.visit_unsigned(self.timestamp);
+        visitor.visit_unsigned(self.filter.hashes as u64);
+        visitor.visit_str(&hex::encode(&self.filter.bits));
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for PackageFilter {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_packages_and_releases() {
+        let names: Vec<PackageName> = (0..100)
+            .map(|i| PackageName::new(format!("test:package{i}")).unwrap())
+            .collect();
+        let versions = [Version::new(1, 0, 0), Version::new(1, 1, 0)];
+
+        let filter = InclusionFilter::from_packages(
+            names.iter().map(|name| (name, versions.as_slice())),
+            0.01,
+        );
+
+        for name in &names {
+            assert!(filter.may_contain_package(name));
+            for version in &versions {
+                assert!(filter.may_contain_release(name, version));
+            }
+        }
+
+        let false_positives = (0..1000)
+            .filter(|i| {
+                filter.may_contain_package(&PackageName::new(format!("test:other{i}")).unwrap())
+            })
+            .count();
+        assert!(false_positives < 50, "{false_positives} false positives");
+
+        let json = serde_json::to_string(&filter).unwrap();
+        assert_eq!(
+            serde_json::from_str::<InclusionFilter>(&json).unwrap(),
+            filter
+        );
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let filter = InclusionFilter::from_packages([], 0.01);
+        assert!(!filter.may_contain_package(&PackageName::new("test:package").unwrap()));
+    }
+}
@@ -0,0 +1,214 @@
+//! Interning of values repeated throughout validator state.
+//!
+//! Validator state refers to the same key IDs, hashes, and versions many
+//! times over, such as in the permissions of a key and every permission
+//! change made by or to it. An [`Interner`] stores each distinct value once
+//! and hands out compact [`Handle`]s in its place; a [`Resolver`] maps the
+//! handles back.
+
+use indexmap::IndexSet;
+use std::{fmt, hash::Hash, marker::PhantomData};
+use thiserror::Error;
+
+/// A handle to a value stored in an [`Interner`].
+///
+/// Handles are only meaningful to the interner (or a clone of it) that
+/// created them.
+pub struct Handle<T> {
+    index: u32,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: u32) -> Self {
+        Self {
+            index,
+            _value: PhantomData,
+        }
+    }
+
+    /// Gets the index of the handle in its interner.
+    pub fn index(self) -> u32 {
+        self.index
+    }
+}
+
+// The trait implementations are written out so that they do not require
+// the interned type to implement them.
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Handle<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({index})", index = self.index)
+    }
+}
+
+/// Maps [`Handle`]s back to the values they were interned from.
+///
+/// Values are resolved to copies, so that a resolver need not hold its
+/// values in a single table.
+pub trait Resolver<T> {
+    /// Resolves a handle to its value.
+    ///
+    /// Returns `None` if the handle was not created by this resolver.
+    fn resolve(&self, handle: Handle<T>) -> Option<T>;
+}
+
+/// An error returned when interning a value in a full [`Interner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("the interner cannot hold more than {} values", u64::from(u32::MAX) + 1)]
+pub struct InternerFull;
+
+/// A table of interned values.
+///
+/// Values are assigned handles in the order they are first interned and
+/// are never removed. An interner holds at most `u32::MAX + 1` values.
+///
+/// Interners are equal if they interned the same values in the same order,
+/// so that equal handles of either refer to equal values.
+#[derive(Clone, Debug)]
+pub struct Interner<T: Hash + Eq> {
+    values: IndexSet<T>,
+}
+
+impl<T: Hash + Eq> PartialEq for Interner<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.values.iter().eq(&other.values)
+    }
+}
+
+impl<T: Hash + Eq> Eq for Interner<T> {}
+
+impl<T: Hash + Eq> Default for Interner<T> {
+    fn default() -> Self {
+        Self {
+            values: IndexSet::new(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> Interner<T> {
+    /// Creates a new, empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns a value, returning its handle.
+    ///
+    /// Interning a value that is already interned returns its existing handle.
+    ///
+    /// Returns an error if the value is not interned and the interner is full.
+    pub fn intern(&mut self, value: T) -> Result<Handle<T>, InternerFull> {
+        if let Some(handle) = self.get(&value) {
+            return Ok(handle);
+        }
+
+        let index = u32::try_from(self.values.len()).map_err(|_| InternerFull)?;
+        self.values.insert(value);
+        Ok(Handle::new(index))
+    }
+
+    /// Gets the handle of a value, if it has been interned.
+    pub fn get(&self, value: &T) -> Option<Handle<T>> {
+        let index = self.values.get_index_of(value)?;
+        u32::try_from(index).ok().map(Handle::new)
+    }
+
+    /// Gets the value of a handle.
+    ///
+    /// Returns `None` if the handle was not created by this interner.
+    pub fn value(&self, handle: Handle<T>) -> Option<&T> {
+        self.values.get_index(handle.index as usize)
+    }
+
+    /// Gets the number of interned values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Determines if no values have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Gets the interned values and their handles in the order they were
+    /// first interned.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        (0..=u32::MAX)
+            .zip(&self.values)
+            .map(|(index, value)| (Handle::new(index), value))
+    }
+}
+
+impl<T: Hash + Eq + Clone> Resolver<T> for Interner<T> {
+    fn resolve(&self, handle: Handle<T>) -> Option<T> {
+        self.value(handle).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_interns_values_once() {
+        let mut interner = Interner::new();
+        let a = interner.intern("a".to_string()).unwrap();
+        let b = interner.intern("b".to_string()).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("a".to_string()), Ok(a));
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get(&"b".to_string()), Some(b));
+        assert_eq!(interner.get(&"c".to_string()), None);
+        assert_eq!(interner.value(a).map(String::as_str), Some("a"));
+        assert_eq!(interner.resolve(b), Some("b".to_string()));
+        assert_eq!(interner.resolve(Handle::new(2)), None);
+        assert_eq!(
+            interner
+                .iter()
+                .map(|(handle, _)| handle)
+                .collect::<Vec<_>>(),
+            [a, b]
+        );
+
+        // Equal interners assign the same handles to the same values
+        let mut reordered = Interner::new();
+        reordered.intern("b".to_string()).unwrap();
+        reordered.intern("a".to_string()).unwrap();
+        assert_ne!(reordered, interner);
+        assert_eq!(interner.clone(), interner);
+    }
+}
@@ -0,0 +1,186 @@
+//! Requests for permissions to a package.
+//!
+//! A prospective maintainer exports a [`KeyRequest`] for their key, signed
+//! by that key, and sends it to an owner of the package. The owner verifies
+//! the request with [`KeyRequest::verify_signed`] and grants the requested
+//! permissions with [`KeyRequest::grant_entry`], so that the key granted is
+//! exactly the key that signed the request rather than a copy of it.
+
+use crate::{
+    package::{EntryError, PackageEntry, Permission},
+    registry::PackageName,
+    SerdeEnvelope,
+};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use thiserror::Error;
+use warg_crypto::{
+    prefix::{self, VisitPrefixEncode},
+    signing, ByteVisitor, Encode, Signable, VisitBytes,
+};
+
+/// Represents an error creating or verifying a key request.
+#[derive(Debug, Error)]
+pub enum KeyRequestError {
+    /// The request is for no permissions.
+    #[error("the key request must be for at least one permission")]
+    NoPermissions,
+    /// The request time is before the Unix epoch.
+    #[error("the key request time is before the Unix epoch")]
+    InvalidTime,
+    /// The request is signed by a key other than the requested key.
+    #[error("the key request must be signed by the requested key `{0}`")]
+    KeyMismatch(signing::KeyID),
+    /// The request could not be signed.
+    #[error("failed to sign key request: {0}")]
+    Signing(#[from] signing::SignatureError),
+    /// The signature of the request is invalid.
+    #[error("the signature of the key request is invalid")]
+    InvalidSignature,
+}
+
+/// A request by the holder of a key to be granted permissions to a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRequest {
+    /// The package the permissions are requested for.
+    pub package: PackageName,
+    /// The key to grant the permissions to.
+    pub key: signing::PublicKey,
+    /// The requested permissions.
+    pub permissions: Vec<Permission>,
+    /// When the request was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl KeyRequest {
+    /// Creates a request for the given permissions to a package, made at the
+    /// given time.
+    pub fn new(
+        package: PackageName,
+        key: signing::PublicKey,
+        permissions: impl IntoIterator<Item = Permission>,
+        time: SystemTime,
+    ) -> Result<Self, KeyRequestError> {
+        let mut unique = Vec::new();
+        for permission in permissions {
+            if !unique.contains(&permission) {
+                unique.push(permission);
+            }
+        }
+        if unique.is_empty() {
+            return Err(KeyRequestError::NoPermissions);
+        }
+
+        Ok(Self {
+            package,
+            key,
+            permissions: unique,
+            timestamp: time
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|_| KeyRequestError::InvalidTime)?
+                .as_secs(),
+        })
+    }
+
+    /// Signs the request with the requested key.
+    pub fn sign<S>(self, signer: &S) -> Result<SerdeEnvelope<Self>, KeyRequestError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        if signer.public_key() != self.key {
+            return Err(KeyRequestError::KeyMismatch(self.key.fingerprint()));
+        }
+
+        Ok(SerdeEnvelope::signed_contents(signer, self)?)
+    }
+
+    /// Verifies that the given request is signed by the requested key.
+    pub fn verify_signed(request: &SerdeEnvelope<Self>) -> Result<(), KeyRequestError> {
+        let key = &request.as_ref().key;
+        if &key.fingerprint() != request.key_id() {
+            return Err(KeyRequestError::KeyMismatch(key.fingerprint()));
+        }
+
+        Self::verify(key, &request.as_ref().encode(), request.signature())
+            .map_err(|_| KeyRequestError::InvalidSignature)
+    }
+
+    /// Creates the entry granting the requested permissions to the requested
+    /// key.
+    pub fn grant_entry(&self) -> Result<PackageEntry, EntryError> {
+        PackageEntry::grant(self.key.clone(), self.permissions.iter().copied())
+    }
+}
+
+impl Signable for KeyRequest {
+    const PREFIX: &'static [u8] = b"WARG-KEY-REQUEST-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for KeyRequest {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-KEY-REQUEST-V0");
+        visitor.visit_str(self.package.as_ref());
+        visitor.visit_str(&self.key.to_string());
+        visitor.visit_unsigned(self.permissions.len() as u64);
+        for permission in &self.permissions {
+            visitor.visit_str(&permission.to_string());
+        }
+        visitor.visit_unsigned(self.timestamp);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for KeyRequest {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::signing::generate_p256_pair;
+
+    #[test]
+    fn test_key_request_round_trip() {
+        let (public_key, private_key) = generate_p256_pair();
+        let request = KeyRequest::new(
+            "example:foo".parse().unwrap(),
+            public_key.clone(),
+            [Permission::Release, Permission::Yank, Permission::Release],
+            SystemTime::now(),
+        )
+        .unwrap();
+        assert_eq!(request.permissions, [Permission::Release, Permission::Yank]);
+
+        let signed = request.clone().sign(&private_key).unwrap();
+        let signed: SerdeEnvelope<KeyRequest> =
+            serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+        KeyRequest::verify_signed(&signed).unwrap();
+        assert_eq!(
+            signed.as_ref().grant_entry().unwrap(),
+            PackageEntry::grant(public_key, [Permission::Release, Permission::Yank]).unwrap()
+        );
+
+        // Only the requested key may sign the request
+        let (_, other_key) = generate_p256_pair();
+        assert!(matches!(
+            request.clone().sign(&other_key),
+            Err(KeyRequestError::KeyMismatch(_))
+        ));
+
+        // Tampering with the request invalidates it
+        let mut tampered = request;
+        tampered.package = "example:bar".parse().unwrap();
+        let tampered = SerdeEnvelope::from_parts_unchecked(
+            tampered,
+            signed.key_id().clone(),
+            signed.signature().clone(),
+        );
+        assert!(matches!(
+            KeyRequest::verify_signed(&tampered),
+            Err(KeyRequestError::InvalidSignature)
+        ));
+    }
+}
@@ -1,21 +1,80 @@
 use indexmap::IndexSet;
+use registry::RecordId;
 use serde::{de::DeserializeOwned, Serialize};
-use warg_crypto::{hash::AnyHash, Decode};
+use std::time::SystemTime;
+use warg_crypto::{hash::AnyHash, prefix, signing, Decode};
 
+pub mod archive;
+mod copublish;
+pub mod discovery;
+mod error;
+pub mod filter;
+pub mod intern;
+mod key_request;
+pub mod migrate;
+pub mod mirror;
 pub mod operator;
 pub mod package;
+pub mod policy;
 mod proto_envelope;
+pub mod record_log;
 pub mod registry;
+mod render;
 mod serde_envelope;
+mod state_export;
+mod version;
+pub mod wire;
 
+pub use copublish::{CoPublication, CoPublicationError, CoPublicationStatus, CoPublishedRecord};
+pub use error::{Error, ErrorKind};
+pub use key_request::{KeyRequest, KeyRequestError};
 pub use proto_envelope::{
-    ProtoEnvelope, ProtoEnvelopeBody, PublishedProtoEnvelope, PublishedProtoEnvelopeBody,
+    ContentType, Cosignature, Countersignature, DetachedSignatureError, ParseEnvelopeError,
+    ProtoEnvelope, ProtoEnvelopeBody, ProtoEnvelopeRef, PublishedProtoEnvelope,
+    PublishedProtoEnvelopeBody,
 };
-pub use semver::{Version, VersionReq};
+/// The version of a package release.
+///
+/// Versions can be constructed in const contexts and are totally ordered, so
+/// they can be used as map keys by resolvers:
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use warg_protocol::Version;
+///
+/// const MINIMUM: Version = Version::new(1, 0, 0);
+///
+/// let mut releases = BTreeMap::new();
+/// releases.insert(Version::parse("1.0.0-rc.1").unwrap(), "rc");
+/// releases.insert(MINIMUM, "stable");
+/// assert_eq!(releases.keys().next_back(), Some(&MINIMUM));
+/// ```
+///
+/// The ordering follows SemVer precedence except that versions differing only
+/// in build metadata, which have the same precedence, are ordered by their
+/// build metadata rather than being equal. Use [`Version::cmp_precedence`] to
+/// compare versions by precedence alone:
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use warg_protocol::Version;
+///
+/// let a = Version::parse("1.0.0+a").unwrap();
+/// let b = Version::parse("1.0.0+b").unwrap();
+/// assert_eq!(a.cmp(&b), Ordering::Less);
+/// assert_eq!(a.cmp_precedence(&b), Ordering::Equal);
+/// ```
+///
+/// Versions are not `Copy`, as their pre-release and build identifiers may
+/// be arbitrarily long; see [`PackedVersion`] for a `Copy` representation.
+pub use semver::Version;
+pub use semver::VersionReq;
 pub use serde_envelope::SerdeEnvelope;
+pub use state_export::{StateExport, StateExportError};
+pub use version::PackedVersion;
 
 /// Trait implemented by the record types.
-pub trait Record: Clone + Decode + Send + Sync {
+pub trait Record: Clone + Decode + ContentType + Send + Sync {
     /// Gets the set of content hashes associated with the record.
     ///
     /// An empty set indicates that the record has no associated content.
@@ -34,9 +93,101 @@ pub trait Validator:
 
     /// Validates the given record.
     fn validate(self, record: &ProtoEnvelope<Self::Record>) -> Result<Self, Self::Error>;
+
+    /// Advances the head of the state to the given record without
+    /// applying any of its entries.
+    ///
+    /// This is used by audits to continue past a record that failed
+    /// validation so that subsequent records still link correctly.
+    ///
+    /// The default implementation leaves the state unchanged, so an audit
+    /// of a state that does not override it reports the records following
+    /// a tolerated record as not linking to the head.
+    fn tolerate(&mut self, record: &ProtoEnvelope<Self::Record>) {
+        let _ = record;
+    }
+
+    /// Gets the identifier of the last record validated, if any.
+    ///
+    /// The default implementation returns `None`, so that exports of a
+    /// state that does not override it do not identify its head.
+    fn head_record_id(&self) -> Option<&RecordId> {
+        None
+    }
+
+    /// Exports a signed snapshot of the state at the current time.
+    ///
+    /// The export records the last validated record and a hash of the
+    /// canonical encoding of the state, attesting that the log was valid up
+    /// to that record.
+    fn export_signed(
+        &self,
+        signer: &signing::PrivateKey,
+    ) -> Result<SerdeEnvelope<StateExport>, StateExportError>
+    where
+        Self: prefix::VisitPrefixEncode,
+    {
+        let export = StateExport::new(self, SystemTime::now())?;
+        Ok(SerdeEnvelope::signed_contents(signer, export)?)
+    }
+
+    /// Verifies that the given signed export was signed by the given key
+    /// and is of this state.
+    fn verify_export(
+        &self,
+        export: &SerdeEnvelope<StateExport>,
+        public_key: &signing::PublicKey,
+    ) -> Result<(), StateExportError>
+    where
+        Self: prefix::VisitPrefixEncode,
+    {
+        StateExport::verify_signed(export, public_key)?;
+        export.as_ref().check(self)
+    }
+
+    /// Validates the given records in audit mode.
+    ///
+    /// Unlike `validate`, a record that fails validation does not stop the
+    /// audit. Instead, the violation is recorded as a [`Finding`], none of the
+    /// offending record's entries are applied, and validation continues with
+    /// the next record.
+    ///
+    /// Returns the resulting state and the findings in log order.
+    fn run_audit<'a, I>(self, records: I) -> (Self, Vec<Finding<Self::Error>>)
+    where
+        Self: Clone,
+        Self::Record: 'a,
+        I: IntoIterator<Item = &'a ProtoEnvelope<Self::Record>>,
+    {
+        let mut findings = Vec::new();
+        let mut state = self;
+        for (index, record) in records.into_iter().enumerate() {
+            let previous = state.clone();
+            state = match state.validate(record) {
+                Ok(state) => state,
+                Err(error) => {
+                    findings.push(Finding { index, error });
+                    let mut state = previous;
+                    state.tolerate(record);
+                    state
+                }
+            };
+        }
+
+        (state, findings)
+    }
 }
 
-/// Helpers for converting to and from protobuf
+/// Represents a validation failure recorded during an audit.
+#[derive(Debug)]
+pub struct Finding<E> {
+    /// The index of the offending record in the audited sequence.
+    pub index: usize,
+    /// The error that validation of the record produced.
+    pub error: E,
+}
+
+// Helpers for converting to and from protobuf
 
 fn prost_to_pbjson_timestamp(timestamp: prost_types::Timestamp) -> pbjson_types::Timestamp {
     pbjson_types::Timestamp {
@@ -96,4 +247,33 @@ mod timestamp {
                 nsecs.parse::<u32>().map_err(D::Error::custom)?,
             ))
     }
+
+    /// Helper module for serializing and deserializing optional timestamps.
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::SystemTime;
+
+        pub fn serialize<S>(
+            timestamp: &Option<SystemTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match timestamp {
+                Some(timestamp) => super::serialize(timestamp, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] SystemTime);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+        }
+    }
 }
@@ -0,0 +1,342 @@
+//! Migration of records encoded under older revisions of the protocol schema.
+//!
+//! Past revisions of the `warg.protocol` schema are vendored as descriptors
+//! in `warg-protobuf`. Migrating a record identifies the earliest revision
+//! whose schema knows every field of the record's encoding, decodes the record
+//! into the current model types, and checks that encoding the model again
+//! reproduces the original bytes exactly. Records are signed over those bytes,
+//! so a record whose re-encoding differs cannot be carried forward without
+//! invalidating its signature.
+
+use crate::{operator, package, ParseEnvelopeError, ProtoEnvelope, ProtoEnvelopeBody, Record};
+use anyhow::Error;
+use prost::Message;
+use prost_types::{field_descriptor_proto::Type, DescriptorProto, FileDescriptorSet};
+use std::sync::OnceLock;
+use thiserror::Error;
+use warg_crypto::Encode;
+use warg_protobuf::revisions;
+
+/// The maximum depth of nested messages in a migrated encoding.
+const MAX_DEPTH: usize = 100;
+
+/// Represents an error migrating a record.
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    /// The encoding has fields unknown to every revision of the schema.
+    #[error("the encoding of `{0}` has fields unknown to every schema revision")]
+    UnknownFields(String),
+    /// The encoding is not a valid protobuf encoding.
+    #[error("the encoding of `{0}` is malformed")]
+    Malformed(String),
+    /// The record could not be decoded into the current model types.
+    #[error("failed to decode record: {0}")]
+    Decode(Error),
+    /// The envelope of the record could not be parsed.
+    #[error(transparent)]
+    Envelope(#[from] ParseEnvelopeError),
+    /// Encoding the migrated record does not reproduce its signed bytes.
+    #[error("re-encoding the migrated record does not preserve its signed bytes")]
+    NotPreserved,
+}
+
+/// A revision of the `warg.protocol` schema.
+#[derive(Debug)]
+pub struct SchemaRevision {
+    number: usize,
+    files: FileDescriptorSet,
+}
+
+impl SchemaRevision {
+    /// Gets the number of the revision; revisions are numbered from zero,
+    /// oldest first.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Determines if this is the current revision of the schema.
+    pub fn is_current(&self) -> bool {
+        self.number == revisions::PROTOCOL.len()
+    }
+
+    /// Determines if every field of the given encoding of the message with
+    /// the given full name, such as `warg.protocol.PackageRecord`, is known
+    /// to this revision.
+    ///
+    /// Returns `false` if the message itself is unknown to this revision.
+    pub fn knows(&self, message: &str, bytes: &[u8]) -> Result<bool, MigrationError> {
+        self.knows_at_depth(message, bytes, 0)
+            .map_err(|()| MigrationError::Malformed(message.trim_start_matches('.').to_string()))
+    }
+
+    fn knows_at_depth(&self, message: &str, mut bytes: &[u8], depth: usize) -> Result<bool, ()> {
+        let Some(descriptor) = self.message(message) else {
+            return Ok(false);
+        };
+        if depth > MAX_DEPTH {
+            return Err(());
+        }
+
+        while !bytes.is_empty() {
+            let key = read_varint(&mut bytes)?;
+            let number = i32::try_from(key >> 3).map_err(|_| ())?;
+            let value = match key & 7 {
+                0 => read_varint(&mut bytes).map(|_| None)?,
+                1 => take(&mut bytes, 8).map(|_| None)?,
+                2 => {
+                    let len = usize::try_from(read_varint(&mut bytes)?).map_err(|_| ())?;
+                    Some(take(&mut bytes, len)?)
+                }
+                5 => take(&mut bytes, 4).map(|_| None)?,
+                _ => return Err(()),
+            };
+
+            let Some(field) = descriptor.field.iter().find(|f| f.number() == number) else {
+                return Ok(false);
+            };
+            if let (Some(value), Type::Message) = (value, field.r#type()) {
+                if !self.knows_at_depth(field.type_name(), value, depth + 1)? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Finds the descriptor of the message with the given full name.
+    fn message(&self, name: &str) -> Option<&DescriptorProto> {
+        let name = name.trim_start_matches('.');
+        self.files.file.iter().find_map(|file| {
+            let name = name
+                .strip_prefix(file.package())
+                .and_then(|name| name.strip_prefix('.'))?;
+            file.message_type.iter().find(|m| m.name() == name)
+        })
+    }
+}
+
+/// Gets every revision of the schema, oldest first; the last is the current
+/// revision.
+pub fn revisions() -> &'static [SchemaRevision] {
+    static REVISIONS: OnceLock<Vec<SchemaRevision>> = OnceLock::new();
+    REVISIONS.get_or_init(|| {
+        revisions::PROTOCOL
+            .iter()
+            .chain([&revisions::CURRENT_PROTOCOL])
+            .enumerate()
+            .map(|(number, bytes)| SchemaRevision {
+                number,
+                files: FileDescriptorSet::decode(*bytes).expect("vendored descriptors are valid"),
+            })
+            .collect()
+    })
+}
+
+/// Finds the earliest revision of the schema that knows every field of the
+/// given encoding of the message with the given full name.
+pub fn earliest_revision(
+    message: &str,
+    bytes: &[u8],
+) -> Result<&'static SchemaRevision, MigrationError> {
+    for revision in revisions() {
+        if revision.knows(message, bytes)? {
+            return Ok(revision);
+        }
+    }
+    Err(MigrationError::UnknownFields(
+        message.trim_start_matches('.').to_string(),
+    ))
+}
+
+/// Trait implemented by the record types that can be migrated.
+pub trait MigratableRecord: Record + Encode {
+    /// The full name of the record's message in the schema.
+    const MESSAGE: &'static str;
+}
+
+impl MigratableRecord for operator::OperatorRecord {
+    const MESSAGE: &'static str = "warg.protocol.OperatorRecord";
+}
+
+impl MigratableRecord for package::PackageRecord {
+    const MESSAGE: &'static str = "warg.protocol.PackageRecord";
+}
+
+/// A record migrated to the current model types.
+#[derive(Debug)]
+pub struct Migrated<T> {
+    /// The migrated value.
+    pub value: T,
+    /// The earliest revision of the schema that knows the value's encoding.
+    pub revision: &'static SchemaRevision,
+}
+
+/// Migrates the given encoding of a record to the current model types.
+pub fn migrate_record<T: MigratableRecord>(bytes: &[u8]) -> Result<Migrated<T>, MigrationError> {
+    let revision = earliest_revision(T::MESSAGE, bytes)?;
+    let value = T::decode(bytes).map_err(MigrationError::Decode)?;
+    if value.encode() != bytes {
+        return Err(MigrationError::NotPreserved);
+    }
+
+    Ok(Migrated { value, revision })
+}
+
+/// Migrates the given encoding of an envelope containing a record to the
+/// current model types.
+///
+/// The envelope's signature is not verified.
+pub fn migrate_envelope<T: MigratableRecord>(
+    bytes: &[u8],
+) -> Result<Migrated<ProtoEnvelope<T>>, MigrationError> {
+    let envelope_revision = earliest_revision("warg.protocol.Envelope", bytes)?;
+    let body = ProtoEnvelopeBody::from_protobuf(bytes)?;
+    // The body preserves whether the envelope has a content type tag, which
+    // envelopes encoded before contents were tagged do not
+    if body.to_protobuf() != bytes {
+        return Err(MigrationError::NotPreserved);
+    }
+    let envelope = ProtoEnvelope::<T>::from_stored(body).map_err(MigrationError::Decode)?;
+    let Migrated { revision, .. } = migrate_record::<T>(envelope.content_bytes())?;
+
+    Ok(Migrated {
+        value: envelope,
+        revision: if envelope_revision.number() > revision.number() {
+            envelope_revision
+        } else {
+            revision
+        },
+    })
+}
+
+/// Decodes the protobuf encoding of an envelope stored by a registry.
+///
+/// Unlike [`ProtoEnvelope::from_protobuf`], envelopes stored before contents
+/// were tagged are accepted; see [`ProtoEnvelope::from_stored`].
+pub fn decode_stored_envelope<T: Record>(
+    bytes: &[u8],
+) -> Result<ProtoEnvelope<T>, ParseEnvelopeError> {
+    let body = ProtoEnvelopeBody::from_protobuf(bytes)?;
+    Ok(ProtoEnvelope::from_stored(body)?)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, ()> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(())?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(())
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], ()> {
+    if bytes.len() < len {
+        return Err(());
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operator::{OperatorEntry, OperatorRecord, Permission};
+    use std::time::SystemTime;
+    use warg_crypto::{hash::HashAlgorithm, signing::generate_p256_pair};
+
+    fn record(entries: Vec<OperatorEntry>) -> OperatorRecord {
+        let (public_key, _) = generate_p256_pair();
+        let mut all = vec![OperatorEntry::Init {
+            hash_algorithm: HashAlgorithm::Sha256,
+            key: public_key,
+        }];
+        all.extend(entries);
+        OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: all,
+        }
+    }
+
+    #[test]
+    fn test_migrates_records_of_each_revision() {
+        let (_, private_key) = generate_p256_pair();
+
+        // Namespace definitions are known to the first revision
+        let old = record(vec![OperatorEntry::DefineNamespace {
+            namespace: "example".to_string(),
+        }]);
+        let envelope = ProtoEnvelope::signed_contents(&private_key, old.clone()).unwrap();
+        let migrated = migrate_envelope::<OperatorRecord>(&envelope.to_protobuf()).unwrap();
+        assert!(migrated.revision.is_current());
+        assert_eq!(migrated.value, envelope);
+
+        // Content type tags were added in a later revision
+        let mut untagged =
+            warg_protobuf::protocol::Envelope::decode(envelope.to_protobuf().as_slice()).unwrap();
+        untagged.content_type = None;
+        let untagged = untagged.encode_to_vec();
+        let migrated = migrate_envelope::<OperatorRecord>(&untagged).unwrap();
+        assert_eq!(migrated.revision.number(), 0);
+        assert_eq!(migrated.value, envelope);
+        assert!(ProtoEnvelope::<OperatorRecord>::from_protobuf(&untagged).is_err());
+        assert_eq!(
+            decode_stored_envelope::<OperatorRecord>(&untagged).unwrap(),
+            envelope
+        );
+
+        // Denied keys were added in a later revision
+        let new = record(vec![OperatorEntry::DenyKey {
+            key_id: private_key.public_key().fingerprint(),
+            log_length: 1,
+        }]);
+        let migrated = migrate_record::<OperatorRecord>(&new.encode()).unwrap();
+        assert!(migrated.revision.is_current());
+        assert_eq!(migrated.value, new);
+
+        let grant = record(vec![OperatorEntry::GrantFlat {
+            key: private_key.public_key(),
+            permissions: vec![Permission::Commit],
+        }]);
+        assert_eq!(
+            migrate_record::<OperatorRecord>(&grant.encode())
+                .unwrap()
+                .revision
+                .number(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_rejects_unpreserved_encodings() {
+        let bytes = record(Vec::new()).encode();
+
+        // Fields unknown to every revision are rejected
+        let mut unknown = bytes.clone();
+        unknown.extend([0xf8, 0x01, 0x01]);
+        assert!(matches!(
+            migrate_record::<OperatorRecord>(&unknown),
+            Err(MigrationError::UnknownFields(_))
+        ));
+
+        // Repeating a field decodes to the same record but a different encoding
+        let mut repeated = bytes.clone();
+        repeated.extend([0x10, 0x00]);
+        assert!(matches!(
+            migrate_record::<OperatorRecord>(&repeated),
+            Err(MigrationError::NotPreserved)
+        ));
+
+        assert!(matches!(
+            migrate_record::<OperatorRecord>(&bytes[..bytes.len() - 1]),
+            Err(MigrationError::Malformed(_))
+        ));
+    }
+}
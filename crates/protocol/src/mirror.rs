@@ -0,0 +1,281 @@
+//! Content availability attestations published by registry mirrors.
+//!
+//! A mirror signs a [`ContentAvailability`] statement for each content blob
+//! it holds, asserting it had the blob as of a registry checkpoint. Resolvers
+//! collect verified statements into an [`AvailabilityIndex`] to pick mirrors
+//! known to have the blobs they need.
+
+use crate::{registry::Checkpoint, SerdeEnvelope};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use thiserror::Error;
+use warg_crypto::{
+    hash::AnyHash,
+    prefix::{self, VisitPrefixEncode},
+    signing, ByteVisitor, Encode, Signable, VisitBytes,
+};
+
+/// Represents an error verifying a content availability attestation.
+#[derive(Debug, Error)]
+pub enum AttestationError {
+    /// The attestation is from a mirror that is not trusted.
+    #[error("mirror `{0}` is not trusted")]
+    UnknownMirror(String),
+    /// The attestation was not signed by the mirror's key.
+    #[error("the attestation from mirror `{0}` has an invalid signature")]
+    InvalidSignature(String),
+}
+
+/// A statement by a mirror that it holds the content with the given digest
+/// as of the given registry checkpoint.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentAvailability {
+    /// The URL of the mirror making the statement.
+    pub mirror: String,
+    /// The digest of the content held by the mirror.
+    pub content: AnyHash,
+    /// The registry checkpoint as of which the content is held.
+    pub checkpoint: Checkpoint,
+    /// When the statement was made, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl ContentAvailability {
+    pub fn new(
+        mirror: impl Into<String>,
+        content: AnyHash,
+        checkpoint: Checkpoint,
+        time: SystemTime,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            mirror: mirror.into(),
+            content,
+            checkpoint,
+            timestamp: time.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+        })
+    }
+
+    pub fn now(
+        mirror: impl Into<String>,
+        content: AnyHash,
+        checkpoint: Checkpoint,
+    ) -> anyhow::Result<Self> {
+        Self::new(mirror, content, checkpoint, SystemTime::now())
+    }
+
+    /// Verifies the signature of the given attestation with the given mirror key.
+    pub fn verify_signed(
+        attestation: &SerdeEnvelope<Self>,
+        public_key: &signing::PublicKey,
+    ) -> Result<(), AttestationError> {
+        let invalid = || AttestationError::InvalidSignature(attestation.as_ref().mirror.clone());
+        if &public_key.fingerprint() != attestation.key_id() {
+            return Err(invalid());
+        }
+
+        Self::verify(
+            public_key,
+            &attestation.as_ref().encode(),
+            attestation.signature(),
+        )
+        .map_err(|_| invalid())
+    }
+}
+
+impl Signable for ContentAvailability {
+    const PREFIX: &'static [u8] = b"WARG-CONTENT-AVAILABILITY-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for ContentAvailability {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-CONTENT-AVAILABILITY-V0");
+        visitor.visit_str(&self.mirror);
+        visitor.visit_str(&self.content.to_string());
+        visitor.visit_unsigned(self.checkpoint.log_length as u64);
+        visitor.visit_str(&self.checkpoint.log_root.to_string());
+        visitor.visit_str(&self.checkpoint.map_root.to_string());
+        visitor.visit_unsigned(self.timestamp);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for ContentAvailability {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+/// Aggregates verified content availability attestations from trusted mirrors.
+///
+/// For each content digest and mirror, only the attestation made as of the
+/// latest checkpoint is kept.
+#[derive(Debug, Default, Clone)]
+pub struct AvailabilityIndex {
+    mirrors: IndexMap<String, signing::PublicKey>,
+    attestations: IndexMap<AnyHash, IndexMap<String, SerdeEnvelope<ContentAvailability>>>,
+}
+
+impl AvailabilityIndex {
+    /// Creates a new, empty availability index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts attestations from the given mirror signed with the given key.
+    pub fn with_mirror(mut self, mirror: impl Into<String>, key: signing::PublicKey) -> Self {
+        self.mirrors.insert(mirror.into(), key);
+        self
+    }
+
+    /// Verifies and adds the given attestation to the index.
+    ///
+    /// Returns `true` if the attestation replaced or added to what is known
+    /// about the mirror, or `false` if an attestation as of a later
+    /// checkpoint is already known.
+    pub fn insert(
+        &mut self,
+        attestation: SerdeEnvelope<ContentAvailability>,
+    ) -> Result<bool, AttestationError> {
+        let statement = attestation.as_ref();
+        let key = self
+            .mirrors
+            .get(&statement.mirror)
+            .ok_or_else(|| AttestationError::UnknownMirror(statement.mirror.clone()))?;
+        ContentAvailability::verify_signed(&attestation, key)?;
+
+        let mirrors = self.attestations.entry(statement.content).or_default();
+        if let Some(existing) = mirrors.get(&statement.mirror) {
+            let existing = existing.as_ref();
+            if (existing.checkpoint.log_length, existing.timestamp)
+                > (statement.checkpoint.log_length, statement.timestamp)
+            {
+                return Ok(false);
+            }
+        }
+
+        mirrors.insert(statement.mirror.clone(), attestation);
+        Ok(true)
+    }
+
+    /// Gets the attestations for the given content, most recent checkpoint first.
+    pub fn attestations(&self, content: &AnyHash) -> Vec<&ContentAvailability> {
+        let mut attestations: Vec<_> = self
+            .attestations
+            .get(content)
+            .into_iter()
+            .flat_map(|mirrors| mirrors.values().map(|a| a.as_ref()))
+            .collect();
+        attestations.sort_by(|a, b| {
+            (b.checkpoint.log_length, b.timestamp).cmp(&(a.checkpoint.log_length, a.timestamp))
+        });
+        attestations
+    }
+
+    /// Gets the mirrors attested to hold the given content, most recent
+    /// checkpoint first.
+    pub fn mirrors_for(&self, content: &AnyHash) -> Vec<&str> {
+        self.attestations(content)
+            .into_iter()
+            .map(|a| a.mirror.as_str())
+            .collect()
+    }
+
+    /// Gets the mirrors attested to hold all of the given content, in the
+    /// order the mirrors were trusted.
+    pub fn mirrors_for_all<'a>(
+        &self,
+        contents: impl IntoIterator<Item = &'a AnyHash>,
+    ) -> Vec<&str> {
+        let mut mirrors: Vec<&str> = self.mirrors.keys().map(String::as_str).collect();
+        for content in contents {
+            let held = self.attestations.get(content);
+            mirrors.retain(|m| held.map(|held| held.contains_key(*m)).unwrap_or(false));
+        }
+
+        mirrors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::{
+        hash::{Hash, Sha256},
+        signing::generate_p256_pair,
+    };
+
+    fn checkpoint(log_length: usize) -> Checkpoint {
+        Checkpoint {
+            log_root: Hash::<Sha256>::default().into(),
+            log_length,
+            map_root: Hash::<Sha256>::default().into(),
+        }
+    }
+
+    fn attest(
+        key: &signing::PrivateKey,
+        mirror: &str,
+        content: &AnyHash,
+        log_length: usize,
+    ) -> SerdeEnvelope<ContentAvailability> {
+        SerdeEnvelope::signed_contents(
+            key,
+            ContentAvailability::now(mirror, *content, checkpoint(log_length)).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn aggregates_attestations() {
+        let (first_pub, first_priv) = generate_p256_pair();
+        let (second_pub, second_priv) = generate_p256_pair();
+        let (_, other_priv) = generate_p256_pair();
+
+        let a: AnyHash = Hash::<Sha256>::of("a").into();
+        let b: AnyHash = Hash::<Sha256>::of("b").into();
+
+        let mut index = AvailabilityIndex::new()
+            .with_mirror("https://first.example.com", first_pub)
+            .with_mirror("https://second.example.com", second_pub);
+
+        assert!(index
+            .insert(attest(&first_priv, "https://first.example.com", &a, 3))
+            .unwrap());
+        assert!(index
+            .insert(attest(&first_priv, "https://first.example.com", &b, 3))
+            .unwrap());
+        assert!(index
+            .insert(attest(&second_priv, "https://second.example.com", &a, 5))
+            .unwrap());
+
+        // An attestation as of an earlier checkpoint is ignored
+        assert!(!index
+            .insert(attest(&first_priv, "https://first.example.com", &a, 2))
+            .unwrap());
+
+        assert!(matches!(
+            index.insert(attest(&other_priv, "https://first.example.com", &a, 4)),
+            Err(AttestationError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            index.insert(attest(&other_priv, "https://other.example.com", &a, 4)),
+            Err(AttestationError::UnknownMirror(_))
+        ));
+
+        assert_eq!(
+            index.mirrors_for(&a),
+            ["https://second.example.com", "https://first.example.com"]
+        );
+        assert_eq!(index.attestations(&a)[1].checkpoint.log_length, 3);
+        assert_eq!(index.mirrors_for(&b), ["https://first.example.com"]);
+        assert_eq!(
+            index.mirrors_for_all([&a, &b]),
+            ["https://first.example.com"]
+        );
+        assert!(index
+            .mirrors_for(&Hash::<Sha256>::of("c").into())
+            .is_empty());
+    }
+}
@@ -1,16 +1,22 @@
 use anyhow::{Context, Error};
 use prost::Message;
 use thiserror::Error;
-use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
+use warg_crypto::{hash::AnyHash, signing, Decode, Encode, Signable};
 use warg_protobuf::protocol as protobuf;
 
-use crate::{pbjson_to_prost_timestamp, prost_to_pbjson_timestamp, registry::RecordId};
+use crate::{
+    pbjson_to_prost_timestamp, prost_to_pbjson_timestamp,
+    registry::{Checkpoint, RecordId},
+    ContentType,
+};
 
 mod model;
 mod state;
 
-pub use model::{OperatorEntry, OperatorRecord};
-pub use state::{LogState, NamespaceState, ValidationError};
+pub use model::{
+    AdminCommand, Advisory, IdentityClaim, Migration, OperatorEntry, OperatorRecord, Permission,
+};
+pub use state::{LogState, NamespaceMigration, NamespaceState, ValidationError};
 
 /// The currently supported operator protocol version.
 pub const OPERATOR_RECORD_VERSION: u32 = 0;
@@ -21,6 +27,10 @@ impl Decode for model::OperatorRecord {
     }
 }
 
+impl ContentType for model::OperatorRecord {
+    const CONTENT_TYPE: &'static str = "WARG-OPERATOR-RECORD-SIGNATURE-V0";
+}
+
 impl TryFrom<protobuf::OperatorRecord> for model::OperatorRecord {
     type Error = Error;
 
@@ -90,6 +100,21 @@ impl TryFrom<protobuf::OperatorEntry> for model::OperatorEntry {
                 namespace: import_namespace.namespace,
                 registry: import_namespace.registry,
             },
+            Contents::DenyKey(deny_key) => model::OperatorEntry::DenyKey {
+                key_id: deny_key.key_id.into(),
+                log_length: deny_key.log_length.try_into()?,
+            },
+            Contents::Migrate(migrate) => model::OperatorEntry::Migrate(migrate.try_into()?),
+            Contents::Admin(admin) => model::OperatorEntry::Admin(admin.try_into()?),
+            Contents::Advisory(advisory) => model::OperatorEntry::Advisory(model::Advisory {
+                id: advisory.id,
+                package: advisory.package.parse()?,
+                affected: advisory.affected.parse()?,
+                summary: advisory.summary,
+            }),
+            Contents::IdentityClaim(claim) => {
+                model::OperatorEntry::IdentityClaim(claim.try_into()?)
+            }
         };
         Ok(output)
     }
@@ -99,6 +124,60 @@ impl TryFrom<protobuf::OperatorEntry> for model::OperatorEntry {
 #[error("no content in entry")]
 struct EmptyContentError;
 
+impl TryFrom<protobuf::OperatorMigrate> for model::Migration {
+    type Error = Error;
+
+    fn try_from(migrate: protobuf::OperatorMigrate) -> Result<Self, Self::Error> {
+        Ok(model::Migration {
+            registry: migrate.registry,
+            checkpoint: Checkpoint {
+                log_root: migrate.log_root.parse()?,
+                log_length: migrate.log_length.try_into()?,
+                map_root: migrate.map_root.parse()?,
+            },
+            namespaces: migrate.namespaces,
+            key: migrate.key.parse()?,
+            signature: migrate.signature.parse()?,
+        })
+    }
+}
+
+impl TryFrom<protobuf::OperatorIdentityClaim> for model::IdentityClaim {
+    type Error = Error;
+
+    fn try_from(claim: protobuf::OperatorIdentityClaim) -> Result<Self, Self::Error> {
+        Ok(model::IdentityClaim {
+            key: claim.key.parse()?,
+            identity: claim.identity,
+            proof: claim.proof.parse()?,
+        })
+    }
+}
+
+impl TryFrom<protobuf::OperatorAdmin> for model::AdminCommand {
+    type Error = Error;
+
+    fn try_from(admin: protobuf::OperatorAdmin) -> Result<Self, Self::Error> {
+        use protobuf::operator_admin::Command;
+        let output = match admin.command.ok_or(EmptyContentError)? {
+            Command::FreezePackage(freeze) => model::AdminCommand::FreezePackage {
+                name: freeze.name.parse()?,
+            },
+            Command::UnfreezePackage(unfreeze) => model::AdminCommand::UnfreezePackage {
+                name: unfreeze.name.parse()?,
+            },
+            Command::RevalidatePackage(revalidate) => model::AdminCommand::RevalidatePackage {
+                name: revalidate.name.parse()?,
+            },
+            Command::CollectGarbage(_) => model::AdminCommand::CollectGarbage,
+            Command::RotateCheckpointKey(rotate) => model::AdminCommand::RotateCheckpointKey {
+                key: rotate.key.parse()?,
+            },
+        };
+        Ok(output)
+    }
+}
+
 impl TryFrom<i32> for model::Permission {
     type Error = Error;
 
@@ -112,6 +191,7 @@ impl TryFrom<i32> for model::Permission {
             protobuf::OperatorPermission::Commit => Ok(model::Permission::Commit),
             protobuf::OperatorPermission::DefineNamespace => Ok(model::Permission::DefineNamespace),
             protobuf::OperatorPermission::ImportNamespace => Ok(model::Permission::ImportNamespace),
+            protobuf::OperatorPermission::Admin => Ok(model::Permission::Admin),
         }
     }
 }
@@ -125,7 +205,7 @@ struct PermissionParseError {
 // Serialization
 
 impl Signable for model::OperatorRecord {
-    const PREFIX: &'static [u8] = b"WARG-OPERATOR-RECORD-SIGNATURE-V0";
+    const PREFIX: &'static [u8] = Self::CONTENT_TYPE.as_bytes();
 }
 
 impl Encode for model::OperatorRecord {
@@ -135,6 +215,14 @@ impl Encode for model::OperatorRecord {
     }
 }
 
+impl model::OperatorRecord {
+    /// Gets the length of the protobuf encoding of the record without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        protobuf::OperatorRecord::from(self).encoded_len()
+    }
+}
+
 impl<'a> From<&'a model::OperatorRecord> for protobuf::OperatorRecord {
     fn from(record: &'a model::OperatorRecord) -> Self {
         protobuf::OperatorRecord {
@@ -182,18 +270,201 @@ impl<'a> From<&'a model::OperatorEntry> for protobuf::OperatorEntry {
                 namespace: namespace.clone(),
                 registry: registry.clone(),
             }),
+            model::OperatorEntry::DenyKey { key_id, log_length } => {
+                Contents::DenyKey(protobuf::OperatorDenyKey {
+                    key_id: key_id.to_string(),
+                    log_length: *log_length as u64,
+                })
+            }
+            model::OperatorEntry::Migrate(migration) => Contents::Migrate(migration.into()),
+            model::OperatorEntry::Admin(command) => Contents::Admin(command.into()),
+            model::OperatorEntry::Advisory(advisory) => {
+                Contents::Advisory(protobuf::OperatorAdvisory {
+                    id: advisory.id.clone(),
+                    package: advisory.package.to_string(),
+                    affected: advisory.affected.to_string(),
+                    summary: advisory.summary.clone(),
+                })
+            }
+            model::OperatorEntry::IdentityClaim(claim) => Contents::IdentityClaim(claim.into()),
         };
         let contents = Some(contents);
         protobuf::OperatorEntry { contents }
     }
 }
 
+impl<'a> From<&'a model::Migration> for protobuf::OperatorMigrate {
+    fn from(migration: &'a model::Migration) -> Self {
+        protobuf::OperatorMigrate {
+            registry: migration.registry.clone(),
+            log_length: migration.checkpoint.log_length as u64,
+            log_root: migration.checkpoint.log_root.to_string(),
+            map_root: migration.checkpoint.map_root.to_string(),
+            namespaces: migration.namespaces.clone(),
+            key: migration.key.to_string(),
+            signature: migration.signature.to_string(),
+        }
+    }
+}
+
+impl<'a> From<&'a model::IdentityClaim> for protobuf::OperatorIdentityClaim {
+    fn from(claim: &'a model::IdentityClaim) -> Self {
+        protobuf::OperatorIdentityClaim {
+            key: claim.key.to_string(),
+            identity: claim.identity.clone(),
+            proof: claim.proof.to_string(),
+        }
+    }
+}
+
+impl<'a> From<&'a model::AdminCommand> for protobuf::OperatorAdmin {
+    fn from(command: &'a model::AdminCommand) -> Self {
+        use protobuf::operator_admin::Command;
+        let command = match command {
+            model::AdminCommand::FreezePackage { name } => {
+                Command::FreezePackage(protobuf::OperatorFreezePackage {
+                    name: name.to_string(),
+                })
+            }
+            model::AdminCommand::UnfreezePackage { name } => {
+                Command::UnfreezePackage(protobuf::OperatorUnfreezePackage {
+                    name: name.to_string(),
+                })
+            }
+            model::AdminCommand::RevalidatePackage { name } => {
+                Command::RevalidatePackage(protobuf::OperatorRevalidatePackage {
+                    name: name.to_string(),
+                })
+            }
+            model::AdminCommand::CollectGarbage => {
+                Command::CollectGarbage(protobuf::OperatorCollectGarbage {})
+            }
+            model::AdminCommand::RotateCheckpointKey { key } => {
+                Command::RotateCheckpointKey(protobuf::OperatorRotateCheckpointKey {
+                    key: key.to_string(),
+                })
+            }
+        };
+        protobuf::OperatorAdmin {
+            command: Some(command),
+        }
+    }
+}
+
+const MIGRATION_PREFIX: &[u8] = b"WARG-OPERATOR-MIGRATION-V0";
+
+impl model::Migration {
+    /// Signs a migration of the given namespaces from the source registry
+    /// as of the given checkpoint.
+    ///
+    /// The source operator key signs the migration for the registry whose
+    /// operator log is initialized with the destination key.
+    pub fn sign(
+        source_key: &signing::PrivateKey,
+        destination: &signing::KeyID,
+        registry: impl Into<String>,
+        checkpoint: Checkpoint,
+        namespaces: impl IntoIterator<Item = String>,
+    ) -> Result<Self, signing::SignatureError> {
+        let registry = registry.into();
+        let namespaces: Vec<String> = namespaces.into_iter().collect();
+        let key = source_key.public_key();
+        let signature = source_key.sign(&migration_payload(
+            destination,
+            protobuf::OperatorMigrate {
+                registry: registry.clone(),
+                log_length: checkpoint.log_length as u64,
+                log_root: checkpoint.log_root.to_string(),
+                map_root: checkpoint.map_root.to_string(),
+                namespaces: namespaces.clone(),
+                key: key.to_string(),
+                signature: String::new(),
+            },
+        ))?;
+
+        Ok(Self {
+            registry,
+            checkpoint,
+            namespaces,
+            key,
+            signature,
+        })
+    }
+
+    /// Verifies the signature of the migration into the registry whose
+    /// operator log is initialized with the destination key.
+    ///
+    /// The signature is verified with the key included in the migration; that
+    /// key must still be verified to be an operator key of the source registry
+    /// (see [`NamespaceMigration::verify_source`](crate::operator::NamespaceMigration::verify_source)).
+    pub fn verify(&self, destination: &signing::KeyID) -> Result<(), signing::SignatureError> {
+        let mut migrate = protobuf::OperatorMigrate::from(self);
+        migrate.signature.clear();
+        self.key
+            .verify(&migration_payload(destination, migrate), &self.signature)
+    }
+}
+
+/// Gets the payload signed by the source operator of a migration.
+///
+/// The payload is the destination key id followed by the migration without
+/// its signature.
+fn migration_payload(destination: &signing::KeyID, migrate: protobuf::OperatorMigrate) -> Vec<u8> {
+    [
+        MIGRATION_PREFIX,
+        b":",
+        destination.to_string().as_bytes(),
+        b":",
+        &migrate.encode_to_vec(),
+    ]
+    .concat()
+}
+
+const IDENTITY_CLAIM_PREFIX: &[u8] = b"WARG-OPERATOR-IDENTITY-CLAIM-V0";
+
+impl model::IdentityClaim {
+    /// Signs a claim of the given identity for the given key.
+    pub fn sign(
+        private_key: &signing::PrivateKey,
+        identity: impl Into<String>,
+    ) -> Result<Self, signing::SignatureError> {
+        let identity = identity.into();
+        let key = private_key.public_key();
+        let proof = private_key.sign(&identity_claim_payload(protobuf::OperatorIdentityClaim {
+            key: key.to_string(),
+            identity: identity.clone(),
+            proof: String::new(),
+        }))?;
+
+        Ok(Self {
+            key,
+            identity,
+            proof,
+        })
+    }
+
+    /// Verifies the key holder's signature of the claim.
+    pub fn verify(&self) -> Result<(), signing::SignatureError> {
+        let mut claim = protobuf::OperatorIdentityClaim::from(self);
+        claim.proof.clear();
+        self.key.verify(&identity_claim_payload(claim), &self.proof)
+    }
+}
+
+/// Gets the payload signed by the key holder of an identity claim.
+///
+/// The payload is the claim without its proof.
+fn identity_claim_payload(claim: protobuf::OperatorIdentityClaim) -> Vec<u8> {
+    [IDENTITY_CLAIM_PREFIX, b":", &claim.encode_to_vec()].concat()
+}
+
 impl<'a> From<&'a model::Permission> for i32 {
     fn from(permission: &'a model::Permission) -> Self {
         let proto_perm = match permission {
             model::Permission::Commit => protobuf::OperatorPermission::Commit,
             model::Permission::DefineNamespace => protobuf::OperatorPermission::DefineNamespace,
             model::Permission::ImportNamespace => protobuf::OperatorPermission::ImportNamespace,
+            model::Permission::Admin => protobuf::OperatorPermission::Admin,
         };
         proto_perm.into()
     }
@@ -212,7 +483,7 @@ mod tests {
     #[test]
     fn test_envelope_roundtrip() {
         let (alice_pub, alice_priv) = generate_p256_pair();
-        let (bob_pub, _bob_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
 
         let record = model::OperatorRecord {
             prev: None,
@@ -231,6 +502,26 @@ mod tests {
                     key_id: bob_pub.fingerprint(),
                     permissions: vec![model::Permission::Commit],
                 },
+                model::OperatorEntry::DenyKey {
+                    key_id: bob_pub.fingerprint(),
+                    log_length: 42,
+                },
+                model::OperatorEntry::Admin(model::AdminCommand::FreezePackage {
+                    name: "test:package".parse().unwrap(),
+                }),
+                model::OperatorEntry::Admin(model::AdminCommand::RotateCheckpointKey {
+                    key: bob_pub.clone(),
+                }),
+                model::OperatorEntry::Admin(model::AdminCommand::CollectGarbage),
+                model::OperatorEntry::Advisory(model::Advisory {
+                    id: "CVE-2024-0001".to_string(),
+                    package: "test:package".parse().unwrap(),
+                    affected: ">=1.0.0, <1.2.3".parse().unwrap(),
+                    summary: "a vulnerability".to_string(),
+                }),
+                model::OperatorEntry::IdentityClaim(
+                    model::IdentityClaim::sign(&bob_priv, "bob@example.com").unwrap(),
+                ),
             ],
         };
 
@@ -1,4 +1,5 @@
-use crate::registry::RecordId;
+use crate::registry::{Checkpoint, PackageName, RecordId, RegistryLen};
+use crate::{Version, VersionReq};
 use core::fmt;
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
@@ -36,15 +37,18 @@ pub enum Permission {
     DefineNamespace,
     /// Permission to import namespace from another registry and add to the operator log.
     ImportNamespace,
+    /// Permission to issue administrative commands.
+    Admin,
 }
 
 impl Permission {
     /// Gets an array of all permissions.
-    pub const fn all() -> [Permission; 3] {
+    pub const fn all() -> [Permission; 4] {
         [
             Permission::Commit,
             Permission::DefineNamespace,
             Permission::ImportNamespace,
+            Permission::Admin,
         ]
     }
 }
@@ -55,6 +59,7 @@ impl fmt::Display for Permission {
             Permission::Commit => write!(f, "commit"),
             Permission::DefineNamespace => write!(f, "defineNamespace"),
             Permission::ImportNamespace => write!(f, "importNamespace"),
+            Permission::Admin => write!(f, "admin"),
         }
     }
 }
@@ -67,6 +72,7 @@ impl FromStr for Permission {
             "commit" => Ok(Permission::Commit),
             "defineNamespace" => Ok(Permission::DefineNamespace),
             "importNamespace" => Ok(Permission::ImportNamespace),
+            "admin" => Ok(Permission::Admin),
             _ => Err(()),
         }
     }
@@ -99,6 +105,119 @@ pub enum OperatorEntry {
     DefineNamespace { namespace: String },
     /// The registry defines a namespace as imported from another registry.
     ImportNamespace { namespace: String, registry: String },
+    /// Declare a key compromised as of a registry log length.
+    /// Package records signed by the key that are sequenced at or after
+    /// the log length are invalid.
+    /// The author of this entry must have the commit permission.
+    DenyKey {
+        key_id: signing::KeyID,
+        log_length: RegistryLen,
+    },
+    /// Migrate namespaces from another registry.
+    /// The namespaces are defined for this registry's package logs, which
+    /// continue the package logs of the source registry.
+    /// The author of this entry must have the define namespace permission.
+    Migrate(Migration),
+    /// An administrative command issued to the registry.
+    /// The command is recorded for auditability.
+    /// The author of this entry must have the admin permission.
+    Admin(AdminCommand),
+    /// Publish a security advisory affecting a range of package versions.
+    /// The author of this entry must have the admin permission.
+    Advisory(Advisory),
+    /// Bind a key to an identity in the registry's key directory.
+    /// The author of this entry must have the admin permission.
+    IdentityClaim(IdentityClaim),
+}
+
+/// A migration of namespaces from another registry, cross-signed by the
+/// operator of the source registry.
+///
+/// The signature binds the migration to the operator key that initialized
+/// the destination registry's operator log, so that it cannot be replayed
+/// into another registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The registry the namespaces are migrated from
+    pub registry: String,
+    /// The checkpoint of the source registry as of which the namespaces are migrated
+    pub checkpoint: Checkpoint,
+    /// The migrated namespaces
+    pub namespaces: Vec<String>,
+    /// The operator key of the source registry
+    pub key: signing::PublicKey,
+    /// The source operator's signature over the migration
+    pub signature: signing::Signature,
+}
+
+/// A security advisory published by the registry operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Advisory {
+    /// The identifier of the advisory, such as a CVE identifier.
+    ///
+    /// Identifiers are unique within an operator log.
+    pub id: String,
+    /// The package affected by the advisory.
+    pub package: PackageName,
+    /// The requirement matching the affected versions of the package.
+    pub affected: VersionReq,
+    /// A summary of the advisory.
+    pub summary: String,
+}
+
+impl Advisory {
+    /// Determines if the advisory affects the given version of its package.
+    pub fn affects(&self, version: &Version) -> bool {
+        self.affected.matches(version)
+    }
+}
+
+/// A binding of a key to an identity, attested by the registry operator.
+///
+/// The claim is signed by the key it is for, proving possession of the key
+/// (see [`IdentityClaim::sign`]); the operator records the claim once it has
+/// verified the identity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityClaim {
+    /// The key the identity is claimed for.
+    pub key: signing::PublicKey,
+    /// The claimed identity, such as an email address.
+    pub identity: String,
+    /// The key holder's signature over the claim.
+    pub proof: signing::Signature,
+}
+
+/// An administrative command issued to the registry by an operator admin key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AdminCommand {
+    /// Freeze a package; new records for it are rejected until it is unfrozen.
+    FreezePackage { name: PackageName },
+    /// Unfreeze a previously frozen package.
+    UnfreezePackage { name: PackageName },
+    /// Force the registry to re-validate the records of a package log.
+    RevalidatePackage { name: PackageName },
+    /// Collect content no longer referenced by any validated record.
+    CollectGarbage,
+    /// Rotate the key the registry signs checkpoints with.
+    /// The new key is granted the commit permission.
+    RotateCheckpointKey { key: signing::PublicKey },
+}
+
+impl fmt::Display for AdminCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FreezePackage { name } => write!(f, "freeze package `{name}`"),
+            Self::UnfreezePackage { name } => write!(f, "unfreeze package `{name}`"),
+            Self::RevalidatePackage { name } => write!(f, "revalidate package `{name}`"),
+            Self::CollectGarbage => write!(f, "collect garbage"),
+            Self::RotateCheckpointKey { key } => {
+                write!(f, "rotate checkpoint key to `{}`", key.fingerprint())
+            }
+        }
+    }
 }
 
 impl OperatorEntry {
@@ -106,9 +225,12 @@ impl OperatorEntry {
     pub fn required_permission(&self) -> Option<Permission> {
         match self {
             Self::Init { .. } => None,
-            Self::GrantFlat { .. } | Self::RevokeFlat { .. } => Some(Permission::Commit),
-            Self::DefineNamespace { .. } => Some(Permission::DefineNamespace),
+            Self::GrantFlat { .. } | Self::RevokeFlat { .. } | Self::DenyKey { .. } => {
+                Some(Permission::Commit)
+            }
+            Self::DefineNamespace { .. } | Self::Migrate(_) => Some(Permission::DefineNamespace),
             Self::ImportNamespace { .. } => Some(Permission::ImportNamespace),
+            Self::Admin(_) | Self::Advisory(_) | Self::IdentityClaim(_) => Some(Permission::Admin),
         }
     }
 }
@@ -1,13 +1,16 @@
 use super::{model, OPERATOR_RECORD_VERSION};
+use crate::package;
 use crate::registry::PackageName;
-use crate::registry::RecordId;
-use crate::ProtoEnvelope;
+use crate::registry::{Checkpoint, RecordId, RegistryIndex, RegistryLen};
+use crate::state_export::{visit_option, visit_time};
+use crate::{ProtoEnvelope, Version};
 use indexmap::{IndexMap, IndexSet};
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 use thiserror::Error;
 use warg_crypto::hash::{HashAlgorithm, Sha256};
-use warg_crypto::{signing, Signable};
+use warg_crypto::prefix;
+use warg_crypto::{signing, ByteVisitor, Signable};
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
@@ -66,6 +69,45 @@ pub enum ValidationError {
 
     #[error("the namespace `{namespace}` is already defined and cannot be redefined")]
     NamespaceAlreadyDefined { namespace: String },
+
+    #[error(
+        "the migration from registry `{registry}` was not signed by its operator for this registry"
+    )]
+    InvalidMigration { registry: String },
+
+    #[error("key `{key_id}` signing the migration from registry `{registry}` is not an operator key of that registry")]
+    MigrationKeyNotTrusted {
+        registry: String,
+        key_id: signing::KeyID,
+    },
+
+    #[error("package `{name}` is already frozen")]
+    PackageAlreadyFrozen { name: PackageName },
+
+    #[error("package `{name}` is not frozen")]
+    PackageNotFrozen { name: PackageName },
+
+    #[error("advisory `{id}` is already published")]
+    AdvisoryAlreadyPublished { id: String },
+
+    #[error("the identity claim `{identity}` was not signed by key `{key_id}`")]
+    InvalidIdentityClaim {
+        key_id: signing::KeyID,
+        identity: String,
+    },
+
+    #[error("identity `{identity}` is already claimed for key `{key_id}`")]
+    IdentityAlreadyClaimed {
+        key_id: signing::KeyID,
+        identity: String,
+    },
+
+    #[error("key `{key_id}` cannot be denied as of registry log length {log_length} by a record sequenced at registry index {registry_index}")]
+    RetroactiveKeyDenial {
+        key_id: signing::KeyID,
+        log_length: RegistryLen,
+        registry_index: RegistryIndex,
+    },
 }
 
 /// The namespace definition.
@@ -74,6 +116,9 @@ pub enum ValidationError {
 struct NamespaceDefinition {
     /// Namespace state.
     state: NamespaceState,
+    /// The migration of the namespace from another registry, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    migration: Option<NamespaceMigration>,
 }
 
 /// The namespace state for defining or importing from other registries.
@@ -90,6 +135,44 @@ pub enum NamespaceState {
     },
 }
 
+/// The source of a namespace migrated from another registry.
+///
+/// The package logs of a migrated namespace continue the package logs of the
+/// source registry as of the migration checkpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceMigration {
+    /// The registry the namespace was migrated from.
+    pub registry: String,
+    /// The checkpoint of the source registry as of which the namespace was migrated.
+    pub checkpoint: Checkpoint,
+    /// The key that signed the migration on behalf of the source registry.
+    ///
+    /// Validating the destination operator log only proves that this key
+    /// signed the migration; see [`NamespaceMigration::verify_source`].
+    pub key_id: signing::KeyID,
+}
+
+impl NamespaceMigration {
+    /// Verifies that the migration was signed by an operator key of the
+    /// source registry, given the validated state of its operator log.
+    ///
+    /// The key must be permitted to sign checkpoints and must not have been
+    /// declared compromised.
+    pub fn verify_source(&self, source: &LogState) -> Result<(), ValidationError> {
+        if !source.key_has_permission_to_sign_checkpoints(&self.key_id)
+            || source.key_denied_since(&self.key_id).is_some()
+        {
+            return Err(ValidationError::MigrationKeyNotTrusted {
+                registry: self.registry.clone(),
+                key_id: self.key_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
 /// Information about the current head of the operator log.
 ///
 /// A head is the last validated record digest and timestamp.
@@ -123,6 +206,18 @@ pub struct LogState {
     /// The namespaces known to the state. The key is the namespace.
     #[serde(skip_serializing_if = "IndexMap::is_empty")]
     namespaces: IndexMap<String, NamespaceDefinition>,
+    /// The keys declared compromised and the log length as of which they are compromised.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    denied_keys: IndexMap<signing::KeyID, RegistryLen>,
+    /// The packages frozen by an admin command.
+    #[serde(skip_serializing_if = "IndexSet::is_empty")]
+    frozen_packages: IndexSet<PackageName>,
+    /// The published advisories, indexed by affected package.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    advisories: IndexMap<PackageName, Vec<model::Advisory>>,
+    /// The key directory of identity claims, indexed by key.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    identities: IndexMap<signing::KeyID, Vec<model::IdentityClaim>>,
 }
 
 impl LogState {
@@ -165,6 +260,134 @@ impl LogState {
         self.namespaces.get(namespace).map(|def| &def.state)
     }
 
+    /// Gets the namespaces known to the state and their states.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&str, &NamespaceState)> {
+        self.namespaces
+            .iter()
+            .map(|(namespace, def)| (namespace.as_str(), &def.state))
+    }
+
+    /// Gets the migration of a namespace from another registry.
+    ///
+    /// Returns `None` if the namespace was not migrated.
+    pub fn namespace_migration(&self, namespace: &str) -> Option<&NamespaceMigration> {
+        self.namespaces.get(namespace)?.migration.as_ref()
+    }
+
+    /// Gets the registry log length as of which the given key was declared
+    /// compromised.
+    ///
+    /// Returns `None` if the key has not been declared compromised.
+    pub fn key_denied_since(&self, key_id: &signing::KeyID) -> Option<RegistryLen> {
+        self.denied_keys.get(key_id).copied()
+    }
+
+    /// Gets the keys declared compromised and the registry log length as of
+    /// which each is compromised.
+    pub fn denied_keys(&self) -> impl Iterator<Item = (&signing::KeyID, RegistryLen)> {
+        self.denied_keys.iter().map(|(k, l)| (k, *l))
+    }
+
+    /// Checks that a package record sequenced at the given registry index
+    /// was not signed by a key declared compromised as of that index.
+    ///
+    /// The record envelope, its entry signatures, and the issuer of its
+    /// publish token are checked.
+    pub fn check_package_record(
+        &self,
+        record: &ProtoEnvelope<package::PackageRecord>,
+        registry_index: RegistryIndex,
+    ) -> Result<(), package::ValidationError> {
+        let signers = std::iter::once(record.key_id())
+            .chain(
+                record
+                    .as_ref()
+                    .entry_signatures
+                    .iter()
+                    .map(|signature| &signature.key_id),
+            )
+            .chain(record.as_ref().publish_token.iter().map(|t| &t.issuer));
+        for key_id in signers {
+            if let Some(log_length) = self.key_denied_since(key_id) {
+                if registry_index >= log_length {
+                    return Err(package::ValidationError::KeyDenied {
+                        key_id: key_id.clone(),
+                        log_length,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that an operator record sequenced at the given registry index
+    /// does not declare a key compromised as of an earlier registry log
+    /// length, which would invalidate records already sequenced.
+    pub fn check_operator_record(
+        record: &ProtoEnvelope<model::OperatorRecord>,
+        registry_index: RegistryIndex,
+    ) -> Result<(), ValidationError> {
+        for entry in &record.as_ref().entries {
+            if let model::OperatorEntry::DenyKey { key_id, log_length } = entry {
+                if *log_length < registry_index {
+                    return Err(ValidationError::RetroactiveKeyDenial {
+                        key_id: key_id.clone(),
+                        log_length: *log_length,
+                        registry_index,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the given package was frozen by an admin command.
+    pub fn package_frozen(&self, name: &PackageName) -> bool {
+        self.frozen_packages.contains(name)
+    }
+
+    /// Gets the packages frozen by admin commands.
+    pub fn frozen_packages(&self) -> impl Iterator<Item = &PackageName> {
+        self.frozen_packages.iter()
+    }
+
+    /// Gets the advisories published for the given package, in log order.
+    pub fn advisories(&self, name: &PackageName) -> &[model::Advisory] {
+        self.advisories
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Gets the advisories affecting the given version of a package, in log
+    /// order.
+    pub fn advisories_for<'a>(
+        &'a self,
+        name: &PackageName,
+        version: &'a Version,
+    ) -> impl Iterator<Item = &'a model::Advisory> {
+        self.advisories(name)
+            .iter()
+            .filter(move |advisory| advisory.affects(version))
+    }
+
+    /// Gets the identity claims recorded for the given key, in log order.
+    pub fn identity_claims(&self, key_id: &signing::KeyID) -> &[model::IdentityClaim] {
+        self.identities
+            .get(key_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Gets the identities claimed for the given key, in log order.
+    pub fn identities<'a>(&'a self, key_id: &signing::KeyID) -> impl Iterator<Item = &'a str> {
+        self.identity_claims(key_id)
+            .iter()
+            .map(|claim| claim.identity.as_str())
+    }
+
     /// Checks the key has permission to sign checkpoints.
     pub fn key_has_permission_to_sign_checkpoints(&self, key_id: &signing::KeyID) -> bool {
         self.check_key_permissions(key_id, &[model::Permission::Commit])
@@ -301,7 +524,7 @@ impl LogState {
                     permissions,
                 } => self.validate_revoke_entry(signer_key_id, key_id, permissions)?,
                 model::OperatorEntry::DefineNamespace { namespace } => {
-                    self.validate_namespace(namespace, NamespaceState::Defined)?
+                    self.validate_namespace(namespace, NamespaceState::Defined, None)?
                 }
                 model::OperatorEntry::ImportNamespace {
                     namespace,
@@ -311,7 +534,21 @@ impl LogState {
                     NamespaceState::Imported {
                         registry: registry.to_string(),
                     },
+                    None,
                 )?,
+                model::OperatorEntry::DenyKey { key_id, log_length } => {
+                    self.validate_deny_key_entry(key_id, *log_length)
+                }
+                model::OperatorEntry::Migrate(migration) => {
+                    self.validate_migrate_entry(migration)?
+                }
+                model::OperatorEntry::Admin(command) => self.validate_admin_entry(command)?,
+                model::OperatorEntry::Advisory(advisory) => {
+                    self.validate_advisory_entry(advisory)?
+                }
+                model::OperatorEntry::IdentityClaim(claim) => {
+                    self.validate_identity_claim_entry(claim)?
+                }
             }
         }
 
@@ -385,10 +622,131 @@ impl LogState {
         Ok(())
     }
 
+    fn validate_deny_key_entry(&mut self, key_id: &signing::KeyID, log_length: RegistryLen) {
+        // A key declared compromised more than once is compromised as of the earliest length
+        self.denied_keys
+            .entry(key_id.clone())
+            .and_modify(|l| *l = (*l).min(log_length))
+            .or_insert(log_length);
+    }
+
+    fn validate_migrate_entry(
+        &mut self,
+        migration: &model::Migration,
+    ) -> Result<(), ValidationError> {
+        // The migration is signed for the key that initialized this log; the
+        // signing key is only known to belong to the source registry once
+        // verified against the source operator log by whoever follows it
+        let (destination, _) = self
+            .keys
+            .first()
+            .ok_or(ValidationError::FirstEntryIsNotInit)?;
+        migration
+            .verify(destination)
+            .map_err(|_| ValidationError::InvalidMigration {
+                registry: migration.registry.clone(),
+            })?;
+
+        for namespace in &migration.namespaces {
+            self.validate_namespace(
+                namespace,
+                NamespaceState::Defined,
+                Some(NamespaceMigration {
+                    registry: migration.registry.clone(),
+                    checkpoint: migration.checkpoint.clone(),
+                    key_id: migration.key.fingerprint(),
+                }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_advisory_entry(
+        &mut self,
+        advisory: &model::Advisory,
+    ) -> Result<(), ValidationError> {
+        if self
+            .advisories
+            .values()
+            .flatten()
+            .any(|existing| existing.id == advisory.id)
+        {
+            return Err(ValidationError::AdvisoryAlreadyPublished {
+                id: advisory.id.clone(),
+            });
+        }
+
+        self.advisories
+            .entry(advisory.package.clone())
+            .or_default()
+            .push(advisory.clone());
+        Ok(())
+    }
+
+    fn validate_identity_claim_entry(
+        &mut self,
+        claim: &model::IdentityClaim,
+    ) -> Result<(), ValidationError> {
+        let key_id = claim.key.fingerprint();
+        claim
+            .verify()
+            .map_err(|_| ValidationError::InvalidIdentityClaim {
+                key_id: key_id.clone(),
+                identity: claim.identity.clone(),
+            })?;
+
+        let claims = self.identities.entry(key_id.clone()).or_default();
+        if claims
+            .iter()
+            .any(|existing| existing.identity == claim.identity)
+        {
+            return Err(ValidationError::IdentityAlreadyClaimed {
+                key_id,
+                identity: claim.identity.clone(),
+            });
+        }
+
+        claims.push(claim.clone());
+        Ok(())
+    }
+
+    fn validate_admin_entry(
+        &mut self,
+        command: &model::AdminCommand,
+    ) -> Result<(), ValidationError> {
+        match command {
+            model::AdminCommand::FreezePackage { name } => {
+                if !self.frozen_packages.insert(name.clone()) {
+                    return Err(ValidationError::PackageAlreadyFrozen { name: name.clone() });
+                }
+            }
+            model::AdminCommand::UnfreezePackage { name } => {
+                if !self.frozen_packages.shift_remove(name) {
+                    return Err(ValidationError::PackageNotFrozen { name: name.clone() });
+                }
+            }
+            model::AdminCommand::RotateCheckpointKey { key } => {
+                let key_id = key.fingerprint();
+                self.keys.insert(key_id.clone(), key.clone());
+                self.permissions
+                    .entry(key_id)
+                    .or_default()
+                    .insert(model::Permission::Commit);
+            }
+            // These commands are recorded for auditability but do not change the state
+            model::AdminCommand::RevalidatePackage { .. } | model::AdminCommand::CollectGarbage => {
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_namespace(
         &mut self,
         namespace: &str,
         state: NamespaceState,
+        migration: Option<NamespaceMigration>,
     ) -> Result<(), ValidationError> {
         if !PackageName::is_valid_namespace(namespace) {
             return Err(ValidationError::InvalidNamespace {
@@ -403,8 +761,10 @@ impl LogState {
             })
         } else {
             // namespace is not defined
-            self.namespaces
-                .insert(namespace.to_string(), NamespaceDefinition { state });
+            self.namespaces.insert(
+                namespace.to_string(),
+                NamespaceDefinition { state, migration },
+            );
 
             Ok(())
         }
@@ -439,6 +799,97 @@ impl crate::Validator for LogState {
     fn validate(self, record: &ProtoEnvelope<Self::Record>) -> Result<Self, Self::Error> {
         self.validate(record)
     }
+
+    fn tolerate(&mut self, record: &ProtoEnvelope<Self::Record>) {
+        self.head = Some(Head {
+            digest: RecordId::operator_record::<Sha256>(record),
+            timestamp: record.as_ref().timestamp,
+        });
+    }
+
+    fn head_record_id(&self) -> Option<&RecordId> {
+        self.head.as_ref().map(|head| &head.digest)
+    }
+}
+
+// The canonical encoding of the state hashed by state exports.
+impl prefix::VisitPrefixEncode for LogState {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-OPERATOR-STATE-V0");
+        visit_option(visitor, self.algorithm.as_ref(), |v, algorithm| {
+            v.visit_str(&algorithm.to_string())
+        });
+        visit_option(visitor, self.head.as_ref(), |v, head| {
+            v.visit_str(&head.digest.to_string());
+            visit_time(v, head.timestamp);
+        });
+
+        visitor.visit_unsigned(self.permissions.len() as u64);
+        for (key_id, permissions) in &self.permissions {
+            visitor.visit_str(&key_id.to_string());
+            visitor.visit_unsigned(permissions.len() as u64);
+            for permission in permissions {
+                visitor.visit_str(&permission.to_string());
+            }
+        }
+
+        visitor.visit_unsigned(self.keys.len() as u64);
+        for (key_id, key) in &self.keys {
+            visitor.visit_str(&key_id.to_string());
+            visitor.visit_str(&key.to_string());
+        }
+
+        visitor.visit_unsigned(self.namespaces.len() as u64);
+        for (namespace, definition) in &self.namespaces {
+            visitor.visit_str(namespace);
+            match &definition.state {
+                NamespaceState::Defined => visitor.visit_str("defined"),
+                NamespaceState::Imported { registry } => {
+                    visitor.visit_str("imported");
+                    visitor.visit_str(registry);
+                }
+            }
+            visit_option(visitor, definition.migration.as_ref(), |v, migration| {
+                v.visit_str(&migration.registry);
+                migration.checkpoint.visit_pe(v);
+                v.visit_str(&migration.key_id.to_string());
+            });
+        }
+
+        visitor.visit_unsigned(self.denied_keys.len() as u64);
+        for (key_id, log_length) in &self.denied_keys {
+            visitor.visit_str(&key_id.to_string());
+            visitor.visit_unsigned(*log_length as u64);
+        }
+
+        visitor.visit_unsigned(self.frozen_packages.len() as u64);
+        for name in &self.frozen_packages {
+            visitor.visit_str(name.as_ref());
+        }
+
+        visitor.visit_unsigned(self.advisories.len() as u64);
+        for (package, advisories) in &self.advisories {
+            visitor.visit_str(package.as_ref());
+            visitor.visit_unsigned(advisories.len() as u64);
+            for advisory in advisories {
+                visitor.visit_str(&advisory.id);
+                visitor.visit_str(advisory.package.as_ref());
+                visitor.visit_str(&advisory.affected.to_string());
+                visitor.visit_str(&advisory.summary);
+            }
+        }
+
+        visitor.visit_unsigned(self.identities.len() as u64);
+        for (key_id, claims) in &self.identities {
+            visitor.visit_str(&key_id.to_string());
+            visitor.visit_unsigned(claims.len() as u64);
+            for claim in claims {
+                visitor.visit_str(&claim.key.to_string());
+                visitor.visit_str(&claim.identity);
+                visitor.visit_str(&claim.proof.to_string());
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -485,11 +936,16 @@ mod tests {
                     IndexSet::from([
                         model::Permission::Commit,
                         model::Permission::DefineNamespace,
-                        model::Permission::ImportNamespace
+                        model::Permission::ImportNamespace,
+                        model::Permission::Admin,
                     ]),
                 )]),
                 keys: IndexMap::from([(alice_id, alice_pub)]),
                 namespaces: IndexMap::new(),
+                denied_keys: IndexMap::new(),
+                frozen_packages: IndexSet::new(),
+                advisories: IndexMap::new(),
+                identities: IndexMap::new(),
             }
         );
     }
@@ -528,10 +984,15 @@ mod tests {
                     model::Permission::Commit,
                     model::Permission::DefineNamespace,
                     model::Permission::ImportNamespace,
+                    model::Permission::Admin,
                 ]),
             )]),
             keys: IndexMap::from([(alice_id, alice_pub)]),
             namespaces: IndexMap::new(),
+            denied_keys: IndexMap::new(),
+            frozen_packages: IndexSet::new(),
+            advisories: IndexMap::new(),
+            identities: IndexMap::new(),
         };
 
         assert_eq!(state, expected);
@@ -610,6 +1071,7 @@ mod tests {
                     model::Permission::Commit,
                     model::Permission::DefineNamespace,
                     model::Permission::ImportNamespace,
+                    model::Permission::Admin,
                 ]),
             )]),
             keys: IndexMap::from([(alice_id, alice_pub)]),
@@ -618,6 +1080,7 @@ mod tests {
                     "my-namespace".to_string(),
                     NamespaceDefinition {
                         state: NamespaceState::Defined,
+                        migration: None,
                     },
                 ),
                 (
@@ -626,9 +1089,14 @@ mod tests {
                         state: NamespaceState::Imported {
                             registry: "registry.example.com".to_string(),
                         },
+                        migration: None,
                     },
                 ),
             ]),
+            denied_keys: IndexMap::new(),
+            frozen_packages: IndexSet::new(),
+            advisories: IndexMap::new(),
+            identities: IndexMap::new(),
         };
 
         assert_eq!(state, expected);
@@ -689,4 +1157,424 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_denied_keys() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::DenyKey {
+                    key_id: bob_id.clone(),
+                    log_length: 10,
+                },
+                // The earliest declaration takes precedence
+                model::OperatorEntry::DenyKey {
+                    key_id: bob_id.clone(),
+                    log_length: 5,
+                },
+                model::OperatorEntry::DenyKey {
+                    key_id: bob_id.clone(),
+                    log_length: 20,
+                },
+            ],
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(state.key_denied_since(&bob_id), Some(5));
+
+        let package_record = ProtoEnvelope::signed_contents(
+            &bob_priv,
+            package::PackageRecord {
+                prev: None,
+                version: package::PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![package::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: bob_pub,
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .expect("failed to sign envelope");
+
+        // A key cannot be denied as of a length the registry log has passed
+        LogState::check_operator_record(&envelope, 5).unwrap();
+        match LogState::check_operator_record(&envelope, 6).unwrap_err() {
+            ValidationError::RetroactiveKeyDenial {
+                key_id,
+                log_length,
+                registry_index,
+            } => {
+                assert_eq!(key_id, bob_id);
+                assert_eq!(log_length, 5);
+                assert_eq!(registry_index, 6);
+            }
+            _ => panic!("expected a different error"),
+        }
+
+        state.check_package_record(&package_record, 4).unwrap();
+        match state.check_package_record(&package_record, 5).unwrap_err() {
+            package::ValidationError::KeyDenied { key_id, log_length } => {
+                assert_eq!(key_id, bob_id);
+                assert_eq!(log_length, 5);
+            }
+            _ => panic!("expected a different error"),
+        }
+    }
+
+    #[test]
+    fn test_admin_commands() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (carol_pub, _) = generate_p256_pair();
+        let name: PackageName = "test:package".parse().unwrap();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::GrantFlat {
+                    key: bob_pub.clone(),
+                    permissions: vec![model::Permission::Commit],
+                },
+                model::OperatorEntry::Admin(model::AdminCommand::FreezePackage {
+                    name: name.clone(),
+                }),
+                model::OperatorEntry::Admin(model::AdminCommand::RotateCheckpointKey {
+                    key: carol_pub.clone(),
+                }),
+            ],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert!(state.package_frozen(&name));
+        assert!(state.key_has_permission_to_sign_checkpoints(&carol_pub.fingerprint()));
+
+        // A package cannot be frozen twice
+        let record = |entries| model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries,
+        };
+        let freeze =
+            model::OperatorEntry::Admin(model::AdminCommand::FreezePackage { name: name.clone() });
+        let frozen = ProtoEnvelope::signed_contents(&alice_priv, record(vec![freeze]))
+            .expect("failed to sign envelope");
+        match state.clone().validate(&frozen).unwrap_err() {
+            ValidationError::PackageAlreadyFrozen { name: n } => assert_eq!(n, name),
+            _ => panic!("expected a different error"),
+        }
+
+        // Admin commands require the admin permission
+        let unfreeze = model::OperatorEntry::Admin(model::AdminCommand::UnfreezePackage {
+            name: name.clone(),
+        });
+        let unauthorized =
+            ProtoEnvelope::signed_contents(&bob_priv, record(vec![unfreeze.clone()]))
+                .expect("failed to sign envelope");
+        match state.clone().validate(&unauthorized).unwrap_err() {
+            ValidationError::UnauthorizedAction {
+                needed_permission, ..
+            } => assert_eq!(needed_permission, model::Permission::Admin),
+            _ => panic!("expected a different error"),
+        }
+
+        let unfrozen = ProtoEnvelope::signed_contents(&alice_priv, record(vec![unfreeze]))
+            .expect("failed to sign envelope");
+        let state = state.validate(&unfrozen).unwrap();
+        assert!(!state.package_frozen(&name));
+    }
+
+    #[test]
+    fn test_migrations() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+        let (_, source_priv) = generate_p256_pair();
+
+        let checkpoint = Checkpoint {
+            log_root: HashAlgorithm::Sha256.digest(b"log"),
+            log_length: 42,
+            map_root: HashAlgorithm::Sha256.digest(b"map"),
+        };
+        let migration = |destination: &signing::PublicKey| {
+            model::Migration::sign(
+                &source_priv,
+                &destination.fingerprint(),
+                "source.example.com",
+                checkpoint.clone(),
+                ["migrated".to_string()],
+            )
+            .expect("failed to sign migration")
+        };
+        let record = |migration| model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub.clone(),
+                },
+                model::OperatorEntry::Migrate(migration),
+            ],
+        };
+
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record(migration(&alice_pub)))
+            .expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(
+            state.namespace_state("migrated"),
+            Some(&NamespaceState::Defined)
+        );
+        assert_eq!(
+            state.namespace_migration("migrated"),
+            Some(&NamespaceMigration {
+                registry: "source.example.com".to_string(),
+                checkpoint: checkpoint.clone(),
+                key_id: source_priv.public_key().fingerprint(),
+            })
+        );
+
+        // The migration is only trusted once its key is verified against the
+        // operator log of the source registry
+        let source_log = |key: &signing::PrivateKey| {
+            let init = ProtoEnvelope::signed_contents(
+                key,
+                model::OperatorRecord {
+                    prev: None,
+                    version: 0,
+                    timestamp: SystemTime::now(),
+                    entries: vec![model::OperatorEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: key.public_key(),
+                    }],
+                },
+            )
+            .expect("failed to sign envelope");
+            LogState::default().validate(&init).unwrap()
+        };
+        let migrated = state.namespace_migration("migrated").unwrap();
+        migrated.verify_source(&source_log(&source_priv)).unwrap();
+        match migrated
+            .verify_source(&source_log(&alice_priv))
+            .unwrap_err()
+        {
+            ValidationError::MigrationKeyNotTrusted { registry, .. } => {
+                assert_eq!(registry, "source.example.com")
+            }
+            _ => panic!("expected a different error"),
+        }
+
+        // A migration signed for another registry is rejected
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record(migration(&bob_pub)))
+            .expect("failed to sign envelope");
+        match LogState::default().validate(&envelope).unwrap_err() {
+            ValidationError::InvalidMigration { registry } => {
+                assert_eq!(registry, "source.example.com")
+            }
+            _ => panic!("expected a different error"),
+        }
+    }
+
+    #[test]
+    fn test_export_signed() {
+        use crate::{StateExportError, Validator};
+
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub.clone(),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        let export = state.export_signed(&alice_priv).unwrap();
+        assert_eq!(
+            export.as_ref().head.as_ref(),
+            Some(&RecordId::operator_record::<Sha256>(&envelope))
+        );
+        state.verify_export(&export, &alice_pub).unwrap();
+
+        // Another party validating the same record arrives at the same state
+        let other = LogState::default().validate(&envelope).unwrap();
+        other.verify_export(&export, &alice_pub).unwrap();
+
+        // The state hash does not depend on how the state is serialized
+        let restored: LogState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        restored.verify_export(&export, &alice_pub).unwrap();
+
+        assert!(matches!(
+            state.verify_export(&export, &bob_pub),
+            Err(StateExportError::InvalidSignature)
+        ));
+
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![model::OperatorEntry::DefineNamespace {
+                namespace: "example".to_string(),
+            }],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = state.validate(&envelope).unwrap();
+        assert!(matches!(
+            state.verify_export(&export, &alice_pub),
+            Err(StateExportError::HeadMismatch { .. })
+        ));
+
+        // A state at the same head but with different contents is detected
+        let mut tampered = state.clone();
+        tampered.namespaces.clear();
+        let export = state.export_signed(&alice_priv).unwrap();
+        assert!(matches!(
+            tampered.verify_export(&export, &alice_pub),
+            Err(StateExportError::StateMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_advisories() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let name: PackageName = "test:package".parse().unwrap();
+        let advisory = |id: &str, affected: &str| {
+            model::OperatorEntry::Advisory(model::Advisory {
+                id: id.to_string(),
+                package: name.clone(),
+                affected: affected.parse().unwrap(),
+                summary: "a vulnerability".to_string(),
+            })
+        };
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                advisory("ADV-1", "<1.2.0"),
+                advisory("ADV-2", ">=1.1.0, <2.0.0"),
+            ],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        let ids = |version: &str| {
+            state
+                .advisories_for(&name, &version.parse().unwrap())
+                .map(|a| a.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("1.0.0"), ["ADV-1"]);
+        assert_eq!(ids("1.1.5"), ["ADV-1", "ADV-2"]);
+        assert!(ids("2.0.0").is_empty());
+        assert_eq!(state.advisories(&name).len(), 2);
+        assert!(state.advisories(&"test:other".parse().unwrap()).is_empty());
+
+        // Advisory identifiers cannot be reused
+        let record = model::OperatorRecord {
+            prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![advisory("ADV-1", "*")],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        assert!(matches!(
+            state.validate(&envelope),
+            Err(ValidationError::AdvisoryAlreadyPublished { id }) if id == "ADV-1"
+        ));
+    }
+
+    #[test]
+    fn test_identity_claims() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+        let claim = model::IdentityClaim::sign(&bob_priv, "bob@example.com").unwrap();
+
+        let record = model::OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::OperatorEntry::IdentityClaim(claim.clone()),
+            ],
+        };
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(
+            state.identities(&bob_id).collect::<Vec<_>>(),
+            ["bob@example.com"]
+        );
+        assert_eq!(state.identity_claims(&bob_id), std::slice::from_ref(&claim));
+        assert!(state
+            .identity_claims(&alice_priv.public_key().fingerprint())
+            .is_empty());
+
+        let next = |entry| {
+            let record = model::OperatorRecord {
+                prev: Some(RecordId::operator_record::<Sha256>(&envelope)),
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![model::OperatorEntry::IdentityClaim(entry)],
+            };
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope")
+        };
+
+        // The same identity cannot be claimed twice for a key
+        assert!(matches!(
+            state.clone().validate(&next(claim.clone())),
+            Err(ValidationError::IdentityAlreadyClaimed { identity, .. }) if identity == "bob@example.com"
+        ));
+
+        // Claims must be signed by the key they are for
+        let forged = model::IdentityClaim {
+            identity: "alice@example.com".to_string(),
+            ..claim
+        };
+        assert!(matches!(
+            state.validate(&next(forged)),
+            Err(ValidationError::InvalidIdentityClaim { key_id, .. }) if key_id == bob_id
+        ));
+    }
 }
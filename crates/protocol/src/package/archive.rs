@@ -0,0 +1,203 @@
+//! Self-contained archives of a single package's record chain.
+//!
+//! A [`PackageArchive`] carries every record of a package log along with
+//! the proofs binding them to a signed checkpoint, so that the provenance
+//! of one package can be attached to an SBOM and verified offline with
+//! only the operator's public key.
+
+use super::{LogState, PackageRecord, ValidationError};
+use crate::{
+    registry::{
+        LogId, LogLeaf, MapLeaf, PackageName, RecordId, RegistryIndex, RegistryLen,
+        TimestampedCheckpoint,
+    },
+    ProtoEnvelope, PublishedProtoEnvelopeBody, SerdeEnvelope,
+};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use thiserror::Error;
+use warg_crypto::{
+    hash::{AnyHash, Hash, HashError, Sha256},
+    signing, Encode, Signable,
+};
+use warg_transparency::{
+    log::{InclusionProofError, LogProofBundle},
+    map::MapProofBundle,
+};
+
+/// Represents an error verifying a package archive.
+#[derive(Debug, Error)]
+pub enum PackageArchiveError {
+    /// The signature of the archived checkpoint is invalid.
+    #[error("the signature of the archived checkpoint is invalid")]
+    InvalidSignature,
+    /// The archive contains no records.
+    #[error("the archive of package `{0}` contains no records")]
+    Empty(PackageName),
+    /// The archived records are not in registry order.
+    #[error("archived record at registry index {0} is out of registry order")]
+    OutOfOrder(RegistryIndex),
+    /// An archived record is beyond the archived checkpoint.
+    #[error("archived record at registry index {index} is beyond the checkpoint log length {log_length}")]
+    BeyondCheckpoint {
+        /// The registry index of the record.
+        index: RegistryIndex,
+        /// The log length of the checkpoint.
+        log_length: RegistryLen,
+    },
+    /// An archived record could not be decoded.
+    #[error("failed to decode archived record: {0}")]
+    InvalidRecord(anyhow::Error),
+    /// The archived records failed validation.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+    /// A proof bundle could not be decoded.
+    #[error("failed to decode proof bundle: {0}")]
+    InvalidProofBundle(anyhow::Error),
+    /// A proof is missing from the archive.
+    #[error("the archive is missing a proof for record `{0}`")]
+    MissingProof(RecordId),
+    /// A log inclusion proof is not for the archived checkpoint.
+    #[error("the inclusion proof for record `{0}` is not for the archived checkpoint")]
+    ProofLogLength(RecordId),
+    /// A log inclusion proof could not be evaluated.
+    #[error(transparent)]
+    InclusionProof(#[from] InclusionProofError),
+    /// A proof evaluated to a root other than that of the checkpoint.
+    #[error("proof evaluated to root `{found}` but the checkpoint root is `{root}`")]
+    IncorrectProof {
+        /// The root of the checkpoint.
+        root: AnyHash,
+        /// The root the proof evaluated to.
+        found: AnyHash,
+    },
+    /// A checkpoint root uses an unsupported hash algorithm.
+    #[error(transparent)]
+    Hash(#[from] HashError),
+}
+
+/// The record chain of a single package, bound to a signed checkpoint.
+///
+/// The map proof binds the head of the package log to the checkpoint's map
+/// root and the log proof binds every record to the checkpoint's log root.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageArchive {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The checkpoint the records are bound to.
+    pub checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    /// The records of the package log, in registry order.
+    pub records: Vec<PublishedProtoEnvelopeBody>,
+    /// The encoded map inclusion proof bundle for the head record.
+    #[serde_as(as = "Base64")]
+    pub map_proof: Vec<u8>,
+    /// The encoded log inclusion proof bundle for every record, in registry
+    /// order.
+    #[serde_as(as = "Base64")]
+    pub log_proof: Vec<u8>,
+}
+
+impl PackageArchive {
+    /// Gets the log identifier of the archived package.
+    pub fn log_id(&self) -> LogId {
+        LogId::package_log::<Sha256>(&self.name)
+    }
+
+    /// Verifies the archive against the operator key that signed its
+    /// checkpoint, returning the validated state of the package log.
+    ///
+    /// Operator policy, such as keys the operator has revoked, is not
+    /// checked; only the operator log carries that.
+    pub fn verify(
+        &self,
+        operator_key: &signing::PublicKey,
+    ) -> Result<LogState, PackageArchiveError> {
+        TimestampedCheckpoint::verify(
+            operator_key,
+            &self.checkpoint.as_ref().encode(),
+            self.checkpoint.signature(),
+        )
+        .map_err(|_| PackageArchiveError::InvalidSignature)?;
+
+        let checkpoint = &self.checkpoint.as_ref().checkpoint;
+        let log_id = self.log_id();
+        let mut state = LogState::default();
+        let mut leafs = Vec::with_capacity(self.records.len());
+        let mut last_index = None;
+        for record in &self.records {
+            let index = record.registry_index;
+            if last_index.is_some_and(|last| index <= last) {
+                return Err(PackageArchiveError::OutOfOrder(index));
+            }
+            if index >= checkpoint.log_length {
+                return Err(PackageArchiveError::BeyondCheckpoint {
+                    index,
+                    log_length: checkpoint.log_length,
+                });
+            }
+            last_index = Some(index);
+
+            let envelope: ProtoEnvelope<PackageRecord> = record
+                .envelope
+                .clone()
+                .try_into()
+                .map_err(PackageArchiveError::InvalidRecord)?;
+            state = state.validate(&envelope)?;
+            leafs.push(LogLeaf {
+                log_id: log_id.clone(),
+                record_id: RecordId::package_record::<Sha256>(&envelope),
+            });
+        }
+
+        let head = leafs
+            .last()
+            .ok_or_else(|| PackageArchiveError::Empty(self.name.clone()))?;
+
+        let bundle: LogProofBundle<Sha256, LogLeaf> = LogProofBundle::decode(&self.log_proof)
+            .map_err(PackageArchiveError::InvalidProofBundle)?;
+        let (log_data, _, inclusions) = bundle.unbundle();
+        let log_root: Hash<Sha256> = checkpoint.log_root.try_into()?;
+        for (i, leaf) in leafs.iter().enumerate() {
+            let proof = inclusions
+                .get(i)
+                .ok_or_else(|| PackageArchiveError::MissingProof(leaf.record_id.clone()))?;
+            if proof.log_length() != checkpoint.log_length {
+                return Err(PackageArchiveError::ProofLogLength(leaf.record_id.clone()));
+            }
+
+            let found = proof.evaluate_value(&log_data, leaf)?;
+            if found != log_root {
+                return Err(PackageArchiveError::IncorrectProof {
+                    root: checkpoint.log_root,
+                    found: found.into(),
+                });
+            }
+        }
+
+        let bundle: MapProofBundle<Sha256, LogId, MapLeaf> =
+            MapProofBundle::decode(&self.map_proof)
+                .map_err(PackageArchiveError::InvalidProofBundle)?;
+        let proof = bundle
+            .unbundle()
+            .into_iter()
+            .next()
+            .ok_or_else(|| PackageArchiveError::MissingProof(head.record_id.clone()))?;
+        let found = proof.evaluate(
+            &log_id,
+            &MapLeaf {
+                record_id: head.record_id.clone(),
+            },
+        );
+        let map_root: Hash<Sha256> = checkpoint.map_root.try_into()?;
+        if found != map_root {
+            return Err(PackageArchiveError::IncorrectProof {
+                root: checkpoint.map_root,
+                found: found.into(),
+            });
+        }
+
+        Ok(state)
+    }
+}
@@ -1,16 +1,36 @@
 use anyhow::Error;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use prost::Message;
+use std::{fmt, str::FromStr, time::SystemTime};
 use thiserror::Error;
-use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
+use warg_crypto::{
+    encryption::{ContentEncryption, WrappedKey},
+    hash::AnyHash,
+    signing, Decode, Encode, Signable,
+};
 use warg_protobuf::protocol as protobuf;
 
-use crate::{pbjson_to_prost_timestamp, prost_to_pbjson_timestamp, registry::RecordId};
+use crate::{
+    pbjson_to_prost_timestamp, prost_to_pbjson_timestamp,
+    registry::{PackageName, RecordId},
+    ContentType,
+};
 
+mod archive;
 mod model;
-mod state;
-
-pub use model::{PackageEntry, PackageRecord, Permission};
-pub use state::{LogState, Release, ReleaseState, ValidationError};
+pub mod state;
+
+pub use archive::{PackageArchive, PackageArchiveError};
+pub use model::{
+    EntryError, EntrySignature, PackageEntry, PackageRecord, Permission, PublishToken,
+    ReleaseArtifact, ReleaseManifest,
+};
+pub use state::{
+    CountersignaturePolicy, HashHandle, Head, KeyHandle, LogState, LogStats, PackageInterner,
+    PackageState, PermissionChange, PermissionChangeKind, PermissionsInfo, Release, ReleaseInfo,
+    ReleaseState, RequiredReviewers, Tag, TimestampPolicy, ValidationError, ValidationRules,
+    VersionHandle, YankInfo, YankPolicy,
+};
 
 /// The currently supported package protocol version.
 pub const PACKAGE_RECORD_VERSION: u32 = 0;
@@ -21,6 +41,10 @@ impl Decode for model::PackageRecord {
     }
 }
 
+impl ContentType for model::PackageRecord {
+    const CONTENT_TYPE: &'static str = "WARG-PACKAGE-RECORD-SIGNATURE-V0";
+}
+
 impl TryFrom<protobuf::PackageRecord> for model::PackageRecord {
     type Error = Error;
 
@@ -44,11 +68,46 @@ impl TryFrom<protobuf::PackageRecord> for model::PackageRecord {
             .collect();
         let entries = entries?;
 
+        let entry_signatures = record
+            .entry_signatures
+            .into_iter()
+            .map(|signature| -> Result<_, Error> {
+                Ok(model::EntrySignature {
+                    entry: signature.entry.try_into()?,
+                    key_id: signature.key_id.into(),
+                    signature: signature.signature.parse()?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let publish_token = record.publish_token.map(TryInto::try_into).transpose()?;
+
         Ok(model::PackageRecord {
             prev,
             version,
             timestamp,
             entries,
+            entry_signatures,
+            publish_token,
+        })
+    }
+}
+
+impl TryFrom<protobuf::PackagePublishToken> for model::PublishToken {
+    type Error = Error;
+
+    fn try_from(token: protobuf::PackagePublishToken) -> Result<Self, Self::Error> {
+        let expires = token.expires.ok_or(InvalidTimestampError)?;
+        Ok(model::PublishToken {
+            key: token.key.parse()?,
+            packages: token
+                .packages
+                .iter()
+                .map(|name| name.parse())
+                .collect::<Result<_, _>>()?,
+            expires: pbjson_to_prost_timestamp(expires).try_into()?,
+            issuer: token.issuer.into(),
+            signature: token.signature.parse()?,
         })
     }
 }
@@ -74,6 +133,7 @@ impl TryFrom<protobuf::PackageEntry> for model::PackageEntry {
                     .into_iter()
                     .map(TryInto::try_into)
                     .collect::<Result<_, _>>()?,
+                proof: grant_flat.proof.map(|proof| proof.parse()).transpose()?,
             },
             Contents::RevokeFlat(revoke_flat) => model::PackageEntry::RevokeFlat {
                 key_id: revoke_flat.key_id.into(),
@@ -89,9 +149,31 @@ impl TryFrom<protobuf::PackageEntry> for model::PackageEntry {
                     .parse()
                     .map_err(|error| Error::new(error) as Error)?,
                 content: release.content_hash.parse()?,
+                encryption: release
+                    .encryption
+                    .map(encryption_from_protobuf)
+                    .transpose()?,
+                manifest: release.manifest.map(manifest_from_protobuf).transpose()?,
             },
             Contents::Yank(yank) => model::PackageEntry::Yank {
                 version: yank.version.parse()?,
+                reason: yank.reason,
+            },
+            Contents::YankRange(yank) => model::PackageEntry::YankRange {
+                range: yank.range.parse()?,
+                reason: yank.reason,
+            },
+            Contents::Tag(tag) => model::PackageEntry::Tag {
+                tag: tag.tag,
+                version: tag.version.parse()?,
+            },
+            Contents::RequireReviewers(require) => model::PackageEntry::RequireReviewers {
+                threshold: require.threshold,
+                reviewers: require
+                    .reviewers
+                    .iter()
+                    .map(|key| key.parse())
+                    .collect::<Result<_, _>>()?,
             },
         };
         Ok(output)
@@ -102,6 +184,72 @@ impl TryFrom<protobuf::PackageEntry> for model::PackageEntry {
 #[error("no content in entry")]
 struct EmptyContentError;
 
+fn encryption_from_protobuf(
+    encryption: protobuf::ContentEncryption,
+) -> Result<ContentEncryption, Error> {
+    Ok(ContentEncryption {
+        recipients: encryption
+            .recipients
+            .into_iter()
+            .map(|recipient| {
+                Ok(WrappedKey {
+                    key_id: recipient.key_id.into(),
+                    encapsulated_key: recipient.encapsulated_key,
+                    wrapped_key: recipient.wrapped_key,
+                })
+            })
+            .collect::<Result<_, Error>>()?,
+    })
+}
+
+fn manifest_from_protobuf(
+    manifest: protobuf::ReleaseManifest,
+) -> Result<model::ReleaseManifest, Error> {
+    Ok(model::ReleaseManifest {
+        artifacts: manifest
+            .artifacts
+            .into_iter()
+            .map(|artifact| {
+                Ok(model::ReleaseArtifact {
+                    name: artifact.name,
+                    content: artifact.content_hash.parse()?,
+                    size: artifact.size,
+                    media_type: artifact.media_type,
+                })
+            })
+            .collect::<Result<_, Error>>()?,
+    })
+}
+
+fn manifest_to_protobuf(manifest: &model::ReleaseManifest) -> protobuf::ReleaseManifest {
+    protobuf::ReleaseManifest {
+        artifacts: manifest
+            .artifacts
+            .iter()
+            .map(|artifact| protobuf::ReleaseArtifact {
+                name: artifact.name.clone(),
+                content_hash: artifact.content.to_string(),
+                size: artifact.size,
+                media_type: artifact.media_type.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn encryption_to_protobuf(encryption: &ContentEncryption) -> protobuf::ContentEncryption {
+    protobuf::ContentEncryption {
+        recipients: encryption
+            .recipients
+            .iter()
+            .map(|recipient| protobuf::WrappedContentKey {
+                key_id: recipient.key_id.to_string(),
+                encapsulated_key: recipient.encapsulated_key.clone(),
+                wrapped_key: recipient.wrapped_key.clone(),
+            })
+            .collect(),
+    }
+}
+
 impl TryFrom<i32> for model::Permission {
     type Error = Error;
 
@@ -127,7 +275,7 @@ struct PermissionParseError {
 // Serialization
 
 impl Signable for model::PackageRecord {
-    const PREFIX: &'static [u8] = b"WARG-PACKAGE-RECORD-SIGNATURE-V0";
+    const PREFIX: &'static [u8] = Self::CONTENT_TYPE.as_bytes();
 }
 
 impl Encode for model::PackageRecord {
@@ -137,6 +285,14 @@ impl Encode for model::PackageRecord {
     }
 }
 
+impl model::PackageRecord {
+    /// Gets the length of the protobuf encoding of the record without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        protobuf::PackageRecord::from(self).encoded_len()
+    }
+}
+
 impl<'a> From<&'a model::PackageRecord> for protobuf::PackageRecord {
     fn from(record: &'a model::PackageRecord) -> Self {
         protobuf::PackageRecord {
@@ -144,7 +300,223 @@ impl<'a> From<&'a model::PackageRecord> for protobuf::PackageRecord {
             version: record.version,
             time: Some(prost_to_pbjson_timestamp(record.timestamp.into())),
             entries: record.entries.iter().map(|entry| entry.into()).collect(),
+            entry_signatures: record
+                .entry_signatures
+                .iter()
+                .map(|signature| protobuf::PackageEntrySignature {
+                    entry: signature.entry as u32,
+                    key_id: signature.key_id.to_string(),
+                    signature: signature.signature.to_string(),
+                })
+                .collect(),
+            publish_token: record.publish_token.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl<'a> From<&'a model::PublishToken> for protobuf::PackagePublishToken {
+    fn from(token: &'a model::PublishToken) -> Self {
+        protobuf::PackagePublishToken {
+            key: token.key.to_string(),
+            packages: token.packages.iter().map(ToString::to_string).collect(),
+            expires: Some(prost_to_pbjson_timestamp(token.expires.into())),
+            issuer: token.issuer.to_string(),
+            signature: token.signature.to_string(),
+        }
+    }
+}
+
+const ENTRY_SIGNATURE_PREFIX: &[u8] = b"WARG-PACKAGE-ENTRY-SIGNATURE-V0";
+const KEY_POSSESSION_PREFIX: &[u8] = b"WARG-PACKAGE-KEY-POSSESSION-V0";
+const PUBLISH_TOKEN_PREFIX: &[u8] = b"WARG-PACKAGE-PUBLISH-TOKEN-V0";
+
+impl model::PublishToken {
+    /// Issues a token authorizing the given key to publish releases of the
+    /// given packages until the token expires.
+    ///
+    /// A token is only accepted by a package log if it is scoped to the
+    /// package of the log alone; see [`model::PublishToken::authorizes`].
+    pub fn issue(
+        issuer: &signing::PrivateKey,
+        key: signing::PublicKey,
+        packages: impl IntoIterator<Item = PackageName>,
+        expires: SystemTime,
+    ) -> Result<Self, signing::SignatureError> {
+        let packages: Vec<PackageName> = packages.into_iter().collect();
+        let issuer_id = issuer.public_key().fingerprint();
+        let signature = issuer.sign(&publish_token_payload(protobuf::PackagePublishToken {
+            key: key.to_string(),
+            packages: packages.iter().map(ToString::to_string).collect(),
+            expires: Some(prost_to_pbjson_timestamp(expires.into())),
+            issuer: issuer_id.to_string(),
+            signature: String::new(),
+        }))?;
+
+        Ok(Self {
+            key,
+            packages,
+            expires,
+            issuer: issuer_id,
+            signature,
+        })
+    }
+
+    /// Verifies that the token was issued by the given key.
+    pub fn verify(&self, issuer: &signing::PublicKey) -> Result<(), signing::SignatureError> {
+        if issuer.fingerprint() != self.issuer {
+            return Err(signing::SignatureError::new());
+        }
+
+        let mut token = protobuf::PackagePublishToken::from(self);
+        token.signature.clear();
+        issuer.verify(&publish_token_payload(token), &self.signature)
+    }
+}
+
+/// Publish tokens are displayed as the base64 encoding of their protobuf
+/// representation, so that they may be passed to a CI system as a secret.
+impl fmt::Display for model::PublishToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = protobuf::PackagePublishToken::from(self);
+        write!(f, "{}", STANDARD.encode(token.encode_to_vec()))
+    }
+}
+
+impl FromStr for model::PublishToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        protobuf::PackagePublishToken::decode(STANDARD.decode(s.trim())?.as_slice())?.try_into()
+    }
+}
+
+/// Gets the payload signed by the issuer of a publish token.
+///
+/// The payload is the token without its signature.
+fn publish_token_payload(token: protobuf::PackagePublishToken) -> Vec<u8> {
+    [PUBLISH_TOKEN_PREFIX, &token.encode_to_vec()].concat()
+}
+
+impl model::PackageRecord {
+    /// Signs the entry at the given index with the given key.
+    ///
+    /// The signature binds the entry to its position in the record and to
+    /// the record's previous hash and timestamp, so it cannot be replayed
+    /// in another record. Any existing signature of the entry is replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of range.
+    pub fn sign_entry<S>(&mut self, index: usize, signer: &S) -> Result<(), signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        let signature = signer.sign(&self.entry_signing_payload(index))?;
+        self.entry_signatures.retain(|s| s.entry != index);
+        self.entry_signatures.push(model::EntrySignature {
+            entry: index,
+            key_id: signer.public_key().fingerprint(),
+            signature,
+        });
+        Ok(())
+    }
+
+    /// Gets the message signed by the signature of the entry at the given
+    /// index, so that entry signatures can be verified in a batch.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn entry_signing_message(&self, index: usize) -> Option<Vec<u8>> {
+        (index < self.entries.len()).then(|| self.entry_signing_payload(index))
+    }
+
+    /// Verifies the signature of the entry at the given index with the given key.
+    ///
+    /// Returns an error if the entry is not individually signed.
+    pub fn verify_entry_signature(
+        &self,
+        index: usize,
+        public_key: &signing::PublicKey,
+    ) -> Result<(), signing::SignatureError> {
+        let signature = self
+            .entry_signature(index)
+            .filter(|s| index < self.entries.len() && s.key_id == public_key.fingerprint())
+            .ok_or_else(signing::SignatureError::new)?;
+        public_key.verify(&self.entry_signing_payload(index), &signature.signature)
+    }
+
+    /// Proves possession of the key granted by the grant entry at the
+    /// given index by signing a challenge with the granted key.
+    ///
+    /// Like an entry signature, the challenge binds the grant to its position
+    /// in the record and to the record's previous hash and timestamp. Any
+    /// existing proof of the grant is replaced.
+    ///
+    /// Returns an error if the entry is not a grant of the given key's
+    /// public key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of range.
+    pub fn prove_key_possession(
+        &mut self,
+        index: usize,
+        private_key: &signing::PrivateKey,
+    ) -> Result<(), signing::SignatureError> {
+        let payload = self.key_possession_payload(index);
+        match &mut self.entries[index] {
+            model::PackageEntry::GrantFlat { key, proof, .. }
+                if key.fingerprint() == private_key.public_key().fingerprint() =>
+            {
+                *proof = Some(private_key.sign(&payload)?);
+                Ok(())
+            }
+            _ => Err(signing::SignatureError::new()),
+        }
+    }
+
+    /// Verifies the proof of possession of the grant entry at the given index.
+    ///
+    /// Returns an error if the entry is not a grant or has no proof.
+    pub fn verify_key_possession(&self, index: usize) -> Result<(), signing::SignatureError> {
+        match self.entries.get(index) {
+            Some(model::PackageEntry::GrantFlat {
+                key,
+                proof: Some(proof),
+                ..
+            }) => key.verify(&self.key_possession_payload(index), proof),
+            _ => Err(signing::SignatureError::new()),
+        }
+    }
+
+    fn entry_signing_payload(&self, index: usize) -> Vec<u8> {
+        self.entry_payload(ENTRY_SIGNATURE_PREFIX, index, (&self.entries[index]).into())
+    }
+
+    fn key_possession_payload(&self, index: usize) -> Vec<u8> {
+        let mut entry = protobuf::PackageEntry::from(&self.entries[index]);
+        if let Some(protobuf::package_entry::Contents::GrantFlat(grant)) = &mut entry.contents {
+            grant.proof = None;
         }
+
+        self.entry_payload(KEY_POSSESSION_PREFIX, index, entry)
+    }
+
+    fn entry_payload(&self, prefix: &[u8], index: usize, entry: protobuf::PackageEntry) -> Vec<u8> {
+        let proto_record = protobuf::PackageRecord {
+            prev: self.prev.as_ref().map(|hash| hash.to_string()),
+            version: self.version,
+            time: Some(prost_to_pbjson_timestamp(self.timestamp.into())),
+            entries: vec![entry],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        [
+            prefix,
+            &(index as u32).to_be_bytes(),
+            &proto_record.encode_to_vec(),
+        ]
+        .concat()
     }
 }
 
@@ -159,12 +531,15 @@ impl<'a> From<&'a model::PackageEntry> for protobuf::PackageEntry {
                 key: key.to_string(),
                 hash_algorithm: hash_algorithm.to_string(),
             }),
-            model::PackageEntry::GrantFlat { key, permissions } => {
-                Contents::GrantFlat(protobuf::PackageGrantFlat {
-                    key: key.to_string(),
-                    permissions: permissions.iter().map(Into::into).collect(),
-                })
-            }
+            model::PackageEntry::GrantFlat {
+                key,
+                permissions,
+                proof,
+            } => Contents::GrantFlat(protobuf::PackageGrantFlat {
+                key: key.to_string(),
+                permissions: permissions.iter().map(Into::into).collect(),
+                proof: proof.as_ref().map(ToString::to_string),
+            }),
             model::PackageEntry::RevokeFlat {
                 key_id,
                 permissions,
@@ -172,15 +547,40 @@ impl<'a> From<&'a model::PackageEntry> for protobuf::PackageEntry {
                 key_id: key_id.to_string(),
                 permissions: permissions.iter().map(Into::into).collect(),
             }),
-            model::PackageEntry::Release { version, content } => {
-                Contents::Release(protobuf::PackageRelease {
+            model::PackageEntry::Release {
+                version,
+                content,
+                encryption,
+                manifest,
+            } => Contents::Release(protobuf::PackageRelease {
+                version: version.to_string(),
+                content_hash: content.to_string(),
+                encryption: encryption.as_ref().map(encryption_to_protobuf),
+                manifest: manifest.as_ref().map(manifest_to_protobuf),
+            }),
+            model::PackageEntry::Yank { version, reason } => {
+                Contents::Yank(protobuf::PackageYank {
                     version: version.to_string(),
-                    content_hash: content.to_string(),
+                    reason: reason.clone(),
                 })
             }
-            model::PackageEntry::Yank { version } => Contents::Yank(protobuf::PackageYank {
+            model::PackageEntry::YankRange { range, reason } => {
+                Contents::YankRange(protobuf::PackageYankRange {
+                    range: range.to_string(),
+                    reason: reason.clone(),
+                })
+            }
+            model::PackageEntry::Tag { tag, version } => Contents::Tag(protobuf::PackageTag {
+                tag: tag.clone(),
                 version: version.to_string(),
             }),
+            model::PackageEntry::RequireReviewers {
+                threshold,
+                reviewers,
+            } => Contents::RequireReviewers(protobuf::PackageRequireReviewers {
+                threshold: *threshold,
+                reviewers: reviewers.iter().map(ToString::to_string).collect(),
+            }),
         };
         let contents = Some(contents);
         protobuf::PackageEntry { contents }
@@ -214,6 +614,8 @@ mod tests {
     fn test_envelope_roundtrip() {
         let (alice_pub, alice_priv) = generate_p256_pair();
         let (bob_pub, _bob_priv) = generate_p256_pair();
+        let (ciphertext, encryption) =
+            ContentEncryption::encrypt(&[0, 1, 2, 3], [&bob_pub]).unwrap();
 
         let record = model::PackageRecord {
             prev: None,
@@ -227,6 +629,7 @@ mod tests {
                 model::PackageEntry::GrantFlat {
                     key: bob_pub.clone(),
                     permissions: vec![model::Permission::Release, model::Permission::Yank],
+                    proof: None,
                 },
                 model::PackageEntry::RevokeFlat {
                     key_id: bob_pub.fingerprint(),
@@ -235,8 +638,31 @@ mod tests {
                 model::PackageEntry::Release {
                     version: Version::new(1, 0, 0),
                     content: HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+                    encryption: None,
+                    manifest: None,
                 },
+                model::PackageEntry::Release {
+                    version: Version::new(1, 1, 0),
+                    content: HashAlgorithm::Sha256.digest(&ciphertext),
+                    encryption: Some(encryption),
+                    manifest: None,
+                },
+                model::PackageEntry::release_with_manifest(
+                    "1.2.0",
+                    HashAlgorithm::Sha256.digest(&[4, 5, 6, 7]),
+                    model::ReleaseManifest {
+                        artifacts: vec![model::ReleaseArtifact {
+                            name: "debug-info".to_string(),
+                            content: HashAlgorithm::Sha256.digest(&[8, 9]),
+                            size: 2,
+                            media_type: "application/octet-stream".to_string(),
+                        }],
+                    },
+                )
+                .unwrap(),
             ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
         };
 
         let first_envelope = match ProtoEnvelope::signed_contents(&alice_priv, record) {
@@ -254,4 +680,111 @@ mod tests {
 
         assert_eq!(first_envelope, second_envelope);
     }
+
+    #[test]
+    fn test_checked_entries() {
+        let content = HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]);
+        let zero = warg_crypto::hash::AnyHash::new(HashAlgorithm::Sha256, vec![0; 32]);
+
+        assert!(model::PackageEntry::release("1.0.0", content).is_ok());
+        assert_eq!(
+            model::PackageEntry::release("1.*", content),
+            Err(EntryError::WildcardVersion("1.*".to_string()))
+        );
+        assert!(matches!(
+            model::PackageEntry::yank("not a version", None),
+            Err(EntryError::InvalidVersion { .. })
+        ));
+        assert_eq!(
+            model::PackageEntry::release("1.0.0", zero),
+            Err(EntryError::ZeroContentHash(zero))
+        );
+
+        let (alice_pub, _) = generate_p256_pair();
+        let (_, encryption) = ContentEncryption::encrypt(&[0, 1, 2, 3], [&alice_pub]).unwrap();
+        assert!(model::PackageEntry::encrypted_release("1.0.0", content, encryption).is_ok());
+        assert_eq!(
+            model::PackageEntry::encrypted_release(
+                "1.0.0",
+                content,
+                ContentEncryption {
+                    recipients: Vec::new()
+                }
+            ),
+            Err(EntryError::NoRecipients)
+        );
+
+        let artifact = |name: &str| model::ReleaseArtifact {
+            name: name.to_string(),
+            content,
+            size: 4,
+            media_type: "text/plain".to_string(),
+        };
+        let manifest = |artifacts| model::ReleaseManifest { artifacts };
+        assert!(model::PackageEntry::release_with_manifest(
+            "1.0.0",
+            content,
+            manifest(vec![artifact("docs")])
+        )
+        .is_ok());
+        assert_eq!(
+            model::PackageEntry::release_with_manifest("1.0.0", content, manifest(vec![])),
+            Err(EntryError::EmptyManifest)
+        );
+        assert_eq!(
+            model::PackageEntry::release_with_manifest(
+                "1.0.0",
+                content,
+                manifest(vec![artifact("docs"), artifact("docs")])
+            ),
+            Err(EntryError::DuplicateArtifact("docs".to_string()))
+        );
+        assert_eq!(
+            model::PackageEntry::grant(alice_pub.clone(), []),
+            Err(EntryError::NoPermissions)
+        );
+        assert_eq!(
+            model::PackageEntry::grant(
+                alice_pub.clone(),
+                [model::Permission::Yank, model::Permission::Yank]
+            ),
+            Ok(model::PackageEntry::GrantFlat {
+                key: alice_pub.clone(),
+                permissions: vec![model::Permission::Yank],
+                proof: None,
+            })
+        );
+
+        let mut record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub.clone(),
+                },
+                model::PackageEntry::release("1.0.0", content).unwrap(),
+                model::PackageEntry::yank("1.0.0", None).unwrap(),
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        assert_eq!(record.validate_self(), Ok(()));
+
+        record
+            .entries
+            .push(model::PackageEntry::release("1.0.0", content).unwrap());
+        assert_eq!(
+            record.validate_self(),
+            Err(EntryError::DuplicateRelease(Version::new(1, 0, 0)))
+        );
+
+        record.entries.pop();
+        record.entries.push(model::PackageEntry::Init {
+            hash_algorithm: HashAlgorithm::Sha256,
+            key: alice_pub,
+        });
+        assert_eq!(record.validate_self(), Err(EntryError::MisplacedInit));
+    }
 }
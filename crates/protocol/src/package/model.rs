@@ -1,13 +1,19 @@
-use crate::registry::RecordId;
+use crate::registry::{LogId, PackageName, RecordId};
 use core::fmt;
-use indexmap::IndexSet;
-use semver::Version;
+use indexmap::{IndexMap, IndexSet};
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, time::SystemTime};
-use warg_crypto::hash::{AnyHash, HashAlgorithm};
+use thiserror::Error;
+use warg_crypto::encryption::ContentEncryption;
+use warg_crypto::hash::{AnyHash, HashAlgorithm, Sha256};
 use warg_crypto::signing;
 
-/// A package record is a collection of entries published together by the same author
+/// A package record is a collection of entries published together.
+///
+/// Entries are authorized by the signer of the record's envelope unless
+/// individually signed by another key, or by the issuer of the record's
+/// publish token if it has one.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PackageRecord {
     /// The hash of the previous package record envelope
@@ -18,13 +24,326 @@ pub struct PackageRecord {
     pub timestamp: SystemTime,
     /// The entries being published in this record
     pub entries: Vec<PackageEntry>,
+    /// Signatures of individual entries by keys other than the envelope signer
+    pub entry_signatures: Vec<EntrySignature>,
+    /// A token authorizing the envelope signer to publish releases on behalf
+    /// of a maintainer key
+    pub publish_token: Option<PublishToken>,
+}
+
+impl PackageRecord {
+    /// Gets the signature of the entry at the given index, if it was
+    /// individually signed.
+    pub fn entry_signature(&self, index: usize) -> Option<&EntrySignature> {
+        self.entry_signatures.iter().find(|s| s.entry == index)
+    }
+
+    /// Gets the key that authorized the entry at the given index.
+    ///
+    /// This is the key that individually signed the entry, or the signer of
+    /// the record's envelope if the entry was not individually signed.
+    pub fn entry_signer<'a>(
+        &'a self,
+        index: usize,
+        envelope_key_id: &'a signing::KeyID,
+    ) -> &'a signing::KeyID {
+        self.entry_signature(index)
+            .map(|s| &s.key_id)
+            .unwrap_or(envelope_key_id)
+    }
+
+    /// Checks the rules that apply within a single record.
+    ///
+    /// This catches records that could never validate before they are signed:
+    /// an init entry that is not the first entry, a version released or
+    /// yanked more than once, a zero content digest, an invalid release
+    /// manifest, an invalid tag name, or a grant or revocation of no
+    /// permissions.
+    ///
+    /// Rules that depend on the state of the package log are checked by
+    /// [`LogState::validate`](super::LogState::validate).
+    pub fn validate_self(&self) -> Result<(), EntryError> {
+        let mut released = IndexSet::new();
+        let mut yanked = IndexSet::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            match entry {
+                PackageEntry::Init { .. } if index > 0 => return Err(EntryError::MisplacedInit),
+                PackageEntry::Init { .. } => {}
+                PackageEntry::GrantFlat { permissions, .. }
+                | PackageEntry::RevokeFlat { permissions, .. } => {
+                    if permissions.is_empty() {
+                        return Err(EntryError::NoPermissions);
+                    }
+                }
+                PackageEntry::Release {
+                    version,
+                    content,
+                    encryption,
+                    manifest,
+                } => {
+                    check_content(content)?;
+                    check_encryption(encryption.as_ref())?;
+                    if let Some(manifest) = manifest {
+                        check_manifest(manifest)?;
+                    }
+                    if !released.insert(version) {
+                        return Err(EntryError::DuplicateRelease(version.clone()));
+                    }
+                }
+                PackageEntry::Yank { version, .. } => {
+                    if !yanked.insert(version) {
+                        return Err(EntryError::DuplicateYank(version.clone()));
+                    }
+                }
+                PackageEntry::YankRange { .. } => {}
+                PackageEntry::Tag { tag, .. } => check_tag(tag)?,
+                PackageEntry::RequireReviewers {
+                    threshold,
+                    reviewers,
+                } => check_reviewers(*threshold, reviewers)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents an error constructing or checking package entries.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EntryError {
+    /// The version is a requirement matching many versions rather than a
+    /// single exact version.
+    #[error("`{0}` is a wildcard version; an exact version is required")]
+    WildcardVersion(String),
+    /// The version range could not be parsed.
+    #[error("invalid version range `{range}`: {message}")]
+    InvalidVersionRange {
+        /// The range that failed to parse.
+        range: String,
+        /// The parse error message.
+        message: String,
+    },
+    /// The version could not be parsed.
+    #[error("invalid version `{version}`: {message}")]
+    InvalidVersion {
+        /// The version that failed to parse.
+        version: String,
+        /// The parse error message.
+        message: String,
+    },
+    /// A release manifest does not list any artifacts.
+    #[error("a release manifest must list at least one artifact")]
+    EmptyManifest,
+    /// A release manifest artifact has an empty name.
+    #[error("release manifest artifacts must have a name")]
+    UnnamedArtifact,
+    /// A release manifest lists more than one artifact with the same name.
+    #[error("artifact `{0}` is listed more than once in the release manifest")]
+    DuplicateArtifact(String),
+    /// The tag name is not a valid channel tag.
+    #[error("invalid tag `{0}`: tags must start with a lowercase letter and contain only lowercase letters, digits, and hyphens")]
+    InvalidTag(String),
+    /// The content digest is all zeros.
+    #[error("content digest `{0}` is a zero hash")]
+    ZeroContentHash(AnyHash),
+    /// A grant or revocation did not specify any permissions.
+    #[error("no permissions were specified")]
+    NoPermissions,
+    /// An init entry was not the first entry of its record.
+    #[error("an init entry must be the first entry of a record")]
+    MisplacedInit,
+    /// A version was released more than once in a record.
+    #[error("version {0} is released more than once in the record")]
+    DuplicateRelease(Version),
+    /// A version was yanked more than once in a record.
+    #[error("version {0} is yanked more than once in the record")]
+    DuplicateYank(Version),
+    /// Encrypted content was released without any recipients.
+    #[error("encrypted content must have at least one recipient")]
+    NoRecipients,
+    /// More reviewers are required than there are distinct reviewer keys.
+    #[error("{threshold} reviewer(s) are required but only {reviewers} distinct reviewer key(s) were given")]
+    UnmetReviewerThreshold {
+        /// The number of reviewers required.
+        threshold: u32,
+        /// The number of distinct reviewer keys.
+        reviewers: usize,
+    },
+}
+
+fn parse_version(version: &str) -> Result<Version, EntryError> {
+    Version::parse(version).map_err(|e| {
+        // Requirements like `1.*` or `1.2` parse as requirements, but not as versions
+        if VersionReq::parse(version).is_ok() {
+            EntryError::WildcardVersion(version.to_string())
+        } else {
+            EntryError::InvalidVersion {
+                version: version.to_string(),
+                message: e.to_string(),
+            }
+        }
+    })
+}
+
+fn check_content(content: &AnyHash) -> Result<(), EntryError> {
+    if content.bytes().iter().all(|b| *b == 0) {
+        return Err(EntryError::ZeroContentHash(*content));
+    }
+
+    Ok(())
+}
+
+pub(super) fn check_manifest(manifest: &ReleaseManifest) -> Result<(), EntryError> {
+    if manifest.artifacts.is_empty() {
+        return Err(EntryError::EmptyManifest);
+    }
+
+    let mut names = IndexSet::new();
+    for artifact in &manifest.artifacts {
+        if artifact.name.is_empty() {
+            return Err(EntryError::UnnamedArtifact);
+        }
+
+        if !names.insert(artifact.name.as_str()) {
+            return Err(EntryError::DuplicateArtifact(artifact.name.clone()));
+        }
+
+        check_content(&artifact.content)?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn check_tag(tag: &str) -> Result<(), EntryError> {
+    // Starting with a letter keeps tags distinct from versions and requirements
+    let mut chars = tag.chars();
+    if !chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        || !chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(EntryError::InvalidTag(tag.to_string()));
+    }
+
+    Ok(())
+}
+
+fn check_reviewers(threshold: u32, reviewers: &[signing::PublicKey]) -> Result<(), EntryError> {
+    let distinct = reviewers
+        .iter()
+        .map(|key| key.fingerprint())
+        .collect::<IndexSet<_>>()
+        .len();
+    if threshold as usize > distinct {
+        return Err(EntryError::UnmetReviewerThreshold {
+            threshold,
+            reviewers: distinct,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_encryption(encryption: Option<&ContentEncryption>) -> Result<(), EntryError> {
+    if encryption.is_some_and(|e| e.recipients.is_empty()) {
+        return Err(EntryError::NoRecipients);
+    }
+
+    Ok(())
+}
+
+/// A manifest of the named artifacts shipped with a release alongside its
+/// primary content, such as debug information or WIT documentation.
+///
+/// Artifacts are never encrypted, even if the primary content is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseManifest {
+    /// The artifacts of the release
+    pub artifacts: Vec<ReleaseArtifact>,
+}
+
+impl ReleaseManifest {
+    /// Gets the artifact with the given name.
+    pub fn artifact(&self, name: &str) -> Option<&ReleaseArtifact> {
+        self.artifacts.iter().find(|a| a.name == name)
+    }
+}
+
+/// A named artifact listed in a [`ReleaseManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseArtifact {
+    /// The name of the artifact, unique within the manifest
+    pub name: String,
+    /// The content digest of the artifact
+    pub content: AnyHash,
+    /// The size of the artifact in bytes
+    pub size: u64,
+    /// The media type of the artifact, such as `application/wasm`
+    pub media_type: String,
+}
+
+/// A signature over a single entry of a package record.
+///
+/// Entry signatures allow a single record to bundle entries authorized by
+/// different keys; the signer of the record's envelope need not have
+/// permission for an individually signed entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrySignature {
+    /// The index of the signed entry in the record
+    pub entry: usize,
+    /// The hash of the key that signed the entry
+    pub key_id: signing::KeyID,
+    /// The signature over the entry
+    pub signature: signing::Signature,
+}
+
+/// A short-lived token issued by a maintainer key that authorizes another
+/// key, such as one held by a CI system, to publish releases of specific
+/// packages.
+///
+/// A record carrying a publish token is signed by the authorized key and may
+/// only contain release entries; the releases are authorized by the issuer,
+/// which must have the release permission when the record is validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishToken {
+    /// The key authorized to publish
+    pub key: signing::PublicKey,
+    /// The packages the key may publish releases of
+    pub packages: Vec<PackageName>,
+    /// When the token expires
+    pub expires: SystemTime,
+    /// The hash of the key that issued the token
+    pub issuer: signing::KeyID,
+    /// The issuer's signature over the token
+    pub signature: signing::Signature,
+}
+
+impl PublishToken {
+    /// Determines if the token authorizes publishing releases to the given
+    /// package log.
+    ///
+    /// The scope of the token must be limited to the package of the log; a
+    /// token scoped to any other package authorizes no log, so a token leaked
+    /// by the CI system of one package cannot publish another.
+    pub fn authorizes(&self, log_id: &LogId) -> bool {
+        !self.packages.is_empty()
+            && self
+                .packages
+                .iter()
+                .all(|name| LogId::package_log::<Sha256>(name) == *log_id)
+    }
+
+    /// Determines if the token has expired as of the given time.
+    pub fn expired(&self, now: SystemTime) -> bool {
+        now > self.expires
+    }
 }
 
 impl crate::Record for PackageRecord {
     fn contents(&self) -> IndexSet<&AnyHash> {
         self.entries
             .iter()
-            .filter_map(PackageEntry::content)
+            .flat_map(|entry| entry.content().into_iter().chain(entry.artifacts()))
             .collect()
     }
 }
@@ -79,9 +398,12 @@ pub enum PackageEntry {
     },
     /// Grant the specified key a permission.
     /// The author of this entry must have the permission.
+    /// If present, the proof must be a signature by the granted key
+    /// proving possession of it (see [`PackageRecord::prove_key_possession`]).
     GrantFlat {
         key: signing::PublicKey,
         permissions: Vec<Permission>,
+        proof: Option<signing::Signature>,
     },
     /// Remove a permission from a key.
     /// The author of this entry must have the permission.
@@ -91,19 +413,201 @@ pub enum PackageEntry {
     },
     /// Release a version of a package.
     /// The version must not have been released yet.
-    Release { version: Version, content: AnyHash },
+    ///
+    /// If the content is encrypted, the content digest is of the ciphertext.
+    /// A release may also ship named artifacts listed in a manifest.
+    Release {
+        version: Version,
+        content: AnyHash,
+        encryption: Option<ContentEncryption>,
+        manifest: Option<ReleaseManifest>,
+    },
     /// Yank a version of a package.
     /// The version must have been released and not yanked.
-    Yank { version: Version },
+    Yank {
+        version: Version,
+        reason: Option<String>,
+    },
+    /// Yank every released version of a package matching a range, as
+    /// of the position of the entry in the log.
+    /// At least one matching version must be released and not yanked;
+    /// matching versions that are already yanked are skipped.
+    ///
+    /// Versions are matched with semver rules, so a pre-release only
+    /// matches a range naming a pre-release of the same version.
+    YankRange {
+        range: VersionReq,
+        reason: Option<String>,
+    },
+    /// Point a named channel tag, such as `latest` or `stable`, at a
+    /// released version.
+    /// The version must have been released and not yanked; a tag that
+    /// already exists is moved to the version.
+    Tag { tag: String, version: Version },
+    /// Require records releasing versions, or changing this requirement, to
+    /// be signed by at least `threshold` of the reviewer keys.
+    /// A threshold of zero removes the requirement.
+    ///
+    /// The requirement may only be changed by a key holding every package
+    /// permission, as a key that may only release could otherwise make
+    /// itself the sole reviewer or drop the requirement it is subject to.
+    ///
+    /// Reviewers approve a record by signing its envelope or cosigning it
+    /// (see [`ProtoEnvelope::cosign`](crate::ProtoEnvelope::cosign)).
+    RequireReviewers {
+        threshold: u32,
+        reviewers: Vec<signing::PublicKey>,
+    },
 }
 
 impl PackageEntry {
+    /// Creates a release entry, checking that the version is exact and the
+    /// content digest is not a zero hash.
+    pub fn release(version: &str, content: AnyHash) -> Result<Self, EntryError> {
+        check_content(&content)?;
+        Ok(Self::Release {
+            version: parse_version(version)?,
+            content,
+            encryption: None,
+            manifest: None,
+        })
+    }
+
+    /// Creates a release entry shipping the artifacts of a manifest
+    /// alongside its content, checking that the version is exact, the
+    /// content digests are not zero hashes, and the manifest is valid.
+    pub fn release_with_manifest(
+        version: &str,
+        content: AnyHash,
+        manifest: ReleaseManifest,
+    ) -> Result<Self, EntryError> {
+        check_content(&content)?;
+        check_manifest(&manifest)?;
+        Ok(Self::Release {
+            version: parse_version(version)?,
+            content,
+            encryption: None,
+            manifest: Some(manifest),
+        })
+    }
+
+    /// Creates a release entry for encrypted content, checking that the
+    /// version is exact, the content digest is not a zero hash, and the
+    /// content has at least one recipient.
+    ///
+    /// The content digest is of the ciphertext.
+    pub fn encrypted_release(
+        version: &str,
+        content: AnyHash,
+        encryption: ContentEncryption,
+    ) -> Result<Self, EntryError> {
+        check_content(&content)?;
+        check_encryption(Some(&encryption))?;
+        Ok(Self::Release {
+            version: parse_version(version)?,
+            content,
+            encryption: Some(encryption),
+            manifest: None,
+        })
+    }
+
+    /// Creates a yank entry, checking that the version is exact.
+    pub fn yank(version: &str, reason: Option<String>) -> Result<Self, EntryError> {
+        Ok(Self::Yank {
+            version: parse_version(version)?,
+            reason,
+        })
+    }
+
+    /// Creates a range yank entry, checking that the range parses.
+    pub fn yank_range(range: &str, reason: Option<String>) -> Result<Self, EntryError> {
+        Ok(Self::YankRange {
+            range: VersionReq::parse(range).map_err(|e| EntryError::InvalidVersionRange {
+                range: range.to_string(),
+                message: e.to_string(),
+            })?,
+            reason,
+        })
+    }
+
+    /// Creates a tag entry, checking that the tag name is valid and the
+    /// version is exact.
+    pub fn tag(tag: &str, version: &str) -> Result<Self, EntryError> {
+        check_tag(tag)?;
+        Ok(Self::Tag {
+            tag: tag.to_string(),
+            version: parse_version(version)?,
+        })
+    }
+
+    /// Creates a required reviewers entry, checking that the threshold can
+    /// be met by the reviewers.
+    ///
+    /// Duplicate reviewers are removed.
+    pub fn require_reviewers(
+        threshold: u32,
+        reviewers: impl IntoIterator<Item = signing::PublicKey>,
+    ) -> Result<Self, EntryError> {
+        let reviewers = reviewers
+            .into_iter()
+            .map(|key| (key.fingerprint(), key))
+            .collect::<IndexMap<_, _>>()
+            .into_values()
+            .collect::<Vec<_>>();
+        check_reviewers(threshold, &reviewers)?;
+        Ok(Self::RequireReviewers {
+            threshold,
+            reviewers,
+        })
+    }
+
+    /// Creates a grant entry, checking that at least one permission is granted.
+    ///
+    /// Duplicate permissions are removed.
+    pub fn grant(
+        key: signing::PublicKey,
+        permissions: impl IntoIterator<Item = Permission>,
+    ) -> Result<Self, EntryError> {
+        let permissions = permissions.into_iter().collect::<IndexSet<_>>();
+        if permissions.is_empty() {
+            return Err(EntryError::NoPermissions);
+        }
+
+        Ok(Self::GrantFlat {
+            key,
+            permissions: permissions.into_iter().collect(),
+            proof: None,
+        })
+    }
+
+    /// Creates a revoke entry, checking that at least one permission is revoked.
+    ///
+    /// Duplicate permissions are removed.
+    pub fn revoke(
+        key_id: signing::KeyID,
+        permissions: impl IntoIterator<Item = Permission>,
+    ) -> Result<Self, EntryError> {
+        let permissions = permissions.into_iter().collect::<IndexSet<_>>();
+        if permissions.is_empty() {
+            return Err(EntryError::NoPermissions);
+        }
+
+        Ok(Self::RevokeFlat {
+            key_id,
+            permissions: permissions.into_iter().collect(),
+        })
+    }
+
     /// Check permission is required to submit this entry
     pub fn required_permission(&self) -> Option<Permission> {
         match self {
-            Self::Init { .. } | Self::GrantFlat { .. } | Self::RevokeFlat { .. } => None,
-            Self::Release { .. } => Some(Permission::Release),
-            Self::Yank { .. } => Some(Permission::Yank),
+            // These entries check the permissions of their signer themselves
+            Self::Init { .. }
+            | Self::GrantFlat { .. }
+            | Self::RevokeFlat { .. }
+            | Self::RequireReviewers { .. } => None,
+            Self::Release { .. } | Self::Tag { .. } => Some(Permission::Release),
+            Self::Yank { .. } | Self::YankRange { .. } => Some(Permission::Yank),
         }
     }
 
@@ -116,4 +620,18 @@ impl PackageEntry {
             _ => None,
         }
     }
+
+    /// Gets the content digests of the artifacts listed in the entry's
+    /// release manifest, if it has one.
+    pub fn artifacts(&self) -> impl Iterator<Item = &AnyHash> {
+        match self {
+            Self::Release {
+                manifest: Some(manifest),
+                ..
+            } => manifest.artifacts.as_slice(),
+            _ => &[],
+        }
+        .iter()
+        .map(|artifact| &artifact.content)
+    }
 }
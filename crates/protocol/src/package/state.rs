@@ -1,13 +1,29 @@
+//! The state of a package log.
+//!
+//! [`PackageState`] is built by validating the records of a package log in
+//! order. Embedders such as registry UIs and resolvers can query it for the
+//! releases, keys, and permissions of a package.
+
 use super::{model, PACKAGE_RECORD_VERSION};
-use crate::registry::RecordId;
-use crate::ProtoEnvelope;
+use crate::intern::{Handle, Interner, InternerFull, Resolver};
+use crate::registry::{LogId, RecordId, RegistryLen};
+use crate::state_export::{visit_option, visit_time};
+use crate::{Cosignature, ProtoEnvelope};
 use indexmap::{map::Entry, IndexMap, IndexSet};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
 use thiserror::Error;
+use warg_crypto::encryption::ContentEncryption;
 use warg_crypto::hash::{AnyHash, HashAlgorithm, Sha256};
-use warg_crypto::{signing, Signable};
+use warg_crypto::timestamp::{TimestampAuthority, TimestampError};
+use warg_crypto::{prefix, signing, ByteVisitor, Signable};
+
+/// The number of seconds in a day.
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
@@ -35,8 +51,12 @@ pub enum ValidationError {
         key_id: signing::KeyID,
     },
 
-    #[error("an entry attempted to release version {version} which is already released")]
-    ReleaseOfReleased { version: Version },
+    #[error("record `{record_id}` attempted to release version {version} which was already released by record `{existing}`")]
+    DuplicateRelease {
+        version: Version,
+        existing: RecordId,
+        record_id: RecordId,
+    },
 
     #[error("an entry attempted to yank version {version} which had not yet been released")]
     YankOfUnreleased { version: Version },
@@ -44,6 +64,23 @@ pub enum ValidationError {
     #[error("an entry attempted to yank version {version} which is already yanked")]
     YankOfYanked { version: Version },
 
+    #[error(
+        "an entry attempted to yank versions matching `{range}` but no unyanked release matches"
+    )]
+    YankRangeMatchesNothing { range: VersionReq },
+
+    #[error("the manifest of version {version} is invalid: {message}")]
+    InvalidManifest { version: Version, message: String },
+
+    #[error("an entry attempted to assign invalid tag `{tag}`")]
+    InvalidTag { tag: String },
+
+    #[error("an entry attempted to tag version {version} as `{tag}` but it has not been released")]
+    TagOfUnreleased { tag: String, version: Version },
+
+    #[error("an entry attempted to tag version {version} as `{tag}` but it has been yanked")]
+    TagOfYanked { tag: String, version: Version },
+
     #[error("unable to verify signature")]
     SignatureError(#[from] signing::SignatureError),
 
@@ -67,6 +104,325 @@ pub enum ValidationError {
 
     #[error("record has lower timestamp than previous")]
     TimestampLowerThanPrevious,
+
+    #[error("entry signature refers to entry {index} which is not in the record")]
+    EntrySignatureOutOfRange { index: usize },
+
+    #[error("entry {index} has more than one signature")]
+    DuplicateEntrySignature { index: usize },
+
+    #[error("the signature of entry {index} is invalid")]
+    InvalidEntrySignature { index: usize },
+
+    #[error("the record requires an operator countersignature")]
+    CountersignatureRequired,
+
+    #[error("the operator countersignature on the record is invalid")]
+    InvalidCountersignature,
+
+    #[error("the record requires a trusted timestamp")]
+    TimestampRequired,
+
+    #[error("the trusted timestamp on the record is invalid: {0}")]
+    InvalidTimestamp(#[source] TimestampError),
+
+    #[error("the proof of possession of the key granted by entry {index} is invalid")]
+    InvalidPossessionProof { index: usize },
+
+    #[error("the grant of entry {index} does not prove possession of the granted key")]
+    PossessionProofRequired { index: usize },
+
+    #[error("the publish token does not authorize key {key_id} that signed the record")]
+    PublishTokenKeyMismatch { key_id: signing::KeyID },
+
+    #[error("the publish token expired before the record was published")]
+    PublishTokenExpired,
+
+    #[error("the publish token is not scoped to package log `{log_id}` alone")]
+    PublishTokenScopeNotAllowed { log_id: LogId },
+
+    #[error("entry {index} is not a release, which is the only entry a publish token authorizes")]
+    PublishTokenEntryNotAllowed { index: usize },
+
+    #[error("the signature of the publish token is invalid")]
+    InvalidPublishToken,
+
+    #[error("the record is signed by key {key_id} which was declared compromised as of registry log length {log_length}")]
+    KeyDenied {
+        key_id: signing::KeyID,
+        log_length: RegistryLen,
+    },
+
+    #[error("the record requires the approval of {threshold} reviewer(s) but has {approvals}")]
+    ReviewersRequired { threshold: u32, approvals: u32 },
+
+    #[error("the cosignature by reviewer {key_id} is invalid")]
+    InvalidCosignature { key_id: signing::KeyID },
+
+    #[error("version {version} can no longer be yanked without an advisory because it was released more than {days} day(s) ago")]
+    YankWindowElapsed { version: Version, days: u64 },
+
+    #[error("the package log refers to too many distinct values: {0}")]
+    InternerFull(#[from] InternerFull),
+
+    #[error("the package log state refers to a value it has not interned")]
+    Uninterned,
+}
+
+/// A policy describing which package entries must be countersigned by the
+/// registry operator before the record containing them may be sequenced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountersignaturePolicy {
+    key: signing::PublicKey,
+    init: bool,
+    key_rotation: bool,
+}
+
+impl CountersignaturePolicy {
+    /// Creates a new policy for countersignatures by the given operator key.
+    ///
+    /// By default, no entries require a countersignature.
+    pub fn new(key: signing::PublicKey) -> Self {
+        Self {
+            key,
+            init: false,
+            key_rotation: false,
+        }
+    }
+
+    /// Requires `init` entries to be countersigned.
+    pub fn with_init(mut self, required: bool) -> Self {
+        self.init = required;
+        self
+    }
+
+    /// Requires key rotation (`grant`, `revoke`, and `require-reviewers`)
+    /// entries to be countersigned.
+    pub fn with_key_rotation(mut self, required: bool) -> Self {
+        self.key_rotation = required;
+        self
+    }
+
+    /// Determines if the given record requires a countersignature.
+    pub fn requires(&self, record: &model::PackageRecord) -> bool {
+        record.entries.iter().any(|entry| match entry {
+            model::PackageEntry::Init { .. } => self.init,
+            model::PackageEntry::GrantFlat { .. }
+            | model::PackageEntry::RevokeFlat { .. }
+            | model::PackageEntry::RequireReviewers { .. } => self.key_rotation,
+            model::PackageEntry::Release { .. }
+            | model::PackageEntry::Yank { .. }
+            | model::PackageEntry::YankRange { .. }
+            | model::PackageEntry::Tag { .. } => false,
+        })
+    }
+
+    /// Checks the countersignature of the given record against the policy.
+    ///
+    /// A countersignature that is present is always verified, even if the
+    /// record does not require one.
+    pub fn check(
+        &self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+    ) -> Result<(), ValidationError> {
+        match record.countersignature() {
+            Some(countersignature) => countersignature
+                .verify(&self.key, record.content_bytes())
+                .map_err(|_| ValidationError::InvalidCountersignature),
+            None if self.requires(record.as_ref()) => {
+                Err(ValidationError::CountersignatureRequired)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// A policy describing which time-stamping authorities are trusted to
+/// timestamp records, and whether records must be timestamped.
+///
+/// A trusted timestamp proves that a record existed at the time of the
+/// timestamp, such as before the key that signed it was revoked.
+#[derive(Debug, Clone)]
+pub struct TimestampPolicy {
+    authorities: Vec<TimestampAuthority>,
+    required: bool,
+}
+
+impl TimestampPolicy {
+    /// Creates a new policy trusting the given authorities.
+    ///
+    /// By default, records are not required to be timestamped.
+    pub fn new(authorities: impl IntoIterator<Item = TimestampAuthority>) -> Self {
+        Self {
+            authorities: authorities.into_iter().collect(),
+            required: false,
+        }
+    }
+
+    /// Requires every record to be timestamped.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Checks the timestamp of the given record against the policy.
+    ///
+    /// Returns the time of the timestamp if the record is timestamped by a
+    /// trusted authority.
+    pub fn check(
+        &self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+    ) -> Result<Option<SystemTime>, ValidationError> {
+        match record.timestamp() {
+            Some(timestamp) => timestamp
+                .verify(record.content_bytes(), &self.authorities)
+                .map(Some)
+                .map_err(ValidationError::InvalidTimestamp),
+            None if self.required => Err(ValidationError::TimestampRequired),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The rules of the protocol a package log is validated under, beyond those
+/// every package log follows.
+///
+/// A registry opts in to the rules it enforces when sequencing records;
+/// clients and auditors of the registry replay its logs under the same
+/// rules with [`PackageState::validate_with_rules`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationRules {
+    #[serde(default)]
+    key_possession: bool,
+}
+
+impl ValidationRules {
+    /// Creates the rules every package log follows.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires every grant to prove possession of the granted key.
+    ///
+    /// See [`model::PackageRecord::prove_key_possession`].
+    pub fn with_key_possession(mut self, required: bool) -> Self {
+        self.key_possession = required;
+        self
+    }
+
+    /// Determines if grants must prove possession of the granted key.
+    pub fn key_possession(&self) -> bool {
+        self.key_possession
+    }
+}
+
+/// A policy allowing versions to be yanked only within a number of days of
+/// their release, unless the yank is accompanied by an advisory.
+///
+/// A yank is accompanied by an advisory if its reason begins with the
+/// identifier of an advisory, such as `CVE-2024-12345`, having one of the
+/// policy's advisory prefixes.
+#[derive(Debug, Clone)]
+pub struct YankPolicy {
+    days: u64,
+    advisory_prefixes: Vec<String>,
+}
+
+impl YankPolicy {
+    /// Creates a new policy allowing yanks within the given number of days
+    /// of release.
+    ///
+    /// By default, no advisory prefixes are recognized.
+    pub fn new(days: u64) -> Self {
+        Self {
+            days,
+            advisory_prefixes: Vec::new(),
+        }
+    }
+
+    /// Recognizes advisories whose identifiers begin with the given prefix,
+    /// such as `CVE-` or `GHSA-`.
+    pub fn with_advisory_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.advisory_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Gets the number of days after release during which a version may be
+    /// yanked without an advisory.
+    pub fn days(&self) -> u64 {
+        self.days
+    }
+
+    /// Determines if the given yank reason is accompanied by an advisory.
+    pub fn is_advisory(&self, reason: Option<&str>) -> bool {
+        reason.is_some_and(|reason| {
+            self.advisory_prefixes
+                .iter()
+                .any(|prefix| reason.starts_with(prefix.as_str()))
+        })
+    }
+
+    /// Checks a yank of a version released at the given time against the
+    /// policy.
+    pub fn check_yank(
+        &self,
+        version: &Version,
+        released: SystemTime,
+        yanked: SystemTime,
+        reason: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        let window = Duration::from_secs(self.days.saturating_mul(SECS_PER_DAY));
+        let elapsed = yanked.duration_since(released).unwrap_or_default();
+        if elapsed <= window || self.is_advisory(reason) {
+            return Ok(());
+        }
+
+        Err(ValidationError::YankWindowElapsed {
+            version: version.clone(),
+            days: self.days,
+        })
+    }
+
+    /// Checks the yanks of the given record against the policy.
+    ///
+    /// Versions yanked by the record are expected to have been released in
+    /// the given log state; versions released by the record itself may
+    /// always be yanked.
+    pub fn check(
+        &self,
+        state: &LogState,
+        record: &model::PackageRecord,
+    ) -> Result<(), ValidationError> {
+        for entry in &record.entries {
+            match entry {
+                model::PackageEntry::Yank { version, reason } => {
+                    if let Some(release) = state.release(version) {
+                        self.check_yank(
+                            version,
+                            release.timestamp,
+                            record.timestamp,
+                            reason.as_deref(),
+                        )?;
+                    }
+                }
+                model::PackageEntry::YankRange { range, reason } => {
+                    for release in state.releases_to_yank(range) {
+                        self.check_yank(
+                            &release.version,
+                            release.timestamp,
+                            record.timestamp,
+                            reason.as_deref(),
+                        )?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Represents the current state of a release.
@@ -85,6 +441,14 @@ pub enum ReleaseState {
         /// The timestamp of the yank.
         #[serde(with = "crate::timestamp")]
         timestamp: SystemTime,
+        /// The reason given for the yank, if any.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        /// The content digest the release had before it was yanked.
+        ///
+        /// This is `None` for states persisted before yanked content was recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<AnyHash>,
     },
 }
 
@@ -98,9 +462,20 @@ pub struct Release {
     pub version: Version,
     /// The key id that released the package.
     pub by: signing::KeyID,
+    /// The key ids other than `by` that signed the record releasing the
+    /// package, such as the signer of a record published under a publish
+    /// token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_signers: Vec<signing::KeyID>,
     /// The timestamp of the release.
     #[serde(with = "crate::timestamp")]
     pub timestamp: SystemTime,
+    /// How the release content is encrypted, if it is encrypted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<ContentEncryption>,
+    /// The artifacts shipped with the release, if it has a manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<model::ReleaseManifest>,
     /// The current state of the release.
     pub state: ReleaseState,
 }
@@ -120,6 +495,154 @@ impl Release {
             ReleaseState::Yanked { .. } => None,
         }
     }
+
+    /// Gets every key id that signed the record releasing the package.
+    pub fn signers(&self) -> impl Iterator<Item = &signing::KeyID> {
+        std::iter::once(&self.by).chain(&self.other_signers)
+    }
+}
+
+/// Represents a summary of a released version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseInfo {
+    /// The version of the release.
+    pub version: Version,
+    /// The content digest of the release.
+    ///
+    /// This is the digest the version was released with, even if it has
+    /// since been yanked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<AnyHash>,
+    /// The id of the record that released the version.
+    pub record_id: RecordId,
+    /// The key id that released the version.
+    pub released_by: signing::KeyID,
+    /// The timestamp of the release.
+    #[serde(with = "crate::timestamp")]
+    pub timestamp: SystemTime,
+    /// Information about the yank of the version, if it has been yanked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yank: Option<YankInfo>,
+    /// The artifacts shipped with the version, if it has a manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<model::ReleaseManifest>,
+}
+
+impl ReleaseInfo {
+    /// Determines if the version has been yanked.
+    pub fn yanked(&self) -> bool {
+        self.yank.is_some()
+    }
+}
+
+impl From<&Release> for ReleaseInfo {
+    fn from(release: &Release) -> Self {
+        let (content, yank) = match &release.state {
+            ReleaseState::Released { content } => (Some(*content), None),
+            ReleaseState::Yanked {
+                by,
+                timestamp,
+                reason,
+                content,
+            } => (
+                *content,
+                Some(YankInfo {
+                    by: by.clone(),
+                    timestamp: *timestamp,
+                    reason: reason.clone(),
+                }),
+            ),
+        };
+
+        Self {
+            version: release.version.clone(),
+            content,
+            record_id: release.record_id.clone(),
+            released_by: release.by.clone(),
+            timestamp: release.timestamp,
+            yank,
+            manifest: release.manifest.clone(),
+        }
+    }
+}
+
+/// Represents information about the yank of a released version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YankInfo {
+    /// The key id that yanked the version.
+    pub by: signing::KeyID,
+    /// The timestamp of the yank.
+    #[serde(with = "crate::timestamp")]
+    pub timestamp: SystemTime,
+    /// The reason given for the yank, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Represents the current target of a channel tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// The version the tag points at.
+    pub version: Version,
+    /// The id of the record that last moved the tag.
+    pub record_id: RecordId,
+    /// The key id that last moved the tag.
+    pub by: signing::KeyID,
+    /// The timestamp of the last move of the tag.
+    #[serde(with = "crate::timestamp")]
+    pub timestamp: SystemTime,
+}
+
+/// Represents the kind of a [`PermissionChange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionChangeKind {
+    /// Permissions were granted to a key.
+    Grant,
+    /// Permissions were revoked from a key.
+    Revoke,
+}
+
+/// Represents a grant or revocation of permissions in a package log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionChange {
+    /// The id of the record containing the change.
+    pub record_id: RecordId,
+    /// The key id that made the change.
+    pub by: signing::KeyID,
+    /// The key id whose permissions changed.
+    pub key_id: signing::KeyID,
+    /// Whether the permissions were granted or revoked.
+    pub kind: PermissionChangeKind,
+    /// The permissions that were granted or revoked.
+    pub permissions: Vec<model::Permission>,
+    /// The timestamp of the change.
+    #[serde(with = "crate::timestamp")]
+    pub timestamp: SystemTime,
+}
+
+/// Represents the permissions of a package log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionsInfo {
+    /// The current permissions of each key holding at least one permission.
+    pub keys: IndexMap<signing::KeyID, IndexSet<model::Permission>>,
+    /// The permission grants and revocations, in package log order.
+    pub history: Vec<PermissionChange>,
+}
+
+/// The reviewers required to approve releases in a package log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequiredReviewers {
+    /// The number of reviewers that must approve a record.
+    pub threshold: u32,
+    /// The reviewer keys.
+    pub reviewers: IndexMap<signing::KeyID, signing::PublicKey>,
 }
 
 /// Information about the current head of the package log.
@@ -135,661 +658,3563 @@ pub struct Head {
     pub timestamp: SystemTime,
 }
 
-/// Calculated state for a package log.
+/// Statistics about a package log.
+#[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogStats {
+    /// The number of validated records in the log.
+    pub records: u64,
+    /// The number of releases in the log, including yanked releases.
+    pub releases: u64,
+    /// The number of yanked releases in the log.
+    pub yanks: u64,
+    /// The number of keys that currently hold at least one permission.
+    pub active_keys: u64,
+    /// The timestamp of the last validated record.
+    #[serde(
+        default,
+        with = "crate::timestamp::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_activity: Option<SystemTime>,
+}
+
+/// Counters maintained incrementally as records are validated.
 #[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default, rename_all = "camelCase")]
-pub struct LogState {
-    /// The hash algorithm used by the package log.
-    /// This is `None` until the first (i.e. init) record is validated.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    algorithm: Option<HashAlgorithm>,
-    /// The current head of the state.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    head: Option<Head>,
-    /// The permissions of each key.
-    #[serde(skip_serializing_if = "IndexMap::is_empty")]
-    permissions: IndexMap<signing::KeyID, IndexSet<model::Permission>>,
-    /// The releases in the package log.
-    #[serde(skip_serializing_if = "IndexMap::is_empty")]
-    releases: IndexMap<Version, Release>,
-    /// The keys known to the state.
-    #[serde(skip_serializing_if = "IndexMap::is_empty")]
-    keys: IndexMap<signing::KeyID, signing::PublicKey>,
+struct Counts {
+    records: u64,
+    releases: u64,
+    yanks: u64,
 }
 
-impl LogState {
-    /// Create a new package log state.
+impl Counts {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A handle to a key ID interned in a [`PackageInterner`].
+pub type KeyHandle = Handle<signing::KeyID>;
+
+/// A handle to a hash, such as a record id or content digest, interned in a
+/// [`PackageInterner`].
+pub type HashHandle = Handle<AnyHash>;
+
+/// A handle to a version interned in a [`PackageInterner`].
+pub type VersionHandle = Handle<Version>;
+
+/// The key IDs, hashes, and versions interned by the state of a package log.
+///
+/// The same values recur throughout the state of a log, such as the key ID
+/// of a key that maintains the package in its permissions, releases, and
+/// permission history. Each state interns them in its own interner, which
+/// holds only the values the log has referred to and is dropped with it.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PackageInterner {
+    key_ids: Interner<signing::KeyID>,
+    hashes: Interner<AnyHash>,
+    versions: Interner<Version>,
+}
+
+impl PackageInterner {
+    /// Creates a new, empty interner.
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Gets the current head of the state.
-    ///
-    /// Returns `None` if no records have been validated yet.
-    pub fn head(&self) -> &Option<Head> {
-        &self.head
+    /// Gets the number of key IDs, hashes, and versions interned.
+    pub fn len(&self) -> usize {
+        self.key_ids.len() + self.hashes.len() + self.versions.len()
     }
 
-    /// Validates an individual package record.
-    ///
-    /// It is expected that `validate` is called in order of the
-    /// records in the log.
-    ///
-    /// Note that on failure, the log state is consumed to prevent
-    /// invalid state from being used in future validations.
-    pub fn validate(
-        mut self,
-        record: &ProtoEnvelope<model::PackageRecord>,
-    ) -> Result<Self, ValidationError> {
-        self.validate_record(record)?;
-        Ok(self)
+    /// Determines if no values have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
+}
 
-    /// Gets the releases known to the state.
-    ///
-    /// The releases are returned in package log order.
-    ///
-    /// Yanked releases are included.
-    pub fn releases(&self) -> impl Iterator<Item = &Release> {
-        self.releases.values()
+impl fmt::Debug for PackageInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackageInterner")
+            .field("key_ids", &self.key_ids.len())
+            .field("hashes", &self.hashes.len())
+            .field("versions", &self.versions.len())
+            .finish()
     }
+}
 
-    /// Gets the release with the given version.
-    ///
-    /// Returns `None` if a release with the given version does not exist.
-    pub fn release(&self, version: &Version) -> Option<&Release> {
-        self.releases.get(version)
+impl Resolver<signing::KeyID> for PackageInterner {
+    fn resolve(&self, handle: KeyHandle) -> Option<signing::KeyID> {
+        self.key_ids.resolve(handle)
     }
+}
 
-    /// Finds the latest release matching the given version requirement.
-    ///
-    /// Releases that have been yanked are not considered.
-    pub fn find_latest_release(&self, req: &VersionReq) -> Option<&Release> {
-        self.releases
-            .values()
-            .filter(|release| !release.yanked() && req.matches(&release.version))
-            .max_by(|a, b| a.version.cmp(&b.version))
+impl Resolver<AnyHash> for PackageInterner {
+    fn resolve(&self, handle: HashHandle) -> Option<AnyHash> {
+        self.hashes.resolve(handle)
     }
+}
 
-    /// Gets the public key of the given key id.
-    ///
-    /// Returns `None` if the key id is not recognized.
-    pub fn public_key(&self, key_id: &signing::KeyID) -> Option<&signing::PublicKey> {
-        self.keys.get(key_id)
+impl Resolver<Version> for PackageInterner {
+    fn resolve(&self, handle: VersionHandle) -> Option<Version> {
+        self.versions.resolve(handle)
     }
+}
 
-    /// Gets the key permissions.
-    ///
-    /// Returns `None` if the key id is not recognized.
-    pub fn key_permissions(&self, key_id: &signing::KeyID) -> Option<&IndexSet<model::Permission>> {
-        self.permissions.get(key_id)
+// A state only holds handles from its own interner, so these always resolve;
+// were one not to, the value would be omitted rather than panic.
+impl PackageInterner {
+    fn key_id(&self, handle: KeyHandle) -> Option<&signing::KeyID> {
+        self.key_ids.value(handle)
     }
 
-    fn initialized(&self) -> bool {
-        // The package log is initialized if the hash algorithm is set
-        self.algorithm.is_some()
+    fn hash(&self, handle: HashHandle) -> Option<AnyHash> {
+        self.hashes.value(handle).copied()
     }
 
-    fn validate_record(
-        &mut self,
-        envelope: &ProtoEnvelope<model::PackageRecord>,
-    ) -> Result<(), ValidationError> {
-        let record = envelope.as_ref();
-        let record_id = RecordId::package_record::<Sha256>(envelope);
-
-        // Validate previous hash
-        self.validate_record_hash(record)?;
-
-        // Validate version
-        self.validate_record_version(record)?;
-
-        // Validate timestamp
-        self.validate_record_timestamp(record)?;
+    fn record_id(&self, handle: HashHandle) -> Option<RecordId> {
+        self.hash(handle).map(Into::into)
+    }
 
-        // Validate entries
-        self.validate_record_entries(
-            &record_id,
-            envelope.key_id(),
-            record.timestamp,
-            &record.entries,
-        )?;
+    fn version(&self, handle: VersionHandle) -> Option<&Version> {
+        self.versions.value(handle)
+    }
 
-        // At this point the digest algorithm must be set via an init entry
-        let _algorithm = self
-            .algorithm
-            .ok_or(ValidationError::InitialRecordDoesNotInit)?;
+    fn release(&self, version: VersionHandle, release: &InternedRelease) -> Option<Release> {
+        Some(Release {
+            record_id: self.record_id(release.record_id)?,
+            version: self.version(version)?.clone(),
+            by: self.key_id(release.by)?.clone(),
+            other_signers: release
+                .other_signers
+                .iter()
+                .map(|&handle| self.key_id(handle).cloned())
+                .collect::<Option<_>>()?,
+            timestamp: release.timestamp,
+            encryption: release.encryption.clone(),
+            manifest: release.manifest.clone(),
+            state: match &release.state {
+                InternedReleaseState::Released { content } => ReleaseState::Released {
+                    content: self.hash(*content)?,
+                },
+                InternedReleaseState::Yanked {
+                    by,
+                    timestamp,
+                    reason,
+                    content,
+                } => ReleaseState::Yanked {
+                    by: self.key_id(*by)?.clone(),
+                    timestamp: *timestamp,
+                    reason: reason.clone(),
+                    content: match content {
+                        Some(content) => Some(self.hash(*content)?),
+                        None => None,
+                    },
+                },
+            },
+        })
+    }
 
-        // Validate the envelope key id
-        let key = self.keys.get(envelope.key_id()).ok_or_else(|| {
-            ValidationError::KeyIDNotRecognized {
-                key_id: envelope.key_id().clone(),
-            }
-        })?;
+    fn intern_release(
+        &mut self,
+        release: Release,
+    ) -> Result<(VersionHandle, InternedRelease), InternerFull> {
+        let version = self.versions.intern(release.version)?;
+        let record_id = self.hashes.intern(release.record_id.into())?;
+        let by = self.key_ids.intern(release.by)?;
+        let other_signers = release
+            .other_signers
+            .into_iter()
+            .map(|key_id| self.key_ids.intern(key_id))
+            .collect::<Result<_, _>>()?;
+        let state = match release.state {
+            ReleaseState::Released { content } => InternedReleaseState::Released {
+                content: self.hashes.intern(content)?,
+            },
+            ReleaseState::Yanked {
+                by,
+                timestamp,
+                reason,
+                content,
+            } => InternedReleaseState::Yanked {
+                by: self.key_ids.intern(by)?,
+                timestamp,
+                reason,
+                content: content
+                    .map(|content| self.hashes.intern(content))
+                    .transpose()?,
+            },
+        };
 
-        // Validate the envelope signature
-        model::PackageRecord::verify(key, envelope.content_bytes(), envelope.signature())?;
+        Ok((
+            version,
+            InternedRelease {
+                record_id,
+                by,
+                other_signers,
+                timestamp: release.timestamp,
+                encryption: release.encryption,
+                manifest: release.manifest,
+                state,
+            },
+        ))
+    }
 
-        // Update the state head
-        self.head = Some(Head {
-            digest: record_id,
-            timestamp: record.timestamp,
-        });
+    fn tag(&self, tag: &InternedTag) -> Option<Tag> {
+        Some(Tag {
+            version: self.version(tag.version)?.clone(),
+            record_id: self.record_id(tag.record_id)?,
+            by: self.key_id(tag.by)?.clone(),
+            timestamp: tag.timestamp,
+        })
+    }
 
-        Ok(())
+    fn permission_change(&self, change: &InternedPermissionChange) -> Option<PermissionChange> {
+        Some(PermissionChange {
+            record_id: self.record_id(change.record_id)?,
+            by: self.key_id(change.by)?.clone(),
+            key_id: self.key_id(change.key_id)?.clone(),
+            kind: change.kind,
+            permissions: change.permissions.clone(),
+            timestamp: change.timestamp,
+        })
     }
+}
 
-    fn validate_record_hash(&self, record: &model::PackageRecord) -> Result<(), ValidationError> {
-        match (&self.head, &record.prev) {
-            (None, Some(_)) => Err(ValidationError::PreviousHashOnFirstRecord),
-            (Some(_), None) => Err(ValidationError::NoPreviousHashAfterInit),
-            (None, None) => Ok(()),
-            (Some(expected), Some(found)) => {
-                if found.algorithm() != expected.digest.algorithm() {
-                    return Err(ValidationError::IncorrectHashAlgorithm {
-                        found: found.algorithm(),
-                        expected: expected.digest.algorithm(),
-                    });
-                }
+/// Maps the handles of one state to the handles of the same values in
+/// another, so that states may be compared by handle.
+struct HandleMap<'a> {
+    from: &'a PackageInterner,
+    to: &'a PackageInterner,
+    // Equal interners assign the same handles to the same values
+    identity: bool,
+}
 
-                if found != &expected.digest {
-                    return Err(ValidationError::RecordHashDoesNotMatch);
-                }
+impl<'a> HandleMap<'a> {
+    fn new(from: &'a PackageInterner, to: &'a PackageInterner) -> Self {
+        Self {
+            from,
+            to,
+            identity: from == to,
+        }
+    }
 
-                Ok(())
-            }
+    fn key(&self, handle: KeyHandle) -> Option<KeyHandle> {
+        if self.identity {
+            return Some(handle);
         }
+        self.to.key_ids.get(self.from.key_ids.value(handle)?)
     }
 
-    fn validate_record_version(
-        &self,
-        record: &model::PackageRecord,
-    ) -> Result<(), ValidationError> {
-        if record.version == PACKAGE_RECORD_VERSION {
-            Ok(())
-        } else {
-            Err(ValidationError::ProtocolVersionNotAllowed {
-                version: record.version,
-            })
+    fn hash(&self, handle: HashHandle) -> Option<HashHandle> {
+        if self.identity {
+            return Some(handle);
         }
+        self.to.hashes.get(self.from.hashes.value(handle)?)
     }
 
-    fn validate_record_timestamp(
-        &self,
-        record: &model::PackageRecord,
-    ) -> Result<(), ValidationError> {
-        if let Some(head) = &self.head {
-            if record.timestamp < head.timestamp {
-                return Err(ValidationError::TimestampLowerThanPrevious);
-            }
+    fn version(&self, handle: VersionHandle) -> Option<VersionHandle> {
+        if self.identity {
+            return Some(handle);
         }
+        self.to.versions.get(self.from.versions.value(handle)?)
+    }
 
-        Ok(())
+    fn same_key(&self, a: KeyHandle, b: KeyHandle) -> bool {
+        self.key(a) == Some(b)
     }
 
-    fn validate_record_entries(
-        &mut self,
-        record_id: &RecordId,
-        signer_key_id: &signing::KeyID,
-        timestamp: SystemTime,
-        entries: &[model::PackageEntry],
-    ) -> Result<(), ValidationError> {
-        for entry in entries {
-            if let Some(permission) = entry.required_permission() {
-                self.check_key_permissions(signer_key_id, &[permission])?;
-            }
+    fn same_hash(&self, a: HashHandle, b: HashHandle) -> bool {
+        self.hash(a) == Some(b)
+    }
+}
 
-            // Process an init entry specially
-            if let model::PackageEntry::Init {
-                hash_algorithm,
-                key,
-            } = entry
-            {
-                self.validate_init_entry(signer_key_id, *hash_algorithm, key)?;
-                continue;
-            }
+/// The state of a package log.
+///
+/// The state is advanced a record at a time with [`PackageState::validate`],
+/// which validates the record and applies each of its entries with
+/// [`PackageState::apply_entry`].
+///
+/// Key IDs, hashes, and versions are interned in the state's own
+/// [`PackageInterner`]; the state is a [`Resolver`] of the [`KeyHandle`]s it
+/// hands out. States are compared by handle, and are equal when they refer
+/// to equal values whatever order the values were interned in.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(try_from = "PackageStateRepr", into = "PackageStateRepr")]
+pub struct PackageState {
+    /// The hash algorithm used by the package log.
+    /// This is `None` until the first (i.e. init) record is validated.
+    algorithm: Option<HashAlgorithm>,
+    /// The current head of the state.
+    head: Option<Head>,
+    /// The interner of the values referred to by the state.
+    interner: PackageInterner,
+    /// The permissions of each key.
+    permissions: IndexMap<KeyHandle, IndexSet<model::Permission>>,
+    /// The releases in the package log.
+    releases: IndexMap<VersionHandle, InternedRelease>,
+    /// The keys known to the state.
+    keys: IndexMap<KeyHandle, signing::PublicKey>,
+    /// The counters used to produce log statistics.
+    counts: Counts,
+    /// The permission grants and revocations in the package log.
+    permission_history: Vec<InternedPermissionChange>,
+    /// The reviewers required to approve releases, if any.
+    required_reviewers: Option<RequiredReviewers>,
+    /// The channel tags of the package log and their current targets.
+    tags: IndexMap<String, InternedTag>,
+}
 
-            // Must have seen an init entry by now
-            if !self.initialized() {
-                return Err(ValidationError::FirstEntryIsNotInit);
-            }
+/// A [`Release`] with interned values, keyed by its version.
+#[derive(Clone, Debug)]
+struct InternedRelease {
+    record_id: HashHandle,
+    by: KeyHandle,
+    other_signers: Vec<KeyHandle>,
+    timestamp: SystemTime,
+    encryption: Option<ContentEncryption>,
+    manifest: Option<model::ReleaseManifest>,
+    state: InternedReleaseState,
+}
 
-            match entry {
-                model::PackageEntry::Init { .. } => unreachable!(), // handled above
-                model::PackageEntry::GrantFlat { key, permissions } => {
-                    self.validate_grant_entry(signer_key_id, key, permissions)?
-                }
-                model::PackageEntry::RevokeFlat {
-                    key_id,
-                    permissions,
-                } => self.validate_revoke_entry(signer_key_id, key_id, permissions)?,
-                model::PackageEntry::Release { version, content } => self.validate_release_entry(
-                    record_id,
-                    signer_key_id,
-                    timestamp,
-                    version,
-                    content,
-                )?,
-                model::PackageEntry::Yank { version } => {
-                    self.validate_yank_entry(signer_key_id, timestamp, version)?
+impl InternedRelease {
+    fn yanked(&self) -> bool {
+        matches!(self.state, InternedReleaseState::Yanked { .. })
+    }
+
+    fn same(&self, other: &Self, map: &HandleMap) -> bool {
+        map.same_hash(self.record_id, other.record_id)
+            && map.same_key(self.by, other.by)
+            && self.other_signers.len() == other.other_signers.len()
+            && self
+                .other_signers
+                .iter()
+                .zip(&other.other_signers)
+                .all(|(&a, &b)| map.same_key(a, b))
+            && self.timestamp == other.timestamp
+            && self.encryption == other.encryption
+            && self.manifest == other.manifest
+            && match (&self.state, &other.state) {
+                (
+                    InternedReleaseState::Released { content: a },
+                    InternedReleaseState::Released { content: b },
+                ) => map.same_hash(*a, *b),
+                (
+                    InternedReleaseState::Yanked {
+                        by,
+                        timestamp,
+                        reason,
+                        content,
+                    },
+                    InternedReleaseState::Yanked {
+                        by: other_by,
+                        timestamp: other_timestamp,
+                        reason: other_reason,
+                        content: other_content,
+                    },
+                ) => {
+                    map.same_key(*by, *other_by)
+                        && timestamp == other_timestamp
+                        && reason == other_reason
+                        && match (content, other_content) {
+                            (Some(a), Some(b)) => map.same_hash(*a, *b),
+                            (a, b) => a.is_none() && b.is_none(),
+                        }
                 }
+                _ => false,
             }
-        }
-
-        Ok(())
     }
+}
 
-    fn validate_init_entry(
-        &mut self,
-        signer_key_id: &signing::KeyID,
-        algorithm: HashAlgorithm,
-        init_key: &signing::PublicKey,
-    ) -> Result<(), ValidationError> {
-        if self.initialized() {
-            return Err(ValidationError::InitialEntryAfterBeginning);
-        }
+/// A [`ReleaseState`] with interned values.
+#[derive(Clone, Debug)]
+enum InternedReleaseState {
+    Released {
+        content: HashHandle,
+    },
+    Yanked {
+        by: KeyHandle,
+        timestamp: SystemTime,
+        reason: Option<String>,
+        content: Option<HashHandle>,
+    },
+}
 
-        if signer_key_id != &init_key.fingerprint() {
-            return Err(ValidationError::KeyIDNotRecognized {
-                key_id: signer_key_id.clone(),
-            });
-        }
+/// A [`Tag`] with interned values.
+#[derive(Clone, Debug)]
+struct InternedTag {
+    version: VersionHandle,
+    record_id: HashHandle,
+    by: KeyHandle,
+    timestamp: SystemTime,
+}
 
-        assert!(self.permissions.is_empty());
-        assert!(self.releases.is_empty());
-        assert!(self.keys.is_empty());
+impl InternedTag {
+    fn same(&self, other: &Self, map: &HandleMap) -> bool {
+        map.version(self.version) == Some(other.version)
+            && map.same_hash(self.record_id, other.record_id)
+            && map.same_key(self.by, other.by)
+            && self.timestamp == other.timestamp
+    }
+}
 
-        self.algorithm = Some(algorithm);
-        self.permissions.insert(
-            signer_key_id.clone(),
-            IndexSet::from(model::Permission::all()),
-        );
-        self.keys.insert(init_key.fingerprint(), init_key.clone());
+/// A [`PermissionChange`] with interned values.
+#[derive(Clone, Debug)]
+struct InternedPermissionChange {
+    record_id: HashHandle,
+    by: KeyHandle,
+    key_id: KeyHandle,
+    kind: PermissionChangeKind,
+    permissions: Vec<model::Permission>,
+    timestamp: SystemTime,
+}
 
-        Ok(())
+impl InternedPermissionChange {
+    fn same(&self, other: &Self, map: &HandleMap) -> bool {
+        map.same_hash(self.record_id, other.record_id)
+            && map.same_key(self.by, other.by)
+            && map.same_key(self.key_id, other.key_id)
+            && self.kind == other.kind
+            && self.permissions == other.permissions
+            && self.timestamp == other.timestamp
     }
+}
 
-    fn validate_grant_entry(
-        &mut self,
-        signer_key_id: &signing::KeyID,
-        key: &signing::PublicKey,
-        permissions: &[model::Permission],
-    ) -> Result<(), ValidationError> {
-        // Check that the current key has the permission they're trying to grant
-        self.check_key_permissions(signer_key_id, permissions)?;
+/// The serialized representation of a [`PackageState`], in which values
+/// are not interned.
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PackageStateRepr {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    algorithm: Option<HashAlgorithm>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<Head>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    permissions: IndexMap<signing::KeyID, IndexSet<model::Permission>>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    releases: IndexMap<Version, Release>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    keys: IndexMap<signing::KeyID, signing::PublicKey>,
+    #[serde(skip_serializing_if = "Counts::is_empty")]
+    counts: Counts,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    permission_history: Vec<PermissionChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    required_reviewers: Option<RequiredReviewers>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    tags: IndexMap<String, Tag>,
+}
 
-        let grant_key_id = key.fingerprint();
-        self.keys.insert(grant_key_id.clone(), key.clone());
-        self.permissions
-            .entry(grant_key_id)
-            .or_default()
-            .extend(permissions);
+impl TryFrom<PackageStateRepr> for PackageState {
+    type Error = InternerFull;
 
-        Ok(())
-    }
+    fn try_from(repr: PackageStateRepr) -> Result<Self, Self::Error> {
+        let mut interner = PackageInterner::new();
+        let permissions = repr
+            .permissions
+            .into_iter()
+            .map(|(key_id, permissions)| Ok((interner.key_ids.intern(key_id)?, permissions)))
+            .collect::<Result<_, InternerFull>>()?;
+        let releases = repr
+            .releases
+            .into_values()
+            .map(|release| interner.intern_release(release))
+            .collect::<Result<_, _>>()?;
+        let keys = repr
+            .keys
+            .into_iter()
+            .map(|(key_id, key)| Ok((interner.key_ids.intern(key_id)?, key)))
+            .collect::<Result<_, InternerFull>>()?;
+        let permission_history = repr
+            .permission_history
+            .into_iter()
+            .map(|change| {
+                Ok(InternedPermissionChange {
+                    record_id: interner.hashes.intern(change.record_id.into())?,
+                    by: interner.key_ids.intern(change.by)?,
+                    key_id: interner.key_ids.intern(change.key_id)?,
+                    kind: change.kind,
+                    permissions: change.permissions,
+                    timestamp: change.timestamp,
+                })
+            })
+            .collect::<Result<_, InternerFull>>()?;
+        let tags = repr
+            .tags
+            .into_iter()
+            .map(|(name, tag)| {
+                let tag = InternedTag {
+                    version: interner.versions.intern(tag.version)?,
+                    record_id: interner.hashes.intern(tag.record_id.into())?,
+                    by: interner.key_ids.intern(tag.by)?,
+                    timestamp: tag.timestamp,
+                };
+                Ok((name, tag))
+            })
+            .collect::<Result<_, InternerFull>>()?;
 
-    fn validate_revoke_entry(
-        &mut self,
-        signer_key_id: &signing::KeyID,
-        key_id: &signing::KeyID,
-        permissions: &[model::Permission],
-    ) -> Result<(), ValidationError> {
-        // Check that the current key has the permission they're trying to revoke
-        self.check_key_permissions(signer_key_id, permissions)?;
+        Ok(Self {
+            algorithm: repr.algorithm,
+            head: repr.head,
+            interner,
+            permissions,
+            releases,
+            keys,
+            counts: repr.counts,
+            permission_history,
+            required_reviewers: repr.required_reviewers,
+            tags,
+        })
+    }
+}
 
-        for permission in permissions {
-            if !self
+impl From<&PackageState> for PackageStateRepr {
+    fn from(state: &PackageState) -> Self {
+        let interner = &state.interner;
+        Self {
+            algorithm: state.algorithm,
+            head: state.head.clone(),
+            permissions: state
                 .permissions
-                .get_mut(key_id)
-                .map(|set| set.swap_remove(permission))
-                .unwrap_or(false)
-            {
-                return Err(ValidationError::PermissionNotFoundToRevoke {
-                    permission: *permission,
-                    key_id: key_id.clone(),
-                });
-            }
+                .iter()
+                .filter_map(|(&handle, permissions)| {
+                    Some((interner.key_id(handle)?.clone(), permissions.clone()))
+                })
+                .collect(),
+            releases: state
+                .releases
+                .iter()
+                .filter_map(|(&version, release)| {
+                    let release = interner.release(version, release)?;
+                    Some((release.version.clone(), release))
+                })
+                .collect(),
+            keys: state
+                .keys
+                .iter()
+                .filter_map(|(&handle, key)| Some((interner.key_id(handle)?.clone(), key.clone())))
+                .collect(),
+            counts: state.counts.clone(),
+            permission_history: state
+                .permission_history
+                .iter()
+                .filter_map(|change| interner.permission_change(change))
+                .collect(),
+            required_reviewers: state.required_reviewers.clone(),
+            tags: state
+                .tags
+                .iter()
+                .filter_map(|(name, tag)| Some((name.clone(), interner.tag(tag)?)))
+                .collect(),
         }
-        Ok(())
     }
+}
 
-    fn validate_release_entry(
-        &mut self,
-        record_id: &RecordId,
-        signer_key_id: &signing::KeyID,
-        timestamp: SystemTime,
-        version: &Version,
-        content: &AnyHash,
-    ) -> Result<(), ValidationError> {
-        match self.releases.entry(version.clone()) {
-            Entry::Occupied(e) => {
-                return Err(ValidationError::ReleaseOfReleased {
-                    version: e.key().clone(),
-                })
-            }
-            Entry::Vacant(e) => {
-                let version = e.key().clone();
-                e.insert(Release {
-                    record_id: record_id.clone(),
-                    version,
-                    by: signer_key_id.clone(),
-                    timestamp,
-                    state: ReleaseState::Released {
-                        content: content.clone(),
-                    },
-                });
-            }
-        }
+impl From<PackageState> for PackageStateRepr {
+    fn from(state: PackageState) -> Self {
+        Self::from(&state)
+    }
+}
 
-        Ok(())
+impl PartialEq for PackageState {
+    fn eq(&self, other: &Self) -> bool {
+        let map = HandleMap::new(&self.interner, &other.interner);
+        self.algorithm == other.algorithm
+            && self.head == other.head
+            && self.permissions.len() == other.permissions.len()
+            && self.permissions.iter().all(|(&key, permissions)| {
+                map.key(key).and_then(|key| other.permissions.get(&key)) == Some(permissions)
+            })
+            && self.releases.len() == other.releases.len()
+            && self.releases.iter().all(|(&version, release)| {
+                map.version(version)
+                    .and_then(|version| other.releases.get(&version))
+                    .is_some_and(|other| release.same(other, &map))
+            })
+            && self.keys.len() == other.keys.len()
+            && self.keys.iter().all(|(&key, public)| {
+                map.key(key).and_then(|key| other.keys.get(&key)) == Some(public)
+            })
+            && self.counts == other.counts
+            && self.permission_history.len() == other.permission_history.len()
+            && self
+                .permission_history
+                .iter()
+                .zip(&other.permission_history)
+                .all(|(a, b)| a.same(b, &map))
+            && self.required_reviewers == other.required_reviewers
+            && self.tags.len() == other.tags.len()
+            && self.tags.iter().all(|(name, tag)| {
+                other
+                    .tags
+                    .get(name)
+                    .is_some_and(|other| tag.same(other, &map))
+            })
     }
+}
 
-    fn validate_yank_entry(
-        &mut self,
-        signer_key_id: &signing::KeyID,
-        timestamp: SystemTime,
-        version: &Version,
-    ) -> Result<(), ValidationError> {
-        match self.releases.get_mut(version) {
-            Some(e) => match e.state {
-                ReleaseState::Yanked { .. } => Err(ValidationError::YankOfYanked {
-                    version: version.clone(),
-                }),
-                ReleaseState::Released { .. } => {
-                    e.state = ReleaseState::Yanked {
-                        by: signer_key_id.clone(),
-                        timestamp,
-                    };
-                    Ok(())
+impl Eq for PackageState {}
+
+impl fmt::Debug for PackageState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        PackageStateRepr::from(self).fmt(f)
+    }
+}
+
+impl Resolver<signing::KeyID> for PackageState {
+    fn resolve(&self, handle: KeyHandle) -> Option<signing::KeyID> {
+        self.interner.resolve(handle)
+    }
+}
+
+/// The validated state of a package log.
+///
+/// This is an alias of [`PackageState`].
+pub type LogState = PackageState;
+
+impl PackageState {
+    /// Create a new package log state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replays the records of a package log, returning the state of the log
+    /// as of the record at the given index in the log.
+    ///
+    /// The records are expected in log order, starting with the first record
+    /// of the log; records after the index are not validated. This answers
+    /// questions about past states of a package, such as which keys held
+    /// permission to release when a version was published.
+    ///
+    /// Returns `Ok(None)` if the log has no record at the index.
+    pub fn at<'a>(
+        records: impl IntoIterator<Item = &'a ProtoEnvelope<model::PackageRecord>>,
+        record_index: usize,
+    ) -> Result<Option<Self>, ValidationError> {
+        let mut state = Self::new();
+        for (index, record) in records.into_iter().enumerate() {
+            state = state.validate(record)?;
+            if index == record_index {
+                return Ok(Some(state));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Replays the records of a package log, returning the state of the log
+    /// as of the record with the given identifier.
+    ///
+    /// See [`PackageState::at`]; the state includes the changes made by the
+    /// record itself, such as the release it published.
+    ///
+    /// Returns `Ok(None)` if the log has no record with the identifier.
+    pub fn at_record<'a>(
+        records: impl IntoIterator<Item = &'a ProtoEnvelope<model::PackageRecord>>,
+        record_id: &RecordId,
+    ) -> Result<Option<Self>, ValidationError> {
+        let mut state = Self::new();
+        for record in records {
+            state = state.validate(record)?;
+            if state.head.as_ref().map(|head| &head.digest) == Some(record_id) {
+                return Ok(Some(state));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Gets the current head of the state.
+    ///
+    /// Returns `None` if no records have been validated yet.
+    pub fn head(&self) -> &Option<Head> {
+        &self.head
+    }
+
+    /// Gets the reviewers required to approve releases, if any.
+    pub fn required_reviewers(&self) -> Option<&RequiredReviewers> {
+        self.required_reviewers.as_ref()
+    }
+
+    /// Validates an individual package record.
+    ///
+    /// It is expected that `validate` is called in order of the
+    /// records in the log.
+    ///
+    /// Note that on failure, the log state is consumed to prevent
+    /// invalid state from being used in future validations.
+    pub fn validate(
+        self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+    ) -> Result<Self, ValidationError> {
+        self.validate_with_rules(record, &ValidationRules::new())
+    }
+
+    /// Validates an individual package record under the given rules.
+    pub fn validate_with_rules(
+        mut self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+        rules: &ValidationRules,
+    ) -> Result<Self, ValidationError> {
+        self.validate_record(record, rules)?;
+        Ok(self)
+    }
+
+    /// Validates an individual package record, additionally enforcing the
+    /// given countersignature policy.
+    pub fn validate_countersigned(
+        self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+        policy: &CountersignaturePolicy,
+    ) -> Result<Self, ValidationError> {
+        policy.check(record)?;
+        self.validate(record)
+    }
+
+    /// Validates an individual package record, additionally enforcing the
+    /// given timestamp policy.
+    pub fn validate_timestamped(
+        self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+        policy: &TimestampPolicy,
+    ) -> Result<Self, ValidationError> {
+        policy.check(record)?;
+        self.validate(record)
+    }
+
+    /// Validates an individual package record, additionally enforcing the
+    /// given yank policy.
+    pub fn validate_with_yank_policy(
+        self,
+        record: &ProtoEnvelope<model::PackageRecord>,
+        policy: &YankPolicy,
+    ) -> Result<Self, ValidationError> {
+        policy.check(&self, record.as_ref())?;
+        self.validate(record)
+    }
+
+    /// Returns whether the state must be rebuilt by validating the package
+    /// log again from its first record.
+    ///
+    /// This is the case for states stored before log statistics or the
+    /// permission history were maintained, which load with no counts or no
+    /// permission changes despite having a head; every log grants permissions
+    /// to its initial key.
+    pub fn needs_replay(&self) -> bool {
+        self.head.is_some() && (self.counts.records == 0 || self.permission_history.is_empty())
+    }
+
+    /// Gets statistics about the package log.
+    ///
+    /// The statistics are maintained as records are validated and do
+    /// not require walking the log; see [`PackageState::needs_replay`].
+    pub fn stats(&self) -> LogStats {
+        LogStats {
+            records: self.counts.records,
+            releases: self.counts.releases,
+            yanks: self.counts.yanks,
+            active_keys: self
+                .permissions
+                .values()
+                .filter(|permissions| !permissions.is_empty())
+                .count() as u64,
+            last_activity: self.head.as_ref().map(|head| head.timestamp),
+        }
+    }
+
+    /// Gets a summary of every release known to the state.
+    ///
+    /// The releases are returned in package log order.
+    ///
+    /// Yanked releases are included.
+    pub fn releases(&self) -> impl Iterator<Item = ReleaseInfo> + '_ {
+        self.stored_releases()
+            .map(|release| ReleaseInfo::from(&release))
+    }
+
+    /// Gets the releases known to the state as stored.
+    ///
+    /// The releases are returned in package log order.
+    ///
+    /// Yanked releases are included.
+    pub fn stored_releases(&self) -> impl Iterator<Item = Release> + '_ {
+        self.releases
+            .iter()
+            .filter_map(|(&version, release)| self.interner.release(version, release))
+    }
+
+    /// Gets the release with the given version.
+    ///
+    /// Returns `None` if a release with the given version does not exist.
+    pub fn release(&self, version: &Version) -> Option<Release> {
+        let handle = self.interner.versions.get(version)?;
+        let release = self.releases.get(&handle)?;
+        self.interner.release(handle, release)
+    }
+
+    /// Gets the releases that a yank of the given range would yank: those
+    /// matching the range that have not been yanked.
+    ///
+    /// The releases are returned in package log order.
+    pub fn releases_to_yank(&self, range: &VersionReq) -> Vec<Release> {
+        self.yankable_releases(range)
+            .filter_map(|(version, release)| self.interner.release(version, release))
+            .collect()
+    }
+
+    /// Gets the channel tags of the package log and their current targets.
+    ///
+    /// The tags are returned in the order they were first assigned.
+    pub fn tags(&self) -> impl Iterator<Item = (&str, Tag)> {
+        self.tags
+            .iter()
+            .filter_map(|(name, tag)| Some((name.as_str(), self.interner.tag(tag)?)))
+    }
+
+    /// Gets the current target of the given channel tag.
+    ///
+    /// Returns `None` if the tag has not been assigned.
+    pub fn tag(&self, name: &str) -> Option<Tag> {
+        self.interner.tag(self.tags.get(name)?)
+    }
+
+    /// Resolves a channel tag to the release it points at.
+    ///
+    /// Returns `None` if the tag has not been assigned or the release it
+    /// points at has since been yanked.
+    pub fn resolve_tag(&self, name: &str) -> Option<Release> {
+        let version = self.tags.get(name)?.version;
+        let release = self
+            .releases
+            .get(&version)
+            .filter(|release| !release.yanked())?;
+        self.interner.release(version, release)
+    }
+
+    /// Finds the latest release matching the given version requirement.
+    ///
+    /// Releases that have been yanked are not considered.
+    pub fn find_latest_release(&self, req: &VersionReq) -> Option<Release> {
+        let (version, release) = self
+            .yankable_releases(req)
+            .max_by(|(a, _), (b, _)| self.interner.version(*a).cmp(&self.interner.version(*b)))?;
+        self.interner.release(version, release)
+    }
+
+    // The releases matching a version requirement that have not been yanked
+    fn yankable_releases<'a>(
+        &'a self,
+        req: &'a VersionReq,
+    ) -> impl Iterator<Item = (VersionHandle, &'a InternedRelease)> + 'a {
+        self.releases
+            .iter()
+            .filter(move |(&version, release)| {
+                !release.yanked()
+                    && self
+                        .interner
+                        .version(version)
+                        .is_some_and(|version| req.matches(version))
+            })
+            .map(|(&version, release)| (version, release))
+    }
+
+    /// Gets the interner of the values referred to by the state.
+    pub fn interner(&self) -> &PackageInterner {
+        &self.interner
+    }
+
+    /// Gets the public key of the given key id.
+    ///
+    /// Returns `None` if the key id is not recognized.
+    pub fn public_key(&self, key_id: &signing::KeyID) -> Option<&signing::PublicKey> {
+        self.keys.get(&self.key_handle(key_id)?)
+    }
+
+    /// Gets the key permissions.
+    ///
+    /// Returns `None` if the key id is not recognized.
+    pub fn key_permissions(&self, key_id: &signing::KeyID) -> Option<&IndexSet<model::Permission>> {
+        self.permissions.get(&self.key_handle(key_id)?)
+    }
+
+    /// Gets the handle of the given key id.
+    ///
+    /// Returns `None` if the key id is not interned.
+    pub fn key_handle(&self, key_id: &signing::KeyID) -> Option<KeyHandle> {
+        self.interner.key_ids.get(key_id)
+    }
+
+    /// Gets the current permissions of each key and the history of
+    /// permission grants and revocations that produced them.
+    ///
+    /// Keys that no longer hold any permission are omitted from the current
+    /// permissions.
+    pub fn permissions(&self) -> PermissionsInfo {
+        PermissionsInfo {
+            keys: self
+                .permissions
+                .iter()
+                .filter(|(_, permissions)| !permissions.is_empty())
+                .filter_map(|(&handle, permissions)| {
+                    Some((self.interner.key_id(handle)?.clone(), permissions.clone()))
+                })
+                .collect(),
+            history: self
+                .permission_history
+                .iter()
+                .filter_map(|change| self.interner.permission_change(change))
+                .collect(),
+        }
+    }
+
+    /// Validates the publish token of a record against the state.
+    ///
+    /// The token must be scoped to the given package log alone and authorize
+    /// the key that signed the record, must not have expired as of the given
+    /// registry time, and must be issued by a key with the release
+    /// permission; the record may only contain release entries.
+    ///
+    /// A package log does not know its own identifier, and the timestamp of a
+    /// record is chosen by its publisher, so registries call this with the
+    /// identifier of the log and their own clock when a record is submitted
+    /// and again when it is sequenced.
+    ///
+    /// Returns the key that must have signed the record, or `None` if the
+    /// record has no publish token.
+    pub fn validate_publish_token<'a>(
+        &self,
+        log_id: &LogId,
+        envelope: &'a ProtoEnvelope<model::PackageRecord>,
+        now: SystemTime,
+    ) -> Result<Option<&'a signing::PublicKey>, ValidationError> {
+        if let Some(token) = &envelope.as_ref().publish_token {
+            if !token.authorizes(log_id) {
+                return Err(ValidationError::PublishTokenScopeNotAllowed {
+                    log_id: log_id.clone(),
+                });
+            }
+        }
+
+        self.check_publish_token(envelope, now)
+    }
+
+    /// Checks the publish token of a record against the state, except for the
+    /// packages it is scoped to.
+    fn check_publish_token<'a>(
+        &self,
+        envelope: &'a ProtoEnvelope<model::PackageRecord>,
+        now: SystemTime,
+    ) -> Result<Option<&'a signing::PublicKey>, ValidationError> {
+        let record = envelope.as_ref();
+        let Some(token) = &record.publish_token else {
+            return Ok(None);
+        };
+
+        if &token.key.fingerprint() != envelope.key_id() {
+            return Err(ValidationError::PublishTokenKeyMismatch {
+                key_id: envelope.key_id().clone(),
+            });
+        }
+
+        if token.expired(now) {
+            return Err(ValidationError::PublishTokenExpired);
+        }
+
+        if let Some(index) = record
+            .entries
+            .iter()
+            .position(|entry| !matches!(entry, model::PackageEntry::Release { .. }))
+        {
+            return Err(ValidationError::PublishTokenEntryNotAllowed { index });
+        }
+
+        let issuer =
+            self.public_key(&token.issuer)
+                .ok_or_else(|| ValidationError::KeyIDNotRecognized {
+                    key_id: token.issuer.clone(),
+                })?;
+        self.check_key_permissions(&token.issuer, &[model::Permission::Release])?;
+        token
+            .verify(issuer)
+            .map_err(|_| ValidationError::InvalidPublishToken)?;
+
+        Ok(Some(&token.key))
+    }
+
+    fn initialized(&self) -> bool {
+        // The package log is initialized if the hash algorithm is set
+        self.algorithm.is_some()
+    }
+
+    fn validate_record(
+        &mut self,
+        envelope: &ProtoEnvelope<model::PackageRecord>,
+        rules: &ValidationRules,
+    ) -> Result<(), ValidationError> {
+        let record = envelope.as_ref();
+        let record_id = RecordId::package_record::<Sha256>(envelope);
+
+        // Validate releases before the previous hash, as a racing release of
+        // the same version will also have been built against a stale head
+        self.validate_record_releases(&record_id, record)?;
+
+        // Validate previous hash
+        self.validate_record_hash(record)?;
+
+        // Validate version
+        self.validate_record_version(record)?;
+
+        // Validate timestamp
+        self.validate_record_timestamp(record)?;
+
+        // Reviewers required before the record apply to the whole record
+        self.validate_record_reviews(envelope)?;
+
+        // A record published under a publish token is authorized by its issuer.
+        // Replaying a log has no registry clock, so the token need only be
+        // unexpired as of the record's timestamp; as the publisher chooses the
+        // timestamp, registries also check the token by their own clock with
+        // `validate_publish_token` when the record is submitted and sequenced
+        let token_key = self.check_publish_token(envelope, record.timestamp)?;
+        let authorizer = match &record.publish_token {
+            Some(token) => &token.issuer,
+            None => envelope.key_id(),
+        };
+
+        // Validate entries
+        self.validate_record_entries(&record_id, authorizer, record, rules)?;
+        self.record_release_signers(envelope)?;
+
+        // At this point the digest algorithm must be set via an init entry
+        let _algorithm = self
+            .algorithm
+            .ok_or(ValidationError::InitialRecordDoesNotInit)?;
+
+        // Validate the envelope key id
+        let key = match token_key {
+            Some(key) => key,
+            None => self.public_key(envelope.key_id()).ok_or_else(|| {
+                ValidationError::KeyIDNotRecognized {
+                    key_id: envelope.key_id().clone(),
                 }
-            },
-            None => Err(ValidationError::YankOfUnreleased {
-                version: version.clone(),
-            }),
+            })?,
+        };
+
+        // Validate the envelope signature
+        model::PackageRecord::verify(key, envelope.content_bytes(), envelope.signature())?;
+
+        // A timestamp must be over the record even if its authority is not
+        // trusted by the validator
+        if let Some(timestamp) = envelope.timestamp() {
+            timestamp
+                .verify_imprint(envelope.content_bytes())
+                .map_err(ValidationError::InvalidTimestamp)?;
         }
+
+        // Update the state head
+        self.head = Some(Head {
+            digest: record_id,
+            timestamp: record.timestamp,
+        });
+        self.counts.records += 1;
+
+        Ok(())
     }
 
-    fn check_key_permissions(
+    fn validate_record_releases(
         &self,
-        key_id: &signing::KeyID,
-        permissions: &[model::Permission],
+        record_id: &RecordId,
+        record: &model::PackageRecord,
     ) -> Result<(), ValidationError> {
-        for permission in permissions {
-            if !self
-                .permissions
-                .get(key_id)
-                .map(|p| p.contains(permission))
-                .unwrap_or(false)
-            {
-                return Err(ValidationError::UnauthorizedAction {
-                    key_id: key_id.clone(),
-                    needed_permission: *permission,
-                });
+        for entry in &record.entries {
+            if let model::PackageEntry::Release { version, .. } = entry {
+                let existing = self
+                    .interner
+                    .versions
+                    .get(version)
+                    .and_then(|handle| self.releases.get(&handle));
+                if let Some(release) = existing {
+                    return Err(ValidationError::DuplicateRelease {
+                        version: version.clone(),
+                        existing: self
+                            .interner
+                            .record_id(release.record_id)
+                            .ok_or(ValidationError::Uninterned)?,
+                        record_id: record_id.clone(),
+                    });
+                }
             }
         }
+
         Ok(())
     }
-}
 
-impl crate::Validator for LogState {
-    type Record = model::PackageRecord;
-    type Error = ValidationError;
+    // Remembers every key that signed the record on the releases it made, so
+    // that releases signed by a key later declared compromised can be audited
+    fn record_release_signers(
+        &mut self,
+        envelope: &ProtoEnvelope<model::PackageRecord>,
+    ) -> Result<(), ValidationError> {
+        let record = envelope.as_ref();
+        let signers = std::iter::once(envelope.key_id())
+            .chain(record.entry_signatures.iter().map(|s| &s.key_id))
+            .chain(record.publish_token.iter().map(|t| &t.issuer))
+            .map(|key_id| self.interner.key_ids.intern(key_id.clone()))
+            .collect::<Result<IndexSet<_>, _>>()?;
+        for entry in &record.entries {
+            if let model::PackageEntry::Release { version, .. } = entry {
+                let Some(version) = self.interner.versions.get(version) else {
+                    continue;
+                };
+                if let Some(release) = self.releases.get_mut(&version) {
+                    release.other_signers = signers
+                        .iter()
+                        .filter(|&&key_id| key_id != release.by)
+                        .copied()
+                        .collect();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_record_hash(&self, record: &model::PackageRecord) -> Result<(), ValidationError> {
+        match (&self.head, &record.prev) {
+            (None, Some(_)) => Err(ValidationError::PreviousHashOnFirstRecord),
+            (Some(_), None) => Err(ValidationError::NoPreviousHashAfterInit),
+            (None, None) => Ok(()),
+            (Some(expected), Some(found)) => {
+                if found.algorithm() != expected.digest.algorithm() {
+                    return Err(ValidationError::IncorrectHashAlgorithm {
+                        found: found.algorithm(),
+                        expected: expected.digest.algorithm(),
+                    });
+                }
+
+                if found != &expected.digest {
+                    return Err(ValidationError::RecordHashDoesNotMatch);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_record_version(
+        &self,
+        record: &model::PackageRecord,
+    ) -> Result<(), ValidationError> {
+        if record.version == PACKAGE_RECORD_VERSION {
+            Ok(())
+        } else {
+            Err(ValidationError::ProtocolVersionNotAllowed {
+                version: record.version,
+            })
+        }
+    }
+
+    fn validate_record_reviews(
+        &self,
+        envelope: &ProtoEnvelope<model::PackageRecord>,
+    ) -> Result<(), ValidationError> {
+        let Some(required) = &self.required_reviewers else {
+            return Ok(());
+        };
+
+        let reviewed = envelope.as_ref().entries.iter().any(|entry| {
+            matches!(
+                entry,
+                model::PackageEntry::Release { .. } | model::PackageEntry::RequireReviewers { .. }
+            )
+        });
+        if !reviewed {
+            return Ok(());
+        }
+
+        // The envelope signature is verified after the entries are validated
+        let mut approvals = IndexSet::new();
+        if required.reviewers.contains_key(envelope.key_id()) {
+            approvals.insert(envelope.key_id());
+        }
+
+        // Reviewer cosignatures are verified together as a batch
+        let payload = Cosignature::payload(envelope.content_bytes());
+        let cosignatures = envelope
+            .cosignatures()
+            .iter()
+            .filter_map(|c| Some((c, required.reviewers.get(&c.key_id)?)))
+            .collect::<Vec<_>>();
+        signing::batch_verify(
+            cosignatures
+                .iter()
+                .map(|(c, key)| signing::BatchItem::new(key, &payload, &c.signature)),
+        )
+        .map_err(|e| ValidationError::InvalidCosignature {
+            key_id: cosignatures[e.failed()[0]].0.key_id.clone(),
+        })?;
+        approvals.extend(cosignatures.iter().map(|(c, _)| &c.key_id));
+
+        let approvals = approvals.len() as u32;
+        if approvals < required.threshold {
+            return Err(ValidationError::ReviewersRequired {
+                threshold: required.threshold,
+                approvals,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_record_timestamp(
+        &self,
+        record: &model::PackageRecord,
+    ) -> Result<(), ValidationError> {
+        if let Some(head) = &self.head {
+            if record.timestamp < head.timestamp {
+                return Err(ValidationError::TimestampLowerThanPrevious);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_record_entries(
+        &mut self,
+        record_id: &RecordId,
+        envelope_key_id: &signing::KeyID,
+        record: &model::PackageRecord,
+        rules: &ValidationRules,
+    ) -> Result<(), ValidationError> {
+        let mut signed = IndexSet::new();
+        for signature in &record.entry_signatures {
+            if signature.entry >= record.entries.len() {
+                return Err(ValidationError::EntrySignatureOutOfRange {
+                    index: signature.entry,
+                });
+            }
+
+            if !signed.insert(signature.entry) {
+                return Err(ValidationError::DuplicateEntrySignature {
+                    index: signature.entry,
+                });
+            }
+        }
+
+        for (index, entry) in record.entries.iter().enumerate() {
+            // Individually signed entries are authorized by their own signer,
+            // which may have been granted permission earlier in this record
+            let signer_key_id = record.entry_signer(index, envelope_key_id);
+            if signed.contains(&index) {
+                self.validate_entry_signature(record, index, entry)?;
+            }
+
+            self.apply_entry(record_id, signer_key_id, record.timestamp, entry)?;
+
+            // A proof of possession that is present is always verified
+            match entry {
+                model::PackageEntry::GrantFlat { proof: Some(_), .. } => {
+                    record
+                        .verify_key_possession(index)
+                        .map_err(|_| ValidationError::InvalidPossessionProof { index })?;
+                }
+                model::PackageEntry::GrantFlat { proof: None, .. } if rules.key_possession => {
+                    return Err(ValidationError::PossessionProofRequired { index });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies an entry of the given record to the state on behalf of the
+    /// given signer.
+    ///
+    /// The signer must hold the permission the entry requires.
+    ///
+    /// Only the entry is validated: the record containing it (its previous
+    /// hash, timestamp, and signatures) is not, and the head of the state is
+    /// not advanced. Use [`PackageState::validate`] to validate whole records.
+    ///
+    /// Note that on failure, the state may have been partially updated.
+    pub fn apply_entry(
+        &mut self,
+        record_id: &RecordId,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        entry: &model::PackageEntry,
+    ) -> Result<(), ValidationError> {
+        if let Some(permission) = entry.required_permission() {
+            self.check_key_permissions(signer_key_id, &[permission])?;
+        }
+
+        // Process an init entry specially
+        if let model::PackageEntry::Init {
+            hash_algorithm,
+            key,
+        } = entry
+        {
+            self.validate_init_entry(signer_key_id, *hash_algorithm, key)?;
+            self.record_permission_change(
+                record_id,
+                signer_key_id,
+                timestamp,
+                key.fingerprint(),
+                PermissionChangeKind::Grant,
+                model::Permission::all().into(),
+            )?;
+            return Ok(());
+        }
+
+        // Must have seen an init entry by now
+        if !self.initialized() {
+            return Err(ValidationError::FirstEntryIsNotInit);
+        }
+
+        match entry {
+            model::PackageEntry::Init { .. } => unreachable!(), // handled above
+            model::PackageEntry::GrantFlat {
+                key, permissions, ..
+            } => {
+                self.validate_grant_entry(signer_key_id, key, permissions)?;
+                self.record_permission_change(
+                    record_id,
+                    signer_key_id,
+                    timestamp,
+                    key.fingerprint(),
+                    PermissionChangeKind::Grant,
+                    permissions.clone(),
+                )?;
+            }
+            model::PackageEntry::RevokeFlat {
+                key_id,
+                permissions,
+            } => {
+                self.validate_revoke_entry(signer_key_id, key_id, permissions)?;
+                self.record_permission_change(
+                    record_id,
+                    signer_key_id,
+                    timestamp,
+                    key_id.clone(),
+                    PermissionChangeKind::Revoke,
+                    permissions.clone(),
+                )?;
+            }
+            model::PackageEntry::Release {
+                version,
+                content,
+                encryption,
+                manifest,
+            } => self.validate_release_entry(
+                record_id,
+                signer_key_id,
+                timestamp,
+                version,
+                content,
+                encryption,
+                manifest,
+            )?,
+            model::PackageEntry::Yank { version, reason } => {
+                self.validate_yank_entry(signer_key_id, timestamp, version, reason)?
+            }
+            model::PackageEntry::YankRange { range, reason } => {
+                self.validate_yank_range_entry(signer_key_id, timestamp, range, reason)?
+            }
+            model::PackageEntry::Tag { tag, version } => {
+                self.validate_tag_entry(record_id, signer_key_id, timestamp, tag, version)?
+            }
+            model::PackageEntry::RequireReviewers {
+                threshold,
+                reviewers,
+            } => {
+                // The record was reviewed under the current requirement, but
+                // changing it also takes every package permission
+                self.check_key_permissions(signer_key_id, &model::Permission::all())?;
+                self.required_reviewers = (*threshold > 0).then(|| RequiredReviewers {
+                    threshold: *threshold,
+                    reviewers: reviewers
+                        .iter()
+                        .map(|key| (key.fingerprint(), key.clone()))
+                        .collect(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record_permission_change(
+        &mut self,
+        record_id: &RecordId,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        key_id: signing::KeyID,
+        kind: PermissionChangeKind,
+        permissions: Vec<model::Permission>,
+    ) -> Result<(), ValidationError> {
+        let record_id = self.interner.hashes.intern(record_id.clone().into())?;
+        let by = self.interner.key_ids.intern(signer_key_id.clone())?;
+        let key_id = self.interner.key_ids.intern(key_id)?;
+        self.permission_history.push(InternedPermissionChange {
+            record_id,
+            by,
+            key_id,
+            kind,
+            permissions,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    fn validate_entry_signature(
+        &self,
+        record: &model::PackageRecord,
+        index: usize,
+        entry: &model::PackageEntry,
+    ) -> Result<(), ValidationError> {
+        let key_id = &record.entry_signature(index).unwrap().key_id;
+        let key = match entry {
+            // The key of an init entry is not yet known to the log
+            model::PackageEntry::Init { key, .. } if &key.fingerprint() == key_id => key,
+            _ => self
+                .public_key(key_id)
+                .ok_or_else(|| ValidationError::KeyIDNotRecognized {
+                    key_id: key_id.clone(),
+                })?,
+        };
+
+        record
+            .verify_entry_signature(index, key)
+            .map_err(|_| ValidationError::InvalidEntrySignature { index })
+    }
+
+    fn validate_init_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        algorithm: HashAlgorithm,
+        init_key: &signing::PublicKey,
+    ) -> Result<(), ValidationError> {
+        if self.initialized() {
+            return Err(ValidationError::InitialEntryAfterBeginning);
+        }
+
+        if signer_key_id != &init_key.fingerprint() {
+            return Err(ValidationError::KeyIDNotRecognized {
+                key_id: signer_key_id.clone(),
+            });
+        }
+
+        assert!(self.permissions.is_empty());
+        assert!(self.releases.is_empty());
+        assert!(self.keys.is_empty());
+
+        self.algorithm = Some(algorithm);
+        let handle = self.interner.key_ids.intern(signer_key_id.clone())?;
+        self.permissions
+            .insert(handle, IndexSet::from(model::Permission::all()));
+        self.keys.insert(handle, init_key.clone());
+
+        Ok(())
+    }
+
+    fn validate_grant_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        key: &signing::PublicKey,
+        permissions: &[model::Permission],
+    ) -> Result<(), ValidationError> {
+        // Check that the current key has the permission they're trying to grant
+        self.check_key_permissions(signer_key_id, permissions)?;
+
+        let handle = self.interner.key_ids.intern(key.fingerprint())?;
+        self.keys.insert(handle, key.clone());
+        self.permissions
+            .entry(handle)
+            .or_default()
+            .extend(permissions);
+
+        Ok(())
+    }
+
+    fn validate_revoke_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        key_id: &signing::KeyID,
+        permissions: &[model::Permission],
+    ) -> Result<(), ValidationError> {
+        // Check that the current key has the permission they're trying to revoke
+        self.check_key_permissions(signer_key_id, permissions)?;
+
+        let handle = self.key_handle(key_id);
+        for permission in permissions {
+            if !handle
+                .and_then(|handle| self.permissions.get_mut(&handle))
+                .map(|set| set.swap_remove(permission))
+                .unwrap_or(false)
+            {
+                return Err(ValidationError::PermissionNotFoundToRevoke {
+                    permission: *permission,
+                    key_id: key_id.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn validate_release_entry(
+        &mut self,
+        record_id: &RecordId,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        version: &Version,
+        content: &AnyHash,
+        encryption: &Option<ContentEncryption>,
+        manifest: &Option<model::ReleaseManifest>,
+    ) -> Result<(), ValidationError> {
+        if let Some(manifest) = manifest {
+            model::check_manifest(manifest).map_err(|e| ValidationError::InvalidManifest {
+                version: version.clone(),
+                message: e.to_string(),
+            })?;
+        }
+
+        let interner = &mut self.interner;
+        let handle = interner.versions.intern(version.clone())?;
+        match self.releases.entry(handle) {
+            Entry::Occupied(e) => {
+                return Err(ValidationError::DuplicateRelease {
+                    version: version.clone(),
+                    existing: interner
+                        .record_id(e.get().record_id)
+                        .ok_or(ValidationError::Uninterned)?,
+                    record_id: record_id.clone(),
+                })
+            }
+            Entry::Vacant(e) => {
+                e.insert(InternedRelease {
+                    record_id: interner.hashes.intern(record_id.clone().into())?,
+                    by: interner.key_ids.intern(signer_key_id.clone())?,
+                    other_signers: Vec::new(),
+                    timestamp,
+                    encryption: encryption.clone(),
+                    manifest: manifest.clone(),
+                    state: InternedReleaseState::Released {
+                        content: interner.hashes.intern(*content)?,
+                    },
+                });
+                self.counts.releases += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_yank_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        version: &Version,
+        reason: &Option<String>,
+    ) -> Result<(), ValidationError> {
+        let interner = &mut self.interner;
+        let release = interner
+            .versions
+            .get(version)
+            .and_then(|handle| self.releases.get_mut(&handle));
+        match release {
+            Some(e) => match &e.state {
+                InternedReleaseState::Yanked { .. } => Err(ValidationError::YankOfYanked {
+                    version: version.clone(),
+                }),
+                InternedReleaseState::Released { content } => {
+                    e.state = InternedReleaseState::Yanked {
+                        by: interner.key_ids.intern(signer_key_id.clone())?,
+                        timestamp,
+                        reason: reason.clone(),
+                        content: Some(*content),
+                    };
+                    self.counts.yanks += 1;
+                    Ok(())
+                }
+            },
+            None => Err(ValidationError::YankOfUnreleased {
+                version: version.clone(),
+            }),
+        }
+    }
+
+    fn validate_yank_range_entry(
+        &mut self,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        range: &VersionReq,
+        reason: &Option<String>,
+    ) -> Result<(), ValidationError> {
+        let versions = self
+            .releases_to_yank(range)
+            .into_iter()
+            .map(|release| release.version)
+            .collect::<Vec<_>>();
+        if versions.is_empty() {
+            return Err(ValidationError::YankRangeMatchesNothing {
+                range: range.clone(),
+            });
+        }
+
+        for version in versions {
+            self.validate_yank_entry(signer_key_id, timestamp, &version, reason)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_tag_entry(
+        &mut self,
+        record_id: &RecordId,
+        signer_key_id: &signing::KeyID,
+        timestamp: SystemTime,
+        tag: &str,
+        version: &Version,
+    ) -> Result<(), ValidationError> {
+        model::check_tag(tag).map_err(|_| ValidationError::InvalidTag {
+            tag: tag.to_string(),
+        })?;
+
+        let interner = &mut self.interner;
+        let release = interner
+            .versions
+            .get(version)
+            .and_then(|handle| Some((handle, self.releases.get(&handle)?)));
+        match release {
+            Some((_, release)) if release.yanked() => Err(ValidationError::TagOfYanked {
+                tag: tag.to_string(),
+                version: version.clone(),
+            }),
+            Some((version, _)) => {
+                self.tags.insert(
+                    tag.to_string(),
+                    InternedTag {
+                        version,
+                        record_id: interner.hashes.intern(record_id.clone().into())?,
+                        by: interner.key_ids.intern(signer_key_id.clone())?,
+                        timestamp,
+                    },
+                );
+                Ok(())
+            }
+            None => Err(ValidationError::TagOfUnreleased {
+                tag: tag.to_string(),
+                version: version.clone(),
+            }),
+        }
+    }
+
+    fn check_key_permissions(
+        &self,
+        key_id: &signing::KeyID,
+        permissions: &[model::Permission],
+    ) -> Result<(), ValidationError> {
+        for permission in permissions {
+            if !self
+                .key_permissions(key_id)
+                .map(|p| p.contains(permission))
+                .unwrap_or(false)
+            {
+                return Err(ValidationError::UnauthorizedAction {
+                    key_id: key_id.clone(),
+                    needed_permission: *permission,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl crate::Validator for LogState {
+    type Record = model::PackageRecord;
+    type Error = ValidationError;
+
+    fn validate(self, record: &ProtoEnvelope<Self::Record>) -> Result<Self, Self::Error> {
+        self.validate(record)
+    }
+
+    fn tolerate(&mut self, record: &ProtoEnvelope<Self::Record>) {
+        self.head = Some(Head {
+            digest: RecordId::package_record::<Sha256>(record),
+            timestamp: record.as_ref().timestamp,
+        });
+    }
+
+    fn head_record_id(&self) -> Option<&RecordId> {
+        self.head.as_ref().map(|head| &head.digest)
+    }
+}
+
+// The canonical encoding of the state hashed by state exports.
+impl prefix::VisitPrefixEncode for PackageState {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        // Values are hashed as resolved, independently of their handles
+        let state = PackageStateRepr::from(self);
+        let visit_permissions =
+            |visitor: &mut prefix::PrefixEncodeVisitor<BV>, permissions: &[model::Permission]| {
+                visitor.visit_unsigned(permissions.len() as u64);
+                for permission in permissions {
+                    visitor.visit_str(&permission.to_string());
+                }
+            };
+
+        visitor.visit_str_raw("WARG-PACKAGE-STATE-V0");
+        visit_option(visitor, self.algorithm.as_ref(), |v, algorithm| {
+            v.visit_str(&algorithm.to_string())
+        });
+        visit_option(visitor, self.head.as_ref(), |v, head| {
+            v.visit_str(&head.digest.to_string());
+            visit_time(v, head.timestamp);
+        });
+
+        visitor.visit_unsigned(state.permissions.len() as u64);
+        for (key_id, permissions) in &state.permissions {
+            visitor.visit_str(&key_id.to_string());
+            visit_permissions(visitor, &permissions.iter().copied().collect::<Vec<_>>());
+        }
+
+        visitor.visit_unsigned(state.keys.len() as u64);
+        for (key_id, key) in &state.keys {
+            visitor.visit_str(&key_id.to_string());
+            visitor.visit_str(&key.to_string());
+        }
+
+        visitor.visit_unsigned(state.releases.len() as u64);
+        for release in state.releases.values() {
+            visitor.visit_str(&release.version.to_string());
+            visitor.visit_str(&release.record_id.to_string());
+            visitor.visit_str(&release.by.to_string());
+            visitor.visit_unsigned(release.other_signers.len() as u64);
+            for key_id in &release.other_signers {
+                visitor.visit_str(&key_id.to_string());
+            }
+            visit_time(visitor, release.timestamp);
+            visit_option(visitor, release.encryption.as_ref(), |v, encryption| {
+                v.visit_unsigned(encryption.recipients.len() as u64);
+                for recipient in &encryption.recipients {
+                    v.visit_str(&recipient.key_id.to_string());
+                    v.visit_bytes(&recipient.encapsulated_key);
+                    v.visit_bytes(&recipient.wrapped_key);
+                }
+            });
+            visit_option(visitor, release.manifest.as_ref(), |v, manifest| {
+                v.visit_unsigned(manifest.artifacts.len() as u64);
+                for artifact in &manifest.artifacts {
+                    v.visit_str(&artifact.name);
+                    v.visit_str(&artifact.content.to_string());
+                    v.visit_unsigned(artifact.size);
+                    v.visit_str(&artifact.media_type);
+                }
+            });
+            match &release.state {
+                ReleaseState::Released { content } => {
+                    visitor.visit_str("released");
+                    visitor.visit_str(&content.to_string());
+                }
+                ReleaseState::Yanked {
+                    by,
+                    timestamp,
+                    reason,
+                    content,
+                } => {
+                    visitor.visit_str("yanked");
+                    visitor.visit_str(&by.to_string());
+                    visit_time(visitor, *timestamp);
+                    visit_option(visitor, reason.as_ref(), |v, reason| v.visit_str(reason));
+                    visit_option(visitor, content.as_ref(), |v, content| {
+                        v.visit_str(&content.to_string())
+                    });
+                }
+            }
+        }
+
+        visitor.visit_unsigned(self.counts.records);
+        visitor.visit_unsigned(self.counts.releases);
+        visitor.visit_unsigned(self.counts.yanks);
+
+        visitor.visit_unsigned(state.permission_history.len() as u64);
+        for change in &state.permission_history {
+            visitor.visit_str(&change.record_id.to_string());
+            visitor.visit_str(&change.by.to_string());
+            visitor.visit_str(&change.key_id.to_string());
+            visitor.visit_str(match change.kind {
+                PermissionChangeKind::Grant => "grant",
+                PermissionChangeKind::Revoke => "revoke",
+            });
+            visit_permissions(visitor, &change.permissions);
+            visit_time(visitor, change.timestamp);
+        }
+
+        visit_option(visitor, self.required_reviewers.as_ref(), |v, required| {
+            v.visit_unsigned(required.threshold.into());
+            v.visit_unsigned(required.reviewers.len() as u64);
+            for (key_id, key) in &required.reviewers {
+                v.visit_str(&key_id.to_string());
+                v.visit_str(&key.to_string());
+            }
+        });
+
+        visitor.visit_unsigned(state.tags.len() as u64);
+        for (name, tag) in &state.tags {
+            visitor.visit_str(name);
+            visitor.visit_str(&tag.version.to_string());
+            visitor.visit_str(&tag.record_id.to_string());
+            visitor.visit_str(&tag.by.to_string());
+            visit_time(visitor, tag.timestamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cosignature;
+    use pretty_assertions::assert_eq;
+    use std::time::{Duration, SystemTime};
+    use warg_crypto::hash::{Hash, HashAlgorithm};
+    use warg_crypto::signing::generate_p256_pair;
+    use warg_crypto::timestamp::TimestampToken;
+
+    #[test]
+    fn test_validate_base_log() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+
+        let timestamp = SystemTime::now();
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp,
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub.clone(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default();
+        let state = state.validate(&envelope).unwrap();
+
+        assert_eq!(
+            state,
+            LogState::try_from(PackageStateRepr {
+                head: Some(Head {
+                    digest: RecordId::package_record::<Sha256>(&envelope),
+                    timestamp,
+                }),
+                algorithm: Some(HashAlgorithm::Sha256),
+                permissions: IndexMap::from([(
+                    alice_id.clone(),
+                    IndexSet::from([model::Permission::Release, model::Permission::Yank]),
+                )]),
+                releases: IndexMap::default(),
+                keys: IndexMap::from([(alice_id.clone(), alice_pub)]),
+                counts: Counts {
+                    records: 1,
+                    ..Default::default()
+                },
+                permission_history: vec![PermissionChange {
+                    record_id: RecordId::package_record::<Sha256>(&envelope),
+                    by: alice_id.clone(),
+                    key_id: alice_id,
+                    kind: PermissionChangeKind::Grant,
+                    permissions: model::Permission::all().into(),
+                    timestamp,
+                }],
+                required_reviewers: None,
+                tags: IndexMap::default(),
+            })
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_larger_log() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+        let bob_id = bob_pub.fingerprint();
+
+        let hash_algo = HashAlgorithm::Sha256;
+        let state = LogState::default();
+
+        // In envelope 0: alice inits and grants bob release
+        let timestamp0 = SystemTime::now();
+        let record0 = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp0,
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: hash_algo,
+                    key: alice_pub.clone(),
+                },
+                model::PackageEntry::GrantFlat {
+                    key: bob_pub.clone(),
+                    permissions: model::Permission::all().into(),
+                    proof: None,
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope0 = ProtoEnvelope::signed_contents(&alice_priv, record0).unwrap();
+        let state = state.validate(&envelope0).unwrap();
+
+        // In envelope 1: bob releases 1.1.0
+        let timestamp1 = timestamp0 + Duration::from_secs(1);
+        let content = hash_algo.digest(&[0, 1, 2, 3]);
+        let record1 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope0)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp1,
+            entries: vec![model::PackageEntry::Release {
+                version: Version::new(1, 1, 0),
+                content,
+                encryption: None,
+                manifest: None,
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        let envelope1 = ProtoEnvelope::signed_contents(&bob_priv, record1).unwrap();
+        let record_id1 = RecordId::package_record::<Sha256>(&envelope1);
+        let state = state.validate(&envelope1).unwrap();
+
+        // At this point, the state should consider 1.1.0 released
+        assert_eq!(
+            state.find_latest_release(&"~1".parse().unwrap()),
+            Some(Release {
+                record_id: record_id1.clone(),
+                version: Version::new(1, 1, 0),
+                by: bob_id.clone(),
+                other_signers: Vec::new(),
+                timestamp: timestamp1,
+                encryption: None,
+                manifest: None,
+                state: ReleaseState::Released { content }
+            })
+        );
+        assert!(state
+            .find_latest_release(&"~1.2".parse().unwrap())
+            .is_none());
+        assert_eq!(
+            state.stored_releases().collect::<Vec<_>>(),
+            vec![Release {
+                record_id: record_id1.clone(),
+                version: Version::new(1, 1, 0),
+                by: bob_id.clone(),
+                other_signers: Vec::new(),
+                timestamp: timestamp1,
+                encryption: None,
+                manifest: None,
+                state: ReleaseState::Released { content }
+            }]
+        );
+
+        // In envelope 2: alice revokes bobs access and yanks 1.1.0
+        let timestamp2 = timestamp1 + Duration::from_secs(1);
+        let record2 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope1)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp2,
+            entries: vec![
+                model::PackageEntry::RevokeFlat {
+                    key_id: bob_id.clone(),
+                    permissions: model::Permission::all().into(),
+                },
+                model::PackageEntry::Yank {
+                    version: Version::new(1, 1, 0),
+                    reason: Some("broken".to_string()),
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope2 = ProtoEnvelope::signed_contents(&alice_priv, record2).unwrap();
+        let state = state.validate(&envelope2).unwrap();
+
+        // At this point, the state should consider 1.1.0 yanked
+        assert!(state.find_latest_release(&"~1".parse().unwrap()).is_none());
+        assert_eq!(
+            state.stored_releases().collect::<Vec<_>>(),
+            vec![Release {
+                record_id: record_id1.clone(),
+                version: Version::new(1, 1, 0),
+                by: bob_id.clone(),
+                other_signers: Vec::new(),
+                timestamp: timestamp1,
+                encryption: None,
+                manifest: None,
+                state: ReleaseState::Yanked {
+                    by: alice_id.clone(),
+                    timestamp: timestamp2,
+                    reason: Some("broken".to_string()),
+                    content: Some(content),
+                }
+            }]
+        );
+        assert_eq!(
+            state.releases().collect::<Vec<_>>(),
+            vec![ReleaseInfo {
+                version: Version::new(1, 1, 0),
+                content: Some(content),
+                record_id: record_id1.clone(),
+                released_by: bob_id.clone(),
+                timestamp: timestamp1,
+                yank: Some(YankInfo {
+                    by: alice_id.clone(),
+                    timestamp: timestamp2,
+                    reason: Some("broken".to_string()),
+                }),
+                manifest: None,
+            }]
+        );
+
+        // Bob's permissions were granted by alice on init and later revoked
+        let record_id0 = RecordId::package_record::<Sha256>(&envelope0);
+        let record_id2 = RecordId::package_record::<Sha256>(&envelope2);
+        let history = vec![
+            PermissionChange {
+                record_id: record_id0.clone(),
+                by: alice_id.clone(),
+                key_id: alice_id.clone(),
+                kind: PermissionChangeKind::Grant,
+                permissions: model::Permission::all().into(),
+                timestamp: timestamp0,
+            },
+            PermissionChange {
+                record_id: record_id0,
+                by: alice_id.clone(),
+                key_id: bob_id.clone(),
+                kind: PermissionChangeKind::Grant,
+                permissions: model::Permission::all().into(),
+                timestamp: timestamp0,
+            },
+            PermissionChange {
+                record_id: record_id2,
+                by: alice_id.clone(),
+                key_id: bob_id.clone(),
+                kind: PermissionChangeKind::Revoke,
+                permissions: model::Permission::all().into(),
+                timestamp: timestamp2,
+            },
+        ];
+        assert_eq!(
+            state.permissions(),
+            PermissionsInfo {
+                keys: IndexMap::from([(
+                    alice_id.clone(),
+                    IndexSet::from(model::Permission::all()),
+                )]),
+                history: history.clone(),
+            }
+        );
+
+        assert_eq!(
+            state,
+            LogState::try_from(PackageStateRepr {
+                algorithm: Some(HashAlgorithm::Sha256),
+                head: Some(Head {
+                    digest: RecordId::package_record::<Sha256>(&envelope2),
+                    timestamp: timestamp2,
+                }),
+                permissions: IndexMap::from([
+                    (
+                        alice_id.clone(),
+                        IndexSet::from([model::Permission::Release, model::Permission::Yank]),
+                    ),
+                    (bob_id.clone(), IndexSet::default()),
+                ]),
+                releases: IndexMap::from([(
+                    Version::new(1, 1, 0),
+                    Release {
+                        record_id: record_id1,
+                        version: Version::new(1, 1, 0),
+                        by: bob_id.clone(),
+                        other_signers: Vec::new(),
+                        timestamp: timestamp1,
+                        encryption: None,
+                        manifest: None,
+                        state: ReleaseState::Yanked {
+                            by: alice_id.clone(),
+                            timestamp: timestamp2,
+                            reason: Some("broken".to_string()),
+                            content: Some(content),
+                        }
+                    }
+                )]),
+                keys: IndexMap::from([(alice_id, alice_pub), (bob_id, bob_pub),]),
+                counts: Counts {
+                    records: 3,
+                    releases: 1,
+                    yanks: 1,
+                },
+                permission_history: history,
+                required_reviewers: None,
+                tags: IndexMap::default(),
+            })
+            .unwrap()
+        );
+
+        assert_eq!(
+            state.stats(),
+            LogStats {
+                records: 3,
+                releases: 1,
+                yanks: 1,
+                active_keys: 1,
+                last_activity: Some(timestamp2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_run_audit() {
+        use crate::Validator;
+
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (_, bob_priv) = generate_p256_pair();
+
+        let timestamp0 = SystemTime::now();
+        let record0 = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp0,
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub.clone(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope0 = ProtoEnvelope::signed_contents(&alice_priv, record0).unwrap();
+
+        // Bob was never granted release permission
+        let timestamp1 = timestamp0 + Duration::from_secs(1);
+        let record1 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope0)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp1,
+            entries: vec![model::PackageEntry::Release {
+                version: Version::new(1, 0, 0),
+                content: HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+                encryption: None,
+                manifest: None,
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope1 = ProtoEnvelope::signed_contents(&bob_priv, record1).unwrap();
+
+        let timestamp2 = timestamp1 + Duration::from_secs(1);
+        let record2 = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope1)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: timestamp2,
+            entries: vec![model::PackageEntry::Release {
+                version: Version::new(1, 1, 0),
+                content: HashAlgorithm::Sha256.digest(&[4, 5, 6, 7]),
+                encryption: None,
+                manifest: None,
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope2 = ProtoEnvelope::signed_contents(&alice_priv, record2).unwrap();
+
+        let (state, findings) = LogState::default().run_audit([&envelope0, &envelope1, &envelope2]);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].index, 1);
+        assert!(matches!(
+            findings[0].error,
+            ValidationError::UnauthorizedAction { .. }
+        ));
+
+        assert!(state.release(&Version::new(1, 0, 0)).is_none());
+        assert!(state.release(&Version::new(1, 1, 0)).is_some());
+        assert_eq!(
+            state.head().as_ref().map(|h| &h.digest),
+            Some(&RecordId::package_record::<Sha256>(&envelope2))
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+        let (bob_pub, _) = generate_p256_pair();
+
+        let timestamp = SystemTime::now();
+        let record = model::PackageRecord {
+            prev: None,
+            version: 0,
+            timestamp,
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub.clone(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = LogState::default();
+        let state = state.validate(&envelope).unwrap();
+
+        let expected = LogState::try_from(PackageStateRepr {
+            head: Some(Head {
+                digest: RecordId::package_record::<Sha256>(&envelope),
+                timestamp,
+            }),
+            algorithm: Some(HashAlgorithm::Sha256),
+            releases: IndexMap::new(),
+            permissions: IndexMap::from([(
+                alice_id.clone(),
+                IndexSet::from([model::Permission::Release, model::Permission::Yank]),
+            )]),
+            keys: IndexMap::from([(alice_id.clone(), alice_pub)]),
+            counts: Counts {
+                records: 1,
+                ..Default::default()
+            },
+            permission_history: vec![PermissionChange {
+                record_id: RecordId::package_record::<Sha256>(&envelope),
+                by: alice_id.clone(),
+                key_id: alice_id,
+                kind: PermissionChangeKind::Grant,
+                permissions: model::Permission::all().into(),
+                timestamp,
+            }],
+            required_reviewers: None,
+            tags: IndexMap::default(),
+        })
+        .unwrap();
+
+        assert_eq!(state, expected);
+
+        let record = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                // This entry is valid
+                model::PackageEntry::GrantFlat {
+                    key: bob_pub,
+                    permissions: vec![model::Permission::Release],
+                    proof: None,
+                },
+                // This entry is not valid
+                model::PackageEntry::RevokeFlat {
+                    key_id: "not-valid".to_string().into(),
+                    permissions: vec![model::Permission::Release],
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        let envelope =
+            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+
+        // This validation should fail
+        match state.validate(&envelope).unwrap_err() {
+            ValidationError::PermissionNotFoundToRevoke { .. } => {}
+            _ => panic!("expected a different error"),
+        }
+    }
+
+    #[test]
+    fn test_apply_entry() {
+        let (alice_pub, _) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+        let bob_id = bob_pub.fingerprint();
+        let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("record")));
+        let timestamp = SystemTime::now();
+        let content: AnyHash =
+            "sha256:b5bb9d8014a0f9b1d61e21e796d78dccdf1352f23cd32812f4850b878ae4944c"
+                .parse()
+                .unwrap();
+
+        let mut state = PackageState::new();
+        let release = model::PackageEntry::release("1.0.0", content).unwrap();
+        assert!(matches!(
+            state.apply_entry(&record_id, &alice_id, timestamp, &release),
+            Err(ValidationError::UnauthorizedAction { .. })
+        ));
+
+        state
+            .apply_entry(
+                &record_id,
+                &alice_id,
+                timestamp,
+                &model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+            )
+            .unwrap();
+        state
+            .apply_entry(&record_id, &alice_id, timestamp, &release)
+            .unwrap();
+
+        let release = state.release(&Version::new(1, 0, 0)).unwrap();
+        assert_eq!(release.record_id, record_id);
+        assert_eq!(release.by, alice_id);
+        assert_eq!(release.content(), Some(&content));
+
+        // Entries are applied without advancing the head
+        assert_eq!(state.head(), &None);
+
+        assert!(matches!(
+            state.apply_entry(
+                &record_id,
+                &bob_id,
+                timestamp,
+                &model::PackageEntry::yank("1.0.0", None).unwrap()
+            ),
+            Err(ValidationError::UnauthorizedAction { .. })
+        ));
+        assert!(!state.release(&Version::new(1, 0, 0)).unwrap().yanked());
+    }
+
+    #[test]
+    fn test_duplicate_release() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let content: AnyHash =
+            "sha256:b5bb9d8014a0f9b1d61e21e796d78dccdf1352f23cd32812f4850b878ae4944c"
+                .parse()
+                .unwrap();
+
+        let init = ProtoEnvelope::signed_contents(
+            &alice_priv,
+            model::PackageRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    model::PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: alice_pub,
+                    },
+                    model::PackageEntry::GrantFlat {
+                        key: bob_pub,
+                        permissions: vec![model::Permission::Release],
+                        proof: None,
+                    },
+                ],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap();
+        let init_id = RecordId::package_record::<Sha256>(&init);
+        let state = LogState::default().validate(&init).unwrap();
+
+        // Alice and Bob race to release the same version against the same head
+        let release = |key: &signing::PrivateKey| {
+            ProtoEnvelope::signed_contents(
+                key,
+                model::PackageRecord {
+                    prev: Some(init_id.clone()),
+                    version: 0,
+                    timestamp: SystemTime::now(),
+                    entries: vec![model::PackageEntry::Release {
+                        version: "1.0.0".parse().unwrap(),
+                        content,
+                        encryption: None,
+                        manifest: None,
+                    }],
+                    entry_signatures: Vec::new(),
+                    publish_token: None,
+                },
+            )
+            .unwrap()
+        };
+        let alice = release(&alice_priv);
+        let bob = release(&bob_priv);
+
+        let state = state.validate(&alice).unwrap();
+        match state.validate(&bob).unwrap_err() {
+            ValidationError::DuplicateRelease {
+                version,
+                existing,
+                record_id,
+            } => {
+                assert_eq!(version.to_string(), "1.0.0");
+                assert_eq!(existing, RecordId::package_record::<Sha256>(&alice));
+                assert_eq!(record_id, RecordId::package_record::<Sha256>(&bob));
+            }
+            e => panic!("unexpected validation error: {e}"),
+        }
+    }
+
+    #[test]
+    fn test_countersignature_policy() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (operator_pub, operator_priv) = generate_p256_pair();
+        let policy = CountersignaturePolicy::new(operator_pub).with_init(true);
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub,
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        assert!(matches!(
+            LogState::default().validate_countersigned(&envelope, &policy),
+            Err(ValidationError::CountersignatureRequired)
+        ));
+
+        // A countersignature by a key other than the operator's is rejected
+        let forged = envelope.clone().countersign(&alice_priv).unwrap();
+        assert!(matches!(
+            LogState::default().validate_countersigned(&forged, &policy),
+            Err(ValidationError::InvalidCountersignature)
+        ));
+
+        // The countersignature survives a protobuf round trip
+        let countersigned = envelope.countersign(&operator_priv).unwrap();
+        let countersigned =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&countersigned.to_protobuf())
+                .unwrap();
+        assert!(countersigned.countersignature().is_some());
+        LogState::default()
+            .validate_countersigned(&countersigned, &policy)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_timestamp_policy() {
+        // The fixture token is over the content bytes of this record
+        let key = signing::PrivateKey::decode(
+            "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+        )
+        .unwrap();
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: key.public_key(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&key, record).unwrap();
+        let token =
+            TimestampToken::from_der(include_bytes!("../../tests/fixtures/timestamp/record.tst"))
+                .unwrap();
+        let authority =
+            TimestampAuthority::from_pem(include_str!("../../tests/fixtures/timestamp/tsa.crt"))
+                .unwrap();
+
+        let policy = TimestampPolicy::new([authority]).with_required(true);
+        assert!(matches!(
+            LogState::default().validate_timestamped(&envelope, &policy),
+            Err(ValidationError::TimestampRequired)
+        ));
+
+        // The timestamp survives a protobuf round trip
+        let timestamped = envelope.clone().with_timestamp(token.clone());
+        let timestamped =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&timestamped.to_protobuf())
+                .unwrap();
+        assert_eq!(policy.check(&timestamped).unwrap(), Some(token.time()));
+        LogState::default()
+            .validate_timestamped(&timestamped, &policy)
+            .unwrap();
+
+        // Authorities not trusted by the policy are rejected
+        assert!(matches!(
+            TimestampPolicy::new([]).check(&timestamped),
+            Err(ValidationError::InvalidTimestamp(
+                TimestampError::UntrustedAuthority
+            ))
+        ));
+
+        // A timestamp over other contents is rejected even without a policy
+        let (other_pub, other_priv) = generate_p256_pair();
+        let other = ProtoEnvelope::signed_contents(
+            &other_priv,
+            model::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: other_pub,
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap()
+        .with_timestamp(token);
+        assert!(matches!(
+            LogState::default().validate(&other),
+            Err(ValidationError::InvalidTimestamp(
+                TimestampError::ImprintMismatch
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_yank_policy() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let policy = YankPolicy::new(30).with_advisory_prefix("CVE-");
+        let version = Version::new(1, 0, 0);
+
+        let released = SystemTime::now();
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: released,
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::PackageEntry::release("1.0.0", HashAlgorithm::Sha256.digest(b"content"))
+                    .unwrap(),
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default()
+            .validate_with_yank_policy(&envelope, &policy)
+            .unwrap();
+
+        let yank = |days: u64, reason: Option<&str>| {
+            let record = model::PackageRecord {
+                prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: released + Duration::from_secs(days * SECS_PER_DAY),
+                entries: vec![model::PackageEntry::yank("1.0.0", reason.map(Into::into)).unwrap()],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            };
+            ProtoEnvelope::signed_contents(&alice_priv, record).unwrap()
+        };
+
+        // Yanks are allowed within the window
+        state
+            .clone()
+            .validate_with_yank_policy(&yank(30, None), &policy)
+            .unwrap();
+
+        // Yanks after the window require an advisory
+        assert!(matches!(
+            state.clone().validate_with_yank_policy(&yank(31, Some("broken")), &policy),
+            Err(ValidationError::YankWindowElapsed { version: v, days: 30 }) if v == version
+        ));
+        state
+            .validate_with_yank_policy(&yank(31, Some("CVE-2024-12345: broken")), &policy)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_yank_range() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let policy = YankPolicy::new(30);
+        let released = SystemTime::now();
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: released,
+            entries: std::iter::once(model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub,
+            })
+            .chain(["1.3.0", "1.4.0", "1.4.1", "1.4.2"].into_iter().map(|v| {
+                model::PackageEntry::release(v, HashAlgorithm::Sha256.digest(v.as_bytes())).unwrap()
+            }))
+            .chain([model::PackageEntry::yank("1.4.0", None).unwrap()])
+            .collect(),
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        let yank = |range: &str, days: u64| {
+            let record = model::PackageRecord {
+                prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: released + Duration::from_secs(days * SECS_PER_DAY),
+                entries: vec![model::PackageEntry::yank_range(
+                    range,
+                    Some("CVE-2024-12345".into()),
+                )
+                .unwrap()],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            };
+            ProtoEnvelope::signed_contents(&alice_priv, record).unwrap()
+        };
+
+        // The range expands to the unyanked releases it matches
+        let yanked = state.clone().validate(&yank("<1.4.2", 0)).unwrap();
+        let versions = |state: &LogState| {
+            state
+                .stored_releases()
+                .filter(|release| release.yanked())
+                .map(|release| release.version.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(versions(&yanked), ["1.3.0", "1.4.0", "1.4.1"]);
+        assert_eq!(
+            yanked.release(&Version::new(1, 4, 1)).unwrap().state,
+            ReleaseState::Yanked {
+                by: alice_priv.public_key().fingerprint(),
+                timestamp: released,
+                reason: Some("CVE-2024-12345".into()),
+                content: Some(HashAlgorithm::Sha256.digest(b"1.4.1")),
+            }
+        );
+
+        // A range matching only yanked or unreleased versions is rejected
+        assert!(matches!(
+            state.clone().validate(&yank("=1.4.0", 0)),
+            Err(ValidationError::YankRangeMatchesNothing { .. })
+        ));
+        assert!(matches!(
+            state.clone().validate(&yank(">=2", 0)),
+            Err(ValidationError::YankRangeMatchesNothing { .. })
+        ));
+
+        // The yank policy applies to every expanded version
+        assert!(matches!(
+            state.clone().validate_with_yank_policy(&yank("^1.4", 31), &policy),
+            Err(ValidationError::YankWindowElapsed { version, .. }) if version == Version::new(1, 4, 1)
+        ));
+
+        // The range survives a protobuf round trip
+        let envelope = yank("<1.4.2, >=1.3", 0);
+        let decoded =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded.as_ref(), envelope.as_ref());
+        assert!(matches!(
+            model::PackageEntry::yank_range("not a range", None),
+            Err(model::EntryError::InvalidVersionRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tags() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let timestamp = SystemTime::now();
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp,
+            entries: std::iter::once(model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub,
+            })
+            .chain(["1.0.0", "1.1.0", "2.0.0-rc.1"].into_iter().map(|v| {
+                model::PackageEntry::release(v, HashAlgorithm::Sha256.digest(v.as_bytes())).unwrap()
+            }))
+            .chain([
+                model::PackageEntry::tag("latest", "1.0.0").unwrap(),
+                model::PackageEntry::tag("nightly", "2.0.0-rc.1").unwrap(),
+            ])
+            .collect(),
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(
+            state.resolve_tag("nightly").unwrap().version,
+            "2.0.0-rc.1".parse().unwrap()
+        );
+        assert!(state.resolve_tag("stable").is_none());
+
+        let next = |entries: Vec<model::PackageEntry>| {
+            let record = model::PackageRecord {
+                prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp,
+                entries,
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            };
+            ProtoEnvelope::signed_contents(&alice_priv, record).unwrap()
+        };
+
+        // Tags are mutable pointers
+        let moved = state
+            .clone()
+            .validate(&next(vec![
+                model::PackageEntry::tag("latest", "1.1.0").unwrap()
+            ]))
+            .unwrap();
+        assert_eq!(
+            moved.resolve_tag("latest").unwrap().version,
+            Version::new(1, 1, 0)
+        );
+        assert_eq!(
+            moved.tags().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["latest", "nightly"]
+        );
+
+        // A yanked target no longer resolves, and cannot be tagged
+        let yanked = state
+            .clone()
+            .validate(&next(vec![
+                model::PackageEntry::yank("1.0.0", None).unwrap()
+            ]))
+            .unwrap();
+        assert_eq!(yanked.tag("latest").unwrap().version, Version::new(1, 0, 0));
+        assert!(yanked.resolve_tag("latest").is_none());
+        assert!(matches!(
+            state.clone().validate(&next(vec![
+                model::PackageEntry::yank("1.0.0", None).unwrap(),
+                model::PackageEntry::tag("stable", "1.0.0").unwrap(),
+            ])),
+            Err(ValidationError::TagOfYanked { .. })
+        ));
+
+        assert!(matches!(
+            state.clone().validate(&next(vec![
+                model::PackageEntry::tag("stable", "3.0.0").unwrap()
+            ])),
+            Err(ValidationError::TagOfUnreleased { .. })
+        ));
+        assert!(matches!(
+            state.validate(&next(vec![model::PackageEntry::Tag {
+                tag: "1.0".into(),
+                version: Version::new(1, 0, 0),
+            }])),
+            Err(ValidationError::InvalidTag { .. })
+        ));
+        assert_eq!(
+            model::PackageEntry::tag("Latest", "1.0.0"),
+            Err(model::EntryError::InvalidTag("Latest".into()))
+        );
+    }
+
+    #[test]
+    fn test_required_reviewers() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (carol_pub, carol_priv) = generate_p256_pair();
+        let (_, mallory_priv) = generate_p256_pair();
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::PackageEntry::require_reviewers(2, [bob_pub, carol_pub]).unwrap(),
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert_eq!(state.required_reviewers().unwrap().threshold, 2);
+
+        let release = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::release(
+                "1.0.0",
+                HashAlgorithm::Sha256.digest(b"content"),
+            )
+            .unwrap()],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let release = ProtoEnvelope::signed_contents(&alice_priv, release).unwrap();
+
+        // Cosignatures by keys other than the reviewers do not count
+        let cosigned = release
+            .clone()
+            .cosign(&bob_priv)
+            .unwrap()
+            .cosign(&mallory_priv)
+            .unwrap();
+        assert!(matches!(
+            state.clone().validate(&cosigned),
+            Err(ValidationError::ReviewersRequired {
+                threshold: 2,
+                approvals: 1
+            })
+        ));
+
+        // A reviewer's cosignature must be valid
+        let forged = release.clone().with_cosignature(Cosignature {
+            key_id: carol_priv.public_key().fingerprint(),
+            signature: Cosignature::sign(&mallory_priv, b"other")
+                .unwrap()
+                .signature,
+        });
+        assert!(matches!(
+            state.clone().validate(&forged),
+            Err(ValidationError::InvalidCosignature { .. })
+        ));
+
+        // The cosignatures survive a protobuf round trip
+        let reviewed = cosigned.cosign(&carol_priv).unwrap();
+        let reviewed =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&reviewed.to_protobuf()).unwrap();
+        let state = state.validate(&reviewed).unwrap();
+        assert!(state.release(&Version::new(1, 0, 0)).is_some());
+
+        // Changing the requirement is itself subject to it
+        let removal = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&reviewed)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::require_reviewers(0, []).unwrap()],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let removal = ProtoEnvelope::signed_contents(&alice_priv, removal).unwrap();
+        assert!(matches!(
+            state.clone().validate(&removal),
+            Err(ValidationError::ReviewersRequired { .. })
+        ));
+        let removal = removal
+            .cosign(&bob_priv)
+            .unwrap()
+            .cosign(&carol_priv)
+            .unwrap();
+        assert!(state
+            .validate(&removal)
+            .unwrap()
+            .required_reviewers()
+            .is_none());
+
+        assert_eq!(
+            model::PackageEntry::require_reviewers(2, [alice_priv.public_key()]).unwrap_err(),
+            model::EntryError::UnmetReviewerThreshold {
+                threshold: 2,
+                reviewers: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_required_reviewers_multi_signer() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (carol_pub, carol_priv) = generate_p256_pair();
+
+        // The maintainer may count as one of the reviewers
+        let init = ProtoEnvelope::signed_contents(
+            &alice_priv,
+            model::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    model::PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: alice_pub.clone(),
+                    },
+                    model::PackageEntry::require_reviewers(2, [alice_pub, bob_pub, carol_pub])
+                        .unwrap(),
+                ],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap();
+        let state = LogState::default().validate(&init).unwrap();
+
+        let release = || model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&init)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::release(
+                "1.0.0",
+                HashAlgorithm::Sha256.digest(b"content"),
+            )
+            .unwrap()],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        // Repeated signatures by the same reviewer count once
+        let repeated =
+            ProtoEnvelope::cosigned_contents(&alice_priv, [&alice_priv, &alice_priv], release())
+                .unwrap();
+        assert!(matches!(
+            state.clone().validate(&repeated),
+            Err(ValidationError::ReviewersRequired {
+                threshold: 2,
+                approvals: 1
+            })
+        ));
+
+        let approved =
+            ProtoEnvelope::cosigned_contents(&alice_priv, [&bob_priv, &carol_priv], release())
+                .unwrap();
+        assert_eq!(approved.signers().count(), 3);
+        let state = state.validate(&approved).unwrap();
+        assert!(state.release(&Version::new(1, 0, 0)).is_some());
+    }
+
+    #[test]
+    fn test_required_reviewers_permission() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (carol_pub, carol_priv) = generate_p256_pair();
+
+        // Bob may only release, and is one of the reviewers
+        let init = ProtoEnvelope::signed_contents(
+            &alice_priv,
+            model::PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    model::PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: alice_pub,
+                    },
+                    model::PackageEntry::GrantFlat {
+                        key: bob_pub.clone(),
+                        permissions: vec![model::Permission::Release],
+                        proof: None,
+                    },
+                    model::PackageEntry::require_reviewers(1, [bob_pub.clone(), carol_pub])
+                        .unwrap(),
+                ],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap();
+        let state = LogState::default().validate(&init).unwrap();
+
+        let change = |signer: &signing::PrivateKey, entry| {
+            ProtoEnvelope::signed_contents(
+                signer,
+                model::PackageRecord {
+                    prev: Some(RecordId::package_record::<Sha256>(&init)),
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries: vec![entry],
+                    entry_signatures: Vec::new(),
+                    publish_token: None,
+                },
+            )
+            .unwrap()
+        };
+
+        // Bob approves his own records, but may not lower the requirement
+        // or make himself the sole reviewer
+        for entry in [
+            model::PackageEntry::require_reviewers(0, []).unwrap(),
+            model::PackageEntry::require_reviewers(1, [bob_pub]).unwrap(),
+        ] {
+            let record = change(&bob_priv, entry).cosign(&carol_priv).unwrap();
+            assert!(matches!(
+                state.clone().validate(&record),
+                Err(ValidationError::UnauthorizedAction {
+                    needed_permission: model::Permission::Yank,
+                    ..
+                })
+            ));
+        }
+
+        // Alice may, once the change is reviewed
+        let removal = change(
+            &alice_priv,
+            model::PackageEntry::require_reviewers(0, []).unwrap(),
+        );
+        assert!(matches!(
+            state.clone().validate(&removal),
+            Err(ValidationError::ReviewersRequired { .. })
+        ));
+        let removal = removal.cosign(&carol_priv).unwrap();
+        assert!(state
+            .validate(&removal)
+            .unwrap()
+            .required_reviewers()
+            .is_none());
+    }
+
+    #[test]
+    fn test_entry_signatures() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (bot_pub, bot_priv) = generate_p256_pair();
+
+        // Alice grants bob release and a bot yank
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::PackageEntry::GrantFlat {
+                    key: bob_pub,
+                    permissions: vec![model::Permission::Release],
+                    proof: None,
+                },
+                model::PackageEntry::GrantFlat {
+                    key: bot_pub.clone(),
+                    permissions: vec![model::Permission::Yank],
+                    proof: None,
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        // The bot bundles bob's release with its own yank of an earlier version
+        let version = Version::new(1, 0, 0);
+        let mut record = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Release {
+                    version: version.clone(),
+                    content: HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+                    encryption: None,
+                    manifest: None,
+                },
+                model::PackageEntry::Yank {
+                    version,
+                    reason: None,
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        // Without bob's signature, the bot is not authorized to release
+        let unsigned = ProtoEnvelope::signed_contents(&bot_priv, record.clone()).unwrap();
+        match state.clone().validate(&unsigned).unwrap_err() {
+            ValidationError::UnauthorizedAction {
+                key_id,
+                needed_permission: model::Permission::Release,
+            } => assert_eq!(key_id, bot_pub.fingerprint()),
+            e => panic!("unexpected error: {e}"),
+        }
+
+        record.sign_entry(0, &bob_priv).unwrap();
+        let signed = ProtoEnvelope::signed_contents(&bot_priv, record.clone()).unwrap();
+        let signed =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&signed.to_protobuf()).unwrap();
+        let new_state = state.clone().validate(&signed).unwrap();
+        assert!(new_state.release(&Version::new(1, 0, 0)).unwrap().yanked());
+
+        // An entry signature cannot be replayed in a different record
+        let mut replayed = record.clone();
+        replayed.timestamp += Duration::from_secs(1);
+        let replayed = ProtoEnvelope::signed_contents(&bot_priv, replayed).unwrap();
+        assert!(matches!(
+            state.clone().validate(&replayed),
+            Err(ValidationError::InvalidEntrySignature { index: 0 })
+        ));
+
+        // Signatures must refer to an entry of the record
+        let mut out_of_range = record;
+        out_of_range.entry_signatures[0].entry = 2;
+        let out_of_range = ProtoEnvelope::signed_contents(&bot_priv, out_of_range).unwrap();
+        assert!(matches!(
+            state.validate(&out_of_range),
+            Err(ValidationError::EntrySignatureOutOfRange { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_publish_tokens() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (ci_pub, ci_priv) = generate_p256_pair();
+        let name: crate::registry::PackageName = "test:package".parse().unwrap();
+
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![model::PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice_pub.clone(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+
+        // Alice issues a token for the CI key to publish releases for an hour
+        let timestamp = SystemTime::now();
+        let token = model::PublishToken::issue(
+            &alice_priv,
+            ci_pub.clone(),
+            [name.clone()],
+            timestamp + Duration::from_secs(3600),
+        )
+        .unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let other = LogId::package_log::<Sha256>(&"test:other".parse().unwrap());
+        assert!(token.authorizes(&log_id));
+        assert!(!token.authorizes(&other));
+        token.verify(&alice_pub).unwrap();
+        assert!(token.verify(&ci_pub).is_err());
+
+        let record = model::PackageRecord {
+            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
+            version: PACKAGE_RECORD_VERSION,
+            timestamp,
+            entries: vec![model::PackageEntry::release(
+                "1.0.0",
+                HashAlgorithm::Sha256.digest(&[0, 1, 2, 3]),
+            )
+            .unwrap()],
+            entry_signatures: Vec::new(),
+            publish_token: Some(token.clone()),
+        };
+
+        // The CI key is not known to the log, but the token authorizes it
+        let envelope = ProtoEnvelope::signed_contents(&ci_priv, record.clone()).unwrap();
+        let envelope =
+            ProtoEnvelope::<model::PackageRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        let new_state = state.clone().validate(&envelope).unwrap();
+        let release = new_state.release(&Version::new(1, 0, 0)).unwrap();
+        assert_eq!(release.by, alice_pub.fingerprint());
 
-    fn validate(self, record: &ProtoEnvelope<Self::Record>) -> Result<Self, Self::Error> {
-        self.validate(record)
-    }
-}
+        // The key signing under the token is remembered as a signer of the release
+        assert_eq!(
+            release.signers().cloned().collect::<Vec<_>>(),
+            [alice_pub.fingerprint(), ci_pub.fingerprint()]
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
-    use std::time::{Duration, SystemTime};
-    use warg_crypto::hash::HashAlgorithm;
-    use warg_crypto::signing::generate_p256_pair;
+        // The registry checks the token's scope and its expiry by its own clock
+        assert_eq!(
+            state
+                .validate_publish_token(&log_id, &envelope, timestamp)
+                .unwrap(),
+            Some(&ci_pub)
+        );
+        assert!(matches!(
+            state.validate_publish_token(&other, &envelope, timestamp),
+            Err(ValidationError::PublishTokenScopeNotAllowed { log_id }) if log_id == other
+        ));
+        assert!(matches!(
+            state.validate_publish_token(&log_id, &envelope, timestamp + Duration::from_secs(7200)),
+            Err(ValidationError::PublishTokenExpired)
+        ));
+
+        // Backdating the record does not revive an expired token
+        let mut backdated = record.clone();
+        backdated.timestamp = timestamp - Duration::from_secs(60);
+        let backdated = ProtoEnvelope::signed_contents(&ci_priv, backdated).unwrap();
+        assert!(matches!(
+            state.validate_publish_token(
+                &log_id,
+                &backdated,
+                timestamp + Duration::from_secs(7200)
+            ),
+            Err(ValidationError::PublishTokenExpired)
+        ));
+
+        // A token scoped to more than the package authorizes none of its packages
+        let broad = model::PublishToken::issue(
+            &alice_priv,
+            ci_pub.clone(),
+            [name.clone(), "test:other".parse().unwrap()],
+            timestamp + Duration::from_secs(3600),
+        )
+        .unwrap();
+        assert!(!broad.authorizes(&log_id));
+        let broad = model::PackageRecord {
+            publish_token: Some(broad),
+            ..record.clone()
+        };
+        let broad = ProtoEnvelope::signed_contents(&ci_priv, broad).unwrap();
+        assert!(matches!(
+            state.validate_publish_token(&log_id, &broad, timestamp),
+            Err(ValidationError::PublishTokenScopeNotAllowed { .. })
+        ));
+
+        // The token only authorizes the key it was issued to
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record.clone()).unwrap();
+        assert!(matches!(
+            state.clone().validate(&envelope),
+            Err(ValidationError::PublishTokenKeyMismatch { .. })
+        ));
+
+        // The token only authorizes releases
+        let mut yank = record.clone();
+        yank.entries
+            .push(model::PackageEntry::yank("1.0.0", None).unwrap());
+        let envelope = ProtoEnvelope::signed_contents(&ci_priv, yank).unwrap();
+        assert!(matches!(
+            state.clone().validate(&envelope),
+            Err(ValidationError::PublishTokenEntryNotAllowed { index: 1 })
+        ));
+
+        // The token cannot be used after it expires
+        let mut expired = record.clone();
+        expired.timestamp += Duration::from_secs(7200);
+        let envelope = ProtoEnvelope::signed_contents(&ci_priv, expired).unwrap();
+        assert!(matches!(
+            state.clone().validate(&envelope),
+            Err(ValidationError::PublishTokenExpired)
+        ));
+
+        // The token cannot be altered
+        let mut altered = record.clone();
+        altered.publish_token.as_mut().unwrap().expires += Duration::from_secs(3600);
+        let envelope = ProtoEnvelope::signed_contents(&ci_priv, altered).unwrap();
+        assert!(matches!(
+            state.clone().validate(&envelope),
+            Err(ValidationError::InvalidPublishToken)
+        ));
+
+        // The token cannot be issued by a key without release permission
+        let forged = model::PackageRecord {
+            publish_token: Some(
+                model::PublishToken::issue(
+                    &ci_priv,
+                    ci_pub,
+                    [name],
+                    timestamp + Duration::from_secs(3600),
+                )
+                .unwrap(),
+            ),
+            ..record
+        };
+        let envelope = ProtoEnvelope::signed_contents(&ci_priv, forged).unwrap();
+        assert!(matches!(
+            state.validate(&envelope),
+            Err(ValidationError::KeyIDNotRecognized { .. })
+        ));
+    }
 
     #[test]
-    fn test_validate_base_log() {
+    fn test_key_possession() {
         let (alice_pub, alice_priv) = generate_p256_pair();
-        let alice_id = alice_pub.fingerprint();
+        let (bob_pub, bob_priv) = generate_p256_pair();
 
-        let timestamp = SystemTime::now();
         let record = model::PackageRecord {
             prev: None,
             version: PACKAGE_RECORD_VERSION,
-            timestamp,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
+                model::PackageEntry::grant(bob_pub, [model::Permission::Release]).unwrap(),
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+
+        // Only the granted key can prove possession of it
+        let mut unproven = record.clone();
+        assert!(unproven.prove_key_possession(1, &alice_priv).is_err());
+        assert!(unproven.prove_key_possession(0, &bob_priv).is_err());
+        assert!(unproven.verify_key_possession(1).is_err());
+
+        let mut proven = record.clone();
+        proven.prove_key_possession(1, &bob_priv).unwrap();
+        proven.verify_key_possession(1).unwrap();
+
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, proven.clone()).unwrap();
+        LogState::default().validate(&envelope).unwrap();
+
+        // A grant without a proof is still valid unless the rules require one
+        let rules = ValidationRules::new().with_key_possession(true);
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, unproven).unwrap();
+        LogState::default().validate(&envelope).unwrap();
+        assert!(matches!(
+            LogState::default().validate_with_rules(&envelope, &rules),
+            Err(ValidationError::PossessionProofRequired { index: 1 })
+        ));
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, proven.clone()).unwrap();
+        LogState::default()
+            .validate_with_rules(&envelope, &rules)
+            .unwrap();
+
+        // A proof does not carry over to a record with a different context
+        let mut moved = proven;
+        moved.timestamp += Duration::from_secs(1);
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, moved).unwrap();
+        assert!(matches!(
+            LogState::default().validate(&envelope),
+            Err(ValidationError::InvalidPossessionProof { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_needs_replay() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let record = model::PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
             entries: vec![model::PackageEntry::Init {
                 hash_algorithm: HashAlgorithm::Sha256,
-                key: alice_pub.clone(),
+                key: alice_pub,
             }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
         };
 
         let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
-        let state = LogState::default();
-        let state = state.validate(&envelope).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
+        assert!(!LogState::default().needs_replay());
+        assert!(!state.needs_replay());
 
-        assert_eq!(
-            state,
-            LogState {
-                head: Some(Head {
-                    digest: RecordId::package_record::<Sha256>(&envelope),
-                    timestamp,
-                }),
-                algorithm: Some(HashAlgorithm::Sha256),
-                permissions: IndexMap::from([(
-                    alice_id.clone(),
-                    IndexSet::from([model::Permission::Release, model::Permission::Yank]),
-                )]),
-                releases: IndexMap::default(),
-                keys: IndexMap::from([(alice_id, alice_pub)]),
-            }
-        );
+        // A state stored before statistics were maintained has no counts
+        let mut json = serde_json::to_value(&state).unwrap();
+        json.as_object_mut().unwrap().remove("counts").unwrap();
+        let stored: LogState = serde_json::from_value(json).unwrap();
+        assert!(stored.needs_replay());
+        assert_eq!(stored.stats().records, 0);
+
+        // A state stored before the permission history was maintained has none
+        let mut json = serde_json::to_value(&state).unwrap();
+        json.as_object_mut()
+            .unwrap()
+            .remove("permissionHistory")
+            .unwrap();
+        let stored: LogState = serde_json::from_value(json).unwrap();
+        assert!(stored.needs_replay());
+        assert!(stored.permissions().history.is_empty());
     }
 
     #[test]
-    fn test_validate_larger_log() {
+    fn test_interned_key_ids() {
         let (alice_pub, alice_priv) = generate_p256_pair();
-        let (bob_pub, bob_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
         let alice_id = alice_pub.fingerprint();
         let bob_id = bob_pub.fingerprint();
 
-        let hash_algo = HashAlgorithm::Sha256;
-        let state = LogState::default();
-
-        // In envelope 0: alice inits and grants bob release
-        let timestamp0 = SystemTime::now();
-        let record0 = model::PackageRecord {
+        let record = model::PackageRecord {
             prev: None,
             version: PACKAGE_RECORD_VERSION,
-            timestamp: timestamp0,
+            timestamp: SystemTime::now(),
             entries: vec![
                 model::PackageEntry::Init {
-                    hash_algorithm: hash_algo,
+                    hash_algorithm: HashAlgorithm::Sha256,
                     key: alice_pub.clone(),
                 },
                 model::PackageEntry::GrantFlat {
-                    key: bob_pub.clone(),
-                    permissions: model::Permission::all().into(),
+                    key: bob_pub,
+                    permissions: vec![model::Permission::Release],
+                    proof: None,
                 },
             ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
         };
-        let envelope0 = ProtoEnvelope::signed_contents(&alice_priv, record0).unwrap();
-        let state = state.validate(&envelope0).unwrap();
 
-        // In envelope 1: bob releases 1.1.0
-        let timestamp1 = timestamp0 + Duration::from_secs(1);
-        let content = hash_algo.digest(&[0, 1, 2, 3]);
-        let record1 = model::PackageRecord {
-            prev: Some(RecordId::package_record::<Sha256>(&envelope0)),
-            version: PACKAGE_RECORD_VERSION,
-            timestamp: timestamp1,
-            entries: vec![model::PackageEntry::Release {
-                version: Version::new(1, 1, 0),
-                content: content.clone(),
-            }],
-        };
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
 
-        let envelope1 = ProtoEnvelope::signed_contents(&bob_priv, record1).unwrap();
-        let record_id1 = RecordId::package_record::<Sha256>(&envelope1);
-        let state = state.validate(&envelope1).unwrap();
+        // Each key id is interned once, however many times it is referred to
+        let alice = state.key_handle(&alice_id).unwrap();
+        let bob = state.key_handle(&bob_id).unwrap();
+        assert_ne!(alice, bob);
+        assert_eq!(state.resolve(alice), Some(alice_id.clone()));
+        assert_eq!(state.resolve(bob), Some(bob_id.clone()));
+        assert_eq!(state.permissions().history.len(), 2);
 
-        // At this point, the state should consider 1.1.0 released
-        assert_eq!(
-            state.find_latest_release(&"~1".parse().unwrap()),
-            Some(&Release {
-                record_id: record_id1.clone(),
-                version: Version::new(1, 1, 0),
-                by: bob_id.clone(),
-                timestamp: timestamp1,
-                state: ReleaseState::Released {
-                    content: content.clone()
-                }
-            })
-        );
-        assert!(state
-            .find_latest_release(&"~1.2".parse().unwrap())
-            .is_none());
-        assert_eq!(
-            state.releases().collect::<Vec<_>>(),
-            vec![&Release {
-                record_id: record_id1.clone(),
-                version: Version::new(1, 1, 0),
-                by: bob_id.clone(),
-                timestamp: timestamp1,
-                state: ReleaseState::Released { content }
-            }]
-        );
+        // The serialized state refers to key ids rather than handles
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.contains(&format!("\"keys\":{{\"{alice_id}\"")));
+        let deserialized: LogState = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.key_handle(&bob_id), Some(bob));
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+    }
 
-        // In envelope 2: alice revokes bobs access and yanks 1.1.0
-        let timestamp2 = timestamp1 + Duration::from_secs(1);
-        let record2 = model::PackageRecord {
-            prev: Some(RecordId::package_record::<Sha256>(&envelope1)),
+    #[test]
+    fn test_interner_per_state() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (bob_pub, _) = generate_p256_pair();
+        let alice_id = alice_pub.fingerprint();
+        let bob_id = bob_pub.fingerprint();
+        let content: AnyHash =
+            "sha256:0000000000000000000000000000000000000000000000000000000000000000"
+                .parse()
+                .unwrap();
+
+        // A package log initialized by alice, who grants bob and releases
+        let record = model::PackageRecord {
+            prev: None,
             version: PACKAGE_RECORD_VERSION,
-            timestamp: timestamp2,
+            timestamp: SystemTime::UNIX_EPOCH,
             entries: vec![
-                model::PackageEntry::RevokeFlat {
-                    key_id: bob_id.clone(),
-                    permissions: model::Permission::all().into(),
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub.clone(),
                 },
-                model::PackageEntry::Yank {
-                    version: Version::new(1, 1, 0),
+                model::PackageEntry::GrantFlat {
+                    key: bob_pub.clone(),
+                    permissions: vec![model::Permission::Release],
+                    proof: None,
+                },
+                model::PackageEntry::Release {
+                    version: "1.0.0".parse().unwrap(),
+                    content,
+                    encryption: None,
+                    manifest: None,
                 },
             ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
         };
-        let envelope2 = ProtoEnvelope::signed_contents(&alice_priv, record2).unwrap();
-        let state = state.validate(&envelope2).unwrap();
+        let envelope = ProtoEnvelope::signed_contents(&alice_priv, record).unwrap();
+        let state = LogState::default().validate(&envelope).unwrap();
 
-        // At this point, the state should consider 1.1.0 yanked
-        assert!(state.find_latest_release(&"~1".parse().unwrap()).is_none());
+        // Each key id, hash, and version is interned once, however often the
+        // state refers to it
+        assert_eq!(state.interner().len(), 2 + 2 + 1);
         assert_eq!(
-            state.releases().collect::<Vec<_>>(),
-            vec![&Release {
-                record_id: record_id1.clone(),
-                version: Version::new(1, 1, 0),
-                by: bob_id.clone(),
-                timestamp: timestamp1,
-                state: ReleaseState::Yanked {
-                    by: alice_id.clone(),
-                    timestamp: timestamp2
-                }
-            }]
+            state.release(&"1.0.0".parse().unwrap()).unwrap().content(),
+            Some(&content)
         );
+        let copy = state.clone();
+        assert_eq!(copy.interner(), state.interner());
+        assert_eq!(copy, state);
 
+        // States are equal when they refer to equal values, whatever their
+        // handles are
+        let mut repr = PackageStateRepr::from(&state);
+        repr.permissions.reverse();
+        repr.keys.reverse();
+        let reordered = PackageState::try_from(repr).unwrap();
+        assert_ne!(reordered.interner(), state.interner());
+        assert_ne!(reordered.key_handle(&alice_id), state.key_handle(&alice_id));
         assert_eq!(
-            state,
-            LogState {
-                algorithm: Some(HashAlgorithm::Sha256),
-                head: Some(Head {
-                    digest: RecordId::package_record::<Sha256>(&envelope2),
-                    timestamp: timestamp2,
-                }),
-                permissions: IndexMap::from([
-                    (
-                        alice_id.clone(),
-                        IndexSet::from([model::Permission::Release, model::Permission::Yank]),
-                    ),
-                    (bob_id.clone(), IndexSet::default()),
-                ]),
-                releases: IndexMap::from([(
-                    Version::new(1, 1, 0),
-                    Release {
-                        record_id: record_id1,
-                        version: Version::new(1, 1, 0),
-                        by: bob_id.clone(),
-                        timestamp: timestamp1,
-                        state: ReleaseState::Yanked {
-                            by: alice_id.clone(),
-                            timestamp: timestamp2
-                        }
-                    }
-                )]),
-                keys: IndexMap::from([(alice_id, alice_pub), (bob_id, bob_pub),]),
-            }
+            reordered.resolve(reordered.key_handle(&bob_id).unwrap()),
+            Some(bob_id)
         );
+        assert_eq!(reordered, state);
+
+        let mut repr = PackageStateRepr::from(&state);
+        repr.keys.reverse();
+        repr.keys[0] = alice_pub;
+        assert_ne!(PackageState::try_from(repr).unwrap(), state);
     }
 
     #[test]
-    fn test_rollback() {
+    fn test_time_travel() {
         let (alice_pub, alice_priv) = generate_p256_pair();
-        let alice_id = alice_pub.fingerprint();
-        let (bob_pub, _) = generate_p256_pair();
+        let (bob_pub, bob_priv) = generate_p256_pair();
+        let bob_id = bob_pub.fingerprint();
+        let content: AnyHash =
+            "sha256:b5bb9d8014a0f9b1d61e21e796d78dccdf1352f23cd32812f4850b878ae4944c"
+                .parse()
+                .unwrap();
 
-        let timestamp = SystemTime::now();
-        let record = model::PackageRecord {
-            prev: None,
-            version: 0,
-            timestamp,
-            entries: vec![model::PackageEntry::Init {
-                hash_algorithm: HashAlgorithm::Sha256,
-                key: alice_pub.clone(),
-            }],
+        let mut records = Vec::new();
+        let mut sign = |key: &signing::PrivateKey, entries| {
+            let record = ProtoEnvelope::signed_contents(
+                key,
+                model::PackageRecord {
+                    prev: records.last().map(RecordId::package_record::<Sha256>),
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries,
+                    entry_signatures: Vec::new(),
+                    publish_token: None,
+                },
+            )
+            .unwrap();
+            records.push(record);
         };
-
-        let envelope =
-            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
-        let state = LogState::default();
-        let state = state.validate(&envelope).unwrap();
-
-        let expected = LogState {
-            head: Some(Head {
-                digest: RecordId::package_record::<Sha256>(&envelope),
-                timestamp,
-            }),
-            algorithm: Some(HashAlgorithm::Sha256),
-            releases: IndexMap::new(),
-            permissions: IndexMap::from([(
-                alice_id.clone(),
-                IndexSet::from([model::Permission::Release, model::Permission::Yank]),
-            )]),
-            keys: IndexMap::from([(alice_id, alice_pub)]),
+        let release = |version: &str| model::PackageEntry::Release {
+            version: version.parse().unwrap(),
+            content,
+            encryption: None,
+            manifest: None,
         };
 
-        assert_eq!(state, expected);
-
-        let record = model::PackageRecord {
-            prev: Some(RecordId::package_record::<Sha256>(&envelope)),
-            version: 0,
-            timestamp: SystemTime::now(),
-            entries: vec![
-                // This entry is valid
+        sign(
+            &alice_priv,
+            vec![
+                model::PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                },
                 model::PackageEntry::GrantFlat {
                     key: bob_pub,
                     permissions: vec![model::Permission::Release],
-                },
-                // This entry is not valid
-                model::PackageEntry::RevokeFlat {
-                    key_id: "not-valid".to_string().into(),
-                    permissions: vec![model::Permission::Release],
+                    proof: None,
                 },
             ],
-        };
+        );
+        sign(&bob_priv, vec![release("2.1.0")]);
+        sign(
+            &alice_priv,
+            vec![model::PackageEntry::RevokeFlat {
+                key_id: bob_id.clone(),
+                permissions: vec![model::Permission::Release],
+            }],
+        );
+        sign(&alice_priv, vec![release("3.0.0")]);
 
-        let envelope =
-            ProtoEnvelope::signed_contents(&alice_priv, record).expect("failed to sign envelope");
+        let state = records
+            .iter()
+            .try_fold(LogState::default(), |state, record| state.validate(record))
+            .unwrap();
+        assert!(state.key_permissions(&bob_id).unwrap().is_empty());
 
-        // This validation should fail
-        match state.validate(&envelope).unwrap_err() {
-            ValidationError::PermissionNotFoundToRevoke { .. } => {}
-            _ => panic!("expected a different error"),
-        }
+        // Bob held the release permission when 2.1.0 was published
+        let record_id = &state.release(&"2.1.0".parse().unwrap()).unwrap().record_id;
+        let past = LogState::at_record(&records, record_id).unwrap().unwrap();
+        assert!(past
+            .key_permissions(&bob_id)
+            .unwrap()
+            .contains(&model::Permission::Release));
+        assert_eq!(past.releases().count(), 1);
+        assert_eq!(LogState::at(&records, 1).unwrap(), Some(past));
+
+        // The state at the last record is the current state
+        assert_eq!(LogState::at(&records, 3).unwrap(), Some(state));
+        assert_eq!(LogState::at(&records, 4).unwrap(), None);
+        assert_eq!(
+            LogState::at_record(&records, &content.into()).unwrap(),
+            None
+        );
     }
 }
@@ -0,0 +1,265 @@
+//! Policies shared by registries and clients.
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+const DEFAULT_MAX_FUTURE_SKEW: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_MAX_PAST_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Represents a timestamp rejected by a [`TimeWindow`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TimeWindowError {
+    /// The timestamp is further in the future than the allowed clock skew.
+    #[error("timestamp is {skew:?} in the future, exceeding the allowed clock skew of {max:?}")]
+    TooFarInFuture {
+        /// How far the timestamp is in the future.
+        skew: Duration,
+        /// The allowed clock skew.
+        max: Duration,
+    },
+    /// The timestamp is older than the allowed age.
+    #[error("timestamp is {age:?} old, exceeding the allowed age of {max:?}")]
+    TooOld {
+        /// The age of the timestamp.
+        age: Duration,
+        /// The allowed age.
+        max: Duration,
+    },
+}
+
+/// A window of time, relative to a local clock, within which a timestamp
+/// made by another party is accepted.
+///
+/// The window allows timestamps up to `max_future_skew` ahead of the local
+/// clock, to tolerate clocks that are out of sync, and up to `max_past_age`
+/// behind it. Both bounds are inclusive.
+///
+/// A window is parsed from a string of the form `<max-future-skew>,<max-past-age>`,
+/// where each duration is a whole number with a unit of `s`, `m`, `h` or `d`,
+/// such as `5m,1d`. When deserialized, each bound may be omitted to use its
+/// default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TimeWindow {
+    #[serde(with = "serde_duration")]
+    max_future_skew: Duration,
+    #[serde(with = "serde_duration")]
+    max_past_age: Duration,
+}
+
+impl TimeWindow {
+    /// Creates a new time window with the given bounds.
+    pub fn new(max_future_skew: Duration, max_past_age: Duration) -> Self {
+        Self {
+            max_future_skew,
+            max_past_age,
+        }
+    }
+
+    /// Sets how far in the future a timestamp may be.
+    ///
+    /// Defaults to 5 minutes.
+    pub fn with_max_future_skew(mut self, skew: Duration) -> Self {
+        self.max_future_skew = skew;
+        self
+    }
+
+    /// Sets how old a timestamp may be.
+    ///
+    /// Defaults to 1 day.
+    pub fn with_max_past_age(mut self, age: Duration) -> Self {
+        self.max_past_age = age;
+        self
+    }
+
+    /// Gets how far in the future a timestamp may be.
+    pub fn max_future_skew(&self) -> Duration {
+        self.max_future_skew
+    }
+
+    /// Gets how old a timestamp may be.
+    pub fn max_past_age(&self) -> Duration {
+        self.max_past_age
+    }
+
+    /// Checks the given timestamp against the window as of the given time.
+    pub fn check(&self, timestamp: SystemTime, now: SystemTime) -> Result<(), TimeWindowError> {
+        match now.duration_since(timestamp) {
+            Ok(age) if age > self.max_past_age => Err(TimeWindowError::TooOld {
+                age,
+                max: self.max_past_age,
+            }),
+            Ok(_) => Ok(()),
+            Err(e) if e.duration() > self.max_future_skew => Err(TimeWindowError::TooFarInFuture {
+                skew: e.duration(),
+                max: self.max_future_skew,
+            }),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Checks the given timestamp against the window as of the current time.
+    pub fn check_now(&self, timestamp: SystemTime) -> Result<(), TimeWindowError> {
+        self.check(timestamp, SystemTime::now())
+    }
+}
+
+impl Default for TimeWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FUTURE_SKEW, DEFAULT_MAX_PAST_AGE)
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{skew},{age}",
+            skew = DisplayDuration(self.max_future_skew),
+            age = DisplayDuration(self.max_past_age)
+        )
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (skew, age) = s.split_once(',').ok_or_else(|| {
+            anyhow!("invalid time window `{s}`: expected `<max-future-skew>,<max-past-age>`")
+        })?;
+        Ok(Self::new(
+            parse_duration(skew.trim()).context("invalid maximum future skew")?,
+            parse_duration(age.trim()).context("invalid maximum past age")?,
+        ))
+    }
+}
+
+/// Parses a duration of a whole number with a unit of `s`, `m`, `h` or `d`,
+/// such as `90s` or `7d`.
+pub fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("duration `{s}` is missing a unit of `s`, `m`, `h` or `d`"))?;
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("invalid duration `{s}`"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid duration unit `{unit}`: expected `s`, `m`, `h` or `d`"),
+    };
+    value
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("duration `{s}` is too large"))
+}
+
+/// Displays a duration in the largest unit accepted by [`parse_duration`]
+/// that divides it.
+struct DisplayDuration(Duration);
+
+impl fmt::Display for DisplayDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        for (unit, size) in [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60)] {
+            if secs != 0 && secs % size == 0 {
+                return write!(f, "{n}{unit}", n = secs / size);
+            }
+        }
+        write!(f, "{secs}s")
+    }
+}
+
+mod serde_duration {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&DisplayDuration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_duration(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_window_boundaries() {
+        let window = TimeWindow::new(Duration::from_secs(60), Duration::from_secs(3600));
+        let now = SystemTime::now();
+
+        assert_eq!(window.check(now, now), Ok(()));
+        assert_eq!(window.check(now + Duration::from_secs(60), now), Ok(()));
+        assert_eq!(
+            window.check(now + Duration::from_secs(61), now),
+            Err(TimeWindowError::TooFarInFuture {
+                skew: Duration::from_secs(61),
+                max: Duration::from_secs(60),
+            })
+        );
+        assert_eq!(window.check(now - Duration::from_secs(3600), now), Ok(()));
+        assert_eq!(
+            window.check(now - Duration::from_secs(3601), now),
+            Err(TimeWindowError::TooOld {
+                age: Duration::from_secs(3601),
+                max: Duration::from_secs(3600),
+            })
+        );
+
+        let exact = TimeWindow::new(Duration::ZERO, Duration::ZERO);
+        assert_eq!(exact.check(now, now), Ok(()));
+        assert!(exact.check(now + Duration::from_nanos(1), now).is_err());
+        assert!(exact.check(now - Duration::from_nanos(1), now).is_err());
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(
+            "5m,1d".parse::<TimeWindow>().unwrap(),
+            TimeWindow::default()
+        );
+        assert_eq!(TimeWindow::default().to_string(), "5m,1d");
+        assert_eq!(
+            "0s, 90s".parse::<TimeWindow>().unwrap(),
+            TimeWindow::new(Duration::ZERO, Duration::from_secs(90))
+        );
+        assert_eq!(
+            TimeWindow::new(Duration::ZERO, Duration::from_secs(90)).to_string(),
+            "0s,90s"
+        );
+
+        for invalid in ["5m", "5m,", "5,1d", "5w,1d", "-5m,1d", "m,1d"] {
+            assert!(invalid.parse::<TimeWindow>().is_err(), "{invalid}");
+        }
+        assert!(parse_duration("99999999999999999999d").is_err());
+        assert!(parse_duration("999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn deserializes_windows() {
+        let window: TimeWindow = serde_json::from_str(r#"{"max-future-skew":"30s"}"#).unwrap();
+        assert_eq!(
+            window,
+            TimeWindow::default().with_max_future_skew(Duration::from_secs(30))
+        );
+        assert_eq!(
+            serde_json::to_string(&window).unwrap(),
+            r#"{"max-future-skew":"30s","max-past-age":"1d"}"#
+        );
+        assert!(serde_json::from_str::<TimeWindow>(r#"{"max-past-age":"1y"}"#).is_err());
+    }
+}
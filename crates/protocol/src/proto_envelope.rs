@@ -1,14 +1,150 @@
 use super::registry::RegistryIndex;
 use anyhow::Error;
 use base64::{engine::general_purpose::STANDARD, Engine};
-use prost::Message;
+use prost::{
+    encoding::{self, DecodeContext, WireType},
+    DecodeError, Message,
+};
 use serde::{Deserialize, Serialize};
 use serde_with::{base64::Base64, serde_as};
 use std::fmt;
 use thiserror::Error;
-use warg_crypto::{hash::AnyHashError, signing, Decode, Signable};
+use warg_crypto::{
+    hash::AnyHashError,
+    signing,
+    timestamp::{TimestampError, TimestampToken},
+    Decode, Signable,
+};
 use warg_protobuf::protocol as protobuf;
 
+const COUNTERSIGNATURE_PREFIX: &[u8] = b"WARG-COUNTERSIGNATURE-V0:";
+const COSIGNATURE_PREFIX: &[u8] = b"WARG-COSIGNATURE-V0:";
+
+/// Trait implemented by the types of envelope contents.
+///
+/// Envelopes are tagged with the type of their contents when encoded, so
+/// that an envelope of one type cannot be decoded as another type whose
+/// encoding happens to be compatible, such as a package record as an
+/// operator record.
+///
+/// The tag is the prefix of the envelope signature, which implementations
+/// define as [`Signable::PREFIX`] from [`ContentType::CONTENT_TYPE`]; the
+/// signature then covers the tag, so retagging an envelope invalidates it.
+pub trait ContentType: Signable {
+    /// The tag identifying the type of the contents.
+    const CONTENT_TYPE: &'static str;
+}
+
+/// Checks the content type tag of an envelope against the expected type.
+///
+/// Untagged envelopes are rejected; those encoded before contents were
+/// tagged are only accepted from registries, by
+/// [`ProtoEnvelope::from_stored`].
+fn check_content_type<Contents: ContentType>(
+    content_type: Option<&str>,
+) -> Result<(), ParseEnvelopeError> {
+    match content_type {
+        Some(found) if found == Contents::CONTENT_TYPE => Ok(()),
+        Some(found) => Err(ParseEnvelopeError::ContentType {
+            expected: Contents::CONTENT_TYPE,
+            found: found.to_string(),
+        }),
+        None => Err(ParseEnvelopeError::Untagged {
+            expected: Contents::CONTENT_TYPE,
+        }),
+    }
+}
+
+/// An operator countersignature over the contents of an envelope.
+///
+/// Countersignatures are used to approve records that are staged by the
+/// registry until the operator attaches one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Countersignature {
+    /// The hash of the key that countersigned the envelope
+    pub key_id: signing::KeyID,
+    /// The countersignature for the content bytes
+    pub signature: signing::Signature,
+}
+
+impl Countersignature {
+    /// Countersigns the given content bytes.
+    pub fn sign<S>(signer: &S, content_bytes: &[u8]) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        Ok(Self {
+            key_id: signer.public_key().fingerprint(),
+            signature: signer.sign(&[COUNTERSIGNATURE_PREFIX, content_bytes].concat())?,
+        })
+    }
+
+    /// Verifies the countersignature of the given content bytes.
+    pub fn verify(
+        &self,
+        public_key: &signing::PublicKey,
+        content_bytes: &[u8],
+    ) -> Result<(), signing::SignatureError> {
+        if public_key.fingerprint() != self.key_id {
+            return Err(signing::SignatureError::new());
+        }
+
+        public_key.verify(
+            &[COUNTERSIGNATURE_PREFIX, content_bytes].concat(),
+            &self.signature,
+        )
+    }
+}
+
+/// An additional signature over the contents of an envelope by a key other
+/// than the envelope signer.
+///
+/// Cosignatures are used by package logs that require releases to be
+/// approved by reviewers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cosignature {
+    /// The hash of the key that cosigned the envelope
+    pub key_id: signing::KeyID,
+    /// The cosignature for the content bytes
+    pub signature: signing::Signature,
+}
+
+impl Cosignature {
+    /// Cosigns the given content bytes.
+    pub fn sign<S>(signer: &S, content_bytes: &[u8]) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        Ok(Self {
+            key_id: signer.public_key().fingerprint(),
+            signature: signer.sign(&Self::payload(content_bytes))?,
+        })
+    }
+
+    /// Verifies the cosignature of the given content bytes.
+    pub fn verify(
+        &self,
+        public_key: &signing::PublicKey,
+        content_bytes: &[u8],
+    ) -> Result<(), signing::SignatureError> {
+        if public_key.fingerprint() != self.key_id {
+            return Err(signing::SignatureError::new());
+        }
+
+        public_key.verify(&Self::payload(content_bytes), &self.signature)
+    }
+
+    /// Gets the message cosigned for the given content bytes.
+    ///
+    /// The message is the same for every cosigner, so it can be computed
+    /// once to verify many cosignatures.
+    pub fn payload(content_bytes: &[u8]) -> Vec<u8> {
+        [COSIGNATURE_PREFIX, content_bytes].concat()
+    }
+}
+
 /// The ProtoEnvelope with the published registry log index.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PublishedProtoEnvelope<Contents> {
@@ -31,29 +167,160 @@ pub struct ProtoEnvelope<Contents> {
     key_id: signing::KeyID,
     /// The signature for the content_bytes
     signature: signing::Signature,
+    /// The operator countersignature for the content_bytes, if any
+    countersignature: Option<Countersignature>,
+    /// The cosignatures for the content_bytes
+    cosignatures: Vec<Cosignature>,
+    /// The trusted timestamp over the content_bytes, if any
+    timestamp: Option<TimestampToken>,
 }
 
 impl<Contents> ProtoEnvelope<Contents> {
     /// Create an envelope for some contents using a signature.
-    pub fn signed_contents(
-        private_key: &signing::PrivateKey,
+    ///
+    /// The signer may be a [`signing::PrivateKey`] or a key held elsewhere,
+    /// such as in a hardware security module.
+    pub fn signed_contents<S>(
+        signer: &S,
         contents: Contents,
     ) -> Result<Self, signing::SignatureError>
     where
+        S: signing::Signer + ?Sized,
         Contents: Signable,
     {
         let content_bytes: Vec<u8> = contents.encode();
 
-        let key_id = private_key.public_key().fingerprint();
-        let signature = contents.sign(private_key)?;
+        let key_id = signer.public_key().fingerprint();
+        let signature = contents.sign(signer)?;
         Ok(ProtoEnvelope {
             contents,
             content_bytes,
             key_id,
             signature,
+            countersignature: None,
+            cosignatures: Vec::new(),
+            timestamp: None,
+        })
+    }
+
+    /// Create an envelope for some contents using a signature made by a
+    /// signer that may need to wait on I/O, such as a key management service.
+    pub async fn signed_contents_async<S>(
+        signer: &S,
+        contents: Contents,
+    ) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::AsyncSigner + ?Sized,
+        Contents: Signable,
+    {
+        let content_bytes: Vec<u8> = contents.encode();
+
+        let key_id = signer.public_key().await?.fingerprint();
+        let signature = signer.sign(&contents.signing_message()).await?;
+        Ok(ProtoEnvelope {
+            contents,
+            content_bytes,
+            key_id,
+            signature,
+            countersignature: None,
+            cosignatures: Vec::new(),
+            timestamp: None,
+        })
+    }
+
+    /// Gets the exact bytes signed for the given contents.
+    ///
+    /// This allows contents to be signed on another machine, such as an
+    /// air-gapped one: the payload is carried to the signer, and the signature
+    /// it produces is attached with [`ProtoEnvelope::from_signing_payload`].
+    pub fn signing_payload(contents: &Contents) -> Vec<u8>
+    where
+        Contents: Signable,
+    {
+        contents.signing_message()
+    }
+
+    /// Decodes the contents of a signing payload, so that a signer can check
+    /// what it is about to sign.
+    pub fn contents_of_signing_payload(payload: &[u8]) -> Result<Contents, DetachedSignatureError>
+    where
+        Contents: Signable + Decode,
+    {
+        let content_bytes = payload
+            .strip_prefix(Contents::PREFIX)
+            .and_then(|rest| rest.strip_prefix(b":"))
+            .ok_or(DetachedSignatureError::WrongPayload)?;
+        Ok(Contents::decode(content_bytes).map_err(ParseEnvelopeError::Contents)?)
+    }
+
+    /// Create an envelope from a signing payload and a signature made over it
+    /// by the given key elsewhere.
+    ///
+    /// The envelope keeps the payload's encoding of the contents, so it is
+    /// assembled from exactly the bytes that were signed.
+    pub fn from_signing_payload(
+        payload: &[u8],
+        public_key: &signing::PublicKey,
+        signature: signing::Signature,
+    ) -> Result<Self, DetachedSignatureError>
+    where
+        Contents: Signable + Decode,
+    {
+        let contents = Self::contents_of_signing_payload(payload)?;
+        let content_bytes = payload[Contents::PREFIX.len() + 1..].to_vec();
+        Contents::verify(public_key, &content_bytes, &signature)?;
+        Ok(ProtoEnvelope {
+            contents,
+            content_bytes,
+            key_id: public_key.fingerprint(),
+            signature,
+            countersignature: None,
+            cosignatures: Vec::new(),
+            timestamp: None,
         })
     }
 
+    /// Create an envelope for some contents and a signature made over their
+    /// signing payload by the given key elsewhere.
+    pub fn from_detached_signature(
+        contents: Contents,
+        public_key: &signing::PublicKey,
+        signature: signing::Signature,
+    ) -> Result<Self, DetachedSignatureError>
+    where
+        Contents: Signable,
+    {
+        let content_bytes = contents.encode();
+        Contents::verify(public_key, &content_bytes, &signature)?;
+        Ok(ProtoEnvelope {
+            contents,
+            content_bytes,
+            key_id: public_key.fingerprint(),
+            signature,
+            countersignature: None,
+            cosignatures: Vec::new(),
+            timestamp: None,
+        })
+    }
+
+    /// Create an envelope for some contents signed by one key and cosigned by
+    /// each of the given keys, e.g. an author and a release bot.
+    pub fn cosigned_contents<'a, S, C>(
+        signer: &S,
+        cosigners: impl IntoIterator<Item = &'a C>,
+        contents: Contents,
+    ) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+        C: signing::Signer + ?Sized + 'a,
+        Contents: Signable,
+    {
+        cosigners.into_iter().try_fold(
+            Self::signed_contents(signer, contents)?,
+            |envelope, cosigner| envelope.cosign(cosigner),
+        )
+    }
+
     /// Get the byte representation of the envelope contents.
     pub fn content_bytes(&self) -> &[u8] {
         &self.content_bytes
@@ -67,40 +334,447 @@ impl<Contents> ProtoEnvelope<Contents> {
         &self.signature
     }
 
+    /// Gets the operator countersignature of the envelope, if any.
+    pub fn countersignature(&self) -> Option<&Countersignature> {
+        self.countersignature.as_ref()
+    }
+
+    /// Attaches an operator countersignature to the envelope.
+    ///
+    /// The countersignature is not part of the signed contents, so attaching
+    /// one does not change the record identifier.
+    pub fn with_countersignature(mut self, countersignature: Countersignature) -> Self {
+        self.countersignature = Some(countersignature);
+        self
+    }
+
+    /// Countersigns the envelope with the given operator key.
+    pub fn countersign<S>(self, signer: &S) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        let countersignature = Countersignature::sign(signer, &self.content_bytes)?;
+        Ok(self.with_countersignature(countersignature))
+    }
+
+    /// Gets the cosignatures of the envelope.
+    pub fn cosignatures(&self) -> &[Cosignature] {
+        &self.cosignatures
+    }
+
+    /// Gets the key IDs of every key that signed the envelope: the signer
+    /// followed by the cosigners.
+    pub fn signers(&self) -> impl Iterator<Item = &signing::KeyID> {
+        std::iter::once(&self.key_id).chain(self.cosignatures.iter().map(|c| &c.key_id))
+    }
+
+    /// Attaches a cosignature to the envelope, replacing any cosignature by
+    /// the same key.
+    ///
+    /// Cosignatures are not part of the signed contents, so attaching one
+    /// does not change the record identifier.
+    pub fn with_cosignature(mut self, cosignature: Cosignature) -> Self {
+        self.cosignatures.retain(|c| c.key_id != cosignature.key_id);
+        self.cosignatures.push(cosignature);
+        self
+    }
+
+    /// Cosigns the envelope with the given key.
+    pub fn cosign<S>(self, signer: &S) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::Signer + ?Sized,
+    {
+        let cosignature = Cosignature::sign(signer, &self.content_bytes)?;
+        Ok(self.with_cosignature(cosignature))
+    }
+
+    /// Gets the trusted timestamp of the envelope, if any.
+    pub fn timestamp(&self) -> Option<&TimestampToken> {
+        self.timestamp.as_ref()
+    }
+
+    /// Attaches a trusted timestamp to the envelope.
+    ///
+    /// The timestamp must be over the content bytes of the envelope; like a
+    /// countersignature, it is not part of the signed contents.
+    pub fn with_timestamp(mut self, timestamp: TimestampToken) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Gets the length of the protobuf representation of the envelope
+    /// without encoding it.
+    pub fn encoded_len(&self) -> usize
+    where
+        Contents: ContentType,
+    {
+        let signature_len = |key_id: &signing::KeyID, signature: &signing::Signature| {
+            string_len(1, key_id.as_str().len()) + string_len(2, signature.encoded_len())
+        };
+
+        string_len(1, self.content_bytes.len())
+            + string_len(2, self.key_id.as_str().len())
+            + string_len(3, self.signature.encoded_len())
+            + self
+                .countersignature
+                .iter()
+                .map(|c| message_len(4, signature_len(&c.key_id, &c.signature)))
+                .sum::<usize>()
+            + self
+                .cosignatures
+                .iter()
+                .map(|c| message_len(5, signature_len(&c.key_id, &c.signature)))
+                .sum::<usize>()
+            + self
+                .timestamp
+                .iter()
+                .map(|t| message_len(6, t.as_bytes().len()))
+                .sum::<usize>()
+            + message_len(7, Contents::CONTENT_TYPE.len())
+    }
+
     /// Get the representation of the entire envelope as a byte vector.
     /// This is the logical inverse of `Envelope::from_bytes`.
-    pub fn to_protobuf(&self) -> Vec<u8> {
+    pub fn to_protobuf(&self) -> Vec<u8>
+    where
+        Contents: ContentType,
+    {
         let proto_envelope = protobuf::Envelope {
             contents: self.content_bytes.clone(),
             key_id: self.key_id.to_string(),
             signature: self.signature.to_string(),
+            countersignature: self
+                .countersignature
+                .as_ref()
+                .map(|c| protobuf::Countersignature {
+                    key_id: c.key_id.to_string(),
+                    signature: c.signature.to_string(),
+                }),
+            cosignatures: cosignatures_to_protobuf(&self.cosignatures),
+            timestamp: self.timestamp.as_ref().map(|t| t.as_bytes().to_vec()),
+            content_type: Some(Contents::CONTENT_TYPE.to_string()),
         };
         proto_envelope.encode_to_vec()
     }
 
+    /// Get the CBOR representation of the envelope.
+    ///
+    /// This is a compact alternative to the protobuf representation for
+    /// verifiers that already link a CBOR library; the content bytes are
+    /// unchanged, so signatures over them remain valid.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Vec<u8>
+    where
+        Contents: ContentType,
+    {
+        let envelope = CborEnvelope {
+            contents: self.content_bytes.clone(),
+            key_id: self.key_id.clone(),
+            signature: self.signature.clone(),
+            countersignature: self.countersignature.clone(),
+            cosignatures: self.cosignatures.clone(),
+            timestamp: self.timestamp.clone(),
+            content_type: Some(Contents::CONTENT_TYPE.to_string()),
+        };
+
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&envelope, &mut bytes).expect("writing to a vector cannot fail");
+        bytes
+    }
+
+    /// Create an entire envelope from its CBOR representation.
+    /// This is the logical inverse of `Envelope::to_cbor`.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ParseEnvelopeError>
+    where
+        Contents: Decode + ContentType,
+    {
+        let envelope: CborEnvelope = ciborium::from_reader(bytes)?;
+        check_content_type::<Contents>(envelope.content_type.as_deref())?;
+        let contents = Contents::decode(&envelope.contents)?;
+        Ok(ProtoEnvelope {
+            contents,
+            content_bytes: envelope.contents,
+            key_id: envelope.key_id,
+            signature: envelope.signature,
+            countersignature: envelope.countersignature,
+            cosignatures: envelope.cosignatures,
+            timestamp: envelope.timestamp,
+        })
+    }
+
     /// Create an entire envelope from a byte vector.
     /// This is the logical inverse of `Envelope::as_bytes`.
     pub fn from_protobuf(bytes: &[u8]) -> Result<Self, ParseEnvelopeError>
     where
-        Contents: Decode,
+        Contents: Decode + ContentType,
     {
         // Parse outer envelope
         let envelope = protobuf::Envelope::decode(bytes)?;
+        check_content_type::<Contents>(envelope.content_type.as_deref())?;
         let contents = Contents::decode(&envelope.contents)?;
 
         // Read key ID and signature
         let key_id = envelope.key_id.into();
         let signature = envelope.signature.parse()?;
+        let countersignature = envelope
+            .countersignature
+            .map(|c| -> Result<_, ParseEnvelopeError> {
+                Ok(Countersignature {
+                    key_id: c.key_id.into(),
+                    signature: c.signature.parse()?,
+                })
+            })
+            .transpose()?;
+        let cosignatures = cosignatures_from_protobuf(envelope.cosignatures)?;
+        let timestamp = envelope
+            .timestamp
+            .map(|t| TimestampToken::from_der(&t))
+            .transpose()?;
 
         Ok(ProtoEnvelope {
             contents,
             content_bytes: envelope.contents,
             key_id,
             signature,
+            countersignature,
+            cosignatures,
+            timestamp,
         })
     }
 }
 
+/// The CBOR representation of an envelope.
+#[cfg(feature = "cbor")]
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CborEnvelope {
+    #[serde_as(as = "serde_with::Bytes")]
+    contents: Vec<u8>,
+    key_id: signing::KeyID,
+    signature: signing::Signature,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    countersignature: Option<Countersignature>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cosignatures: Vec<Cosignature>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<TimestampToken>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+}
+
+/// Gets the encoded length of a `bytes` or `string` field, which is omitted
+/// when empty.
+fn string_len(tag: u32, len: usize) -> usize {
+    match len {
+        0 => 0,
+        len => message_len(tag, len),
+    }
+}
+
+/// Gets the encoded length of an embedded message field.
+fn message_len(tag: u32, len: usize) -> usize {
+    encoding::key_len(tag) + encoding::encoded_len_varint(len as u64) + len
+}
+
+fn cosignatures_to_protobuf(cosignatures: &[Cosignature]) -> Vec<protobuf::Cosignature> {
+    cosignatures
+        .iter()
+        .map(|c| protobuf::Cosignature {
+            key_id: c.key_id.to_string(),
+            signature: c.signature.to_string(),
+        })
+        .collect()
+}
+
+fn cosignatures_from_protobuf(
+    cosignatures: Vec<protobuf::Cosignature>,
+) -> Result<Vec<Cosignature>, ParseEnvelopeError> {
+    cosignatures
+        .into_iter()
+        .map(|c| {
+            Ok(Cosignature {
+                key_id: c.key_id.into(),
+                signature: c.signature.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// A protobuf envelope parsed without copying its contents.
+///
+/// The content bytes, key ID, and signature borrow from the buffer the
+/// envelope was parsed from; the contents themselves are only decoded by
+/// [`ProtoEnvelopeRef::decode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtoEnvelopeRef<'a> {
+    bytes: &'a [u8],
+    content_bytes: &'a [u8],
+    key_id: &'a str,
+    signature: &'a str,
+    countersignature: Option<(&'a str, &'a str)>,
+    timestamp: Option<&'a [u8]>,
+    content_type: Option<&'a str>,
+}
+
+impl<'a> ProtoEnvelopeRef<'a> {
+    /// Parses an envelope from the protobuf representation produced by
+    /// [`ProtoEnvelope::to_protobuf`].
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ParseEnvelopeError> {
+        let mut envelope = Self {
+            bytes,
+            content_bytes: &[],
+            key_id: "",
+            signature: "",
+            countersignature: None,
+            timestamp: None,
+            content_type: None,
+        };
+
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            match encoding::decode_key(&mut rest)? {
+                (1, WireType::LengthDelimited) => {
+                    envelope.content_bytes = take_length_delimited(&mut rest)?
+                }
+                (2, WireType::LengthDelimited) => envelope.key_id = take_str(&mut rest)?,
+                (3, WireType::LengthDelimited) => envelope.signature = take_str(&mut rest)?,
+                (4, WireType::LengthDelimited) => {
+                    envelope.countersignature = Some(take_signature(&mut rest)?)
+                }
+                // Cosignatures are only checked here; they are parsed again
+                // when the envelope is decoded
+                (5, WireType::LengthDelimited) => {
+                    take_signature(&mut rest)?;
+                }
+                (6, WireType::LengthDelimited) => {
+                    envelope.timestamp = Some(take_length_delimited(&mut rest)?)
+                }
+                (7, WireType::LengthDelimited) => {
+                    envelope.content_type = Some(take_str(&mut rest)?)
+                }
+                (1..=7, _) => return Err(DecodeError::new("invalid wire type").into()),
+                (tag, wire_type) => {
+                    encoding::skip_field(wire_type, tag, &mut rest, DecodeContext::default())?
+                }
+            }
+        }
+
+        Ok(envelope)
+    }
+
+    /// Gets the unparsed key IDs and signatures of the cosignatures of the
+    /// envelope.
+    pub fn cosignatures(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let mut rest = self.bytes;
+        std::iter::from_fn(move || {
+            // The envelope was checked when it was parsed
+            while !rest.is_empty() {
+                match encoding::decode_key(&mut rest).ok()? {
+                    (5, WireType::LengthDelimited) => return take_signature(&mut rest).ok(),
+                    (tag, wire_type) => {
+                        encoding::skip_field(wire_type, tag, &mut rest, DecodeContext::default())
+                            .ok()?
+                    }
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Gets the byte representation of the envelope contents.
+    pub fn content_bytes(&self) -> &'a [u8] {
+        self.content_bytes
+    }
+
+    /// Gets the unparsed key ID of the envelope.
+    pub fn key_id(&self) -> &'a str {
+        self.key_id
+    }
+
+    /// Gets the unparsed signature of the envelope.
+    pub fn signature(&self) -> &'a str {
+        self.signature
+    }
+
+    /// Gets the type of the envelope contents, if the envelope is tagged.
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.content_type
+    }
+
+    /// Decodes the contents of the envelope into an owned envelope.
+    pub fn decode<Contents>(&self) -> Result<ProtoEnvelope<Contents>, ParseEnvelopeError>
+    where
+        Contents: Decode + ContentType,
+    {
+        check_content_type::<Contents>(self.content_type)?;
+        let countersignature = self
+            .countersignature
+            .map(|(key_id, signature)| -> Result<_, ParseEnvelopeError> {
+                Ok(Countersignature {
+                    key_id: key_id.to_string().into(),
+                    signature: signature.parse()?,
+                })
+            })
+            .transpose()?;
+        let cosignatures = self
+            .cosignatures()
+            .map(|(key_id, signature)| -> Result<_, ParseEnvelopeError> {
+                Ok(Cosignature {
+                    key_id: key_id.to_string().into(),
+                    signature: signature.parse()?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ProtoEnvelope {
+            contents: Contents::decode(self.content_bytes)?,
+            content_bytes: self.content_bytes.to_vec(),
+            key_id: self.key_id.to_string().into(),
+            signature: self.signature.parse()?,
+            countersignature,
+            cosignatures,
+            timestamp: self.timestamp.map(TimestampToken::from_der).transpose()?,
+        })
+    }
+}
+
+/// Takes a length-delimited `Countersignature` or `Cosignature` message,
+/// returning its key ID and signature.
+fn take_signature<'a>(bytes: &mut &'a [u8]) -> Result<(&'a str, &'a str), DecodeError> {
+    let mut message = take_length_delimited(bytes)?;
+    let (mut key_id, mut signature) = ("", "");
+    while !message.is_empty() {
+        match encoding::decode_key(&mut message)? {
+            (1, WireType::LengthDelimited) => key_id = take_str(&mut message)?,
+            (2, WireType::LengthDelimited) => signature = take_str(&mut message)?,
+            (tag, wire_type) => {
+                encoding::skip_field(wire_type, tag, &mut message, DecodeContext::default())?
+            }
+        }
+    }
+
+    Ok((key_id, signature))
+}
+
+fn take_length_delimited<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+    let len = encoding::decode_varint(bytes)?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= bytes.len())
+        .ok_or_else(|| DecodeError::new("buffer underflow"))?;
+    let (field, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(field)
+}
+
+fn take_str<'a>(bytes: &mut &'a [u8]) -> Result<&'a str, DecodeError> {
+    std::str::from_utf8(take_length_delimited(bytes)?)
+        .map_err(|_| DecodeError::new("invalid string value: data is not UTF-8 encoded"))
+}
+
 impl<Content> AsRef<Content> for ProtoEnvelope<Content> {
     fn as_ref(&self) -> &Content {
         &self.contents
@@ -116,11 +790,41 @@ pub enum ParseEnvelopeError {
     #[error("failed to parse envelope contents from bytes")]
     Contents(#[from] Error),
 
+    #[cfg(feature = "cbor")]
+    #[error("failed to parse the CBOR envelope")]
+    Cbor(#[from] ciborium::de::Error<std::io::Error>),
+
     #[error("failed to parse envelope key id")]
     KeyID(#[from] AnyHashError),
 
     #[error("failed to parse envelope signature")]
     Signature(#[from] signing::SignatureParseError),
+
+    #[error("failed to parse envelope timestamp")]
+    Timestamp(#[from] TimestampError),
+
+    #[error("the envelope contains `{found}` rather than `{expected}`")]
+    ContentType {
+        expected: &'static str,
+        found: String,
+    },
+
+    #[error("the envelope is not tagged as containing `{expected}`")]
+    Untagged { expected: &'static str },
+}
+
+/// Errors that occur in the process of assembling an envelope from a
+/// detached signature
+#[derive(Error, Debug)]
+pub enum DetachedSignatureError {
+    #[error("the signing payload is not for contents of this type")]
+    WrongPayload,
+
+    #[error(transparent)]
+    Parse(#[from] ParseEnvelopeError),
+
+    #[error("the detached signature is invalid: {0}")]
+    Signature(#[from] signing::SignatureError),
 }
 
 #[serde_as]
@@ -134,32 +838,125 @@ pub struct ProtoEnvelopeBody {
     key_id: signing::KeyID,
     /// The signature for the content_bytes
     signature: signing::Signature,
+    /// The operator countersignature for the content_bytes, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    countersignature: Option<Countersignature>,
+    /// The cosignatures for the content_bytes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cosignatures: Vec<Cosignature>,
+    /// The trusted timestamp over the content_bytes, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<TimestampToken>,
+    /// The type of the contents, if tagged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+}
+
+impl ProtoEnvelopeBody {
+    /// Get the protobuf representation of the envelope body.
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        protobuf::Envelope {
+            contents: self.content_bytes.clone(),
+            key_id: self.key_id.to_string(),
+            signature: self.signature.to_string(),
+            countersignature: self
+                .countersignature
+                .as_ref()
+                .map(|c| protobuf::Countersignature {
+                    key_id: c.key_id.to_string(),
+                    signature: c.signature.to_string(),
+                }),
+            cosignatures: cosignatures_to_protobuf(&self.cosignatures),
+            timestamp: self.timestamp.as_ref().map(|t| t.as_bytes().to_vec()),
+            content_type: self.content_type.clone(),
+        }
+        .encode_to_vec()
+    }
+
+    /// Create an envelope body from its protobuf representation.
+    ///
+    /// Unlike [`ProtoEnvelope::from_protobuf`], the contents are not decoded.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, ParseEnvelopeError> {
+        let envelope = protobuf::Envelope::decode(bytes)?;
+        Ok(Self {
+            content_bytes: envelope.contents,
+            key_id: envelope.key_id.into(),
+            signature: envelope.signature.parse()?,
+            countersignature: envelope
+                .countersignature
+                .map(|c| -> Result<_, ParseEnvelopeError> {
+                    Ok(Countersignature {
+                        key_id: c.key_id.into(),
+                        signature: c.signature.parse()?,
+                    })
+                })
+                .transpose()?,
+            cosignatures: cosignatures_from_protobuf(envelope.cosignatures)?,
+            timestamp: envelope
+                .timestamp
+                .map(|t| TimestampToken::from_der(&t))
+                .transpose()?,
+            content_type: envelope.content_type,
+        })
+    }
 }
 
 impl<Content> TryFrom<ProtoEnvelopeBody> for ProtoEnvelope<Content>
 where
-    Content: Decode,
+    Content: Decode + ContentType,
 {
     type Error = Error;
 
     fn try_from(value: ProtoEnvelopeBody) -> Result<Self, Self::Error> {
+        check_content_type::<Content>(value.content_type.as_deref())?;
+        Self::decode_body(value)
+    }
+}
+
+impl<Content> ProtoEnvelope<Content>
+where
+    Content: Decode + ContentType,
+{
+    /// Decodes the body of an envelope stored or published by a registry.
+    ///
+    /// Unlike the conversion with [`TryFrom`], bodies encoded before contents
+    /// were tagged are accepted, as the registry validated them when they
+    /// were stored. Envelopes submitted to a registry must be tagged.
+    pub fn from_stored(value: ProtoEnvelopeBody) -> Result<Self, Error> {
+        if value.content_type.is_some() {
+            check_content_type::<Content>(value.content_type.as_deref())?;
+        }
+        Self::decode_body(value)
+    }
+
+    fn decode_body(value: ProtoEnvelopeBody) -> Result<Self, Error> {
         let contents = Content::decode(&value.content_bytes)?;
         let envelope = ProtoEnvelope {
             contents,
             content_bytes: value.content_bytes,
             key_id: value.key_id,
             signature: value.signature,
+            countersignature: value.countersignature,
+            cosignatures: value.cosignatures,
+            timestamp: value.timestamp,
         };
         Ok(envelope)
     }
 }
 
-impl<Content> From<ProtoEnvelope<Content>> for ProtoEnvelopeBody {
+impl<Content> From<ProtoEnvelope<Content>> for ProtoEnvelopeBody
+where
+    Content: ContentType,
+{
     fn from(value: ProtoEnvelope<Content>) -> Self {
         ProtoEnvelopeBody {
             content_bytes: value.content_bytes,
             key_id: value.key_id,
             signature: value.signature,
+            countersignature: value.countersignature,
+            cosignatures: value.cosignatures,
+            timestamp: value.timestamp,
+            content_type: Some(Content::CONTENT_TYPE.to_string()),
         }
     }
 }
@@ -170,6 +967,10 @@ impl fmt::Debug for ProtoEnvelopeBody {
             .field("content_bytes", &STANDARD.encode(&self.content_bytes))
             .field("key_id", &self.key_id)
             .field("signature", &self.signature)
+            .field("countersignature", &self.countersignature)
+            .field("cosignatures", &self.cosignatures)
+            .field("timestamp", &self.timestamp)
+            .field("content_type", &self.content_type)
             .finish()
     }
 }
@@ -187,19 +988,24 @@ pub struct PublishedProtoEnvelopeBody {
 
 impl<Content> TryFrom<PublishedProtoEnvelopeBody> for PublishedProtoEnvelope<Content>
 where
-    Content: Decode,
+    Content: Decode + ContentType,
 {
     type Error = Error;
 
+    /// Published envelopes are decoded with [`ProtoEnvelope::from_stored`],
+    /// so records published before contents were tagged are accepted.
     fn try_from(value: PublishedProtoEnvelopeBody) -> Result<Self, Self::Error> {
         Ok(PublishedProtoEnvelope {
-            envelope: ProtoEnvelope::<Content>::try_from(value.envelope)?,
+            envelope: ProtoEnvelope::<Content>::from_stored(value.envelope)?,
             registry_index: value.registry_index,
         })
     }
 }
 
-impl<Content> From<PublishedProtoEnvelope<Content>> for PublishedProtoEnvelopeBody {
+impl<Content> From<PublishedProtoEnvelope<Content>> for PublishedProtoEnvelopeBody
+where
+    Content: ContentType,
+{
     fn from(value: PublishedProtoEnvelope<Content>) -> Self {
         PublishedProtoEnvelopeBody {
             envelope: ProtoEnvelopeBody::from(value.envelope),
@@ -221,3 +1027,440 @@ impl fmt::Debug for PublishedProtoEnvelopeBody {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operator::{OperatorEntry, OperatorRecord};
+    use crate::package::PackageRecord;
+    use std::time::SystemTime;
+    use warg_crypto::{
+        hash::HashAlgorithm,
+        signing::{
+            generate_ed25519_pair, generate_p256_pair, generate_rsa_pss_pair, AsyncSigner,
+            SignatureAlgorithm, Signer,
+        },
+        Encode,
+    };
+
+    #[test]
+    fn test_encoded_len() {
+        let (public_key, private_key) = generate_p256_pair();
+        let record = OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: public_key,
+            }],
+        };
+        assert_eq!(record.encoded_len(), record.encode().len());
+
+        let envelope = ProtoEnvelope::signed_contents(&private_key, record).unwrap();
+        assert_eq!(envelope.encoded_len(), envelope.to_protobuf().len());
+
+        let envelope = envelope
+            .countersign(&private_key)
+            .unwrap()
+            .cosign(&generate_p256_pair().1)
+            .unwrap()
+            .cosign(&generate_p256_pair().1)
+            .unwrap();
+        assert_eq!(envelope.encoded_len(), envelope.to_protobuf().len());
+    }
+
+    #[test]
+    fn test_detached_signature() {
+        let (public_key, private_key) = generate_p256_pair();
+        let record = OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: public_key.clone(),
+            }],
+        };
+
+        // The payload is signed elsewhere, and the signature attached to it
+        let payload = ProtoEnvelope::signing_payload(&record);
+        assert_eq!(
+            ProtoEnvelope::<OperatorRecord>::contents_of_signing_payload(&payload).unwrap(),
+            record
+        );
+        let signature = private_key.sign(&payload).unwrap();
+        let envelope = ProtoEnvelope::<OperatorRecord>::from_signing_payload(
+            &payload,
+            &public_key,
+            signature.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            envelope,
+            ProtoEnvelope::signed_contents(&private_key, record.clone()).unwrap()
+        );
+        assert_eq!(
+            ProtoEnvelope::from_detached_signature(record.clone(), &public_key, signature.clone())
+                .unwrap(),
+            envelope
+        );
+
+        // Signatures by another key, or over other contents, are rejected
+        let (other_key, _) = generate_p256_pair();
+        assert!(matches!(
+            ProtoEnvelope::<OperatorRecord>::from_signing_payload(
+                &payload,
+                &other_key,
+                signature.clone()
+            ),
+            Err(DetachedSignatureError::Signature(_))
+        ));
+        assert!(matches!(
+            ProtoEnvelope::<OperatorRecord>::from_signing_payload(
+                &record.encode(),
+                &public_key,
+                signature
+            ),
+            Err(DetachedSignatureError::WrongPayload)
+        ));
+    }
+
+    #[test]
+    fn test_ed25519_round_trip() {
+        let (public_key, private_key) = generate_ed25519_pair();
+        let (_, cosigner) = generate_p256_pair();
+        let envelope = ProtoEnvelope::cosigned_contents(
+            &private_key,
+            [&cosigner],
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(envelope.encoded_len(), envelope.to_protobuf().len());
+
+        let decoded =
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded, envelope);
+        OperatorRecord::verify(&public_key, decoded.content_bytes(), decoded.signature()).unwrap();
+        assert_eq!(
+            ProtoEnvelopeRef::parse(&envelope.to_protobuf())
+                .unwrap()
+                .decode::<OperatorRecord>()
+                .unwrap(),
+            envelope
+        );
+    }
+
+    #[test]
+    fn test_content_type() {
+        let (public_key, private_key) = generate_p256_pair();
+        let envelope = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::GrantFlat {
+                    key: public_key.clone(),
+                    permissions: vec![crate::operator::Permission::Commit],
+                }],
+            },
+        )
+        .unwrap();
+        let bytes = envelope.to_protobuf();
+        assert_eq!(
+            ProtoEnvelopeRef::parse(&bytes).unwrap().content_type(),
+            Some("WARG-OPERATOR-RECORD-SIGNATURE-V0")
+        );
+
+        // The contents of a grant decode as either type, but the tag does not
+        assert!(PackageRecord::decode(envelope.content_bytes()).is_ok());
+        assert!(matches!(
+            ProtoEnvelope::<PackageRecord>::from_protobuf(&bytes),
+            Err(ParseEnvelopeError::ContentType {
+                expected: "WARG-PACKAGE-RECORD-SIGNATURE-V0",
+                ..
+            })
+        ));
+        assert!(matches!(
+            ProtoEnvelopeRef::parse(&bytes)
+                .unwrap()
+                .decode::<PackageRecord>(),
+            Err(ParseEnvelopeError::ContentType { .. })
+        ));
+        let body: ProtoEnvelopeBody = serde_json::from_str(
+            &serde_json::to_string(&ProtoEnvelopeBody::from(envelope.clone())).unwrap(),
+        )
+        .unwrap();
+        assert!(ProtoEnvelope::<PackageRecord>::try_from(body.clone()).is_err());
+        assert_eq!(
+            ProtoEnvelope::<OperatorRecord>::try_from(body).unwrap(),
+            envelope
+        );
+
+        // The tag is signed, so retagging the envelope invalidates it
+        let mut retagged = protobuf::Envelope::decode(bytes.as_slice()).unwrap();
+        retagged.content_type = Some(PackageRecord::CONTENT_TYPE.to_string());
+        let retagged =
+            ProtoEnvelope::<PackageRecord>::from_protobuf(&retagged.encode_to_vec()).unwrap();
+        assert!(OperatorRecord::verify(
+            &public_key,
+            envelope.content_bytes(),
+            envelope.signature()
+        )
+        .is_ok());
+        assert!(
+            PackageRecord::verify(&public_key, retagged.content_bytes(), retagged.signature())
+                .is_err()
+        );
+
+        // Envelopes encoded before contents were tagged are only accepted
+        // from registries
+        let mut untagged = protobuf::Envelope::decode(bytes.as_slice()).unwrap();
+        untagged.content_type = None;
+        let untagged = untagged.encode_to_vec();
+        assert!(matches!(
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&untagged),
+            Err(ParseEnvelopeError::Untagged { .. })
+        ));
+        assert!(ProtoEnvelopeRef::parse(&untagged)
+            .unwrap()
+            .decode::<OperatorRecord>()
+            .is_err());
+        let body = ProtoEnvelopeBody::from_protobuf(&untagged).unwrap();
+        assert!(ProtoEnvelope::<OperatorRecord>::try_from(body.clone()).is_err());
+        assert_eq!(
+            ProtoEnvelope::<OperatorRecord>::from_stored(body.clone()).unwrap(),
+            envelope
+        );
+        let published =
+            PublishedProtoEnvelope::<OperatorRecord>::try_from(PublishedProtoEnvelopeBody {
+                envelope: body,
+                registry_index: 0,
+            })
+            .unwrap();
+        assert_eq!(published.envelope, envelope);
+    }
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        let (public_key, private_key) = generate_p256_pair();
+        let token =
+            TimestampToken::from_der(include_bytes!("../tests/fixtures/timestamp/record.tst"))
+                .unwrap();
+        let envelope = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key,
+                }],
+            },
+        )
+        .unwrap()
+        .with_timestamp(token.clone());
+        assert_eq!(envelope.timestamp(), Some(&token));
+        assert_eq!(envelope.encoded_len(), envelope.to_protobuf().len());
+
+        let decoded =
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(
+            ProtoEnvelopeRef::parse(&envelope.to_protobuf())
+                .unwrap()
+                .decode::<OperatorRecord>()
+                .unwrap(),
+            envelope
+        );
+    }
+
+    #[test]
+    fn test_rsa_pss_round_trip() {
+        let (public_key, private_key) =
+            generate_rsa_pss_pair(SignatureAlgorithm::RsaPss2048).unwrap();
+        let envelope = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(envelope.key_id(), &public_key.fingerprint());
+        assert_eq!(envelope.encoded_len(), envelope.to_protobuf().len());
+
+        let decoded =
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded, envelope);
+        OperatorRecord::verify(&public_key, decoded.content_bytes(), decoded.signature()).unwrap();
+    }
+
+    #[test]
+    fn test_dyn_signer_round_trip() {
+        let (public_key, private_key) = generate_p256_pair();
+        let (_, cosigner) = generate_ed25519_pair();
+        let signer: &dyn Signer = &private_key;
+        let envelope = ProtoEnvelope::cosigned_contents(
+            signer,
+            [&cosigner as &dyn Signer],
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: signer.public_key(),
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(envelope.key_id(), &public_key.fingerprint());
+
+        let decoded =
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded, envelope);
+        OperatorRecord::verify(&public_key, decoded.content_bytes(), decoded.signature()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_async_signer_matches_signer() {
+        let (public_key, private_key) = generate_p256_pair();
+        let record = OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: public_key.clone(),
+            }],
+        };
+
+        let signer: &dyn AsyncSigner = &private_key;
+        let envelope = ProtoEnvelope::signed_contents_async(signer, record.clone())
+            .await
+            .unwrap();
+        assert_eq!(envelope.key_id(), &public_key.fingerprint());
+        assert_eq!(
+            envelope,
+            ProtoEnvelope::signed_contents(&private_key, record).unwrap()
+        );
+        OperatorRecord::verify(&public_key, envelope.content_bytes(), envelope.signature())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_cosigned_round_trip() {
+        let (public_key, private_key) = generate_p256_pair();
+        let (author_public, author) = generate_p256_pair();
+        let (bot_public, bot) = generate_p256_pair();
+        let envelope = ProtoEnvelope::cosigned_contents(
+            &private_key,
+            [&author, &bot],
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            envelope.signers().cloned().collect::<Vec<_>>(),
+            [
+                public_key.fingerprint(),
+                author_public.fingerprint(),
+                bot_public.fingerprint()
+            ]
+        );
+
+        let decoded =
+            ProtoEnvelope::<OperatorRecord>::from_protobuf(&envelope.to_protobuf()).unwrap();
+        assert_eq!(decoded, envelope);
+        OperatorRecord::verify(&public_key, decoded.content_bytes(), decoded.signature()).unwrap();
+        for (key, cosignature) in [author_public, bot_public]
+            .iter()
+            .zip(decoded.cosignatures())
+        {
+            cosignature.verify(key, decoded.content_bytes()).unwrap();
+        }
+
+        // Cosigning again replaces the cosignature without touching the contents
+        let recosigned = decoded.clone().cosign(&author).unwrap();
+        assert_eq!(recosigned.content_bytes(), envelope.content_bytes());
+        assert_eq!(recosigned.cosignatures().len(), 2);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        use crate::{
+            registry::{Checkpoint, TimestampedCheckpoint},
+            SerdeEnvelope,
+        };
+
+        let (public_key, private_key) = generate_p256_pair();
+        let envelope = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                }],
+            },
+        )
+        .unwrap()
+        .countersign(&private_key)
+        .unwrap()
+        .cosign(&generate_p256_pair().1)
+        .unwrap();
+
+        let decoded = ProtoEnvelope::<OperatorRecord>::from_cbor(&envelope.to_cbor()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert!(matches!(
+            ProtoEnvelope::<OperatorRecord>::from_cbor(&envelope.to_protobuf()),
+            Err(ParseEnvelopeError::Cbor(_))
+        ));
+
+        let checkpoint = SerdeEnvelope::signed_contents(
+            &private_key,
+            TimestampedCheckpoint::new(
+                Checkpoint {
+                    log_root: HashAlgorithm::Sha256.digest(b"log"),
+                    log_length: 1,
+                    map_root: HashAlgorithm::Sha256.digest(b"map"),
+                },
+                SystemTime::now(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let decoded =
+            SerdeEnvelope::<TimestampedCheckpoint>::from_cbor(&checkpoint.to_cbor()).unwrap();
+        assert_eq!(decoded, checkpoint);
+        TimestampedCheckpoint::verify(&public_key, &decoded.as_ref().encode(), decoded.signature())
+            .unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! Files of log records for offline replay.
+//!
+//! A record log file is a sequence of envelopes in their protobuf
+//! representation, each prefixed by its length as a protobuf varint (i.e. a
+//! stream of length-delimited protobuf messages).
+//!
+//! Records are read back as [`ProtoEnvelopeRef`]s that borrow from the file's
+//! contents. With the `mmap` feature enabled, [`MmapRecordLog`] maps a file
+//! into memory so that monitors replaying large logs neither copy nor buffer
+//! records they do not need.
+
+use crate::{ContentType, ParseEnvelopeError, ProtoEnvelope, ProtoEnvelopeRef};
+use prost::{encode_length_delimiter, encoding::decode_varint, DecodeError};
+use std::io::{self, Write};
+
+/// Writes records to a record log file.
+pub struct RecordLogWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> RecordLogWriter<W> {
+    /// Creates a new writer of records to the given writer.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Appends a record to the log.
+    pub fn append<Contents>(&mut self, envelope: &ProtoEnvelope<Contents>) -> io::Result<()>
+    where
+        Contents: ContentType,
+    {
+        let bytes = envelope.to_protobuf();
+        let mut prefix = Vec::with_capacity(prost::length_delimiter_len(bytes.len()));
+        encode_length_delimiter(bytes.len(), &mut prefix)?;
+        self.writer.write_all(&prefix)?;
+        self.writer.write_all(&bytes)
+    }
+
+    /// Flushes the log, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// An iterator over the records of a record log.
+///
+/// Iteration stops after the first record that fails to parse.
+#[derive(Debug, Clone)]
+pub struct RecordLogReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> RecordLogReader<'a> {
+    /// Creates a reader over the given record log contents.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn next_record(&mut self) -> Result<ProtoEnvelopeRef<'a>, ParseEnvelopeError> {
+        let mut bytes = self.bytes;
+        let len = decode_varint(&mut bytes)?;
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= bytes.len())
+            .ok_or_else(|| DecodeError::new("truncated record"))?;
+        let (record, rest) = bytes.split_at(len);
+        let envelope = ProtoEnvelopeRef::parse(record)?;
+        self.bytes = rest;
+        Ok(envelope)
+    }
+}
+
+impl<'a> Iterator for RecordLogReader<'a> {
+    type Item = Result<ProtoEnvelopeRef<'a>, ParseEnvelopeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let record = self.next_record();
+        if record.is_err() {
+            self.bytes = &[];
+        }
+        Some(record)
+    }
+}
+
+/// A record log file mapped into memory.
+#[cfg(feature = "mmap")]
+pub struct MmapRecordLog {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapRecordLog {
+    /// Maps the record log file at the given path.
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the file is required to not be modified while mapped
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+
+    /// Gets the size of the record log in bytes.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Determines if the record log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Reads the records of the log.
+    pub fn records(&self) -> RecordLogReader<'_> {
+        RecordLogReader::new(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operator::{OperatorEntry, OperatorRecord};
+    use std::time::SystemTime;
+    use warg_crypto::{hash::HashAlgorithm, signing::generate_p256_pair};
+
+    fn records() -> Vec<ProtoEnvelope<OperatorRecord>> {
+        let (public_key, private_key) = generate_p256_pair();
+        let record = ProtoEnvelope::signed_contents(
+            &private_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key,
+                }],
+            },
+        )
+        .unwrap();
+        let countersigned = record.clone().countersign(&private_key).unwrap();
+        let cosigned = countersigned
+            .clone()
+            .cosign(&generate_p256_pair().1)
+            .unwrap()
+            .cosign(&generate_p256_pair().1)
+            .unwrap();
+        vec![record, countersigned, cosigned]
+    }
+
+    #[test]
+    fn reads_written_records() {
+        let records = records();
+        let mut writer = RecordLogWriter::new(Vec::new());
+        for record in &records {
+            writer.append(record).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        let read = RecordLogReader::new(&bytes)
+            .map(|r| r.and_then(|r| r.decode::<OperatorRecord>()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read, records);
+
+        // Parsed envelopes borrow from the log
+        let first = RecordLogReader::new(&bytes).next().unwrap().unwrap();
+        let range = bytes.as_ptr_range();
+        assert!(range.contains(&first.content_bytes().as_ptr()));
+        assert_eq!(first.content_bytes(), records[0].content_bytes());
+
+        // A truncated log yields an error and stops
+        let mut reader = RecordLogReader::new(&bytes[..bytes.len() - 1]);
+        for _ in 1..records.len() {
+            assert!(reader.next().unwrap().is_ok());
+        }
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn reads_mapped_records() {
+        let records = records();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = RecordLogWriter::new(file.as_file());
+        for record in &records {
+            writer.append(record).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let log = MmapRecordLog::open(file.path()).unwrap();
+        let read = log
+            .records()
+            .map(|r| r.and_then(|r| r.decode::<OperatorRecord>()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read, records);
+    }
+}
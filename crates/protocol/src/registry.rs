@@ -1,11 +1,18 @@
-use crate::{operator::OperatorRecord, package::PackageRecord, ProtoEnvelope};
+use crate::{
+    operator::OperatorRecord,
+    package::{LogStats, PackageRecord},
+    ProtoEnvelope,
+};
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, SupportedDigest};
 use warg_crypto::prefix::VisitPrefixEncode;
+use warg_crypto::signing::KeyID;
 use warg_crypto::{prefix, ByteVisitor, Signable, VisitBytes};
 use wasmparser::names::KebabStr;
 
@@ -81,6 +88,116 @@ impl VisitBytes for TimestampedCheckpoint {
     }
 }
 
+/// A statement by the registry operator that the given checkpoint was the
+/// latest checkpoint of the registry as of the given time.
+///
+/// The registry re-signs the statement on an interval, so a client can
+/// detect a mirror serving a checkpoint that is no longer the latest.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreshnessAssertion {
+    #[serde(flatten)]
+    pub checkpoint: Checkpoint,
+    pub timestamp: u64,
+}
+
+impl FreshnessAssertion {
+    pub fn new(checkpoint: Checkpoint, time: SystemTime) -> anyhow::Result<Self> {
+        Ok(Self {
+            checkpoint,
+            timestamp: time.duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+        })
+    }
+
+    pub fn now(checkpoint: Checkpoint) -> anyhow::Result<Self> {
+        Self::new(checkpoint, SystemTime::now())
+    }
+
+    /// Gets the time the assertion was made.
+    pub fn time(&self) -> SystemTime {
+        std::time::UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+
+    /// Gets the age of the assertion as of the given time.
+    ///
+    /// Assertions made after the given time have an age of zero.
+    pub fn age(&self, now: SystemTime) -> Duration {
+        let now = now
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Duration::from_secs(now.saturating_sub(self.timestamp))
+    }
+}
+
+impl Signable for FreshnessAssertion {
+    const PREFIX: &'static [u8] = b"WARG-FRESHNESS-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for FreshnessAssertion {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-FRESHNESS-ASSERTION-V0");
+        visitor.visit_unsigned(self.checkpoint.log_length as u64);
+        visitor.visit_str(&self.checkpoint.log_root.to_string());
+        visitor.visit_str(&self.checkpoint.map_root.to_string());
+        visitor.visit_unsigned(self.timestamp);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for FreshnessAssertion {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
+/// A statement by the registry operator that a record was sequenced at the
+/// given index of the registry log and is included in the given checkpoint.
+///
+/// A publisher can keep a receipt as evidence that the registry accepted its
+/// record, even if the registry later omits the record from its log.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordReceipt {
+    pub log_id: LogId,
+    pub record_id: RecordId,
+    pub registry_index: RegistryIndex,
+    pub checkpoint: Checkpoint,
+}
+
+impl RecordReceipt {
+    /// Gets the log leaf of the record.
+    pub fn leaf(&self) -> LogLeaf {
+        LogLeaf {
+            log_id: self.log_id.clone(),
+            record_id: self.record_id.clone(),
+        }
+    }
+}
+
+impl Signable for RecordReceipt {
+    const PREFIX: &'static [u8] = b"WARG-RECEIPT-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for RecordReceipt {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-RECORD-RECEIPT-V0");
+        visitor.visit_str(&self.log_id.to_string());
+        visitor.visit_str(&self.record_id.to_string());
+        visitor.visit_unsigned(self.registry_index as u64);
+        visitor.visit_unsigned(self.checkpoint.log_length as u64);
+        visitor.visit_str(&self.checkpoint.log_root.to_string());
+        visitor.visit_str(&self.checkpoint.map_root.to_string());
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for RecordReceipt {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct MapLeaf {
     pub record_id: RecordId,
@@ -122,6 +239,98 @@ impl VisitBytes for LogLeaf {
     }
 }
 
+/// A record submitted for sequencing into the registry log.
+///
+/// Records sequenced into the same checkpoint are ordered canonically by
+/// [`canonical_order`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedRecord {
+    /// The log leaf of the record.
+    pub leaf: LogLeaf,
+    /// The previous record of the record's log, if any.
+    pub prev: Option<RecordId>,
+    /// The timestamp of the record.
+    pub timestamp: SystemTime,
+}
+
+/// Compares two submitted records by their canonical sequencing order.
+///
+/// Records are ordered by record identifier, then by timestamp, and finally
+/// by log identifier.
+pub fn compare_submitted(a: &SubmittedRecord, b: &SubmittedRecord) -> Ordering {
+    a.leaf
+        .record_id
+        .cmp(&b.leaf.record_id)
+        .then_with(|| a.timestamp.cmp(&b.timestamp))
+        .then_with(|| a.leaf.log_id.cmp(&b.leaf.log_id))
+}
+
+/// Orders records submitted for sequencing into the same checkpoint.
+///
+/// Records are sequenced in ascending order of [`compare_submitted`], except
+/// that a record is never sequenced before the submitted record it names as
+/// its previous record. Duplicate submissions are removed.
+///
+/// If the submitted records name each other as previous records in a cycle,
+/// which records of valid logs cannot as a record identifier commits to the
+/// previous record, no record may be ready to be sequenced. The least record
+/// not yet sequenced is then sequenced as if its previous record had been,
+/// and is expected to fail validation.
+///
+/// Given the same set of records, the resulting order is always the same,
+/// so independent implementations produce identical log roots.
+pub fn canonical_order(records: impl IntoIterator<Item = SubmittedRecord>) -> Vec<SubmittedRecord> {
+    let mut records = records.into_iter().collect::<Vec<_>>();
+    records.sort_by(compare_submitted);
+    records.dedup();
+
+    // As the records are sorted, the order of their indexes is the comparator order
+    let submitted = records
+        .iter()
+        .map(|r| &r.leaf.record_id)
+        .collect::<HashSet<_>>();
+    let mut waiting: HashMap<&RecordId, Vec<usize>> = HashMap::new();
+    let mut ready = BinaryHeap::new();
+    for (index, record) in records.iter().enumerate() {
+        match &record.prev {
+            Some(prev) if submitted.contains(prev) => waiting.entry(prev).or_default().push(index),
+            _ => ready.push(Reverse(index)),
+        }
+    }
+
+    let mut sequenced = vec![false; records.len()];
+    let mut order = Vec::with_capacity(records.len());
+    let mut least = 0;
+    while order.len() < records.len() {
+        // Take the least ready record, or the least record left if none is ready
+        let index = match ready.pop() {
+            Some(Reverse(index)) => index,
+            None => {
+                while sequenced[least] {
+                    least += 1;
+                }
+                least
+            }
+        };
+
+        // A record sequenced to break a cycle is made ready again by its previous record
+        if std::mem::replace(&mut sequenced[index], true) {
+            continue;
+        }
+
+        order.push(index);
+        if let Some(next) = waiting.remove(&records[index].leaf.record_id) {
+            ready.extend(next.into_iter().map(Reverse));
+        }
+    }
+
+    let mut records = records.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .into_iter()
+        .filter_map(|index| records[index].take())
+        .collect()
+}
+
 /// Represents a valid package name in the registry.
 ///
 /// Valid package names conform to the component model specification.
@@ -219,7 +428,7 @@ impl<'de> Deserialize<'de> for PackageName {
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct LogId(AnyHash);
 
@@ -267,7 +476,60 @@ impl AsRef<[u8]> for LogId {
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+/// A key of the registry's verifiable map.
+///
+/// Each kind of key is hashed with its own domain-separation prefix, so
+/// package heads, operator state, and key index entries can share a single
+/// map without colliding. Package and operator keys hash identically to
+/// their [`LogId`], keeping existing map roots and proofs valid.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MapKey(AnyHash);
+
+impl MapKey {
+    /// The map key of the head of a package log.
+    pub fn package<D: SupportedDigest>(name: &PackageName) -> Self {
+        LogId::package_log::<D>(name).into()
+    }
+
+    /// The map key of the operator log state.
+    pub fn operator<D: SupportedDigest>() -> Self {
+        LogId::operator_log::<D>().into()
+    }
+
+    /// The map key of a key index entry.
+    pub fn key<D: SupportedDigest>(id: &KeyID) -> Self {
+        let prefix: &[u8] = b"WARG-KEY-INDEX-ID-V0:".as_slice();
+        let hash: Hash<D> = Hash::of((prefix, id.as_str()));
+        Self(hash.into())
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl VisitBytes for MapKey {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        visitor.visit_bytes(self.0.bytes())
+    }
+}
+
+impl From<LogId> for MapKey {
+    fn from(id: LogId) -> Self {
+        Self(id.0)
+    }
+}
+
+impl From<MapKey> for AnyHash {
+    fn from(key: MapKey) -> Self {
+        key.0
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct RecordId(AnyHash);
 
@@ -313,6 +575,59 @@ impl AsRef<[u8]> for RecordId {
     }
 }
 
+/// Statistics aggregated over the package logs of a registry.
+#[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryStats {
+    /// The number of package logs aggregated.
+    pub packages: u64,
+    /// The total number of validated records.
+    pub records: u64,
+    /// The total number of releases, including yanked releases.
+    pub releases: u64,
+    /// The total number of yanked releases.
+    pub yanks: u64,
+    /// The total number of keys holding at least one permission in a package log.
+    ///
+    /// A key active in multiple package logs is counted once per log.
+    pub active_keys: u64,
+    /// The timestamp of the most recently validated record across all package logs.
+    #[serde(
+        default,
+        with = "crate::timestamp::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_activity: Option<SystemTime>,
+}
+
+impl RegistryStats {
+    /// Adds the statistics of a package log to the aggregate.
+    pub fn add(&mut self, stats: &LogStats) {
+        self.packages += 1;
+        self.records += stats.records;
+        self.releases += stats.releases;
+        self.yanks += stats.yanks;
+        self.active_keys += stats.active_keys;
+        self.last_activity = self.last_activity.max(stats.last_activity);
+    }
+}
+
+impl<'a> Extend<&'a LogStats> for RegistryStats {
+    fn extend<T: IntoIterator<Item = &'a LogStats>>(&mut self, iter: T) {
+        for stats in iter {
+            self.add(stats);
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a LogStats> for RegistryStats {
+    fn from_iter<T: IntoIterator<Item = &'a LogStats>>(iter: T) -> Self {
+        let mut aggregate = Self::default();
+        aggregate.extend(iter);
+        aggregate
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -329,4 +644,190 @@ mod tests {
             proof.evaluate(&LogId::operator_log::<Sha256>(), &"foobar")
         );
     }
+
+    #[test]
+    fn map_key() {
+        let name: PackageName = "test:package".parse().unwrap();
+        let id = KeyID::from("sha256:abc".to_string());
+        let keys = [
+            MapKey::package::<Sha256>(&name),
+            MapKey::operator::<Sha256>(),
+            MapKey::key::<Sha256>(&id),
+        ];
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), keys.len());
+        assert_eq!(keys[0], LogId::package_log::<Sha256>(&name).into());
+        assert_eq!(keys[1], LogId::operator_log::<Sha256>().into());
+
+        let map = Map::<Sha256, MapKey, &'static str>::default()
+            .insert(keys[0].clone(), "head")
+            .insert(keys[2].clone(), "key");
+
+        let proof = map.prove(keys[0].clone()).unwrap();
+        assert_eq!(map.root().clone(), proof.evaluate(&keys[0], &"head"));
+        assert!(map.prove(keys[1].clone()).is_none());
+
+        // Package keys produce the same map as log ids
+        let by_log_id = Map::<Sha256, LogId, &'static str>::default()
+            .insert(LogId::package_log::<Sha256>(&name), "head");
+        assert_eq!(
+            by_log_id.root(),
+            Map::<Sha256, MapKey, &'static str>::default()
+                .insert(keys[0].clone(), "head")
+                .root()
+        );
+    }
+
+    #[test]
+    fn registry_stats() {
+        let now = SystemTime::now();
+        let earlier = now - Duration::from_secs(60);
+
+        let stats = [
+            LogStats {
+                records: 3,
+                releases: 2,
+                yanks: 1,
+                active_keys: 1,
+                last_activity: Some(now),
+            },
+            LogStats {
+                records: 1,
+                releases: 0,
+                yanks: 0,
+                active_keys: 2,
+                last_activity: Some(earlier),
+            },
+        ];
+
+        assert_eq!(
+            stats.iter().collect::<RegistryStats>(),
+            RegistryStats {
+                packages: 2,
+                records: 4,
+                releases: 2,
+                yanks: 1,
+                active_keys: 3,
+                last_activity: Some(now),
+            }
+        );
+    }
+
+    #[test]
+    fn freshness_age() {
+        let now = SystemTime::now();
+        let checkpoint = Checkpoint {
+            log_root: Hash::<Sha256>::default().into(),
+            log_length: 1,
+            map_root: Hash::<Sha256>::default().into(),
+        };
+
+        let assertion = FreshnessAssertion::new(checkpoint, now - Duration::from_secs(90)).unwrap();
+        assert!(assertion.age(now) >= Duration::from_secs(89));
+        assert!(assertion.age(now) <= Duration::from_secs(91));
+        assert_eq!(
+            assertion.age(now - Duration::from_secs(120)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn canonical_record_order() {
+        let now = SystemTime::now();
+        let record = |n: u8, log: &str, prev: Option<u8>| SubmittedRecord {
+            leaf: LogLeaf {
+                log_id: LogId::from(AnyHash::from(Hash::<Sha256>::of(log))),
+                record_id: RecordId::from(AnyHash::from(Hash::<Sha256>::of(n))),
+            },
+            prev: prev.map(|p| RecordId::from(AnyHash::from(Hash::<Sha256>::of(p)))),
+            timestamp: now,
+        };
+
+        let records = (0..8u8)
+            .map(|n| record(n, "a", None))
+            .chain([
+                record(8, "b", None),
+                record(9, "b", Some(8)),
+                record(10, "b", Some(9)),
+            ])
+            .collect::<Vec<_>>();
+
+        // The order does not depend on the submission order
+        let ordered = canonical_order(records.clone());
+        let mut reversed = records.clone();
+        reversed.reverse();
+        assert_eq!(canonical_order(reversed), ordered);
+
+        // Duplicate submissions are removed
+        let mut duplicated = records.clone();
+        duplicated.extend(records.iter().take(3).cloned());
+        assert_eq!(canonical_order(duplicated), ordered);
+        assert_eq!(ordered.len(), records.len());
+
+        // Records are ordered by the comparator, but never before their previous record
+        let position = |n: usize| ordered.iter().position(|r| *r == records[n]).unwrap();
+        assert!(position(8) < position(9));
+        assert!(position(9) < position(10));
+        let unchained = ordered
+            .iter()
+            .filter(|r| r.leaf.log_id == records[0].leaf.log_id)
+            .collect::<Vec<_>>();
+        assert!(unchained
+            .windows(2)
+            .all(|pair| compare_submitted(pair[0], pair[1]) == Ordering::Less));
+    }
+
+    #[test]
+    fn canonical_record_order_breaks_cycles() {
+        let now = SystemTime::now();
+        let id = |n: u8| RecordId::from(AnyHash::from(Hash::<Sha256>::of(n)));
+        let record = |n: u8, prev: Option<u8>| SubmittedRecord {
+            leaf: LogLeaf {
+                log_id: LogId::from(AnyHash::from(Hash::<Sha256>::of("log"))),
+                record_id: id(n),
+            },
+            prev: prev.map(id),
+            timestamp: now,
+        };
+
+        // Records 0, 1, and 2 form a cycle, record 3 follows the cycle, and
+        // record 4 names itself as its previous record
+        let records = vec![
+            record(0, Some(2)),
+            record(1, Some(0)),
+            record(2, Some(1)),
+            record(3, Some(1)),
+            record(4, Some(4)),
+            record(5, None),
+        ];
+        let ordered = canonical_order(records.clone());
+        assert_eq!(ordered.len(), records.len());
+        let mut reversed = records.clone();
+        reversed.reverse();
+        assert_eq!(canonical_order(reversed), ordered);
+
+        // A record only precedes its previous record when no record was ready,
+        // and it is then the least record left
+        let submitted = |id: &RecordId| records.iter().any(|r| r.leaf.record_id == *id);
+        let mut broken = 0;
+        for (index, record) in ordered.iter().enumerate() {
+            let ready = |r: &SubmittedRecord| match &r.prev {
+                Some(prev) if submitted(prev) => {
+                    ordered[..index].iter().any(|s| s.leaf.record_id == *prev)
+                }
+                _ => true,
+            };
+            if ready(record) {
+                continue;
+            }
+
+            broken += 1;
+            let left = &ordered[index..];
+            assert!(!left.iter().any(ready));
+            assert!(left
+                .iter()
+                .all(|r| compare_submitted(record, r) != Ordering::Greater));
+        }
+        assert!(broken >= 2);
+        assert_eq!(ordered[0], records[5]);
+    }
 }
@@ -0,0 +1,345 @@
+//! Human-readable summaries of records and their entries.
+//!
+//! Entries are displayed on a single line, and records as a header line
+//! followed by an indented line for each entry, so that they can be printed
+//! by the CLI and included in error messages and server logs.
+
+use crate::{operator, package};
+use std::{
+    fmt::{self, Display},
+    time::SystemTime,
+};
+
+/// Displays a timestamp as an RFC 3339 date and time in UTC, truncated to
+/// the second.
+pub(crate) struct DisplayTimestamp(pub(crate) SystemTime);
+
+impl Display for DisplayTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ok(since_epoch) = self.0.duration_since(SystemTime::UNIX_EPOCH) else {
+            return write!(f, "before 1970-01-01T00:00:00Z");
+        };
+        let secs = since_epoch.as_secs();
+        let (days, secs) = (secs / 86400, secs % 86400);
+
+        // Converts days since the epoch to a civil date in the proleptic
+        // Gregorian calendar
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z % 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z",
+            hour = secs / 3600,
+            minute = secs / 60 % 60,
+            second = secs % 60
+        )
+    }
+}
+
+// Joins the displayed items with commas.
+fn join<T: Display>(items: impl IntoIterator<Item = T>) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Displays an optional reason for a yank.
+fn reason(reason: &Option<String>) -> String {
+    match reason {
+        Some(reason) => format!(": {reason}"),
+        None => String::new(),
+    }
+}
+
+impl Display for package::PackageEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Init {
+                hash_algorithm,
+                key,
+            } => write!(
+                f,
+                "initialize package with {hash_algorithm} and key `{key_id}`",
+                key_id = key.fingerprint()
+            ),
+            Self::GrantFlat {
+                key,
+                permissions,
+                proof,
+            } => write!(
+                f,
+                "grant ({permissions}) to `{key_id}`{proven}",
+                permissions = join(permissions),
+                key_id = key.fingerprint(),
+                proven = if proof.is_some() {
+                    " with proof of possession"
+                } else {
+                    ""
+                }
+            ),
+            Self::RevokeFlat {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "revoke ({permissions}) from `{key_id}`",
+                permissions = join(permissions)
+            ),
+            Self::Release {
+                version,
+                content,
+                encryption,
+                manifest,
+            } => {
+                write!(f, "release {version} with content digest `{content}`")?;
+                if let Some(encryption) = encryption {
+                    write!(
+                        f,
+                        " (encrypted for {count} recipient(s))",
+                        count = encryption.recipients.len()
+                    )?;
+                }
+                if let Some(manifest) = manifest {
+                    write!(
+                        f,
+                        " and artifact(s) {names}",
+                        names = join(manifest.artifacts.iter().map(|a| format!("`{}`", a.name)))
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Yank { version, reason: r } => write!(f, "yank {version}{}", reason(r)),
+            Self::YankRange { range, reason: r } => {
+                write!(f, "yank versions matching `{range}`{}", reason(r))
+            }
+            Self::Tag { tag, version } => write!(f, "tag {version} as `{tag}`"),
+            Self::RequireReviewers {
+                threshold: 0,
+                reviewers: _,
+            } => write!(f, "remove the reviewer requirement"),
+            Self::RequireReviewers {
+                threshold,
+                reviewers,
+            } => write!(
+                f,
+                "require {threshold} of reviewer(s) {keys}",
+                keys = join(
+                    reviewers
+                        .iter()
+                        .map(|key| format!("`{}`", key.fingerprint()))
+                )
+            ),
+        }
+    }
+}
+
+impl Display for package::PackageRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "package record (protocol version {version}) created at {timestamp}",
+            version = self.version,
+            timestamp = DisplayTimestamp(self.timestamp)
+        )?;
+        match &self.prev {
+            Some(prev) => write!(f, "\n  previous record: `{prev}`")?,
+            None => write!(f, "\n  first record of the log")?,
+        }
+        for (index, entry) in self.entries.iter().enumerate() {
+            write!(f, "\n  entry {index}: {entry}")?;
+            if let Some(signature) = self.entry_signature(index) {
+                write!(f, " (signed by `{key_id}`)", key_id = signature.key_id)?;
+            }
+        }
+        if let Some(token) = &self.publish_token {
+            write!(
+                f,
+                "\n  published with a token issued by `{issuer}` to `{key_id}`, expiring at {expires}",
+                issuer = token.issuer,
+                key_id = token.key.fingerprint(),
+                expires = DisplayTimestamp(token.expires)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for operator::OperatorEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Init {
+                hash_algorithm,
+                key,
+            } => write!(
+                f,
+                "initialize operator log with {hash_algorithm} and key `{key_id}`",
+                key_id = key.fingerprint()
+            ),
+            Self::GrantFlat { key, permissions } => write!(
+                f,
+                "grant ({permissions}) to `{key_id}`",
+                permissions = join(permissions),
+                key_id = key.fingerprint()
+            ),
+            Self::RevokeFlat {
+                key_id,
+                permissions,
+            } => write!(
+                f,
+                "revoke ({permissions}) from `{key_id}`",
+                permissions = join(permissions)
+            ),
+            Self::DefineNamespace { namespace } => write!(f, "define namespace `{namespace}`"),
+            Self::ImportNamespace {
+                namespace,
+                registry,
+            } => write!(
+                f,
+                "import namespace `{namespace}` from registry `{registry}`"
+            ),
+            Self::DenyKey { key_id, log_length } => write!(
+                f,
+                "deny key `{key_id}` as of registry log length {log_length}"
+            ),
+            Self::Migrate(migration) => write!(
+                f,
+                "migrate namespace(s) {namespaces} from registry `{registry}` as of log length {log_length}",
+                namespaces = join(migration.namespaces.iter().map(|n| format!("`{n}`"))),
+                registry = migration.registry,
+                log_length = migration.checkpoint.log_length
+            ),
+            Self::Admin(command) => write!(f, "{command}"),
+            Self::Advisory(advisory) => write!(
+                f,
+                "advisory `{id}` for versions `{affected}` of package `{package}`: {summary}",
+                id = advisory.id,
+                affected = advisory.affected,
+                package = advisory.package,
+                summary = advisory.summary
+            ),
+            Self::IdentityClaim(claim) => write!(
+                f,
+                "claim identity `{identity}` for `{key_id}`",
+                identity = claim.identity,
+                key_id = claim.key.fingerprint()
+            ),
+        }
+    }
+}
+
+impl Display for operator::OperatorRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "operator record (protocol version {version}) created at {timestamp}",
+            version = self.version,
+            timestamp = DisplayTimestamp(self.timestamp)
+        )?;
+        match &self.prev {
+            Some(prev) => write!(f, "\n  previous record: `{prev}`")?,
+            None => write!(f, "\n  first record of the log")?,
+        }
+        for (index, entry) in self.entries.iter().enumerate() {
+            write!(f, "\n  entry {index}: {entry}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        operator::{OperatorEntry, OperatorRecord},
+        package::{PackageEntry, PackageRecord, Permission},
+    };
+    use std::time::Duration;
+    use warg_crypto::{
+        hash::{HashAlgorithm, Sha256},
+        signing::generate_p256_pair,
+    };
+
+    #[test]
+    fn test_display_timestamp() {
+        let at = |secs| DisplayTimestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400).to_string(), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_709_251_199).to_string(), "2024-02-29T23:59:59Z");
+        assert_eq!(at(4_102_444_800).to_string(), "2100-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_display_records() {
+        let (public_key, _) = generate_p256_pair();
+        let key_id = public_key.fingerprint();
+        let content = warg_crypto::hash::Hash::<Sha256>::of("content").into();
+        let record = PackageRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: public_key.clone(),
+                },
+                PackageEntry::GrantFlat {
+                    key: public_key.clone(),
+                    permissions: vec![Permission::Release, Permission::Yank],
+                    proof: None,
+                },
+                PackageEntry::release("1.0.0", content).unwrap(),
+                PackageEntry::Yank {
+                    version: "1.0.0".parse().unwrap(),
+                    reason: Some("broken".to_string()),
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        };
+        assert_eq!(
+            record.to_string(),
+            format!(
+                "package record (protocol version 0) created at 2023-11-14T22:13:20Z\n  \
+                 first record of the log\n  \
+                 entry 0: initialize package with sha256 and key `{key_id}`\n  \
+                 entry 1: grant (release, yank) to `{key_id}`\n  \
+                 entry 2: release 1.0.0 with content digest `{content}`\n  \
+                 entry 3: yank 1.0.0: broken"
+            )
+        );
+
+        let record = OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::UNIX_EPOCH,
+            entries: vec![
+                OperatorEntry::DefineNamespace {
+                    namespace: "example".to_string(),
+                },
+                OperatorEntry::DenyKey {
+                    key_id: key_id.clone(),
+                    log_length: 7,
+                },
+            ],
+        };
+        assert_eq!(
+            record.to_string(),
+            format!(
+                "operator record (protocol version 0) created at 1970-01-01T00:00:00Z\n  \
+                 first record of the log\n  \
+                 entry 0: define namespace `example`\n  \
+                 entry 1: deny key `{key_id}` as of registry log length 7"
+            )
+        );
+    }
+}
@@ -29,15 +29,35 @@ impl<Contents> SerdeEnvelope<Contents> {
     }
 
     /// Create an envelope for some contents using a signature.
-    pub fn signed_contents(
-        private_key: &signing::PrivateKey,
+    pub fn signed_contents<S>(
+        signer: &S,
         contents: Contents,
     ) -> Result<Self, signing::SignatureError>
     where
+        S: signing::Signer + ?Sized,
         Contents: Signable,
     {
-        let key_id = private_key.public_key().fingerprint();
-        let signature = contents.sign(private_key)?;
+        let key_id = signer.public_key().fingerprint();
+        let signature = contents.sign(signer)?;
+        Ok(SerdeEnvelope {
+            contents,
+            key_id,
+            signature,
+        })
+    }
+
+    /// Create an envelope for some contents using a signature made by a
+    /// signer that may need to wait on I/O.
+    pub async fn signed_contents_async<S>(
+        signer: &S,
+        contents: Contents,
+    ) -> Result<Self, signing::SignatureError>
+    where
+        S: signing::AsyncSigner + ?Sized,
+        Contents: Signable,
+    {
+        let key_id = signer.public_key().await?.fingerprint();
+        let signature = signer.sign(&contents.signing_message()).await?;
         Ok(SerdeEnvelope {
             contents,
             key_id,
@@ -56,6 +76,30 @@ impl<Contents> SerdeEnvelope<Contents> {
     pub fn signature(&self) -> &signing::Signature {
         &self.signature
     }
+
+    /// Get the CBOR representation of the envelope.
+    ///
+    /// Unlike a [`ProtoEnvelope`](crate::ProtoEnvelope), the signature is
+    /// over the canonical encoding of the contents rather than the serialized
+    /// bytes, so the contents may be verified in any representation.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Vec<u8>
+    where
+        Contents: Serialize,
+    {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("writing to a vector cannot fail");
+        bytes
+    }
+
+    /// Create an envelope from its CBOR representation.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, crate::ParseEnvelopeError>
+    where
+        Contents: serde::de::DeserializeOwned,
+    {
+        Ok(ciborium::from_reader(bytes)?)
+    }
 }
 
 impl<Content> AsRef<Content> for SerdeEnvelope<Content> {
@@ -0,0 +1,188 @@
+//! Signed exports of validated log state.
+//!
+//! An auditor that validated a log up to some record can export a signed
+//! [`StateExport`] stating the record it stopped at and a hash of the
+//! resulting state. Another party that validates the same records can then
+//! verify the export against its own state with
+//! [`Validator::verify_export`](crate::Validator::verify_export), and the
+//! auditor can later resume from the exported position knowing the state it
+//! vouched for.
+
+use crate::{registry::RecordId, SerdeEnvelope, Validator};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use thiserror::Error;
+use warg_crypto::{
+    hash::{AnyHash, Hash, Sha256},
+    prefix::{self, VisitPrefixEncode},
+    signing, ByteVisitor, Encode, Signable, VisitBytes,
+};
+
+const STATE_HASH_PREFIX: &[u8] = b"WARG-VALIDATED-STATE-V1:";
+
+/// Represents an error exporting or verifying validated state.
+#[derive(Debug, Error)]
+pub enum StateExportError {
+    /// The export time is before the Unix epoch.
+    #[error("the export time is before the Unix epoch")]
+    InvalidTime,
+    /// The export could not be signed.
+    #[error("failed to sign state export: {0}")]
+    Signing(#[from] signing::SignatureError),
+    /// The signature of the export is invalid.
+    #[error("the signature of the state export is invalid")]
+    InvalidSignature,
+    /// The export was made at a different position in the log.
+    #[error("the state export is of head {exported:?} but the state is at head {actual:?}")]
+    HeadMismatch {
+        /// The head of the export.
+        exported: Option<RecordId>,
+        /// The head of the state.
+        actual: Option<RecordId>,
+    },
+    /// The exported state differs from the state.
+    #[error("the exported state hash `{exported}` does not match the state hash `{actual}`")]
+    StateMismatch {
+        /// The state hash of the export.
+        exported: AnyHash,
+        /// The hash of the state.
+        actual: AnyHash,
+    },
+}
+
+/// A statement that a log was validated up to a record, resulting in the
+/// state with the given hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateExport {
+    /// The last record validated, or `None` if no records were validated.
+    pub head: Option<RecordId>,
+    /// The hash of the validated state.
+    pub state: AnyHash,
+    /// When the state was exported, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl StateExport {
+    /// Creates an export of the given validated state at the given time.
+    pub fn new<V>(state: &V, time: SystemTime) -> Result<Self, StateExportError>
+    where
+        V: Validator + VisitPrefixEncode,
+    {
+        Ok(Self {
+            head: state.head_record_id().cloned(),
+            state: hash_state(state),
+            timestamp: time
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|_| StateExportError::InvalidTime)?
+                .as_secs(),
+        })
+    }
+
+    /// Verifies the signature of the given export with the given key.
+    pub fn verify_signed(
+        export: &SerdeEnvelope<Self>,
+        public_key: &signing::PublicKey,
+    ) -> Result<(), StateExportError> {
+        if &public_key.fingerprint() != export.key_id() {
+            return Err(StateExportError::InvalidSignature);
+        }
+
+        Self::verify(public_key, &export.as_ref().encode(), export.signature())
+            .map_err(|_| StateExportError::InvalidSignature)
+    }
+
+    /// Checks that the export is of the given validated state.
+    pub fn check<V>(&self, state: &V) -> Result<(), StateExportError>
+    where
+        V: Validator + VisitPrefixEncode,
+    {
+        let head = state.head_record_id();
+        if self.head.as_ref() != head {
+            return Err(StateExportError::HeadMismatch {
+                exported: self.head.clone(),
+                actual: head.cloned(),
+            });
+        }
+
+        let actual = hash_state(state);
+        if self.state != actual {
+            return Err(StateExportError::StateMismatch {
+                exported: self.state,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes the canonical encoding of the given state.
+///
+/// The canonical encoding of a state is its prefix encoding, which visits
+/// the contents of the state in a fixed order; the maps and sets of the
+/// states are ordered by when their entries were validated.
+fn hash_state<V: VisitPrefixEncode>(state: &V) -> AnyHash {
+    struct Canonical<'a, V>(&'a V);
+
+    impl<V: VisitPrefixEncode> VisitBytes for Canonical<'_, V> {
+        fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+            self.0.visit_bv(visitor);
+        }
+    }
+
+    Hash::<Sha256>::of((STATE_HASH_PREFIX, Canonical(state))).into()
+}
+
+/// Visits a time as seconds and nanoseconds since the Unix epoch.
+pub(crate) fn visit_time<BV: ?Sized + ByteVisitor>(
+    visitor: &mut prefix::PrefixEncodeVisitor<BV>,
+    time: SystemTime,
+) {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    visitor.visit_unsigned(since_epoch.as_secs());
+    visitor.visit_unsigned(since_epoch.subsec_nanos().into());
+}
+
+/// Visits an optional value, preceded by whether it is present.
+pub(crate) fn visit_option<BV: ?Sized + ByteVisitor, T>(
+    visitor: &mut prefix::PrefixEncodeVisitor<BV>,
+    value: Option<&T>,
+    visit: impl FnOnce(&mut prefix::PrefixEncodeVisitor<BV>, &T),
+) {
+    match value {
+        Some(value) => {
+            visitor.visit_unsigned(1);
+            visit(visitor, value);
+        }
+        None => visitor.visit_unsigned(0),
+    }
+}
+
+impl Signable for StateExport {
+    const PREFIX: &'static [u8] = b"WARG-STATE-EXPORT-SIGNATURE-V0";
+}
+
+impl prefix::VisitPrefixEncode for StateExport {
+    fn visit_pe<BV: ?Sized + ByteVisitor>(&self, visitor: &mut prefix::PrefixEncodeVisitor<BV>) {
+        visitor.visit_str_raw("WARG-STATE-EXPORT-V0");
+        visitor.visit_str(
+            &self
+                .head
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        );
+        visitor.visit_str(&self.state.to_string());
+        visitor.visit_unsigned(self.timestamp);
+    }
+}
+
+// Manual impls of VisitBytes for VisitPrefixEncode to avoid conflict with blanket impls
+impl VisitBytes for StateExport {
+    fn visit<BV: ?Sized + ByteVisitor>(&self, visitor: &mut BV) {
+        self.visit_bv(visitor);
+    }
+}
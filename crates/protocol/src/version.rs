@@ -0,0 +1,193 @@
+//! A `Copy` representation of package versions.
+
+use crate::intern::{Handle, Interner, InternerFull};
+use semver::Version;
+use std::{
+    cmp::Ordering,
+    fmt,
+    sync::{OnceLock, RwLock},
+};
+
+/// The versions with pre-release or build identifiers packed so far.
+///
+/// The table is shared by the process and never shrinks, so that a packed
+/// version remains valid for as long as the process runs.
+fn interned() -> &'static RwLock<Interner<Version>> {
+    static INTERNED: OnceLock<RwLock<Interner<Version>>> = OnceLock::new();
+    INTERNED.get_or_init(Default::default)
+}
+
+/// A package version that is `Copy`.
+///
+/// A version without pre-release or build identifiers is stored inline and
+/// can be constructed in const contexts. Any other version is interned in a
+/// table shared by the process and stored as a handle to it; interned
+/// versions are never freed, so packing untrusted versions in a long-running
+/// process should be bounded by the caller.
+///
+/// Packed versions are equal and hash alike exactly when their versions are
+/// equal, and are ordered as their versions are, so they can be used as map
+/// keys by resolvers:
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use warg_protocol::{PackedVersion, Version};
+///
+/// const MINIMUM: PackedVersion = PackedVersion::new(1, 0, 0);
+///
+/// let rc = PackedVersion::pack(&Version::parse("1.0.0-rc.1").unwrap()).unwrap();
+/// let mut releases = BTreeMap::new();
+/// releases.insert(rc, "rc");
+/// releases.insert(MINIMUM, "stable");
+/// assert_eq!(releases.keys().next_back(), Some(&MINIMUM));
+/// assert_eq!(rc.unpack().to_string(), "1.0.0-rc.1");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedVersion(Packed);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Packed {
+    Inline { major: u64, minor: u64, patch: u64 },
+    Interned(Handle<Version>),
+}
+
+impl PackedVersion {
+    /// Creates a version without pre-release or build identifiers.
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self(Packed::Inline {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Packs the given version.
+    ///
+    /// Returns an error if the version must be interned and the table of
+    /// interned versions is full.
+    pub fn pack(version: &Version) -> Result<Self, InternerFull> {
+        if version.pre.is_empty() && version.build.is_empty() {
+            return Ok(Self::new(version.major, version.minor, version.patch));
+        }
+
+        if let Some(handle) = interned().read().unwrap().get(version) {
+            return Ok(Self(Packed::Interned(handle)));
+        }
+
+        let handle = interned().write().unwrap().intern(version.clone())?;
+        Ok(Self(Packed::Interned(handle)))
+    }
+
+    /// Unpacks the version.
+    pub fn unpack(self) -> Version {
+        match self.0 {
+            Packed::Inline {
+                major,
+                minor,
+                patch,
+            } => Version::new(major, minor, patch),
+            Packed::Interned(handle) => interned()
+                .read()
+                .unwrap()
+                .value(handle)
+                .expect("packed versions are never removed")
+                .clone(),
+        }
+    }
+}
+
+impl TryFrom<&Version> for PackedVersion {
+    type Error = InternerFull;
+
+    fn try_from(version: &Version) -> Result<Self, Self::Error> {
+        Self::pack(version)
+    }
+}
+
+impl From<PackedVersion> for Version {
+    fn from(version: PackedVersion) -> Self {
+        version.unpack()
+    }
+}
+
+impl PartialOrd for PackedVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PackedVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.0, other.0) {
+            (
+                Packed::Inline {
+                    major,
+                    minor,
+                    patch,
+                },
+                Packed::Inline {
+                    major: other_major,
+                    minor: other_minor,
+                    patch: other_patch,
+                },
+            ) => (major, minor, patch).cmp(&(other_major, other_minor, other_patch)),
+            _ => self.unpack().cmp(&other.unpack()),
+        }
+    }
+}
+
+impl fmt::Display for PackedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.unpack().fmt(f)
+    }
+}
+
+impl fmt::Debug for PackedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PackedVersion({self})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn it_packs_versions() {
+        let versions = [
+            "0.1.0",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.0+a",
+            "1.0.0+b",
+            "1.2.3",
+            "2.0.0-beta",
+        ]
+        .map(|v| Version::parse(v).unwrap());
+
+        let packed = versions
+            .iter()
+            .map(|v| PackedVersion::pack(v).unwrap())
+            .collect::<Vec<_>>();
+        for (version, packed) in versions.iter().zip(&packed) {
+            assert_eq!(&packed.unpack(), version);
+            assert_eq!(packed.to_string(), version.to_string());
+            assert_eq!(PackedVersion::pack(version).unwrap(), *packed);
+        }
+
+        // Packed versions are ordered as their versions are
+        for (a, pa) in versions.iter().zip(&packed) {
+            for (b, pb) in versions.iter().zip(&packed) {
+                assert_eq!(pa.cmp(pb), a.cmp(b), "{a} and {b}");
+                assert_eq!(pa == pb, a == b);
+            }
+        }
+
+        // Versions without identifiers are stored inline
+        assert_eq!(packed[4], PackedVersion::new(1, 0, 0));
+        assert_eq!(packed.iter().collect::<HashSet<_>>().len(), versions.len());
+    }
+}
@@ -0,0 +1,122 @@
+//! Helpers for detecting changes to the wire format.
+//!
+//! The protobuf encodings of records, envelopes, and proofs are signed and
+//! hashed, so an accidental change to the wire format, such as renumbering a
+//! field, silently invalidates every existing log. Snapshot tests encode fixed
+//! values with [`assert_wire_stable!`](crate::assert_wire_stable) and compare
+//! the bytes to a snapshot checked into the repository.
+//!
+//! Set the `BLESS` environment variable to a non-empty value to update the
+//! snapshots to the current encodings instead of comparing them; review the
+//! resulting changes before committing them.
+
+use std::{fmt::Write, fs, path::Path};
+
+/// The number of bytes on each line of a snapshot.
+const BYTES_PER_LINE: usize = 32;
+
+/// Asserts that the given encoded bytes match the snapshot with the given
+/// name.
+///
+/// Snapshots are stored in the `tests/snapshots` directory of the crate
+/// invoking the macro as `<name>.snap`.
+///
+/// # Example
+///
+/// ```ignore
+/// warg_protocol::assert_wire_stable!("package-record", record.encode());
+/// ```
+#[macro_export]
+macro_rules! assert_wire_stable {
+    ($name:expr, $bytes:expr $(,)?) => {
+        $crate::wire::assert_snapshot(
+            ::std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots")),
+            $name,
+            ::std::convert::AsRef::<[u8]>::as_ref(&$bytes),
+        )
+    };
+}
+
+/// Asserts that the given encoded bytes match the snapshot with the given
+/// name in the given directory.
+///
+/// If the `BLESS` environment variable is set, the snapshot is written
+/// instead.
+///
+/// This is the implementation of [`assert_wire_stable!`](crate::assert_wire_stable).
+///
+/// # Panics
+///
+/// Panics if the snapshot does not exist or does not match.
+pub fn assert_snapshot(dir: &Path, name: &str, bytes: &[u8]) {
+    let path = dir.join(format!("{name}.snap"));
+    let actual = format_snapshot(bytes);
+
+    if std::env::var_os("BLESS").is_some_and(|v| !v.is_empty()) {
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, &actual))
+            .unwrap_or_else(|e| {
+                panic!(
+                    "failed to write snapshot `{path}`: {e}",
+                    path = path.display()
+                )
+            });
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read snapshot `{path}` (set `BLESS=1` to create it): {e}",
+            path = path.display()
+        )
+    });
+
+    if expected != actual {
+        panic!(
+            "the wire encoding of `{name}` does not match snapshot `{path}`\n\
+             \n\
+             expected:\n{expected}\n\
+             actual:\n{actual}\n\
+             if the change is intentional, set `BLESS=1` to update the snapshot",
+            path = path.display()
+        );
+    }
+}
+
+/// Formats bytes as lines of lowercase hex.
+fn format_snapshot(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2 + bytes.len() / BYTES_PER_LINE + 1);
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        for b in line {
+            write!(s, "{b:02x}").unwrap();
+        }
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_snapshots() {
+        assert_eq!(format_snapshot(&[]), "");
+        assert_eq!(format_snapshot(&[0x0a, 0xff]), "0aff\n");
+        let s = format_snapshot(&[0xab; BYTES_PER_LINE + 1]);
+        assert_eq!(s, format!("{}\nab\n", "ab".repeat(BYTES_PER_LINE)));
+    }
+
+    #[test]
+    fn compares_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("value.snap"), "0102\n").unwrap();
+        assert_snapshot(dir.path(), "value", &[1, 2]);
+
+        let result = std::panic::catch_unwind(|| assert_snapshot(dir.path(), "value", &[1, 3]));
+        assert!(result.is_err());
+
+        let result = std::panic::catch_unwind(|| assert_snapshot(dir.path(), "missing", &[1]));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! Snapshot tests of the protobuf encoding of every wire message.
+//!
+//! See the `wire` module for how to update the snapshots.
+
+use prost::Message;
+use std::time::{Duration, SystemTime};
+use warg_crypto::{
+    encryption::{ContentEncryption, WrappedKey},
+    hash::{AnyHash, HashAlgorithm, Sha256},
+    signing, Encode,
+};
+use warg_protobuf::protocol as protobuf;
+use warg_protocol::{
+    assert_wire_stable,
+    operator::{
+        Advisory, IdentityClaim, Migration, OperatorEntry, OperatorRecord,
+        Permission as OperatorPermission,
+    },
+    package::{PackageEntry, PackageRecord, Permission, PublishToken},
+    registry::{Checkpoint, LogId, LogLeaf, MapLeaf, PackageName, RecordId},
+    Countersignature, ProtoEnvelope, ProtoEnvelopeBody,
+};
+use warg_transparency::{
+    log::{LogBuilder, LogData, LogProofBundle, VecLog},
+    map::{Map, MapProofBundle},
+};
+
+// The test keys described in `tests/README.md`
+const ALICE: &str = "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=";
+const BOB: &str = "ecdsa-p256:2CV1EpLaSYEn4In4OAEDAj5O4Hzu8AFAxgHXuG310Ew=";
+
+fn key(key: &str) -> signing::PrivateKey {
+    signing::PrivateKey::decode(key.to_string()).unwrap()
+}
+
+fn time(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::new(secs, 123_456_789)
+}
+
+fn hash(content: &str) -> AnyHash {
+    HashAlgorithm::Sha256.digest(content.as_bytes())
+}
+
+fn record_id(content: &str) -> RecordId {
+    RecordId::from(hash(content))
+}
+
+fn operator_record() -> OperatorRecord {
+    let alice = key(ALICE);
+    let bob = key(BOB);
+    OperatorRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![
+            OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice.public_key(),
+            },
+            OperatorEntry::GrantFlat {
+                key: bob.public_key(),
+                permissions: vec![
+                    OperatorPermission::Commit,
+                    OperatorPermission::DefineNamespace,
+                    OperatorPermission::ImportNamespace,
+                ],
+            },
+            OperatorEntry::RevokeFlat {
+                key_id: bob.public_key().fingerprint(),
+                permissions: vec![OperatorPermission::Commit],
+            },
+            OperatorEntry::DefineNamespace {
+                namespace: "example".to_string(),
+            },
+            OperatorEntry::ImportNamespace {
+                namespace: "imported".to_string(),
+                registry: "registry.example.com".to_string(),
+            },
+            OperatorEntry::DenyKey {
+                key_id: bob.public_key().fingerprint(),
+                log_length: 42,
+            },
+        ],
+    }
+}
+
+fn package_record() -> PackageRecord {
+    let alice = key(ALICE);
+    let bob = key(BOB);
+    let mut record = PackageRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![
+            PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice.public_key(),
+            },
+            PackageEntry::GrantFlat {
+                key: bob.public_key(),
+                permissions: vec![Permission::Release, Permission::Yank],
+                proof: Some(bob.sign(b"proof").unwrap()),
+            },
+            PackageEntry::RevokeFlat {
+                key_id: bob.public_key().fingerprint(),
+                permissions: vec![Permission::Yank],
+            },
+            PackageEntry::release("1.0.0", hash("content")).unwrap(),
+            PackageEntry::encrypted_release(
+                "1.1.0-beta.1+build",
+                hash("encrypted content"),
+                ContentEncryption {
+                    recipients: vec![WrappedKey {
+                        key_id: alice.public_key().fingerprint(),
+                        encapsulated_key: (0..65).collect(),
+                        wrapped_key: (0..80).collect(),
+                    }],
+                },
+            )
+            .unwrap(),
+            PackageEntry::yank("1.0.0", Some("broken".to_string())).unwrap(),
+            PackageEntry::yank("1.1.0-beta.1+build", None).unwrap(),
+        ],
+        entry_signatures: Vec::new(),
+        publish_token: None,
+    };
+    record.sign_entry(1, &alice).unwrap();
+    record
+}
+
+#[test]
+fn operator_record_is_wire_stable() {
+    assert_wire_stable!("operator-record", operator_record().encode());
+}
+
+#[test]
+fn operator_migration_is_wire_stable() {
+    let alice = key(ALICE);
+    let migration = Migration::sign(
+        &key(BOB),
+        &alice.public_key().fingerprint(),
+        "registry.example.com",
+        Checkpoint {
+            log_root: hash("log"),
+            log_length: 42,
+            map_root: hash("map"),
+        },
+        ["example".to_string(), "other".to_string()],
+    )
+    .unwrap();
+
+    let record = OperatorRecord {
+        prev: None,
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![
+            OperatorEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: alice.public_key(),
+            },
+            OperatorEntry::Migrate(migration),
+        ],
+    };
+    assert_wire_stable!("operator-record-migration", record.encode());
+}
+
+#[test]
+fn operator_advisory_is_wire_stable() {
+    let record = OperatorRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![OperatorEntry::Advisory(Advisory {
+            id: "CVE-2024-0001".to_string(),
+            package: PackageName::new("example:foo").unwrap(),
+            affected: ">=1.0.0, <1.2.3".parse().unwrap(),
+            summary: "a vulnerability".to_string(),
+        })],
+    };
+    assert_wire_stable!("operator-record-advisory", record.encode());
+}
+
+#[test]
+fn operator_identity_claim_is_wire_stable() {
+    let record = OperatorRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![OperatorEntry::IdentityClaim(
+            IdentityClaim::sign(&key(BOB), "bob@example.com").unwrap(),
+        )],
+    };
+    assert_wire_stable!("operator-record-identity-claim", record.encode());
+}
+
+#[test]
+fn package_record_is_wire_stable() {
+    assert_wire_stable!("package-record", package_record().encode());
+}
+
+#[test]
+fn required_reviewers_are_wire_stable() {
+    let record = PackageRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![PackageEntry::require_reviewers(
+            1,
+            [key(ALICE).public_key(), key(BOB).public_key()],
+        )
+        .unwrap()],
+        entry_signatures: Vec::new(),
+        publish_token: None,
+    };
+    assert_wire_stable!("package-record-required-reviewers", record.encode());
+}
+
+#[test]
+fn publish_token_is_wire_stable() {
+    let token = PublishToken::issue(
+        &key(ALICE),
+        key(BOB).public_key(),
+        [
+            PackageName::new("example:foo").unwrap(),
+            PackageName::new("example:bar").unwrap(),
+        ],
+        time(1_700_003_600),
+    )
+    .unwrap();
+
+    let record = PackageRecord {
+        prev: Some(record_id("prev")),
+        version: 0,
+        timestamp: time(1_700_000_000),
+        entries: vec![PackageEntry::release("2.0.0", hash("content")).unwrap()],
+        entry_signatures: Vec::new(),
+        publish_token: Some(token),
+    };
+    assert_wire_stable!("package-record-publish-token", record.encode());
+}
+
+#[test]
+fn envelope_is_wire_stable() {
+    let envelope = ProtoEnvelope::signed_contents(&key(ALICE), package_record()).unwrap();
+    assert_wire_stable!("envelope", envelope.to_protobuf());
+
+    let countersignature = Countersignature::sign(&key(BOB), envelope.content_bytes()).unwrap();
+    let envelope = envelope.with_countersignature(countersignature);
+    assert_wire_stable!("envelope-countersigned", envelope.to_protobuf());
+
+    let envelope = envelope.cosign(&key(BOB)).unwrap();
+    assert_wire_stable!("envelope-cosigned", envelope.to_protobuf());
+}
+
+#[test]
+fn fetch_logs_frames_are_wire_stable() {
+    let envelope = ProtoEnvelope::signed_contents(&key(ALICE), package_record()).unwrap();
+    let name = PackageName::new("example:foo").unwrap();
+
+    let frame = protobuf::FetchLogsFrame {
+        contents: Some(protobuf::fetch_logs_frame::Contents::Record(
+            protobuf::FetchedRecord {
+                log_id: Some(LogId::package_log::<Sha256>(&name).to_string()),
+                envelope: ProtoEnvelopeBody::from(envelope).to_protobuf(),
+                registry_index: 7,
+                fetch_token: record_id("fetch token").to_string(),
+            },
+        )),
+    };
+    assert_wire_stable!("fetch-logs-frame-record", frame.encode_to_vec());
+
+    let frame = protobuf::FetchLogsFrame {
+        contents: Some(protobuf::fetch_logs_frame::Contents::End(
+            protobuf::FetchLogsEnd {
+                more: true,
+                warnings: vec!["warning".to_string()],
+            },
+        )),
+    };
+    assert_wire_stable!("fetch-logs-frame-end", frame.encode_to_vec());
+
+    let frame = protobuf::FetchLogsFrame {
+        contents: Some(protobuf::fetch_logs_frame::Contents::Error(
+            protobuf::FetchLogsError {
+                error: r#"{"status":404,"type":"log","id":"sha256:00"}"#.to_string(),
+            },
+        )),
+    };
+    assert_wire_stable!("fetch-logs-frame-error", frame.encode_to_vec());
+}
+
+fn log_leaf(i: usize) -> LogLeaf {
+    LogLeaf {
+        log_id: LogId::package_log::<Sha256>(
+            &PackageName::new(format!("example:package{i}")).unwrap(),
+        ),
+        record_id: record_id(&format!("record {i}")),
+    }
+}
+
+#[test]
+fn log_proof_bundle_is_wire_stable() {
+    let mut log = VecLog::<Sha256, LogLeaf>::default();
+    let nodes = (0..10).map(|i| log.push(&log_leaf(i))).collect::<Vec<_>>();
+    let length = log.checkpoint().length();
+
+    let bundle = LogProofBundle::bundle(
+        vec![log.prove_consistency(3, length)],
+        vec![
+            log.prove_inclusion(nodes[2], length),
+            log.prove_inclusion(nodes[7], length),
+        ],
+        &log,
+    )
+    .unwrap();
+    assert_wire_stable!("log-proof-bundle", bundle.encode());
+}
+
+#[test]
+fn map_proof_bundle_is_wire_stable() {
+    let mut map = Map::<Sha256, LogId, MapLeaf>::default();
+    for i in 0..10 {
+        let leaf = log_leaf(i);
+        map = map.insert(
+            leaf.log_id,
+            MapLeaf {
+                record_id: leaf.record_id,
+            },
+        );
+    }
+
+    let bundle = MapProofBundle::bundle(vec![
+        map.prove(log_leaf(2).log_id).unwrap(),
+        map.prove(log_leaf(7).log_id).unwrap(),
+    ]);
+    assert_wire_stable!("map-proof-bundle", bundle.encode());
+}
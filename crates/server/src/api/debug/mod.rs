@@ -132,7 +132,9 @@ async fn get_package_info(
                             key: Some(key.to_string()),
                             ..Default::default()
                         },
-                        GrantFlat { key, permissions } => EntryInfo {
+                        GrantFlat {
+                            key, permissions, ..
+                        } => EntryInfo {
                             kind: "grant",
                             key: Some(key.to_string()),
                             permissions: permissions.clone(),
@@ -147,13 +149,15 @@ async fn get_package_info(
                             permissions: permissions.clone(),
                             ..Default::default()
                         },
-                        Release { version, content } => EntryInfo {
+                        Release {
+                            version, content, ..
+                        } => EntryInfo {
                             kind: "release",
                             version: Some(version.clone()),
                             content: Some(content.clone()),
                             ..Default::default()
                         },
-                        Yank { version } => EntryInfo {
+                        Yank { version, .. } => EntryInfo {
                             kind: "yank",
                             version: Some(version.clone()),
                             ..Default::default()
@@ -173,7 +177,7 @@ async fn get_package_info(
         })
         .collect::<Result<_, DebugError>>()?;
 
-    let releases = package_state.releases().cloned().collect();
+    let releases = package_state.stored_releases().collect();
 
     Ok(Json(PackageInfo {
         package_name,
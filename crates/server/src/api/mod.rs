@@ -1,8 +1,17 @@
 use crate::{
-    policy::{content::ContentPolicy, record::RecordPolicy},
-    services::CoreService,
+    auth::{Access, AuthError, Authenticator},
+    content::ContentFiles,
+    policy::{content::ContentPolicy, record::RecordPolicy, staging::StagingPolicy},
+    services::{CoreService, KeyIndex, Quarantine, SearchIndex},
+};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header::WWW_AUTHENTICATE, HeaderValue, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
 };
-use axum::{body::Body, http::Request, Router};
 use std::{path::PathBuf, sync::Arc};
 use tower::ServiceBuilder;
 use tower_http::{
@@ -13,59 +22,134 @@ use tower_http::{
 };
 use tracing::{Level, Span};
 use url::Url;
+use warg_crypto::signing::PrivateKey;
+use warg_protocol::{
+    discovery::OperatorKeys, package::YankPolicy, policy::TimeWindow, SerdeEnvelope,
+};
 
 pub mod v1;
+pub mod well_known;
 
 #[cfg(feature = "debug")]
 pub mod debug;
 
 /// Creates the router for the API.
+#[allow(clippy::too_many_arguments)]
 pub fn create_router(
     content_base_url: Url,
     core: CoreService,
     temp_dir: PathBuf,
-    files_dir: PathBuf,
+    files: ContentFiles,
     content_policy: Option<Arc<dyn ContentPolicy>>,
+    allow_encrypted_content: bool,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    staging_policy: Option<Arc<StagingPolicy>>,
+    yank_policy: Option<Arc<YankPolicy>>,
+    time_window: TimeWindow,
+    search_index: Option<SearchIndex>,
+    key_index: Option<KeyIndex>,
+    next_operator_key: Option<PrivateKey>,
+    quarantine: Option<Quarantine>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    operator_keys: Option<Arc<SerdeEnvelope<OperatorKeys>>>,
 ) -> Router {
     let router = Router::new();
     #[cfg(feature = "debug")]
     let router = router.nest("/debug", debug::Config::new(core.clone()).into_router());
-    router
+    let router = router
         .nest(
             "/v1",
             v1::create_router(
                 content_base_url,
                 core,
                 temp_dir,
-                files_dir.clone(),
+                files.clone(),
                 content_policy,
+                allow_encrypted_content,
                 record_policy,
+                staging_policy,
+                yank_policy,
+                time_window,
+                search_index,
+                key_index,
+                next_operator_key,
+                quarantine,
             ),
         )
-        .nest_service("/content", ServeDir::new(files_dir))
-        .layer(
-            ServiceBuilder::new()
-                .layer(
-                    TraceLayer::new_for_http()
-                        .make_span_with(DefaultMakeSpan::new().include_headers(true))
-                        .on_request(|request: &Request<Body>, _span: &Span| {
-                            tracing::info!("starting {} {}", request.method(), request.uri().path())
-                        })
-                        .on_response(
-                            DefaultOnResponse::new()
-                                .level(Level::INFO)
-                                .latency_unit(LatencyUnit::Micros),
-                        ),
-                )
-                .layer(
-                    CorsLayer::new()
-                        .allow_origin(Any)
-                        .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
-                        .allow_headers([
-                            axum::http::header::CONTENT_TYPE,
-                            axum::http::header::ACCEPT,
-                        ]),
-                ),
-        )
+        .nest_service("/content", ServeDir::new(files.dir()));
+
+    let router = match authenticator {
+        Some(authenticator) => {
+            router.layer(middleware::from_fn_with_state(authenticator, authenticate))
+        }
+        None => router,
+    };
+
+    // Discovery documents are served without authentication so that new
+    // clients can bootstrap trust in the registry
+    let router = router.nest(
+        "/.well-known",
+        well_known::Config::new(operator_keys).into_router(),
+    );
+
+    router.layer(
+        ServiceBuilder::new()
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(DefaultMakeSpan::new().include_headers(true))
+                    .on_request(|request: &Request<Body>, _span: &Span| {
+                        tracing::info!("starting {} {}", request.method(), request.uri().path())
+                    })
+                    .on_response(
+                        DefaultOnResponse::new()
+                            .level(Level::INFO)
+                            .latency_unit(LatencyUnit::Micros),
+                    ),
+            )
+            .layer(
+                CorsLayer::new()
+                    .allow_origin(Any)
+                    .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
+                    .allow_headers([
+                        axum::http::header::CONTENT_TYPE,
+                        axum::http::header::ACCEPT,
+                        axum::http::header::AUTHORIZATION,
+                    ]),
+            ),
+    )
+}
+
+/// Determines the access required by a request.
+///
+/// Requests that modify package logs or inspect the quarantined records of
+/// publishers require publish access; all other requests require read access.
+fn required_access(method: &Method, path: &str) -> Access {
+    if (method != Method::GET && method != Method::HEAD && path.starts_with("/v1/package/"))
+        || path.starts_with("/v1/admin/quarantine")
+    {
+        Access::Publish
+    } else {
+        Access::Read
+    }
+}
+
+async fn authenticate(
+    State(authenticator): State<Arc<dyn Authenticator>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let access = required_access(request.method(), request.uri().path());
+    let (status, e) = match authenticator.authenticate(access, request.headers()).await {
+        Ok(()) => return next.run(request).await,
+        Err(e @ AuthError::Unauthenticated) => (StatusCode::UNAUTHORIZED, e),
+        Err(e @ AuthError::Forbidden(_)) => (StatusCode::FORBIDDEN, e),
+    };
+
+    let mut response = v1::Error::new(status, e.to_string()).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
 }
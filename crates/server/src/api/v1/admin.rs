@@ -0,0 +1,382 @@
+use super::{package, Json, Path, RegistryHeader};
+use crate::{
+    content::ContentFiles,
+    datastore::DataStoreError,
+    services::{CoreService, CoreServiceError, Quarantine},
+};
+use axum::{
+    debug_handler,
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use futures::StreamExt;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use warg_api::v1::{
+    admin::{
+        AdminCommandRequest, AdminCommandResponse, AdminError, QuarantineResponse,
+        QuarantinedRecord,
+    },
+    package::PackageError,
+};
+use warg_crypto::signing::PrivateKey;
+use warg_protocol::{
+    operator::{self, AdminCommand, OperatorEntry},
+    package::PackageRecord,
+    ProtoEnvelope, Record as _,
+};
+
+/// Content files modified within this period are never collected, as they
+/// may belong to records that are still pending.
+const GARBAGE_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+pub struct Config {
+    core_service: CoreService,
+    files: ContentFiles,
+    temp_dir: PathBuf,
+    next_operator_key: Option<PrivateKey>,
+    package: package::Config,
+}
+
+impl Config {
+    pub fn new(
+        core_service: CoreService,
+        files: ContentFiles,
+        temp_dir: PathBuf,
+        next_operator_key: Option<PrivateKey>,
+        package: package::Config,
+    ) -> Self {
+        Self {
+            core_service,
+            files,
+            temp_dir,
+            next_operator_key,
+            package,
+        }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/command", post(execute_command))
+            .route("/quarantine", get(list_quarantine))
+            .route("/quarantine/:id", get(get_quarantined_record))
+            .route("/quarantine/:id/requeue", post(requeue_quarantined_record))
+            .with_state(self)
+    }
+
+    fn quarantine(&self) -> Result<&Quarantine, AdminApiError> {
+        self.package.quarantine().ok_or_else(|| {
+            AdminApiError(AdminError::NotSupported(
+                "the registry does not quarantine rejected records".to_string(),
+            ))
+        })
+    }
+
+    fn quarantined_record(&self, id: u64) -> Result<QuarantinedRecord, AdminApiError> {
+        self.quarantine()?.get(id).ok_or_else(|| {
+            AdminApiError(AdminError::Message {
+                status: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("quarantined record `{id}` was not found"),
+            })
+        })
+    }
+
+    // Removes content files not referenced by any package record that has
+    // not been rejected or that is quarantined, along with abandoned uploads,
+    // returning the number of files removed.
+    //
+    // Pending records keep their content, as records staged for a
+    // countersignature and held co-publication parts cannot upload it again.
+    async fn collect_garbage(&self) -> Result<usize, AdminApiError> {
+        let mut referenced = HashSet::new();
+        let mut contents = self
+            .core_service
+            .store()
+            .get_all_retained_contents()
+            .await?;
+        while let Some(digest) = contents.next().await {
+            referenced.extend(self.files.locate(&digest?));
+        }
+
+        if let Some(quarantine) = self.package.quarantine() {
+            for quarantined in quarantine.records() {
+                let record: ProtoEnvelope<PackageRecord> = quarantined
+                    .record
+                    .try_into()
+                    .map_err(AdminApiError::internal_error)?;
+                referenced.extend(
+                    record
+                        .as_ref()
+                        .contents()
+                        .into_iter()
+                        .filter_map(|digest| self.files.locate(digest)),
+                );
+            }
+        }
+
+        let cutoff = SystemTime::now() - GARBAGE_GRACE_PERIOD;
+        let mut removed = remove_files(self.files.dir(), cutoff, &referenced).await?;
+        removed += remove_files(&self.temp_dir, cutoff, &HashSet::new()).await?;
+        Ok(removed)
+    }
+}
+
+// Removes the files of a directory and its subdirectories last modified
+// before the cutoff, except for the files to keep.
+async fn remove_files(
+    dir: &std::path::Path,
+    cutoff: SystemTime,
+    keep: &HashSet<PathBuf>,
+) -> Result<usize, AdminApiError> {
+    let mut removed = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .map_err(AdminApiError::internal_error)?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(AdminApiError::internal_error)?
+        {
+            let metadata = entry
+                .metadata()
+                .await
+                .map_err(AdminApiError::internal_error)?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            if !metadata.is_file()
+                || metadata
+                    .modified()
+                    .map_or(true, |modified| modified > cutoff)
+                || keep.contains(&entry.path())
+            {
+                continue;
+            }
+
+            tokio::fs::remove_file(entry.path())
+                .await
+                .map_err(AdminApiError::internal_error)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+struct AdminApiError(AdminError);
+
+impl AdminApiError {
+    fn bad_request(message: impl ToString) -> Self {
+        Self(AdminError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: message.to_string(),
+        })
+    }
+
+    fn internal_error(e: impl std::fmt::Display) -> Self {
+        tracing::error!("unexpected error: {e}");
+        Self(AdminError::Message {
+            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: "an error occurred while processing the request".into(),
+        })
+    }
+}
+
+impl From<DataStoreError> for AdminApiError {
+    fn from(e: DataStoreError) -> Self {
+        match e {
+            DataStoreError::OperatorValidationFailed(
+                operator::ValidationError::UnauthorizedAction { .. }
+                | operator::ValidationError::KeyIDNotRecognized { .. }
+                | operator::ValidationError::SignatureError(_),
+            ) => Self(AdminError::Unauthorized(e.to_string())),
+            DataStoreError::OperatorValidationFailed(e) => {
+                Self(AdminError::Rejection(e.to_string()))
+            }
+            e => Self::internal_error(e),
+        }
+    }
+}
+
+impl From<CoreServiceError> for AdminApiError {
+    fn from(e: CoreServiceError) -> Self {
+        match e {
+            CoreServiceError::DataStore(e) => e.into(),
+            e => Self::internal_error(e),
+        }
+    }
+}
+
+impl IntoResponse for AdminApiError {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+    }
+}
+
+#[debug_handler]
+async fn execute_command(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Json(body): Json<AdminCommandRequest<'static>>,
+) -> Result<Json<AdminCommandResponse>, AdminApiError> {
+    let record: ProtoEnvelope<operator::OperatorRecord> = body
+        .record
+        .into_owned()
+        .try_into()
+        .map_err(AdminApiError::bad_request)?;
+
+    // Only admin commands, advisories, and identity claims may be submitted
+    // through this API
+    let entries = &record.as_ref().entries;
+    if entries.iter().any(|entry| {
+        !matches!(
+            entry,
+            OperatorEntry::Admin(_) | OperatorEntry::Advisory(_) | OperatorEntry::IdentityClaim(_)
+        )
+    }) {
+        return Err(AdminApiError::bad_request(
+            "the record may only contain admin commands, advisories, and identity claims",
+        ));
+    }
+    if entries.is_empty() {
+        return Err(AdminApiError::bad_request(
+            "the record contains no admin commands, advisories, or identity claims",
+        ));
+    }
+    let commands = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            OperatorEntry::Admin(command) => Some(command.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // The checkpoint key can only be rotated to the configured next operator key
+    let mut next_operator_key = None;
+    for command in &commands {
+        if let AdminCommand::RotateCheckpointKey { key } = command {
+            match &config.next_operator_key {
+                Some(next) if &next.public_key() == key => next_operator_key = Some(next.clone()),
+                _ => {
+                    return Err(AdminApiError(AdminError::NotSupported(format!(
+                        "key `{key_id}` is not the next operator key of the registry",
+                        key_id = key.fingerprint()
+                    ))))
+                }
+            }
+        }
+    }
+
+    let record_id = config.core_service.append_operator_record(&record).await?;
+
+    // Sequence the record right away so that further commands can build on it
+    config.core_service.flush_now().await?;
+
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let command = match entry {
+            OperatorEntry::Advisory(advisory) => {
+                results.push(format!(
+                    "published advisory `{id}` for package `{name}`",
+                    id = advisory.id,
+                    name = advisory.package
+                ));
+                continue;
+            }
+            OperatorEntry::IdentityClaim(claim) => {
+                results.push(format!(
+                    "key `{key_id}` is bound to identity `{identity}`",
+                    key_id = claim.key.fingerprint(),
+                    identity = claim.identity
+                ));
+                continue;
+            }
+            OperatorEntry::Admin(command) => command,
+            _ => unreachable!("entries were checked above"),
+        };
+        results.push(match command {
+            AdminCommand::FreezePackage { name } => format!("package `{name}` is frozen"),
+            AdminCommand::UnfreezePackage { name } => format!("package `{name}` is unfrozen"),
+            AdminCommand::RevalidatePackage { name } => {
+                match config.core_service.revalidate_package(name).await {
+                    Ok(count) => format!("validated {count} record(s) of package `{name}`"),
+                    Err(CoreServiceError::DataStore(DataStoreError::LogNotFound(_))) => {
+                        format!("package `{name}` was not found")
+                    }
+                    Err(CoreServiceError::DataStore(DataStoreError::PackageValidationFailed(
+                        e,
+                    ))) => format!("package `{name}` failed to validate: {e}"),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            AdminCommand::CollectGarbage => {
+                let removed = config.collect_garbage().await?;
+                format!("removed {removed} unreferenced file(s)")
+            }
+            AdminCommand::RotateCheckpointKey { key } => {
+                if let Some(next) = next_operator_key.take() {
+                    config.core_service.rotate_operator_key(next).await?;
+                }
+                format!(
+                    "checkpoints are signed with key `{key_id}`",
+                    key_id = key.fingerprint()
+                )
+            }
+            command => format!("recorded command to {command}"),
+        });
+    }
+
+    Ok(Json(AdminCommandResponse { record_id, results }))
+}
+
+#[debug_handler]
+async fn list_quarantine(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<QuarantineResponse>, AdminApiError> {
+    Ok(Json(QuarantineResponse {
+        records: config.quarantine()?.records(),
+    }))
+}
+
+#[debug_handler]
+async fn get_quarantined_record(
+    State(config): State<Config>,
+    Path(id): Path<u64>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<QuarantinedRecord>, AdminApiError> {
+    config.quarantined_record(id).map(Json)
+}
+
+#[debug_handler]
+async fn requeue_quarantined_record(
+    State(config): State<Config>,
+    Path(id): Path<u64>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<impl IntoResponse, AdminApiError> {
+    let quarantined = config.quarantined_record(id)?;
+    let record = config
+        .package
+        .requeue(quarantined)
+        .await
+        .map_err(|e| match e {
+            PackageError::Rejection(reason) => AdminApiError(AdminError::Rejection(reason)),
+            e => AdminApiError(AdminError::Message {
+                status: e.status(),
+                message: e.to_string(),
+            }),
+        })?;
+
+    Ok((StatusCode::ACCEPTED, Json(record)))
+}
@@ -1,9 +1,9 @@
 use super::{Json, Path, RegistryHeader};
+use crate::content::ContentFiles;
 use axum::{
     debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::get, Router,
 };
 use indexmap::IndexMap;
-use std::path::PathBuf;
 use url::Url;
 use warg_api::v1::content::{ContentError, ContentSource, ContentSourcesResponse};
 use warg_crypto::hash::AnyHash;
@@ -11,14 +11,14 @@ use warg_crypto::hash::AnyHash;
 #[derive(Clone)]
 pub struct Config {
     content_base_url: Url,
-    files_dir: PathBuf,
+    files: ContentFiles,
 }
 
 impl Config {
-    pub fn new(content_base_url: Url, files_dir: PathBuf) -> Self {
+    pub fn new(content_base_url: Url, files: ContentFiles) -> Self {
         Self {
             content_base_url,
-            files_dir,
+            files,
         }
     }
 
@@ -28,25 +28,16 @@ impl Config {
             .with_state(self)
     }
 
-    fn content_present(&self, digest: &AnyHash) -> bool {
-        self.content_path(digest).is_file()
-    }
-
-    fn content_file_name(&self, digest: &AnyHash) -> String {
-        digest.to_string().replace(':', "-")
-    }
-
-    fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.files_dir.join(self.content_file_name(digest))
-    }
-
-    fn content_url(&self, digest: &AnyHash) -> String {
-        self.content_base_url
-            .join("content/")
-            .unwrap()
-            .join(&self.content_file_name(digest))
-            .unwrap()
-            .to_string()
+    fn content_url(&self, digest: &AnyHash) -> Option<String> {
+        let path = self.files.locate(digest)?;
+        Some(
+            self.content_base_url
+                .join("content/")
+                .unwrap()
+                .join(&self.files.url_path(&path)?)
+                .unwrap()
+                .to_string(),
+        )
     }
 }
 
@@ -64,12 +55,11 @@ async fn get_content(
     Path(digest): Path<AnyHash>,
     RegistryHeader(_registry_header): RegistryHeader,
 ) -> Result<Json<ContentSourcesResponse>, ContentApiError> {
-    if !config.content_present(&digest) {
+    let Some(url) = config.content_url(&digest) else {
         return Err(ContentApiError(ContentError::ContentDigestNotFound(digest)));
-    }
+    };
 
     let mut content_sources = IndexMap::with_capacity(1);
-    let url = config.content_url(&digest);
     content_sources.insert(
         digest,
         vec![ContentSource::HttpGet {
@@ -0,0 +1,59 @@
+use super::{Error, RegistryHeader};
+use crate::{feed::FeedGenerator, services::CoreService};
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use url::Url;
+use warg_api::v1::feed::{FeedQuery, FEED_CONTENT_TYPE};
+
+const DEFAULT_FEED_LIMIT: u16 = 100;
+const MAX_FEED_LIMIT: u16 = 1000;
+
+#[derive(Clone)]
+pub struct Config {
+    core: CoreService,
+    url: Url,
+}
+
+impl Config {
+    pub fn new(core: CoreService, url: Url) -> Self {
+        Self { core, url }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new().route("/", get(get_feed)).with_state(self)
+    }
+}
+
+#[debug_handler]
+async fn get_feed(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_FEED_LIMIT);
+    if limit == 0 || limit > MAX_FEED_LIMIT {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            format!("feed limit must be between 1 and {MAX_FEED_LIMIT}"),
+        ));
+    }
+
+    let feed = FeedGenerator::new(&config.core, config.url.clone())
+        .generate(limit as usize)
+        .await
+        .map_err(|e| {
+            tracing::error!("unexpected data store error: {e}");
+            Error::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "an error occurred while processing the request",
+            )
+        })?;
+
+    Ok(([(CONTENT_TYPE, FEED_CONTENT_TYPE)], feed).into_response())
+}
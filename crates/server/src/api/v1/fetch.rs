@@ -1,21 +1,28 @@
 use super::{Json, RegistryHeader};
-use crate::datastore::DataStoreError;
+use crate::datastore::{DataStore, DataStoreError};
 use crate::services::CoreService;
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::{
+    body::Body,
     debug_handler,
-    extract::State,
-    response::IntoResponse,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
+use bytes::Bytes;
+use futures::StreamExt;
 use indexmap::IndexMap;
+use std::convert::Infallible;
 use warg_api::v1::fetch::{
-    FetchError, FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest,
-    FetchPackageNamesResponse, PublishedRecord,
+    FetchError, FetchLogsFrame, FetchLogsRequest, FetchLogsResponse, FetchPackageNamesRequest,
+    FetchPackageNamesResponse, PublishedRecord, FETCH_LOGS_STREAM_CONTENT_TYPE,
+};
+use warg_crypto::hash::{AnyHash, Hash, HashAlgorithm, Sha256};
+use warg_protocol::filter::PackageFilter;
+use warg_protocol::registry::{
+    Checkpoint, FreshnessAssertion, LogId, RecordId, RegistryLen, TimestampedCheckpoint,
 };
-use warg_crypto::hash::{AnyHash, Sha256};
-use warg_protocol::registry::{LogId, RecordId, TimestampedCheckpoint};
 use warg_protocol::SerdeEnvelope;
 
 const DEFAULT_RECORDS_LIMIT: u16 = 100;
@@ -36,7 +43,11 @@ impl Config {
     pub fn into_router(self) -> Router {
         Router::new()
             .route("/checkpoint", get(fetch_checkpoint))
+            .route("/checkpoint/:log_length", get(fetch_checkpoint_at))
+            .route("/filter", get(fetch_package_filter))
+            .route("/freshness", get(fetch_freshness))
             .route("/logs", post(fetch_logs))
+            .route("/logs/stream", post(fetch_logs_stream))
             .route("/names", post(fetch_package_names))
             .with_state(self)
     }
@@ -81,36 +92,214 @@ impl IntoResponse for FetchApiError {
     }
 }
 
+/// Computes the entity tag of a response derived from the given checkpoint.
+///
+/// Any additional bytes (e.g. the request for a page of records) are
+/// included in the tag.
+fn entity_tag(checkpoint: &Checkpoint, extra: &[u8]) -> HeaderValue {
+    let hash: AnyHash = Hash::<Sha256>::of(checkpoint).into();
+    let hash = if extra.is_empty() {
+        hash
+    } else {
+        HashAlgorithm::Sha256.digest(&[hash.bytes(), extra].concat())
+    };
+
+    HeaderValue::from_str(&format!("\"{hash}\"")).expect("entity tag should be a valid header")
+}
+
+/// Determines if the `If-None-Match` header of a request matches the given entity tag.
+fn is_not_modified(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let etag = etag.to_str().expect("entity tag should be a valid string");
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Computes the entity tag of a page of records.
+///
+/// The records up to a checkpoint never change, so a page is identified by
+/// the checkpoint it was requested at and the request itself.
+async fn logs_entity_tag(
+    config: &Config,
+    body: &FetchLogsRequest<'_>,
+) -> Result<HeaderValue, FetchApiError> {
+    let checkpoint = config
+        .core_service
+        .store()
+        .get_checkpoint(body.log_length)
+        .await?;
+    let request = serde_json::to_vec(body).map_err(|e| {
+        tracing::error!("failed to serialize fetch logs request: {e}");
+        FetchApiError(FetchError::Message {
+            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: "an error occurred while processing the request".into(),
+        })
+    })?;
+
+    Ok(entity_tag(&checkpoint.as_ref().checkpoint, &request))
+}
+
 #[debug_handler]
 async fn fetch_logs(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
+    headers: HeaderMap,
+    Json(body): Json<FetchLogsRequest<'static>>,
+) -> Result<Response, FetchApiError> {
+    let etag = logs_entity_tag(&config, &body).await?;
+    if is_not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let response = fetch_records(&config, body).await?;
+    Ok(([(header::ETAG, etag)], Json(response)).into_response())
+}
+
+#[debug_handler]
+async fn fetch_logs_stream(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    headers: HeaderMap,
     Json(body): Json<FetchLogsRequest<'static>>,
-) -> Result<Json<FetchLogsResponse>, FetchApiError> {
-    let limit = body.limit.unwrap_or(DEFAULT_RECORDS_LIMIT);
-    if limit == 0 || limit > MAX_RECORDS_LIMIT {
-        return Err(FetchApiError::bad_request(format!(
-            "invalid records limit value `{limit}`: must be between 1 and {MAX_RECORDS_LIMIT}"
-        )));
+) -> Result<Response, FetchApiError> {
+    let etag = logs_entity_tag(&config, &body).await?;
+    if is_not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
     }
 
-    let operator_fetch_token: Option<RecordId> = match body.operator {
-        Some(s) => Some(
+    let FetchQuery {
+        log_length,
+        limit,
+        operator,
+        packages,
+    } = FetchQuery::parse(body)?;
+
+    // Fetch the operator records and check the requested package logs before
+    // responding so that an invalid request is reported with an error status
+    let store = config.core_service.shared_store();
+    let operator = operator_records(store.as_ref(), log_length, operator.as_ref(), limit).await?;
+    for (log_id, since) in &packages {
+        store
+            .get_package_records(log_id, log_length, since.as_ref(), 1)
+            .await?;
+    }
+    let more = operator.len() == limit as usize;
+    let operator = operator.into_iter().map(|record| {
+        Ok(Bytes::from(
+            FetchLogsFrame::Operator(record).encode_length_delimited(),
+        ))
+    });
+
+    // Package logs are fetched from the store one at a time as the response
+    // is sent, so that only a single log's records are held in memory
+    let packages = futures::stream::unfold(
+        (store, packages.into_iter(), more, false),
+        move |(store, mut packages, more, done)| async move {
+            if done {
+                return None;
+            }
+
+            let Some((log_id, since)) = packages.next() else {
+                let end = FetchLogsFrame::End {
+                    more,
+                    warnings: Vec::default(),
+                };
+                return Some((
+                    Bytes::from(end.encode_length_delimited()),
+                    (store, packages, more, true),
+                ));
+            };
+
+            match package_records(store.as_ref(), &log_id, log_length, since.as_ref(), limit).await
+            {
+                Ok(records) => {
+                    let more = more || records.len() == limit as usize;
+                    let frames = records
+                        .into_iter()
+                        .flat_map(|record| {
+                            FetchLogsFrame::Package {
+                                log_id: log_id.clone(),
+                                record,
+                            }
+                            .encode_length_delimited()
+                        })
+                        .collect::<Vec<_>>();
+                    Some((Bytes::from(frames), (store, packages, more, false)))
+                }
+                Err(FetchApiError(e)) => Some((
+                    Bytes::from(FetchLogsFrame::encode_error_length_delimited(&e)),
+                    (store, packages, more, true),
+                )),
+            }
+        },
+    )
+    .map(Ok::<_, Infallible>);
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(FETCH_LOGS_STREAM_CONTENT_TYPE),
+            ),
+            (header::ETAG, etag),
+        ],
+        Body::from_stream(futures::stream::iter(operator).chain(packages)),
+    )
+        .into_response())
+}
+
+/// A parsed fetch logs request.
+struct FetchQuery {
+    log_length: RegistryLen,
+    limit: u16,
+    operator: Option<RecordId>,
+    packages: Vec<(LogId, Option<RecordId>)>,
+}
+
+impl FetchQuery {
+    fn parse(body: FetchLogsRequest<'static>) -> Result<Self, FetchApiError> {
+        let limit = body.limit.unwrap_or(DEFAULT_RECORDS_LIMIT);
+        if limit == 0 || limit > MAX_RECORDS_LIMIT {
+            return Err(FetchApiError::bad_request(format!(
+                "invalid records limit value `{limit}`: must be between 1 and {MAX_RECORDS_LIMIT}"
+            )));
+        }
+
+        let parse_token = |s: String| {
             s.parse::<AnyHash>()
-                .map_err(|_| FetchApiError(FetchError::FetchTokenNotFound(s.into_owned())))?
-                .into(),
-        ),
-        None => None,
-    };
-    let operator: Vec<PublishedRecord> = config
-        .core_service
-        .store()
-        .get_operator_records(
-            &LogId::operator_log::<Sha256>(),
-            body.log_length,
-            operator_fetch_token.as_ref(),
+                .map(RecordId::from)
+                .map_err(|_| FetchApiError(FetchError::FetchTokenNotFound(s)))
+        };
+
+        Ok(Self {
+            log_length: body.log_length,
             limit,
-        )
+            operator: body
+                .operator
+                .map(|s| parse_token(s.into_owned()))
+                .transpose()?,
+            packages: body
+                .packages
+                .into_owned()
+                .into_iter()
+                .map(|(id, token)| Ok((id, token.map(parse_token).transpose()?)))
+                .collect::<Result<_, FetchApiError>>()?,
+        })
+    }
+}
+
+async fn operator_records(
+    store: &dyn DataStore,
+    log_length: RegistryLen,
+    since: Option<&RecordId>,
+    limit: u16,
+) -> Result<Vec<PublishedRecord>, FetchApiError> {
+    Ok(store
+        .get_operator_records(&LogId::operator_log::<Sha256>(), log_length, since, limit)
         .await?
         .into_iter()
         .map(|envelope| {
@@ -121,59 +310,129 @@ async fn fetch_logs(
                 fetch_token,
             }
         })
-        .collect();
+        .collect())
+}
 
-    let mut more = operator.len() == limit as usize;
+async fn package_records(
+    store: &dyn DataStore,
+    log_id: &LogId,
+    log_length: RegistryLen,
+    since: Option<&RecordId>,
+    limit: u16,
+) -> Result<Vec<PublishedRecord>, FetchApiError> {
+    Ok(store
+        .get_package_records(log_id, log_length, since, limit)
+        .await?
+        .into_iter()
+        .map(|envelope| {
+            // use the record ID as the fetch token
+            let fetch_token = RecordId::package_record::<Sha256>(&envelope.envelope).to_string();
+            PublishedRecord {
+                envelope: envelope.into(),
+                fetch_token,
+            }
+        })
+        .collect())
+}
+
+async fn fetch_records(
+    config: &Config,
+    body: FetchLogsRequest<'static>,
+) -> Result<FetchLogsResponse, FetchApiError> {
+    let query = FetchQuery::parse(body)?;
+    let store = config.core_service.store();
+
+    let operator = operator_records(
+        store,
+        query.log_length,
+        query.operator.as_ref(),
+        query.limit,
+    )
+    .await?;
+    let mut more = operator.len() == query.limit as usize;
 
     let mut map = IndexMap::new();
-    let packages = body.packages.into_owned();
-    for (id, fetch_token) in packages {
-        let since: Option<RecordId> = match fetch_token {
-            Some(s) => Some(
-                s.parse::<AnyHash>()
-                    .map_err(|_| FetchApiError(FetchError::FetchTokenNotFound(s)))?
-                    .into(),
-            ),
-            None => None,
-        };
-        let records: Vec<PublishedRecord> = config
-            .core_service
-            .store()
-            .get_package_records(&id, body.log_length, since.as_ref(), limit)
-            .await?
-            .into_iter()
-            .map(|envelope| {
-                // use the record ID as the fetch token
-                let fetch_token =
-                    RecordId::package_record::<Sha256>(&envelope.envelope).to_string();
-                PublishedRecord {
-                    envelope: envelope.into(),
-                    fetch_token,
-                }
-            })
-            .collect();
-        more |= records.len() == limit as usize;
+    for (id, since) in query.packages {
+        let records =
+            package_records(store, &id, query.log_length, since.as_ref(), query.limit).await?;
+        more |= records.len() == query.limit as usize;
         map.insert(id, records);
     }
 
-    Ok(Json(FetchLogsResponse {
+    Ok(FetchLogsResponse {
         more,
         operator,
         packages: map,
         warnings: Vec::default(),
-    }))
+    })
 }
 
 #[debug_handler]
 async fn fetch_checkpoint(
     State(config): State<Config>,
     RegistryHeader(_registry_header): RegistryHeader,
+    headers: HeaderMap,
+) -> Result<Response, FetchApiError> {
+    let checkpoint = config.core_service.store().get_latest_checkpoint().await?;
+    let etag = entity_tag(&checkpoint.as_ref().checkpoint, &[]);
+    if is_not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    Ok(([(header::ETAG, etag)], Json(checkpoint)).into_response())
+}
+
+#[debug_handler]
+async fn fetch_checkpoint_at(
+    State(config): State<Config>,
+    Path(log_length): Path<RegistryLen>,
+    RegistryHeader(_registry_header): RegistryHeader,
 ) -> Result<Json<SerdeEnvelope<TimestampedCheckpoint>>, FetchApiError> {
     Ok(Json(
-        config.core_service.store().get_latest_checkpoint().await?,
+        config
+            .core_service
+            .store()
+            .get_checkpoint(log_length)
+            .await?,
     ))
 }
 
+#[debug_handler]
+async fn fetch_freshness(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<SerdeEnvelope<FreshnessAssertion>>, FetchApiError> {
+    config
+        .core_service
+        .freshness()
+        .await
+        .map(Json)
+        .ok_or_else(|| {
+            FetchApiError(FetchError::Message {
+                status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                message: "a freshness assertion has not yet been signed".into(),
+            })
+        })
+}
+
+#[debug_handler]
+async fn fetch_package_filter(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<SerdeEnvelope<PackageFilter>>, FetchApiError> {
+    config
+        .core_service
+        .package_filter()
+        .await
+        .map(Json)
+        .ok_or_else(|| {
+            FetchApiError(FetchError::Message {
+                status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                message: "a package filter has not yet been signed".into(),
+            })
+        })
+}
+
 #[debug_handler]
 async fn fetch_package_names(
     State(config): State<Config>,
@@ -0,0 +1,104 @@
+use super::{Json, Path, RegistryHeader};
+use crate::services::{CoreService, KeyIndex};
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use indexmap::IndexSet;
+use warg_api::v1::key::{KeyError, KeyRecord, KeyRecordsQuery, KeyRecordsResponse};
+use warg_crypto::signing::KeyID;
+
+const DEFAULT_RECORDS_LIMIT: u16 = 100;
+const MAX_RECORDS_LIMIT: u16 = 1000;
+
+#[derive(Clone)]
+pub struct Config {
+    core_service: CoreService,
+    index: Option<KeyIndex>,
+}
+
+impl Config {
+    pub fn new(core_service: CoreService, index: Option<KeyIndex>) -> Self {
+        Self {
+            core_service,
+            index,
+        }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/:key_id/records", get(get_records))
+            .with_state(self)
+    }
+}
+
+struct KeyApiError(KeyError);
+
+impl KeyApiError {
+    fn internal_error(e: impl std::fmt::Display) -> Self {
+        tracing::error!("unexpected error: {e}");
+        Self(KeyError::Message {
+            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: "an error occurred while processing the request".into(),
+        })
+    }
+}
+
+impl IntoResponse for KeyApiError {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+    }
+}
+
+#[debug_handler]
+async fn get_records(
+    State(config): State<Config>,
+    Path(key_id): Path<KeyID>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<KeyRecordsQuery>,
+) -> Result<Json<KeyRecordsResponse>, KeyApiError> {
+    let index = config
+        .index
+        .as_ref()
+        .ok_or(KeyApiError(KeyError::NotSupported))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_RECORDS_LIMIT);
+    if limit == 0 || limit > MAX_RECORDS_LIMIT {
+        return Err(KeyApiError(KeyError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: format!("records limit must be between 1 and {MAX_RECORDS_LIMIT}"),
+        }));
+    }
+
+    let (records, more) = index.records(&key_id, query.since, limit as usize).await;
+
+    let log_ids = records
+        .iter()
+        .map(|(_, leaf)| leaf.log_id.clone())
+        .collect::<IndexSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let names = config
+        .core_service
+        .store()
+        .get_package_names(&log_ids)
+        .await
+        .map_err(KeyApiError::internal_error)?;
+
+    Ok(Json(KeyRecordsResponse {
+        more,
+        records: records
+            .into_iter()
+            .map(|(registry_index, leaf)| KeyRecord {
+                name: names.get(&leaf.log_id).cloned().flatten(),
+                log_id: leaf.log_id,
+                record_id: leaf.record_id,
+                registry_index,
+            })
+            .collect(),
+    }))
+}
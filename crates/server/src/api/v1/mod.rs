@@ -1,6 +1,7 @@
 use crate::{
-    policy::{content::ContentPolicy, record::RecordPolicy},
-    services::CoreService,
+    content::ContentFiles,
+    policy::{content::ContentPolicy, record::RecordPolicy, staging::StagingPolicy},
+    services::{CoreService, KeyIndex, Quarantine, SearchIndex},
 };
 use anyhow::Result;
 use axum::{
@@ -16,14 +17,20 @@ use axum::{
 use serde::{Serialize, Serializer};
 use std::{path::PathBuf, str::FromStr, sync::Arc};
 use url::Url;
-use warg_api::v1::REGISTRY_HEADER_NAME;
+use warg_api::v1::{paths, REGISTRY_HEADER_NAME};
+use warg_crypto::signing::PrivateKey;
+use warg_protocol::{package::YankPolicy, policy::TimeWindow};
 
+pub mod admin;
 pub mod content;
+pub mod feed;
 pub mod fetch;
+pub mod key;
 pub mod ledger;
 pub mod monitor;
 pub mod package;
 pub mod proof;
+pub mod search;
 
 /// An extractor that wraps the JSON extractor of Axum.
 ///
@@ -56,6 +63,15 @@ pub struct Error {
     message: String,
 }
 
+impl Error {
+    pub(crate) fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
 impl From<JsonRejection> for Error {
     fn from(rejection: JsonRejection) -> Self {
         Self {
@@ -124,33 +140,62 @@ impl FromStr for RegistryHeader {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_router(
     content_base_url: Url,
     core: CoreService,
     temp_dir: PathBuf,
-    files_dir: PathBuf,
+    files: ContentFiles,
     content_policy: Option<Arc<dyn ContentPolicy>>,
+    allow_encrypted_content: bool,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    staging_policy: Option<Arc<StagingPolicy>>,
+    yank_policy: Option<Arc<YankPolicy>>,
+    time_window: TimeWindow,
+    search_index: Option<SearchIndex>,
+    key_index: Option<KeyIndex>,
+    next_operator_key: Option<PrivateKey>,
+    quarantine: Option<Quarantine>,
 ) -> Router {
     let proof_config = proof::Config::new(core.clone());
     let package_config = package::Config::new(
         core.clone(),
-        files_dir.clone(),
-        temp_dir,
+        files.clone(),
+        temp_dir.clone(),
         content_policy,
+        allow_encrypted_content,
         record_policy,
+        staging_policy,
+        yank_policy,
+        time_window,
+        quarantine,
+    );
+    let admin_config = admin::Config::new(
+        core.clone(),
+        files.clone(),
+        temp_dir,
+        next_operator_key,
+        package_config.clone(),
     );
     let fetch_config = fetch::Config::new(core.clone());
-    let content_config = content::Config::new(content_base_url, files_dir);
+    let feed_config =
+        feed::Config::new(core.clone(), content_base_url.join(paths::feed()).unwrap());
+    let content_config = content::Config::new(content_base_url, files);
     let monitor_config = monitor::Config::new(core.clone());
+    let key_config = key::Config::new(core.clone(), key_index);
     let ledger_config = ledger::Config::new(core);
+    let search_config = search::Config::new(search_index);
 
     Router::new()
+        .nest("/admin", admin_config.into_router())
         .nest("/content", content_config.into_router())
+        .nest("/feed", feed_config.into_router())
         .nest("/fetch", fetch_config.into_router())
+        .nest("/key", key_config.into_router())
         .nest("/ledger", ledger_config.into_router())
         .nest("/package", package_config.into_router())
         .nest("/proof", proof_config.into_router())
+        .nest("/search", search_config.into_router())
         .nest("/verify", monitor_config.into_router())
         .fallback(not_found)
 }
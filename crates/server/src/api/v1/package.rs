@@ -1,61 +1,83 @@
 use super::{Json, Path, RegistryHeader};
 use crate::{
+    content::ContentFiles,
     datastore::{DataStoreError, RecordStatus},
     policy::{
         content::{ContentPolicy, ContentPolicyError},
         record::{RecordPolicy, RecordPolicyError},
+        staging::StagingPolicy,
     },
-    services::CoreService,
+    services::{CoreService, Quarantine},
 };
 use axum::{
     body::{Body, BodyDataStream},
     debug_handler,
     extract::State,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Router,
 };
 use futures::StreamExt;
 use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 use tempfile::NamedTempFile;
 use tokio::io::AsyncWriteExt;
+use warg_api::v1::admin::QuarantinedRecord;
 use warg_api::v1::package::{
-    MissingContent, PackageError, PackageRecord, PackageRecordState, PublishRecordRequest,
-    UploadEndpoint,
+    MissingContent, PackageError, PackageRecord, PackageRecordReceipt, PackageRecordState,
+    PublishRecordRequest, ReleaseConflict, UploadEndpoint,
 };
 use warg_crypto::hash::{AnyHash, Sha256};
 use warg_protocol::{
-    package,
-    registry::{LogId, RecordId},
-    ProtoEnvelope, Record as _,
+    package::{self, CountersignaturePolicy, YankPolicy},
+    policy::TimeWindow,
+    registry::{LogId, LogLeaf, PackageName, RecordId},
+    CoPublication, Countersignature, ProtoEnvelope, Record as _,
 };
 
 #[derive(Clone)]
 pub struct Config {
     core_service: CoreService,
-    files_dir: PathBuf,
+    files: ContentFiles,
     temp_dir: PathBuf,
     content_policy: Option<Arc<dyn ContentPolicy>>,
+    allow_encrypted_content: bool,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    staging_policy: Option<Arc<StagingPolicy>>,
+    yank_policy: Option<Arc<YankPolicy>>,
+    time_window: TimeWindow,
+    quarantine: Option<Quarantine>,
 }
 
 impl Config {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         core_service: CoreService,
-        files_dir: PathBuf,
+        files: ContentFiles,
         temp_dir: PathBuf,
         content_policy: Option<Arc<dyn ContentPolicy>>,
+        allow_encrypted_content: bool,
         record_policy: Option<Arc<dyn RecordPolicy>>,
+        staging_policy: Option<Arc<StagingPolicy>>,
+        yank_policy: Option<Arc<YankPolicy>>,
+        time_window: TimeWindow,
+        quarantine: Option<Quarantine>,
     ) -> Self {
         Self {
             core_service,
-            files_dir,
+            files,
             temp_dir,
             content_policy,
+            allow_encrypted_content,
             record_policy,
+            staging_policy,
+            yank_policy,
+            time_window,
+            quarantine,
         }
     }
 
@@ -63,23 +85,195 @@ impl Config {
         Router::new()
             .route("/:log_id/record", post(publish_record))
             .route("/:log_id/record/:record_id", get(get_record))
+            .route(
+                "/:log_id/record/:record_id/receipt",
+                get(get_record_receipt),
+            )
             .route(
                 "/:log_id/record/:record_id/content/:digest",
                 post(upload_content),
             )
+            .route(
+                "/:log_id/record/:record_id/countersignature",
+                post(countersign_record),
+            )
             .with_state(self)
     }
 
-    fn content_present(&self, digest: &AnyHash) -> bool {
-        self.content_path(digest).is_file()
+    fn countersignature_policy(&self, name: &PackageName) -> Option<CountersignaturePolicy> {
+        self.staging_policy.as_ref().map(|policy| {
+            policy.countersignature_policy(self.core_service.operator_public_key(), name)
+        })
+    }
+
+    /// Determines if the given record is staged awaiting a countersignature.
+    async fn is_staged(
+        &self,
+        log_id: &LogId,
+        record: &ProtoEnvelope<package::PackageRecord>,
+    ) -> Result<bool, PackageApiError> {
+        if self.staging_policy.is_none() || record.countersignature().is_some() {
+            return Ok(false);
+        }
+
+        let name = self
+            .core_service
+            .store()
+            .get_package_names(std::slice::from_ref(log_id))
+            .await?
+            .swap_remove(log_id)
+            .flatten()
+            .ok_or_else(|| PackageApiError(PackageError::LogNotFound(log_id.clone())))?;
+
+        Ok(self
+            .countersignature_policy(&name)
+            .is_some_and(|policy| policy.requires(record.as_ref())))
+    }
+
+    /// Finds the release by another record of a version released by the given
+    /// record that caused the record to be rejected with the given reason.
+    ///
+    /// Returns `None` if the record was not rejected as a duplicate release.
+    async fn release_conflict(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        record: &ProtoEnvelope<package::PackageRecord>,
+        reason: &str,
+    ) -> Result<Option<ReleaseConflict>, PackageApiError> {
+        for version in record
+            .as_ref()
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                package::PackageEntry::Release { version, .. } => Some(version),
+                _ => None,
+            })
+        {
+            if let Some(released_by) = self
+                .core_service
+                .store()
+                .get_package_release(log_id, version)
+                .await?
+                .filter(|id| id != record_id)
+            {
+                // Rejection reasons are stored as the message of the validation error
+                let duplicate = DataStoreError::from(package::ValidationError::DuplicateRelease {
+                    version: version.clone(),
+                    existing: released_by.clone(),
+                    record_id: record_id.clone(),
+                });
+                if reason != duplicate.to_string() {
+                    continue;
+                }
+
+                return Ok(Some(ReleaseConflict {
+                    version: version.clone(),
+                    record_id: released_by,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Checks the yanks of the given record against the yank policy.
+    async fn check_yank_policy(
+        &self,
+        log_id: &LogId,
+        record: &ProtoEnvelope<package::PackageRecord>,
+    ) -> Result<(), PackageApiError> {
+        let Some(policy) = &self.yank_policy else {
+            return Ok(());
+        };
+
+        let store = self.core_service.store();
+        for entry in &record.as_ref().entries {
+            match entry {
+                package::PackageEntry::Yank { version, reason } => {
+                    // Yanks of versions that were never released fail validation instead
+                    let Some(release_id) = store.get_package_release(log_id, version).await? else {
+                        continue;
+                    };
+
+                    let released = store
+                        .get_package_record(log_id, &release_id)
+                        .await?
+                        .envelope
+                        .as_ref()
+                        .timestamp;
+                    policy
+                        .check_yank(
+                            version,
+                            released,
+                            record.as_ref().timestamp,
+                            reason.as_deref(),
+                        )
+                        .map_err(PackageApiError::bad_request)?;
+                }
+                package::PackageEntry::YankRange { range, reason } => {
+                    for release in store.get_package_releases(log_id).await? {
+                        if release.yanked() || !range.matches(&release.version) {
+                            continue;
+                        }
+
+                        policy
+                            .check_yank(
+                                &release.version,
+                                release.timestamp,
+                                record.as_ref().timestamp,
+                                reason.as_deref(),
+                            )
+                            .map_err(PackageApiError::bad_request)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
     }
 
-    fn content_file_name(&self, digest: &AnyHash) -> String {
-        digest.to_string().replace(':', "-")
+    /// Checks that manifest artifacts already present on the server match
+    /// their declared sizes; missing artifacts are checked as uploaded.
+    fn check_artifact_sizes(
+        &self,
+        record: &ProtoEnvelope<package::PackageRecord>,
+    ) -> Result<(), PackageApiError> {
+        for entry in &record.as_ref().entries {
+            let package::PackageEntry::Release {
+                manifest: Some(manifest),
+                ..
+            } = entry
+            else {
+                continue;
+            };
+
+            for artifact in &manifest.artifacts {
+                let Some(metadata) = self
+                    .files
+                    .locate(&artifact.content)
+                    .and_then(|path| std::fs::metadata(path).ok())
+                else {
+                    continue;
+                };
+
+                if metadata.len() != artifact.size {
+                    return Err(PackageApiError::bad_request(format!(
+                        "artifact `{name}` is {size} byte(s) but the release manifest declares {expected} byte(s)",
+                        name = artifact.name,
+                        size = metadata.len(),
+                        expected = artifact.size,
+                    )));
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    fn content_path(&self, digest: &AnyHash) -> PathBuf {
-        self.files_dir.join(self.content_file_name(digest))
+    fn content_present(&self, digest: &AnyHash) -> bool {
+        self.files.contains(digest)
     }
 
     fn build_missing_content<'a>(
@@ -93,7 +287,7 @@ impl Config {
             .map(|digest| {
                 let url = format!("v1/package/{log_id}/record/{record_id}/content/{digest}");
                 (
-                    digest.clone(),
+                    *digest,
                     MissingContent {
                         upload: vec![UploadEndpoint::Http {
                             method: "POST".to_string(),
@@ -125,6 +319,20 @@ impl PackageApiError {
         })
     }
 
+    fn not_staged(record_id: &RecordId) -> Self {
+        Self(PackageError::Message {
+            status: StatusCode::CONFLICT.as_u16(),
+            message: format!("package record `{record_id}` is not staged"),
+        })
+    }
+
+    fn not_published(record_id: &RecordId) -> Self {
+        Self(PackageError::Message {
+            status: StatusCode::CONFLICT.as_u16(),
+            message: format!("package record `{record_id}` is not yet published"),
+        })
+    }
+
     fn unsupported(message: impl ToString) -> Self {
         Self(PackageError::Message {
             status: StatusCode::NOT_IMPLEMENTED.as_u16(),
@@ -141,11 +349,13 @@ impl From<DataStoreError> for PackageApiError {
             }
             DataStoreError::LogNotFound(id) => PackageError::LogNotFound(id),
             DataStoreError::RecordNotFound(id) => PackageError::RecordNotFound(id),
+            DataStoreError::RecordExists(id) => PackageError::RecordAlreadySubmitted(id),
             DataStoreError::UnknownKey(_) | DataStoreError::SignatureVerificationFailed(_) => {
                 PackageError::Unauthorized(e.to_string())
             }
             DataStoreError::PackageNamespaceNotDefined(id) => PackageError::NamespaceNotDefined(id),
             DataStoreError::PackageNamespaceImported(id) => PackageError::NamespaceImported(id),
+            DataStoreError::PackageFrozen(_) => PackageError::Rejection(e.to_string()),
             // Other errors are internal server errors
             e => {
                 tracing::error!("unexpected data store error: {e}");
@@ -186,85 +396,291 @@ async fn publish_record(
     State(config): State<Config>,
     Path(log_id): Path<LogId>,
     RegistryHeader(_registry_header): RegistryHeader,
+    headers: HeaderMap,
     Json(body): Json<PublishRecordRequest<'static>>,
 ) -> Result<impl IntoResponse, PackageApiError> {
-    let expected_log_id = LogId::package_log::<Sha256>(&body.package_name);
-    if expected_log_id != log_id {
-        return Err(PackageApiError::bad_request(format!(
-            "package log identifier `{expected_log_id}` derived from `{name}` does not match provided log identifier `{log_id}`",
-            name = body.package_name
-        )));
+    let package_name = body.package_name.clone().into_owned();
+    let record = body.record.clone().into_owned();
+    match config.publish(log_id.clone(), body).await {
+        Ok(record) => Ok((StatusCode::ACCEPTED, Json(record))),
+        Err(e) => {
+            config.reject(&log_id, &package_name, record, &e, &headers);
+            Err(e)
+        }
     }
+}
 
-    let record: ProtoEnvelope<package::PackageRecord> = body
-        .record
-        .into_owned()
-        .try_into()
-        .map_err(PackageApiError::bad_request)?;
+impl Config {
+    /// Gets the quarantine of rejected records, if one is configured.
+    pub(crate) fn quarantine(&self) -> Option<&Quarantine> {
+        self.quarantine.as_ref()
+    }
+
+    /// Publishes a quarantined record again.
+    ///
+    /// The record is published without the expected head and
+    /// co-publication of the original request. It is removed from the quarantine if accepted.
+    pub(crate) async fn requeue(
+        &self,
+        quarantined: QuarantinedRecord,
+    ) -> Result<PackageRecord, PackageError> {
+        let request = PublishRecordRequest {
+            package_name: Cow::Owned(quarantined.package_name),
+            record: Cow::Owned(quarantined.record),
+            content_sources: Default::default(),
+            expected_head: None,
+            copublication: None,
+        };
+
+        let result = self.publish(quarantined.log_id, request).await;
+        if let Some(quarantine) = &self.quarantine {
+            match &result {
+                Ok(_) => {
+                    quarantine.remove(quarantined.id);
+                }
+                Err(e) => quarantine.reject(quarantined.id, e.0.to_string()),
+            }
+        }
 
-    // Specifying content sources is not allowed in this implementation
-    if !body.content_sources.is_empty() {
-        return Err(PackageApiError::unsupported(
-            "specifying content sources is not supported",
-        ));
+        result.map_err(|e| e.0)
     }
 
-    // Verify the package name is unique in a case insensitive way and
-    // the namespace is defined in the operator log and not imported
-    // from another registry.
-    config
-        .core_service
-        .store()
-        .verify_can_publish_package(&LogId::operator_log::<Sha256>(), &body.package_name)
-        .await?;
+    /// Logs a rejected publish request and quarantines its record.
+    ///
+    /// Only rejections of the request are recorded; requests that failed due
+    /// to a server error are not.
+    fn reject(
+        &self,
+        log_id: &LogId,
+        package_name: &PackageName,
+        record: warg_protocol::ProtoEnvelopeBody,
+        e: &PackageApiError,
+        headers: &HeaderMap,
+    ) {
+        if !(400..500).contains(&e.0.status()) {
+            return;
+        }
 
-    // Preemptively perform the policy check on the record before storing it
-    // This is performed here so that we never store an unauthorized record
-    if let Some(policy) = &config.record_policy {
-        policy.check(&body.package_name, &record)?;
+        let reason = e.0.to_string();
+        let Ok(record) = ProtoEnvelope::<package::PackageRecord>::try_from(record) else {
+            tracing::warn!(%log_id, package = %package_name, reason, "rejected malformed package record");
+            return;
+        };
+
+        tracing::warn!(
+            %log_id,
+            package = %package_name,
+            submitter = %record.key_id(),
+            reason,
+            "rejected package record"
+        );
+
+        if let Some(quarantine) = &self.quarantine {
+            let id = quarantine.insert(log_id, package_name, &record, reason, headers);
+            tracing::debug!(id, "quarantined rejected package record");
+        }
     }
 
-    // Verify the signature on the record itself before storing it
-    config
-        .core_service
-        .store()
-        .verify_package_record_signature(&log_id, &record)
-        .await?;
+    async fn publish(
+        &self,
+        log_id: LogId,
+        body: PublishRecordRequest<'static>,
+    ) -> Result<PackageRecord, PackageApiError> {
+        let expected_log_id = LogId::package_log::<Sha256>(&body.package_name);
+        if expected_log_id != log_id {
+            return Err(PackageApiError::bad_request(format!(
+                "package log identifier `{expected_log_id}` derived from `{name}` does not match provided log identifier `{log_id}`",
+                name = body.package_name
+            )));
+        }
 
-    let record_id = RecordId::package_record::<Sha256>(&record);
-    let mut missing = record.as_ref().contents();
-    missing.retain(|d| !config.content_present(d));
+        let record: ProtoEnvelope<package::PackageRecord> = body
+            .record
+            .into_owned()
+            .try_into()
+            .map_err(PackageApiError::bad_request)?;
 
-    config
-        .core_service
-        .store()
-        .store_package_record(&log_id, &body.package_name, &record_id, &record, &missing)
-        .await?;
+        // Specifying content sources is not allowed in this implementation
+        if !body.content_sources.is_empty() {
+            return Err(PackageApiError::unsupported(
+                "specifying content sources is not supported",
+            ));
+        }
 
-    // If there's no missing content, submit the record for processing now
-    if missing.is_empty() {
-        config
+        // Verify the package name is unique in a case insensitive way and
+        // the namespace is defined in the operator log and not imported
+        // from another registry.
+        self.core_service
+            .store()
+            .verify_can_publish_package(&LogId::operator_log::<Sha256>(), &body.package_name)
+            .await?;
+
+        // Reject records whose timestamps the server clock cannot vouch for
+        self.time_window
+            .check(record.as_ref().timestamp, SystemTime::now())
+            .map_err(|e| PackageApiError::bad_request(format!("invalid record timestamp: {e}")))?;
+
+        // A publish token must be scoped to the package alone and be unexpired; the
+        // rest of the token chain is validated with the record's signature below
+        if let Some(token) = &record.as_ref().publish_token {
+            if !token.authorizes(&log_id) {
+                return Err(PackageApiError(PackageError::Unauthorized(format!(
+                    "the publish token is not scoped to package `{name}` alone",
+                    name = body.package_name
+                ))));
+            }
+
+            if token.expired(SystemTime::now()) {
+                return Err(PackageApiError(PackageError::Unauthorized(
+                    "the publish token has expired".to_string(),
+                )));
+            }
+        }
+
+        // Preemptively perform the policy check on the record before storing it
+        // This is performed here so that we never store an unauthorized record
+        if let Some(policy) = &self.record_policy {
+            policy.check(&body.package_name, &record)?;
+        }
+
+        // Reject records built against a head other than the current head of the log
+        if let Some(expected_head) = &body.expected_head {
+            if record.as_ref().prev.as_ref() != Some(expected_head) {
+                return Err(PackageApiError::bad_request(format!(
+                    "expected head `{expected_head}` does not match the previous record of the record"
+                )));
+            }
+
+            let current_head = self
+                .core_service
+                .store()
+                .get_package_log_head(&log_id)
+                .await?;
+            // Records may also be chained onto a record that is still pending
+            if current_head.as_ref() != Some(expected_head)
+                && !matches!(
+                    self.core_service
+                        .store()
+                        .get_package_record(&log_id, expected_head)
+                        .await
+                        .map(|r| r.status),
+                    Ok(RecordStatus::Pending | RecordStatus::MissingContent(_))
+                )
+            {
+                return Err(PackageApiError(PackageError::HeadMismatch { current_head }));
+            }
+        }
+
+        // Verify the signature on the record itself before storing it
+        self.core_service
+            .store()
+            .verify_package_record_signature(&log_id, &record)
+            .await?;
+
+        self.check_yank_policy(&log_id, &record).await?;
+        self.check_artifact_sizes(&record)?;
+
+        // Records requiring an operator countersignature are staged until one is attached
+        let staged = match self
+            .countersignature_policy(&body.package_name)
+            .map(|policy| policy.check(&record))
+        {
+            None | Some(Ok(())) => false,
+            Some(Err(package::ValidationError::CountersignatureRequired)) => true,
+            Some(Err(e)) => return Err(PackageApiError::bad_request(e)),
+        };
+
+        // Reject a replay of a record that was already submitted, even if it
+        // was rejected; the record id covers the signed timestamp and previous
+        // record, so a captured record cannot be submitted again. Storing the
+        // record rejects it too, for a replay racing the original submission.
+        //
+        // No separate per-record nonce is needed: a nonce outside the signed
+        // contents could be rewritten by whoever replays the record, and the
+        // signed contents already make the record id unique since a record
+        // is linked to the record before it and can only be appended once
+        let record_id = RecordId::package_record::<Sha256>(&record);
+        if self
             .core_service
-            .submit_package_record(log_id, record_id.clone())
-            .await;
+            .store()
+            .get_package_record(&log_id, &record_id)
+            .await
+            .is_ok()
+        {
+            return Err(PackageApiError(PackageError::RecordAlreadySubmitted(
+                record_id,
+            )));
+        }
+
+        // The parts of a co-publication are held until every part is submitted
+        if let Some(copublication) = &body.copublication {
+            CoPublication::verify_signed(copublication).map_err(PackageApiError::bad_request)?;
+            if copublication.key_id() != record.key_id() {
+                return Err(PackageApiError::bad_request(
+                    "the co-publication must be signed by the key signing the record",
+                ));
+            }
+
+            let copublication = copublication.as_ref();
+            if copublication
+                .part(&body.package_name)
+                .map(|part| &part.record_id)
+                != Some(&record_id)
+            {
+                return Err(PackageApiError::bad_request(format!(
+                    "the co-publication does not include record `{record_id}` of package `{name}`",
+                    name = body.package_name
+                )));
+            }
+
+            let leaves = copublication
+                .parts
+                .iter()
+                .map(|part| LogLeaf {
+                    log_id: LogId::package_log::<Sha256>(&part.package),
+                    record_id: part.record_id.clone(),
+                })
+                .collect();
+            if !self.core_service.register_copublication(leaves) {
+                return Err(PackageApiError::bad_request(
+                    "a record of the co-publication is part of another co-publication",
+                ));
+            }
+        }
+
+        let mut missing = record.as_ref().contents();
+        missing.retain(|d| !self.content_present(d));
+
+        self.core_service
+            .store()
+            .store_package_record(&log_id, &body.package_name, &record_id, &record, &missing)
+            .await?;
+
+        // If there's no missing content, submit the record for processing now
+        if missing.is_empty() {
+            if staged {
+                return Ok(PackageRecord {
+                    record_id,
+                    state: PackageRecordState::Staged,
+                });
+            }
+
+            self.core_service
+                .submit_package_record(log_id, record_id.clone())
+                .await;
 
-        return Ok((
-            StatusCode::ACCEPTED,
-            Json(PackageRecord {
+            return Ok(PackageRecord {
                 record_id,
                 state: PackageRecordState::Processing,
-            }),
-        ));
-    }
+            });
+        }
 
-    let missing_content = config.build_missing_content(&log_id, &record_id, missing);
-    Ok((
-        StatusCode::ACCEPTED,
-        Json(PackageRecord {
+        let missing_content = self.build_missing_content(&log_id, &record_id, missing);
+        Ok(PackageRecord {
             record_id,
             state: PackageRecordState::Sourcing { missing_content },
-        }),
-    ))
+        })
+    }
 }
 
 #[debug_handler]
@@ -287,15 +703,26 @@ async fn get_record(
                 state: PackageRecordState::Sourcing { missing_content },
             }))
         }
+        RecordStatus::Pending if config.is_staged(&log_id, &record.envelope).await? => {
+            Ok(Json(PackageRecord {
+                record_id,
+                state: PackageRecordState::Staged,
+            }))
+        }
         // Validated is considered still processing until included in a checkpoint
         RecordStatus::Pending | RecordStatus::Validated => Ok(Json(PackageRecord {
             record_id,
             state: PackageRecordState::Processing,
         })),
-        RecordStatus::Rejected(reason) => Ok(Json(PackageRecord {
-            record_id,
-            state: PackageRecordState::Rejected { reason },
-        })),
+        RecordStatus::Rejected(reason) => {
+            let conflict = config
+                .release_conflict(&log_id, &record_id, &record.envelope, &reason)
+                .await?;
+            Ok(Json(PackageRecord {
+                record_id,
+                state: PackageRecordState::Rejected { reason, conflict },
+            }))
+        }
         RecordStatus::Published => {
             let registry_index = record.registry_index.unwrap();
 
@@ -307,6 +734,40 @@ async fn get_record(
     }
 }
 
+#[debug_handler]
+async fn get_record_receipt(
+    State(config): State<Config>,
+    Path((log_id, record_id)): Path<(LogId, RecordId)>,
+    RegistryHeader(_registry_header): RegistryHeader,
+) -> Result<Json<PackageRecordReceipt>, PackageApiError> {
+    let record = config
+        .core_service
+        .store()
+        .get_package_record(&log_id, &record_id)
+        .await?;
+    let registry_index = match (record.status, record.registry_index) {
+        (RecordStatus::Published, Some(registry_index)) => registry_index,
+        _ => return Err(PackageApiError::not_published(&record_id)),
+    };
+
+    let receipt = config
+        .core_service
+        .record_receipt(log_id, record_id.clone(), registry_index)
+        .await
+        .map_err(PackageApiError::internal_error)?
+        .ok_or_else(|| PackageApiError::not_published(&record_id))?;
+    let proof = config
+        .core_service
+        .log_inclusion_proofs(receipt.as_ref().checkpoint.log_length, &[registry_index])
+        .await
+        .map_err(PackageApiError::internal_error)?;
+
+    Ok(Json(PackageRecordReceipt {
+        receipt,
+        proof: proof.encode(),
+    }))
+}
+
 #[debug_handler]
 async fn upload_content(
     State(config): State<Config>,
@@ -341,11 +802,54 @@ async fn upload_content(
         path = tmp_path.display()
     );
 
+    let record = config
+        .core_service
+        .store()
+        .get_package_record(&log_id, &record_id)
+        .await?;
+    let entries = &record.envelope.as_ref().entries;
+
+    // Content policies cannot inspect encrypted content, so if the registry
+    // allows it they are not applied to content released encrypted; manifest
+    // artifacts are not components, so they are only checked against their
+    // declared size
+    let encrypted = config.allow_encrypted_content
+        && entries.iter().any(|entry| match entry {
+            package::PackageEntry::Release {
+                content,
+                encryption,
+                ..
+            } => *content == digest && encryption.is_some(),
+            _ => false,
+        });
+    let artifact_size = entries
+        .iter()
+        .all(|entry| entry.content() != Some(&digest))
+        .then(|| {
+            entries
+                .iter()
+                .flat_map(|entry| match entry {
+                    package::PackageEntry::Release {
+                        manifest: Some(manifest),
+                        ..
+                    } => manifest.artifacts.as_slice(),
+                    _ => &[],
+                })
+                .find(|artifact| artifact.content == digest)
+                .map(|artifact| artifact.size)
+        })
+        .flatten();
+
     let res = process_content(
         &tmp_path,
         &digest,
         body.into_data_stream(),
-        config.content_policy.as_deref(),
+        if encrypted || artifact_size.is_some() {
+            None
+        } else {
+            config.content_policy.as_deref()
+        },
+        artifact_size,
     )
     .await;
 
@@ -365,24 +869,104 @@ async fn upload_content(
     // Only persist the file if the content was successfully processed
     res?;
 
+    let path = config.files.path(&digest);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(PackageApiError::internal_error)?;
+    }
     tmp_path
-        .persist(config.content_path(&digest))
+        .persist(path)
         .map_err(PackageApiError::internal_error)?;
 
     // If this is the last content needed, submit the record for processing now
+    // unless it is staged awaiting a countersignature
     if config
         .core_service
         .store()
         .set_content_present(&log_id, &record_id, &digest)
         .await?
     {
+        let record = config
+            .core_service
+            .store()
+            .get_package_record(&log_id, &record_id)
+            .await?;
+
+        if !config.is_staged(&log_id, &record.envelope).await? {
+            config
+                .core_service
+                .submit_package_record(log_id, record_id.clone())
+                .await;
+        }
+    }
+
+    Ok(StatusCode::CREATED)
+}
+
+#[debug_handler]
+async fn countersign_record(
+    State(config): State<Config>,
+    Path((log_id, record_id)): Path<(LogId, RecordId)>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Json(countersignature): Json<Countersignature>,
+) -> Result<impl IntoResponse, PackageApiError> {
+    let record = config
+        .core_service
+        .store()
+        .get_package_record(&log_id, &record_id)
+        .await?;
+
+    match record.status {
+        RecordStatus::MissingContent(_) | RecordStatus::Pending
+            if config.is_staged(&log_id, &record.envelope).await? => {}
+        _ => return Err(PackageApiError::not_staged(&record_id)),
+    }
+
+    countersignature
+        .verify(
+            &config.core_service.operator_public_key(),
+            record.envelope.content_bytes(),
+        )
+        .map_err(|_| {
+            PackageApiError(PackageError::Unauthorized(
+                "the countersignature was not made by the registry operator".into(),
+            ))
+        })?;
+
+    match config
+        .core_service
+        .store()
+        .set_package_record_countersignature(&log_id, &record_id, &countersignature)
+        .await
+    {
+        Ok(()) => {}
+        Err(DataStoreError::RecordNotPending(_)) => {
+            return Err(PackageApiError::not_staged(&record_id))
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    // If the content is already present, submit the record for processing now
+    let mut missing = record.envelope.as_ref().contents();
+    missing.retain(|d| !config.content_present(d));
+    if missing.is_empty() {
         config
             .core_service
             .submit_package_record(log_id, record_id.clone())
             .await;
+
+        return Ok(Json(PackageRecord {
+            record_id,
+            state: PackageRecordState::Processing,
+        }));
     }
 
-    Ok(StatusCode::CREATED)
+    let missing_content = config.build_missing_content(&log_id, &record_id, missing);
+    Ok(Json(PackageRecord {
+        record_id,
+        state: PackageRecordState::Sourcing { missing_content },
+    }))
 }
 
 async fn process_content(
@@ -390,6 +974,7 @@ async fn process_content(
     digest: &AnyHash,
     mut stream: BodyDataStream,
     policy: Option<&dyn ContentPolicy>,
+    expected_size: Option<u64>,
 ) -> Result<(), PackageApiError> {
     let mut tmp_file = tokio::fs::File::create(&path)
         .await
@@ -397,6 +982,7 @@ async fn process_content(
 
     let mut hasher = digest.algorithm().hasher();
     let mut policy = policy.map(|p| p.new_stream_policy(digest)).transpose()?;
+    let mut size = 0u64;
 
     while let Some(chunk) = stream
         .next()
@@ -408,6 +994,7 @@ async fn process_content(
             policy.check(&chunk)?;
         }
 
+        size += chunk.len() as u64;
         hasher.update(&chunk);
         tmp_file
             .write_all(&chunk)
@@ -422,6 +1009,12 @@ async fn process_content(
         )));
     }
 
+    if let Some(expected) = expected_size.filter(|expected| *expected != size) {
+        return Err(PackageApiError(PackageError::Rejection(format!(
+            "content is {size} byte(s) but the release manifest declares {expected} byte(s)"
+        ))));
+    }
+
     if let Some(mut policy) = policy {
         policy.finalize()?;
     }
@@ -4,7 +4,8 @@ use axum::{
     debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::post, Router,
 };
 use warg_api::v1::proof::{
-    ConsistencyRequest, ConsistencyResponse, InclusionRequest, InclusionResponse, ProofError,
+    ConsistencyRequest, ConsistencyResponse, DeltaRequest, DeltaResponse, InclusionRequest,
+    InclusionResponse, ProofError,
 };
 use warg_protocol::registry::{RegistryIndex, RegistryLen};
 
@@ -21,6 +22,7 @@ impl Config {
     pub fn into_router(self) -> Router {
         Router::new()
             .route("/consistency", post(prove_consistency))
+            .route("/delta", post(prove_delta))
             .route("/inclusion", post(prove_inclusion))
             .with_state(self)
     }
@@ -40,6 +42,10 @@ impl From<CoreServiceError> for ProofApiError {
             CoreServiceError::IncorrectProof { root, found } => {
                 ProofError::IncorrectProof { root, found }
             }
+            e @ CoreServiceError::InvalidCheckpointRange { .. } => ProofError::Message {
+                status: 400,
+                message: e.to_string(),
+            },
             other => {
                 tracing::error!("Unhandled CoreServiceError: {other:?}");
                 ProofError::Message {
@@ -94,3 +100,17 @@ async fn prove_inclusion(
         map: map_bundle.encode(),
     }))
 }
+
+#[debug_handler]
+async fn prove_delta(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Json(body): Json<DeltaRequest>,
+) -> Result<Json<DeltaResponse>, ProofApiError> {
+    let (changes, map_bundle) = config.core.map_delta(body.from, body.to).await?;
+
+    Ok(Json(DeltaResponse {
+        changes,
+        map: map_bundle.encode(),
+    }))
+}
@@ -0,0 +1,61 @@
+use super::{Json, RegistryHeader};
+use crate::services::SearchIndex;
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use warg_api::v1::search::{SearchError, SearchQuery, SearchResponse};
+
+const DEFAULT_SEARCH_LIMIT: u16 = 100;
+const MAX_SEARCH_LIMIT: u16 = 1000;
+
+#[derive(Clone)]
+pub struct Config {
+    index: Option<SearchIndex>,
+}
+
+impl Config {
+    pub fn new(index: Option<SearchIndex>) -> Self {
+        Self { index }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new().route("/", get(search)).with_state(self)
+    }
+}
+
+struct SearchApiError(SearchError);
+
+impl IntoResponse for SearchApiError {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+    }
+}
+
+#[debug_handler]
+async fn search(
+    State(config): State<Config>,
+    RegistryHeader(_registry_header): RegistryHeader,
+    Query(query): Query<SearchQuery<'static>>,
+) -> Result<Json<SearchResponse>, SearchApiError> {
+    let index = config
+        .index
+        .as_ref()
+        .ok_or(SearchApiError(SearchError::NotSupported))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit == 0 || limit > MAX_SEARCH_LIMIT {
+        return Err(SearchApiError(SearchError::Message {
+            status: StatusCode::BAD_REQUEST.as_u16(),
+            message: format!("search limit must be between 1 and {MAX_SEARCH_LIMIT}"),
+        }));
+    }
+
+    Ok(Json(SearchResponse {
+        packages: index.search(&query.q, limit as usize).await,
+    }))
+}
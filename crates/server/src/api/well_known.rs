@@ -0,0 +1,49 @@
+use super::v1::Json;
+use axum::{
+    debug_handler, extract::State, http::StatusCode, response::IntoResponse, routing::get, Router,
+};
+use std::sync::Arc;
+use warg_api::v1::fetch::FetchError;
+use warg_protocol::{discovery::OperatorKeys, SerdeEnvelope};
+
+#[derive(Clone)]
+pub struct Config {
+    operator_keys: Option<Arc<SerdeEnvelope<OperatorKeys>>>,
+}
+
+impl Config {
+    pub fn new(operator_keys: Option<Arc<SerdeEnvelope<OperatorKeys>>>) -> Self {
+        Self { operator_keys }
+    }
+
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/warg/operator-keys", get(operator_keys))
+            .with_state(self)
+    }
+}
+
+struct WellKnownApiError(FetchError);
+
+impl IntoResponse for WellKnownApiError {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::from_u16(self.0.status()).unwrap(), Json(self.0)).into_response()
+    }
+}
+
+#[debug_handler]
+async fn operator_keys(
+    State(config): State<Config>,
+) -> Result<Json<SerdeEnvelope<OperatorKeys>>, WellKnownApiError> {
+    config
+        .operator_keys
+        .as_deref()
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| {
+            WellKnownApiError(FetchError::Message {
+                status: StatusCode::NOT_FOUND.as_u16(),
+                message: "the registry does not publish its operator keys".to_string(),
+            })
+        })
+}
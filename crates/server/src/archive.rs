@@ -0,0 +1,232 @@
+//! Archives old ranges of the registry log to cold storage.
+//!
+//! See [`warg_api::v1::archive`] for a description of the layout.
+
+use crate::{
+    datastore::DataStoreError,
+    services::{CoreService, CoreServiceError},
+};
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use warg_api::v1::archive::{self, ArchiveSegment, SegmentRecord};
+use warg_crypto::{
+    hash::{HashAlgorithm, Sha256},
+    signing::{self, PrivateKey},
+};
+use warg_protocol::{
+    archive::{ArchiveManifest, ArchivedRange},
+    registry::{LogId, RegistryIndex, RegistryLen},
+    PublishedProtoEnvelope, PublishedProtoEnvelopeBody, SerdeEnvelope,
+};
+
+const DEFAULT_SEGMENT_LEN: RegistryLen = 10000;
+const PAGE_SIZE: usize = 1000;
+
+/// Represents an error that occurred while archiving the registry log.
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    /// A data store error occurred.
+    #[error("data store error: {0}")]
+    DataStore(#[from] DataStoreError),
+    /// A proof could not be generated.
+    #[error(transparent)]
+    CoreService(#[from] CoreServiceError),
+    /// A record in the registry log has not been published.
+    #[error("the record at registry index {0} has not been published")]
+    RecordNotPublished(RegistryIndex),
+    /// The manifest could not be created.
+    #[error("failed to create archive manifest: {0}")]
+    Manifest(anyhow::Error),
+    /// The manifest could not be signed.
+    #[error("failed to sign archive manifest: {0}")]
+    Signing(#[from] signing::SignatureError),
+    /// An I/O error occurred writing to the output directory.
+    #[error("failed to write `{path}`: {source}")]
+    Io {
+        /// The path that failed to be written.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// A file could not be serialized.
+    #[error("failed to serialize `{path}`: {source}")]
+    Serialization {
+        /// The path that failed to be serialized.
+        path: PathBuf,
+        /// The underlying serialization error.
+        source: serde_json::Error,
+    },
+}
+
+/// A summary of an archival.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    /// The registry log length of the checkpoint of the manifest.
+    pub log_length: RegistryLen,
+    /// The number of segments archived.
+    pub segments: usize,
+    /// The number of records archived.
+    pub records: usize,
+}
+
+/// Archives complete segments of the registry log as of its latest
+/// checkpoint, signing a manifest of the segments with the operator key.
+///
+/// The output directory may then be uploaded to an object store, after
+/// which the archived records no longer need to be served by the registry.
+/// The output is deterministic apart from the manifest timestamp.
+pub struct LogArchiver<'a> {
+    core: &'a CoreService,
+    operator_key: &'a PrivateKey,
+    segment_len: RegistryLen,
+}
+
+impl<'a> LogArchiver<'a> {
+    /// Creates a new archiver for the given core service.
+    pub fn new(core: &'a CoreService, operator_key: &'a PrivateKey) -> Self {
+        Self {
+            core,
+            operator_key,
+            segment_len: DEFAULT_SEGMENT_LEN,
+        }
+    }
+
+    /// Sets the number of records in each segment.
+    ///
+    /// Defaults to 10000.
+    pub fn with_segment_len(mut self, len: RegistryLen) -> Self {
+        self.segment_len = len.max(1);
+        self
+    }
+
+    /// Archives the registry log to the given output directory.
+    ///
+    /// Only complete segments are archived; the records after the last
+    /// complete segment are left for a later archival.
+    pub async fn archive(&self, output: &Path) -> Result<ArchiveSummary, ArchiveError> {
+        let checkpoint = self
+            .core
+            .store()
+            .get_latest_checkpoint()
+            .await?
+            .as_ref()
+            .checkpoint
+            .clone();
+        let log_length = checkpoint.log_length;
+
+        let mut summary = ArchiveSummary {
+            log_length,
+            ..Default::default()
+        };
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start + self.segment_len <= log_length {
+            let end = start + self.segment_len;
+            let segment = self.segment(log_length, start, end).await?;
+            summary.records += segment.records.len();
+
+            let bytes = to_json(&output.join(archive::segment(start, end)), &segment)?;
+            segments.push(ArchivedRange {
+                start,
+                end,
+                digest: HashAlgorithm::Sha256.digest(&bytes),
+            });
+            write(output, &archive::segment(start, end), &bytes)?;
+            start = end;
+        }
+        summary.segments = segments.len();
+
+        let manifest =
+            ArchiveManifest::now(checkpoint, segments).map_err(ArchiveError::Manifest)?;
+        let manifest = SerdeEnvelope::signed_contents(self.operator_key, manifest)?;
+        let path = output.join(archive::manifest());
+        write(output, archive::manifest(), &to_json(&path, &manifest)?)?;
+
+        Ok(summary)
+    }
+
+    async fn segment(
+        &self,
+        log_length: RegistryLen,
+        start: RegistryIndex,
+        end: RegistryIndex,
+    ) -> Result<ArchiveSegment, ArchiveError> {
+        let store = self.core.store();
+        let operator_log_id = LogId::operator_log::<Sha256>();
+
+        let mut records = Vec::with_capacity(end - start);
+        while start + records.len() < end {
+            let next = start + records.len();
+            let leafs = store
+                .get_log_leafs_starting_with_registry_index(next, PAGE_SIZE.min(end - next))
+                .await?;
+            if leafs.is_empty() {
+                return Err(ArchiveError::RecordNotPublished(next));
+            }
+
+            for (index, leaf) in leafs {
+                if index >= end {
+                    break;
+                }
+
+                let record = if leaf.log_id == operator_log_id {
+                    let record = store
+                        .get_operator_record(&leaf.log_id, &leaf.record_id)
+                        .await?;
+                    PublishedProtoEnvelopeBody::from(PublishedProtoEnvelope {
+                        envelope: record.envelope,
+                        registry_index: index,
+                    })
+                } else {
+                    let record = store
+                        .get_package_record(&leaf.log_id, &leaf.record_id)
+                        .await?;
+                    PublishedProtoEnvelopeBody::from(PublishedProtoEnvelope {
+                        envelope: record.envelope,
+                        registry_index: index,
+                    })
+                };
+
+                records.push(SegmentRecord {
+                    log_id: leaf.log_id,
+                    record,
+                });
+            }
+        }
+
+        let indexes: Vec<_> = (start..end).collect();
+        let proof = self
+            .core
+            .log_inclusion_proofs(log_length, &indexes)
+            .await?
+            .encode();
+
+        Ok(ArchiveSegment { records, proof })
+    }
+}
+
+fn to_json(path: &Path, value: &impl Serialize) -> Result<Vec<u8>, ArchiveError> {
+    let mut json =
+        serde_json::to_vec_pretty(value).map_err(|source| ArchiveError::Serialization {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+fn write(output: &Path, path: &str, bytes: &[u8]) -> Result<(), ArchiveError> {
+    let path = output.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| ArchiveError::Io {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+
+    fs::write(&path, bytes).map_err(|source| ArchiveError::Io { path, source })
+}
@@ -0,0 +1,217 @@
+//! Module for authenticating requests to the registry.
+//!
+//! When an [`Authenticator`] is configured with
+//! [`Config::with_authenticator`](crate::Config::with_authenticator), every
+//! request is authenticated before it reaches the API, so that private
+//! registries can protect fetching and publishing without modifying the
+//! HTTP layer.
+
+use axum::{
+    async_trait,
+    http::{header::AUTHORIZATION, HeaderMap},
+};
+use indexmap::IndexSet;
+use serde::Deserialize;
+use std::fmt;
+use thiserror::Error;
+use warg_crypto::hash::{AnyHash, HashAlgorithm};
+
+/// The access required by a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Fetching logs, checkpoints, proofs, and content.
+    Read,
+    /// Publishing records and uploading content.
+    Publish,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Publish => write!(f, "publish"),
+        }
+    }
+}
+
+/// Represents an authentication error.
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// The request did not provide valid credentials.
+    #[error("the request requires authentication")]
+    Unauthenticated,
+    /// The provided credentials do not grant the required access.
+    #[error("the provided credentials do not grant {0} access")]
+    Forbidden(Access),
+}
+
+/// The result type returned by authenticators.
+pub type AuthResult<T> = Result<T, AuthError>;
+
+/// A trait implemented by request authenticators.
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    /// Authenticates a request requiring the given access.
+    ///
+    /// The request is rejected if an error is returned.
+    async fn authenticate(&self, access: Access, headers: &HeaderMap) -> AuthResult<()>;
+}
+
+/// Gets the bearer token of a request, if it has one.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// An authenticator that accepts a known set of bearer tokens.
+///
+/// Tokens are identified by their SHA-256 digests so that the tokens
+/// themselves need not be stored in the registry's configuration.
+///
+/// Tokens granting publish access also grant read access.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BearerTokenAuthenticator {
+    #[serde(default)]
+    anonymous_read: bool,
+    #[serde(default)]
+    read: IndexSet<AnyHash>,
+    #[serde(default)]
+    publish: IndexSet<AnyHash>,
+}
+
+impl BearerTokenAuthenticator {
+    /// Creates a new bearer token authenticator.
+    ///
+    /// By default, no tokens are accepted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether requests requiring only read access are accepted
+    /// without a token.
+    pub fn with_anonymous_read(mut self, anonymous_read: bool) -> Self {
+        self.anonymous_read = anonymous_read;
+        self
+    }
+
+    /// Accepts the token with the given SHA-256 digest for the given access.
+    pub fn with_token_digest(mut self, access: Access, digest: AnyHash) -> Self {
+        match access {
+            Access::Read => self.read.insert(digest),
+            Access::Publish => self.publish.insert(digest),
+        };
+        self
+    }
+
+    /// Accepts the given token for the given access.
+    pub fn with_token(self, access: Access, token: &str) -> Self {
+        self.with_token_digest(access, HashAlgorithm::Sha256.digest(token.as_bytes()))
+    }
+}
+
+#[async_trait]
+impl Authenticator for BearerTokenAuthenticator {
+    async fn authenticate(&self, access: Access, headers: &HeaderMap) -> AuthResult<()> {
+        if access == Access::Read && self.anonymous_read {
+            return Ok(());
+        }
+
+        let token = bearer_token(headers).ok_or(AuthError::Unauthenticated)?;
+        let digest = HashAlgorithm::Sha256.digest(token.as_bytes());
+        if self.publish.contains(&digest) {
+            return Ok(());
+        }
+
+        if self.read.contains(&digest) {
+            return match access {
+                Access::Read => Ok(()),
+                Access::Publish => Err(AuthError::Forbidden(access)),
+            };
+        }
+
+        Err(AuthError::Unauthenticated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(token: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn bearer_tokens_grant_access() {
+        let authenticator = BearerTokenAuthenticator::new()
+            .with_token(Access::Read, "reader")
+            .with_token(Access::Publish, "publisher");
+
+        assert!(matches!(
+            authenticator
+                .authenticate(Access::Read, &headers(None))
+                .await,
+            Err(AuthError::Unauthenticated)
+        ));
+        assert!(matches!(
+            authenticator
+                .authenticate(Access::Read, &headers(Some("unknown")))
+                .await,
+            Err(AuthError::Unauthenticated)
+        ));
+        assert!(authenticator
+            .authenticate(Access::Read, &headers(Some("reader")))
+            .await
+            .is_ok());
+        assert!(matches!(
+            authenticator
+                .authenticate(Access::Publish, &headers(Some("reader")))
+                .await,
+            Err(AuthError::Forbidden(Access::Publish))
+        ));
+        for access in [Access::Read, Access::Publish] {
+            assert!(authenticator
+                .authenticate(access, &headers(Some("publisher")))
+                .await
+                .is_ok());
+        }
+
+        let authenticator = authenticator.with_anonymous_read(true);
+        assert!(authenticator
+            .authenticate(Access::Read, &headers(None))
+            .await
+            .is_ok());
+        assert!(authenticator
+            .authenticate(Access::Publish, &headers(None))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn parses_bearer_tokens() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("bearer abc "));
+        assert_eq!(bearer_token(&headers), Some("abc"));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer "));
+        assert_eq!(bearer_token(&headers), None);
+    }
+}
@@ -1,13 +1,31 @@
 use anyhow::{Context, Result};
 use clap::{Parser, ValueEnum};
 use secrecy::SecretString;
-use std::{net::SocketAddr, path::PathBuf};
+#[cfg(feature = "fault-injection")]
+use std::num::NonZeroU64;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 use tokio::signal;
 use tracing_subscriber::filter::LevelFilter;
 use url::Url;
-use warg_crypto::signing::PrivateKey;
-use warg_protocol::operator;
-use warg_server::{args::get_opt_secret, policy::record::AuthorizedKeyPolicy, Config, Server};
+use warg_crypto::{
+    hash::StorageLayout,
+    signing::{KeyID, PrivateKey},
+};
+use warg_protocol::{
+    operator,
+    package::{ValidationRules, YankPolicy},
+    policy::TimeWindow,
+};
+use warg_server::{
+    args::get_opt_secret,
+    auth::BearerTokenAuthenticator,
+    policy::{
+        record::{AuthorizedKeyPolicy, RecordPolicyCollection},
+        staging::StagingPolicy,
+    },
+    services::{FileLease, Quarantine},
+    Config, Server,
+};
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum DataStoreKind {
@@ -31,6 +49,12 @@ struct Args {
     #[arg(long, env = "WARG_CONTENT_DIR")]
     content_dir: PathBuf,
 
+    /// The layout of new content files: `flat`, `hash-prefix`, or `date`.
+    ///
+    /// Defaults to storing content files directly in the content directory.
+    #[arg(long, env = "WARG_CONTENT_LAYOUT")]
+    content_layout: Option<StorageLayout>,
+
     /// The base content URL to use; defaults to the server address.
     #[arg(long, env = "WARG_CONTENT_BASE_URL")]
     content_base_url: Option<Url>,
@@ -67,13 +91,122 @@ struct Args {
     #[arg(long, env = "WARG_OPERATOR_KEY_FILE", conflicts_with = "operator_key")]
     operator_key_file: Option<PathBuf>,
 
+    /// The path to the key to sign checkpoints with once an admin rotates
+    /// the checkpoint key.
+    #[arg(long, env = "WARG_NEXT_OPERATOR_KEY_FILE")]
+    next_operator_key_file: Option<PathBuf>,
+
     /// The path to the authorized keys record policy file.
     #[arg(long, env = "WARG_AUTHORIZED_KEYS_FILE")]
     authorized_keys_file: Option<PathBuf>,
 
+    /// The path to the operator keys document, signed by the registry's root
+    /// of trust, to serve at `/.well-known/warg/operator-keys`.
+    #[arg(long, env = "WARG_OPERATOR_KEYS_FILE")]
+    operator_keys_file: Option<PathBuf>,
+
+    /// The path to the bearer tokens file for authenticating requests.
+    ///
+    /// If not specified, requests are not authenticated.
+    #[arg(long, env = "WARG_AUTH_TOKENS_FILE")]
+    auth_tokens_file: Option<PathBuf>,
+
     /// The initial namespace defined for this registry.
     #[arg(long, env = "WARG_NAMESPACE")]
     namespace: Option<String>,
+
+    /// The namespaces whose package initializations are staged until
+    /// countersigned by the operator.
+    #[arg(
+        long = "reserved-namespace",
+        env = "WARG_RESERVED_NAMESPACES",
+        value_delimiter = ','
+    )]
+    reserved_namespaces: Vec<String>,
+
+    /// The number of seconds between checkpoints.
+    #[arg(long, env = "WARG_CHECKPOINT_INTERVAL_SECS")]
+    checkpoint_interval_secs: Option<u64>,
+
+    /// The lock file granting this server the right to sign checkpoints.
+    ///
+    /// Servers sharing a data store should share a lock file so that only
+    /// one of them sequences records at a time.
+    #[arg(long, env = "WARG_SEQUENCER_LOCK_FILE")]
+    sequencer_lock_file: Option<PathBuf>,
+
+    /// The number of seconds after which an unrenewed sequencer lock expires.
+    #[arg(long, env = "WARG_SEQUENCER_LEASE_TTL_SECS", default_value_t = 60)]
+    sequencer_lease_ttl_secs: u64,
+
+    /// The number of submitted records that triggers a checkpoint before the
+    /// checkpoint interval elapses.
+    #[arg(long, env = "WARG_CHECKPOINT_BATCH_SIZE")]
+    checkpoint_batch_size: Option<usize>,
+
+    /// Stage key rotations in any package until countersigned by the operator.
+    #[arg(long, env = "WARG_COUNTERSIGN_KEY_ROTATION")]
+    countersign_key_rotation: bool,
+
+    /// The number of days after release during which a version may be yanked
+    /// without an advisory.
+    #[arg(long, env = "WARG_YANK_WINDOW_DAYS")]
+    yank_window_days: Option<u64>,
+
+    /// The identifier prefixes of advisories that allow yanks after the yank
+    /// window, such as `CVE-`.
+    #[arg(
+        long = "advisory-prefix",
+        env = "WARG_ADVISORY_PREFIXES",
+        value_delimiter = ',',
+        requires = "yank_window_days"
+    )]
+    advisory_prefixes: Vec<String>,
+
+    /// The window relative to the server clock within which record timestamps
+    /// must fall, as `<max-future-skew>,<max-past-age>`.
+    #[arg(long, env = "WARG_TIME_WINDOW", default_value = "5m,1d")]
+    time_window: TimeWindow,
+
+    /// Require permission grants to prove possession of the granted key.
+    ///
+    /// Clients and auditors must validate package logs under the same rule.
+    #[arg(long, env = "WARG_REQUIRE_KEY_POSSESSION")]
+    require_key_possession: bool,
+
+    /// The webhook URLs to notify of registry events.
+    #[arg(long = "webhook-url", env = "WARG_WEBHOOK_URLS", value_delimiter = ',')]
+    webhook_urls: Vec<Url>,
+
+    /// The IDs of keys to declare compromised.
+    #[arg(long = "deny-key", env = "WARG_DENIED_KEYS", value_delimiter = ',')]
+    denied_keys: Vec<String>,
+
+    /// The number of rejected package records to hold for inspection with
+    /// the admin API.
+    #[arg(long, env = "WARG_QUARANTINE_CAPACITY")]
+    quarantine_capacity: Option<usize>,
+
+    /// Drop every Nth published record after validation.
+    ///
+    /// For testing monitors and clients only.
+    #[cfg(feature = "fault-injection")]
+    #[arg(long, env = "WARG_FAULT_DROP_PUBLISH_EVERY")]
+    fault_drop_publish_every: Option<NonZeroU64>,
+
+    /// The number of milliseconds to delay every checkpoint by.
+    ///
+    /// For testing monitors and clients only.
+    #[cfg(feature = "fault-injection")]
+    #[arg(long, env = "WARG_FAULT_CHECKPOINT_DELAY_MS")]
+    fault_checkpoint_delay_ms: Option<u64>,
+
+    /// Corrupt every Nth log inclusion proof served.
+    ///
+    /// For testing monitors and clients only.
+    #[cfg(feature = "fault-injection")]
+    #[arg(long, env = "WARG_FAULT_CORRUPT_PROOF_EVERY")]
+    fault_corrupt_proof_every: Option<NonZeroU64>,
 }
 
 impl Args {
@@ -108,16 +241,115 @@ async fn main() -> Result<()> {
         .with_addr(args.listen)
         .with_shutdown(shutdown_signal());
 
+    if let Some(layout) = args.content_layout {
+        config = config.with_content_layout(layout);
+    }
+
     if let Some(url) = args.content_base_url {
         config = config.with_content_base_url(url);
     }
 
-    if let Some(path) = args.authorized_keys_file {
-        let authorized_keys_data = std::fs::read_to_string(&path)
+    if let Some(secs) = args.checkpoint_interval_secs {
+        config = config.with_checkpoint_interval(Duration::from_secs(secs));
+    }
+
+    if let Some(size) = args.checkpoint_batch_size {
+        config = config.with_checkpoint_batch_size(size);
+    }
+
+    if let Some(path) = args.sequencer_lock_file {
+        config = config.with_sequencer_lease(FileLease::new(
+            path,
+            Duration::from_secs(args.sequencer_lease_ttl_secs),
+        ));
+    }
+
+    for url in args.webhook_urls {
+        config = config.with_webhook(url);
+    }
+
+    if let Some(path) = args.next_operator_key_file {
+        let key_str = get_opt_secret("next-operator-key", Some(path), None)?;
+        let key = PrivateKey::decode(key_str).context("failed to parse next operator key")?;
+        config = config.with_next_operator_key(key);
+    }
+
+    for key_id in args.denied_keys {
+        config = config.with_denied_key(KeyID::from(key_id));
+    }
+
+    if let Some(path) = &args.auth_tokens_file {
+        let auth_tokens_data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read auth tokens from {path:?}"))?;
+        let authenticator: BearerTokenAuthenticator = toml::from_str(&auth_tokens_data)
+            .with_context(|| format!("failed to decode auth tokens from {path:?}"))?;
+        config = config.with_authenticator(authenticator);
+    }
+
+    if let Some(path) = &args.operator_keys_file {
+        let operator_keys_data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read operator keys from {path:?}"))?;
+        let operator_keys = serde_json::from_str(&operator_keys_data)
+            .with_context(|| format!("failed to decode operator keys from {path:?}"))?;
+        config = config.with_operator_keys(operator_keys);
+    }
+
+    let mut record_policy = RecordPolicyCollection::new();
+    if let Some(path) = &args.authorized_keys_file {
+        let authorized_keys_data = std::fs::read_to_string(path)
             .with_context(|| format!("failed to read authorized keys from {path:?}"))?;
         let authorized_key_policy: AuthorizedKeyPolicy = toml::from_str(&authorized_keys_data)
             .with_context(|| format!("failed to decode authorized keys from {path:?}"))?;
-        config = config.with_record_policy(authorized_key_policy);
+        record_policy.push(authorized_key_policy);
+    }
+
+    if args.authorized_keys_file.is_some() {
+        config = config.with_record_policy(record_policy);
+    }
+
+    if args.require_key_possession {
+        config = config.with_validation_rules(ValidationRules::new().with_key_possession(true));
+    }
+
+    if !args.reserved_namespaces.is_empty() || args.countersign_key_rotation {
+        let mut staging_policy =
+            StagingPolicy::new().with_key_rotation(args.countersign_key_rotation);
+        for namespace in args.reserved_namespaces {
+            staging_policy = staging_policy.with_reserved_namespace(namespace)?;
+        }
+        config = config.with_staging_policy(staging_policy);
+    }
+
+    config = config.with_time_window(args.time_window);
+
+    if let Some(days) = args.yank_window_days {
+        let yank_policy = args
+            .advisory_prefixes
+            .into_iter()
+            .fold(YankPolicy::new(days), YankPolicy::with_advisory_prefix);
+        config = config.with_yank_policy(yank_policy);
+    }
+
+    if let Some(capacity) = args.quarantine_capacity {
+        config = config.with_quarantine(Quarantine::new(capacity));
+    }
+
+    #[cfg(feature = "fault-injection")]
+    if args.fault_drop_publish_every.is_some()
+        || args.fault_checkpoint_delay_ms.is_some()
+        || args.fault_corrupt_proof_every.is_some()
+    {
+        let mut faults = warg_server::faults::Faults::new();
+        if let Some(n) = args.fault_drop_publish_every {
+            faults = faults.with_dropped_publishes(n);
+        }
+        if let Some(ms) = args.fault_checkpoint_delay_ms {
+            faults = faults.with_checkpoint_delay(Duration::from_millis(ms));
+        }
+        if let Some(n) = args.fault_corrupt_proof_every {
+            faults = faults.with_corrupted_proofs(n);
+        }
+        config = config.with_faults(faults);
     }
 
     let config = match args.data_store {
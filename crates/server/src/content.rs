@@ -0,0 +1,130 @@
+//! The content files stored by a registry.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use warg_crypto::hash::{AnyHash, StorageLayout};
+
+/// The content files of a registry, stored in a directory.
+///
+/// Without a layout, a content file is named `<algo>-<hex>` directly in the
+/// directory, and its URL path is its file name. With a layout, new content
+/// files are arranged by the layout and their URL paths follow it.
+///
+/// Files are located whether stored without a layout or under any layout,
+/// so the layout of a directory may change once content is stored.
+///
+/// Cloning the files produces a handle to the same directory.
+#[derive(Debug, Clone)]
+pub struct ContentFiles {
+    dir: PathBuf,
+    layout: Option<StorageLayout>,
+}
+
+impl ContentFiles {
+    /// Creates the content files stored in the given directory without a layout.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            layout: None,
+        }
+    }
+
+    /// Sets the layout of new content files.
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Gets the directory of the content files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Locates the file of the content with the given digest.
+    ///
+    /// Returns `None` if the content is not present.
+    pub fn locate(&self, digest: &AnyHash) -> Option<PathBuf> {
+        let unarranged = || Some(self.unarranged_path(digest)).filter(|p| p.is_file());
+        match self.layout {
+            Some(layout) => layout.locate(&self.dir, digest).or_else(unarranged),
+            None => unarranged().or_else(|| StorageLayout::default().locate(&self.dir, digest)),
+        }
+    }
+
+    /// Determines if the content with the given digest is present.
+    pub fn contains(&self, digest: &AnyHash) -> bool {
+        self.locate(digest).is_some()
+    }
+
+    /// Gets the path at which to store new content with the given digest.
+    ///
+    /// The parent directories of the path may not exist.
+    pub fn path(&self, digest: &AnyHash) -> PathBuf {
+        match self.layout {
+            Some(layout) => layout.path(&self.dir, digest, SystemTime::now()),
+            None => self.unarranged_path(digest),
+        }
+    }
+
+    /// Gets the URL path, relative to the content URL of the registry, of
+    /// the given content file.
+    ///
+    /// Returns `None` if the file is not in the directory.
+    pub fn url_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        let segments = relative
+            .iter()
+            .map(|segment| segment.to_str())
+            .collect::<Option<Vec<_>>>()?;
+        Some(segments.join("/"))
+    }
+
+    fn unarranged_path(&self, digest: &AnyHash) -> PathBuf {
+        self.dir.join(digest.to_string().replace(':', "-"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use warg_crypto::hash::{Hash, Sha256};
+
+    #[test]
+    fn it_locates_files_after_a_layout_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let unarranged: AnyHash = Hash::<Sha256>::of("unarranged").into();
+        let arranged: AnyHash = Hash::<Sha256>::of("arranged").into();
+
+        let files = ContentFiles::new(dir.path());
+        let unarranged_path = files.path(&unarranged);
+        fs::write(&unarranged_path, "unarranged").unwrap();
+        assert_eq!(
+            files.url_path(&unarranged_path).unwrap(),
+            unarranged.to_string().replace(':', "-")
+        );
+
+        let files = files.with_layout(StorageLayout::HashPrefix);
+        let arranged_path = files.path(&arranged);
+        fs::create_dir_all(arranged_path.parent().unwrap()).unwrap();
+        fs::write(&arranged_path, "arranged").unwrap();
+        let hex = arranged.to_string().replace("sha256:", "");
+        assert_eq!(
+            files.url_path(&arranged_path).unwrap(),
+            format!("sha256/{}/{}/{hex}", &hex[..2], &hex[2..4])
+        );
+
+        // Content is found whichever layout it was stored under
+        for files in [
+            files.clone(),
+            files.clone().with_layout(StorageLayout::Date),
+            ContentFiles::new(dir.path()),
+        ] {
+            assert_eq!(files.locate(&unarranged), Some(unarranged_path.clone()));
+            assert_eq!(files.locate(&arranged), Some(arranged_path.clone()));
+        }
+        assert!(!files.contains(&Hash::<Sha256>::of("missing").into()));
+    }
+}
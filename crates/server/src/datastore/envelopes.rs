@@ -0,0 +1,119 @@
+use indexmap::IndexMap;
+use warg_protocol::{registry::RecordId, ProtoEnvelope};
+
+struct StoredEnvelope<R> {
+    envelope: ProtoEnvelope<R>,
+    refs: usize,
+}
+
+/// A content-addressed store of record envelopes.
+///
+/// Envelopes are keyed by their record identifier and reference counted by
+/// the indexes that refer to them, so storing an identical envelope more than
+/// once (e.g. a re-submission or an imported record) does not duplicate it.
+/// Envelopes are never removed, as rejected records remain queryable.
+pub struct EnvelopeStore<R> {
+    envelopes: IndexMap<RecordId, StoredEnvelope<R>>,
+}
+
+impl<R> Default for EnvelopeStore<R> {
+    fn default() -> Self {
+        Self {
+            envelopes: IndexMap::new(),
+        }
+    }
+}
+
+impl<R: Clone> EnvelopeStore<R> {
+    /// Adds a reference to the given envelope, storing it if not already present.
+    ///
+    /// Returns `true` if the envelope was newly stored.
+    pub fn insert(&mut self, record_id: &RecordId, envelope: &ProtoEnvelope<R>) -> bool {
+        match self.envelopes.get_mut(record_id) {
+            Some(stored) => {
+                debug_assert!(stored.envelope.content_bytes() == envelope.content_bytes());
+                stored.refs += 1;
+                false
+            }
+            None => {
+                self.envelopes.insert(
+                    record_id.clone(),
+                    StoredEnvelope {
+                        envelope: envelope.clone(),
+                        refs: 1,
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+impl<R> EnvelopeStore<R> {
+    /// Gets the envelope with the given record identifier.
+    pub fn get(&self, record_id: &RecordId) -> Option<&ProtoEnvelope<R>> {
+        self.envelopes.get(record_id).map(|stored| &stored.envelope)
+    }
+
+    /// Gets a mutable reference to the envelope with the given record identifier.
+    ///
+    /// Callers must not modify the content of the envelope.
+    pub fn get_mut(&mut self, record_id: &RecordId) -> Option<&mut ProtoEnvelope<R>> {
+        self.envelopes
+            .get_mut(record_id)
+            .map(|stored| &mut stored.envelope)
+    }
+
+    /// Gets the number of references to the envelope with the given record identifier.
+    pub fn refs(&self, record_id: &RecordId) -> usize {
+        self.envelopes
+            .get(record_id)
+            .map(|stored| stored.refs)
+            .unwrap_or(0)
+    }
+
+    /// Gets the number of distinct envelopes in the store.
+    pub fn len(&self) -> usize {
+        self.envelopes.len()
+    }
+
+    /// Determines if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.envelopes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use warg_crypto::{hash::Sha256, signing::PrivateKey};
+    use warg_protocol::operator::{OperatorEntry, OperatorRecord};
+
+    #[test]
+    fn deduplicates_envelopes() {
+        let key = PrivateKey::decode(
+            "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+        )
+        .unwrap();
+        let record = OperatorRecord {
+            prev: None,
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::Init {
+                hash_algorithm: warg_crypto::hash::HashAlgorithm::Sha256,
+                key: key.public_key(),
+            }],
+        };
+        let envelope = ProtoEnvelope::signed_contents(&key, record).unwrap();
+        let record_id = RecordId::operator_record::<Sha256>(&envelope);
+
+        let mut store = EnvelopeStore::default();
+        assert!(store.insert(&record_id, &envelope));
+        assert!(!store.insert(&record_id, &envelope));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.refs(&record_id), 2);
+        assert!(store.get(&record_id).is_some());
+        assert!(!store.is_empty());
+    }
+}
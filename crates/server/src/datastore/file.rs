@@ -0,0 +1,607 @@
+use super::{DataStore, DataStoreError, MemoryDataStore, Record};
+use futures::Stream;
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+use warg_crypto::hash::AnyHash;
+use warg_protocol::{
+    operator, package,
+    registry::{
+        LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
+    },
+    Countersignature, ProtoEnvelope, ProtoEnvelopeBody, PublishedProtoEnvelope, SerdeEnvelope,
+    Version,
+};
+
+/// An operation that changed the state of a [`FileDataStore`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum JournalEntry {
+    #[serde(rename_all = "camelCase")]
+    StoreOperatorRecord {
+        log_id: LogId,
+        record_id: RecordId,
+        record: ProtoEnvelopeBody,
+    },
+    #[serde(rename_all = "camelCase")]
+    RejectOperatorRecord {
+        log_id: LogId,
+        record_id: RecordId,
+        reason: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    CommitOperatorRecord {
+        log_id: LogId,
+        record_id: RecordId,
+        registry_index: RegistryIndex,
+    },
+    #[serde(rename_all = "camelCase")]
+    StorePackageRecord {
+        log_id: LogId,
+        package_name: PackageName,
+        record_id: RecordId,
+        record: ProtoEnvelopeBody,
+        missing: Vec<AnyHash>,
+    },
+    #[serde(rename_all = "camelCase")]
+    RejectPackageRecord {
+        log_id: LogId,
+        record_id: RecordId,
+        reason: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    CommitPackageRecord {
+        log_id: LogId,
+        record_id: RecordId,
+        registry_index: RegistryIndex,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        policy: Option<package::CountersignaturePolicy>,
+        #[serde(default)]
+        rules: package::ValidationRules,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetContentPresent {
+        log_id: LogId,
+        record_id: RecordId,
+        digest: AnyHash,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetPackageRecordCountersignature {
+        log_id: LogId,
+        record_id: RecordId,
+        countersignature: Countersignature,
+    },
+    #[serde(rename_all = "camelCase")]
+    StoreCheckpoint {
+        checkpoint_id: AnyHash,
+        checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    },
+}
+
+impl JournalEntry {
+    /// Applies the entry to the given store.
+    ///
+    /// Operations that failed when first applied are journaled too, as a
+    /// failed operation may still change the state of the store (e.g. a
+    /// commit that fails validation rejects the record); replaying them
+    /// fails in the same way.
+    async fn apply(self, store: &MemoryDataStore) -> Result<(), DataStoreError> {
+        match self {
+            Self::StoreOperatorRecord {
+                log_id,
+                record_id,
+                record,
+            } => {
+                let record = ProtoEnvelope::from_stored(record)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                store
+                    .store_operator_record(&log_id, &record_id, &record)
+                    .await
+            }
+            Self::RejectOperatorRecord {
+                log_id,
+                record_id,
+                reason,
+            } => {
+                store
+                    .reject_operator_record(&log_id, &record_id, &reason)
+                    .await
+            }
+            Self::CommitOperatorRecord {
+                log_id,
+                record_id,
+                registry_index,
+            } => {
+                store
+                    .commit_operator_record(&log_id, &record_id, registry_index)
+                    .await
+            }
+            Self::StorePackageRecord {
+                log_id,
+                package_name,
+                record_id,
+                record,
+                missing,
+            } => {
+                let record = ProtoEnvelope::from_stored(record)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                store
+                    .store_package_record(
+                        &log_id,
+                        &package_name,
+                        &record_id,
+                        &record,
+                        &missing.iter().collect(),
+                    )
+                    .await
+            }
+            Self::RejectPackageRecord {
+                log_id,
+                record_id,
+                reason,
+            } => {
+                store
+                    .reject_package_record(&log_id, &record_id, &reason)
+                    .await
+            }
+            Self::CommitPackageRecord {
+                log_id,
+                record_id,
+                registry_index,
+                policy,
+                rules,
+            } => {
+                store
+                    .commit_package_record(
+                        &log_id,
+                        &record_id,
+                        registry_index,
+                        policy.as_ref(),
+                        &rules,
+                    )
+                    .await
+            }
+            Self::SetContentPresent {
+                log_id,
+                record_id,
+                digest,
+            } => store
+                .set_content_present(&log_id, &record_id, &digest)
+                .await
+                .map(|_| ()),
+            Self::SetPackageRecordCountersignature {
+                log_id,
+                record_id,
+                countersignature,
+            } => {
+                store
+                    .set_package_record_countersignature(&log_id, &record_id, &countersignature)
+                    .await
+            }
+            Self::StoreCheckpoint {
+                checkpoint_id,
+                checkpoint,
+            } => store.store_checkpoint(&checkpoint_id, checkpoint).await,
+        }
+    }
+}
+
+/// Represents a data store persisted to a journal file.
+///
+/// Every change to the store is appended to the journal, which is replayed
+/// into memory when the store is opened; reads are served from memory.
+///
+/// This is intended for local, single-process registries rather than
+/// production deployments.
+///
+/// Cloning the store produces a handle to the same data.
+#[derive(Clone)]
+pub struct FileDataStore {
+    path: PathBuf,
+    store: MemoryDataStore,
+    journal: Arc<Mutex<File>>,
+}
+
+impl FileDataStore {
+    /// Opens the data store journaled to the given file, creating the file
+    /// if it does not exist.
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, DataStoreError> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let store = MemoryDataStore::new();
+        if path.is_file() {
+            tracing::debug!(
+                "replaying data store journal `{path}`",
+                path = path.display()
+            );
+            for line in BufReader::new(File::open(&path)?).lines() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+
+                let entry: JournalEntry = serde_json::from_str(&line)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if let Err(e) = entry.apply(&store).await {
+                    tracing::debug!("replayed journal operation failed: {e}");
+                }
+            }
+        }
+
+        let journal = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            store,
+            journal: Arc::new(Mutex::new(journal)),
+        })
+    }
+
+    /// Gets the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies an operation to the store and appends it to the journal.
+    ///
+    /// The journal is locked while the operation is applied so that the
+    /// journal order matches the order the operations were applied in.
+    async fn apply<T>(
+        &self,
+        entry: JournalEntry,
+        op: impl std::future::Future<Output = Result<T, DataStoreError>>,
+    ) -> Result<T, DataStoreError> {
+        let mut journal = self.journal.lock().await;
+        let result = op.await;
+
+        let mut line = serde_json::to_vec(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        line.push(b'\n');
+        journal.write_all(&line)?;
+        result
+    }
+}
+
+#[axum::async_trait]
+impl DataStore for FileDataStore {
+    async fn get_all_checkpoints(
+        &self,
+    ) -> Result<
+        Pin<Box<dyn Stream<Item = Result<TimestampedCheckpoint, DataStoreError>> + Send>>,
+        DataStoreError,
+    > {
+        self.store.get_all_checkpoints().await
+    }
+
+    async fn get_all_validated_records(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LogLeaf, DataStoreError>> + Send>>, DataStoreError>
+    {
+        self.store.get_all_validated_records().await
+    }
+
+    async fn get_all_retained_contents(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<AnyHash, DataStoreError>> + Send>>, DataStoreError>
+    {
+        self.store.get_all_retained_contents().await
+    }
+
+    async fn get_log_leafs_with_registry_index(
+        &self,
+        entries: &[RegistryIndex],
+    ) -> Result<Vec<LogLeaf>, DataStoreError> {
+        self.store.get_log_leafs_with_registry_index(entries).await
+    }
+
+    async fn store_operator_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        record: &ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::StoreOperatorRecord {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                record: record.clone().into(),
+            },
+            self.store.store_operator_record(log_id, record_id, record),
+        )
+        .await
+    }
+
+    async fn reject_operator_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        reason: &str,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::RejectOperatorRecord {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                reason: reason.to_string(),
+            },
+            self.store.reject_operator_record(log_id, record_id, reason),
+        )
+        .await
+    }
+
+    async fn commit_operator_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        registry_index: RegistryIndex,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::CommitOperatorRecord {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                registry_index,
+            },
+            self.store
+                .commit_operator_record(log_id, record_id, registry_index),
+        )
+        .await
+    }
+
+    async fn store_package_record(
+        &self,
+        log_id: &LogId,
+        package_name: &PackageName,
+        record_id: &RecordId,
+        record: &ProtoEnvelope<package::PackageRecord>,
+        missing: &IndexSet<&AnyHash>,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::StorePackageRecord {
+                log_id: log_id.clone(),
+                package_name: package_name.clone(),
+                record_id: record_id.clone(),
+                record: record.clone().into(),
+                missing: missing.iter().map(|digest| **digest).collect(),
+            },
+            self.store
+                .store_package_record(log_id, package_name, record_id, record, missing),
+        )
+        .await
+    }
+
+    async fn reject_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        reason: &str,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::RejectPackageRecord {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                reason: reason.to_string(),
+            },
+            self.store.reject_package_record(log_id, record_id, reason),
+        )
+        .await
+    }
+
+    async fn commit_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        registry_index: RegistryIndex,
+        policy: Option<&package::CountersignaturePolicy>,
+        rules: &package::ValidationRules,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::CommitPackageRecord {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                registry_index,
+                policy: policy.cloned(),
+                rules: *rules,
+            },
+            self.store
+                .commit_package_record(log_id, record_id, registry_index, policy, rules),
+        )
+        .await
+    }
+
+    async fn is_content_missing(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        digest: &AnyHash,
+    ) -> Result<bool, DataStoreError> {
+        self.store
+            .is_content_missing(log_id, record_id, digest)
+            .await
+    }
+
+    async fn set_content_present(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        digest: &AnyHash,
+    ) -> Result<bool, DataStoreError> {
+        self.apply(
+            JournalEntry::SetContentPresent {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                digest: *digest,
+            },
+            self.store.set_content_present(log_id, record_id, digest),
+        )
+        .await
+    }
+
+    async fn set_package_record_countersignature(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        countersignature: &Countersignature,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::SetPackageRecordCountersignature {
+                log_id: log_id.clone(),
+                record_id: record_id.clone(),
+                countersignature: countersignature.clone(),
+            },
+            self.store
+                .set_package_record_countersignature(log_id, record_id, countersignature),
+        )
+        .await
+    }
+
+    async fn store_checkpoint(
+        &self,
+        checkpoint_id: &AnyHash,
+        ts_checkpoint: SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), DataStoreError> {
+        self.apply(
+            JournalEntry::StoreCheckpoint {
+                checkpoint_id: *checkpoint_id,
+                checkpoint: ts_checkpoint.clone(),
+            },
+            self.store.store_checkpoint(checkpoint_id, ts_checkpoint),
+        )
+        .await
+    }
+
+    async fn get_latest_checkpoint(
+        &self,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        self.store.get_latest_checkpoint().await
+    }
+
+    async fn get_checkpoint(
+        &self,
+        log_length: RegistryLen,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, DataStoreError> {
+        self.store.get_checkpoint(log_length).await
+    }
+
+    async fn get_package_names(
+        &self,
+        log_ids: &[LogId],
+    ) -> Result<IndexMap<LogId, Option<PackageName>>, DataStoreError> {
+        self.store.get_package_names(log_ids).await
+    }
+
+    async fn get_log_leafs_starting_with_registry_index(
+        &self,
+        starting_index: RegistryIndex,
+        limit: usize,
+    ) -> Result<Vec<(RegistryIndex, LogLeaf)>, DataStoreError> {
+        self.store
+            .get_log_leafs_starting_with_registry_index(starting_index, limit)
+            .await
+    }
+
+    async fn get_operator_records(
+        &self,
+        log_id: &LogId,
+        registry_log_length: RegistryLen,
+        since: Option<&RecordId>,
+        limit: u16,
+    ) -> Result<Vec<PublishedProtoEnvelope<operator::OperatorRecord>>, DataStoreError> {
+        self.store
+            .get_operator_records(log_id, registry_log_length, since, limit)
+            .await
+    }
+
+    async fn get_package_records(
+        &self,
+        log_id: &LogId,
+        registry_log_length: RegistryLen,
+        since: Option<&RecordId>,
+        limit: u16,
+    ) -> Result<Vec<PublishedProtoEnvelope<package::PackageRecord>>, DataStoreError> {
+        self.store
+            .get_package_records(log_id, registry_log_length, since, limit)
+            .await
+    }
+
+    async fn get_operator_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<Record<operator::OperatorRecord>, DataStoreError> {
+        self.store.get_operator_record(log_id, record_id).await
+    }
+
+    async fn get_package_record(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+    ) -> Result<Record<package::PackageRecord>, DataStoreError> {
+        self.store.get_package_record(log_id, record_id).await
+    }
+
+    async fn get_package_log_head(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        self.store.get_package_log_head(log_id).await
+    }
+
+    async fn get_package_release(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        self.store.get_package_release(log_id, version).await
+    }
+
+    async fn get_package_releases(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<package::Release>, DataStoreError> {
+        self.store.get_package_releases(log_id).await
+    }
+
+    async fn verify_package_record_signature(
+        &self,
+        log_id: &LogId,
+        record: &ProtoEnvelope<package::PackageRecord>,
+    ) -> Result<(), DataStoreError> {
+        self.store
+            .verify_package_record_signature(log_id, record)
+            .await
+    }
+
+    async fn verify_can_publish_package(
+        &self,
+        operator_log_id: &LogId,
+        package_name: &PackageName,
+    ) -> Result<(), DataStoreError> {
+        self.store
+            .verify_can_publish_package(operator_log_id, package_name)
+            .await
+    }
+
+    async fn verify_timestamped_checkpoint_signature(
+        &self,
+        operator_log_id: &LogId,
+        ts_checkpoint: &SerdeEnvelope<TimestampedCheckpoint>,
+    ) -> Result<(), DataStoreError> {
+        self.store
+            .verify_timestamped_checkpoint_signature(operator_log_id, ts_checkpoint)
+            .await
+    }
+
+    #[cfg(feature = "debug")]
+    async fn debug_list_package_names(&self) -> anyhow::Result<Vec<PackageName>> {
+        self.store.debug_list_package_names().await
+    }
+}
@@ -1,29 +1,32 @@
-use super::{DataStore, DataStoreError};
+use super::{DataStore, DataStoreError, EnvelopeStore};
 use futures::Stream;
-use indexmap::{IndexMap, IndexSet};
-use std::{pin::Pin, sync::Arc};
+use indexmap::{map::Entry as MapEntry, IndexMap, IndexSet};
+use std::{pin::Pin, sync::Arc, time::SystemTime};
 use tokio::sync::RwLock;
-use warg_crypto::{hash::AnyHash, Encode, Signable};
+use warg_crypto::{
+    hash::{AnyHash, Sha256},
+    Encode, Signable,
+};
 use warg_protocol::{
     operator,
     package::{self, PackageEntry},
     registry::{
         LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+    Countersignature, ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Version,
 };
 
-struct Entry<R> {
+struct Entry {
     registry_index: RegistryIndex,
-    record_content: ProtoEnvelope<R>,
+    record_id: RecordId,
 }
 
-struct Log<S, R> {
+struct Log<S> {
     state: S,
-    entries: Vec<Entry<R>>,
+    entries: Vec<Entry>,
 }
 
-impl<S, R> Default for Log<S, R>
+impl<S> Default for Log<S>
 where
     S: Default,
 {
@@ -43,24 +46,13 @@ struct Record {
 }
 
 enum PendingRecord {
-    Operator {
-        record: Option<ProtoEnvelope<operator::OperatorRecord>>,
-    },
-    Package {
-        record: Option<ProtoEnvelope<package::PackageRecord>>,
-        missing: IndexSet<AnyHash>,
-    },
+    Operator,
+    Package { missing: IndexSet<AnyHash> },
 }
 
 enum RejectedRecord {
-    Operator {
-        record: ProtoEnvelope<operator::OperatorRecord>,
-        reason: String,
-    },
-    Package {
-        record: ProtoEnvelope<package::PackageRecord>,
-        reason: String,
-    },
+    Operator { reason: String },
+    Package { reason: String },
 }
 
 enum RecordStatus {
@@ -71,8 +63,10 @@ enum RecordStatus {
 
 #[derive(Default)]
 struct State {
-    operators: IndexMap<LogId, Log<operator::LogState, operator::OperatorRecord>>,
-    packages: IndexMap<LogId, Log<package::LogState, package::PackageRecord>>,
+    operators: IndexMap<LogId, Log<operator::LogState>>,
+    packages: IndexMap<LogId, Log<package::LogState>>,
+    operator_envelopes: EnvelopeStore<operator::OperatorRecord>,
+    package_envelopes: EnvelopeStore<package::PackageRecord>,
     package_names: IndexMap<LogId, Option<PackageName>>,
     checkpoints: IndexMap<RegistryLen, SerdeEnvelope<TimestampedCheckpoint>>,
     records: IndexMap<LogId, IndexMap<RecordId, RecordStatus>>,
@@ -85,6 +79,9 @@ struct State {
 ///
 /// Note: this is mainly used for testing, so it is not very efficient as
 /// it shares a single RwLock for all operations.
+///
+/// Cloning the store produces a handle to the same data.
+#[derive(Clone)]
 pub struct MemoryDataStore(Arc<RwLock<State>>);
 
 impl MemoryDataStore {
@@ -107,14 +104,46 @@ impl DataStore for MemoryDataStore {
         Pin<Box<dyn Stream<Item = Result<TimestampedCheckpoint, DataStoreError>> + Send>>,
         DataStoreError,
     > {
-        Ok(Box::pin(futures::stream::empty()))
+        let state = self.0.read().await;
+        let checkpoints = state
+            .checkpoints
+            .values()
+            .map(|checkpoint| Ok(checkpoint.as_ref().clone()))
+            .collect::<Vec<_>>();
+        Ok(Box::pin(futures::stream::iter(checkpoints)))
     }
 
     async fn get_all_validated_records(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<LogLeaf, DataStoreError>> + Send>>, DataStoreError>
     {
-        Ok(Box::pin(futures::stream::empty()))
+        let state = self.0.read().await;
+        let leafs = state
+            .log_leafs
+            .values()
+            .cloned()
+            .map(Ok)
+            .collect::<Vec<_>>();
+        Ok(Box::pin(futures::stream::iter(leafs)))
+    }
+
+    async fn get_all_retained_contents(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<AnyHash, DataStoreError>> + Send>>, DataStoreError>
+    {
+        let state = self.0.read().await;
+        let contents = state
+            .records
+            .values()
+            .flat_map(|records| records.iter())
+            .filter(|(_, status)| !matches!(status, RecordStatus::Rejected(_)))
+            .filter_map(|(record_id, _)| state.package_envelopes.get(record_id))
+            .flat_map(|envelope| envelope.as_ref().contents())
+            .cloned()
+            .collect::<IndexSet<_>>();
+        Ok(Box::pin(futures::stream::iter(
+            contents.into_iter().map(Ok),
+        )))
     }
 
     async fn get_log_leafs_starting_with_registry_index(
@@ -183,14 +212,19 @@ impl DataStore for MemoryDataStore {
         record: &ProtoEnvelope<operator::OperatorRecord>,
     ) -> Result<(), DataStoreError> {
         let mut state = self.0.write().await;
-        let prev = state.records.entry(log_id.clone()).or_default().insert(
-            record_id.clone(),
-            RecordStatus::Pending(PendingRecord::Operator {
-                record: Some(record.clone()),
-            }),
-        );
-
-        assert!(prev.is_none());
+        match state
+            .records
+            .entry(log_id.clone())
+            .or_default()
+            .entry(record_id.clone())
+        {
+            MapEntry::Occupied(_) => return Err(DataStoreError::RecordExists(record_id.clone())),
+            MapEntry::Vacant(e) => {
+                e.insert(RecordStatus::Pending(PendingRecord::Operator));
+            }
+        }
+
+        state.operator_envelopes.insert(record_id, record);
         Ok(())
     }
 
@@ -209,13 +243,12 @@ impl DataStore for MemoryDataStore {
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
-        let record = match status {
-            RecordStatus::Pending(PendingRecord::Operator { record }) => record.take().unwrap(),
+        match status {
+            RecordStatus::Pending(PendingRecord::Operator) => {}
             _ => return Err(DataStoreError::RecordNotPending(record_id.clone())),
         };
 
         *status = RecordStatus::Rejected(RejectedRecord::Operator {
-            record,
             reason: reason.to_string(),
         });
 
@@ -232,6 +265,7 @@ impl DataStore for MemoryDataStore {
 
         let State {
             operators,
+            operator_envelopes,
             records,
             log_leafs,
             ..
@@ -244,13 +278,13 @@ impl DataStore for MemoryDataStore {
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
-            RecordStatus::Pending(PendingRecord::Operator { record }) => {
-                let record = record.take().unwrap();
+            RecordStatus::Pending(PendingRecord::Operator) => {
+                let record = operator_envelopes
+                    .get(record_id)
+                    .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
                 let log = operators.entry(log_id.clone()).or_default();
-                match log
-                    .state
-                    .clone()
-                    .validate(&record)
+                match operator::LogState::check_operator_record(record, registry_index)
+                    .and_then(|_| log.state.clone().validate(record))
                     .map_err(DataStoreError::from)
                 {
                     Ok(s) => {
@@ -258,7 +292,7 @@ impl DataStore for MemoryDataStore {
                         let index = log.entries.len();
                         log.entries.push(Entry {
                             registry_index,
-                            record_content: record,
+                            record_id: record_id.clone(),
                         });
                         *status = RecordStatus::Validated(Record {
                             index,
@@ -275,7 +309,6 @@ impl DataStore for MemoryDataStore {
                     }
                     Err(e) => {
                         *status = RecordStatus::Rejected(RejectedRecord::Operator {
-                            record,
                             reason: e.to_string(),
                         });
                         Err(e)
@@ -301,19 +334,27 @@ impl DataStore for MemoryDataStore {
             missing.is_subset(&contents)
         });
 
+        // A record is stored once, so that a replayed submission is rejected
+        // even when it races the original
         let mut state = self.0.write().await;
-        let prev = state.records.entry(log_id.clone()).or_default().insert(
-            record_id.clone(),
-            RecordStatus::Pending(PendingRecord::Package {
-                record: Some(record.clone()),
-                missing: missing.iter().map(|&d| d.clone()).collect(),
-            }),
-        );
+        match state
+            .records
+            .entry(log_id.clone())
+            .or_default()
+            .entry(record_id.clone())
+        {
+            MapEntry::Occupied(_) => return Err(DataStoreError::RecordExists(record_id.clone())),
+            MapEntry::Vacant(e) => {
+                e.insert(RecordStatus::Pending(PendingRecord::Package {
+                    missing: missing.iter().map(|&d| *d).collect(),
+                }));
+            }
+        }
         state
             .package_names
             .insert(log_id.clone(), Some(package_name.clone()));
 
-        assert!(prev.is_none());
+        state.package_envelopes.insert(record_id, record);
         Ok(())
     }
 
@@ -332,13 +373,12 @@ impl DataStore for MemoryDataStore {
             .get_mut(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
-        let record = match status {
-            RecordStatus::Pending(PendingRecord::Package { record, .. }) => record.take().unwrap(),
+        match status {
+            RecordStatus::Pending(PendingRecord::Package { .. }) => {}
             _ => return Err(DataStoreError::RecordNotPending(record_id.clone())),
         };
 
         *status = RecordStatus::Rejected(RejectedRecord::Package {
-            record,
             reason: reason.to_string(),
         });
 
@@ -350,11 +390,15 @@ impl DataStore for MemoryDataStore {
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        policy: Option<&package::CountersignaturePolicy>,
+        rules: &package::ValidationRules,
     ) -> Result<(), DataStoreError> {
         let mut state = self.0.write().await;
 
         let State {
+            operators,
             packages,
+            package_envelopes,
             records,
             log_leafs,
             ..
@@ -367,21 +411,39 @@ impl DataStore for MemoryDataStore {
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
-            RecordStatus::Pending(PendingRecord::Package { record, .. }) => {
-                let record = record.take().unwrap();
+            RecordStatus::Pending(PendingRecord::Package { .. }) => {
+                let record = package_envelopes
+                    .get(record_id)
+                    .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
                 let log = packages.entry(log_id.clone()).or_default();
-                match log
-                    .state
-                    .clone()
-                    .validate(&record)
-                    .map_err(DataStoreError::from)
-                {
+
+                // Records signed by keys declared compromised by the operator are rejected
+                let check = match operators.get(&LogId::operator_log::<Sha256>()) {
+                    Some(operator) => operator.state.check_package_record(record, registry_index),
+                    None => Ok(()),
+                };
+
+                // A publish token must still be valid when the record is sequenced
+                let check = check.map_err(DataStoreError::from).and_then(|_| {
+                    if record.as_ref().publish_token.is_some() {
+                        log.state
+                            .validate_publish_token(log_id, record, SystemTime::now())?;
+                    }
+                    Ok(())
+                });
+
+                match check.and_then(|_| {
+                    if let Some(policy) = policy {
+                        policy.check(record)?;
+                    }
+                    Ok(log.state.clone().validate_with_rules(record, rules)?)
+                }) {
                     Ok(state) => {
                         log.state = state;
                         let index = log.entries.len();
                         log.entries.push(Entry {
                             registry_index,
-                            record_content: record,
+                            record_id: record_id.clone(),
                         });
                         *status = RecordStatus::Validated(Record {
                             index,
@@ -398,7 +460,6 @@ impl DataStore for MemoryDataStore {
                     }
                     Err(e) => {
                         *status = RecordStatus::Rejected(RejectedRecord::Package {
-                            record,
                             reason: e.to_string(),
                         });
                         Err(e)
@@ -426,7 +487,7 @@ impl DataStore for MemoryDataStore {
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
-            RecordStatus::Pending(PendingRecord::Operator { .. }) => {
+            RecordStatus::Pending(PendingRecord::Operator) => {
                 // Operator records have no content
                 Ok(false)
             }
@@ -454,7 +515,7 @@ impl DataStore for MemoryDataStore {
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
         match status {
-            RecordStatus::Pending(PendingRecord::Operator { .. }) => {
+            RecordStatus::Pending(PendingRecord::Operator) => {
                 // Operator records have no content, so conceptually already present
                 Ok(false)
             }
@@ -471,6 +532,35 @@ impl DataStore for MemoryDataStore {
         }
     }
 
+    async fn set_package_record_countersignature(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        countersignature: &Countersignature,
+    ) -> Result<(), DataStoreError> {
+        let mut state = self.0.write().await;
+        let status = state
+            .records
+            .get(log_id)
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?
+            .get(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
+
+        match status {
+            RecordStatus::Pending(PendingRecord::Package { .. }) => {}
+            _ => return Err(DataStoreError::RecordNotPending(record_id.clone())),
+        }
+
+        let envelope = state
+            .package_envelopes
+            .get_mut(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
+        *envelope = envelope
+            .clone()
+            .with_countersignature(countersignature.clone());
+        Ok(())
+    }
+
     async fn store_checkpoint(
         &self,
         _checkpoint_id: &AnyHash,
@@ -537,7 +627,11 @@ impl DataStore for MemoryDataStore {
             .skip(start_log_idx)
             .take_while(|entry| entry.registry_index < registry_log_length)
             .map(|entry| PublishedProtoEnvelope {
-                envelope: entry.record_content.clone(),
+                envelope: state
+                    .operator_envelopes
+                    .get(&entry.record_id)
+                    .unwrap()
+                    .clone(),
                 registry_index: entry.registry_index,
             })
             .take(limit as usize)
@@ -576,7 +670,11 @@ impl DataStore for MemoryDataStore {
             .skip(start_log_idx)
             .take_while(|entry| entry.registry_index < registry_log_length)
             .map(|entry| PublishedProtoEnvelope {
-                envelope: entry.record_content.clone(),
+                envelope: state
+                    .package_envelopes
+                    .get(&entry.record_id)
+                    .unwrap()
+                    .clone(),
                 registry_index: entry.registry_index,
             })
             .take(limit as usize)
@@ -596,21 +694,12 @@ impl DataStore for MemoryDataStore {
             .get(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
-        let (status, envelope, registry_index) = match status {
-            RecordStatus::Pending(PendingRecord::Operator { record, .. }) => {
-                (super::RecordStatus::Pending, record.clone().unwrap(), None)
+        let (status, registry_index) = match status {
+            RecordStatus::Pending(PendingRecord::Operator) => (super::RecordStatus::Pending, None),
+            RecordStatus::Rejected(RejectedRecord::Operator { reason }) => {
+                (super::RecordStatus::Rejected(reason.into()), None)
             }
-            RecordStatus::Rejected(RejectedRecord::Operator { record, reason }) => (
-                super::RecordStatus::Rejected(reason.into()),
-                record.clone(),
-                None,
-            ),
             RecordStatus::Validated(r) => {
-                let log = state
-                    .operators
-                    .get(log_id)
-                    .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
-
                 let published_length = state
                     .checkpoints
                     .last()
@@ -623,13 +712,18 @@ impl DataStore for MemoryDataStore {
                     } else {
                         super::RecordStatus::Validated
                     },
-                    log.entries[r.index].record_content.clone(),
                     Some(r.registry_index),
                 )
             }
             _ => return Err(DataStoreError::RecordNotFound(record_id.clone())),
         };
 
+        let envelope = state
+            .operator_envelopes
+            .get(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?
+            .clone();
+
         Ok(super::Record {
             status,
             envelope,
@@ -650,21 +744,14 @@ impl DataStore for MemoryDataStore {
             .get(record_id)
             .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?;
 
-        let (status, envelope, registry_index) = match status {
-            RecordStatus::Pending(PendingRecord::Package { record, .. }) => {
-                (super::RecordStatus::Pending, record.clone().unwrap(), None)
+        let (status, registry_index) = match status {
+            RecordStatus::Pending(PendingRecord::Package { .. }) => {
+                (super::RecordStatus::Pending, None)
+            }
+            RecordStatus::Rejected(RejectedRecord::Package { reason }) => {
+                (super::RecordStatus::Rejected(reason.into()), None)
             }
-            RecordStatus::Rejected(RejectedRecord::Package { record, reason }) => (
-                super::RecordStatus::Rejected(reason.into()),
-                record.clone(),
-                None,
-            ),
             RecordStatus::Validated(r) => {
-                let log = state
-                    .packages
-                    .get(log_id)
-                    .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
-
                 let published_length = state
                     .checkpoints
                     .last()
@@ -677,13 +764,18 @@ impl DataStore for MemoryDataStore {
                     } else {
                         super::RecordStatus::Validated
                     },
-                    log.entries[r.index].record_content.clone(),
                     Some(r.registry_index),
                 )
             }
             _ => return Err(DataStoreError::RecordNotFound(record_id.clone())),
         };
 
+        let envelope = state
+            .package_envelopes
+            .get(record_id)
+            .ok_or_else(|| DataStoreError::RecordNotFound(record_id.clone()))?
+            .clone();
+
         Ok(super::Record {
             status,
             envelope,
@@ -691,27 +783,72 @@ impl DataStore for MemoryDataStore {
         })
     }
 
+    async fn get_package_log_head(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        let state = self.0.read().await;
+        Ok(state
+            .packages
+            .get(log_id)
+            .and_then(|log| log.state.head().as_ref())
+            .map(|head| head.digest.clone()))
+    }
+
+    async fn get_package_release(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        let state = self.0.read().await;
+        Ok(state
+            .packages
+            .get(log_id)
+            .and_then(|log| log.state.release(version))
+            .map(|release| release.record_id))
+    }
+
+    async fn get_package_releases(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<package::Release>, DataStoreError> {
+        let state = self.0.read().await;
+        Ok(state
+            .packages
+            .get(log_id)
+            .map(|log| log.state.stored_releases().collect())
+            .unwrap_or_default())
+    }
+
     async fn verify_package_record_signature(
         &self,
         log_id: &LogId,
         record: &ProtoEnvelope<package::PackageRecord>,
     ) -> Result<(), DataStoreError> {
         let state = self.0.read().await;
-        let key = match state
-            .packages
-            .get(log_id)
-            .and_then(|log| log.state.public_key(record.key_id()))
-        {
-            Some(key) => Some(key),
+        let log_state = state.packages.get(log_id).map(|log| &log.state);
+
+        // A record published under a publish token is signed by the key it authorizes
+        let token_key = match log_state {
+            Some(log_state) if record.as_ref().publish_token.is_some() => {
+                log_state.validate_publish_token(log_id, record, SystemTime::now())?
+            }
+            Some(_) => None,
+            None if record.as_ref().publish_token.is_some() => {
+                return Err(DataStoreError::LogNotFound(log_id.clone()))
+            }
+            None => None,
+        };
+
+        let key = match token_key.or_else(|| log_state?.public_key(record.key_id())) {
+            Some(key) => key,
             None => match record.as_ref().entries.first() {
-                Some(PackageEntry::Init { key, .. }) => Some(key),
+                Some(PackageEntry::Init { key, .. }) => key,
                 _ => return Err(DataStoreError::UnknownKey(record.key_id().clone())),
             },
-        }
-        .ok_or_else(|| DataStoreError::UnknownKey(record.key_id().clone()))?;
+        };
 
-        package::PackageRecord::verify(key, record.content_bytes(), record.signature())
-            .map_err(|_| DataStoreError::SignatureVerificationFailed(record.signature().clone()))
+        super::verify_package_record_signatures(key, record, |key_id| log_state?.public_key(key_id))
     }
 
     async fn verify_can_publish_package(
@@ -720,15 +857,14 @@ impl DataStore for MemoryDataStore {
         package_name: &PackageName,
     ) -> Result<(), DataStoreError> {
         let state = self.0.read().await;
-
-        // verify namespace is defined and not imported
-        match state
+        let state = &state
             .operators
             .get(operator_log_id)
             .ok_or_else(|| DataStoreError::LogNotFound(operator_log_id.clone()))?
-            .state
-            .namespace_state(package_name.namespace())
-        {
+            .state;
+
+        // verify namespace is defined and not imported
+        match state.namespace_state(package_name.namespace()) {
             Some(state) => match state {
                 operator::NamespaceState::Defined => {}
                 operator::NamespaceState::Imported { .. } => {
@@ -744,6 +880,10 @@ impl DataStore for MemoryDataStore {
             }
         }
 
+        if state.package_frozen(package_name) {
+            return Err(DataStoreError::PackageFrozen(package_name.clone()));
+        }
+
         Ok(())
     }
 
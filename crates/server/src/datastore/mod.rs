@@ -4,20 +4,26 @@ use std::pin::Pin;
 use thiserror::Error;
 use warg_crypto::{
     hash::AnyHash,
-    signing::{KeyID, Signature},
+    signing::{self, KeyID, Signature},
+    Signable,
 };
 use warg_protocol::{
-    operator, package,
+    operator,
+    package::{self, PackageEntry},
     registry::{
         LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen, TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope,
+    Countersignature, ProtoEnvelope, PublishedProtoEnvelope, SerdeEnvelope, Version,
 };
 
+mod envelopes;
+mod file;
 mod memory;
 #[cfg(feature = "postgres")]
 mod postgres;
 
+pub use envelopes::*;
+pub use file::*;
 pub use memory::*;
 #[cfg(feature = "postgres")]
 pub use postgres::*;
@@ -36,6 +42,9 @@ pub enum DataStoreError {
     #[error("record `{0}` was not found")]
     RecordNotFound(RecordId),
 
+    #[error("record `{0}` was already stored")]
+    RecordExists(RecordId),
+
     #[error("log leaf {0} was not found")]
     LogLeafNotFound(RegistryIndex),
 
@@ -62,6 +71,9 @@ pub enum DataStoreError {
     )]
     PackageNamespaceImported(String),
 
+    #[error("package `{0}` is frozen and cannot accept publishes")]
+    PackageFrozen(PackageName),
+
     #[error("key id `{0}` does not have permission")]
     KeyUnauthorized(KeyID),
 
@@ -74,6 +86,9 @@ pub enum DataStoreError {
     #[error("the record was rejected: {0}")]
     Rejection(String),
 
+    #[error("data store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[cfg(feature = "postgres")]
     #[error("a connection could not be established to the PostgreSQL server: {0}")]
     ConnectionPool(#[from] diesel_async::pooled_connection::deadpool::PoolError),
@@ -133,6 +148,17 @@ pub trait DataStore: Send + Sync {
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<LogLeaf, DataStoreError>> + Send>>, DataStoreError>;
 
+    /// Gets a stream of the content digests of all package records that
+    /// have not been rejected.
+    ///
+    /// Pending records are included, such as records staged for a
+    /// countersignature and the held parts of a co-publication.
+    ///
+    /// This is an expensive operation that scans every stored record.
+    async fn get_all_retained_contents(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<AnyHash, DataStoreError>> + Send>>, DataStoreError>;
+
     /// Looks up the log_id and record_id from the registry log index.  
     async fn get_log_leafs_with_registry_index(
         &self,
@@ -140,6 +166,8 @@ pub trait DataStore: Send + Sync {
     ) -> Result<Vec<LogLeaf>, DataStoreError>;
 
     /// Stores the given operator record.
+    ///
+    /// Returns [`DataStoreError::RecordExists`] if the record was already stored.
     async fn store_operator_record(
         &self,
         log_id: &LogId,
@@ -173,6 +201,9 @@ pub trait DataStore: Send + Sync {
     ///
     /// The `missing` set is the set of content digests that are currently
     /// missing from data storage.
+    ///
+    /// Returns [`DataStoreError::RecordExists`] if the record was already
+    /// stored, in whatever state it is now.
     async fn store_package_record(
         &self,
         log_id: &LogId,
@@ -197,11 +228,16 @@ pub trait DataStore: Send + Sync {
     /// The record must be in a pending state.
     ///
     /// If validation succeeds, the record will be considered part of the log.
+    ///
+    /// If a countersignature policy is given, the record is also validated
+    /// against it. The record is validated under the given rules.
     async fn commit_package_record(
         &self,
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        policy: Option<&package::CountersignaturePolicy>,
+        rules: &package::ValidationRules,
     ) -> Result<(), DataStoreError>;
 
     /// Determines if the given content digest is missing for the record.
@@ -229,6 +265,16 @@ pub trait DataStore: Send + Sync {
         digest: &AnyHash,
     ) -> Result<bool, DataStoreError>;
 
+    /// Attaches an operator countersignature to the given package record.
+    ///
+    /// The record must be in a pending state.
+    async fn set_package_record_countersignature(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        countersignature: &Countersignature,
+    ) -> Result<(), DataStoreError>;
+
     /// Stores a new checkpoint.
     async fn store_checkpoint(
         &self,
@@ -292,10 +338,37 @@ pub trait DataStore: Send + Sync {
         record_id: &RecordId,
     ) -> Result<Record<package::PackageRecord>, DataStoreError>;
 
+    /// Gets the head of a package log.
+    ///
+    /// Returns `None` if the log does not exist or has no validated records.
+    async fn get_package_log_head(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Option<RecordId>, DataStoreError>;
+
+    /// Gets the identifier of the record that released the given version of a package.
+    ///
+    /// Returns `None` if the log does not exist or the version was not released.
+    async fn get_package_release(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Option<RecordId>, DataStoreError>;
+
+    /// Gets the releases of a package, including yanked releases, in package
+    /// log order.
+    ///
+    /// Returns an empty list if the log does not exist.
+    async fn get_package_releases(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<package::Release>, DataStoreError>;
+
     /// Verifies the signature of a package record.
     ///
     /// This is different from `validate_package_record` in that
-    /// only the signature on the envelope is verified.
+    /// only the signature on the envelope and the signatures of
+    /// individually signed entries are verified, as a batch.
     ///
     /// It does not attempt to validate the record itself.
     async fn verify_package_record_signature(
@@ -304,9 +377,9 @@ pub trait DataStore: Send + Sync {
         record: &ProtoEnvelope<package::PackageRecord>,
     ) -> Result<(), DataStoreError>;
 
-    /// Verifies the package name is unique in a case insensitive way and that the
+    /// Verifies the package name is unique in a case insensitive way, that the
     /// package namespace is defined for this registry and is not imported
-    /// from another registry.
+    /// from another registry, and that the package is not frozen.
     async fn verify_can_publish_package(
         &self,
         operator_log_id: &LogId,
@@ -327,3 +400,48 @@ pub trait DataStore: Send + Sync {
         anyhow::bail!("not implemented")
     }
 }
+
+/// Verifies the envelope signature of a package record and the signatures of
+/// its individually signed entries as a single batch.
+///
+/// The envelope is verified with the given key. Entry signatures are verified
+/// with keys known to the log or keys granted by the record itself; entry
+/// signatures by any other key are left for validation to reject.
+fn verify_package_record_signatures<'a>(
+    key: &'a signing::PublicKey,
+    record: &'a ProtoEnvelope<package::PackageRecord>,
+    known_key: impl Fn(&KeyID) -> Option<&'a signing::PublicKey>,
+) -> Result<(), DataStoreError> {
+    let contents = record.as_ref();
+    let granted = contents
+        .entries
+        .iter()
+        .filter_map(|entry| match entry {
+            PackageEntry::Init { key, .. } | PackageEntry::GrantFlat { key, .. } => {
+                Some((key.fingerprint(), key))
+            }
+            _ => None,
+        })
+        .collect::<IndexMap<_, _>>();
+
+    let mut signatures = vec![(
+        key,
+        package::PackageRecord::prefixed_message(record.content_bytes()),
+        record.signature(),
+    )];
+    for entry_signature in &contents.entry_signatures {
+        let key = known_key(&entry_signature.key_id)
+            .or_else(|| granted.get(&entry_signature.key_id).copied());
+        let message = contents.entry_signing_message(entry_signature.entry);
+        if let (Some(key), Some(message)) = (key, message) {
+            signatures.push((key, message, &entry_signature.signature));
+        }
+    }
+
+    signing::batch_verify(
+        signatures
+            .iter()
+            .map(|(key, message, signature)| signing::BatchItem::new(key, message, signature)),
+    )
+    .map_err(|e| DataStoreError::SignatureVerificationFailed(signatures[e.failed()[0]].2.clone()))
+}
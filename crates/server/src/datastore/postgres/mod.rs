@@ -18,16 +18,20 @@ use diesel_migrations::{
 use futures::{Stream, StreamExt};
 use indexmap::{IndexMap, IndexSet};
 use secrecy::{ExposeSecret, SecretString};
-use std::pin::Pin;
-use warg_crypto::{hash::AnyHash, Decode, Encode, Signable};
+use std::{pin::Pin, time::SystemTime};
+use warg_crypto::{
+    hash::{AnyHash, Sha256},
+    Encode, Signable,
+};
 use warg_protocol::{
-    operator,
+    migrate, operator,
     package::{self, PackageEntry},
     registry::{
         Checkpoint, LogId, LogLeaf, PackageName, RecordId, RegistryIndex, RegistryLen,
         TimestampedCheckpoint,
     },
-    ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Validator,
+    Countersignature, ProtoEnvelope, PublishedProtoEnvelope, Record as _, SerdeEnvelope, Validator,
+    Version,
 };
 
 mod models;
@@ -35,7 +39,7 @@ mod schema;
 
 sql_function!(fn lower(x: Nullable<Text>) -> Nullable<Text>);
 
-async fn get_records<R: Decode>(
+async fn get_records<R: warg_protocol::Record>(
     conn: &mut AsyncPgConnection,
     log_id: i32,
     registry_log_length: RegistryLen,
@@ -83,7 +87,7 @@ async fn get_records<R: Decode>(
         .await?
         .into_iter()
         .map(
-            |(record_id, c, index)| match ProtoEnvelope::from_protobuf(&c) {
+            |(record_id, c, index)| match migrate::decode_stored_envelope(&c) {
                 Ok(envelope) => Ok(PublishedProtoEnvelope {
                     envelope,
                     registry_index: index.unwrap() as RegistryIndex,
@@ -152,7 +156,7 @@ where
                 .await
                 .map_err(|e| match e {
                     diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
-                        DataStoreError::Conflict
+                        DataStoreError::RecordExists(record_id.clone())
                     }
                     e => e.into(),
                 })?;
@@ -212,6 +216,7 @@ async fn commit_record<V>(
     log_id: i32,
     record_id: &RecordId,
     registry_index: RegistryIndex,
+    validate: impl FnOnce(V, &ProtoEnvelope<V::Record>) -> Result<V, DataStoreError> + Send,
 ) -> Result<(), DataStoreError>
 where
     V: Validator + 'static,
@@ -241,7 +246,7 @@ where
                 .optional()?
                 .ok_or_else(|| DataStoreError::RecordNotPending(record_id.clone()))?;
 
-            let record = ProtoEnvelope::<V::Record>::from_protobuf(&content).map_err(|e| {
+            let record = migrate::decode_stored_envelope::<V::Record>(&content).map_err(|e| {
                 DataStoreError::InvalidRecordContents {
                     record_id: record_id.clone(),
                     message: e.to_string(),
@@ -249,7 +254,7 @@ where
             })?;
 
             // Validate the record
-            let validator = validator.0.validate(&record).map_err(Into::into)?;
+            let validator = validate(validator.0, &record)?;
 
             // Store the updated validation state
             diesel::update(schema::logs::table)
@@ -342,7 +347,7 @@ where
                 super::RecordStatus::Rejected(record.reason.unwrap_or_default())
             }
         },
-        envelope: ProtoEnvelope::from_protobuf(&record.content).map_err(|e| {
+        envelope: migrate::decode_stored_envelope(&record.content).map_err(|e| {
             DataStoreError::InvalidRecordContents {
                 record_id: record_id.clone(),
                 message: e.to_string(),
@@ -450,6 +455,25 @@ impl DataStore for PostgresDataStore {
         ))
     }
 
+    async fn get_all_retained_contents(
+        &self,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<AnyHash, DataStoreError>> + Send>>, DataStoreError>
+    {
+        // The returned future will keep the connection from the pool until dropped
+        let mut conn = self.pool.get().await?;
+
+        Ok(Box::pin(
+            schema::contents::table
+                .inner_join(schema::records::table)
+                .select(schema::contents::digest)
+                .filter(schema::records::status.ne(RecordStatus::Rejected))
+                .distinct()
+                .load_stream::<ParsedText<AnyHash>>(&mut conn)
+                .await?
+                .map(|r| r.map_err(Into::into).map(|digest| digest.0)),
+        ))
+    }
+
     async fn get_log_leafs_starting_with_registry_index(
         &self,
         starting_index: RegistryIndex,
@@ -608,8 +632,18 @@ impl DataStore for PostgresDataStore {
             .optional()?
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        match commit_record::<operator::LogState>(conn.as_mut(), log_id, record_id, registry_index)
-            .await
+        // Keys cannot be denied as of a registry log length already passed
+        match commit_record::<operator::LogState>(
+            conn.as_mut(),
+            log_id,
+            record_id,
+            registry_index,
+            |state, record| {
+                operator::LogState::check_operator_record(record, registry_index)?;
+                Ok(state.validate(record)?)
+            },
+        )
+        .await
         {
             Ok(()) => Ok(()),
             Err(e) => {
@@ -662,9 +696,11 @@ impl DataStore for PostgresDataStore {
         log_id: &LogId,
         record_id: &RecordId,
         registry_index: RegistryIndex,
+        policy: Option<&package::CountersignaturePolicy>,
+        rules: &package::ValidationRules,
     ) -> Result<(), DataStoreError> {
         let mut conn = self.pool.get().await?;
-        let log_id = schema::logs::table
+        let id = schema::logs::table
             .select(schema::logs::id)
             .filter(schema::logs::log_id.eq(TextRef(log_id)))
             .first::<i32>(conn.as_mut())
@@ -672,12 +708,42 @@ impl DataStore for PostgresDataStore {
             .optional()?
             .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
 
-        match commit_record::<package::LogState>(conn.as_mut(), log_id, record_id, registry_index)
+        // Records signed by keys declared compromised by the operator are rejected,
+        // as are records missing a countersignature required by the policy and
+        // records whose publish token is no longer valid; the record is then
+        // validated under the rules
+        let operator = schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(&LogId::operator_log::<Sha256>())))
+            .first::<Json<operator::LogState>>(conn.as_mut())
             .await
+            .optional()?;
+        let validate = |state: package::LogState,
+                        record: &ProtoEnvelope<package::PackageRecord>| {
+            if let Some(operator) = &operator {
+                operator.check_package_record(record, registry_index)?;
+            }
+            if let Some(policy) = policy {
+                policy.check(record)?;
+            }
+            if record.as_ref().publish_token.is_some() {
+                state.validate_publish_token(log_id, record, SystemTime::now())?;
+            }
+            Ok(state.validate_with_rules(record, rules)?)
+        };
+
+        match commit_record::<package::LogState>(
+            conn.as_mut(),
+            id,
+            record_id,
+            registry_index,
+            validate,
+        )
+        .await
         {
             Ok(()) => Ok(()),
             Err(e) => {
-                reject_record(conn.as_mut(), log_id, record_id, &e.to_string()).await?;
+                reject_record(conn.as_mut(), id, record_id, &e.to_string()).await?;
                 Err(e)
             }
         }
@@ -769,6 +835,49 @@ impl DataStore for PostgresDataStore {
         .await
     }
 
+    async fn set_package_record_countersignature(
+        &self,
+        log_id: &LogId,
+        record_id: &RecordId,
+        countersignature: &Countersignature,
+    ) -> Result<(), DataStoreError> {
+        let mut conn = self.pool.get().await?;
+        conn.transaction::<_, DataStoreError, _>(|conn| {
+            async move {
+                let (id, content) = schema::records::table
+                    .inner_join(schema::logs::table)
+                    .select((schema::records::id, schema::records::content))
+                    .filter(
+                        schema::records::status
+                            .eq(RecordStatus::Pending)
+                            .and(schema::logs::log_id.eq(TextRef(log_id)))
+                            .and(schema::records::record_id.eq(TextRef(record_id))),
+                    )
+                    .first::<(i32, Vec<u8>)>(conn.as_mut())
+                    .await
+                    .optional()?
+                    .ok_or_else(|| DataStoreError::RecordNotPending(record_id.clone()))?;
+
+                let record = migrate::decode_stored_envelope::<package::PackageRecord>(&content)
+                    .map_err(|e| DataStoreError::InvalidRecordContents {
+                        record_id: record_id.clone(),
+                        message: e.to_string(),
+                    })?
+                    .with_countersignature(countersignature.clone());
+
+                diesel::update(schema::records::table)
+                    .filter(schema::records::id.eq(id))
+                    .set(schema::records::content.eq(record.to_protobuf()))
+                    .execute(conn.as_mut())
+                    .await?;
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
     async fn store_checkpoint(
         &self,
         checkpoint_id: &AnyHash,
@@ -929,6 +1038,57 @@ impl DataStore for PostgresDataStore {
         get_record::<package::LogState>(conn.as_mut(), log_id, record_id).await
     }
 
+    async fn get_package_log_head(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let validator = schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<Json<package::LogState>>(&mut conn)
+            .await
+            .optional()?;
+
+        Ok(validator.and_then(|v| v.head().as_ref().map(|head| head.digest.clone())))
+    }
+
+    async fn get_package_release(
+        &self,
+        log_id: &LogId,
+        version: &Version,
+    ) -> Result<Option<RecordId>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let validator = schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<Json<package::LogState>>(&mut conn)
+            .await
+            .optional()?;
+
+        Ok(validator.and_then(|v| v.release(version).map(|release| release.record_id)))
+    }
+
+    async fn get_package_releases(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Vec<package::Release>, DataStoreError> {
+        let mut conn = self.pool.get().await?;
+
+        let validator = schema::logs::table
+            .select(schema::logs::validator)
+            .filter(schema::logs::log_id.eq(TextRef(log_id)))
+            .first::<Json<package::LogState>>(&mut conn)
+            .await
+            .optional()?;
+
+        Ok(validator
+            .map(|v| v.stored_releases().collect())
+            .unwrap_or_default())
+    }
+
     async fn verify_package_record_signature(
         &self,
         log_id: &LogId,
@@ -943,11 +1103,20 @@ impl DataStore for PostgresDataStore {
             .await
             .optional()?;
 
+        // A record published under a publish token is signed by the key it authorizes
+        let token_key = match &validator {
+            Some(validator) if record.as_ref().publish_token.is_some() => {
+                validator.validate_publish_token(log_id, record, SystemTime::now())?
+            }
+            Some(_) => None,
+            None if record.as_ref().publish_token.is_some() => {
+                return Err(DataStoreError::LogNotFound(log_id.clone()))
+            }
+            None => None,
+        };
+
         #[allow(clippy::get_first)] // Vec::first() conflicts with diesel's RunQueryDsl
-        let key = match validator
-            .as_ref()
-            .and_then(|v| v.public_key(record.key_id()))
-        {
+        let key = match token_key.or_else(|| validator.as_ref()?.public_key(record.key_id())) {
             Some(key) => key,
             None => match record.as_ref().entries.get(0) {
                 Some(PackageEntry::Init { key, .. }) => key,
@@ -955,8 +1124,9 @@ impl DataStore for PostgresDataStore {
             },
         };
 
-        package::PackageRecord::verify(key, record.content_bytes(), record.signature())
-            .map_err(|_| DataStoreError::SignatureVerificationFailed(record.signature().clone()))
+        super::verify_package_record_signatures(key, record, |key_id| {
+            validator.as_ref()?.public_key(key_id)
+        })
     }
 
     async fn verify_can_publish_package(
@@ -991,6 +1161,10 @@ impl DataStore for PostgresDataStore {
             }
         }
 
+        if validator.package_frozen(package_name) {
+            return Err(DataStoreError::PackageFrozen(package_name.clone()));
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,120 @@
+//! Module for registry event notifications.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use warg_crypto::signing::KeyID;
+use warg_protocol::{
+    registry::{Checkpoint, LogId, PackageName, RecordId, RegistryIndex},
+    Version,
+};
+
+mod webhook;
+
+pub use webhook::*;
+
+const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// Represents an event emitted by the registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Event {
+    /// A record was validated and sequenced into the registry log.
+    #[serde(rename_all = "camelCase")]
+    RecordSequenced {
+        /// The log the record belongs to.
+        log_id: LogId,
+        /// The identifier of the record.
+        record_id: RecordId,
+        /// The index of the record in the registry log.
+        registry_index: RegistryIndex,
+        /// The keys that signed the record's envelope, any of its entries, or its
+        /// publish token.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        signers: Vec<KeyID>,
+    },
+    /// A new checkpoint was signed by the operator.
+    #[serde(rename_all = "camelCase")]
+    CheckpointSigned {
+        /// The checkpoint that was signed.
+        checkpoint: Checkpoint,
+    },
+    /// A package log was initialized.
+    #[serde(rename_all = "camelCase")]
+    PackageInitialized {
+        /// The log of the package.
+        log_id: LogId,
+        /// The name of the package, if known.
+        name: Option<PackageName>,
+        /// The identifier of the init record.
+        record_id: RecordId,
+    },
+    /// A package version was yanked.
+    #[serde(rename_all = "camelCase")]
+    VersionYanked {
+        /// The log of the package.
+        log_id: LogId,
+        /// The name of the package, if known.
+        name: Option<PackageName>,
+        /// The identifier of the record containing the yank.
+        record_id: RecordId,
+        /// The version that was yanked.
+        version: Version,
+    },
+    /// An anomaly detector flagged a package record.
+    #[serde(rename_all = "camelCase")]
+    AnomalyDetected {
+        /// The log of the package.
+        log_id: LogId,
+        /// The name of the package, if known.
+        name: Option<PackageName>,
+        /// The identifier of the anomalous record.
+        record_id: RecordId,
+        /// The index of the record in the registry log.
+        registry_index: RegistryIndex,
+        /// The name of the detector that flagged the record.
+        detector: String,
+        /// A description of the anomaly.
+        description: String,
+    },
+}
+
+/// A bus for distributing registry events to subscribers.
+///
+/// Cloning the bus produces a handle to the same bus.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// Creates a new event bus.
+    ///
+    /// The capacity is the number of events retained for slow subscribers;
+    /// subscribers lagging further behind will miss events.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Subscribes to events published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Determines if the bus has any subscribers.
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Publishes an event to all current subscribers.
+    pub fn publish(&self, event: Event) {
+        // An error here means there are no subscribers, which is not a failure
+        let _ = self.sender.send(event);
+    }
+}
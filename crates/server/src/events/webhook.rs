@@ -0,0 +1,361 @@
+use super::{Event, EventBus};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::broadcast::error::RecvError,
+    task::{JoinHandle, JoinSet},
+};
+use url::Url;
+use warg_crypto::signing::{PrivateKey, PublicKey, Signature, SignatureError};
+
+/// The header containing the operator signature of a webhook payload.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-warg-signature";
+
+/// The header containing the identifier of the key that signed a webhook payload.
+pub const WEBHOOK_KEY_ID_HEADER: &str = "x-warg-key-id";
+
+const WEBHOOK_PAYLOAD_PREFIX: &[u8] = b"WARG-WEBHOOK-PAYLOAD-V0:";
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+fn signing_payload(body: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(WEBHOOK_PAYLOAD_PREFIX.len() + body.len());
+    payload.extend_from_slice(WEBHOOK_PAYLOAD_PREFIX);
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// Signs a webhook payload body.
+pub fn sign_webhook_payload(key: &PrivateKey, body: &[u8]) -> Result<Signature, SignatureError> {
+    key.sign(&signing_payload(body))
+}
+
+/// Verifies the signature of a webhook payload body.
+///
+/// Receivers should verify payloads against the registry's operator key.
+pub fn verify_webhook_payload(
+    key: &PublicKey,
+    body: &[u8],
+    signature: &Signature,
+) -> Result<(), SignatureError> {
+    key.verify(&signing_payload(body), signature)
+}
+
+/// Delivers registry events to a webhook endpoint.
+///
+/// Each event is delivered as a JSON `POST` request signed by the operator key.
+/// Failed deliveries are retried with exponential backoff, up to a delay of
+/// five minutes between attempts.
+pub struct WebhookDispatcher {
+    url: Url,
+    signing_key: PrivateKey,
+    client: reqwest::Client,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+impl std::fmt::Debug for WebhookDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookDispatcher")
+            .field("url", &self.url)
+            .field("signing_key", &"<redacted>")
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_delay", &self.retry_delay)
+            .finish()
+    }
+}
+
+impl WebhookDispatcher {
+    /// Creates a new webhook dispatcher for the given endpoint.
+    pub fn new(url: Url, signing_key: PrivateKey) -> Self {
+        Self {
+            url,
+            signing_key,
+            client: reqwest::Client::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+
+    /// Sets the maximum number of delivery attempts for each event.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry of a failed delivery.
+    ///
+    /// The delay doubles with each subsequent retry, up to five minutes.
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay.min(MAX_RETRY_DELAY);
+        self
+    }
+
+    /// Spawns a task delivering events published on the given bus.
+    ///
+    /// Each event is delivered on its own task, so retrying a failed delivery
+    /// does not hold up the delivery of later events.
+    ///
+    /// The task completes when all handles to the bus are dropped and pending
+    /// deliveries have finished.
+    pub fn spawn(self, bus: &EventBus) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        let dispatcher = Arc::new(self);
+        tokio::spawn(async move {
+            let mut deliveries = JoinSet::new();
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let dispatcher = dispatcher.clone();
+                        deliveries.spawn(async move { dispatcher.dispatch(&event).await });
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!(
+                            "webhook `{url}` missed {count} event(s)",
+                            url = dispatcher.url
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+
+                // Reap finished deliveries so the set does not grow unbounded
+                while deliveries.try_join_next().is_some() {}
+            }
+
+            while deliveries.join_next().await.is_some() {}
+        })
+    }
+
+    /// Delivers a single event, retrying on failure.
+    pub async fn dispatch(&self, event: &Event) {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("failed to serialize event {event:?}: {e}");
+                return;
+            }
+        };
+
+        let signature = match sign_webhook_payload(&self.signing_key, &body) {
+            Ok(signature) => signature,
+            Err(e) => {
+                tracing::error!("failed to sign webhook payload: {e}");
+                return;
+            }
+        };
+
+        let key_id = self.signing_key.public_key().fingerprint();
+        let mut delay = self.retry_delay;
+        for attempt in 1..=self.max_attempts {
+            let res = self
+                .client
+                .post(self.url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(WEBHOOK_SIGNATURE_HEADER, signature.to_string())
+                .header(WEBHOOK_KEY_ID_HEADER, key_id.to_string())
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(|res| res.error_for_status());
+
+            match res {
+                Ok(_) => return,
+                Err(e) if attempt < self.max_attempts => {
+                    tracing::debug!(
+                        "webhook `{url}` delivery attempt {attempt} failed: {e}",
+                        url = self.url
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = next_retry_delay(delay);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "webhook `{url}` delivery failed after {attempt} attempt(s): {e}",
+                        url = self.url
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Doubles a retry delay, up to the maximum delay between attempts.
+fn next_retry_delay(delay: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::{net::TcpListener, sync::mpsc};
+    use warg_crypto::hash::{HashAlgorithm, Sha256};
+    use warg_protocol::registry::{Checkpoint, LogId};
+
+    #[derive(Clone)]
+    struct Receiver {
+        attempts: Arc<AtomicUsize>,
+        fail_first: usize,
+        reject: Option<Vec<u8>>,
+        tx: mpsc::Sender<(HeaderMap, Vec<u8>)>,
+    }
+
+    async fn receive(
+        State(receiver): State<Receiver>,
+        headers: HeaderMap,
+        body: axum::body::Bytes,
+    ) -> axum::http::StatusCode {
+        if receiver.attempts.fetch_add(1, Ordering::SeqCst) < receiver.fail_first
+            || receiver.reject.as_deref() == Some(&body)
+        {
+            return axum::http::StatusCode::SERVICE_UNAVAILABLE;
+        }
+
+        receiver.tx.send((headers, body.to_vec())).await.unwrap();
+        axum::http::StatusCode::NO_CONTENT
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payload_with_retries() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let receiver = Receiver {
+            attempts: Default::default(),
+            fail_first: 2,
+            reject: None,
+            tx,
+        };
+        let attempts = receiver.attempts.clone();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let key = PrivateKey::decode(
+            "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+        )
+        .unwrap();
+        let dispatcher =
+            WebhookDispatcher::new(format!("http://{addr}/hook").parse().unwrap(), key.clone())
+                .with_retry_delay(Duration::from_millis(10));
+
+        let bus = EventBus::default();
+        let handle = dispatcher.spawn(&bus);
+
+        let digest = HashAlgorithm::Sha256.digest(&[1, 2, 3]);
+        let event = Event::CheckpointSigned {
+            checkpoint: Checkpoint {
+                log_root: digest,
+                log_length: 1,
+                map_root: digest,
+            },
+        };
+        bus.publish(event.clone());
+
+        let (headers, body) = rx.recv().await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(serde_json::from_slice::<Event>(&body).unwrap(), event);
+        assert_eq!(
+            headers[WEBHOOK_KEY_ID_HEADER].to_str().unwrap(),
+            key.public_key().fingerprint().to_string()
+        );
+
+        let signature: Signature = headers[WEBHOOK_SIGNATURE_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        verify_webhook_payload(&key.public_key(), &body, &signature).unwrap();
+        assert!(verify_webhook_payload(&key.public_key(), b"tampered", &signature).is_err());
+
+        // Dropping the bus stops the dispatcher
+        drop(bus);
+        handle.await.unwrap();
+
+        // Ensure log IDs round-trip through event payloads
+        let event = Event::RecordSequenced {
+            log_id: LogId::operator_log::<Sha256>(),
+            record_id: HashAlgorithm::Sha256.digest(&[4]).into(),
+            registry_index: 7,
+            signers: vec!["sha256:abc".to_string().into()],
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""type":"recordSequenced""#));
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn retries_do_not_block_later_events() {
+        let checkpoint = |log_length| Event::CheckpointSigned {
+            checkpoint: Checkpoint {
+                log_root: HashAlgorithm::Sha256.digest(&[1]),
+                log_length,
+                map_root: HashAlgorithm::Sha256.digest(&[2]),
+            },
+        };
+        let failing = checkpoint(1);
+        let (tx, mut rx) = mpsc::channel(1);
+        let receiver = Receiver {
+            attempts: Default::default(),
+            fail_first: 0,
+            reject: Some(serde_json::to_vec(&failing).unwrap()),
+            tx,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let key = PrivateKey::decode(
+            "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+        )
+        .unwrap();
+        let dispatcher =
+            WebhookDispatcher::new(format!("http://{addr}/hook").parse().unwrap(), key)
+                .with_max_attempts(u32::MAX)
+                .with_retry_delay(Duration::from_secs(60));
+
+        let bus = EventBus::default();
+        let handle = dispatcher.spawn(&bus);
+
+        // The second event is delivered while the first waits to be retried
+        bus.publish(failing);
+        bus.publish(checkpoint(2));
+        let (_, body) = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("the later event should not wait on retries")
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Event>(&body).unwrap(),
+            checkpoint(2)
+        );
+
+        drop(bus);
+        handle.abort();
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        assert_eq!(
+            next_retry_delay(Duration::from_secs(1)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(next_retry_delay(MAX_RETRY_DELAY), MAX_RETRY_DELAY);
+        assert_eq!(next_retry_delay(Duration::MAX), MAX_RETRY_DELAY);
+
+        let mut delay = DEFAULT_RETRY_DELAY;
+        for _ in 0..1000 {
+            delay = next_retry_delay(delay);
+        }
+        assert_eq!(delay, MAX_RETRY_DELAY);
+    }
+}
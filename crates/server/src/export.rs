@@ -0,0 +1,328 @@
+//! Exports registry state to a static site layout.
+//!
+//! See [`warg_api::v1::static_site`] for a description of the layout.
+
+use crate::{
+    content::ContentFiles,
+    datastore::DataStoreError,
+    services::{CoreService, CoreServiceError},
+};
+use indexmap::{IndexMap, IndexSet};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use warg_api::v1::{
+    proof::InclusionResponse,
+    static_site::{self, StaticIndex, StaticLog},
+};
+use warg_crypto::hash::{AnyHash, Sha256};
+use warg_protocol::{
+    package::PackageArchive,
+    registry::{LogId, PackageName, RecordId, RegistryIndex, RegistryLen},
+    PublishedProtoEnvelopeBody, Record,
+};
+
+const PAGE_SIZE: u16 = 1000;
+
+/// Represents an error that occurred while exporting a static site.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    /// A data store error occurred.
+    #[error("data store error: {0}")]
+    DataStore(#[from] DataStoreError),
+    /// A proof could not be generated.
+    #[error(transparent)]
+    CoreService(#[from] CoreServiceError),
+    /// The name of a package log is unknown.
+    #[error("the name of package log `{0}` is unknown")]
+    UnknownPackageName(LogId),
+    /// The package to export has no records as of the latest checkpoint.
+    #[error("package `{0}` has no records as of the latest checkpoint")]
+    PackageNotFound(PackageName),
+    /// Content referenced by a package record is missing.
+    #[error("content `{0}` is missing from the content directory")]
+    ContentMissing(AnyHash),
+    /// An I/O error occurred writing to the output directory.
+    #[error("failed to write `{path}`: {source}")]
+    Io {
+        /// The path that failed to be written.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// A file could not be serialized.
+    #[error("failed to serialize `{path}`: {source}")]
+    Serialization {
+        /// The path that failed to be serialized.
+        path: PathBuf,
+        /// The underlying serialization error.
+        source: serde_json::Error,
+    },
+}
+
+/// A summary of an export.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// The registry log length of the exported checkpoint.
+    pub log_length: RegistryLen,
+    /// The number of packages exported.
+    pub packages: usize,
+    /// The number of records exported, including operator records.
+    pub records: usize,
+    /// The number of content files exported.
+    pub content: usize,
+}
+
+/// Exports the state of a registry at its latest checkpoint to a static site.
+///
+/// The output is deterministic: exporting the same checkpoint twice produces
+/// identical files.
+pub struct StaticSiteExporter<'a> {
+    core: &'a CoreService,
+    files: ContentFiles,
+}
+
+impl<'a> StaticSiteExporter<'a> {
+    /// Creates a new exporter for the given core service.
+    ///
+    /// The `files_dir` is the directory containing the registry's content
+    /// files, which are found whatever their layout.
+    pub fn new(core: &'a CoreService, files_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            core,
+            files: ContentFiles::new(files_dir),
+        }
+    }
+
+    /// Exports the registry to the given output directory.
+    ///
+    /// Existing files in the output directory are overwritten.
+    pub async fn export(&self, output: &Path) -> Result<ExportSummary, ExportError> {
+        let store = self.core.store();
+        let checkpoint = store.get_latest_checkpoint().await?;
+        let log_length = checkpoint.as_ref().checkpoint.log_length;
+
+        // Find the log heads and package logs as of the checkpoint
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let mut heads: IndexMap<LogId, RegistryIndex> = IndexMap::new();
+        let mut next = 0;
+        while next < log_length {
+            let leafs = store
+                .get_log_leafs_starting_with_registry_index(next, PAGE_SIZE as usize)
+                .await?;
+            if leafs.is_empty() {
+                break;
+            }
+
+            for (index, leaf) in leafs {
+                if index >= log_length {
+                    break;
+                }
+                heads.insert(leaf.log_id, index);
+                next = index + 1;
+            }
+        }
+
+        let package_log_ids = heads
+            .keys()
+            .filter(|id| **id != operator_log_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut packages: BTreeMap<PackageName, LogId> = BTreeMap::new();
+        for (log_id, name) in store.get_package_names(&package_log_ids).await? {
+            let name = name.ok_or_else(|| ExportError::UnknownPackageName(log_id.clone()))?;
+            packages.insert(name, log_id);
+        }
+
+        let mut summary = ExportSummary {
+            log_length,
+            packages: packages.len(),
+            ..Default::default()
+        };
+
+        write_json(output, static_site::checkpoint(), &checkpoint)?;
+        write_json(
+            output,
+            static_site::index(),
+            &StaticIndex {
+                log_length,
+                packages: packages
+                    .iter()
+                    .map(|(name, id)| (name.clone(), id.clone()))
+                    .collect(),
+            },
+        )?;
+
+        // Export the operator log
+        let records = self.operator_records(&operator_log_id, log_length).await?;
+        summary.records += records.len();
+        write_json(output, static_site::operator_log(), &StaticLog { records })?;
+        if let Some(&head) = heads.get(&operator_log_id) {
+            write_json(
+                output,
+                static_site::operator_proof(),
+                &self.inclusion_proof(log_length, head).await?,
+            )?;
+        }
+
+        // Export the package logs and their content
+        let mut content = IndexSet::new();
+        for log_id in packages.values() {
+            let records = self
+                .package_records(log_id, log_length, &mut content)
+                .await?;
+            summary.records += records.len();
+            write_json(
+                output,
+                &static_site::package_log(log_id),
+                &StaticLog { records },
+            )?;
+            write_json(
+                output,
+                &static_site::package_proof(log_id),
+                &self.inclusion_proof(log_length, heads[log_id]).await?,
+            )?;
+        }
+
+        for digest in &content {
+            let Some(source) = self.files.locate(digest) else {
+                return Err(ExportError::ContentMissing(*digest));
+            };
+
+            let path = output.join(static_site::content(digest));
+            create_parent(&path)?;
+            fs::copy(&source, &path).map_err(|source| ExportError::Io { path, source })?;
+        }
+        summary.content = content.len();
+
+        Ok(summary)
+    }
+
+    /// Exports the record chain of a single package at the registry's
+    /// latest checkpoint.
+    ///
+    /// The archive can be verified offline with [`PackageArchive::verify`].
+    pub async fn export_package(&self, name: &PackageName) -> Result<PackageArchive, ExportError> {
+        let checkpoint = self.core.store().get_latest_checkpoint().await?;
+        let log_length = checkpoint.as_ref().checkpoint.log_length;
+        let log_id = LogId::package_log::<Sha256>(name);
+
+        let records = match self
+            .package_records(&log_id, log_length, &mut IndexSet::new())
+            .await
+        {
+            Err(ExportError::DataStore(DataStoreError::LogNotFound(_))) => Vec::new(),
+            records => records?,
+        };
+        let indexes = records.iter().map(|r| r.registry_index).collect::<Vec<_>>();
+        let head = *indexes
+            .last()
+            .ok_or_else(|| ExportError::PackageNotFound(name.clone()))?;
+
+        let log_proof = self.core.log_inclusion_proofs(log_length, &indexes).await?;
+        let map_proof = self.core.map_inclusion_proofs(log_length, &[head]).await?;
+        Ok(PackageArchive {
+            name: name.clone(),
+            checkpoint,
+            records,
+            map_proof: map_proof.encode(),
+            log_proof: log_proof.encode(),
+        })
+    }
+
+    async fn operator_records(
+        &self,
+        log_id: &LogId,
+        log_length: RegistryLen,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, ExportError> {
+        let mut records = Vec::new();
+        let mut since = None;
+        loop {
+            let page = self
+                .core
+                .store()
+                .get_operator_records(log_id, log_length, since.as_ref(), PAGE_SIZE)
+                .await?;
+            let more = page.len() == PAGE_SIZE as usize;
+            if let Some(last) = page.last() {
+                since = Some(RecordId::operator_record::<Sha256>(&last.envelope));
+            }
+
+            records.extend(page.into_iter().map(PublishedProtoEnvelopeBody::from));
+            if !more {
+                return Ok(records);
+            }
+        }
+    }
+
+    async fn package_records(
+        &self,
+        log_id: &LogId,
+        log_length: RegistryLen,
+        content: &mut IndexSet<AnyHash>,
+    ) -> Result<Vec<PublishedProtoEnvelopeBody>, ExportError> {
+        let mut records = Vec::new();
+        let mut since = None;
+        loop {
+            let page = self
+                .core
+                .store()
+                .get_package_records(log_id, log_length, since.as_ref(), PAGE_SIZE)
+                .await?;
+            let more = page.len() == PAGE_SIZE as usize;
+            if let Some(last) = page.last() {
+                since = Some(RecordId::package_record::<Sha256>(&last.envelope));
+            }
+
+            for record in page {
+                content.extend(record.envelope.as_ref().contents().into_iter().cloned());
+                records.push(PublishedProtoEnvelopeBody::from(record));
+            }
+
+            if !more {
+                return Ok(records);
+            }
+        }
+    }
+
+    async fn inclusion_proof(
+        &self,
+        log_length: RegistryLen,
+        index: RegistryIndex,
+    ) -> Result<InclusionResponse, ExportError> {
+        let log = self.core.log_inclusion_proofs(log_length, &[index]).await?;
+        let map = self.core.map_inclusion_proofs(log_length, &[index]).await?;
+        Ok(InclusionResponse {
+            log: log.encode(),
+            map: map.encode(),
+        })
+    }
+}
+
+fn create_parent(path: &Path) -> Result<(), ExportError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| ExportError::Io {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+
+    Ok(())
+}
+
+fn write_json(output: &Path, path: &str, value: &impl Serialize) -> Result<(), ExportError> {
+    let path = output.join(path);
+    let mut json =
+        serde_json::to_vec_pretty(value).map_err(|source| ExportError::Serialization {
+            path: path.clone(),
+            source,
+        })?;
+    json.push(b'\n');
+
+    create_parent(&path)?;
+    fs::write(&path, json).map_err(|source| ExportError::Io { path, source })
+}
@@ -0,0 +1,91 @@
+//! Fault injection for resilience testing.
+//!
+//! Faults make the server misbehave on purpose so that operators can verify
+//! that their monitors and clients detect a misbehaving registry. They are
+//! only available with the `fault-injection` feature, which must never be
+//! enabled for a production registry.
+
+use std::{
+    num::NonZeroU64,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The faults to inject into a server.
+#[derive(Debug, Default)]
+pub struct Faults {
+    drop_publish_every: Option<NonZeroU64>,
+    checkpoint_delay: Option<Duration>,
+    corrupt_proof_every: Option<NonZeroU64>,
+    publishes: AtomicU64,
+    proofs: AtomicU64,
+}
+
+impl Faults {
+    /// Creates a new set of faults that injects nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops every `n`th published record after it has been validated.
+    ///
+    /// Dropped records are accepted by the publish API but never sequenced.
+    pub fn with_dropped_publishes(mut self, n: NonZeroU64) -> Self {
+        self.drop_publish_every = Some(n);
+        self
+    }
+
+    /// Delays every checkpoint by the given duration.
+    pub fn with_checkpoint_delay(mut self, delay: Duration) -> Self {
+        self.checkpoint_delay = Some(delay);
+        self
+    }
+
+    /// Corrupts every `n`th log inclusion proof served.
+    ///
+    /// Corrupted proofs evaluate to a log root other than the one of the
+    /// requested checkpoint.
+    pub fn with_corrupted_proofs(mut self, n: NonZeroU64) -> Self {
+        self.corrupt_proof_every = Some(n);
+        self
+    }
+
+    /// Counts a published record, returning whether to drop it.
+    pub(crate) fn drop_publish(&self) -> bool {
+        Self::nth(&self.publishes, self.drop_publish_every)
+    }
+
+    /// Returns the delay to apply before checkpointing, if any.
+    pub(crate) fn checkpoint_delay(&self) -> Option<Duration> {
+        self.checkpoint_delay
+    }
+
+    /// Counts a served log inclusion proof, returning whether to corrupt it.
+    pub(crate) fn corrupt_proof(&self) -> bool {
+        Self::nth(&self.proofs, self.corrupt_proof_every)
+    }
+
+    fn nth(counter: &AtomicU64, every: Option<NonZeroU64>) -> bool {
+        match every {
+            Some(every) => (counter.fetch_add(1, Ordering::Relaxed) + 1) % every.get() == 0,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_injects_every_nth_fault() {
+        let faults = Faults::new().with_dropped_publishes(NonZeroU64::new(3).unwrap());
+        let dropped = (0..9).map(|_| faults.drop_publish()).collect::<Vec<_>>();
+        assert_eq!(
+            dropped,
+            [false, false, true, false, false, true, false, false, true]
+        );
+        assert!(!faults.corrupt_proof());
+        assert_eq!(faults.checkpoint_delay(), None);
+    }
+}
@@ -0,0 +1,265 @@
+//! Generates an Atom feed of registry activity.
+//!
+//! The feed lists new packages, releases, and yanks from the most recently
+//! sequenced package records, for people and simple integrations that want
+//! to watch a registry without running a full client.
+
+use crate::{datastore::DataStoreError, services::CoreService};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::{fmt::Write, time::SystemTime};
+use url::Url;
+use warg_crypto::hash::Sha256;
+use warg_protocol::{
+    package::PackageEntry,
+    registry::{LogId, PackageName, RecordId},
+};
+
+/// Generates an Atom feed of the registry activity up to its latest checkpoint.
+pub struct FeedGenerator<'a> {
+    core: &'a CoreService,
+    url: Url,
+    title: String,
+}
+
+impl<'a> FeedGenerator<'a> {
+    /// Creates a new feed generator for the given core service.
+    ///
+    /// The `url` is the location the feed is served from; it is used as the
+    /// feed's identifier and must not change between generations.
+    pub fn new(core: &'a CoreService, url: Url) -> Self {
+        let title = match url.host_str() {
+            Some(host) => format!("Warg registry activity for {host}"),
+            None => "Warg registry activity".to_string(),
+        };
+
+        Self { core, url, title }
+    }
+
+    /// Sets the title of the feed.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Generates the feed from at most `limit` of the latest package records,
+    /// newest first.
+    ///
+    /// Records without new packages, releases, or yanks (such as permission
+    /// grants) count against the limit but produce no feed entries.
+    pub async fn generate(&self, limit: usize) -> Result<String, DataStoreError> {
+        let store = self.core.store();
+        let log_length = store
+            .get_latest_checkpoint()
+            .await?
+            .into_contents()
+            .checkpoint
+            .log_length;
+
+        let start = log_length.saturating_sub(limit);
+        let leafs = store
+            .get_log_leafs_starting_with_registry_index(start, log_length - start)
+            .await?;
+
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let log_ids = leafs
+            .iter()
+            .map(|(_, leaf)| leaf.log_id.clone())
+            .filter(|id| *id != operator_log_id)
+            .collect::<Vec<_>>();
+        let names = store.get_package_names(&log_ids).await?;
+
+        let mut entries = Vec::new();
+        for (_, leaf) in leafs.iter().rev() {
+            let Some(Some(name)) = names.get(&leaf.log_id) else {
+                continue;
+            };
+
+            let record = store
+                .get_package_record(&leaf.log_id, &leaf.record_id)
+                .await?;
+            let record = record.envelope.as_ref();
+            for (index, entry) in record.entries.iter().enumerate().rev() {
+                if let Some(entry) = FeedEntry::new(name, &leaf.record_id, index, entry) {
+                    entries.push((record.timestamp, entry));
+                }
+            }
+        }
+
+        Ok(self.render(&entries))
+    }
+
+    fn render(&self, entries: &[(SystemTime, FeedEntry)]) -> String {
+        let updated = entries
+            .first()
+            .map(|(timestamp, _)| *timestamp)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut feed = String::new();
+        feed.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        feed.push('\n');
+        feed.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        feed.push('\n');
+        let _ = writeln!(feed, "  <id>{}</id>", escape(self.url.as_str()));
+        let _ = writeln!(feed, "  <title>{}</title>", escape(&self.title));
+        let _ = writeln!(feed, "  <updated>{}</updated>", format_time(updated));
+        let _ = writeln!(
+            feed,
+            r#"  <link rel="self" href="{}"/>"#,
+            escape(self.url.as_str())
+        );
+        let _ = writeln!(
+            feed,
+            "  <author><name>{}</name></author>",
+            escape(self.url.host_str().unwrap_or("warg"))
+        );
+
+        for (timestamp, entry) in entries {
+            feed.push_str("  <entry>\n");
+            let _ = writeln!(feed, "    <id>{}</id>", escape(&entry.id));
+            let _ = writeln!(feed, "    <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(feed, "    <updated>{}</updated>", format_time(*timestamp));
+            let _ = writeln!(feed, r#"    <category term="{}"/>"#, entry.kind);
+            let _ = writeln!(
+                feed,
+                r#"    <content type="text">{}</content>"#,
+                escape(&entry.content)
+            );
+            feed.push_str("  </entry>\n");
+        }
+
+        feed.push_str("</feed>\n");
+        feed
+    }
+}
+
+struct FeedEntry {
+    id: String,
+    kind: &'static str,
+    title: String,
+    content: String,
+}
+
+impl FeedEntry {
+    fn new(
+        name: &PackageName,
+        record_id: &RecordId,
+        index: usize,
+        entry: &PackageEntry,
+    ) -> Option<Self> {
+        let (kind, title, content) = match entry {
+            PackageEntry::Init { .. } => (
+                "package",
+                format!("{name} created"),
+                format!("Package `{name}` was created."),
+            ),
+            PackageEntry::Release {
+                version, content, ..
+            } => (
+                "release",
+                format!("{name} {version} released"),
+                format!(
+                    "Version {version} of package `{name}` was released with content `{content}`."
+                ),
+            ),
+            PackageEntry::Yank { version, reason } => (
+                "yank",
+                format!("{name} {version} yanked"),
+                with_reason(
+                    format!("Version {version} of package `{name}` was yanked."),
+                    reason.as_deref(),
+                ),
+            ),
+            PackageEntry::YankRange { range, reason } => (
+                "yank",
+                format!("{name} {range} yanked"),
+                with_reason(
+                    format!("Versions {range} of package `{name}` were yanked."),
+                    reason.as_deref(),
+                ),
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            id: format!("urn:warg:record:{record_id}:{index}"),
+            kind,
+            title,
+            content,
+        })
+    }
+}
+
+fn with_reason(content: String, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("{content} Reason: {reason}"),
+        None => content,
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use warg_crypto::hash::{AnyHash, Hash};
+
+    #[test]
+    fn test_feed_entry() {
+        let name = PackageName::new("test:package").unwrap();
+        let record_id = RecordId::from(AnyHash::from(Hash::<Sha256>::of("record")));
+
+        let entry = FeedEntry::new(
+            &name,
+            &record_id,
+            1,
+            &PackageEntry::Yank {
+                version: "1.0.0".parse().unwrap(),
+                reason: Some("CVE <1234> & more".to_string()),
+            },
+        )
+        .unwrap();
+        assert_eq!(entry.id, format!("urn:warg:record:{record_id}:1"));
+        assert_eq!(entry.title, "test:package 1.0.0 yanked");
+        assert_eq!(
+            escape(&entry.content),
+            "Version 1.0.0 of package `test:package` was yanked. Reason: CVE &lt;1234&gt; &amp; more"
+        );
+
+        assert!(FeedEntry::new(
+            &name,
+            &record_id,
+            0,
+            &PackageEntry::Tag {
+                tag: "latest".to_string(),
+                version: "1.0.0".parse().unwrap(),
+            },
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(SystemTime::UNIX_EPOCH + Duration::from_secs(86400 + 61)),
+            "1970-01-02T00:01:01Z"
+        );
+    }
+}
@@ -0,0 +1,539 @@
+//! Imports packages from existing registry dumps.
+//!
+//! Each imported package is given a synthetic package log, initialized and
+//! signed by a designated migration key, that releases (and yanks) the
+//! versions listed in the dump.
+//!
+//! Only package metadata is imported; the released content digests refer to
+//! the original tarballs, which are not uploaded to the registry.
+
+use crate::{
+    datastore::DataStoreError,
+    services::{CoreService, CoreServiceError},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use indexmap::{IndexMap, IndexSet};
+use serde::Deserialize;
+use std::{io::BufRead, str::FromStr, time::Duration, time::SystemTime};
+use thiserror::Error;
+use warg_crypto::{
+    hash::{AnyHash, HashAlgorithm, Sha256},
+    signing::PrivateKey,
+};
+use warg_protocol::{
+    package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+    registry::{LogId, PackageName, RecordId},
+    ProtoEnvelope, Version,
+};
+
+const DEFAULT_MAX_ENTRIES_PER_RECORD: usize = 100;
+const DEFAULT_THROTTLE: Duration = Duration::from_millis(10);
+
+/// Represents an error that occurred while importing packages.
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// The dump format is unknown.
+    #[error("unknown dump format `{0}`: expected `warg`, `crates-io`, or `npm`")]
+    UnknownFormat(String),
+    /// The dump could not be read.
+    #[error("failed to read line {line} of dump: {source}")]
+    Read {
+        /// The line that failed to be read.
+        line: usize,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
+    /// A line of the dump could not be parsed.
+    #[error("failed to parse line {line} of dump: {source}")]
+    Parse {
+        /// The line that failed to be parsed.
+        line: usize,
+        /// The underlying parse error.
+        source: serde_json::Error,
+    },
+    /// The namespace to import into is invalid.
+    #[error("invalid namespace `{0}`")]
+    InvalidNamespace(String),
+    /// A data store error occurred.
+    #[error("data store error: {0}")]
+    DataStore(#[from] DataStoreError),
+    /// A core service error occurred.
+    #[error(transparent)]
+    CoreService(#[from] CoreServiceError),
+    /// A record could not be signed.
+    #[error("failed to sign record: {0}")]
+    Signing(anyhow::Error),
+}
+
+/// The format of a registry dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// JSON lines of `{"name", "versions": [{"version", "digest", "yanked"}]}`
+    /// objects with fully-qualified warg package names.
+    Warg,
+    /// JSON lines in the format of the crates.io index.
+    CratesIo,
+    /// JSON lines of npm packuments.
+    ///
+    /// Only versions with a `sha256` integrity are imported.
+    Npm,
+}
+
+impl FromStr for DumpFormat {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warg" => Ok(Self::Warg),
+            "crates-io" => Ok(Self::CratesIo),
+            "npm" => Ok(Self::Npm),
+            _ => Err(ImportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// A version of a package to import.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ImportVersion {
+    /// The version of the package.
+    pub version: Version,
+    /// The digest of the version's content.
+    pub digest: AnyHash,
+    /// Whether or not the version is yanked.
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// A package to import.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ImportPackage {
+    /// The name of the package.
+    pub name: PackageName,
+    /// The versions of the package, in ascending order.
+    pub versions: Vec<ImportVersion>,
+}
+
+/// An entry of a dump that was skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    /// The name of the package in the dump.
+    pub name: String,
+    /// The version that was skipped, or `None` if the entire package was skipped.
+    pub version: Option<String>,
+    /// The reason the entry was skipped.
+    pub reason: String,
+}
+
+/// A parsed registry dump.
+#[derive(Debug, Default)]
+pub struct Dump {
+    /// The packages to import, sorted by name.
+    pub packages: Vec<ImportPackage>,
+    /// The entries of the dump that cannot be imported.
+    pub skipped: Vec<Skipped>,
+}
+
+#[derive(Deserialize)]
+struct CratesIoVersion {
+    name: String,
+    vers: String,
+    cksum: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct NpmPackument {
+    name: String,
+    #[serde(default)]
+    versions: IndexMap<String, NpmVersion>,
+}
+
+#[derive(Deserialize)]
+struct NpmVersion {
+    dist: NpmDist,
+}
+
+#[derive(Deserialize)]
+struct NpmDist {
+    #[serde(default)]
+    integrity: Option<String>,
+}
+
+/// Converts a package name from another ecosystem to a warg package name in
+/// the given namespace.
+///
+/// Names are lowercased, a leading `@` is removed, and `_`, `.`, and `/` are
+/// replaced with `-`.
+pub fn convert_name(namespace: &str, name: &str) -> Option<PackageName> {
+    let name = name
+        .trim_start_matches('@')
+        .to_lowercase()
+        .replace(['_', '.', '/'], "-");
+    PackageName::new(format!("{namespace}:{name}")).ok()
+}
+
+impl Dump {
+    /// Parses a registry dump in the given format.
+    ///
+    /// Packages from other ecosystems are imported into the given namespace.
+    pub fn parse(
+        format: DumpFormat,
+        reader: impl BufRead,
+        namespace: &str,
+    ) -> Result<Self, ImportError> {
+        if !PackageName::is_valid_namespace(namespace) {
+            return Err(ImportError::InvalidNamespace(namespace.to_string()));
+        }
+
+        let mut dump = Self::default();
+        let mut packages: IndexMap<PackageName, IndexMap<Version, ImportVersion>> = IndexMap::new();
+        let mut sources: IndexMap<PackageName, String> = IndexMap::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.map_err(|source| ImportError::Read {
+                line: line_number,
+                source,
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parse_error = |source| ImportError::Parse {
+                line: line_number,
+                source,
+            };
+
+            let (source_name, versions) = match format {
+                DumpFormat::Warg => {
+                    let package: ImportPackage =
+                        serde_json::from_str(&line).map_err(parse_error)?;
+                    let versions = package.versions.into_iter().map(Ok).collect::<Vec<_>>();
+                    (package.name.to_string(), versions)
+                }
+                DumpFormat::CratesIo => {
+                    let krate: CratesIoVersion =
+                        serde_json::from_str(&line).map_err(parse_error)?;
+                    let version = krate
+                        .vers
+                        .parse()
+                        .map_err(|e| (krate.vers.clone(), format!("invalid version: {e}")))
+                        .and_then(|version| {
+                            format!("sha256:{cksum}", cksum = krate.cksum)
+                                .parse()
+                                .map(|digest| ImportVersion {
+                                    version,
+                                    digest,
+                                    yanked: krate.yanked,
+                                })
+                                .map_err(|e| (krate.vers.clone(), format!("invalid checksum: {e}")))
+                        });
+                    (krate.name, vec![version])
+                }
+                DumpFormat::Npm => {
+                    let packument: NpmPackument =
+                        serde_json::from_str(&line).map_err(parse_error)?;
+                    let versions = packument
+                        .versions
+                        .into_iter()
+                        .map(|(vers, info)| {
+                            let version = vers
+                                .parse()
+                                .map_err(|e| (vers.clone(), format!("invalid version: {e}")))?;
+                            let digest = info
+                                .dist
+                                .integrity
+                                .as_deref()
+                                .and_then(npm_sha256_integrity)
+                                .ok_or_else(|| (vers.clone(), "no sha256 integrity".to_string()))?;
+                            Ok(ImportVersion {
+                                version,
+                                digest,
+                                yanked: false,
+                            })
+                        })
+                        .collect();
+                    (packument.name, versions)
+                }
+            };
+
+            let name = match format {
+                DumpFormat::Warg => source_name.parse().ok(),
+                _ => convert_name(namespace, &source_name),
+            };
+            let Some(name) = name else {
+                dump.skipped.push(Skipped {
+                    name: source_name,
+                    version: None,
+                    reason: "name cannot be converted to a package name".to_string(),
+                });
+                continue;
+            };
+
+            // Different source names may convert to the same package name
+            match sources.get(&name) {
+                Some(existing) if *existing != source_name => {
+                    dump.skipped.push(Skipped {
+                        name: source_name,
+                        version: None,
+                        reason: format!("package name `{name}` conflicts with `{existing}`"),
+                    });
+                    continue;
+                }
+                Some(_) => {}
+                None => {
+                    sources.insert(name.clone(), source_name.clone());
+                }
+            }
+
+            let entry = packages.entry(name).or_default();
+            for version in versions {
+                match version {
+                    Ok(version) if entry.contains_key(&version.version) => {
+                        dump.skipped.push(Skipped {
+                            name: source_name.clone(),
+                            version: Some(version.version.to_string()),
+                            reason: "duplicate version".to_string(),
+                        });
+                    }
+                    Ok(version) => {
+                        entry.insert(version.version.clone(), version);
+                    }
+                    Err((version, reason)) => dump.skipped.push(Skipped {
+                        name: source_name.clone(),
+                        version: Some(version),
+                        reason,
+                    }),
+                }
+            }
+        }
+
+        packages.sort_keys();
+        dump.packages = packages
+            .into_iter()
+            .filter(|(_, versions)| !versions.is_empty())
+            .map(|(name, mut versions)| {
+                versions.sort_keys();
+                ImportPackage {
+                    name,
+                    versions: versions.into_values().collect(),
+                }
+            })
+            .collect();
+
+        Ok(dump)
+    }
+}
+
+fn npm_sha256_integrity(integrity: &str) -> Option<AnyHash> {
+    integrity.split_whitespace().find_map(|hash| {
+        let bytes = STANDARD.decode(hash.strip_prefix("sha256-")?).ok()?;
+        (bytes.len() == 32).then(|| AnyHash::new(HashAlgorithm::Sha256, bytes))
+    })
+}
+
+/// A summary of an import.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// The number of packages imported.
+    pub packages: usize,
+    /// The number of records submitted for sequencing.
+    pub records: usize,
+    /// The packages that were skipped because they already exist.
+    pub existing: Vec<PackageName>,
+}
+
+/// Imports packages into a registry as synthetic package logs.
+pub struct PackageImporter<'a> {
+    core: &'a CoreService,
+    migration_key: PrivateKey,
+    max_entries_per_record: usize,
+    throttle: Duration,
+}
+
+impl<'a> PackageImporter<'a> {
+    /// Creates a new importer for the given core service.
+    ///
+    /// Imported package logs are initialized and signed by the migration key.
+    pub fn new(core: &'a CoreService, migration_key: PrivateKey) -> Self {
+        Self {
+            core,
+            migration_key,
+            max_entries_per_record: DEFAULT_MAX_ENTRIES_PER_RECORD,
+            throttle: DEFAULT_THROTTLE,
+        }
+    }
+
+    /// Sets the maximum number of entries in each imported record.
+    ///
+    /// Packages with more versions are imported as a chain of records.
+    pub fn with_max_entries_per_record(mut self, max: usize) -> Self {
+        self.max_entries_per_record = max.max(2);
+        self
+    }
+
+    /// Sets the delay between submitting records for sequencing.
+    pub fn with_throttle(mut self, throttle: Duration) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Imports the given packages.
+    ///
+    /// Packages that already exist in the registry are skipped.
+    pub async fn import(&self, packages: &[ImportPackage]) -> Result<ImportSummary, ImportError> {
+        let store = self.core.store();
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let mut summary = ImportSummary::default();
+        let mut interval = (!self.throttle.is_zero()).then(|| tokio::time::interval(self.throttle));
+
+        for package in packages {
+            let log_id = LogId::package_log::<Sha256>(&package.name);
+            match store.get_package_names(std::slice::from_ref(&log_id)).await {
+                Ok(names) if names.get(&log_id).is_some_and(Option::is_some) => {
+                    summary.existing.push(package.name.clone());
+                    continue;
+                }
+                Ok(_) | Err(DataStoreError::LogNotFound(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            store
+                .verify_can_publish_package(&operator_log_id, &package.name)
+                .await?;
+
+            let mut prev: Option<RecordId> = None;
+            for entries in self.entries(package).chunks(self.max_entries_per_record) {
+                let record = PackageRecord {
+                    prev: prev.clone(),
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries: entries.to_vec(),
+                    entry_signatures: Vec::new(),
+                    publish_token: None,
+                };
+                let envelope = ProtoEnvelope::signed_contents(&self.migration_key, record)
+                    .map_err(|e| ImportError::Signing(e.into()))?;
+                let record_id = RecordId::package_record::<Sha256>(&envelope);
+
+                store
+                    .store_package_record(
+                        &log_id,
+                        &package.name,
+                        &record_id,
+                        &envelope,
+                        &IndexSet::new(),
+                    )
+                    .await?;
+
+                if let Some(interval) = &mut interval {
+                    interval.tick().await;
+                }
+
+                self.core
+                    .submit_package_record(log_id.clone(), record_id.clone())
+                    .await;
+                summary.records += 1;
+                prev = Some(record_id);
+            }
+
+            summary.packages += 1;
+        }
+
+        Ok(summary)
+    }
+
+    fn entries(&self, package: &ImportPackage) -> Vec<PackageEntry> {
+        let mut entries = Vec::with_capacity(1 + package.versions.len() * 2);
+        entries.push(PackageEntry::Init {
+            hash_algorithm: HashAlgorithm::Sha256,
+            key: self.migration_key.public_key(),
+        });
+        entries.extend(package.versions.iter().map(|v| PackageEntry::Release {
+            version: v.version.clone(),
+            content: v.digest,
+            encryption: None,
+            manifest: None,
+        }));
+        entries.extend(
+            package
+                .versions
+                .iter()
+                .filter(|v| v.yanked)
+                .map(|v| PackageEntry::Yank {
+                    version: v.version.clone(),
+                    reason: None,
+                }),
+        );
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_crates_io_index() {
+        let digest = HashAlgorithm::Sha256.digest(b"tarball");
+        let cksum = digest
+            .to_string()
+            .strip_prefix("sha256:")
+            .unwrap()
+            .to_string();
+        let index = format!(
+            r#"{{"name":"serde_json","vers":"1.0.1","cksum":"{cksum}","yanked":true}}
+{{"name":"serde_json","vers":"1.0.0","cksum":"{cksum}","yanked":false}}
+{{"name":"serde-json","vers":"2.0.0","cksum":"{cksum}"}}
+{{"name":"1password","vers":"0.1.0","cksum":"{cksum}"}}
+{{"name":"bad","vers":"nope","cksum":"{cksum}"}}
+"#
+        );
+
+        let dump = Dump::parse(DumpFormat::CratesIo, index.as_bytes(), "crates").unwrap();
+        assert_eq!(dump.packages.len(), 1);
+        let package = &dump.packages[0];
+        assert_eq!(package.name.as_ref(), "crates:serde-json");
+        assert_eq!(
+            package
+                .versions
+                .iter()
+                .map(|v| (v.version.to_string(), v.yanked))
+                .collect::<Vec<_>>(),
+            [("1.0.0".to_string(), false), ("1.0.1".to_string(), true)]
+        );
+        assert!(package.versions.iter().all(|v| v.digest == digest));
+
+        let skipped = dump
+            .skipped
+            .iter()
+            .map(|s| (s.name.as_str(), s.version.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                ("serde-json", None),
+                ("1password", None),
+                ("bad", Some("nope"))
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_npm_packuments() {
+        let digest = HashAlgorithm::Sha256.digest(b"tarball");
+        let integrity = STANDARD.encode(digest.bytes());
+        let packument = format!(
+            r#"{{"name":"@scope/Pkg.js","versions":{{"1.0.0":{{"dist":{{"integrity":"sha512-AAAA sha256-{integrity}"}}}},"1.1.0":{{"dist":{{"integrity":"sha512-AAAA"}}}}}}}}"#
+        );
+
+        let dump = Dump::parse(DumpFormat::Npm, packument.as_bytes(), "npm").unwrap();
+        assert_eq!(dump.packages.len(), 1);
+        assert_eq!(dump.packages[0].name.as_ref(), "npm:scope-pkg-js");
+        assert_eq!(dump.packages[0].versions.len(), 1);
+        assert_eq!(dump.packages[0].versions[0].digest, digest);
+        assert_eq!(dump.skipped.len(), 1);
+        assert_eq!(dump.skipped[0].version.as_deref(), Some("1.1.0"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Serves a registry in-process, without a listening socket.
+//!
+//! An [`InProcessServer`] dispatches requests sent by a `warg-client` API
+//! client directly to the server's router, so that publishing, fetching, and
+//! proof verification can be exercised end-to-end without any networking.
+
+use anyhow::anyhow;
+use axum::{body::Body, Router};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Response, StatusCode,
+};
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+use url::Url;
+use warg_client::{
+    api::ClientError,
+    transport::{self, Transport, TransportRequest},
+};
+
+/// The URL of a registry served in-process.
+///
+/// Requests sent through an [`InProcessTransport`] are dispatched by path, so
+/// the host of this URL is never resolved.
+pub const IN_PROCESS_URL: &str = "https://in-process.warg.invalid";
+
+/// Represents a warg registry server running in-process.
+///
+/// Created with [`Server::in_process`](crate::Server::in_process).
+pub struct InProcessServer {
+    url: Url,
+    router: Router,
+    core_handle: JoinHandle<()>,
+}
+
+impl InProcessServer {
+    pub(crate) fn new(url: Url, router: Router, core_handle: JoinHandle<()>) -> Self {
+        Self {
+            url,
+            router,
+            core_handle,
+        }
+    }
+
+    /// Gets the URL clients should use for the registry.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Creates a transport that sends requests to this server.
+    pub fn transport(&self) -> InProcessTransport {
+        InProcessTransport {
+            router: self.router.clone(),
+        }
+    }
+
+    /// Shuts down the server, awaiting completion of its background task(s).
+    ///
+    /// The background task(s) only complete once every transport created by
+    /// [`InProcessServer::transport`] has been dropped.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        drop(self.router);
+        self.core_handle.await?;
+        Ok(())
+    }
+}
+
+/// A client transport that sends requests to an [`InProcessServer`].
+#[derive(Clone)]
+pub struct InProcessTransport {
+    router: Router,
+}
+
+#[axum::async_trait]
+impl Transport for InProcessTransport {
+    async fn send(&self, request: TransportRequest) -> Result<Response, ClientError> {
+        let mut builder = axum::http::Request::builder()
+            .method(request.method.as_str())
+            .uri(request.url.as_str());
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_bytes());
+        }
+
+        let body = request.body.into_bytes().await?;
+        let request = builder.body(Body::from(body)).map_err(|e| anyhow!(e))?;
+
+        tracing::debug!(
+            method = %request.method(),
+            uri = %request.uri(),
+            "dispatching in-process request"
+        );
+        let response = match self.router.clone().oneshot(request).await {
+            Ok(response) => response,
+            Err(e) => match e {},
+        };
+
+        let status = StatusCode::from_u16(response.status().as_u16()).map_err(|e| anyhow!(e))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in response.headers() {
+            headers.append(
+                HeaderName::from_bytes(name.as_str().as_bytes()).map_err(|e| anyhow!(e))?,
+                HeaderValue::from_bytes(value.as_bytes()).map_err(|e| anyhow!(e))?,
+            );
+        }
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        Ok(transport::response(status, headers, body))
+    }
+}
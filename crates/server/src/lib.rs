@@ -1,24 +1,52 @@
-use crate::{api::create_router, datastore::MemoryDataStore};
-use anyhow::{Context, Result};
+use crate::{api::create_router, content::ContentFiles, datastore::MemoryDataStore};
+use anyhow::{bail, Context, Result};
+use auth::Authenticator;
 use axum::Router;
 use datastore::DataStore;
+use events::{EventBus, WebhookDispatcher};
 use futures::Future;
-use policy::{content::ContentPolicy, record::RecordPolicy};
-use services::CoreService;
+use policy::{content::ContentPolicy, record::RecordPolicy, staging::StagingPolicy};
+use services::{AnomalyMonitor, CoreService, KeyIndex, Quarantine, SearchIndex, SequencerLease};
 use std::{fs, net::SocketAddr, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
 use tokio::{net::TcpListener, task::JoinHandle};
 use url::Url;
-use warg_crypto::signing::PrivateKey;
-use warg_protocol::operator;
+use warg_crypto::{
+    hash::StorageLayout,
+    signing::{KeyID, PrivateKey},
+};
+use warg_protocol::{
+    discovery::OperatorKeys,
+    operator,
+    package::{ValidationRules, YankPolicy},
+    policy::TimeWindow,
+    SerdeEnvelope,
+};
 
 pub mod api;
+pub mod archive;
 pub mod args;
+pub mod auth;
+pub mod content;
 pub mod datastore;
+pub mod events;
+pub mod export;
+#[cfg(feature = "fault-injection")]
+pub mod faults;
+pub mod feed;
+pub mod import;
+#[cfg(feature = "in-process")]
+pub mod in_process;
+#[cfg(feature = "in-process")]
+pub mod local;
+pub mod migrate;
 pub mod policy;
+pub mod recover;
 pub mod services;
 
 const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8090";
 const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_FRESHNESS_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_PROOF_CACHE_CAPACITY: usize = 1024;
 
 type ShutdownFut = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
 
@@ -29,17 +57,39 @@ pub struct Config {
     addr: Option<SocketAddr>,
     data_store: Option<Box<dyn DataStore>>,
     content_dir: PathBuf,
+    content_layout: Option<StorageLayout>,
     content_base_url: Option<Url>,
     shutdown: Option<ShutdownFut>,
     checkpoint_interval: Option<Duration>,
+    checkpoint_batch_size: Option<usize>,
+    freshness_interval: Option<Duration>,
+    proof_cache_capacity: Option<usize>,
+    sequencer_lease: Option<Arc<dyn SequencerLease>>,
     content_policy: Option<Arc<dyn ContentPolicy>>,
+    allow_encrypted_content: bool,
     record_policy: Option<Arc<dyn RecordPolicy>>,
+    staging_policy: Option<Arc<StagingPolicy>>,
+    validation_rules: Option<ValidationRules>,
+    yank_policy: Option<Arc<YankPolicy>>,
+    time_window: Option<TimeWindow>,
+    events: Option<EventBus>,
+    webhooks: Vec<Url>,
+    search_index: Option<SearchIndex>,
+    key_index: Option<KeyIndex>,
+    anomaly_monitor: Option<AnomalyMonitor>,
+    quarantine: Option<Quarantine>,
+    denied_keys: Vec<KeyID>,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    operator_keys: Option<Arc<SerdeEnvelope<OperatorKeys>>>,
+    next_operator_key: Option<PrivateKey>,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<faults::Faults>,
 }
 
 impl std::fmt::Debug for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Config")
-            .field("operator_key", &"<redacted>")
+        let mut f = f.debug_struct("Config");
+        f.field("operator_key", &"<redacted>")
             .field("namespaces", &self.namespaces)
             .field("addr", &self.addr)
             .field(
@@ -47,17 +97,47 @@ impl std::fmt::Debug for Config {
                 &self.data_store.as_ref().map(|_| "dyn DataStore"),
             )
             .field("content_dir", &self.content_dir)
+            .field("content_layout", &self.content_layout)
             .field("shutdown", &self.shutdown.as_ref().map(|_| "dyn Future"))
             .field("checkpoint_interval", &self.checkpoint_interval)
+            .field("checkpoint_batch_size", &self.checkpoint_batch_size)
+            .field("freshness_interval", &self.freshness_interval)
+            .field("proof_cache_capacity", &self.proof_cache_capacity)
+            .field(
+                "sequencer_lease",
+                &self.sequencer_lease.as_ref().map(|_| "dyn SequencerLease"),
+            )
             .field(
                 "content_policy",
                 &self.content_policy.as_ref().map(|_| "dyn ContentPolicy"),
             )
+            .field("allow_encrypted_content", &self.allow_encrypted_content)
             .field(
                 "record_policy",
                 &self.record_policy.as_ref().map(|_| "dyn RecordPolicy"),
             )
-            .finish()
+            .field("staging_policy", &self.staging_policy)
+            .field("validation_rules", &self.validation_rules)
+            .field("yank_policy", &self.yank_policy)
+            .field("events", &self.events)
+            .field("webhooks", &self.webhooks)
+            .field("search_index", &self.search_index.is_some())
+            .field("key_index", &self.key_index.is_some())
+            .field("anomaly_monitor", &self.anomaly_monitor)
+            .field("quarantine", &self.quarantine.is_some())
+            .field("denied_keys", &self.denied_keys)
+            .field(
+                "authenticator",
+                &self.authenticator.as_ref().map(|_| "dyn Authenticator"),
+            )
+            .field("operator_keys", &self.operator_keys)
+            .field(
+                "next_operator_key",
+                &self.next_operator_key.as_ref().map(|_| "<redacted>"),
+            );
+        #[cfg(feature = "fault-injection")]
+        f.field("faults", &self.faults);
+        f.finish()
     }
 }
 
@@ -74,11 +154,33 @@ impl Config {
             addr: None,
             data_store: None,
             content_dir,
+            content_layout: None,
             content_base_url: None,
             shutdown: None,
             checkpoint_interval: None,
+            checkpoint_batch_size: None,
+            freshness_interval: None,
+            proof_cache_capacity: None,
+            sequencer_lease: None,
             content_policy: None,
+            allow_encrypted_content: false,
             record_policy: None,
+            staging_policy: None,
+            validation_rules: None,
+            yank_policy: None,
+            time_window: None,
+            events: None,
+            webhooks: Vec::new(),
+            search_index: None,
+            key_index: None,
+            anomaly_monitor: None,
+            quarantine: None,
+            denied_keys: Vec::new(),
+            authenticator: None,
+            operator_keys: None,
+            next_operator_key: None,
+            #[cfg(feature = "fault-injection")]
+            faults: None,
         }
     }
 
@@ -96,6 +198,15 @@ impl Config {
         self
     }
 
+    /// Specify the layout of new content files.
+    ///
+    /// If not set, content files are stored directly in the content files
+    /// directory. Content stored under a previous layout is still served.
+    pub fn with_content_layout(mut self, layout: StorageLayout) -> Self {
+        self.content_layout = Some(layout);
+        self
+    }
+
     /// Specify the data store to use.
     ///
     /// If this is not specified, the server will use an in-memory data store.
@@ -129,17 +240,204 @@ impl Config {
         self
     }
 
+    /// Sets the number of submitted records that triggers a checkpoint
+    /// before the checkpoint interval elapses.
+    ///
+    /// Small registries can use a batch size of one to sequence each record
+    /// as soon as it is submitted, while large ones keep batching records
+    /// over the checkpoint interval.
+    pub fn with_checkpoint_batch_size(mut self, size: usize) -> Self {
+        self.checkpoint_batch_size = Some(size);
+        self
+    }
+
+    /// Sets the interval on which the server re-signs the freshness assertion
+    /// of its latest checkpoint.
+    ///
+    /// The assertion is also re-signed whenever a new checkpoint is created.
+    pub fn with_freshness_interval(mut self, interval: Duration) -> Self {
+        self.freshness_interval = Some(interval);
+        self
+    }
+
+    /// Sets the number of map inclusion proofs to cache for the latest checkpoint.
+    ///
+    /// A capacity of zero disables proof caching.
+    pub fn with_proof_cache_capacity(mut self, capacity: usize) -> Self {
+        self.proof_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the lease the server must hold to sign checkpoints.
+    ///
+    /// Set this when several server instances share a data store so that
+    /// only one of them sequences records at a time.
+    pub fn with_sequencer_lease(mut self, lease: impl SequencerLease + 'static) -> Self {
+        self.sequencer_lease = Some(Arc::new(lease));
+        self
+    }
+
     /// Sets the content policy to use for the server.
     pub fn with_content_policy(mut self, policy: impl ContentPolicy + 'static) -> Self {
         self.content_policy = Some(Arc::new(policy));
         self
     }
 
+    /// Sets whether content released encrypted is exempt from the content
+    /// policy.
+    ///
+    /// Content policies cannot inspect encrypted content, so a registry with a
+    /// content policy rejects encrypted content that the policy does not
+    /// accept unless this is enabled. Defaults to `false`.
+    pub fn with_encrypted_content(mut self, allow: bool) -> Self {
+        self.allow_encrypted_content = allow;
+        self
+    }
+
     /// Sets the record policy to use for the server.
     pub fn with_record_policy(mut self, policy: impl RecordPolicy + 'static) -> Self {
         self.record_policy = Some(Arc::new(policy));
         self
     }
+
+    /// Sets the staging policy to use for the server.
+    ///
+    /// Records staged by the policy are not published until the registry
+    /// operator countersigns them.
+    pub fn with_staging_policy(mut self, policy: StagingPolicy) -> Self {
+        self.staging_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets the rules package records are validated under when sequenced.
+    ///
+    /// Clients and auditors of the registry should validate its package
+    /// logs under the same rules.
+    pub fn with_validation_rules(mut self, rules: ValidationRules) -> Self {
+        self.validation_rules = Some(rules);
+        self
+    }
+
+    /// Sets the yank policy to use for the server.
+    ///
+    /// Records yanking versions outside of the policy's window after release
+    /// are rejected unless the yank is accompanied by an advisory.
+    pub fn with_yank_policy(mut self, policy: YankPolicy) -> Self {
+        self.yank_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Sets the window relative to the server clock within which the
+    /// timestamps of published records must fall.
+    ///
+    /// If this is not specified, the default time window is used.
+    pub fn with_time_window(mut self, window: TimeWindow) -> Self {
+        self.time_window = Some(window);
+        self
+    }
+
+    /// Sets the event bus the server publishes registry events to.
+    ///
+    /// If this is not specified, the server will create its own event bus.
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Adds a webhook endpoint to notify of registry events.
+    ///
+    /// Webhook payloads are signed with the operator key.
+    pub fn with_webhook(mut self, url: Url) -> Self {
+        self.webhooks.push(url);
+        self
+    }
+
+    /// Enables package search using the given search index.
+    ///
+    /// The index is populated on startup and kept up to date as packages
+    /// are initialized. If this is not specified, search is not supported.
+    pub fn with_search_index(mut self, index: SearchIndex) -> Self {
+        self.search_index = Some(index);
+        self
+    }
+
+    /// Enables key activity queries using the given key index.
+    ///
+    /// The index is populated on startup and kept up to date as records
+    /// are sequenced. If this is not specified, key activity queries are
+    /// not supported.
+    pub fn with_key_index(mut self, index: KeyIndex) -> Self {
+        self.key_index = Some(index);
+        self
+    }
+
+    /// Inspects sequenced package records with the given anomaly monitor.
+    ///
+    /// The monitor's per-package baselines are populated on startup, and an
+    /// event is published for every anomaly its detectors flag. If this is
+    /// not specified, records are not inspected for anomalies.
+    pub fn with_anomaly_monitor(mut self, monitor: AnomalyMonitor) -> Self {
+        self.anomaly_monitor = Some(monitor);
+        self
+    }
+
+    /// Holds package records rejected on publish in the given quarantine.
+    ///
+    /// Quarantined records can be inspected and requeued with the admin API.
+    /// If this is not specified, rejected records are only logged.
+    pub fn with_quarantine(mut self, quarantine: Quarantine) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    /// Declares a key compromised.
+    ///
+    /// On startup, keys not already declared compromised in the operator log
+    /// are declared compromised as of the current registry log length; package
+    /// records signed by them are rejected from then on.
+    pub fn with_denied_key(mut self, key_id: KeyID) -> Self {
+        self.denied_keys.push(key_id);
+        self
+    }
+
+    /// Sets the authenticator to use for the server.
+    ///
+    /// Every request is authenticated before it is handled. If this is not
+    /// specified, requests are not authenticated.
+    pub fn with_authenticator(mut self, authenticator: impl Authenticator + 'static) -> Self {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Sets the operator keys document to serve at
+    /// `/.well-known/warg/operator-keys`.
+    ///
+    /// The document is signed by a root of trust out of band; the server
+    /// only checks that it lists the operator key.
+    pub fn with_operator_keys(mut self, operator_keys: SerdeEnvelope<OperatorKeys>) -> Self {
+        self.operator_keys = Some(Arc::new(operator_keys));
+        self
+    }
+
+    /// Sets the key the registry signs checkpoints with once an admin
+    /// rotates the checkpoint key.
+    ///
+    /// An admin command rotating the checkpoint key to any other key is
+    /// rejected.
+    pub fn with_next_operator_key(mut self, key: PrivateKey) -> Self {
+        self.next_operator_key = Some(key);
+        self
+    }
+
+    /// Sets the faults to inject into the server.
+    ///
+    /// Faults make the server misbehave on purpose; they are for testing
+    /// monitors and clients only.
+    #[cfg(feature = "fault-injection")]
+    pub fn with_faults(mut self, faults: faults::Faults) -> Self {
+        self.faults = Some(faults);
+        self
+    }
 }
 
 /// Represents the warg registry server.
@@ -178,26 +476,107 @@ impl Server {
             .with_context(|| format!("failed to bind to address `{addr}`"))?;
         let addr = listener.local_addr()?;
 
-        tracing::debug!(
-            "using server configuration: {config:?}",
-            config = self.config
-        );
+        let mut config = self.config;
+        let shutdown = config.shutdown.take();
+        let (router, core_handle) =
+            Self::start(config, Url::parse(&format!("http://{addr}")).unwrap()).await?;
 
-        let store = self
-            .config
+        Ok(InitializedServer {
+            listener,
+            router,
+            core_handle,
+            shutdown,
+        })
+    }
+
+    /// Initializes the server's internal state and background task(s) without
+    /// a listening socket, returning an [`in_process::InProcessServer`] that
+    /// serves requests sent through its client transport.
+    ///
+    /// Useful for fast, deterministic end-to-end tests.
+    #[cfg(feature = "in-process")]
+    pub async fn in_process(self) -> Result<in_process::InProcessServer> {
+        let url = Url::parse(in_process::IN_PROCESS_URL).unwrap();
+        let (router, core_handle) = Self::start(self.config, url.clone()).await?;
+        Ok(in_process::InProcessServer::new(url, router, core_handle))
+    }
+
+    async fn start(
+        config: Config,
+        default_content_base_url: Url,
+    ) -> Result<(Router, JoinHandle<()>)> {
+        tracing::debug!("using server configuration: {config:?}");
+
+        if let Some(operator_keys) = &config.operator_keys {
+            let key_id = config.operator_key.public_key().fingerprint();
+            let operator_keys: &OperatorKeys = operator_keys.as_ref().as_ref();
+            if operator_keys.key(&key_id).is_none() {
+                bail!("the operator keys document does not list operator key `{key_id}`");
+            }
+        }
+
+        let store = config
             .data_store
             .unwrap_or_else(|| Box::<MemoryDataStore>::default());
+        let events = config.events.unwrap_or_default();
+        for url in config.webhooks {
+            tracing::debug!("dispatching events to webhook `{url}`");
+            WebhookDispatcher::new(url, config.operator_key.clone()).spawn(&events);
+        }
+
         let (core, core_handle) = CoreService::start(
-            self.config.operator_key,
-            self.config.namespaces,
+            config.operator_key,
+            config.namespaces,
             store,
-            self.config
+            config
                 .checkpoint_interval
                 .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL),
+            config.checkpoint_batch_size,
+            config
+                .freshness_interval
+                .unwrap_or(DEFAULT_FRESHNESS_INTERVAL),
+            events.clone(),
+            config
+                .proof_cache_capacity
+                .unwrap_or(DEFAULT_PROOF_CACHE_CAPACITY),
+            config.sequencer_lease,
         )
         .await?;
 
-        let temp_dir = self.config.content_dir.join("tmp");
+        if let Some(policy) = &config.staging_policy {
+            core.enforce_staging(policy.clone());
+        }
+
+        if let Some(rules) = config.validation_rules {
+            core.enforce_rules(rules);
+        }
+
+        #[cfg(feature = "fault-injection")]
+        if let Some(faults) = config.faults {
+            tracing::warn!("injecting faults: {faults:?}");
+            core.inject_faults(faults);
+        }
+
+        if let Some(record_id) = core.deny_keys(config.denied_keys).await? {
+            tracing::info!("declared keys compromised in operator record `{record_id}`");
+        }
+
+        if let Some(index) = &config.search_index {
+            tracing::debug!("populating search index");
+            index.start(core.store(), &events).await?;
+        }
+
+        if let Some(index) = &config.key_index {
+            tracing::debug!("populating key index");
+            index.start(core.shared_store(), &events).await?;
+        }
+
+        if let Some(monitor) = config.anomaly_monitor {
+            tracing::debug!("populating package baselines");
+            core.monitor_anomalies(monitor).await?;
+        }
+
+        let temp_dir = config.content_dir.join("tmp");
         fs::create_dir_all(&temp_dir).with_context(|| {
             format!(
                 "failed to create content temp directory `{path}`",
@@ -205,7 +584,7 @@ impl Server {
             )
         })?;
 
-        let files_dir = self.config.content_dir.join("files");
+        let files_dir = config.content_dir.join("files");
         fs::create_dir_all(&files_dir).with_context(|| {
             format!(
                 "failed to create content files directory `{path}`",
@@ -213,26 +592,32 @@ impl Server {
             )
         })?;
 
-        let content_base_url = self
-            .config
-            .content_base_url
-            .unwrap_or_else(|| Url::parse(&format!("http://{addr}")).unwrap());
+        let content_base_url = config.content_base_url.unwrap_or(default_content_base_url);
+        let mut files = ContentFiles::new(files_dir);
+        if let Some(layout) = config.content_layout {
+            files = files.with_layout(layout);
+        }
 
         let router = create_router(
             content_base_url,
             core,
             temp_dir,
-            files_dir,
-            self.config.content_policy,
-            self.config.record_policy,
+            files,
+            config.content_policy,
+            config.allow_encrypted_content,
+            config.record_policy,
+            config.staging_policy,
+            config.yank_policy,
+            config.time_window.unwrap_or_default(),
+            config.search_index,
+            config.key_index,
+            config.next_operator_key,
+            config.quarantine,
+            config.authenticator,
+            config.operator_keys,
         );
 
-        Ok(InitializedServer {
-            listener,
-            router,
-            core_handle,
-            shutdown: self.config.shutdown,
-        })
+        Ok((router, core_handle))
     }
 }
 
@@ -0,0 +1,79 @@
+//! Runs a fully offline registry backed by a local directory.
+//!
+//! A [`LocalRegistry`] serves a registry in-process from a directory holding
+//! its records, checkpoints, and content, so that packages can be published
+//! and resolved without running a server, e.g. in tests, demos, or
+//! air-gapped environments. The registry's state survives being closed and
+//! reopened.
+//!
+//! The directory is laid out as follows:
+//!
+//! * `journal.jsonl` - the journal of the registry's data store (see
+//!   [`FileDataStore`]).
+//! * `content/` - the content of released package versions.
+
+use crate::{
+    datastore::FileDataStore,
+    in_process::{InProcessServer, InProcessTransport},
+    Config, Server,
+};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+const CONTENT_DIR: &str = "content";
+
+/// Represents a registry served in-process from a local directory.
+pub struct LocalRegistry {
+    dir: PathBuf,
+    server: InProcessServer,
+}
+
+impl LocalRegistry {
+    /// Opens the registry in the given directory, creating it if it does not
+    /// exist.
+    ///
+    /// The data store and content directory of the configuration are
+    /// replaced with ones in the given directory. The operator key of the
+    /// configuration must be the same each time the registry is opened.
+    pub async fn open(dir: impl Into<PathBuf>, mut config: Config) -> Result<Self> {
+        let dir = dir.into();
+        let journal = dir.join(JOURNAL_FILE);
+        let store = FileDataStore::open(&journal).await.with_context(|| {
+            format!(
+                "failed to open local registry journal `{path}`",
+                path = journal.display()
+            )
+        })?;
+
+        config.content_dir = dir.join(CONTENT_DIR);
+        let server = Server::new(config.with_data_store(store))
+            .in_process()
+            .await?;
+        Ok(Self { dir, server })
+    }
+
+    /// Gets the directory of the registry.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Gets the URL clients should use for the registry.
+    pub fn url(&self) -> &Url {
+        self.server.url()
+    }
+
+    /// Creates a transport that sends requests to the registry.
+    pub fn transport(&self) -> InProcessTransport {
+        self.server.transport()
+    }
+
+    /// Closes the registry, awaiting completion of its background task(s).
+    ///
+    /// As with [`InProcessServer::shutdown`], every transport created by
+    /// [`LocalRegistry::transport`] must have been dropped.
+    pub async fn close(self) -> Result<()> {
+        self.server.shutdown().await
+    }
+}
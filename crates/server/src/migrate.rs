@@ -0,0 +1,441 @@
+//! Splits and merges registries by namespace.
+//!
+//! Migrating namespaces to a new registry produces an archive of records for
+//! it. The new registry's operator log begins with a migration entry for each
+//! source registry, cross-signed by the source operator and recording the
+//! source checkpoint; the package logs of the migrated namespaces follow
+//! unchanged. Clients can therefore verify that a source operator agreed to
+//! the migration and continue following package logs from the new registry.
+//!
+//! The archives are turned into registry state with
+//! [`Recoverer`](crate::recover::Recoverer) using the new operator key.
+
+use crate::recover::{ArchivedRecord, Recoverer, Recovery, RecoveryError};
+use indexmap::IndexSet;
+use std::time::SystemTime;
+use thiserror::Error;
+use warg_crypto::{
+    hash::{HashAlgorithm, Sha256},
+    signing::{PrivateKey, PublicKey, SignatureError},
+};
+use warg_protocol::{
+    operator::{Migration, NamespaceState, OperatorEntry, OperatorRecord, OPERATOR_RECORD_VERSION},
+    registry::RecordId,
+    ProtoEnvelope,
+};
+
+/// Represents an error that prevented a migration.
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    /// The archive of a source registry contains records that do not validate.
+    #[error(
+        "the archive of registry `{registry}` contains {count} record(s) that do not validate"
+    )]
+    InvalidArchive {
+        /// The source registry.
+        registry: String,
+        /// The number of records that do not validate.
+        count: usize,
+    },
+    /// The archive of a source registry contains no records.
+    #[error("the archive of registry `{0}` is empty")]
+    EmptyArchive(String),
+    /// A namespace to migrate is not known to the source registry.
+    #[error("namespace `{namespace}` is not known to registry `{registry}`")]
+    NamespaceNotFound {
+        /// The source registry.
+        registry: String,
+        /// The namespace.
+        namespace: String,
+    },
+    /// A namespace is known to more than one source registry.
+    #[error("namespace `{0}` is known to more than one source registry")]
+    NamespaceConflict(String),
+    /// The operator key of a source registry is not an operator key in its
+    /// operator log.
+    #[error("the operator key of registry `{0}` is not permitted to sign its checkpoints")]
+    UntrustedOperatorKey(String),
+    /// A source registry could not be recovered from its archive.
+    #[error(transparent)]
+    Recovery(#[from] RecoveryError),
+    /// A record or migration could not be signed.
+    #[error("failed to sign migration: {0}")]
+    Signing(#[from] SignatureError),
+}
+
+/// A registry to migrate namespaces from.
+pub struct SourceRegistry {
+    registry: String,
+    operator_key: PrivateKey,
+    operator_key_history: Vec<PublicKey>,
+    archive: Vec<ArchivedRecord>,
+}
+
+impl SourceRegistry {
+    /// Creates a new source registry from an archive of its records in
+    /// registry order.
+    ///
+    /// The operator key cross-signs migrations from the registry.
+    pub fn new(
+        registry: impl Into<String>,
+        operator_key: PrivateKey,
+        archive: Vec<ArchivedRecord>,
+    ) -> Self {
+        let operator_key_history = vec![operator_key.public_key()];
+        Self {
+            registry: registry.into(),
+            operator_key,
+            operator_key_history,
+            archive,
+        }
+    }
+
+    /// Sets the history of keys that have signed the registry's operator log.
+    ///
+    /// Defaults to the registry's current operator key.
+    pub fn with_operator_key_history(mut self, keys: impl IntoIterator<Item = PublicKey>) -> Self {
+        self.operator_key_history = keys.into_iter().collect();
+        self
+    }
+
+    /// Validates the archive, returning the recovered state of the registry.
+    fn recover(&self) -> Result<Recovery, MigrationError> {
+        let recovery = Recoverer::new(
+            self.operator_key.clone(),
+            self.operator_key_history.iter().cloned(),
+        )
+        .recover(self.archive.iter().cloned())?;
+
+        let count = recovery.report().rejected.len();
+        if count > 0 {
+            return Err(MigrationError::InvalidArchive {
+                registry: self.registry.clone(),
+                count,
+            });
+        }
+
+        Ok(recovery)
+    }
+}
+
+/// Splits a registry into two new registries.
+///
+/// The first registry receives the given namespaces and the second receives
+/// the remaining namespaces of the source registry. Each new registry's
+/// operator log is initialized with the corresponding operator key.
+///
+/// Returns the archives of records of the two new registries.
+pub fn split(
+    source: &SourceRegistry,
+    namespaces: &[String],
+    first_operator_key: &PrivateKey,
+    second_operator_key: &PrivateKey,
+) -> Result<(Vec<ArchivedRecord>, Vec<ArchivedRecord>), MigrationError> {
+    let recovery = source.recover()?;
+    let operator = recovery.operator_state();
+
+    let first = namespaces.iter().cloned().collect::<IndexSet<_>>();
+    if let Some(namespace) = first
+        .iter()
+        .find(|namespace| operator.namespace_state(namespace).is_none())
+    {
+        return Err(MigrationError::NamespaceNotFound {
+            registry: source.registry.clone(),
+            namespace: namespace.clone(),
+        });
+    }
+
+    let second = operator
+        .namespaces()
+        .map(|(namespace, _)| namespace.to_string())
+        .filter(|namespace| !first.contains(namespace))
+        .collect::<IndexSet<_>>();
+
+    Ok((
+        migrate(first_operator_key, &[(source, &recovery, first)])?,
+        migrate(second_operator_key, &[(source, &recovery, second)])?,
+    ))
+}
+
+/// Merges registries into a new registry.
+///
+/// The new registry receives every namespace of the source registries, which
+/// must not share any namespaces. Its operator log is initialized with the
+/// given operator key.
+///
+/// Returns the archive of records of the new registry.
+pub fn merge(
+    sources: &[SourceRegistry],
+    operator_key: &PrivateKey,
+) -> Result<Vec<ArchivedRecord>, MigrationError> {
+    let recoveries = sources
+        .iter()
+        .map(SourceRegistry::recover)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut seen = IndexSet::new();
+    let mut parts = Vec::with_capacity(sources.len());
+    for (source, recovery) in sources.iter().zip(&recoveries) {
+        let namespaces = recovery
+            .operator_state()
+            .namespaces()
+            .map(|(namespace, _)| namespace.to_string())
+            .collect::<IndexSet<_>>();
+        for namespace in &namespaces {
+            if !seen.insert(namespace.clone()) {
+                return Err(MigrationError::NamespaceConflict(namespace.clone()));
+            }
+        }
+
+        parts.push((source, recovery, namespaces));
+    }
+
+    migrate(operator_key, &parts)
+}
+
+/// Migrates the given namespaces of each source registry to a new registry.
+fn migrate(
+    operator_key: &PrivateKey,
+    sources: &[(&SourceRegistry, &Recovery, IndexSet<String>)],
+) -> Result<Vec<ArchivedRecord>, MigrationError> {
+    let destination = operator_key.public_key().fingerprint();
+    let timestamp = SystemTime::now();
+
+    let mut entries = vec![OperatorEntry::Init {
+        hash_algorithm: HashAlgorithm::Sha256,
+        key: operator_key.public_key(),
+    }];
+    let mut packages = Vec::new();
+    let mut denied_keys = IndexSet::new();
+    for (source, recovery, namespaces) in sources {
+        let operator = recovery.operator_state();
+        let checkpoint = recovery
+            .checkpoints()
+            .last()
+            .map(|checkpoint| checkpoint.as_ref().checkpoint.clone())
+            .ok_or_else(|| MigrationError::EmptyArchive(source.registry.clone()))?;
+
+        // Namespaces defined by the source are migrated with their package
+        // logs, while imported namespaces remain imported
+        let mut defined = Vec::new();
+        for namespace in namespaces {
+            match operator.namespace_state(namespace) {
+                Some(NamespaceState::Defined) => defined.push(namespace.clone()),
+                Some(NamespaceState::Imported { registry }) => {
+                    entries.push(OperatorEntry::ImportNamespace {
+                        namespace: namespace.clone(),
+                        registry: registry.clone(),
+                    })
+                }
+                None => {}
+            }
+        }
+
+        if !defined.is_empty() {
+            // Clients only follow migrations signed by an operator key of the source
+            if !operator.key_has_permission_to_sign_checkpoints(
+                &source.operator_key.public_key().fingerprint(),
+            ) {
+                return Err(MigrationError::UntrustedOperatorKey(
+                    source.registry.clone(),
+                ));
+            }
+
+            entries.push(OperatorEntry::Migrate(Migration::sign(
+                &source.operator_key,
+                &destination,
+                source.registry.clone(),
+                checkpoint,
+                defined.iter().cloned(),
+            )?));
+        }
+
+        packages.extend(source.archive.iter().filter(|record| match record {
+            ArchivedRecord::Package { name, .. } => defined.iter().any(|n| n == name.namespace()),
+            ArchivedRecord::Operator(_) => false,
+        }));
+
+        denied_keys.extend(operator.denied_keys().map(|(key_id, _)| key_id.clone()));
+    }
+
+    let init = ProtoEnvelope::signed_contents(
+        operator_key,
+        OperatorRecord {
+            prev: None,
+            version: OPERATOR_RECORD_VERSION,
+            timestamp,
+            entries,
+        },
+    )?;
+    let init_id = RecordId::operator_record::<Sha256>(&init);
+
+    let mut archive = Vec::with_capacity(packages.len() + 2);
+    archive.push(ArchivedRecord::Operator(init));
+    archive.extend(packages.into_iter().cloned());
+
+    // Keys declared compromised by a source remain so for new records; records
+    // they signed before the declaration were valid in the source
+    if !denied_keys.is_empty() {
+        let log_length = archive.len();
+        archive.push(ArchivedRecord::Operator(ProtoEnvelope::signed_contents(
+            operator_key,
+            OperatorRecord {
+                prev: Some(init_id),
+                version: OPERATOR_RECORD_VERSION,
+                timestamp,
+                entries: denied_keys
+                    .into_iter()
+                    .map(|key_id| OperatorEntry::DenyKey { key_id, log_length })
+                    .collect(),
+            },
+        )?));
+    }
+
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_protocol::{
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::PackageName,
+    };
+
+    // Alice and Bob are the test keys described in `crates/protocol/tests/README.md`
+    const ALICE: &str = "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=";
+    const BOB: &str = "ecdsa-p256:2CV1EpLaSYEn4In4OAEDAj5O4Hzu8AFAxgHXuG310Ew=";
+    const CAROL: &str = "ecdsa-p256:AwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwM=";
+    const DAVE: &str = "ecdsa-p256:BAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQ=";
+
+    fn key(key: &str) -> PrivateKey {
+        PrivateKey::decode(key.to_string()).unwrap()
+    }
+
+    fn source_registry(
+        registry: &str,
+        operator_key: &PrivateKey,
+        namespaces: &[&str],
+    ) -> SourceRegistry {
+        let mut entries = vec![OperatorEntry::Init {
+            hash_algorithm: HashAlgorithm::Sha256,
+            key: operator_key.public_key(),
+        }];
+        entries.extend(
+            namespaces
+                .iter()
+                .map(|namespace| OperatorEntry::DefineNamespace {
+                    namespace: namespace.to_string(),
+                }),
+        );
+        let mut archive = vec![ArchivedRecord::Operator(
+            ProtoEnvelope::signed_contents(
+                operator_key,
+                OperatorRecord {
+                    prev: None,
+                    version: OPERATOR_RECORD_VERSION,
+                    timestamp: SystemTime::now(),
+                    entries,
+                },
+            )
+            .unwrap(),
+        )];
+
+        let publisher = key(BOB);
+        for namespace in namespaces {
+            archive.push(ArchivedRecord::Package {
+                name: PackageName::new(format!("{namespace}:package")).unwrap(),
+                envelope: ProtoEnvelope::signed_contents(
+                    &publisher,
+                    PackageRecord {
+                        prev: None,
+                        version: PACKAGE_RECORD_VERSION,
+                        timestamp: SystemTime::now(),
+                        entries: vec![
+                            PackageEntry::Init {
+                                hash_algorithm: HashAlgorithm::Sha256,
+                                key: publisher.public_key(),
+                            },
+                            PackageEntry::release(
+                                "1.0.0",
+                                HashAlgorithm::Sha256.digest(namespace.as_bytes()),
+                            )
+                            .unwrap(),
+                        ],
+                        entry_signatures: Vec::new(),
+                        publish_token: None,
+                    },
+                )
+                .unwrap(),
+            });
+        }
+
+        SourceRegistry::new(registry, operator_key.clone(), archive)
+    }
+
+    fn recover(operator_key: &PrivateKey, archive: Vec<ArchivedRecord>) -> Recovery {
+        let recovery = Recoverer::new(operator_key.clone(), [operator_key.public_key()])
+            .recover(archive)
+            .unwrap();
+        assert!(recovery.report().rejected.is_empty());
+        recovery
+    }
+
+    #[test]
+    fn splits_and_merges_registries() {
+        let source_key = key(ALICE);
+        let first_key = key(CAROL);
+        let second_key = key(DAVE);
+        let source = source_registry("source.example.com", &source_key, &["alpha", "beta"]);
+
+        let (first, second) =
+            split(&source, &["alpha".to_string()], &first_key, &second_key).unwrap();
+
+        let first = recover(&first_key, first);
+        assert_eq!(first.report().packages, 1);
+        let operator = first.operator_state();
+        let migration = operator.namespace_migration("alpha").unwrap();
+        assert_eq!(migration.registry, "source.example.com");
+        migration
+            .verify_source(source.recover().unwrap().operator_state())
+            .unwrap();
+        assert!(migration
+            .verify_source(
+                source_registry("other.example.com", &second_key, &[])
+                    .recover()
+                    .unwrap()
+                    .operator_state()
+            )
+            .is_err());
+        assert!(operator.namespace_state("beta").is_none());
+
+        let second = recover(&second_key, second);
+        assert_eq!(second.report().packages, 1);
+        assert!(second.operator_state().namespace_state("alpha").is_none());
+        assert!(second
+            .operator_state()
+            .namespace_migration("beta")
+            .is_some());
+
+        let err = split(&source, &["gamma".to_string()], &first_key, &second_key).unwrap_err();
+        assert!(matches!(err, MigrationError::NamespaceNotFound { .. }));
+
+        let other = source_registry("other.example.com", &second_key, &["gamma"]);
+        let merged = merge(&[source, other], &first_key).unwrap();
+        let merged = recover(&first_key, merged);
+        assert_eq!(merged.report().packages, 3);
+        let operator = merged.operator_state();
+        assert_eq!(
+            operator.namespace_migration("gamma").unwrap().registry,
+            "other.example.com"
+        );
+        assert!(operator.namespace_migration("beta").is_some());
+
+        let conflicting = [
+            source_registry("a.example.com", &source_key, &["alpha"]),
+            source_registry("b.example.com", &second_key, &["alpha"]),
+        ];
+        let err = merge(&conflicting, &first_key).unwrap_err();
+        assert!(matches!(err, MigrationError::NamespaceConflict(ns) if ns == "alpha"));
+    }
+}
@@ -2,3 +2,4 @@
 
 pub mod content;
 pub mod record;
+pub mod staging;
@@ -124,8 +124,9 @@ impl RecordPolicy for AuthorizedKeyPolicy {
         name: &PackageName,
         record: &ProtoEnvelope<PackageRecord>,
     ) -> RecordPolicyResult<()> {
-        let key = record.key_id();
-        for entry in &record.as_ref().entries {
+        for (index, entry) in record.as_ref().entries.iter().enumerate() {
+            // Individually signed entries are authorized by their own signer
+            let key = record.as_ref().entry_signer(index, record.key_id());
             let is_init = matches!(entry, PackageEntry::Init { .. });
             if !self.key_authorized_for_entry(key, name, is_init) {
                 return Err(RecordPolicyError::Unauthorized(format!(
@@ -0,0 +1,89 @@
+//! Module for the server staging policy.
+use anyhow::{bail, Result};
+use indexmap::IndexSet;
+use warg_crypto::signing::PublicKey;
+use warg_protocol::{package::CountersignaturePolicy, registry::PackageName};
+
+/// A policy that determines which package records are staged until the
+/// registry operator countersigns them.
+///
+/// Staged records are stored by the registry but are not sequenced into
+/// the log until an operator countersignature is attached.
+#[derive(Debug, Default)]
+pub struct StagingPolicy {
+    reserved_namespaces: IndexSet<String>,
+    reserved_packages: IndexSet<PackageName>,
+    key_rotation: bool,
+}
+
+impl StagingPolicy {
+    /// Creates a new staging policy.
+    ///
+    /// By default, no records are staged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves a namespace, requiring the initialization of any package in
+    /// it to be countersigned.
+    pub fn with_reserved_namespace(mut self, namespace: impl Into<String>) -> Result<Self> {
+        let namespace = namespace.into();
+        if !PackageName::is_valid_namespace(&namespace) {
+            bail!("namespace `{namespace}` is not a valid kebab-cased string");
+        }
+
+        self.reserved_namespaces.insert(namespace);
+        Ok(self)
+    }
+
+    /// Reserves a package name, requiring its initialization to be countersigned.
+    pub fn with_reserved_package(mut self, package_name: impl Into<String>) -> Result<Self> {
+        self.reserved_packages
+            .insert(PackageName::new(package_name.into())?);
+        Ok(self)
+    }
+
+    /// Requires key rotations (permission grants and revocations) in any
+    /// package to be countersigned.
+    pub fn with_key_rotation(mut self, required: bool) -> Self {
+        self.key_rotation = required;
+        self
+    }
+
+    /// Determines if the given package name is reserved.
+    pub fn is_reserved(&self, name: &PackageName) -> bool {
+        self.reserved_packages.contains(name) || self.reserved_namespaces.contains(name.namespace())
+    }
+
+    /// Gets the countersignature policy for the given package, where
+    /// countersignatures are made by the given operator key.
+    pub fn countersignature_policy(
+        &self,
+        operator_key: PublicKey,
+        name: &PackageName,
+    ) -> CountersignaturePolicy {
+        CountersignaturePolicy::new(operator_key)
+            .with_init(self.is_reserved(name))
+            .with_key_rotation(self.key_rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_names() -> Result<()> {
+        let policy = StagingPolicy::new()
+            .with_reserved_namespace("core")?
+            .with_reserved_package("test:reserved")?;
+
+        assert!(policy.is_reserved(&PackageName::new("core:any")?));
+        assert!(policy.is_reserved(&PackageName::new("test:reserved")?));
+        assert!(!policy.is_reserved(&PackageName::new("test:other")?));
+        assert!(StagingPolicy::new()
+            .with_reserved_namespace("NotValid")
+            .is_err());
+        Ok(())
+    }
+}
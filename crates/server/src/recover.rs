@@ -0,0 +1,354 @@
+//! Recovers registry state from an archive of records.
+//!
+//! If a registry's data store is lost or corrupted but an archive of its
+//! records survives, [`Recoverer::recover`] re-validates every record from
+//! scratch, rebuilds the registry log and map, and signs a fresh chain of
+//! checkpoints. Records that no longer validate are left out of the recovered
+//! state and reported.
+//!
+//! The recovered state can then be restored to an empty data store with
+//! [`Recovery::restore`], from which the registry starts as usual.
+
+use crate::datastore::{DataStore, DataStoreError};
+use indexmap::{IndexMap, IndexSet};
+use thiserror::Error;
+use warg_crypto::{
+    hash::{Hash, Sha256},
+    signing::{KeyID, PrivateKey, PublicKey},
+};
+use warg_protocol::{
+    operator, package,
+    registry::{
+        Checkpoint, LogId, LogLeaf, MapKey, MapLeaf, PackageName, RecordId, RegistryIndex,
+        RegistryLen, TimestampedCheckpoint,
+    },
+    ProtoEnvelope, SerdeEnvelope,
+};
+use warg_transparency::{
+    log::{LogBuilder, VecLog},
+    map::Map,
+};
+
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 1000;
+
+/// Represents an error that prevented a recovery.
+#[derive(Debug, Error)]
+pub enum RecoveryError {
+    /// The operator key may not sign checkpoints of the recovered state.
+    #[error("operator key `{0}` does not have permission to sign checkpoints")]
+    OperatorKeyUnauthorized(KeyID),
+    /// A checkpoint could not be signed.
+    #[error("failed to sign checkpoint: {0}")]
+    Signing(anyhow::Error),
+    /// A data store error occurred while restoring.
+    #[error("data store error: {0}")]
+    DataStore(#[from] DataStoreError),
+}
+
+/// A record in an archive of a registry's records.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum ArchivedRecord {
+    /// A record of the operator log.
+    Operator(ProtoEnvelope<operator::OperatorRecord>),
+    /// A record of a package log.
+    Package {
+        /// The name of the package.
+        name: PackageName,
+        /// The package record.
+        envelope: ProtoEnvelope<package::PackageRecord>,
+    },
+}
+
+impl ArchivedRecord {
+    /// Gets the log id of the record.
+    pub fn log_id(&self) -> LogId {
+        match self {
+            Self::Operator(_) => LogId::operator_log::<Sha256>(),
+            Self::Package { name, .. } => LogId::package_log::<Sha256>(name),
+        }
+    }
+
+    /// Gets the record id of the record.
+    pub fn record_id(&self) -> RecordId {
+        match self {
+            Self::Operator(envelope) => RecordId::operator_record::<Sha256>(envelope),
+            Self::Package { envelope, .. } => RecordId::package_record::<Sha256>(envelope),
+        }
+    }
+}
+
+/// A record of the archive that no longer validates.
+#[derive(Debug)]
+pub struct RejectedRecord {
+    /// The index of the record in the archive.
+    pub index: usize,
+    /// The log of the record.
+    pub log_id: LogId,
+    /// The id of the record.
+    pub record_id: RecordId,
+    /// The reason the record was rejected.
+    pub error: DataStoreError,
+}
+
+/// A report of a recovery.
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// The number of records recovered, including operator records.
+    pub records: usize,
+    /// The number of packages recovered.
+    pub packages: usize,
+    /// The records of the archive that no longer validate, in archive order.
+    pub rejected: Vec<RejectedRecord>,
+}
+
+/// Registry state recovered from an archive of records.
+pub struct Recovery {
+    records: Vec<(LogLeaf, ArchivedRecord)>,
+    checkpoints: Vec<SerdeEnvelope<TimestampedCheckpoint>>,
+    operator: operator::LogState,
+    report: RecoveryReport,
+}
+
+impl Recovery {
+    /// Gets the leafs of the recovered registry log in order.
+    pub fn leafs(&self) -> impl Iterator<Item = &LogLeaf> {
+        self.records.iter().map(|(leaf, _)| leaf)
+    }
+
+    /// Gets the fresh chain of signed checkpoints of the recovered state.
+    pub fn checkpoints(&self) -> &[SerdeEnvelope<TimestampedCheckpoint>] {
+        &self.checkpoints
+    }
+
+    /// Gets the validated state of the recovered operator log.
+    pub fn operator_state(&self) -> &operator::LogState {
+        &self.operator
+    }
+
+    /// Gets the report of the recovery.
+    pub fn report(&self) -> &RecoveryReport {
+        &self.report
+    }
+
+    /// Restores the recovered state to the given data store.
+    ///
+    /// The data store is expected to be empty.
+    pub async fn restore(&self, store: &dyn DataStore) -> Result<(), RecoveryError> {
+        for (index, (leaf, record)) in self.records.iter().enumerate() {
+            let LogLeaf { log_id, record_id } = leaf;
+            let registry_index = index as RegistryIndex;
+            match record {
+                ArchivedRecord::Operator(envelope) => {
+                    store
+                        .store_operator_record(log_id, record_id, envelope)
+                        .await?;
+                    store
+                        .commit_operator_record(log_id, record_id, registry_index)
+                        .await?;
+                }
+                ArchivedRecord::Package { name, envelope } => {
+                    store
+                        .store_package_record(log_id, name, record_id, envelope, &IndexSet::new())
+                        .await?;
+                    store
+                        .commit_package_record(
+                            log_id,
+                            record_id,
+                            registry_index,
+                            None,
+                            &package::ValidationRules::new(),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        for checkpoint in &self.checkpoints {
+            let checkpoint_id = Hash::<Sha256>::of(&checkpoint.as_ref().checkpoint).into();
+            store
+                .store_checkpoint(&checkpoint_id, checkpoint.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Recovers registry state from an archive of records.
+pub struct Recoverer {
+    operator_key: PrivateKey,
+    operator_keys: IndexSet<KeyID>,
+    checkpoint_interval: usize,
+}
+
+impl Recoverer {
+    /// Creates a new recoverer.
+    ///
+    /// The operator key history is the set of keys that have signed the
+    /// registry's operator log; operator records signed by any other key are
+    /// rejected. The operator key signs the checkpoints of the recovered
+    /// state and must have permission to do so in the recovered operator log.
+    pub fn new(
+        operator_key: PrivateKey,
+        operator_key_history: impl IntoIterator<Item = PublicKey>,
+    ) -> Self {
+        Self {
+            operator_key,
+            operator_keys: operator_key_history
+                .into_iter()
+                .map(|key| key.fingerprint())
+                .collect(),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+        }
+    }
+
+    /// Sets the number of recovered records between checkpoints.
+    ///
+    /// A checkpoint is always signed for the complete recovered log.
+    pub fn with_checkpoint_interval(mut self, records: usize) -> Self {
+        self.checkpoint_interval = records.max(1);
+        self
+    }
+
+    /// Recovers registry state from the given records in registry order.
+    ///
+    /// Every record is validated as if it were being published again; records
+    /// that fail validation are left out of the recovered state and reported.
+    pub fn recover(
+        &self,
+        archive: impl IntoIterator<Item = ArchivedRecord>,
+    ) -> Result<Recovery, RecoveryError> {
+        let mut state = State::default();
+        let mut records = Vec::new();
+        let mut checkpoints = Vec::new();
+        let mut report = RecoveryReport::default();
+
+        for (index, record) in archive.into_iter().enumerate() {
+            let log_id = record.log_id();
+            let record_id = record.record_id();
+            let registry_index = records.len() as RegistryIndex;
+            if let Err(error) = self.validate(&mut state, &log_id, &record, registry_index) {
+                tracing::debug!("record `{record_id}` no longer validates: {error}");
+                report.rejected.push(RejectedRecord {
+                    index,
+                    log_id,
+                    record_id,
+                    error,
+                });
+                continue;
+            }
+
+            let leaf = LogLeaf { log_id, record_id };
+            state.push(&leaf);
+            records.push((leaf, record));
+
+            if records.len() % self.checkpoint_interval == 0 {
+                checkpoints.push(self.sign_checkpoint(&state)?);
+            }
+        }
+
+        if records.len() % self.checkpoint_interval != 0 {
+            checkpoints.push(self.sign_checkpoint(&state)?);
+        }
+
+        report.records = records.len();
+        report.packages = state.packages.len();
+        Ok(Recovery {
+            records,
+            checkpoints,
+            operator: state.operator,
+            report,
+        })
+    }
+
+    fn validate(
+        &self,
+        state: &mut State,
+        log_id: &LogId,
+        record: &ArchivedRecord,
+        registry_index: RegistryIndex,
+    ) -> Result<(), DataStoreError> {
+        match record {
+            ArchivedRecord::Operator(envelope) => {
+                if !self.operator_keys.contains(envelope.key_id()) {
+                    return Err(DataStoreError::KeyUnauthorized(envelope.key_id().clone()));
+                }
+
+                state.operator = state.operator.clone().validate(envelope)?;
+            }
+            ArchivedRecord::Package { name, envelope } => {
+                match state.operator.namespace_state(name.namespace()) {
+                    Some(operator::NamespaceState::Defined) => {}
+                    Some(operator::NamespaceState::Imported { .. }) => {
+                        return Err(DataStoreError::PackageNamespaceImported(
+                            name.namespace().to_string(),
+                        ))
+                    }
+                    None => {
+                        return Err(DataStoreError::PackageNamespaceNotDefined(
+                            name.namespace().to_string(),
+                        ))
+                    }
+                }
+
+                state
+                    .operator
+                    .check_package_record(envelope, registry_index)?;
+                let package = state.packages.entry(log_id.clone()).or_default();
+
+                // The package scope of a publish token is checked by the
+                // registry rather than the package log; archives do not record
+                // when records were sequenced, so tokens are checked as of the
+                // record timestamps
+                package.validate_publish_token(log_id, envelope, envelope.as_ref().timestamp)?;
+                *package = package.clone().validate(envelope)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sign_checkpoint(
+        &self,
+        state: &State,
+    ) -> Result<SerdeEnvelope<TimestampedCheckpoint>, RecoveryError> {
+        let key_id = self.operator_key.public_key().fingerprint();
+        if !state
+            .operator
+            .key_has_permission_to_sign_checkpoints(&key_id)
+        {
+            return Err(RecoveryError::OperatorKeyUnauthorized(key_id));
+        }
+
+        let log_checkpoint = state.log.checkpoint();
+        let checkpoint = Checkpoint {
+            log_length: log_checkpoint.length() as RegistryLen,
+            log_root: log_checkpoint.root().into(),
+            map_root: state.map.root().into(),
+        };
+        let timestamped = TimestampedCheckpoint::now(checkpoint).map_err(RecoveryError::Signing)?;
+        SerdeEnvelope::signed_contents(&self.operator_key, timestamped)
+            .map_err(|e| RecoveryError::Signing(e.into()))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    log: VecLog<Sha256, LogLeaf>,
+    map: Map<Sha256, MapKey, MapLeaf>,
+    operator: operator::LogState,
+    packages: IndexMap<LogId, package::LogState>,
+}
+
+impl State {
+    fn push(&mut self, leaf: &LogLeaf) {
+        self.log.push(leaf);
+        self.map = self.map.insert(
+            leaf.log_id.clone().into(),
+            MapLeaf {
+                record_id: leaf.record_id.clone(),
+            },
+        );
+    }
+}
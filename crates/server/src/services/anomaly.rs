@@ -0,0 +1,440 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use warg_crypto::{hash::Sha256, signing::KeyID};
+use warg_protocol::{
+    package::{PackageEntry, PackageRecord},
+    registry::{LogId, RegistryIndex},
+    ProtoEnvelope,
+};
+
+use crate::datastore::{DataStore, DataStoreError};
+
+const POPULATE_PAGE_SIZE: usize = 1000;
+
+/// The number of recent records retained in a package baseline.
+const RECENT_RECORDS: usize = 32;
+
+/// The default window of a [`GrantReleaseBurst`] detector.
+const DEFAULT_BURST_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// The default number of releases of a [`GrantReleaseBurst`] detector.
+const DEFAULT_BURST_RELEASES: usize = 3;
+
+/// The kind of an entry in a package record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryKind {
+    /// An `init` entry.
+    Init,
+    /// A `grant` entry.
+    Grant,
+    /// A `revoke` entry.
+    Revoke,
+    /// A `release` entry.
+    Release,
+    /// A `yank` or `yank-range` entry.
+    Yank,
+    /// A `tag` entry.
+    Tag,
+    /// A `require-reviewers` entry.
+    RequireReviewers,
+    /// An entry of another kind.
+    Other,
+}
+
+impl From<&PackageEntry> for EntryKind {
+    fn from(entry: &PackageEntry) -> Self {
+        match entry {
+            PackageEntry::Init { .. } => Self::Init,
+            PackageEntry::GrantFlat { .. } => Self::Grant,
+            PackageEntry::RevokeFlat { .. } => Self::Revoke,
+            PackageEntry::Release { .. } => Self::Release,
+            PackageEntry::Yank { .. } | PackageEntry::YankRange { .. } => Self::Yank,
+            PackageEntry::Tag { .. } => Self::Tag,
+            PackageEntry::RequireReviewers { .. } => Self::RequireReviewers,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A recently sequenced package record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentRecord {
+    /// The index of the record in the registry log.
+    pub registry_index: RegistryIndex,
+    /// The timestamp of the record.
+    pub timestamp: SystemTime,
+    /// The key that signed the record.
+    pub signer: KeyID,
+    /// The kinds of the entries of the record, in record order.
+    pub entries: Vec<EntryKind>,
+}
+
+/// The baseline activity of a package, against which newly sequenced
+/// records of the package are inspected.
+#[derive(Debug, Clone, Default)]
+pub struct PackageBaseline {
+    records: u64,
+    first_activity: Option<SystemTime>,
+    entries: IndexMap<EntryKind, u64>,
+    recent: VecDeque<RecentRecord>,
+}
+
+impl PackageBaseline {
+    /// Gets the number of records of the package.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Gets the number of entries of the given kind in the package log.
+    pub fn entries(&self, kind: EntryKind) -> u64 {
+        self.entries.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// Gets the share of the entries in the package log that are of the
+    /// given kind, between 0 and 1.
+    pub fn entry_share(&self, kind: EntryKind) -> f64 {
+        let total: u64 = self.entries.values().sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.entries(kind) as f64 / total as f64
+    }
+
+    /// Gets the mean interval between records of the package.
+    ///
+    /// Returns `None` if the package has fewer than two records.
+    pub fn mean_interval(&self) -> Option<Duration> {
+        let first = self.first_activity?;
+        let last = self.recent.back()?.timestamp;
+        let intervals = u32::try_from(self.records.checked_sub(1)?).ok()?;
+        if intervals == 0 {
+            return None;
+        }
+
+        Some(last.duration_since(first).unwrap_or_default() / intervals)
+    }
+
+    /// Gets the most recent records of the package, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &RecentRecord> {
+        self.recent.iter()
+    }
+
+    fn last_index(&self) -> Option<RegistryIndex> {
+        self.recent.back().map(|r| r.registry_index)
+    }
+
+    fn add(&mut self, registry_index: RegistryIndex, record: &ProtoEnvelope<PackageRecord>) {
+        let timestamp = record.as_ref().timestamp;
+        let entries = record
+            .as_ref()
+            .entries
+            .iter()
+            .map(EntryKind::from)
+            .collect::<Vec<_>>();
+
+        self.records += 1;
+        self.first_activity.get_or_insert(timestamp);
+        for kind in &entries {
+            *self.entries.entry(*kind).or_default() += 1;
+        }
+
+        if self.recent.len() == RECENT_RECORDS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(RecentRecord {
+            registry_index,
+            timestamp,
+            signer: record.key_id().clone(),
+            entries,
+        });
+    }
+}
+
+/// An anomaly detected in the activity of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    /// The name of the detector that detected the anomaly.
+    pub detector: String,
+    /// A description of the anomaly.
+    pub description: String,
+}
+
+/// A heuristic for detecting anomalous package records.
+pub trait AnomalyDetector: Send + Sync {
+    /// Inspects a newly sequenced package record against the baseline of its
+    /// package, which does not yet include the record.
+    ///
+    /// Returns the anomaly detected, if any.
+    fn inspect(
+        &self,
+        baseline: &PackageBaseline,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> Option<Anomaly>;
+}
+
+/// Detects a burst of releases shortly after a key was granted permission,
+/// as happens when a newly granted key is abused.
+#[derive(Debug, Clone)]
+pub struct GrantReleaseBurst {
+    window: Duration,
+    releases: usize,
+}
+
+impl GrantReleaseBurst {
+    /// Creates a detector for `releases` or more releases within `window` of
+    /// a grant.
+    pub fn new(window: Duration, releases: usize) -> Self {
+        Self { window, releases }
+    }
+}
+
+impl Default for GrantReleaseBurst {
+    fn default() -> Self {
+        Self::new(DEFAULT_BURST_WINDOW, DEFAULT_BURST_RELEASES)
+    }
+}
+
+impl AnomalyDetector for GrantReleaseBurst {
+    fn inspect(
+        &self,
+        baseline: &PackageBaseline,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> Option<Anomaly> {
+        let entries = record
+            .as_ref()
+            .entries
+            .iter()
+            .map(EntryKind::from)
+            .collect::<Vec<_>>();
+        let released = entries.iter().filter(|k| **k == EntryKind::Release).count();
+        if released == 0 {
+            return None;
+        }
+
+        let timestamp = record.as_ref().timestamp;
+        let since = timestamp
+            .checked_sub(self.window)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let window = baseline
+            .recent()
+            .filter(|r| r.timestamp >= since)
+            .map(|r| r.entries.as_slice())
+            .chain([entries.as_slice()]);
+
+        // Count the releases from the first grant in the window onward
+        let mut granted = false;
+        let mut before = 0;
+        for entries in window {
+            granted |= entries.contains(&EntryKind::Grant);
+            if granted {
+                before += entries.iter().filter(|k| **k == EntryKind::Release).count();
+            }
+        }
+        let before = before.checked_sub(released)?;
+
+        // Only the record crossing the threshold is anomalous
+        (before < self.releases && before + released >= self.releases).then(|| Anomaly {
+            detector: "grant-release-burst".into(),
+            description: format!(
+                "{count} releases within {window:?} of a key being granted permission",
+                count = before + released,
+                window = self.window,
+            ),
+        })
+    }
+}
+
+/// A monitor of package activity that maintains per-package baselines and
+/// inspects newly sequenced records with its anomaly detectors.
+///
+/// Cloning the monitor produces a handle to the same baselines.
+#[derive(Clone, Default)]
+pub struct AnomalyMonitor {
+    detectors: Vec<Arc<dyn AnomalyDetector>>,
+    baselines: Arc<RwLock<HashMap<LogId, PackageBaseline>>>,
+}
+
+impl std::fmt::Debug for AnomalyMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnomalyMonitor")
+            .field("detectors", &self.detectors.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnomalyMonitor {
+    /// Creates a new monitor without any anomaly detectors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an anomaly detector to the monitor.
+    pub fn with_detector(mut self, detector: impl AnomalyDetector + 'static) -> Self {
+        self.detectors.push(Arc::new(detector));
+        self
+    }
+
+    /// Gets the baseline of the given package log.
+    pub async fn baseline(&self, log_id: &LogId) -> Option<PackageBaseline> {
+        self.baselines.read().await.get(log_id).cloned()
+    }
+
+    /// Inspects a newly sequenced package record with every detector before
+    /// adding it to the baseline of its package.
+    ///
+    /// Records at or before the last record observed for the package are
+    /// ignored.
+    pub async fn observe(
+        &self,
+        log_id: &LogId,
+        registry_index: RegistryIndex,
+        record: &ProtoEnvelope<PackageRecord>,
+    ) -> Vec<Anomaly> {
+        let mut baselines = self.baselines.write().await;
+        let baseline = baselines.entry(log_id.clone()).or_default();
+        if baseline
+            .last_index()
+            .is_some_and(|last| registry_index <= last)
+        {
+            return Vec::new();
+        }
+
+        let anomalies = self
+            .detectors
+            .iter()
+            .filter_map(|detector| detector.inspect(baseline, record))
+            .collect();
+        baseline.add(registry_index, record);
+        anomalies
+    }
+
+    /// Populates the baselines from the records in the given data store.
+    ///
+    /// Existing records are not inspected by the detectors.
+    pub async fn populate(&self, store: &dyn DataStore) -> Result<(), DataStoreError> {
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let mut start = 0;
+        loop {
+            let leafs = store
+                .get_log_leafs_starting_with_registry_index(start, POPULATE_PAGE_SIZE)
+                .await?;
+            let Some((last, _)) = leafs.last() else {
+                break;
+            };
+            start = last + 1;
+
+            for (registry_index, leaf) in &leafs {
+                if leaf.log_id == operator_log_id {
+                    continue;
+                }
+
+                let envelope = store
+                    .get_package_record(&leaf.log_id, &leaf.record_id)
+                    .await?
+                    .envelope;
+                let mut baselines = self.baselines.write().await;
+                let baseline = baselines.entry(leaf.log_id.clone()).or_default();
+                if !baseline
+                    .last_index()
+                    .is_some_and(|last| *registry_index <= last)
+                {
+                    baseline.add(*registry_index, &envelope);
+                }
+            }
+
+            if leafs.len() < POPULATE_PAGE_SIZE {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::{hash::HashAlgorithm, signing::generate_p256_pair};
+    use warg_protocol::package::{Permission, PACKAGE_RECORD_VERSION};
+
+    #[tokio::test]
+    async fn it_detects_a_release_burst_after_a_grant() {
+        let (alice_pub, alice_priv) = generate_p256_pair();
+        let (mallory_pub, _) = generate_p256_pair();
+        let content = HashAlgorithm::Sha256.digest(b"content");
+        let start = SystemTime::now();
+        let record = |minutes: u64, entries: Vec<PackageEntry>| {
+            ProtoEnvelope::signed_contents(
+                &alice_priv,
+                PackageRecord {
+                    prev: None,
+                    version: PACKAGE_RECORD_VERSION,
+                    timestamp: start + Duration::from_secs(minutes * 60),
+                    entries,
+                    entry_signatures: Vec::new(),
+                    publish_token: None,
+                },
+            )
+            .unwrap()
+        };
+        let release = |version: &str| PackageEntry::Release {
+            version: version.parse().unwrap(),
+            content,
+            encryption: None,
+            manifest: None,
+        };
+
+        let monitor = AnomalyMonitor::new().with_detector(GrantReleaseBurst::default());
+        let log_id = LogId::package_log::<Sha256>(&"test:package".parse().unwrap());
+        let records = [
+            record(
+                0,
+                vec![PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: alice_pub,
+                }],
+            ),
+            record(10, vec![release("1.0.0")]),
+            record(
+                120,
+                vec![PackageEntry::GrantFlat {
+                    key: mallory_pub,
+                    permissions: vec![Permission::Release],
+                    proof: None,
+                }],
+            ),
+            record(121, vec![release("1.0.1"), release("1.0.2")]),
+            record(122, vec![release("1.0.3")]),
+            record(123, vec![release("1.0.4")]),
+        ];
+
+        let mut detected = Vec::new();
+        for (index, record) in records.iter().enumerate() {
+            detected.push(monitor.observe(&log_id, index, record).await.len());
+        }
+
+        // Only the record crossing the threshold is anomalous
+        assert_eq!(detected, [0, 0, 0, 0, 1, 0]);
+
+        // Records already observed are ignored
+        assert!(monitor.observe(&log_id, 4, &records[4]).await.is_empty());
+
+        let baseline = monitor.baseline(&log_id).await.unwrap();
+        assert_eq!(baseline.records(), 6);
+        assert_eq!(baseline.entries(EntryKind::Release), 5);
+        assert_eq!(baseline.entry_share(EntryKind::Grant), 1.0 / 7.0);
+        assert_eq!(
+            baseline.mean_interval(),
+            Some(Duration::from_secs(123 * 60 / 5))
+        );
+    }
+}
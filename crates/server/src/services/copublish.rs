@@ -0,0 +1,110 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+use warg_protocol::registry::LogLeaf;
+
+/// Holds the submitted parts of co-publications until every part has been
+/// submitted, so that all of the parts are sequenced together.
+///
+/// Co-publications are only tracked in memory; the parts of a
+/// co-publication that are held when the server restarts are not sequenced
+/// until they are submitted again.
+#[derive(Default)]
+pub(crate) struct CoPublicationTracker {
+    groups: Mutex<Groups>,
+}
+
+#[derive(Default)]
+struct Groups {
+    next_id: usize,
+    // The group of each part of a pending co-publication.
+    parts: HashMap<LogLeaf, usize>,
+    // The parts of each pending co-publication and which have been submitted.
+    groups: HashMap<usize, (Vec<LogLeaf>, HashSet<LogLeaf>)>,
+}
+
+impl CoPublicationTracker {
+    /// Registers a co-publication of the given parts.
+    ///
+    /// Registering the same co-publication again has no effect. Returns
+    /// `false` if any part is already a part of a different co-publication.
+    pub(crate) fn register(&self, leaves: Vec<LogLeaf>) -> bool {
+        let mut groups = self.groups.lock().unwrap();
+        let existing: HashSet<_> = leaves
+            .iter()
+            .filter_map(|leaf| groups.parts.get(leaf).copied())
+            .collect();
+        match existing.into_iter().collect::<Vec<_>>()[..] {
+            [] => {}
+            [id] => return groups.groups[&id].0 == leaves,
+            _ => return false,
+        }
+
+        let id = groups.next_id;
+        groups.next_id += 1;
+        for leaf in &leaves {
+            groups.parts.insert(leaf.clone(), id);
+        }
+        groups.groups.insert(id, (leaves, HashSet::new()));
+        true
+    }
+
+    /// Submits a record, returning the records now ready to be sequenced.
+    ///
+    /// A record that is not part of a co-publication is ready immediately;
+    /// the parts of a co-publication are all ready once the last is
+    /// submitted.
+    pub(crate) fn submit(&self, leaf: LogLeaf) -> Vec<LogLeaf> {
+        let mut groups = self.groups.lock().unwrap();
+        let Some(id) = groups.parts.get(&leaf).copied() else {
+            return vec![leaf];
+        };
+
+        let (leaves, submitted) = groups.groups.get_mut(&id).unwrap();
+        submitted.insert(leaf);
+        if submitted.len() < leaves.len() {
+            return Vec::new();
+        }
+
+        let (leaves, _) = groups.groups.remove(&id).unwrap();
+        for leaf in &leaves {
+            groups.parts.remove(leaf);
+        }
+        leaves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warg_crypto::hash::{AnyHash, Hash, Sha256};
+    use warg_protocol::registry::LogId;
+
+    fn leaf(package: &str) -> LogLeaf {
+        LogLeaf {
+            log_id: LogId::package_log::<Sha256>(&package.parse().unwrap()),
+            record_id: AnyHash::from(Hash::<Sha256>::of(package)).into(),
+        }
+    }
+
+    #[test]
+    fn holds_parts_until_all_are_submitted() {
+        let tracker = CoPublicationTracker::default();
+        let (foo, bar, baz) = (leaf("test:foo"), leaf("test:bar"), leaf("test:baz"));
+        assert!(tracker.register(vec![foo.clone(), bar.clone()]));
+        assert!(tracker.register(vec![foo.clone(), bar.clone()]));
+        assert!(!tracker.register(vec![bar.clone(), baz.clone()]));
+
+        // Records outside of a co-publication are not held
+        assert_eq!(tracker.submit(baz.clone()), std::slice::from_ref(&baz));
+
+        assert!(tracker.submit(bar.clone()).is_empty());
+        assert!(tracker.submit(bar.clone()).is_empty());
+        assert_eq!(tracker.submit(foo.clone()), [foo.clone(), bar.clone()]);
+
+        // The parts are forgotten once released
+        assert_eq!(tracker.submit(foo.clone()), [foo]);
+        assert!(tracker.register(vec![bar, baz]));
+    }
+}
@@ -1,88 +1,209 @@
 use std::{
+    collections::HashMap,
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
 use futures::{pin_mut, StreamExt};
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use thiserror::Error;
 use tokio::{
-    sync::{mpsc, RwLock},
+    sync::{mpsc, oneshot, RwLock},
     task::JoinHandle,
     time::MissedTickBehavior,
 };
 use warg_crypto::{
     hash::{AnyHash, Hash, Sha256, SupportedDigest},
-    signing::PrivateKey,
+    signing::{KeyID, PrivateKey, PublicKey, SignatureError},
 };
 use warg_protocol::{
-    operator,
+    filter::{InclusionFilter, PackageFilter},
+    operator, package,
     registry::{
-        Checkpoint, LogId, LogLeaf, MapLeaf, RecordId, RegistryIndex, RegistryLen,
+        canonical_order, Checkpoint, FreshnessAssertion, LogId, LogLeaf, MapKey, MapLeaf,
+        PackageName, RecordId, RecordReceipt, RegistryIndex, RegistryLen, SubmittedRecord,
         TimestampedCheckpoint,
     },
-    ProtoEnvelope, SerdeEnvelope,
+    ProtoEnvelope, SerdeEnvelope, Version,
 };
 use warg_transparency::{
     log::{LogBuilder, LogData, LogProofBundle, Node, VecLog},
-    map::{Map, MapProofBundle},
+    map::{Map, MapProofBundle, Proof},
 };
 
-use crate::datastore::{DataStore, DataStoreError};
+use super::{
+    keys::record_signers,
+    proof_cache::{ProofCache, ProofCacheStats},
+    AnomalyMonitor, CoPublicationTracker, LeaseError, SequencerLease,
+};
+use crate::{
+    datastore::{DataStore, DataStoreError},
+    events::{Event, EventBus},
+    policy::staging::StagingPolicy,
+};
+
+/// The number of log leafs loaded at a time when computing a checkpoint delta.
+const DELTA_LEAFS_BATCH_SIZE: usize = 1000;
+
+/// The false positive rate of the published package filter.
+const PACKAGE_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
 
 #[derive(Clone)]
 pub struct CoreService<Digest: SupportedDigest = Sha256> {
     inner: Arc<Inner<Digest>>,
 
-    // Channel sender used by `submit_package_record` to serialize submissions.
-    submit_entry_tx: mpsc::Sender<LogLeaf>,
+    // Channel sender used by `submit_package_record` and `flush_now` to
+    // serialize submissions.
+    update_tx: mpsc::Sender<StateUpdate>,
 }
 
 impl<Digest: SupportedDigest> CoreService<Digest> {
     /// Starts the `CoreService`, returning a `clone`able handle to the
     /// service and a [`JoinHandle`] which should be awaited after dropping all
     /// copies of the service handle to allow for graceful shutdown.
+    ///
+    /// Submitted records are sequenced into a new checkpoint every
+    /// `checkpoint_interval`, or as soon as `checkpoint_batch_size` records
+    /// are waiting, whichever comes first.
+    ///
+    /// If a sequencer lease is given, it is acquired before any state is
+    /// loaded and renewed before each checkpoint is signed; once it is lost,
+    /// the service stops sequencing records.
+    #[allow(clippy::too_many_arguments)]
     pub async fn start(
         operator_key: PrivateKey,
         namespaces: Option<Vec<(String, operator::NamespaceState)>>,
         store: Box<dyn DataStore>,
         checkpoint_interval: Duration,
+        checkpoint_batch_size: Option<usize>,
+        freshness_interval: Duration,
+        events: EventBus,
+        proof_cache_capacity: usize,
+        lease: Option<Arc<dyn SequencerLease>>,
     ) -> Result<(Self, JoinHandle<()>), CoreServiceError> {
+        // Only one sequencer may sign checkpoints for the registry
+        if let Some(lease) = &lease {
+            lease.acquire().await?;
+        }
+
         // Build service
         let mut inner = Inner {
-            operator_key,
-            store,
+            operator_key: std::sync::RwLock::new(operator_key),
+            store: store.into(),
+            lease,
+            events,
             state: Default::default(),
+            map_proofs: ProofCache::new(proof_cache_capacity),
+            consistency_proofs: ProofCache::new(proof_cache_capacity),
+            freshness: Default::default(),
+            packages: Default::default(),
+            filter: Default::default(),
+            anomalies: Default::default(),
+            staging: Default::default(),
+            rules: Default::default(),
+            copublications: Default::default(),
+            #[cfg(feature = "fault-injection")]
+            faults: Default::default(),
         };
         inner.initialize(namespaces).await?;
 
+        let checkpoint = inner
+            .store
+            .get_latest_checkpoint()
+            .await?
+            .into_contents()
+            .checkpoint;
+        inner
+            .sign_freshness(checkpoint.clone())
+            .await
+            .map_err(|e| CoreServiceError::InitializationFailure(e.to_string()))?;
+        inner
+            .sign_filter(checkpoint)
+            .await
+            .map_err(|e| CoreServiceError::InitializationFailure(e.to_string()))?;
+
         // Spawn state update task
         let inner = Arc::new(inner);
-        let (submit_entry_tx, submit_entry_rx) = tokio::sync::mpsc::channel(4);
-        let handle = tokio::spawn(
-            inner
-                .clone()
-                .process_state_updates(submit_entry_rx, checkpoint_interval),
-        );
+        let (update_tx, update_rx) = tokio::sync::mpsc::channel(4);
+        let handle = tokio::spawn(inner.clone().process_state_updates(
+            update_rx,
+            checkpoint_interval,
+            checkpoint_batch_size,
+            freshness_interval,
+        ));
 
-        let svc = Self {
-            inner,
-            submit_entry_tx,
-        };
+        let svc = Self { inner, update_tx };
         Ok((svc, handle))
     }
 
+    /// Inspects sequenced package records with the given anomaly monitor,
+    /// publishing an event for every anomaly detected.
+    ///
+    /// The monitor's baselines are populated from the records already in the
+    /// data store. A monitor can only be set once; subsequent calls are
+    /// ignored.
+    pub async fn monitor_anomalies(&self, monitor: AnomalyMonitor) -> Result<(), DataStoreError> {
+        if self.inner.anomalies.set(monitor.clone()).is_err() {
+            return Ok(());
+        }
+
+        monitor.populate(self.inner.store.as_ref()).await
+    }
+
+    /// Enforces the given staging policy when sequencing package records.
+    ///
+    /// Records the policy requires to be countersigned by the registry
+    /// operator are rejected if they are not. A staging policy can only be
+    /// set once; subsequent calls are ignored.
+    pub fn enforce_staging(&self, policy: Arc<StagingPolicy>) {
+        let _ = self.inner.staging.set(policy);
+    }
+
+    /// Enforces the given validation rules when sequencing package records.
+    ///
+    /// Clients and auditors replaying the registry's package logs are
+    /// expected to validate them under the same rules. Rules can only be
+    /// set once; subsequent calls are ignored.
+    pub fn enforce_rules(&self, rules: package::ValidationRules) {
+        let _ = self.inner.rules.set(rules);
+    }
+
+    /// Injects the given faults into the service.
+    ///
+    /// Faults can only be injected once; subsequent calls are ignored.
+    #[cfg(feature = "fault-injection")]
+    pub fn inject_faults(&self, faults: crate::faults::Faults) {
+        let _ = self.inner.faults.set(faults);
+    }
+
     /// Constructs a log consistency proof between the given log tree roots.
     pub async fn log_consistency_proof(
         &self,
         from_log_length: RegistryLen,
         to_log_length: RegistryLen,
     ) -> Result<LogProofBundle<Digest, LogLeaf>, CoreServiceError> {
+        if let Some(bundle) = self
+            .inner
+            .consistency_proofs
+            .get(to_log_length, from_log_length)
+        {
+            return Ok(bundle);
+        }
+
         let state = self.inner.state.read().await;
 
         let proof = state.log.prove_consistency(from_log_length, to_log_length);
-        LogProofBundle::bundle(vec![proof], vec![], &state.log)
-            .map_err(CoreServiceError::BundleFailure)
+        let bundle = LogProofBundle::bundle(vec![proof], vec![], &state.log)
+            .map_err(CoreServiceError::BundleFailure)?;
+
+        // Only proofs between lengths the log has reached are final
+        if to_log_length <= state.log.length() {
+            self.inner
+                .consistency_proofs
+                .insert(to_log_length, from_log_length, bundle.clone());
+        }
+
+        Ok(bundle)
     }
 
     /// Constructs log inclusion proofs for the given entries at the given log tree root.
@@ -105,6 +226,24 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
             })
             .collect::<Result<Vec<_>, CoreServiceError>>()?;
 
+        #[cfg(feature = "fault-injection")]
+        if self.inner.faults.get().is_some_and(|f| f.corrupt_proof()) {
+            // Prove inclusion in a log with a bogus leaf appended, which
+            // evaluates to a root other than that of the requested checkpoint
+            tracing::warn!("corrupting log inclusion proofs at log length {log_length}");
+            let mut log = state.log.clone();
+            log.push(&LogLeaf {
+                log_id: LogId::operator_log::<Digest>(),
+                record_id: AnyHash::from(Hash::<Digest>::of("fault")).into(),
+            });
+            let proofs = entries
+                .iter()
+                .map(|&index| log.prove_inclusion(state.leaf_index[index], log_length + 1))
+                .collect();
+            return LogProofBundle::bundle(vec![], proofs, &log)
+                .map_err(CoreServiceError::BundleFailure);
+        }
+
         LogProofBundle::bundle(vec![], proofs, &state.log).map_err(CoreServiceError::BundleFailure)
     }
 
@@ -113,7 +252,7 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
         &self,
         log_length: RegistryLen,
         entries: &[RegistryIndex],
-    ) -> Result<MapProofBundle<Digest, LogId, MapLeaf>, CoreServiceError> {
+    ) -> Result<MapProofBundle<Digest, MapKey, MapLeaf>, CoreServiceError> {
         let state = self.inner.state.read().await;
 
         let (map_root, map) = state
@@ -121,26 +260,41 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
             .get(&log_length)
             .ok_or_else(|| CoreServiceError::CheckpointNotFound(log_length))?;
 
-        let indexes = self
-            .inner
-            .store
-            .get_log_leafs_with_registry_index(entries)
-            .await
-            .map_err(CoreServiceError::DataStore)?;
+        let mut proofs = entries
+            .iter()
+            .map(|&index| self.inner.map_proofs.get(log_length, index))
+            .collect::<Vec<_>>();
 
-        let proofs = indexes
+        let missing = entries
             .iter()
-            .map(|log_leaf| {
+            .zip(&proofs)
+            .filter_map(|(&index, proof)| proof.is_none().then_some(index))
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            let leafs = self
+                .inner
+                .store
+                .get_log_leafs_with_registry_index(&missing)
+                .await
+                .map_err(CoreServiceError::DataStore)?;
+
+            let mut generated = missing.into_iter().zip(leafs.iter());
+            for proof in proofs.iter_mut().filter(|proof| proof.is_none()) {
+                let Some((index, log_leaf)) = generated.next() else {
+                    break;
+                };
                 let LogLeaf { log_id, record_id } = log_leaf;
+                let key = MapKey::from(log_id.clone());
 
-                let proof = map
-                    .prove(log_id.clone())
+                let generated = map
+                    .prove(key.clone())
                     .ok_or_else(|| CoreServiceError::PackageNotIncluded(log_id.clone()))?;
 
                 let map_leaf = MapLeaf {
                     record_id: record_id.clone(),
                 };
-                let found_root = proof.evaluate(log_id, &map_leaf);
+                let found_root = generated.evaluate(&key, &map_leaf);
                 if &found_root != map_root {
                     return Err(CoreServiceError::IncorrectProof {
                         root: map_root.into(),
@@ -148,11 +302,219 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
                     });
                 }
 
-                Ok(proof)
+                self.inner
+                    .map_proofs
+                    .insert(log_length, index, generated.clone());
+                *proof = Some(generated);
+            }
+        }
+
+        Ok(MapProofBundle::bundle(
+            proofs.into_iter().flatten().collect(),
+        ))
+    }
+
+    /// Gets the map entries that changed between the given checkpoints, with
+    /// map inclusion proofs of their values at the later checkpoint.
+    ///
+    /// The changes are in registry log order of the last change to each entry.
+    pub async fn map_delta(
+        &self,
+        from_log_length: RegistryLen,
+        to_log_length: RegistryLen,
+    ) -> Result<(Vec<LogLeaf>, MapProofBundle<Digest, MapKey, MapLeaf>), CoreServiceError> {
+        if from_log_length > to_log_length {
+            return Err(CoreServiceError::InvalidCheckpointRange {
+                from: from_log_length,
+                to: to_log_length,
+            });
+        }
+
+        {
+            let state = self.inner.state.read().await;
+            for log_length in [from_log_length, to_log_length] {
+                if log_length > 0 && !state.map_index.contains_key(&log_length) {
+                    return Err(CoreServiceError::CheckpointNotFound(log_length));
+                }
+            }
+        }
+
+        // Later changes to an entry replace earlier ones
+        let mut changes = IndexMap::new();
+        let mut index = from_log_length as RegistryIndex;
+        while index < to_log_length as RegistryIndex {
+            let limit = DELTA_LEAFS_BATCH_SIZE.min(to_log_length - index);
+            let leafs = self
+                .inner
+                .store
+                .get_log_leafs_starting_with_registry_index(index, limit)
+                .await?;
+            if leafs.is_empty() {
+                return Err(CoreServiceError::LeafNotFound(index));
+            }
+
+            for (leaf_index, LogLeaf { log_id, record_id }) in leafs {
+                changes.shift_remove(&log_id);
+                changes.insert(log_id, record_id);
+                index = leaf_index + 1;
+            }
+        }
+
+        let state = self.inner.state.read().await;
+        let (_, map) = state
+            .map_index
+            .get(&to_log_length)
+            .ok_or_else(|| CoreServiceError::CheckpointNotFound(to_log_length))?;
+
+        let proofs = changes
+            .keys()
+            .map(|log_id| {
+                map.prove(log_id.clone().into())
+                    .ok_or_else(|| CoreServiceError::PackageNotIncluded(log_id.clone()))
             })
-            .collect::<Result<Vec<_>, CoreServiceError>>()?;
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            changes
+                .into_iter()
+                .map(|(log_id, record_id)| LogLeaf { log_id, record_id })
+                .collect(),
+            MapProofBundle::bundle(proofs),
+        ))
+    }
+
+    /// Gets the changes to the registry between the given checkpoints.
+    ///
+    /// The changes are found by diffing the verifiable maps of the two
+    /// checkpoints, so the cost is proportional to the number of changed logs
+    /// rather than the number of records between the checkpoints.
+    pub async fn changes_between(
+        &self,
+        from_log_length: RegistryLen,
+        to_log_length: RegistryLen,
+    ) -> Result<RegistryChanges, CoreServiceError> {
+        if from_log_length > to_log_length {
+            return Err(CoreServiceError::InvalidCheckpointRange {
+                from: from_log_length,
+                to: to_log_length,
+            });
+        }
+
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        let mut policy_changed = false;
+        {
+            let state = self.inner.state.read().await;
+            let empty = VerifiableMap::default();
+            let map_at = |log_length| {
+                if log_length == 0 {
+                    return Ok(&empty);
+                }
+
+                state
+                    .map_index
+                    .get(&log_length)
+                    .map(|(_, map)| map)
+                    .ok_or(CoreServiceError::CheckpointNotFound(log_length))
+            };
+
+            let operator_log_id = LogId::operator_log::<Digest>();
+            for change in map_at(from_log_length)?.diff(map_at(to_log_length)?) {
+                let Some(log_id) = state.map_keys.get(&change.key) else {
+                    continue;
+                };
+
+                if log_id == &operator_log_id {
+                    policy_changed = true;
+                } else if change.old.is_none() {
+                    added.push(log_id.clone());
+                } else {
+                    updated.push(log_id.clone());
+                }
+            }
+        }
+
+        let names = self
+            .inner
+            .store
+            .get_package_names(&[added.as_slice(), updated.as_slice()].concat())
+            .await?;
+        let names_of = |log_ids: Vec<LogId>| {
+            log_ids
+                .iter()
+                .filter_map(|log_id| names.get(log_id).cloned().flatten())
+                .collect()
+        };
+
+        Ok(RegistryChanges {
+            added: names_of(added),
+            updated: names_of(updated),
+            policy_changed,
+        })
+    }
+
+    /// Gets statistics about the usage of the map inclusion proof cache.
+    pub fn proof_cache_stats(&self) -> ProofCacheStats {
+        self.inner.map_proofs.stats()
+    }
 
-        Ok(MapProofBundle::bundle(proofs))
+    /// Gets statistics about the usage of the log consistency proof cache.
+    pub fn consistency_proof_cache_stats(&self) -> ProofCacheStats {
+        self.inner.consistency_proofs.stats()
+    }
+
+    /// Gets the latest signed freshness assertion.
+    ///
+    /// The assertion is re-signed on the freshness interval and whenever a
+    /// new checkpoint is created.
+    pub async fn freshness(&self) -> Option<SerdeEnvelope<FreshnessAssertion>> {
+        self.inner.freshness.read().await.clone()
+    }
+
+    /// Gets the latest signed package filter.
+    ///
+    /// The filter contains the names and released versions of all packages
+    /// as of the latest checkpoint and is re-signed whenever a new
+    /// checkpoint is created.
+    pub async fn package_filter(&self) -> Option<SerdeEnvelope<PackageFilter>> {
+        self.inner.filter.read().await.clone()
+    }
+
+    /// Signs a receipt for a record sequenced at the given registry index,
+    /// as of the latest checkpoint.
+    ///
+    /// Returns `None` if the record is not yet included in a checkpoint.
+    pub async fn record_receipt(
+        &self,
+        log_id: LogId,
+        record_id: RecordId,
+        registry_index: RegistryIndex,
+    ) -> Result<Option<SerdeEnvelope<RecordReceipt>>, CoreServiceError> {
+        let checkpoint = self
+            .store()
+            .get_latest_checkpoint()
+            .await?
+            .into_contents()
+            .checkpoint;
+        if registry_index >= checkpoint.log_length {
+            return Ok(None);
+        }
+
+        let receipt = RecordReceipt {
+            log_id,
+            record_id,
+            registry_index,
+            checkpoint,
+        };
+        Ok(Some(SerdeEnvelope::signed_contents(
+            &self.inner.operator_key(),
+            receipt,
+        )?))
+    }
+
+    /// Gets the public key of the registry operator.
+    pub fn operator_public_key(&self) -> PublicKey {
+        self.inner.operator_key().public_key()
     }
 
     /// Gets the data store associated with the transparency service.
@@ -160,27 +522,202 @@ impl<Digest: SupportedDigest> CoreService<Digest> {
         self.inner.store.as_ref()
     }
 
+    /// Gets a shared handle to the data store associated with the
+    /// transparency service, for use by background tasks.
+    pub fn shared_store(&self) -> Arc<dyn DataStore> {
+        self.inner.store.clone()
+    }
+
     /// Submits a package record to be processed.
     pub async fn submit_package_record(&self, log_id: LogId, record_id: RecordId) {
-        self.submit_entry_tx
-            .send(LogLeaf { log_id, record_id })
+        #[cfg(feature = "fault-injection")]
+        if self.inner.faults.get().is_some_and(|f| f.drop_publish()) {
+            tracing::warn!("dropping package record `{record_id}`");
+            return;
+        }
+
+        // The parts of a co-publication are submitted together so that they
+        // are sequenced into the same checkpoint
+        let leaves = self
+            .inner
+            .copublications
+            .submit(LogLeaf { log_id, record_id });
+        if leaves.is_empty() {
+            return;
+        }
+
+        self.update_tx
+            .send(StateUpdate::Submit(leaves))
             .await
             .unwrap()
     }
+
+    /// Registers a co-publication of the given package records.
+    ///
+    /// Once registered, a record of the co-publication is held when
+    /// submitted until every record of the co-publication is submitted.
+    /// Returns `false` if a record is already part of a different
+    /// co-publication.
+    pub fn register_copublication(&self, leaves: Vec<LogLeaf>) -> bool {
+        self.inner.copublications.register(leaves)
+    }
+
+    /// Sequences the records submitted so far into a new checkpoint without
+    /// waiting for the checkpoint interval.
+    ///
+    /// Returns the latest checkpoint, which is unchanged if there was nothing
+    /// to sequence, or an error if the service's sequencer lease was lost.
+    pub async fn flush_now(&self) -> Result<Checkpoint, CoreServiceError> {
+        let (tx, rx) = oneshot::channel();
+        self.update_tx.send(StateUpdate::Flush(tx)).await.unwrap();
+        rx.await.unwrap()
+    }
+
+    /// Declares the given keys compromised as of the current registry log length.
+    ///
+    /// Package records signed by the keys that are sequenced after the
+    /// declaration are rejected. Keys already declared compromised are ignored.
+    ///
+    /// Returns the id of the operator record declaring the keys, or `None`
+    /// if there were no keys to declare.
+    pub async fn deny_keys(
+        &self,
+        key_ids: impl IntoIterator<Item = KeyID>,
+    ) -> Result<Option<RecordId>, CoreServiceError> {
+        self.inner.deny_keys(key_ids).await
+    }
+
+    /// Appends an operator record signed by an operator key other than the
+    /// registry's own, such as an admin key issuing administrative commands.
+    ///
+    /// The record must build on the current head of the operator log. It is
+    /// validated before it is stored, so an invalid record leaves the
+    /// operator log unchanged.
+    pub async fn append_operator_record(
+        &self,
+        record: &ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<RecordId, CoreServiceError> {
+        self.inner.append_operator_record(record).await
+    }
+
+    /// Switches the key the service signs checkpoints, freshness assertions,
+    /// and package filters with.
+    ///
+    /// The key must already have permission to sign checkpoints in the
+    /// operator log.
+    pub async fn rotate_operator_key(&self, key: PrivateKey) -> Result<(), CoreServiceError> {
+        let key_id = key.public_key().fingerprint();
+        let state = self.inner.state.read().await;
+        if !state
+            .operator
+            .key_has_permission_to_sign_checkpoints(&key_id)
+        {
+            return Err(CoreServiceError::KeyUnauthorized(key_id));
+        }
+
+        *self.inner.operator_key.write().unwrap() = key;
+        Ok(())
+    }
+
+    /// Re-validates the records of a package log from its first record as of
+    /// the current registry log length.
+    ///
+    /// Returns the number of records validated.
+    pub async fn revalidate_package(&self, name: &PackageName) -> Result<usize, CoreServiceError> {
+        let log_id = LogId::package_log::<Digest>(name);
+        let (log_length, operator) = {
+            let state = self.inner.state.read().await;
+            (state.log.length() as RegistryLen, state.operator.clone())
+        };
+
+        let mut validator = package::LogState::new();
+        let mut since = None;
+        let mut count = 0;
+        loop {
+            let records = self
+                .inner
+                .store
+                .get_package_records(
+                    &log_id,
+                    log_length,
+                    since.as_ref(),
+                    DELTA_LEAFS_BATCH_SIZE as u16,
+                )
+                .await?;
+            let Some(last) = records.last() else {
+                break;
+            };
+            since = Some(RecordId::package_record::<Digest>(&last.envelope));
+
+            for record in &records {
+                operator
+                    .check_package_record(&record.envelope, record.registry_index)
+                    .map_err(DataStoreError::from)?;
+                validator = validator
+                    .validate(&record.envelope)
+                    .map_err(DataStoreError::from)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
 }
 
 struct Inner<Digest: SupportedDigest> {
-    // Operator signing key
-    operator_key: PrivateKey,
+    // Operator signing key; replaced when the checkpoint key is rotated.
+    operator_key: std::sync::RwLock<PrivateKey>,
 
     // DataStore persists transparency state.
-    store: Box<dyn DataStore>,
+    store: Arc<dyn DataStore>,
+
+    // The lease granting this service the right to sign checkpoints, if any.
+    lease: Option<Arc<dyn SequencerLease>>,
+
+    // EventBus notifies subscribers of registry activity.
+    events: EventBus,
 
     // In-memory transparency state.
     state: RwLock<State<Digest>>,
+
+    // Cache of generated map inclusion proofs, keyed by checkpoint log length and registry index.
+    map_proofs: ProofCache<RegistryIndex, Proof<Digest, MapKey, MapLeaf>>,
+
+    // Cache of log consistency proof bundles, keyed by the log lengths proven
+    // consistent. The log is append-only, so these are never invalidated.
+    consistency_proofs: ProofCache<RegistryLen, LogProofBundle<Digest, LogLeaf>>,
+
+    // The latest signed freshness assertion of the latest checkpoint.
+    freshness: RwLock<Option<SerdeEnvelope<FreshnessAssertion>>>,
+
+    // The released versions of each sequenced package, keyed by package name.
+    packages: RwLock<IndexMap<PackageName, Vec<Version>>>,
+
+    // The latest signed package filter of the latest checkpoint.
+    filter: RwLock<Option<SerdeEnvelope<PackageFilter>>>,
+
+    // The monitor inspecting sequenced package records for anomalies, if any.
+    anomalies: std::sync::OnceLock<AnomalyMonitor>,
+
+    // The staging policy determining which records must be countersigned, if any.
+    staging: std::sync::OnceLock<Arc<StagingPolicy>>,
+
+    // The rules package records are validated under beyond those of every package log.
+    rules: std::sync::OnceLock<package::ValidationRules>,
+
+    // The submitted parts of co-publications waiting for their other parts.
+    copublications: CoPublicationTracker,
+
+    // The faults to inject, if any.
+    #[cfg(feature = "fault-injection")]
+    faults: std::sync::OnceLock<crate::faults::Faults>,
 }
 
 impl<Digest: SupportedDigest> Inner<Digest> {
+    fn operator_key(&self) -> PrivateKey {
+        self.operator_key.read().unwrap().clone()
+    }
+
     // Load state from DataStore or initialize empty state, returning any
     // entries that are not yet part of a checkpoint.
     async fn initialize(
@@ -206,9 +743,23 @@ impl<Digest: SupportedDigest> Inner<Digest> {
             checkpoints_by_len.insert(checkpoint.log_length, checkpoint);
         }
 
+        let operator_log_id = LogId::operator_log::<Digest>();
         let state = self.state.get_mut();
+        let packages = self.packages.get_mut();
         while let Some(entry) = published.next().await {
-            state.push_entry(entry?);
+            let entry = entry?;
+            if entry.log_id == operator_log_id {
+                let record = self
+                    .store
+                    .get_operator_record(&entry.log_id, &entry.record_id)
+                    .await?;
+                state.validate_operator_record(&record.envelope)?;
+            } else if let Some((name, versions)) =
+                released_versions(self.store.as_ref(), &entry).await?
+            {
+                packages.entry(name).or_default().extend(versions);
+            }
+            state.push_entry(entry);
             if let Some(stored_checkpoint) =
                 checkpoints_by_len.get(&(state.log.length() as RegistryLen))
             {
@@ -225,12 +776,13 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         &mut self,
         namespaces: Option<Vec<(String, operator::NamespaceState)>>,
     ) -> Result<(), CoreServiceError> {
+        let operator_key = self.operator_key();
         let state = self.state.get_mut();
 
         // Construct operator init record
         let init = operator::OperatorEntry::Init {
             hash_algorithm: Digest::ALGORITHM,
-            key: self.operator_key.public_key(),
+            key: operator_key.public_key(),
         };
         let entries = if let Some(namespaces) = namespaces {
             let mut entries = Vec::with_capacity(1 + namespaces.len());
@@ -260,7 +812,7 @@ impl<Digest: SupportedDigest> Inner<Digest> {
             entries,
         };
         let signed_init_record =
-            ProtoEnvelope::signed_contents(&self.operator_key, init_record).unwrap();
+            ProtoEnvelope::signed_contents(&operator_key, init_record).unwrap();
         let log_id = LogId::operator_log::<Digest>();
         let record_id = RecordId::operator_record::<Digest>(&signed_init_record);
 
@@ -273,6 +825,7 @@ impl<Digest: SupportedDigest> Inner<Digest> {
             .await?;
 
         // Update state with init record
+        state.validate_operator_record(&signed_init_record)?;
         state.push_entry(LogLeaf { log_id, record_id });
 
         // "zero" checkpoint to be updated
@@ -281,16 +834,102 @@ impl<Digest: SupportedDigest> Inner<Digest> {
             log_length: 0,
             map_root: Hash::<Digest>::default().into(),
         };
-        self.update_checkpoint(&mut checkpoint).await;
+        self.update_checkpoint(&mut checkpoint).await?;
 
         Ok(())
     }
 
+    // Appends an operator record declaring the given keys compromised
+    async fn deny_keys(
+        &self,
+        key_ids: impl IntoIterator<Item = KeyID>,
+    ) -> Result<Option<RecordId>, CoreServiceError> {
+        let mut state = self.state.write().await;
+
+        let key_ids = key_ids
+            .into_iter()
+            .filter(|key_id| state.operator.key_denied_since(key_id).is_none())
+            .collect::<IndexSet<_>>();
+        if key_ids.is_empty() {
+            return Ok(None);
+        }
+
+        // Records sequenced after this one are signed after the declaration
+        let registry_index = state.log.length() as RegistryIndex;
+        let head = state.operator.head().clone();
+        let record = operator::OperatorRecord {
+            prev: head.as_ref().map(|head| head.digest.clone()),
+            version: operator::OPERATOR_RECORD_VERSION,
+            timestamp: head
+                .map(|head| head.timestamp.max(SystemTime::now()))
+                .unwrap_or_else(SystemTime::now),
+            entries: key_ids
+                .into_iter()
+                .map(|key_id| operator::OperatorEntry::DenyKey {
+                    key_id,
+                    log_length: registry_index,
+                })
+                .collect(),
+        };
+        let signed_record = ProtoEnvelope::signed_contents(&self.operator_key(), record).unwrap();
+        let log_id = LogId::operator_log::<Digest>();
+        let record_id = RecordId::operator_record::<Digest>(&signed_record);
+
+        self.store
+            .store_operator_record(&log_id, &record_id, &signed_record)
+            .await?;
+        self.store
+            .commit_operator_record(&log_id, &record_id, registry_index)
+            .await?;
+
+        state.validate_operator_record(&signed_record)?;
+        state.push_entry(LogLeaf {
+            log_id,
+            record_id: record_id.clone(),
+        });
+
+        Ok(Some(record_id))
+    }
+
+    // Appends an operator record signed by another operator key
+    async fn append_operator_record(
+        &self,
+        record: &ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<RecordId, CoreServiceError> {
+        let mut state = self.state.write().await;
+
+        // Validate against a copy so that a rejected record leaves the state untouched
+        let registry_index = state.log.length() as RegistryIndex;
+        let operator = operator::LogState::check_operator_record(record, registry_index)
+            .and_then(|_| state.operator.clone().validate(record))
+            .map_err(DataStoreError::from)?;
+
+        let log_id = LogId::operator_log::<Digest>();
+        let record_id = RecordId::operator_record::<Digest>(record);
+
+        self.store
+            .store_operator_record(&log_id, &record_id, record)
+            .await?;
+        self.store
+            .commit_operator_record(&log_id, &record_id, registry_index)
+            .await?;
+
+        state.operator = operator;
+        state.push_entry(LogLeaf {
+            log_id,
+            record_id: record_id.clone(),
+        });
+
+        Ok(record_id)
+    }
+
     // Runs the service's state update loop.
     async fn process_state_updates(
         self: Arc<Self>,
-        mut submit_entry_rx: mpsc::Receiver<LogLeaf>,
+        mut update_rx: mpsc::Receiver<StateUpdate>,
         checkpoint_interval: Duration,
+        checkpoint_batch_size: Option<usize>,
+        freshness_interval: Duration,
     ) {
         let mut checkpoint = self
             .store
@@ -303,29 +942,181 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         let mut checkpoint_interval = tokio::time::interval(checkpoint_interval);
         checkpoint_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
+        // The freshness assertion was signed on start, so skip the immediate first tick
+        let mut freshness_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + freshness_interval,
+            freshness_interval,
+        );
+        freshness_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // Submitted entries are buffered until the next checkpoint so that they can be
+        // sequenced in canonical order
+        let mut submitted = Vec::new();
+
+        // Once the sequencer lease is lost, nothing more is sequenced or signed
+        let mut lost: Option<Arc<LeaseError>> = None;
+        fn sequence(lost: &mut Option<Arc<LeaseError>>, result: Result<(), LeaseError>) {
+            if let Err(err) = result {
+                tracing::error!("Sequencer lease lost; no longer sequencing records: {err}");
+                *lost = Some(Arc::new(err));
+            }
+        }
+
         loop {
             tokio::select! {
-                entry = submit_entry_rx.recv() => match entry {
-                    Some(entry) => self.process_package_entry(&entry).await,
-                    None => break, // Channel closed
+                update = update_rx.recv() => match update {
+                    Some(StateUpdate::Submit(entries)) if lost.is_some() => {
+                        for entry in entries {
+                            tracing::error!("Not sequencing record `{record_id}` without the sequencer lease", record_id = entry.record_id);
+                        }
+                    }
+                    Some(StateUpdate::Submit(entries)) => {
+                        submitted.extend(entries);
+                        if checkpoint_batch_size.is_some_and(|size| submitted.len() >= size) {
+                            sequence(&mut lost, self.sequence_and_checkpoint(std::mem::take(&mut submitted), &mut checkpoint).await);
+                            checkpoint_interval.reset();
+                        }
+                    }
+                    Some(StateUpdate::Flush(tx)) => {
+                        if lost.is_none() {
+                            sequence(&mut lost, self.sequence_and_checkpoint(std::mem::take(&mut submitted), &mut checkpoint).await);
+                            checkpoint_interval.reset();
+                        }
+                        let _ = tx.send(match &lost {
+                            Some(err) => Err(CoreServiceError::LeaseLost(err.clone())),
+                            None => Ok(checkpoint.clone()),
+                        });
+                    }
+                    None => {
+                        // Channel closed; sequence what remains
+                        if lost.is_none() && !submitted.is_empty() {
+                            sequence(&mut lost, self.sequence_and_checkpoint(std::mem::take(&mut submitted), &mut checkpoint).await);
+                        }
+                        break;
+                    }
                 },
-                _ = checkpoint_interval.tick() => self.update_checkpoint(&mut checkpoint).await,
+                _ = checkpoint_interval.tick(), if lost.is_none() => {
+                    sequence(&mut lost, self.sequence_and_checkpoint(std::mem::take(&mut submitted), &mut checkpoint).await);
+                }
+                _ = freshness_interval.tick(), if lost.is_none() => {
+                    if let Err(err) = self.sign_freshness(checkpoint.clone()).await {
+                        tracing::error!("Error signing freshness of checkpoint {checkpoint:?}: {err:?}");
+                    }
+                }
+            }
+        }
+
+        // Let another sequencer take over right away
+        if let (Some(lease), None) = (&self.lease, &lost) {
+            if let Err(err) = lease.release().await {
+                tracing::error!("Error releasing sequencer lease: {err}");
+            }
+        }
+    }
+
+    // Sequences the given package entries and signs a checkpoint including
+    // them, provided the sequencer lease is still held.
+    async fn sequence_and_checkpoint(
+        &self,
+        entries: Vec<LogLeaf>,
+        checkpoint: &mut Checkpoint,
+    ) -> Result<(), LeaseError> {
+        self.renew_lease().await?;
+        self.sequence_package_entries(entries).await;
+        self.update_checkpoint(checkpoint).await
+    }
+
+    // Renews the sequencer lease, if any.
+    async fn renew_lease(&self) -> Result<(), LeaseError> {
+        match &self.lease {
+            Some(lease) => lease.renew().await,
+            None => Ok(()),
+        }
+    }
+
+    // Sequences the package entries submitted since the last checkpoint in canonical order
+    async fn sequence_package_entries(&self, entries: Vec<LogLeaf>) {
+        if entries.is_empty() {
+            return;
+        }
+
+        let mut submitted = Vec::with_capacity(entries.len());
+        for leaf in entries {
+            match self
+                .store
+                .get_package_record(&leaf.log_id, &leaf.record_id)
+                .await
+            {
+                Ok(record) => {
+                    let record = record.envelope.as_ref();
+                    submitted.push(SubmittedRecord {
+                        prev: record.prev.clone(),
+                        timestamp: record.timestamp,
+                        leaf,
+                    });
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "failed to load submitted package record `{record_id}`: {err}",
+                        record_id = leaf.record_id
+                    );
+                }
             }
         }
+
+        for record in canonical_order(submitted) {
+            self.process_package_entry(&record.leaf).await;
+        }
+    }
+
+    // Gets the countersignature policy of the given package log, if records are staged
+    async fn countersignature_policy(
+        &self,
+        log_id: &LogId,
+    ) -> Result<Option<package::CountersignaturePolicy>, DataStoreError> {
+        let Some(staging) = self.staging.get() else {
+            return Ok(None);
+        };
+
+        let name = self
+            .store
+            .get_package_names(std::slice::from_ref(log_id))
+            .await?
+            .swap_remove(log_id)
+            .flatten()
+            .ok_or_else(|| DataStoreError::LogNotFound(log_id.clone()))?;
+        Ok(Some(staging.countersignature_policy(
+            self.operator_key().public_key(),
+            &name,
+        )))
     }
 
     // Processes a submitted package entry
     async fn process_package_entry(&self, entry: &LogLeaf) {
         tracing::debug!("Processing entry {entry:?}");
 
-        let mut state = self.state.write().await;
         let LogLeaf { log_id, record_id } = entry;
+        let policy = match self.countersignature_policy(log_id).await {
+            Ok(policy) => policy,
+            Err(e) => {
+                tracing::error!("failed to get policy for package record `{record_id}`: {e}");
+                return;
+            }
+        };
+
+        let mut state = self.state.write().await;
 
         // Validate and commit the package entry to the store
         let registry_index = state.log.length() as RegistryIndex;
         let commit_res = self
             .store
-            .commit_package_record(log_id, record_id, registry_index)
+            .commit_package_record(
+                log_id,
+                record_id,
+                registry_index,
+                policy.as_ref(),
+                &self.rules.get().copied().unwrap_or_default(),
+            )
             .await;
 
         if let Err(err) = commit_res {
@@ -334,7 +1125,13 @@ impl<Digest: SupportedDigest> Inner<Digest> {
                 | DataStoreError::OperatorValidationFailed(_)
                 | DataStoreError::PackageValidationFailed(_) => {
                     // The record failed to validate and was rejected; do not include it in the next checkpoint
-                    tracing::debug!("record `{record_id}` rejected: {err:?}");
+                    match self.store.get_package_record(log_id, record_id).await {
+                        Ok(record) => tracing::debug!(
+                            "record `{record_id}` rejected: {err}\n{record}",
+                            record = record.envelope.as_ref()
+                        ),
+                        Err(_) => tracing::debug!("record `{record_id}` rejected: {err:?}"),
+                    }
                 }
                 e => {
                     // TODO: this should be made more robust with a proper reliable message
@@ -346,34 +1143,277 @@ impl<Digest: SupportedDigest> Inner<Digest> {
         }
 
         state.push_entry(entry.clone());
+        drop(state);
+
+        match released_versions(self.store.as_ref(), entry).await {
+            Ok(Some((name, versions))) => self
+                .packages
+                .write()
+                .await
+                .entry(name)
+                .or_default()
+                .extend(versions),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("failed to get released versions of record `{record_id}`: {e}")
+            }
+        }
+
+        self.publish_package_events(entry, registry_index).await;
     }
 
-    // Store a checkpoint including the given new entries
-    async fn update_checkpoint(&self, checkpoint: &mut Checkpoint) {
+    // Publishes the events for a sequenced package entry
+    async fn publish_package_events(&self, entry: &LogLeaf, registry_index: RegistryIndex) {
+        let anomalies = self.anomalies.get();
+        if !self.events.has_subscribers() && anomalies.is_none() {
+            return;
+        }
+
+        let LogLeaf { log_id, record_id } = entry;
+        let record = self.store.get_package_record(log_id, record_id).await;
+
+        let signers = match &record {
+            Ok(record) => record_signers(&record.envelope),
+            Err(_) => IndexSet::new(),
+        };
+
+        self.events.publish(Event::RecordSequenced {
+            log_id: log_id.clone(),
+            record_id: record_id.clone(),
+            registry_index,
+            signers: signers.into_iter().collect(),
+        });
+
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::error!("failed to get package record `{record_id}` for events: {e}");
+                return;
+            }
+        };
+
+        if let Some(monitor) = anomalies {
+            let anomalies = monitor
+                .observe(log_id, registry_index, &record.envelope)
+                .await;
+            if !anomalies.is_empty() {
+                let name = self.package_name(log_id).await;
+                for anomaly in anomalies {
+                    tracing::warn!(
+                        "detector `{detector}` flagged record `{record_id}` of log `{log_id}`: {description}",
+                        detector = anomaly.detector,
+                        description = anomaly.description,
+                    );
+                    self.events.publish(Event::AnomalyDetected {
+                        log_id: log_id.clone(),
+                        name: name.clone(),
+                        record_id: record_id.clone(),
+                        registry_index,
+                        detector: anomaly.detector,
+                        description: anomaly.description,
+                    });
+                }
+            }
+        }
+
+        let entries = &record.envelope.as_ref().entries;
+        if !entries.iter().any(|entry| {
+            matches!(
+                entry,
+                package::PackageEntry::Init { .. }
+                    | package::PackageEntry::Yank { .. }
+                    | package::PackageEntry::YankRange { .. }
+            )
+        }) {
+            return;
+        }
+
+        let name = self.package_name(log_id).await;
+
+        for entry in entries {
+            match entry {
+                package::PackageEntry::Init { .. } => {
+                    self.events.publish(Event::PackageInitialized {
+                        log_id: log_id.clone(),
+                        name: name.clone(),
+                        record_id: record_id.clone(),
+                    })
+                }
+                package::PackageEntry::Yank { version, .. } => {
+                    self.events.publish(Event::VersionYanked {
+                        log_id: log_id.clone(),
+                        name: name.clone(),
+                        record_id: record_id.clone(),
+                        version: version.clone(),
+                    })
+                }
+                package::PackageEntry::YankRange { range, .. } => {
+                    let releases = match self.store.get_package_releases(log_id).await {
+                        Ok(releases) => releases,
+                        Err(e) => {
+                            tracing::error!("failed to get releases of log `{log_id}`: {e}");
+                            continue;
+                        }
+                    };
+
+                    // A version is yanked only once, so a matching yank made
+                    // at the record's time was made by the record
+                    for release in releases {
+                        let package::ReleaseState::Yanked { timestamp, .. } = &release.state else {
+                            continue;
+                        };
+
+                        if range.matches(&release.version)
+                            && *timestamp == record.envelope.as_ref().timestamp
+                        {
+                            self.events.publish(Event::VersionYanked {
+                                log_id: log_id.clone(),
+                                name: name.clone(),
+                                record_id: record_id.clone(),
+                                version: release.version,
+                            })
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Gets the name of a package for events, if known
+    async fn package_name(&self, log_id: &LogId) -> Option<PackageName> {
+        match self
+            .store
+            .get_package_names(std::slice::from_ref(log_id))
+            .await
         {
+            Ok(mut names) => names.swap_remove(log_id).flatten(),
+            Err(e) => {
+                tracing::error!("failed to get package name for log `{log_id}`: {e}");
+                None
+            }
+        }
+    }
+
+    // Store a checkpoint including the given new entries
+    async fn update_checkpoint(&self, checkpoint: &mut Checkpoint) -> Result<(), LeaseError> {
+        #[cfg(feature = "fault-injection")]
+        if let Some(delay) = self.faults.get().and_then(|f| f.checkpoint_delay()) {
+            tracing::warn!("delaying checkpoint by {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+
+        let updated = {
             // Recalculate the checkpoint if necessary
             let mut state = self.state.write().await;
             if state.log.length() as RegistryLen != checkpoint.log_length {
                 *checkpoint = state.checkpoint();
                 tracing::debug!("Updating to checkpoint {checkpoint:?}");
+                true
+            } else {
+                false
             }
-        }
+        };
+
+        // Sequencing may have taken long enough for the lease to be lost
+        self.renew_lease().await?;
 
         if let Err(err) = self.sign_and_store_checkpoint(checkpoint.clone()).await {
             tracing::error!("Error storing checkpoint {checkpoint:?}: {err:?}");
+            return Ok(());
+        }
+
+        if updated {
+            if let Err(err) = self.sign_freshness(checkpoint.clone()).await {
+                tracing::error!("Error signing freshness of checkpoint {checkpoint:?}: {err:?}");
+            }
+
+            if let Err(err) = self.sign_filter(checkpoint.clone()).await {
+                tracing::error!(
+                    "Error signing package filter of checkpoint {checkpoint:?}: {err:?}"
+                );
+            }
+
+            self.map_proofs.invalidate(checkpoint.log_length);
+            tracing::debug!(
+                "proof cache hit rate: {rate:?}",
+                rate = self.map_proofs.stats().hit_rate()
+            );
+
+            self.events.publish(Event::CheckpointSigned {
+                checkpoint: checkpoint.clone(),
+            });
         }
+
+        Ok(())
     }
 
     async fn sign_and_store_checkpoint(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
         let checkpoint_id = Hash::<Digest>::of(&checkpoint).into();
         let timestamped = TimestampedCheckpoint::now(checkpoint.clone())?;
-        let signed = SerdeEnvelope::signed_contents(&self.operator_key, timestamped)?;
+        let signed = SerdeEnvelope::signed_contents(&self.operator_key(), timestamped)?;
         self.store.store_checkpoint(&checkpoint_id, signed).await?;
         Ok(())
     }
+
+    async fn sign_freshness(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        let assertion = FreshnessAssertion::now(checkpoint)?;
+        let signed = SerdeEnvelope::signed_contents(&self.operator_key(), assertion)?;
+        *self.freshness.write().await = Some(signed);
+        Ok(())
+    }
+
+    async fn sign_filter(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        let filter = {
+            let packages = self.packages.read().await;
+            InclusionFilter::from_packages(
+                packages
+                    .iter()
+                    .map(|(name, versions)| (name, versions.as_slice())),
+                PACKAGE_FILTER_FALSE_POSITIVE_RATE,
+            )
+        };
+        let filter = PackageFilter::now(checkpoint, filter)?;
+        let signed = SerdeEnvelope::signed_contents(&self.operator_key(), filter)?;
+        *self.filter.write().await = Some(signed);
+        Ok(())
+    }
+}
+
+// Gets the name and released versions of the package record of the given
+// log leaf, or `None` if the record neither initializes the package nor
+// releases a version.
+async fn released_versions(
+    store: &dyn DataStore,
+    leaf: &LogLeaf,
+) -> Result<Option<(PackageName, Vec<Version>)>, DataStoreError> {
+    let record = store
+        .get_package_record(&leaf.log_id, &leaf.record_id)
+        .await?;
+
+    let mut initialized = false;
+    let mut versions = Vec::new();
+    for entry in &record.envelope.as_ref().entries {
+        match entry {
+            package::PackageEntry::Init { .. } => initialized = true,
+            package::PackageEntry::Release { version, .. } => versions.push(version.clone()),
+            _ => {}
+        }
+    }
+
+    if !initialized && versions.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(store
+        .get_package_names(std::slice::from_ref(&leaf.log_id))
+        .await?
+        .swap_remove(&leaf.log_id)
+        .flatten()
+        .map(|name| (name, versions)))
 }
 
-type VerifiableMap<Digest> = Map<Digest, LogId, MapLeaf>;
+type VerifiableMap<Digest> = Map<Digest, MapKey, MapLeaf>;
 
 #[derive(Default)]
 struct State<Digest: SupportedDigest> {
@@ -382,10 +1422,15 @@ struct State<Digest: SupportedDigest> {
     // Index log tree nodes by registry log index of the record
     leaf_index: Vec<Node>,
 
-    // The verifiable map of package logs' latest entries (log_id -> record_id)
+    // The verifiable map of logs' latest entries (map key -> record_id)
     map: VerifiableMap<Digest>,
     // Index verifiable map snapshots by log length (at checkpoints only)
     map_index: IndexMap<RegistryLen, (Hash<Digest>, VerifiableMap<Digest>)>,
+    // The log id of each map key, keyed by the hash of the key
+    map_keys: HashMap<Hash<Digest>, LogId>,
+
+    // The validated state of the operator log
+    operator: operator::LogState,
 }
 
 impl<Digest: SupportedDigest> State<Digest> {
@@ -394,7 +1439,17 @@ impl<Digest: SupportedDigest> State<Digest> {
         self.leaf_index.push(node);
 
         let LogLeaf { log_id, record_id } = log_leaf;
-        self.map = self.map.insert(log_id, MapLeaf { record_id });
+        let key = MapKey::from(log_id.clone());
+        self.map_keys.entry(Hash::of(&key)).or_insert(log_id);
+        self.map = self.map.insert(key, MapLeaf { record_id });
+    }
+
+    fn validate_operator_record(
+        &mut self,
+        record: &ProtoEnvelope<operator::OperatorRecord>,
+    ) -> Result<(), DataStoreError> {
+        self.operator = std::mem::take(&mut self.operator).validate(record)?;
+        Ok(())
     }
 
     fn checkpoint(&mut self) -> Checkpoint {
@@ -416,6 +1471,25 @@ impl<Digest: SupportedDigest> State<Digest> {
     }
 }
 
+// An update to be processed by the service's state update loop.
+enum StateUpdate {
+    // Package records were submitted to be sequenced together.
+    Submit(Vec<LogLeaf>),
+    // Submitted records are to be sequenced immediately.
+    Flush(oneshot::Sender<Result<Checkpoint, CoreServiceError>>),
+}
+
+/// The changes to the registry between two checkpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryChanges {
+    /// The packages first published after the earlier checkpoint.
+    pub added: Vec<PackageName>,
+    /// The existing packages whose logs have new records.
+    pub updated: Vec<PackageName>,
+    /// Whether the operator log, and thus registry policy, changed.
+    pub policy_changed: bool,
+}
+
 #[derive(Debug, Error)]
 pub enum CoreServiceError {
     #[error("checkpoint at log length `{0}` was not found")]
@@ -432,4 +1506,14 @@ pub enum CoreServiceError {
     DataStore(#[from] DataStoreError),
     #[error("initialization failed: {0}")]
     InitializationFailure(String),
+    #[error("log length `{from}` is after log length `{to}`")]
+    InvalidCheckpointRange { from: RegistryLen, to: RegistryLen },
+    #[error("key id `{0}` does not have permission to sign checkpoints")]
+    KeyUnauthorized(KeyID),
+    #[error("failed to sign: {0}")]
+    SignatureFailure(#[from] SignatureError),
+    #[error("failed to acquire the sequencer lease: {0}")]
+    LeaseUnavailable(#[from] LeaseError),
+    #[error("sequencer lease lost: {0}")]
+    LeaseLost(Arc<LeaseError>),
 }
@@ -0,0 +1,275 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use indexmap::IndexSet;
+use tokio::{
+    sync::{broadcast::error::RecvError, RwLock},
+    task::JoinHandle,
+};
+use warg_crypto::{hash::Sha256, signing::KeyID};
+use warg_protocol::{
+    package,
+    registry::{LogId, LogLeaf, RegistryIndex},
+    ProtoEnvelope,
+};
+
+use crate::{
+    datastore::{DataStore, DataStoreError},
+    events::{Event, EventBus},
+};
+
+const POPULATE_PAGE_SIZE: usize = 1000;
+
+/// An index of the package records signed by each key.
+///
+/// A record is indexed under every key that signed its envelope or any of
+/// its entries, so that everything published with a key may be audited.
+///
+/// Cloning the index produces a handle to the same index.
+#[derive(Debug, Clone, Default)]
+pub struct KeyIndex {
+    keys: Arc<RwLock<HashMap<KeyID, BTreeMap<RegistryIndex, LogLeaf>>>>,
+}
+
+impl KeyIndex {
+    /// Adds a record signed by the given keys to the index.
+    pub async fn insert(
+        &self,
+        signers: impl IntoIterator<Item = KeyID>,
+        registry_index: RegistryIndex,
+        leaf: &LogLeaf,
+    ) {
+        let mut keys = self.keys.write().await;
+        for key_id in signers {
+            keys.entry(key_id)
+                .or_default()
+                .insert(registry_index, leaf.clone());
+        }
+    }
+
+    /// Gets the records signed by the given key, in registry order.
+    ///
+    /// Only records with a registry index greater than `since` are returned.
+    ///
+    /// Returns the records and whether there are more records to fetch.
+    pub async fn records(
+        &self,
+        key_id: &KeyID,
+        since: Option<RegistryIndex>,
+        limit: usize,
+    ) -> (Vec<(RegistryIndex, LogLeaf)>, bool) {
+        let keys = self.keys.read().await;
+        let Some(records) = keys.get(key_id) else {
+            return (Vec::new(), false);
+        };
+
+        let mut records = records
+            .range(since.map(|since| since + 1).unwrap_or_default()..)
+            .map(|(index, leaf)| (*index, leaf.clone()));
+        let page = records.by_ref().take(limit).collect();
+        (page, records.next().is_some())
+    }
+
+    /// Populates the index from the given data store and spawns a task that
+    /// keeps the index up to date as records are sequenced.
+    ///
+    /// If the task falls behind the event bus, the missed records are
+    /// indexed from the data store. The task completes when all handles to
+    /// the event bus are dropped.
+    pub async fn start(
+        &self,
+        store: Arc<dyn DataStore>,
+        events: &EventBus,
+    ) -> Result<JoinHandle<()>, DataStoreError> {
+        // Subscribe before populating so that no record is missed
+        let mut rx = events.subscribe();
+        let mut next = self.populate(store.as_ref(), 0).await?;
+
+        let index = self.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(Event::RecordSequenced {
+                        log_id,
+                        record_id,
+                        registry_index,
+                        signers,
+                    }) => {
+                        index
+                            .insert(signers, registry_index, &LogLeaf { log_id, record_id })
+                            .await;
+                        next = next.max(registry_index + 1);
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!("key index missed {count} event(s); resynchronizing");
+                        match index.populate(store.as_ref(), next).await {
+                            Ok(populated) => next = populated,
+                            Err(e) => tracing::error!("failed to resynchronize key index: {e}"),
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+
+    /// Indexes the records in the given data store starting at the given
+    /// registry index, returning the registry index following the last
+    /// record indexed.
+    async fn populate(
+        &self,
+        store: &dyn DataStore,
+        mut start: RegistryIndex,
+    ) -> Result<RegistryIndex, DataStoreError> {
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        loop {
+            let leafs = store
+                .get_log_leafs_starting_with_registry_index(start, POPULATE_PAGE_SIZE)
+                .await?;
+            let Some((last, _)) = leafs.last() else {
+                return Ok(start);
+            };
+            start = last + 1;
+
+            for (registry_index, leaf) in &leafs {
+                if leaf.log_id == operator_log_id {
+                    continue;
+                }
+
+                let envelope = store
+                    .get_package_record(&leaf.log_id, &leaf.record_id)
+                    .await?
+                    .envelope;
+                self.insert(record_signers(&envelope), *registry_index, leaf)
+                    .await;
+            }
+
+            if leafs.len() < POPULATE_PAGE_SIZE {
+                return Ok(start);
+            }
+        }
+    }
+}
+
+/// Gets the keys that signed the given package record: the envelope signer,
+/// the signers of its entries, and the issuer of its publish token.
+pub(crate) fn record_signers(envelope: &ProtoEnvelope<package::PackageRecord>) -> IndexSet<KeyID> {
+    let record = envelope.as_ref();
+    let mut signers = IndexSet::new();
+    signers.insert(envelope.key_id().clone());
+    signers.extend(record.entry_signatures.iter().map(|s| s.key_id.clone()));
+    signers.extend(record.publish_token.iter().map(|t| t.issuer.clone()));
+    signers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastore::MemoryDataStore;
+    use std::time::SystemTime;
+    use warg_crypto::{hash::HashAlgorithm, signing::PrivateKey};
+    use warg_protocol::{
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::{Checkpoint, PackageName, RecordId},
+    };
+
+    #[tokio::test]
+    async fn records_are_paged_in_registry_order() {
+        let index = KeyIndex::default();
+        let alice: KeyID = "sha256:alice".to_string().into();
+        let bob: KeyID = "sha256:bob".to_string().into();
+        let leaf = |i: u8| LogLeaf {
+            log_id: LogId::package_log::<Sha256>(&"test:package".parse().unwrap()),
+            record_id: HashAlgorithm::Sha256.digest(&[i]).into(),
+        };
+
+        index.insert([alice.clone()], 3, &leaf(3)).await;
+        index
+            .insert([alice.clone(), bob.clone()], 1, &leaf(1))
+            .await;
+        index.insert([alice.clone()], 5, &leaf(5)).await;
+
+        let indexes = |records: Vec<(RegistryIndex, LogLeaf)>| {
+            records.into_iter().map(|(i, _)| i).collect::<Vec<_>>()
+        };
+
+        let (records, more) = index.records(&alice, None, 2).await;
+        assert_eq!(indexes(records), [1, 3]);
+        assert!(more);
+
+        let (records, more) = index.records(&alice, Some(3), 2).await;
+        assert_eq!(indexes(records), [5]);
+        assert!(!more);
+
+        let (records, more) = index.records(&bob, None, 10).await;
+        assert_eq!(records, [(1, leaf(1))]);
+        assert!(!more);
+
+        let (records, more) = index
+            .records(&"sha256:eve".to_string().into(), None, 10)
+            .await;
+        assert!(records.is_empty());
+        assert!(!more);
+    }
+
+    #[tokio::test]
+    async fn resynchronizes_after_missing_events() {
+        let store = Arc::new(MemoryDataStore::default());
+        let events = EventBus::new(1);
+        let index = KeyIndex::default();
+        let handle = index.start(store.clone(), &events).await.unwrap();
+
+        // Sequence a record without publishing its event
+        let key = PrivateKey::decode(
+            "ecdsa-p256:I+UlDo0HxyBBFeelhPPWmD+LnklOpqZDkrFP5VduASk=".to_string(),
+        )
+        .unwrap();
+        let name: PackageName = "test:package".parse().unwrap();
+        let log_id = LogId::package_log::<Sha256>(&name);
+        let record = ProtoEnvelope::signed_contents(
+            &key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: key.public_key(),
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap();
+        let record_id = RecordId::package_record::<Sha256>(&record);
+        store
+            .store_package_record(&log_id, &name, &record_id, &record, &IndexSet::new())
+            .await
+            .unwrap();
+        store
+            .commit_package_record(&log_id, &record_id, 0, None, &Default::default())
+            .await
+            .unwrap();
+
+        // Overflow the event bus so that the index lags behind it
+        for log_length in 1..=3 {
+            events.publish(Event::CheckpointSigned {
+                checkpoint: Checkpoint {
+                    log_root: HashAlgorithm::Sha256.digest(b"log"),
+                    log_length,
+                    map_root: HashAlgorithm::Sha256.digest(b"map"),
+                },
+            });
+        }
+        drop(events);
+        handle.await.unwrap();
+
+        let (records, _) = index
+            .records(&key.public_key().fingerprint(), None, 10)
+            .await;
+        assert_eq!(records, [(0, LogLeaf { log_id, record_id })]);
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::ErrorKind,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+/// An error acquiring or renewing a [`SequencerLease`].
+#[derive(Debug, Error)]
+pub enum LeaseError {
+    #[error("sequencer lease is held by `{holder}`")]
+    Held { holder: String },
+    #[error("sequencer lease was lost to `{holder}`")]
+    Lost { holder: String },
+    #[error("sequencer lease expired")]
+    Expired,
+    #[error("sequencer lease was removed")]
+    Removed,
+    #[error("sequencer lease I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A lease granting a single sequencer the right to sign checkpoints for a
+/// registry.
+///
+/// The core service acquires the lease before loading any state, and renews
+/// it before sequencing each batch of records and before signing each
+/// checkpoint. If the lease cannot be renewed, the service stops sequencing
+/// rather than risk signing a checkpoint that forks the registry log.
+#[axum::async_trait]
+pub trait SequencerLease: Send + Sync {
+    /// Acquires the lease, failing if another sequencer holds it.
+    async fn acquire(&self) -> Result<(), LeaseError>;
+
+    /// Renews the lease, failing if it is no longer held by this sequencer.
+    async fn renew(&self) -> Result<(), LeaseError>;
+
+    /// Releases the lease so that another sequencer may acquire it.
+    async fn release(&self) -> Result<(), LeaseError>;
+}
+
+/// A [`SequencerLease`] backed by a lock file.
+///
+/// The file names the sequencer holding the lease and is rewritten on every
+/// renewal. A lease whose file has not been renewed within the lease's time
+/// to live is expired and may be taken over by another sequencer, so the time
+/// to live should comfortably exceed the checkpoint interval.
+///
+/// The lock file may be on a filesystem shared between the sequencers of a
+/// highly-available deployment. Should two sequencers take over an expired
+/// lease at once, the one whose takeover is overwritten sees the lease as
+/// lost on its next renewal, before signing anything.
+#[derive(Debug, Clone)]
+pub struct FileLease {
+    path: PathBuf,
+    holder: String,
+    ttl: Duration,
+}
+
+impl FileLease {
+    /// Creates a lease backed by the lock file at the given path, expiring
+    /// if not renewed within the given time to live.
+    pub fn new(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self::with_holder(
+            path,
+            ttl,
+            format!("{pid}-{nanos}", pid = std::process::id()),
+        )
+    }
+
+    /// Creates a lease backed by the lock file at the given path, identifying
+    /// this sequencer as the given holder.
+    ///
+    /// The holder must be unique among the sequencers sharing the lock file.
+    pub fn with_holder(path: impl Into<PathBuf>, ttl: Duration, holder: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            holder: holder.into(),
+            ttl,
+        }
+    }
+
+    /// Gets the holder identifying this sequencer.
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    // Reads the current holder of the lock file and whether its lease has
+    // expired, or `None` if there is no lock file.
+    async fn current(&self) -> Result<Option<(String, bool)>, LeaseError> {
+        let holder = match tokio::fs::read_to_string(&self.path).await {
+            Ok(holder) => holder,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let modified = tokio::fs::metadata(&self.path).await?.modified()?;
+        let expired = modified.elapsed().unwrap_or_default() > self.ttl;
+        Ok(Some((holder, expired)))
+    }
+
+    // Writes this sequencer as the holder of the lock file, replacing any
+    // existing file atomically.
+    async fn write(&self) -> Result<(), LeaseError> {
+        let tmp = self.path.with_extension(format!("{}.tmp", self.holder));
+        tokio::fs::write(&tmp, &self.holder).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[axum::async_trait]
+impl SequencerLease for FileLease {
+    async fn acquire(&self) -> Result<(), LeaseError> {
+        match self.current().await? {
+            Some((holder, false)) if holder != self.holder => {
+                return Err(LeaseError::Held { holder })
+            }
+            Some((holder, true)) if holder != self.holder => {
+                tracing::warn!("taking over expired sequencer lease from `{holder}`");
+            }
+            _ => {}
+        }
+
+        self.write().await?;
+
+        // Confirm that a concurrent takeover did not replace this one
+        self.renew().await
+    }
+
+    async fn renew(&self) -> Result<(), LeaseError> {
+        match self.current().await? {
+            Some((holder, _)) if holder != self.holder => Err(LeaseError::Lost { holder }),
+            Some((_, true)) => Err(LeaseError::Expired),
+            Some((_, false)) => self.write().await,
+            None => Err(LeaseError::Removed),
+        }
+    }
+
+    async fn release(&self) -> Result<(), LeaseError> {
+        match self.current().await? {
+            Some((holder, _)) if holder == self.holder => {
+                tokio::fs::remove_file(&self.path).await?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_lease_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sequencer.lock");
+        let first = FileLease::with_holder(&path, Duration::from_secs(60), "first");
+        let second = FileLease::with_holder(&path, Duration::from_secs(60), "second");
+
+        first.acquire().await.unwrap();
+        assert!(matches!(
+            second.acquire().await,
+            Err(LeaseError::Held { holder }) if holder == "first"
+        ));
+        assert!(matches!(
+            second.renew().await,
+            Err(LeaseError::Lost { holder }) if holder == "first"
+        ));
+        first.renew().await.unwrap();
+
+        // Releasing the lease lets another sequencer acquire it
+        second.release().await.unwrap();
+        first.renew().await.unwrap();
+        first.release().await.unwrap();
+        second.acquire().await.unwrap();
+        assert!(matches!(
+            first.renew().await,
+            Err(LeaseError::Lost { holder }) if holder == "second"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_lease_expires() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sequencer.lock");
+        let first = FileLease::with_holder(&path, Duration::from_millis(50), "first");
+        let second = FileLease::with_holder(&path, Duration::from_millis(50), "second");
+
+        first.acquire().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(matches!(first.renew().await, Err(LeaseError::Expired)));
+
+        // An expired lease may be taken over
+        second.acquire().await.unwrap();
+        assert!(matches!(
+            first.renew().await,
+            Err(LeaseError::Lost { holder }) if holder == "second"
+        ));
+    }
+}
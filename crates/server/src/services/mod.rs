@@ -1,3 +1,20 @@
+mod anomaly;
+mod copublish;
 mod core;
+mod keys;
+mod lease;
+mod proof_cache;
+mod quarantine;
+mod search;
 
-pub use self::core::{CoreService, CoreServiceError};
+pub use self::anomaly::{
+    Anomaly, AnomalyDetector, AnomalyMonitor, EntryKind, GrantReleaseBurst, PackageBaseline,
+    RecentRecord,
+};
+pub(crate) use self::copublish::CoPublicationTracker;
+pub use self::core::{CoreService, CoreServiceError, RegistryChanges};
+pub use self::keys::KeyIndex;
+pub use self::lease::{FileLease, LeaseError, SequencerLease};
+pub use self::proof_cache::ProofCacheStats;
+pub use self::quarantine::Quarantine;
+pub use self::search::SearchIndex;
@@ -0,0 +1,143 @@
+use std::{
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use warg_protocol::registry::RegistryLen;
+
+/// Statistics about the usage of a proof cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofCacheStats {
+    /// The number of proofs served from the cache.
+    pub hits: u64,
+    /// The number of proofs that had to be generated.
+    pub misses: u64,
+    /// The number of proofs currently in the cache.
+    pub entries: usize,
+}
+
+impl ProofCacheStats {
+    /// Gets the ratio of cache hits to total lookups.
+    ///
+    /// Returns `None` if no lookups have been performed.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+/// A least-recently-used cache of proofs keyed by checkpoint log length.
+pub(crate) struct ProofCache<K, V> {
+    capacity: usize,
+    entries: Mutex<IndexMap<(RegistryLen, K), V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K, V> ProofCache<K, V>
+where
+    K: Hash + Eq,
+    V: Clone,
+{
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    /// Gets a cached proof, marking it as most recently used.
+    pub(crate) fn get(&self, log_length: RegistryLen, key: K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_index_of(&(log_length, key)) {
+            Some(index) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let last = entries.len() - 1;
+                entries.move_index(index, last);
+                Some(entries[last].clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Inserts a proof, evicting the least recently used proof if the cache is full.
+    pub(crate) fn insert(&self, log_length: RegistryLen, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let key = (log_length, key);
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.shift_remove_index(0);
+        }
+
+        let (index, _) = entries.insert_full(key, value);
+        let last = entries.len() - 1;
+        entries.move_index(index, last);
+    }
+
+    /// Removes all proofs for checkpoints other than the given one.
+    pub(crate) fn invalidate(&self, latest: RegistryLen) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(log_length, _), _| *log_length == latest);
+    }
+
+    pub(crate) fn stats(&self) -> ProofCacheStats {
+        ProofCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ProofCache::new(2);
+        cache.insert(1, 0, "a");
+        cache.insert(1, 1, "b");
+
+        // Touch the first entry so the second is evicted next
+        assert_eq!(cache.get(1, 0), Some("a"));
+        cache.insert(1, 2, "c");
+
+        assert_eq!(cache.get(1, 1), None);
+        assert_eq!(cache.get(1, 0), Some("a"));
+        assert_eq!(cache.get(1, 2), Some("c"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.hit_rate(), Some(0.75));
+    }
+
+    #[test]
+    fn invalidates_older_checkpoints() {
+        let cache = ProofCache::new(4);
+        cache.insert(1, 0, "a");
+        cache.insert(2, 0, "b");
+        cache.invalidate(2);
+
+        assert_eq!(cache.get(1, 0), None);
+        assert_eq!(cache.get(2, 0), Some("b"));
+    }
+}
@@ -0,0 +1,187 @@
+use axum::http::HeaderMap;
+use indexmap::IndexMap;
+use std::{
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use warg_api::v1::admin::QuarantinedRecord;
+use warg_crypto::hash::Sha256;
+use warg_protocol::{
+    package::PackageRecord,
+    registry::{LogId, PackageName, RecordId},
+    ProtoEnvelope,
+};
+
+/// The value substituted for the value of a redacted header.
+const REDACTED: &str = "<redacted>";
+
+/// Header names containing any of these words may carry secrets.
+const SECRET_HEADER_WORDS: &[&str] = &[
+    "auth",
+    "cookie",
+    "credential",
+    "key",
+    "password",
+    "secret",
+    "session",
+    "token",
+];
+
+/// A bounded store of package records rejected on publish.
+///
+/// Rejected records are held so that operators can inspect why the records
+/// of a publisher are being rejected and requeue them once the cause is
+/// fixed. Only the most recently rejected records are held.
+///
+/// Cloning the quarantine produces a handle to the same quarantine.
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    capacity: usize,
+    next_id: u64,
+    records: IndexMap<u64, QuarantinedRecord>,
+}
+
+impl Quarantine {
+    /// Creates a new quarantine holding at most `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                capacity,
+                next_id: 1,
+                records: IndexMap::new(),
+            })),
+        }
+    }
+
+    /// Gets the quarantined records, oldest first.
+    pub fn records(&self) -> Vec<QuarantinedRecord> {
+        self.inner
+            .lock()
+            .unwrap()
+            .records
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Gets a quarantined record by its identifier.
+    pub fn get(&self, id: u64) -> Option<QuarantinedRecord> {
+        self.inner.lock().unwrap().records.get(&id).cloned()
+    }
+
+    /// Quarantines a rejected record, evicting the oldest record if the
+    /// quarantine is full.
+    ///
+    /// The values of request headers that may carry secrets are redacted.
+    pub(crate) fn insert(
+        &self,
+        log_id: &LogId,
+        package_name: &PackageName,
+        record: &ProtoEnvelope<PackageRecord>,
+        reason: String,
+        headers: &HeaderMap,
+    ) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        if inner.capacity == 0 {
+            return id;
+        }
+
+        while inner.records.len() >= inner.capacity {
+            inner.records.shift_remove_index(0);
+        }
+
+        inner.records.insert(
+            id,
+            QuarantinedRecord {
+                id,
+                log_id: log_id.clone(),
+                package_name: package_name.clone(),
+                record_id: RecordId::package_record::<Sha256>(record),
+                submitter: record.key_id().clone(),
+                reason,
+                timestamp: unix_timestamp(SystemTime::now()),
+                headers: redact_headers(headers),
+                record: record.clone().into(),
+            },
+        );
+        id
+    }
+
+    /// Records that a quarantined record was rejected again.
+    pub(crate) fn reject(&self, id: u64, reason: String) {
+        if let Some(record) = self.inner.lock().unwrap().records.get_mut(&id) {
+            record.reason = reason;
+            record.timestamp = unix_timestamp(SystemTime::now());
+        }
+    }
+
+    /// Removes a record from the quarantine.
+    pub(crate) fn remove(&self, id: u64) -> Option<QuarantinedRecord> {
+        self.inner.lock().unwrap().records.shift_remove(&id)
+    }
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Converts request headers to strings, redacting the values of headers
+/// that may carry secrets.
+///
+/// Repeated headers are joined with commas.
+fn redact_headers(headers: &HeaderMap) -> IndexMap<String, String> {
+    let mut redacted = IndexMap::<String, String>::new();
+    for (name, value) in headers {
+        let name = name.as_str();
+        let value = if SECRET_HEADER_WORDS.iter().any(|word| name.contains(word)) {
+            REDACTED.into()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+
+        match redacted.get_mut(name) {
+            Some(existing) if value != REDACTED => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            Some(_) => {}
+            None => {
+                redacted.insert(name.to_string(), value);
+            }
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn it_redacts_secret_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("cookie", HeaderValue::from_static("session=secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.insert("user-agent", HeaderValue::from_static("warg"));
+        headers.append("accept", HeaderValue::from_static("application/json"));
+        headers.append("accept", HeaderValue::from_static("text/plain"));
+
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["authorization"], REDACTED);
+        assert_eq!(redacted["cookie"], REDACTED);
+        assert_eq!(redacted["x-api-key"], REDACTED);
+        assert_eq!(redacted["user-agent"], "warg");
+        assert_eq!(redacted["accept"], "application/json, text/plain");
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use tokio::{
+    sync::{broadcast::error::RecvError, RwLock},
+    task::JoinHandle,
+};
+use warg_crypto::hash::Sha256;
+use warg_protocol::registry::{LogId, PackageName};
+
+use crate::{
+    datastore::{DataStore, DataStoreError},
+    events::{Event, EventBus},
+};
+
+/// An inverted index for searching packages by name.
+///
+/// Package names are split into lowercase words on namespace and kebab-case
+/// boundaries; a query matches a package when every query term is a word, or
+/// the prefix of a word, of the package name.
+///
+/// Cloning the index produces a handle to the same index.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    words: Arc<RwLock<BTreeMap<String, BTreeSet<PackageName>>>>,
+}
+
+fn tokenize(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| c == ':' || c == '-' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl SearchIndex {
+    /// Adds a package to the index.
+    pub async fn insert(&self, name: &PackageName) {
+        let mut words = self.words.write().await;
+        for word in tokenize(name.as_ref()) {
+            words.entry(word).or_default().insert(name.clone());
+        }
+    }
+
+    /// Searches the index for packages matching the given query.
+    ///
+    /// Results are sorted by package name.
+    pub async fn search(&self, query: &str, limit: usize) -> Vec<PackageName> {
+        let words = self.words.read().await;
+
+        let mut matches: Option<BTreeSet<PackageName>> = None;
+        for term in tokenize(query) {
+            let found: BTreeSet<PackageName> = words
+                .range(term.clone()..)
+                .take_while(|(word, _)| word.starts_with(&term))
+                .flat_map(|(_, names)| names.iter().cloned())
+                .collect();
+
+            matches = Some(match matches {
+                Some(matches) => matches.intersection(&found).cloned().collect(),
+                None => found,
+            });
+        }
+
+        matches
+            .unwrap_or_default()
+            .into_iter()
+            .take(limit)
+            .collect()
+    }
+
+    /// Populates the index from the given data store and spawns a task that
+    /// keeps the index up to date as packages are initialized.
+    ///
+    /// The task completes when all handles to the event bus are dropped.
+    pub async fn start(
+        &self,
+        store: &dyn DataStore,
+        events: &EventBus,
+    ) -> Result<JoinHandle<()>, DataStoreError> {
+        // Subscribe before populating so that no package is missed
+        let mut rx = events.subscribe();
+
+        let operator_log_id = LogId::operator_log::<Sha256>();
+        let mut log_ids = HashSet::new();
+        let mut records = store.get_all_validated_records().await?;
+        while let Some(leaf) = records.next().await {
+            let leaf = leaf?;
+            if leaf.log_id != operator_log_id {
+                log_ids.insert(leaf.log_id);
+            }
+        }
+
+        let log_ids = log_ids.into_iter().collect::<Vec<_>>();
+        for name in store
+            .get_package_names(&log_ids)
+            .await?
+            .into_values()
+            .flatten()
+        {
+            self.insert(&name).await;
+        }
+
+        let index = self.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(Event::PackageInitialized {
+                        name: Some(name), ..
+                    }) => index.insert(&name).await,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!("search index missed {count} event(s)");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn search_matches_word_prefixes() {
+        let index = SearchIndex::default();
+        for name in [
+            "wasi:http",
+            "wasi:http-types",
+            "example:http-client",
+            "wasi:io",
+        ] {
+            index.insert(&name.parse().unwrap()).await;
+        }
+
+        let names = |results: Vec<PackageName>| {
+            results
+                .into_iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(index.search("http", 10).await),
+            ["example:http-client", "wasi:http", "wasi:http-types"]
+        );
+        assert_eq!(
+            names(index.search("WASI ht", 10).await),
+            ["wasi:http", "wasi:http-types"]
+        );
+        assert_eq!(
+            names(index.search("wasi:http-ty", 10).await),
+            ["wasi:http-types"]
+        );
+        assert_eq!(
+            names(index.search("http", 1).await),
+            ["example:http-client"]
+        );
+        assert!(index.search("missing", 10).await.is_empty());
+        assert!(index.search("", 10).await.is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! Signing of Warg registry records with keys held by AWS Key Management
+//! Service (KMS).
+//!
+//! A [`KmsSigner`] implements [`AsyncSigner`] by asking KMS to sign the
+//! SHA-256 digest of each message, so the private key never leaves KMS.
+//! Asymmetric KMS keys with the `ECC_NIST_P256`, `RSA_2048`, and `RSA_4096`
+//! key specs and the `SIGN_VERIFY` key usage are supported; RSA keys sign with
+//! RSASSA-PSS.
+
+#![deny(missing_docs)]
+
+use async_trait::async_trait;
+use aws_sdk_kms::{
+    primitives::Blob,
+    types::{KeySpec, KeyUsageType, MessageType, SigningAlgorithmSpec},
+    Client,
+};
+use p256::pkcs8::DecodePublicKey;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use warg_crypto::signing::{AsyncSigner, PublicKey, Signature, SignatureError};
+
+pub use aws_sdk_kms;
+
+/// An error creating a [`KmsSigner`].
+#[derive(Debug, Error)]
+pub enum KmsSignerError {
+    /// A request to KMS failed.
+    #[error("KMS request failed: {0}")]
+    Kms(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// KMS did not return the public key of the key.
+    #[error("KMS did not return a public key for key `{0}`")]
+    MissingPublicKey(String),
+    /// The key is not an asymmetric signing key.
+    #[error("KMS key `{0}` is not a signing key")]
+    NotSigningKey(String),
+    /// The key spec of the key is not supported.
+    #[error("KMS key `{key_id}` has unsupported key spec `{spec}`")]
+    UnsupportedKeySpec {
+        /// The ID of the key.
+        key_id: String,
+        /// The key spec of the key.
+        spec: String,
+    },
+    /// The public key returned by KMS could not be decoded.
+    #[error("KMS returned an invalid public key for key `{0}`")]
+    InvalidPublicKey(String),
+}
+
+/// Signs with an asymmetric key held by AWS KMS.
+#[derive(Debug, Clone)]
+pub struct KmsSigner {
+    client: Client,
+    key_id: String,
+    algorithm: SigningAlgorithmSpec,
+    public_key: PublicKey,
+}
+
+impl KmsSigner {
+    /// Creates a signer for the KMS key with the given ID, which may be a key
+    /// ID, key ARN, alias name, or alias ARN.
+    ///
+    /// The public key of the key is fetched once, here, so that key IDs can be
+    /// computed without a request to KMS.
+    pub async fn new(client: Client, key_id: impl Into<String>) -> Result<Self, KmsSignerError> {
+        let key_id = key_id.into();
+        let output = client
+            .get_public_key()
+            .key_id(&key_id)
+            .send()
+            .await
+            .map_err(|e| KmsSignerError::Kms(e.into()))?;
+
+        if output.key_usage() != Some(&KeyUsageType::SignVerify) {
+            return Err(KmsSignerError::NotSigningKey(key_id));
+        }
+
+        let der = output
+            .public_key()
+            .ok_or_else(|| KmsSignerError::MissingPublicKey(key_id.clone()))?
+            .as_ref();
+        let (algorithm, public_key) = match output.key_spec() {
+            Some(KeySpec::EccNistP256) => (
+                SigningAlgorithmSpec::EcdsaSha256,
+                decode_p256_public_key(der),
+            ),
+            Some(KeySpec::Rsa2048 | KeySpec::Rsa4096) => (
+                SigningAlgorithmSpec::RsassaPssSha256,
+                decode_rsa_public_key(der),
+            ),
+            spec => {
+                return Err(KmsSignerError::UnsupportedKeySpec {
+                    key_id,
+                    spec: spec.map(|s| s.as_str()).unwrap_or("none").to_string(),
+                })
+            }
+        };
+        let public_key =
+            public_key.ok_or_else(|| KmsSignerError::InvalidPublicKey(key_id.clone()))?;
+
+        Ok(Self {
+            client,
+            key_id,
+            algorithm,
+            public_key,
+        })
+    }
+
+    /// Gets the ID of the KMS key the signer signs with.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+}
+
+#[async_trait]
+impl AsyncSigner for KmsSigner {
+    async fn public_key(&self) -> Result<PublicKey, SignatureError> {
+        Ok(self.public_key.clone())
+    }
+
+    async fn sign(&self, msg: &[u8]) -> Result<Signature, SignatureError> {
+        // KMS only signs raw messages of up to 4 KiB, so sign a digest instead
+        let digest = Sha256::digest(msg);
+        let output = self
+            .client
+            .sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(digest.to_vec()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(self.algorithm.clone())
+            .send()
+            .await
+            .map_err(SignatureError::from_source)?;
+
+        let signature = output.signature().ok_or_else(SignatureError::new)?;
+        decode_signature(&self.public_key, signature.as_ref())
+    }
+}
+
+// Decodes a DER-encoded SubjectPublicKeyInfo of a P-256 key.
+fn decode_p256_public_key(der: &[u8]) -> Option<PublicKey> {
+    p256::ecdsa::VerifyingKey::from_public_key_der(der)
+        .ok()
+        .map(PublicKey::from)
+}
+
+// Decodes a DER-encoded SubjectPublicKeyInfo of an RSA key.
+fn decode_rsa_public_key(der: &[u8]) -> Option<PublicKey> {
+    rsa::RsaPublicKey::from_public_key_der(der)
+        .ok()
+        .and_then(|key| PublicKey::try_from(key).ok())
+}
+
+// Decodes a signature returned by KMS for the given public key.
+//
+// KMS returns ECDSA signatures DER-encoded and RSA signatures as raw bytes.
+fn decode_signature(public_key: &PublicKey, bytes: &[u8]) -> Result<Signature, SignatureError> {
+    match public_key {
+        PublicKey::EcdsaP256(_) => {
+            let signature = p256::ecdsa::Signature::from_der(bytes)?;
+            Ok(Signature::P256(
+                signature.normalize_s().unwrap_or(signature),
+            ))
+        }
+        PublicKey::RsaPss(_) => Ok(Signature::RsaPss(rsa::pss::Signature::try_from(bytes)?)),
+        PublicKey::Ed25519(_) => Err(SignatureError::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::pkcs8::EncodePublicKey;
+    use rand_core::OsRng;
+    use warg_crypto::signing::{generate_rsa_pss_pair, PrivateKey, SignatureAlgorithm};
+
+    #[test]
+    fn test_decode_p256() {
+        let signing_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let der = signing_key.verifying_key().to_public_key_der().unwrap();
+        let public_key = decode_p256_public_key(der.as_bytes()).unwrap();
+        assert_eq!(public_key, PublicKey::from(*signing_key.verifying_key()));
+
+        // KMS signs the digest of the message with ECDSA_SHA_256
+        let msg = b"hello world";
+        let (signature, _): (p256::ecdsa::Signature, _) = signing_key
+            .sign_prehash_recoverable(&Sha256::digest(msg))
+            .unwrap();
+        let signature = decode_signature(&public_key, signature.to_der().as_bytes()).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+
+        assert!(decode_rsa_public_key(der.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn test_decode_rsa() {
+        let (public_key, private_key) =
+            generate_rsa_pss_pair(SignatureAlgorithm::RsaPss2048).unwrap();
+        let PublicKey::RsaPss(key) = &public_key else {
+            unreachable!()
+        };
+        let der = key.to_public_key_der().unwrap();
+        assert_eq!(decode_rsa_public_key(der.as_bytes()).unwrap(), public_key);
+        assert!(decode_p256_public_key(der.as_bytes()).is_none());
+
+        let msg = b"hello world";
+        let Signature::RsaPss(signature) = PrivateKey::sign(&private_key, msg).unwrap() else {
+            unreachable!()
+        };
+        let bytes: Box<[u8]> = signature.into();
+        let signature = decode_signature(&public_key, &bytes).unwrap();
+        public_key.verify(msg, &signature).unwrap();
+    }
+}
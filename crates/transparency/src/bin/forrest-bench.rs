@@ -0,0 +1,293 @@
+//! Load testing of the verifiable map with deterministic workloads.
+//!
+//! A workload populates a map with an initial set of keys and then runs a
+//! mix of inserts of new keys, updates of existing keys, and lookups (with
+//! inclusion proofs) of existing keys. Updated and looked up keys are chosen
+//! with an approximately Zipf distribution over the order keys were inserted
+//! in, so that, as in a registry, the oldest packages are the most active.
+//!
+//! Workloads are generated from a seed, so two runs with the same arguments
+//! perform the same operations and end at the same root, and changes to the
+//! map or to hashing can be compared on identical workloads.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use warg_crypto::hash::{Hash, Sha256};
+use warg_transparency::map::{Map, MapProofBundle};
+
+/// Counts the bytes allocated on the heap.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// The seed the workload is generated from
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// The number of keys in the map before the workload runs
+    #[arg(long, default_value = "10000")]
+    initial: usize,
+
+    /// The number of operations in the workload
+    #[arg(long, default_value = "100000")]
+    operations: usize,
+
+    /// The percentage of operations inserting new keys
+    #[arg(long, default_value = "10")]
+    inserts: u8,
+
+    /// The percentage of operations updating existing keys
+    #[arg(long, default_value = "30")]
+    updates: u8,
+
+    /// The percentage of operations looking up existing keys; the remainder
+    /// of the operations after inserts and updates by default
+    #[arg(long)]
+    lookups: Option<u8>,
+
+    /// The exponent of the Zipf distribution of the keys updated and looked
+    /// up; zero chooses keys uniformly
+    #[arg(long, default_value = "1.0")]
+    zipf_exponent: f64,
+
+    /// The number of inserts and updates applied to the map at once, as a
+    /// registry does for the records of each checkpoint
+    #[arg(long, default_value = "100")]
+    batch_size: usize,
+}
+
+/// An operation of a workload.
+enum Operation {
+    Insert,
+    Update,
+    Lookup,
+}
+
+/// Generates the operations of a workload.
+struct Workload {
+    rng: StdRng,
+    inserts: u8,
+    updates: u8,
+    zipf_exponent: f64,
+}
+
+impl Workload {
+    fn next(&mut self) -> Operation {
+        let roll = self.rng.gen_range(0..100);
+        if roll < self.inserts {
+            Operation::Insert
+        } else if roll < self.inserts + self.updates {
+            Operation::Update
+        } else {
+            Operation::Lookup
+        }
+    }
+
+    /// Chooses the index of one of the given number of keys.
+    ///
+    /// Samples the continuous power law approximating a Zipf distribution by
+    /// inversion, which needs no tables as the number of keys grows.
+    fn choose(&mut self, len: usize) -> usize {
+        let n = len as f64;
+        let u: f64 = self.rng.gen();
+        let s = self.zipf_exponent;
+        let rank = if s == 0.0 {
+            u * n
+        } else if (s - 1.0).abs() < f64::EPSILON {
+            n.powf(u) - 1.0
+        } else {
+            ((n.powf(1.0 - s) - 1.0) * u + 1.0).powf(1.0 / (1.0 - s)) - 1.0
+        };
+        (rank as usize).min(len - 1)
+    }
+
+    fn value(&mut self) -> [u8; 32] {
+        self.rng.gen()
+    }
+}
+
+/// Derives the key with the given index, as log IDs are derived from names.
+fn key(index: usize) -> [u8; 32] {
+    Hash::<Sha256>::of(&(index as u64).to_le_bytes()[..])
+        .bytes()
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes")
+}
+
+/// The count and total time of a kind of operation.
+#[derive(Default)]
+struct Timing {
+    count: usize,
+    elapsed: Duration,
+}
+
+impl Timing {
+    fn report(&self, name: &str) {
+        let secs = self.elapsed.as_secs_f64();
+        let rate = if secs > 0.0 {
+            self.count as f64 / secs
+        } else {
+            0.0
+        };
+        println!(
+            "{name:<8} {count:>10} ops in {secs:>9.3}s ({rate:.0} ops/s)",
+            count = self.count
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let lookups = args
+        .lookups
+        .unwrap_or(100u8.saturating_sub(args.inserts.saturating_add(args.updates)));
+    if u16::from(args.inserts) + u16::from(args.updates) + u16::from(lookups) != 100 {
+        bail!("the percentages of inserts, updates, and lookups must add up to 100");
+    }
+    if args.initial == 0 && args.inserts < 100 {
+        bail!("updates and lookups require at least one initial key");
+    }
+    if args.batch_size == 0 {
+        bail!("the batch size must be at least one");
+    }
+    if args.zipf_exponent < 0.0 {
+        bail!("the Zipf exponent must not be negative");
+    }
+
+    let mut workload = Workload {
+        rng: StdRng::seed_from_u64(args.seed),
+        inserts: args.inserts,
+        updates: args.updates,
+        zipf_exponent: args.zipf_exponent,
+    };
+
+    let start = Instant::now();
+    let mut values: Vec<[u8; 32]> = (0..args.initial).map(|_| workload.value()).collect();
+    let mut map = Map::<Sha256, [u8; 32], [u8; 32]>::default().extend(
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (key(index), *value)),
+    );
+    let populate = Timing {
+        count: args.initial,
+        elapsed: start.elapsed(),
+    };
+
+    // Inserts and updates are batched by key index; `values` holds the
+    // values of the keys in the map, and `len` also counts pending inserts
+    let mut len = args.initial;
+    let mut batch: Vec<(usize, [u8; 32])> = Vec::with_capacity(args.batch_size);
+    let mut writes = Timing::default();
+    let mut reads = Timing::default();
+    let mut proof_bytes = 0;
+    let mut max_proof_bytes = 0;
+
+    let mut apply = |map: Map<Sha256, [u8; 32], [u8; 32]>,
+                     values: &mut Vec<[u8; 32]>,
+                     batch: &mut Vec<(usize, [u8; 32])>| {
+        let items: Vec<_> = batch
+            .iter()
+            .map(|(index, value)| (key(*index), *value))
+            .collect();
+        writes.count += items.len();
+        let start = Instant::now();
+        let map = map.extend(items);
+        writes.elapsed += start.elapsed();
+        for (index, value) in batch.drain(..) {
+            if index == values.len() {
+                values.push(value);
+            } else {
+                values[index] = value;
+            }
+        }
+        map
+    };
+
+    for _ in 0..args.operations {
+        match workload.next() {
+            Operation::Insert => {
+                batch.push((len, workload.value()));
+                len += 1;
+            }
+            Operation::Update => {
+                let index = workload.choose(len);
+                batch.push((index, workload.value()));
+            }
+            Operation::Lookup if values.is_empty() => {}
+            Operation::Lookup => {
+                let index = workload.choose(values.len());
+                let key = key(index);
+                let start = Instant::now();
+                let proof = map.prove(key).expect("workload keys are inserted");
+                reads.elapsed += start.elapsed();
+                reads.count += 1;
+
+                assert_eq!(&proof.evaluate(&key, &values[index]), map.root());
+                let size = MapProofBundle::bundle(vec![proof]).encoded_len();
+                proof_bytes += size;
+                max_proof_bytes = max_proof_bytes.max(size);
+            }
+        }
+
+        if batch.len() == args.batch_size {
+            map = apply(map, &mut values, &mut batch);
+        }
+    }
+    map = apply(map, &mut values, &mut batch);
+    drop(values);
+
+    println!(
+        "seed {seed}, {initial} initial keys, {operations} operations ({inserts}% inserts, {updates}% updates, {lookups}% lookups), Zipf exponent {s}, batches of {batch_size}",
+        seed = args.seed,
+        initial = args.initial,
+        operations = args.operations,
+        inserts = args.inserts,
+        updates = args.updates,
+        s = args.zipf_exponent,
+        batch_size = args.batch_size,
+    );
+    populate.report("populate");
+    writes.report("write");
+    reads.report("lookup");
+    if let Some(mean) = proof_bytes.checked_div(reads.count) {
+        println!("proofs   {mean} bytes mean, {max_proof_bytes} bytes max");
+    }
+    println!(
+        "memory   {current} KiB in use, {peak} KiB peak",
+        current = ALLOCATED.load(Ordering::Relaxed) / 1024,
+        peak = PEAK.load(Ordering::Relaxed) / 1024
+    );
+    println!("keys     {len}");
+    println!("root     {root}", root = map.root());
+    Ok(())
+}
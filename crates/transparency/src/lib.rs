@@ -12,5 +12,7 @@ extern crate alloc;
 #[cfg(test)]
 extern crate std;
 
+pub mod limits;
 pub mod log;
 pub mod map;
+mod proto_len;
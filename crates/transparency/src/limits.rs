@@ -0,0 +1,108 @@
+//! Sanity limits for decoded transparency structures.
+//!
+//! Proofs and proof bundles come from untrusted sources, so their decoders
+//! check them against a set of [`Limits`] before handing them to a verifier.
+//! This keeps a malicious peer from making a verifier walk an arbitrarily
+//! deep structure or hash an arbitrarily long proof.
+
+use thiserror::Error;
+use warg_crypto::hash::{Digest, SupportedDigest};
+
+/// Limits on the size of decoded logs, maps, and proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum length of a log.
+    pub max_log_length: usize,
+    /// The maximum depth of a map.
+    ///
+    /// A map is never deeper than the bit length of its digest, which is
+    /// enforced regardless of this limit.
+    pub max_map_depth: usize,
+}
+
+impl Limits {
+    /// Gets the default limits for structures using the given digest.
+    ///
+    /// The maximum log length is the largest length representable in the
+    /// wire format, and the maximum map depth is the bit length of the
+    /// digest.
+    pub fn for_digest<D: SupportedDigest>() -> Self {
+        Self {
+            max_log_length: u32::MAX as usize,
+            max_map_depth: digest_bits::<D>(),
+        }
+    }
+
+    /// Gets the maximum depth of a log, i.e. the maximum height of a node
+    /// in a log of the maximum length.
+    pub fn max_log_depth(&self) -> usize {
+        (usize::BITS - self.max_log_length.leading_zeros()) as usize
+    }
+
+    /// Gets the maximum number of hashes in an inclusion proof for a log.
+    ///
+    /// An inclusion proof walks up to a balanced root of the log and then
+    /// across every other balanced root, each of which is bounded by the
+    /// depth of the log.
+    pub fn max_log_proof_len(&self) -> usize {
+        2 * self.max_log_depth()
+    }
+
+    /// Gets the maximum number of peer hashes in an inclusion proof for a
+    /// map using the given digest.
+    pub fn max_map_proof_len<D: SupportedDigest>(&self) -> usize {
+        self.max_map_depth.min(digest_bits::<D>())
+    }
+
+    /// Checks that a log length is within the limits.
+    pub fn check_log_length(&self, length: usize) -> Result<(), LimitError> {
+        if length > self.max_log_length {
+            return Err(LimitError::LogTooLong {
+                length,
+                max: self.max_log_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn digest_bits<D: SupportedDigest>() -> usize {
+    <D as Digest>::output_size() * 8
+}
+
+/// An error indicating that a decoded structure exceeds its [`Limits`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    /// A log is longer than the maximum log length.
+    #[error("log length {length} exceeds the maximum of {max}")]
+    LogTooLong {
+        /// The length of the log.
+        length: usize,
+        /// The maximum log length.
+        max: usize,
+    },
+    /// A proof refers to a log length beyond the length of the log it proves.
+    #[error("log length {length} exceeds the proven log length {log_length}")]
+    LengthBeyondLog {
+        /// The referenced length.
+        length: usize,
+        /// The length of the proven log.
+        log_length: usize,
+    },
+    /// A proof refers to a node that does not exist in the log it proves.
+    #[error("node {index} does not exist in a log of length {log_length}")]
+    NodeBeyondLog {
+        /// The index of the node.
+        index: usize,
+        /// The length of the proven log.
+        log_length: usize,
+    },
+    /// A proof contains more hashes than a valid proof could require.
+    #[error("proof contains {len} hashes, exceeding the maximum of {max}")]
+    ProofTooLong {
+        /// The number of hashes in the proof.
+        len: usize,
+        /// The maximum number of hashes.
+        max: usize,
+    },
+}
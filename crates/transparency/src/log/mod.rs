@@ -6,10 +6,10 @@
 //!
 //! Implementations:
 //! * [`InOrderLog`] -
-//! The only implementation in this module is ,
-//! which is a [`VerifiableLog`] whose contents are structured
-//! using binary in-order interval numbering as described in
-//! [Dat - Distributed Dataset Synchronization and Versioning][2].
+//!   The only implementation in this module is ,
+//!   which is a [`VerifiableLog`] whose contents are structured
+//!   using binary in-order interval numbering as described in
+//!   [Dat - Distributed Dataset Synchronization and Versioning][2].
 
 mod node;
 /// Logic for constructing and validating proofs
@@ -18,6 +18,7 @@ mod proof_bundle;
 mod sparse_data;
 mod stack_log;
 mod vec_log;
+mod verify;
 
 use warg_crypto::{
     hash::{Hash, SupportedDigest},
@@ -33,6 +34,9 @@ pub use proof_bundle::ProofBundle;
 pub use proof_bundle::ProofBundle as LogProofBundle;
 pub use stack_log::StackLog;
 pub use vec_log::VecLog;
+pub use verify::{
+    consistency_proof_len, evaluate_consistency, evaluate_inclusion, inclusion_proof_len,
+};
 
 /// A [merkle tree][0] log data type based on [DAT][1].
 /// where the merkle tree computation is conformant to
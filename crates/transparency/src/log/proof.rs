@@ -33,6 +33,10 @@ pub enum InclusionProofError {
     /// needed to perform proof validation.
     #[error("required hash for proof is not available")]
     HashNotKnown,
+    /// Indicates that a proof contained more hashes than
+    /// are needed to perform proof validation.
+    #[error("proof contains more hashes than required")]
+    TooManyHashes,
 }
 
 /// The nodes visited when verifying the inclusion proof.
@@ -151,6 +155,20 @@ where
         })
     }
 
+    /// Collects the hashes of the nodes visited by the inclusion proof,
+    /// for evaluation with [`evaluate_inclusion`](super::evaluate_inclusion).
+    pub fn hashes(&self, hashes: &impl LogData<D, V>) -> Result<Vec<Hash<D>>, InclusionProofError> {
+        self.walk()?
+            .nodes
+            .into_iter()
+            .map(|node| {
+                hashes
+                    .hash_for(node)
+                    .ok_or(InclusionProofError::HashNotKnown)
+            })
+            .collect()
+    }
+
     /// Evaluate an inclusion proof.
     /// Callers should verify that the returned root matches their expectation.
     ///
@@ -248,6 +266,9 @@ pub enum ConsistencyProofError {
     /// Happens when hashes required for evaluation were not present
     #[error("a hash needed for evaluation was not available")]
     HashNotKnown,
+    /// Happens when a proof contains more hashes than evaluation requires
+    #[error("proof contains more hashes than required")]
+    TooManyHashes,
     /// Happens when an inclusion proof is evaluated and has an error
     #[error("constituent inclusion proof failed")]
     InclusionError(#[from] InclusionProofError),
@@ -306,6 +327,28 @@ where
         Ok((old_root, new_root))
     }
 
+    /// Collects the hashes of the balanced roots of the old log followed by
+    /// the hashes of their inclusion proofs, for evaluation with
+    /// [`evaluate_consistency`](super::evaluate_consistency).
+    pub fn hashes(
+        &self,
+        hashes: &impl LogData<D, V>,
+    ) -> Result<Vec<Hash<D>>, ConsistencyProofError> {
+        let inclusions = self.inclusions()?;
+        let mut result = inclusions
+            .iter()
+            .map(|inc_proof| {
+                hashes
+                    .hash_for(inc_proof.leaf())
+                    .ok_or(ConsistencyProofError::HashNotKnown)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for inc_proof in inclusions {
+            result.extend(inc_proof.hashes(hashes)?);
+        }
+        Ok(result)
+    }
+
     /// Convert the consistency proof into a sequence of inclusion proofs.
     /// Each inclusion proof verifies that one of the balanced roots
     /// of the old tree is present in the root of the new tree.
@@ -361,7 +404,7 @@ mod tests {
         log.push(&102);
         log.push(&104);
 
-        let root: Hash<Sha256> = hash_branch(log.as_ref()[1].clone(), log.as_ref()[4].clone());
+        let root: Hash<Sha256> = hash_branch(log.as_ref()[1], log.as_ref()[4]);
 
         // node 0
         let inc_proof = InclusionProof::new(Node(0), 3);
@@ -409,9 +452,8 @@ mod tests {
         log.push(&110);
         log.push(&112);
 
-        let artificial_branch: Hash<Sha256> =
-            hash_branch(log.as_ref()[9].clone(), log.as_ref()[12].clone());
-        let root: Hash<Sha256> = hash_branch(log.as_ref()[3].clone(), artificial_branch);
+        let artificial_branch: Hash<Sha256> = hash_branch(log.as_ref()[9], log.as_ref()[12]);
+        let root: Hash<Sha256> = hash_branch(log.as_ref()[3], artificial_branch);
 
         // node 6
         let inc_proof = InclusionProof::new(Node(6), 7);
@@ -1,7 +1,7 @@
 use alloc::vec::Vec;
 use anyhow::Error;
 use indexmap::IndexSet;
-use prost::Message;
+use prost::{encoding, Message};
 use std::marker::PhantomData;
 use warg_crypto::{
     hash::{Hash, SupportedDigest},
@@ -9,11 +9,15 @@ use warg_crypto::{
 };
 use warg_protobuf::transparency as protobuf;
 
-use crate::log::{
-    node::Node,
-    proof::{ConsistencyProof, InclusionProof},
-    sparse_data::SparseLogData,
-    LogData,
+use crate::{
+    limits::{LimitError, Limits},
+    log::{
+        node::Node,
+        proof::{ConsistencyProof, InclusionProof},
+        sparse_data::SparseLogData,
+        LogData,
+    },
+    proto_len::{delimited_len, packed_len},
 };
 
 /// A collection of inclusion proof info
@@ -26,12 +30,30 @@ where
     consistent_lengths: Vec<u32>,
     included_indices: Vec<Node>,
     hashes: Vec<(Node, Hash<D>)>,
-    /// Marker for value type
-    _digest: PhantomData<D>,
+    /// Marker for digest type
+    _digest: PhantomData<fn() -> D>,
     /// Marker for value type
     _value: PhantomData<V>,
 }
 
+// Manual impl of Clone to avoid requiring the value type to be cloneable
+impl<D, V> Clone for ProofBundle<D, V>
+where
+    D: SupportedDigest,
+    V: VisitBytes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            log_length: self.log_length,
+            consistent_lengths: self.consistent_lengths.clone(),
+            included_indices: self.included_indices.clone(),
+            hashes: self.hashes.clone(),
+            _digest: PhantomData,
+            _value: PhantomData,
+        }
+    }
+}
+
 impl<D, V> ProofBundle<D, V>
 where
     D: SupportedDigest,
@@ -133,6 +155,34 @@ where
         (data, c_proofs, i_proofs)
     }
 
+    /// Gets the length of the protobuf encoding of the bundle without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        let hashes: usize = self
+            .hashes
+            .iter()
+            .map(|(node, hash)| {
+                let index = node.0 as u32;
+                let len = if index == 0 {
+                    0
+                } else {
+                    encoding::uint32::encoded_len(1, &index)
+                } + delimited_len(2, hash.bytes().len());
+                delimited_len(4, len)
+            })
+            .sum();
+
+        let log_length = if self.log_length == 0 {
+            0
+        } else {
+            encoding::uint32::encoded_len(1, &self.log_length)
+        };
+        log_length
+            + packed_len(2, self.consistent_lengths.iter().copied())
+            + packed_len(3, self.included_indices.iter().map(|n| n.0 as u32))
+            + hashes
+    }
+
     /// Turn a bundle into bytes using protobuf
     pub fn encode(self) -> Vec<u8> {
         let proto: protobuf::LogProofBundle = self.into();
@@ -141,10 +191,117 @@ where
 
     /// Parse a bundle from bytes using protobuf
     pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode_with_limits(bytes, &Limits::for_digest::<D>())
+    }
+
+    /// Parse a bundle from bytes using protobuf, checking it against the
+    /// given limits.
+    ///
+    /// A bundle exceeding the limits fails with a [`LimitError`].
+    pub fn decode_with_limits(bytes: &[u8], limits: &Limits) -> Result<Self, Error> {
         let proto = protobuf::LogProofBundle::decode(bytes)?;
-        let bundle = proto.try_into()?;
-        Ok(bundle)
+        Self::from_protobuf(proto, limits)
     }
+
+    fn from_protobuf(value: protobuf::LogProofBundle, limits: &Limits) -> Result<Self, Error> {
+        let log_length = value.log_length as usize;
+        limits.check_log_length(log_length)?;
+
+        // Each consistency proof requires the inclusion of every balanced
+        // root of the old log, along with the hash of that root
+        let mut max_hashes = value.included_indices.len() * limits.max_log_proof_len();
+        for length in value.consistent_lengths.iter().map(|len| *len as usize) {
+            if length > log_length {
+                return Err(LimitError::LengthBeyondLog { length, log_length }.into());
+            }
+            max_hashes += length.count_ones() as usize * (limits.max_log_proof_len() + 1);
+        }
+        if value.hashes.len() > max_hashes {
+            return Err(LimitError::ProofTooLong {
+                len: value.hashes.len(),
+                max: max_hashes,
+            }
+            .into());
+        }
+
+        let nodes = value
+            .included_indices
+            .iter()
+            .chain(value.hashes.iter().map(|entry| &entry.index));
+        for index in nodes.map(|index| *index as usize) {
+            if !Node(index).exists_at_length(log_length) {
+                return Err(LimitError::NodeBeyondLog { index, log_length }.into());
+            }
+        }
+
+        let included_indices = value
+            .included_indices
+            .into_iter()
+            .map(|index| Node(index as usize))
+            .collect();
+        let mut hashes = Vec::new();
+        for entry in value.hashes {
+            hashes.push((Node(entry.index as usize), entry.hash.try_into()?))
+        }
+        Ok(ProofBundle {
+            log_length: value.log_length,
+            consistent_lengths: value.consistent_lengths,
+            included_indices,
+            hashes,
+            _digest: PhantomData,
+            _value: PhantomData,
+        })
+    }
+
+    /// Turn a bundle into bytes using CBOR
+    #[cfg(feature = "cbor")]
+    pub fn encode_cbor(self) -> Result<Vec<u8>, Error> {
+        let proto: protobuf::LogProofBundle = self.into();
+        let cbor = CborProofBundle {
+            log_length: proto.log_length,
+            consistent_lengths: proto.consistent_lengths,
+            included_indices: proto.included_indices,
+            hashes: proto
+                .hashes
+                .into_iter()
+                .map(|entry| (entry.index, entry.hash))
+                .collect(),
+        };
+
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&cbor, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Parse a bundle from bytes using CBOR
+    #[cfg(feature = "cbor")]
+    pub fn decode_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        let cbor: CborProofBundle = ciborium::from_reader(bytes)?;
+        protobuf::LogProofBundle {
+            log_length: cbor.log_length,
+            consistent_lengths: cbor.consistent_lengths,
+            included_indices: cbor.included_indices,
+            hashes: cbor
+                .hashes
+                .into_iter()
+                .map(|(index, hash)| protobuf::HashEntry { index, hash })
+                .collect(),
+        }
+        .try_into()
+    }
+}
+
+/// The CBOR representation of a log proof bundle.
+#[cfg(feature = "cbor")]
+#[serde_with::serde_as]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CborProofBundle {
+    log_length: u32,
+    consistent_lengths: Vec<u32>,
+    included_indices: Vec<u32>,
+    #[serde_as(as = "Vec<(_, serde_with::Bytes)>")]
+    hashes: Vec<(u32, Vec<u8>)>,
 }
 
 impl<D, V> From<ProofBundle<D, V>> for protobuf::LogProofBundle
@@ -183,23 +340,117 @@ where
     type Error = Error;
 
     fn try_from(value: protobuf::LogProofBundle) -> Result<Self, Self::Error> {
-        let included_indices = value
-            .included_indices
-            .into_iter()
-            .map(|index| Node(index as usize))
-            .collect();
-        let mut hashes = Vec::new();
-        for entry in value.hashes {
-            hashes.push((Node(entry.index as usize), entry.hash.try_into()?))
+        Self::from_protobuf(value, &Limits::for_digest::<D>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{LogBuilder, VecLog};
+    use warg_crypto::hash::Sha256;
+
+    #[test]
+    fn test_encoded_len() {
+        let mut log: VecLog<Sha256, &str> = VecLog::default();
+        for value in ["a", "b", "c", "d", "e"] {
+            log.push(&value);
         }
-        let bundle = ProofBundle {
-            log_length: value.log_length,
-            consistent_lengths: value.consistent_lengths,
-            included_indices,
-            hashes,
-            _digest: PhantomData,
-            _value: PhantomData,
+
+        for (consistency, inclusions) in [
+            (vec![], vec![Node(0)]),
+            (vec![log.prove_consistency(2, 5)], vec![Node(4), Node(8)]),
+        ] {
+            let inclusions = inclusions
+                .into_iter()
+                .map(|node| log.prove_inclusion(node, 5))
+                .collect();
+            let bundle = ProofBundle::bundle(consistency, inclusions, &log).unwrap();
+            assert_eq!(bundle.encoded_len(), bundle.encode().len());
+        }
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let mut log: VecLog<Sha256, &str> = VecLog::default();
+        for value in ["a", "b", "c", "d", "e"] {
+            log.push(&value);
+        }
+
+        let bytes = ProofBundle::bundle(
+            vec![log.prove_consistency(2, 5)],
+            vec![log.prove_inclusion(Node(4), 5)],
+            &log,
+        )
+        .unwrap()
+        .encode();
+        ProofBundle::<Sha256, &str>::decode(&bytes).unwrap();
+
+        let limits = Limits {
+            max_log_length: 4,
+            ..Limits::for_digest::<Sha256>()
+        };
+        let err = ProofBundle::<Sha256, &str>::decode_with_limits(&bytes, &limits)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::LogTooLong { length: 5, max: 4 })
+        );
+
+        let malformed = |f: fn(&mut protobuf::LogProofBundle)| {
+            let mut proto = protobuf::LogProofBundle::decode(bytes.as_slice()).unwrap();
+            f(&mut proto);
+            ProofBundle::<Sha256, &str>::decode(&proto.encode_to_vec())
+                .err()
+                .unwrap()
+                .downcast::<LimitError>()
+                .unwrap()
+        };
+        assert_eq!(
+            malformed(|proto| proto.consistent_lengths.push(6)),
+            LimitError::LengthBeyondLog {
+                length: 6,
+                log_length: 5
+            }
+        );
+        assert_eq!(
+            malformed(|proto| proto.included_indices.push(u32::MAX)),
+            LimitError::NodeBeyondLog {
+                index: u32::MAX as usize,
+                log_length: 5
+            }
+        );
+        assert!(matches!(
+            malformed(|proto| {
+                let entry = proto.hashes[0].clone();
+                proto.hashes.resize(1000, entry);
+            }),
+            LimitError::ProofTooLong { len: 1000, .. }
+        ));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let mut log: VecLog<Sha256, &str> = VecLog::default();
+        for value in ["a", "b", "c", "d", "e"] {
+            log.push(&value);
+        }
+
+        let bundle = || {
+            ProofBundle::bundle(
+                vec![log.prove_consistency(2, 5)],
+                vec![log.prove_inclusion(Node(4), 5)],
+                &log,
+            )
+            .unwrap()
         };
-        Ok(bundle)
+
+        let cbor = bundle().encode_cbor().unwrap();
+        let decoded = ProofBundle::<Sha256, &str>::decode_cbor(&cbor).unwrap();
+        assert_eq!(decoded.encode(), bundle().encode());
+
+        assert!(ProofBundle::<Sha256, &str>::decode_cbor(&cbor[..cbor.len() - 1]).is_err());
     }
 }
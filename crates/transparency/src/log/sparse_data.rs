@@ -23,11 +23,7 @@ where
     V: VisitBytes,
 {
     fn get_index(&self, node: Node) -> Option<usize> {
-        let result = self.data.binary_search_by_key(&node, |entry| entry.0);
-        match result {
-            Ok(index) => Some(index),
-            Err(_) => None,
-        }
+        self.data.binary_search_by_key(&node, |entry| entry.0).ok()
     }
 }
 
@@ -14,7 +14,7 @@ use super::{hash_branch, hash_empty, hash_leaf, Checkpoint, LogBuilder, LogData}
 
 /// A verifiable log where the node hashes are stored
 /// contiguously in memory by index.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct VecLog<D, V>
 where
     D: SupportedDigest,
@@ -28,6 +28,20 @@ where
     _value: PhantomData<V>,
 }
 
+impl<D, V> Clone for VecLog<D, V>
+where
+    D: SupportedDigest,
+    V: VisitBytes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            length: self.length,
+            tree: self.tree.clone(),
+            _value: PhantomData,
+        }
+    }
+}
+
 /// Height is the number of child-edges between the node and leaves
 /// A leaf has height 0
 ///
@@ -279,7 +293,7 @@ mod tests {
             let old_root = tree.root_at(old_length).unwrap();
 
             for (j, new_root) in roots.iter().enumerate().skip(i) {
-                let new_root = new_root.clone();
+                let new_root = *new_root;
                 let new_length = j + 1;
 
                 let proof = tree.prove_consistency(old_length, new_length);
@@ -0,0 +1,290 @@
+//! Allocation-free evaluation of log proofs.
+//!
+//! The functions in this module evaluate inclusion and consistency proofs
+//! from a flat slice of hashes rather than from a [`LogData`](super::LogData)
+//! implementation. Every intermediate value is a [`Hash`], a fixed-size
+//! buffer sized by the digest's output, so evaluation never allocates and
+//! can run in environments without a heap allocator.
+//!
+//! The hashes of a proof are laid out as follows:
+//!
+//! * An inclusion proof holds the hashes of the nodes of its
+//!   [`InclusionProofWalk`](super::InclusionProofWalk), in order.
+//! * A consistency proof holds the hashes of the balanced roots of the old
+//!   log from left to right, followed by the inclusion proof of each of those
+//!   roots in the new log, in the same order.
+//!
+//! [`InclusionProof::hashes`](super::InclusionProof::hashes) and
+//! [`ConsistencyProof::hashes`](super::ConsistencyProof::hashes) produce
+//! hashes in this layout.
+
+use warg_crypto::hash::{Hash, SupportedDigest};
+
+use super::{hash_branch, node::Node, ConsistencyProofError, InclusionProofError};
+
+/// Finds the balanced root of a log with the given length whose height
+/// corresponds to the given bit of the length.
+fn broot_for_height(length: usize, height: u32) -> Node {
+    let start = (length >> height >> 1) << height << 1;
+    Node(2 * start + (1 << height) - 1)
+}
+
+/// Iterates the balanced roots of a log with the given length whose heights
+/// are among the given ones, in the order of the heights.
+fn broots(length: usize, heights: impl Iterator<Item = u32>) -> impl Iterator<Item = Node> {
+    heights
+        .filter(move |height| (length >> height) & 1 == 1)
+        .map(move |height| broot_for_height(length, height))
+}
+
+/// Computes the number of hashes in an inclusion proof of the given leaf in
+/// a log with the given length.
+pub fn inclusion_proof_len(leaf: Node, log_length: usize) -> Result<usize, InclusionProofError> {
+    if !leaf.exists_at_length(log_length) {
+        return Err(InclusionProofError::LeafTooNew);
+    }
+
+    let mut len = 0;
+    let mut current = leaf;
+    while current.parent().exists_at_length(log_length) {
+        len += 1;
+        current = current.parent();
+    }
+
+    // Every other balanced root is also part of the proof
+    Ok(len + log_length.count_ones() as usize - 1)
+}
+
+/// Computes the number of hashes in a consistency proof between logs with
+/// the given lengths.
+pub fn consistency_proof_len(
+    old_length: usize,
+    new_length: usize,
+) -> Result<usize, ConsistencyProofError> {
+    if old_length > new_length {
+        return Err(ConsistencyProofError::PointsOutOfOrder);
+    }
+
+    let mut len = 0;
+    for broot in broots(old_length, 0..usize::BITS) {
+        len += 1 + inclusion_proof_len(broot, new_length)?;
+    }
+    Ok(len)
+}
+
+/// Evaluate an inclusion proof without allocating.
+/// Callers should verify that the returned root matches their expectation.
+///
+/// Walks the inclusion proof of the leaf with the given hash, combining it
+/// with each hash of the proof in turn, and returns the root hash.
+pub fn evaluate_inclusion<D: SupportedDigest>(
+    leaf: Node,
+    leaf_hash: &Hash<D>,
+    log_length: usize,
+    proof: &[Hash<D>],
+) -> Result<Hash<D>, InclusionProofError> {
+    if !leaf.exists_at_length(log_length) {
+        return Err(InclusionProofError::LeafTooNew);
+    }
+
+    let mut hashes = proof.iter();
+    let mut next = || hashes.next().ok_or(InclusionProofError::HashNotKnown);
+
+    // Walk upwards until you hit a balanced root for the original tree
+    let mut current = leaf;
+    let mut root = leaf_hash.clone();
+    while current.parent().exists_at_length(log_length) {
+        let sibling = next()?;
+        root = if current.index() < current.sibling().index() {
+            hash_branch(&root, sibling)
+        } else {
+            hash_branch(sibling, &root)
+        };
+        current = current.parent();
+    }
+
+    // Summarize all of the smaller broots
+    let height = current.height();
+    let mut lower = None;
+    for _ in broots(log_length, 0..height) {
+        let broot = next()?;
+        lower = Some(match lower {
+            Some(lower) => hash_branch(broot, &lower),
+            None => broot.clone(),
+        });
+    }
+
+    // Combine broot with summary of smaller roots
+    if let Some(lower) = lower {
+        root = hash_branch(&root, &lower);
+    }
+
+    // Combine with any larger roots
+    for _ in broots(log_length, height + 1..usize::BITS) {
+        root = hash_branch(next()?, &root);
+    }
+
+    if hashes.next().is_some() {
+        return Err(InclusionProofError::TooManyHashes);
+    }
+
+    Ok(root)
+}
+
+/// Evaluate a consistency proof without allocating.
+/// Callers should verify that the returned roots match their expectation.
+///
+/// Evaluates the inclusion proof of each balanced root of the old log in the
+/// new log, and returns the old and new root hashes.
+pub fn evaluate_consistency<D: SupportedDigest>(
+    old_length: usize,
+    new_length: usize,
+    proof: &[Hash<D>],
+) -> Result<(Hash<D>, Hash<D>), ConsistencyProofError> {
+    let expected = consistency_proof_len(old_length, new_length)?;
+    if proof.len() < expected {
+        return Err(ConsistencyProofError::HashNotKnown);
+    }
+    if proof.len() > expected {
+        return Err(ConsistencyProofError::TooManyHashes);
+    }
+
+    let count = old_length.count_ones() as usize;
+    let (broot_hashes, mut walks) = proof.split_at(count);
+
+    // The old root summarizes the old broots from right to left
+    let old_root = broot_hashes
+        .iter()
+        .rev()
+        .fold(None, |acc: Option<Hash<D>>, broot| {
+            Some(match acc {
+                Some(acc) => hash_branch(broot, &acc),
+                None => broot.clone(),
+            })
+        })
+        .ok_or(ConsistencyProofError::HashNotKnown)?;
+
+    // Each old broot must be included in the same new root
+    let mut new_root: Option<Hash<D>> = None;
+    let old_broots = broots(old_length, (0..usize::BITS).rev());
+    for (broot, broot_hash) in old_broots.zip(broot_hashes) {
+        let len = inclusion_proof_len(broot, new_length)?;
+        let (walk, rest) = walks.split_at(len);
+        walks = rest;
+
+        let found_root = evaluate_inclusion(broot, broot_hash, new_length, walk)?;
+        match &new_root {
+            Some(previous_root) if previous_root != &found_root => {
+                return Err(ConsistencyProofError::DivergingRoots);
+            }
+            Some(_) => {}
+            None => new_root = Some(found_root),
+        }
+    }
+
+    let new_root = new_root.ok_or(ConsistencyProofError::HashNotKnown)?;
+    Ok((old_root, new_root))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log::{LogBuilder, LogData, VecLog};
+
+    use super::*;
+
+    use warg_crypto::hash::Sha256;
+
+    fn log(len: u8) -> VecLog<Sha256, u8> {
+        let mut log = VecLog::default();
+        for i in 0..len {
+            log.push(&i);
+        }
+        log
+    }
+
+    #[test]
+    fn test_inclusion_matches_walk() {
+        let log = log(33);
+        for log_length in 1..=33 {
+            for i in 0..log_length {
+                let leaf = Node(i * 2);
+                let inc_proof = log.prove_inclusion(leaf, log_length);
+                let proof = inc_proof.hashes(&log).unwrap();
+                assert_eq!(inclusion_proof_len(leaf, log_length).unwrap(), proof.len());
+
+                let leaf_hash = log.hash_for(leaf).unwrap();
+                let root = evaluate_inclusion(leaf, &leaf_hash, log_length, &proof).unwrap();
+                assert_eq!(root, inc_proof.evaluate_hash(&log, leaf_hash).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_inclusion_errors() {
+        let log = log(7);
+        let leaf = Node(6);
+        let leaf_hash = log.hash_for(leaf).unwrap();
+        let mut proof = log.prove_inclusion(leaf, 7).hashes(&log).unwrap();
+
+        assert_eq!(
+            evaluate_inclusion(leaf, &leaf_hash, 3, &proof),
+            Err(InclusionProofError::LeafTooNew)
+        );
+        assert_eq!(
+            evaluate_inclusion(leaf, &leaf_hash, 7, &proof[1..]),
+            Err(InclusionProofError::HashNotKnown)
+        );
+
+        proof.push(leaf_hash);
+        assert_eq!(
+            evaluate_inclusion(leaf, &leaf_hash, 7, &proof),
+            Err(InclusionProofError::TooManyHashes)
+        );
+    }
+
+    #[test]
+    fn test_consistency_matches_inclusions() {
+        let log = log(33);
+        for old_length in 1..=33 {
+            for new_length in old_length..=33 {
+                let cons_proof = log.prove_consistency(old_length, new_length);
+                let proof = cons_proof.hashes(&log).unwrap();
+                assert_eq!(
+                    consistency_proof_len(old_length, new_length).unwrap(),
+                    proof.len()
+                );
+
+                let roots = evaluate_consistency(old_length, new_length, &proof).unwrap();
+                assert_eq!(roots, cons_proof.evaluate(&log).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_errors() {
+        let log = log(11);
+        let mut proof = log.prove_consistency(5, 11).hashes(&log).unwrap();
+
+        assert_eq!(
+            evaluate_consistency(11, 5, &proof),
+            Err(ConsistencyProofError::PointsOutOfOrder)
+        );
+        assert_eq!(
+            evaluate_consistency(5, 11, &proof[1..]),
+            Err(ConsistencyProofError::HashNotKnown)
+        );
+
+        // Swapping the old broots produces a different root for each
+        proof.swap(0, 1);
+        assert_eq!(
+            evaluate_consistency(5, 11, &proof),
+            Err(ConsistencyProofError::DivergingRoots)
+        );
+
+        proof.push(proof[0]);
+        assert_eq!(
+            evaluate_consistency(5, 11, &proof),
+            Err(ConsistencyProofError::TooManyHashes)
+        );
+    }
+}
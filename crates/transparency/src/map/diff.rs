@@ -0,0 +1,168 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use warg_crypto::hash::{Hash, SupportedDigest};
+
+use super::{link::Link, node::Node, path::Side};
+
+/// A key whose value differs between two maps.
+///
+/// Keys and values are identified by hash, as a map stores only the hash of
+/// each key and the leaf hash of each value.
+#[derive(Debug)]
+pub struct MapChange<D: SupportedDigest> {
+    /// The hash of the key.
+    pub key: Hash<D>,
+    /// The leaf hash of the value in the earlier map, if the key was present.
+    pub old: Option<Hash<D>>,
+    /// The leaf hash of the value in the later map, if the key is present.
+    pub new: Option<Hash<D>>,
+}
+
+impl<D: SupportedDigest> Clone for MapChange<D> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            old: self.old.clone(),
+            new: self.new.clone(),
+        }
+    }
+}
+
+/// Computes the changes between two map trees, in key hash order.
+///
+/// Subtrees with equal hashes are skipped, so the cost is proportional to
+/// the number of changes rather than the size of the maps.
+pub(crate) fn diff<D: SupportedDigest>(old: &Link<D>, new: &Link<D>) -> Vec<MapChange<D>> {
+    let mut changes = Vec::new();
+    let mut key = Hash::<D>::default().bytes().to_vec();
+    diff_links(old, new, 0, &mut key, &mut changes);
+    changes
+}
+
+fn diff_links<D: SupportedDigest>(
+    old: &Link<D>,
+    new: &Link<D>,
+    depth: usize,
+    key: &mut [u8],
+    changes: &mut Vec<MapChange<D>>,
+) {
+    if old.hash() == new.hash() {
+        return;
+    }
+
+    if let (Node::Fork(old), Node::Fork(new)) = (old.node(), new.node()) {
+        for side in [Side::Left, Side::Right] {
+            set_bit(key, depth, side);
+            diff_links(&old[side], &new[side], depth + 1, key, changes);
+        }
+        return;
+    }
+
+    // The subtrees are shaped differently; compare their leaves directly
+    let mut leaves = BTreeMap::new();
+    collect(old.node(), depth, key, &mut |key, value| {
+        leaves
+            .entry(key.bytes().to_vec())
+            .or_insert_with(|| MapChange {
+                key,
+                old: None,
+                new: None,
+            })
+            .old = Some(value);
+    });
+    collect(new.node(), depth, key, &mut |key, value| {
+        leaves
+            .entry(key.bytes().to_vec())
+            .or_insert_with(|| MapChange {
+                key,
+                old: None,
+                new: None,
+            })
+            .new = Some(value);
+    });
+
+    changes.extend(
+        leaves
+            .into_values()
+            .filter(|change| change.old != change.new),
+    );
+}
+
+fn collect<D: SupportedDigest>(
+    node: &Node<D>,
+    depth: usize,
+    key: &mut [u8],
+    leaf: &mut impl FnMut(Hash<D>, Hash<D>),
+) {
+    match node {
+        Node::Leaf(value) => {
+            if let Ok(hash) = Hash::try_from(key.to_vec()) {
+                leaf(hash, value.clone());
+            }
+        }
+        Node::Singleton(singleton) => leaf(singleton.key.clone(), singleton.value.clone()),
+        Node::Fork(fork) => {
+            for side in [Side::Left, Side::Right] {
+                set_bit(key, depth, side);
+                collect(fork[side].node(), depth + 1, key, leaf);
+            }
+        }
+        Node::Empty(_) => {}
+    }
+}
+
+fn set_bit(key: &mut [u8], at: usize, side: Side) {
+    let mask = 1 << (7 - at % 8);
+    match side {
+        Side::Left => key[at / 8] &= !mask,
+        Side::Right => key[at / 8] |= mask,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Map;
+    use warg_crypto::hash::{Hash, Sha256};
+
+    #[test]
+    fn test_diff() {
+        let first = Map::<Sha256, &'static str, &'static str>::default();
+        let second = first.extend([("foo", "bar"), ("baz", "bat")]);
+        let third = second.extend([("foo", "qux"), ("baz", "bat"), ("new", "value")]);
+
+        assert!(second.diff(&second).is_empty());
+
+        let changes = second.diff(&third);
+        assert_eq!(changes.len(), 2);
+        let foo = changes.iter().find(|c| c.key == Hash::of("foo")).unwrap();
+        assert_eq!(foo.old, Some(Hash::of((0b0, "bar"))));
+        assert_eq!(foo.new, Some(Hash::of((0b0, "qux"))));
+        let new = changes.iter().find(|c| c.key == Hash::of("new")).unwrap();
+        assert_eq!(new.old, None);
+        assert_eq!(new.new, Some(Hash::of((0b0, "value"))));
+
+        // Every key of a map is a change from the empty map
+        let changes = first.diff(&third);
+        assert_eq!(changes.len(), 3);
+        assert!(changes
+            .windows(2)
+            .all(|w| w[0].key.bytes() < w[1].key.bytes()));
+        assert!(changes.iter().all(|c| c.old.is_none()));
+    }
+
+    #[test]
+    fn test_diff_many() {
+        let before = Map::<Sha256, u8, u8>::default().extend((0..200).map(|i| (i, 0)));
+        let after = before.extend((190..=255).step_by(5).map(|i| (i, 1)));
+
+        let changes = before.diff(&after);
+        let expected = (190..=255).step_by(5).collect::<Vec<u8>>();
+        assert_eq!(changes.len(), expected.len());
+        for i in expected {
+            let change = changes.iter().find(|c| c.key == Hash::of(i)).unwrap();
+            assert_eq!(change.old.is_some(), i < 200);
+            assert_eq!(change.new, Some(Hash::of((0b0, 1u8))));
+        }
+    }
+}
@@ -4,6 +4,7 @@ use std::marker::PhantomData;
 use warg_crypto::hash::{Hash, SupportedDigest};
 use warg_crypto::VisitBytes;
 
+use super::diff::{self, MapChange};
 use super::link::Link;
 use super::node::Node;
 use super::path::Path;
@@ -164,7 +165,7 @@ where
 impl<D, K, V> Map<D, K, V>
 where
     D: SupportedDigest,
-    K: VisitBytes + Clone + ?Sized,
+    K: VisitBytes + Clone,
     V: VisitBytes + Clone,
 {
     /// The hash of the root of the tree.
@@ -190,6 +191,13 @@ where {
         self.link.node().prove(Path::new(&Hash::of(key)))
     }
 
+    /// Gets the keys whose values differ between this map and a later one.
+    ///
+    /// The changes are ordered by key hash.
+    pub fn diff(&self, later: &Self) -> Vec<MapChange<D>> {
+        diff::diff(&self.link, &later.link)
+    }
+
     /// Insert a value into the map, creating a new map.
     ///
     /// This replaces any existing items with the same key.
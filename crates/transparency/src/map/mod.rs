@@ -10,6 +10,7 @@
 
 #![allow(clippy::module_inception)]
 
+mod diff;
 mod fork;
 mod link;
 mod map;
@@ -19,6 +20,7 @@ mod proof;
 mod proof_bundle;
 mod singleton;
 
+pub use diff::MapChange;
 pub use map::Map;
 pub use proof::Proof;
 pub use proof_bundle::ProofBundle as MapProofBundle;
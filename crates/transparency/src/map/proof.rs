@@ -41,6 +41,17 @@ where
     pub peers: Vec<Option<Hash<D>>>,
 }
 
+impl<D, K, V> Clone for Proof<D, K, V>
+where
+    D: SupportedDigest,
+    K: VisitBytes,
+    V: VisitBytes,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.peers.clone())
+    }
+}
+
 impl<D, K, V> Proof<D, K, V>
 where
     D: SupportedDigest,
@@ -112,7 +123,7 @@ mod tests {
         let b = a.insert("foo", b"bar");
         let c = b.insert("baz", b"bat");
 
-        let root = c.root().clone();
+        let root = *c.root();
 
         let p = c.prove("baz").unwrap();
 
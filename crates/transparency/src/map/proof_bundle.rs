@@ -7,7 +7,11 @@ use warg_crypto::{
 };
 use warg_protobuf::transparency as protobuf;
 
-use crate::map::proof::Proof;
+use crate::{
+    limits::{LimitError, Limits},
+    map::proof::Proof,
+    proto_len::delimited_len,
+};
 
 /// A collection of inclusion proof info
 pub struct ProofBundle<D, K, V>
@@ -35,6 +39,28 @@ where
         self.proofs
     }
 
+    /// Gets the length of the protobuf encoding of the bundle without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        self.proofs
+            .iter()
+            .map(|proof| {
+                let len = proof
+                    .peers
+                    .iter()
+                    .map(|peer| {
+                        let len = peer
+                            .as_ref()
+                            .map(|hash| delimited_len(1, hash.bytes().len()))
+                            .unwrap_or_default();
+                        delimited_len(1, len)
+                    })
+                    .sum();
+                delimited_len(1, len)
+            })
+            .sum()
+    }
+
     /// Turn a bundle into bytes using protobuf
     pub fn encode(self) -> Vec<u8> {
         let proto: protobuf::MapProofBundle = self.into();
@@ -43,12 +69,71 @@ where
 
     /// Parse a bundle from bytes using protobuf
     pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode_with_limits(bytes, &Limits::for_digest::<D>())
+    }
+
+    /// Parse a bundle from bytes using protobuf, checking it against the
+    /// given limits.
+    ///
+    /// A bundle exceeding the limits fails with a [`LimitError`].
+    pub fn decode_with_limits(bytes: &[u8], limits: &Limits) -> Result<Self, Error> {
         let proto = protobuf::MapProofBundle::decode(bytes)?;
-        let bundle = proto.try_into()?;
-        Ok(bundle)
+        let mut proofs = Vec::new();
+        for entry in proto.proofs {
+            proofs.push(Proof::from_protobuf(entry, limits)?);
+        }
+        Ok(ProofBundle { proofs })
+    }
+
+    /// Turn a bundle into bytes using CBOR
+    ///
+    /// Each proof is encoded as an array of its peer hashes, with `null`
+    /// for empty subtrees.
+    #[cfg(feature = "cbor")]
+    pub fn encode_cbor(self) -> Result<Vec<u8>, Error> {
+        let proto: protobuf::MapProofBundle = self.into();
+        let cbor = CborProofBundle {
+            proofs: proto
+                .proofs
+                .into_iter()
+                .map(|proof| proof.hashes.into_iter().map(|h| h.hash).collect())
+                .collect(),
+        };
+
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&cbor, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Parse a bundle from bytes using CBOR
+    #[cfg(feature = "cbor")]
+    pub fn decode_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        let cbor: CborProofBundle = ciborium::from_reader(bytes)?;
+        protobuf::MapProofBundle {
+            proofs: cbor
+                .proofs
+                .into_iter()
+                .map(|hashes| protobuf::MapInclusionProof {
+                    hashes: hashes
+                        .into_iter()
+                        .map(|hash| protobuf::OptionalHash { hash })
+                        .collect(),
+                })
+                .collect(),
+        }
+        .try_into()
     }
 }
 
+/// The CBOR representation of a map proof bundle.
+#[cfg(feature = "cbor")]
+#[serde_with::serde_as]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CborProofBundle {
+    #[serde_as(as = "Vec<Vec<Option<serde_with::Bytes>>>")]
+    proofs: Vec<Vec<Option<Vec<u8>>>>,
+}
+
 impl<D, K, V> From<ProofBundle<D, K, V>> for protobuf::MapProofBundle
 where
     D: SupportedDigest,
@@ -84,9 +169,10 @@ where
     type Error = Error;
 
     fn try_from(value: protobuf::MapProofBundle) -> Result<Self, Self::Error> {
+        let limits = Limits::for_digest::<D>();
         let mut proofs = Vec::new();
         for entry in value.proofs {
-            proofs.push(entry.try_into()?);
+            proofs.push(Proof::from_protobuf(entry, &limits)?);
         }
         let bundle = ProofBundle { proofs };
         Ok(bundle)
@@ -102,9 +188,104 @@ where
     type Error = Error;
 
     fn try_from(value: protobuf::MapInclusionProof) -> Result<Self, Self::Error> {
+        Self::from_protobuf(value, &Limits::for_digest::<D>())
+    }
+}
+
+impl<D, K, V> Proof<D, K, V>
+where
+    D: SupportedDigest,
+    K: VisitBytes,
+    V: VisitBytes,
+{
+    fn from_protobuf(value: protobuf::MapInclusionProof, limits: &Limits) -> Result<Self, Error> {
+        let max = limits.max_map_proof_len::<D>();
+        if value.hashes.len() > max {
+            return Err(LimitError::ProofTooLong {
+                len: value.hashes.len(),
+                max,
+            }
+            .into());
+        }
+
         let peers: Result<Vec<Option<Hash<D>>>, Error> =
             value.hashes.into_iter().map(|h| h.try_into()).collect();
         let proof = Proof::new(peers?);
         Ok(proof)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use warg_crypto::hash::Sha256;
+
+    #[test]
+    fn test_encoded_len() {
+        let map = Map::<Sha256, &str, &str>::default()
+            .insert("foo", "bar")
+            .insert("baz", "bat");
+
+        let empty = ProofBundle::<Sha256, &str, &str>::bundle(Vec::new());
+        assert_eq!(empty.encoded_len(), 0);
+
+        let bundle =
+            ProofBundle::bundle(vec![map.prove("foo").unwrap(), map.prove("baz").unwrap()]);
+        assert_eq!(bundle.encoded_len(), bundle.encode().len());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let map = Map::<Sha256, &str, &str>::default()
+            .insert("foo", "bar")
+            .insert("baz", "bat");
+        let bytes = ProofBundle::bundle(vec![map.prove("foo").unwrap()]).encode();
+
+        let limits = Limits {
+            max_map_depth: 0,
+            ..Limits::for_digest::<Sha256>()
+        };
+        let err = ProofBundle::<Sha256, &str, &str>::decode_with_limits(&bytes, &limits)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<LimitError>(),
+            Some(LimitError::ProofTooLong { max: 0, .. })
+        ));
+
+        // A proof can never be deeper than the digest
+        let mut proto = protobuf::MapProofBundle::decode(bytes.as_slice()).unwrap();
+        proto.proofs[0]
+            .hashes
+            .resize(257, protobuf::OptionalHash { hash: None });
+        let err = ProofBundle::<Sha256, &str, &str>::decode(&proto.encode_to_vec())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::ProofTooLong { len: 257, max: 256 })
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let map = Map::<Sha256, &str, &str>::default()
+            .insert("foo", "bar")
+            .insert("baz", "bat");
+
+        let bundle =
+            || ProofBundle::bundle(vec![map.prove("foo").unwrap(), map.prove("baz").unwrap()]);
+
+        let cbor = bundle().encode_cbor().unwrap();
+        let decoded = ProofBundle::<Sha256, &str, &str>::decode_cbor(&cbor).unwrap();
+        assert_eq!(decoded.encode(), bundle().encode());
+
+        let proof = ProofBundle::<Sha256, &str, &str>::decode_cbor(&cbor)
+            .unwrap()
+            .unbundle()
+            .remove(0);
+        assert_eq!(&proof.evaluate(&"foo", &"bar"), map.root());
+    }
+}
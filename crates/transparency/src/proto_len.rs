@@ -0,0 +1,16 @@
+//! Helpers for computing the lengths of protobuf encodings.
+
+use prost::encoding;
+
+/// Gets the encoded length of a length-delimited field.
+pub(crate) fn delimited_len(tag: u32, len: usize) -> usize {
+    encoding::key_len(tag) + encoding::encoded_len_varint(len as u64) + len
+}
+
+/// Gets the encoded length of a packed repeated `uint32` field.
+pub(crate) fn packed_len(tag: u32, values: impl Iterator<Item = u32>) -> usize {
+    match values.map(|v| encoding::encoded_len_varint(v.into())).sum() {
+        0 => 0,
+        len => delimited_len(tag, len),
+    }
+}
@@ -1,10 +1,16 @@
 use prost::Message;
 
+/// The vendored past revisions of the `warg.protocol` schema, oldest first.
+///
+/// Each is kept in `warg/protocol/revisions/<revision>/warg.proto`.
+const PROTOCOL_REVISIONS: &[&str] = &["0"];
+
 fn main() -> anyhow::Result<()> {
     let proto_files = &[
         "warg/protocol/warg.proto",
         "warg/transparency/proofs.proto",
         "warg/internal/internal.proto",
+        "warg/api/api.proto",
     ];
 
     // Tell cargo to recompile if any of these proto files are changed
@@ -20,6 +26,32 @@ fn main() -> anyhow::Result<()> {
 
     let file_descriptor_set_bytes = file_descriptor_set.encode_to_vec();
 
+    // Emit descriptors of the current and vendored revisions of the protocol schema
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+    let revisions = PROTOCOL_REVISIONS
+        .iter()
+        .map(|revision| {
+            (
+                revision.to_string(),
+                format!("warg/protocol/revisions/{revision}/warg.proto"),
+            )
+        })
+        .chain([(
+            "current".to_string(),
+            "warg/protocol/warg.proto".to_string(),
+        )]);
+    for (revision, proto_file) in revisions {
+        println!("cargo:rerun-if-changed={proto_file}");
+        let descriptors = protox::Compiler::new(["."])?
+            .include_imports(true)
+            .open_file(&proto_file)?
+            .file_descriptor_set();
+        std::fs::write(
+            out_dir.join(format!("warg.protocol.{revision}.bin")),
+            descriptors.encode_to_vec(),
+        )?;
+    }
+
     prost_build::Config::new()
         // Override prost-types with pbjson-types
         .compile_well_known_types()
@@ -28,7 +60,12 @@ fn main() -> anyhow::Result<()> {
 
     pbjson_build::Builder::new()
         .register_descriptors(&file_descriptor_set_bytes)?
-        .build(&[".warg.protocol", ".warg.transparency", ".warg.internal"])?;
+        .build(&[
+            ".warg.protocol",
+            ".warg.transparency",
+            ".warg.internal",
+            ".warg.api",
+        ])?;
 
     Ok(())
 }
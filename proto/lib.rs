@@ -48,3 +48,25 @@ pub mod internal {
     // Generated by [`pbjson-build`]
     include!(concat!(env!("OUT_DIR"), "/warg.internal.serde.rs"));
 }
+
+pub mod api {
+    // Generated by [`prost-build`]
+    include!(concat!(env!("OUT_DIR"), "/warg.api.rs"));
+    // Generated by [`pbjson-build`]
+    include!(concat!(env!("OUT_DIR"), "/warg.api.serde.rs"));
+}
+
+pub mod revisions {
+    //! Encoded `FileDescriptorSet`s of the revisions of the `warg.protocol`
+    //! schema.
+
+    /// The vendored past revisions of the schema, oldest first.
+    pub const PROTOCOL: &[&[u8]] = &[include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/warg.protocol.0.bin"
+    ))];
+
+    /// The current revision of the schema.
+    pub const CURRENT_PROTOCOL: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/warg.protocol.current.bin"));
+}
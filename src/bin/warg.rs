@@ -3,9 +3,9 @@ use clap::Parser;
 use std::process::exit;
 use tracing_subscriber::EnvFilter;
 use warg_cli::commands::{
-    BundleCommand, ClearCommand, ConfigCommand, DependenciesCommand, DownloadCommand, InfoCommand,
-    KeyCommand, LockCommand, LoginCommand, LogoutCommand, PublishCommand, ResetCommand,
-    UpdateCommand,
+    AdminCommand, BundleCommand, ClearCommand, ConfigCommand, DependenciesCommand, DownloadCommand,
+    InfoCommand, KeyCommand, LockCommand, LoginCommand, LogoutCommand, PublishCommand,
+    ResetCommand, SbomCommand, UpdateCommand,
 };
 use warg_client::ClientError;
 
@@ -28,11 +28,14 @@ enum WargCli {
     Key(KeyCommand),
     Lock(LockCommand),
     Bundle(BundleCommand),
+    Sbom(SbomCommand),
     Dependencies(DependenciesCommand),
     Download(DownloadCommand),
     Update(UpdateCommand),
     #[clap(subcommand)]
     Publish(PublishCommand),
+    #[clap(subcommand)]
+    Admin(AdminCommand),
     Reset(ResetCommand),
     Clear(ClearCommand),
     Login(LoginCommand),
@@ -51,10 +54,12 @@ async fn main() -> Result<()> {
         WargCli::Key(cmd) => cmd.exec().await,
         WargCli::Lock(cmd) => cmd.exec().await,
         WargCli::Bundle(cmd) => cmd.exec().await,
+        WargCli::Sbom(cmd) => cmd.exec().await,
         WargCli::Dependencies(cmd) => cmd.exec().await,
         WargCli::Download(cmd) => cmd.exec().await,
         WargCli::Update(cmd) => cmd.exec().await,
         WargCli::Publish(cmd) => cmd.exec().await,
+        WargCli::Admin(cmd) => cmd.exec().await,
         WargCli::Reset(cmd) => cmd.exec().await,
         WargCli::Clear(cmd) => cmd.exec().await,
         WargCli::Login(cmd) => cmd.exec().await,
@@ -139,6 +144,14 @@ This may be expected behavior for registries that offer key management."
         ClientError::PublishRejected { name, reason, .. } => {
             eprintln!("Package `{name}` publish rejected: {reason}")
         }
+        ClientError::DuplicateRelease {
+            name,
+            version,
+            released_by,
+            ..
+        } => {
+            eprintln!("Package `{name}` publish rejected: version `{version}` was already released by record `{released_by}`")
+        }
         ClientError::ConflictPendingPublish {
             name,
             pending_record_id,
@@ -8,6 +8,7 @@ use warg_client::storage::RegistryDomain;
 use warg_client::{ClientError, Config, FileSystemClient, StorageLockResult};
 use warg_crypto::signing::PrivateKey;
 
+mod admin;
 mod bundle;
 mod clear;
 mod config;
@@ -20,8 +21,10 @@ mod login;
 mod logout;
 mod publish;
 mod reset;
+mod sbom;
 mod update;
 
+pub use self::admin::*;
 pub use self::bundle::*;
 pub use self::clear::*;
 pub use self::config::*;
@@ -34,6 +37,7 @@ pub use self::login::*;
 pub use self::logout::*;
 pub use self::publish::*;
 pub use self::reset::*;
+pub use self::sbom::*;
 pub use self::update::*;
 
 /// Common options for commands.
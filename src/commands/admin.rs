@@ -0,0 +1,94 @@
+use super::CommonOptions;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use warg_crypto::signing::PublicKey;
+use warg_protocol::{operator, registry::PackageName};
+
+/// Issue administrative commands to a warg registry.
+///
+/// Commands are signed with the configured signing key, which must have the
+/// admin permission in the registry's operator log.
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// Freeze a package, rejecting new records for it.
+    Freeze(AdminPackageCommand),
+    /// Unfreeze a frozen package.
+    Unfreeze(AdminPackageCommand),
+    /// Force the registry to re-validate a package log.
+    Revalidate(AdminPackageCommand),
+    /// Remove content no longer referenced by any record.
+    Gc(AdminGcCommand),
+    /// Rotate the key the registry signs checkpoints with.
+    RotateKey(AdminRotateKeyCommand),
+}
+
+impl AdminCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let (common, command) = match self {
+            Self::Freeze(cmd) => (
+                cmd.common,
+                operator::AdminCommand::FreezePackage { name: cmd.name },
+            ),
+            Self::Unfreeze(cmd) => (
+                cmd.common,
+                operator::AdminCommand::UnfreezePackage { name: cmd.name },
+            ),
+            Self::Revalidate(cmd) => (
+                cmd.common,
+                operator::AdminCommand::RevalidatePackage { name: cmd.name },
+            ),
+            Self::Gc(cmd) => (cmd.common, operator::AdminCommand::CollectGarbage),
+            Self::RotateKey(cmd) => (
+                cmd.common,
+                operator::AdminCommand::RotateCheckpointKey { key: cmd.key },
+            ),
+        };
+
+        let config = common.read_config()?;
+        let client = common.create_client(&config)?;
+        let signing_key = common.signing_key(None).await?;
+
+        let response = client.admin(&signing_key, [command]).await?;
+        for result in response.results {
+            println!("{result}");
+        }
+        println!(
+            "recorded in operator record `{record_id}`",
+            record_id = response.record_id
+        );
+
+        Ok(())
+    }
+}
+
+/// Issue an administrative command for a package.
+#[derive(Args)]
+pub struct AdminPackageCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package name.
+    #[clap(value_name = "PACKAGE")]
+    pub name: PackageName,
+}
+
+/// Remove content no longer referenced by any record.
+#[derive(Args)]
+pub struct AdminGcCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+}
+
+/// Rotate the key the registry signs checkpoints with.
+#[derive(Args)]
+pub struct AdminRotateKeyCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The public key to sign checkpoints with, which the registry must be
+    /// configured with as its next operator key.
+    #[clap(value_name = "PUBLIC_KEY")]
+    pub key: PublicKey,
+}
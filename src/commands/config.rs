@@ -2,7 +2,7 @@ use super::CommonOptions;
 use anyhow::{bail, Context, Result};
 use clap::Args;
 use std::path::PathBuf;
-use warg_client::{Config, RegistryUrl};
+use warg_client::{storage::StorageLayout, Config, RegistryUrl};
 
 /// Creates a new warg configuration file.
 #[derive(Args)]
@@ -27,6 +27,11 @@ pub struct ConfigCommand {
     #[clap(long)]
     pub auto_accept_federation_hints: bool,
 
+    /// The layout of the files in the registries and content directories
+    /// (`flat`, `hash-prefix` or `date`).
+    #[clap(long, value_name = "LAYOUT", default_value = "flat")]
+    pub storage_layout: StorageLayout,
+
     /// Overwrite the existing configuration file.
     #[clap(long)]
     pub overwrite: bool,
@@ -81,6 +86,7 @@ impl ConfigCommand {
             ignore_federation_hints: self.ignore_federation_hints,
             auto_accept_federation_hints: self.auto_accept_federation_hints,
             disable_interactive: false,
+            storage_layout: self.storage_layout,
         };
 
         config.write_to_file(&path)?;
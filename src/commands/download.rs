@@ -19,6 +19,9 @@ pub struct DownloadCommand {
     /// The version requirement of the package to download; defaults to `*`.
     #[clap(long, short, value_name = "VERSION")]
     pub version: Option<String>,
+    /// The channel tag of the package to download, such as `latest`.
+    #[clap(long, short, value_name = "TAG", conflicts_with = "version")]
+    pub tag: Option<String>,
     /// The output path for the file. If not specified, just downloads to local cache.
     #[clap(long, short = 'o')]
     pub output: Option<PathBuf>,
@@ -32,19 +35,29 @@ impl DownloadCommand {
 
         println!("downloading package `{name}`...", name = self.name);
 
-        // if user specifies exact verion, then set the `VersionReq` to exact match
-        let version = match &self.version {
-            Some(version) => VersionReq::parse(&format!("={}", version))?,
-            None => VersionReq::STAR,
-        };
+        let download = match &self.tag {
+            Some(tag) => client.download_tag(&self.name, tag).await?.ok_or_else(|| {
+                ClientError::PackageTagDoesNotExist {
+                    tag: tag.clone(),
+                    name: self.name.clone(),
+                }
+            })?,
+            None => {
+                // if user specifies exact verion, then set the `VersionReq` to exact match
+                let version = match &self.version {
+                    Some(version) => VersionReq::parse(&format!("={}", version))?,
+                    None => VersionReq::STAR,
+                };
 
-        let download = client
-            .download(&self.name, &version)
-            .await?
-            .ok_or_else(|| ClientError::PackageVersionRequirementDoesNotExist {
-                name: self.name.clone(),
-                version,
-            })?;
+                client
+                    .download(&self.name, &version)
+                    .await?
+                    .ok_or_else(|| ClientError::PackageVersionRequirementDoesNotExist {
+                        name: self.name.clone(),
+                        version,
+                    })?
+            }
+        };
 
         println!(
             "Downloaded version {version} of package `{name}` ({digest}) to local cache",
@@ -83,7 +96,13 @@ impl DownloadCommand {
                 }
             })
         {
-            std::fs::copy(download.path, &path)?;
+            if download.encryption.is_some() {
+                let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
+                let signing_key = self.common.signing_key(registry_domain.as_ref()).await?;
+                std::fs::write(&path, download.decrypt(&signing_key).await?)?;
+            } else {
+                std::fs::copy(download.path, &path)?;
+            }
             println!(
                 "Wrote `{name}` to {path}",
                 name = self.name,
@@ -39,6 +39,25 @@ impl InfoCommand {
                     println!("registry: {registry}");
                 }
                 Self::print_package_info(&info);
+                for release in info.state.releases() {
+                    let identities = client
+                        .identities_for(&package, &release.released_by)
+                        .await?;
+                    if !identities.is_empty() {
+                        println!(
+                            "  version {version} was released by {identities} (verified)",
+                            version = release.version,
+                            identities = identities.join(", ")
+                        );
+                    }
+                }
+                for release in client.compromised_releases(&info).await? {
+                    println!(
+                        "  warning: version {version} was released by compromised key {key_id}",
+                        version = release.version,
+                        key_id = release.by
+                    );
+                }
             }
             None => {
                 client
@@ -66,7 +85,7 @@ impl InfoCommand {
         println!("  name: {name}", name = info.name);
         println!("  versions:");
         info.state.releases().for_each(|r| {
-            if let Some(content) = r.content() {
+            if let (false, Some(content)) = (r.yanked(), &r.content) {
                 Self::print_release(&r.version, content);
             }
         });
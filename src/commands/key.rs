@@ -3,11 +3,14 @@ use clap::{Args, Subcommand};
 use dialoguer::{theme::ColorfulTheme, Confirm, Password};
 use p256::ecdsa::SigningKey;
 use rand_core::OsRng;
+use secrecy::zeroize::Zeroizing;
+use std::{path::PathBuf, time::SystemTime};
 use warg_client::{
     keyring::{delete_signing_key, get_signing_key, set_signing_key},
     Config,
 };
-use warg_crypto::signing::PrivateKey;
+use warg_crypto::signing::{EncryptedPrivateKey, PrivateKey};
+use warg_protocol::{package::Permission, registry::PackageName, KeyRequest};
 
 use super::CommonOptions;
 
@@ -27,6 +30,8 @@ impl KeyCommand {
             KeySubcommand::Info(cmd) => cmd.exec().await,
             KeySubcommand::Set(cmd) => cmd.exec().await,
             KeySubcommand::Delete(cmd) => cmd.exec().await,
+            KeySubcommand::Request(cmd) => cmd.exec().await,
+            KeySubcommand::Export(cmd) => cmd.exec().await,
         }
     }
 }
@@ -42,6 +47,10 @@ pub enum KeySubcommand {
     Set(KeySetCommand),
     /// Deletes the signing key for a registry from the local keyring.
     Delete(KeyDeleteCommand),
+    /// Exports a request for permissions to a package for the signing key.
+    Request(KeyRequestCommand),
+    /// Exports the signing key for a registry to a passphrase-encrypted file.
+    Export(KeyExportCommand),
 }
 
 /// Creates a new signing key for a registry in the local keyring.
@@ -82,6 +91,10 @@ pub struct KeyInfoCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
+
+    /// Print the public key as a PEM-encoded SubjectPublicKeyInfo.
+    #[clap(long)]
+    pub pem: bool,
 }
 
 impl KeyInfoCommand {
@@ -94,6 +107,10 @@ impl KeyInfoCommand {
             config.home_url.as_deref(),
         )?;
         let public_key = private_key.public_key();
+        if self.pem {
+            print!("{}", public_key.to_spki_pem());
+            return Ok(());
+        }
         println!("Key ID: {}", public_key.fingerprint());
         println!("Public Key: {public_key}");
         Ok(())
@@ -106,17 +123,65 @@ pub struct KeySetCommand {
     /// The common command options.
     #[clap(flatten)]
     pub common: CommonOptions,
+
+    /// Read the signing key from a PEM-encoded, unencrypted PKCS#8 file,
+    /// such as one generated by `openssl genpkey`, instead of prompting.
+    #[clap(long, value_name = "FILE", conflicts_with = "encrypted")]
+    pub pem: Option<PathBuf>,
+
+    /// Read the signing key from a passphrase-encrypted file written by
+    /// `warg key export`, instead of prompting.
+    #[clap(long, value_name = "FILE")]
+    pub encrypted: Option<PathBuf>,
 }
 
 impl KeySetCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
-        let key_str = Password::with_theme(&ColorfulTheme::default())
-            .with_prompt("input signing key (expected format is `<alg>:<base64>`): ")
-            .interact()
-            .context("failed to read signing key")?;
-        let key =
-            PrivateKey::decode(key_str).context("signing key is not in the correct format")?;
+        let key = match (&self.pem, &self.encrypted) {
+            (_, Some(path)) => {
+                let encrypted: EncryptedPrivateKey =
+                    serde_json::from_str(&std::fs::read_to_string(path).with_context(|| {
+                        format!("failed to read signing key `{path}`", path = path.display())
+                    })?)
+                    .with_context(|| {
+                        format!(
+                            "signing key `{path}` is not an encrypted key",
+                            path = path.display()
+                        )
+                    })?;
+                let passphrase = Zeroizing::new(
+                    Password::with_theme(&ColorfulTheme::default())
+                        .with_prompt("passphrase of the signing key")
+                        .interact()
+                        .context("failed to read passphrase")?,
+                );
+                PrivateKey::decrypt(&encrypted, &passphrase).with_context(|| {
+                    format!(
+                        "failed to decrypt signing key `{path}`",
+                        path = path.display()
+                    )
+                })?
+            }
+            (Some(path), None) => {
+                let pem = Zeroizing::new(std::fs::read_to_string(path).with_context(|| {
+                    format!("failed to read signing key `{path}`", path = path.display())
+                })?);
+                PrivateKey::from_pkcs8_pem(&pem).with_context(|| {
+                    format!(
+                        "signing key `{path}` is not a supported PKCS#8 key",
+                        path = path.display()
+                    )
+                })?
+            }
+            (None, None) => {
+                let key_str = Password::with_theme(&ColorfulTheme::default())
+                    .with_prompt("input signing key (expected format is `<alg>:<base64>`): ")
+                    .interact()
+                    .context("failed to read signing key")?;
+                PrivateKey::decode(key_str).context("signing key is not in the correct format")?
+            }
+        };
         let config = &mut self.common.read_config()?;
 
         set_signing_key(
@@ -175,3 +240,116 @@ impl KeyDeleteCommand {
         Ok(())
     }
 }
+
+/// Exports a request for permissions to a package for the signing key.
+///
+/// The request is signed by the key, and is sent to an owner of the package
+/// to grant with `warg publish grant --request`.
+#[derive(Args)]
+pub struct KeyRequestCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package to request permissions to.
+    #[clap(long, short, value_name = "PACKAGE")]
+    pub name: PackageName,
+    /// The permission(s) to request.
+    #[clap(
+        long = "permission",
+        value_delimiter = ',',
+        default_value = "release,yank"
+    )]
+    pub permissions: Vec<Permission>,
+    /// The file to write the request to; defaults to standard output.
+    #[clap(long, short, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+impl KeyRequestCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = &self.common.read_config()?;
+        let private_key = get_signing_key(
+            self.common.registry.as_deref(),
+            &config.keys,
+            config.home_url.as_deref(),
+        )?;
+        let public_key = private_key.public_key();
+
+        let request = KeyRequest::new(
+            self.name.clone(),
+            public_key.clone(),
+            self.permissions.iter().copied(),
+            SystemTime::now(),
+        )?
+        .sign(&private_key)?;
+        let json = serde_json::to_string_pretty(&request)?;
+
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, json + "\n").with_context(|| {
+                    format!(
+                        "failed to write key request `{path}`",
+                        path = path.display()
+                    )
+                })?;
+                println!(
+                    "wrote request for key ID `{key_id}` to `{path}`",
+                    key_id = public_key.fingerprint(),
+                    path = path.display()
+                );
+            }
+            None => println!("{json}"),
+        }
+
+        Ok(())
+    }
+}
+
+/// Exports the signing key for a registry to a passphrase-encrypted file.
+///
+/// The file may be imported again with `warg key set --encrypted`.
+#[derive(Args)]
+pub struct KeyExportCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The file to write the encrypted key to.
+    #[clap(long, short, value_name = "FILE")]
+    pub output: PathBuf,
+}
+
+impl KeyExportCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = &self.common.read_config()?;
+        let private_key = get_signing_key(
+            self.common.registry.as_deref(),
+            &config.keys,
+            config.home_url.as_deref(),
+        )?;
+
+        let passphrase = Zeroizing::new(
+            Password::with_theme(&ColorfulTheme::default())
+                .with_prompt("passphrase to encrypt the signing key with")
+                .with_confirmation("confirm passphrase", "the passphrases do not match")
+                .interact()
+                .context("failed to read passphrase")?,
+        );
+        let json = serde_json::to_string_pretty(&private_key.encrypt(&passphrase))?;
+        std::fs::write(&self.output, json + "\n").with_context(|| {
+            format!(
+                "failed to write signing key `{path}`",
+                path = self.output.display()
+            )
+        })?;
+
+        println!(
+            "wrote encrypted signing key with key ID `{key_id}` to `{path}`",
+            key_id = private_key.public_key().fingerprint(),
+            path = self.output.display()
+        );
+
+        Ok(())
+    }
+}
@@ -1,9 +1,16 @@
 use super::CommonOptions;
 use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
 use clap::{Args, Subcommand};
+use dialoguer::{theme::ColorfulTheme, Confirm};
 use futures::TryStreamExt;
 use itertools::Itertools;
-use std::{future::Future, path::PathBuf, time::Duration};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 use tokio::io::BufReader;
 use tokio_util::io::ReaderStream;
 use warg_client::{
@@ -11,13 +18,14 @@ use warg_client::{
     FileSystemClient,
 };
 use warg_crypto::{
+    encryption::ContentEncryption,
     hash::AnyHash,
     signing::{KeyID, PublicKey},
 };
 use warg_protocol::{
-    package::Permission,
+    package::{Permission, PublishToken, ReleaseArtifact, ReleaseManifest},
     registry::{PackageName, RecordId},
-    Version,
+    KeyRequest, SerdeEnvelope, Version, VersionReq,
 };
 
 const DEFAULT_WAIT_INTERVAL: Duration = Duration::from_secs(1);
@@ -56,6 +64,32 @@ where
     }
 }
 
+/// Reads a key request file and verifies that it is signed by the requested
+/// key and is for the given package.
+fn read_key_request(path: &Path, name: &PackageName) -> Result<KeyRequest> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read key request `{path}`", path = path.display()))?;
+    let request: SerdeEnvelope<KeyRequest> =
+        serde_json::from_str(&contents).with_context(|| {
+            format!(
+                "failed to parse key request `{path}`",
+                path = path.display()
+            )
+        })?;
+    KeyRequest::verify_signed(&request)?;
+
+    let request = request.into_contents();
+    if &request.package != name {
+        bail!(
+            "key request `{path}` is for package `{requested}`, not `{name}`",
+            path = path.display(),
+            requested = request.package
+        );
+    }
+
+    Ok(request)
+}
+
 /// Publish a package to a warg registry.
 #[derive(Subcommand)]
 pub enum PublishCommand {
@@ -65,10 +99,14 @@ pub enum PublishCommand {
     Release(PublishReleaseCommand),
     /// Yank a package version.
     Yank(PublishYankCommand),
+    /// Point a channel tag at a package version.
+    Tag(PublishTagCommand),
     /// Grant permissions for the package.
     Grant(PublishGrantCommand),
     /// Revoke permissions for the package.
     Revoke(PublishRevokeCommand),
+    /// Issue a token authorizing another key to publish releases.
+    Token(PublishTokenCommand),
     /// Start a new pending publish.
     Start(PublishStartCommand),
     /// List the records in a pending publish.
@@ -88,8 +126,10 @@ impl PublishCommand {
             Self::Init(cmd) => cmd.exec().await,
             Self::Release(cmd) => cmd.exec().await,
             Self::Yank(cmd) => cmd.exec().await,
+            Self::Tag(cmd) => cmd.exec().await,
             Self::Grant(cmd) => cmd.exec().await,
             Self::Revoke(cmd) => cmd.exec().await,
+            Self::Token(cmd) => cmd.exec().await,
             Self::Start(cmd) => cmd.exec().await,
             Self::List(cmd) => cmd.exec().await,
             Self::Abort(cmd) => cmd.exec().await,
@@ -180,6 +220,24 @@ pub struct PublishReleaseCommand {
     /// The path to the package being published.
     #[clap(value_name = "PATH")]
     pub path: PathBuf,
+    /// Encrypt the package content for the given recipient public key.
+    ///
+    /// May be specified more than once; each recipient may decrypt the
+    /// content with their signing key.
+    #[clap(long = "recipient", value_name = "PUBLIC_KEY")]
+    pub recipients: Vec<PublicKey>,
+    /// Ship a named artifact alongside the package, as `NAME[:MEDIA_TYPE]=PATH`.
+    ///
+    /// May be specified more than once. Artifacts are never encrypted; the
+    /// media type defaults to `application/octet-stream`.
+    #[clap(long = "artifact", value_name = "ARTIFACT")]
+    pub artifacts: Vec<ArtifactArg>,
+    /// Publish under a token issued with `publish token`.
+    ///
+    /// The signing key need not have permission to release the package if
+    /// the token authorizes it.
+    #[clap(long, value_name = "TOKEN", env = "WARG_PUBLISH_TOKEN")]
+    pub publish_token: Option<String>,
     /// Whether to wait for the publish to complete.
     #[clap(long)]
     pub no_wait: bool,
@@ -189,13 +247,58 @@ impl PublishReleaseCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
         let config = self.common.read_config()?;
-        let client = self.common.create_client(&config)?;
+        let mut client = self.common.create_client(&config)?;
         let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
         let signing_key = self.common.signing_key(registry_domain.as_ref()).await?;
 
+        if let Some(token) = &self.publish_token {
+            if client.registry().load_publish().await?.is_some() {
+                bail!("a publish token cannot be used with a pending publish");
+            }
+
+            let token = token
+                .parse::<PublishToken>()
+                .context("invalid publish token")?;
+            client = client.with_publish_token(token);
+        }
+
         let path = self.path.clone();
         let version = self.version.clone();
+        let recipients = self.recipients.clone();
+        let artifacts = self.artifacts.clone();
         match enqueue(&client, &self.name, move |c| async move {
+            let mut manifest = None;
+            if !artifacts.is_empty() {
+                let mut stored = Vec::with_capacity(artifacts.len());
+                for artifact in artifacts {
+                    stored.push(artifact.store(c).await?);
+                }
+                manifest = Some(ReleaseManifest { artifacts: stored });
+            }
+
+            if !recipients.is_empty() {
+                let content = tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("failed to read `{path}`", path = path.display()))?;
+                let (ciphertext, encryption) = ContentEncryption::encrypt(&content, &recipients)?;
+                let content = c
+                    .content()
+                    .store_content(
+                        Box::pin(futures::stream::once(
+                            async move { Ok(Bytes::from(ciphertext)) },
+                        )),
+                        None,
+                    )
+                    .await?;
+
+                return Ok(PublishEntry::Release {
+                    version,
+                    content,
+                    encryption: Some(encryption),
+                    manifest,
+                });
+            }
+
             let content = c
                 .content()
                 .store_content(
@@ -211,7 +314,12 @@ impl PublishReleaseCommand {
                 )
                 .await?;
 
-            Ok(PublishEntry::Release { version, content })
+            Ok(PublishEntry::Release {
+                version,
+                content,
+                encryption: None,
+                manifest,
+            })
         })
         .await?
         {
@@ -254,6 +362,60 @@ impl PublishReleaseCommand {
     }
 }
 
+/// A named artifact to ship alongside a released package.
+#[derive(Clone, Debug)]
+pub struct ArtifactArg {
+    name: String,
+    media_type: String,
+    path: PathBuf,
+}
+
+impl ArtifactArg {
+    /// Stores the artifact in content storage, returning its manifest entry.
+    async fn store(self, client: &FileSystemClient) -> Result<ReleaseArtifact> {
+        let file = tokio::fs::File::open(&self.path)
+            .await
+            .with_context(|| format!("failed to open `{path}`", path = self.path.display()))?;
+        let size = file.metadata().await?.len();
+        let content = client
+            .content()
+            .store_content(
+                Box::pin(ReaderStream::new(BufReader::new(file)).map_err(|e| anyhow!(e))),
+                None,
+            )
+            .await?;
+
+        Ok(ReleaseArtifact {
+            name: self.name,
+            content,
+            size,
+            media_type: self.media_type,
+        })
+    }
+}
+
+impl FromStr for ArtifactArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, path) = s
+            .split_once('=')
+            .context("expected an artifact of the form `NAME[:MEDIA_TYPE]=PATH`")?;
+        let (name, media_type) = name
+            .split_once(':')
+            .unwrap_or((name, "application/octet-stream"));
+        if name.is_empty() {
+            bail!("artifact names cannot be empty");
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            media_type: media_type.to_string(),
+            path: path.into(),
+        })
+    }
+}
+
 /// Yank a package release from a warg registry.
 #[derive(Args)]
 #[clap(disable_version_flag = true)]
@@ -265,6 +427,89 @@ pub struct PublishYankCommand {
     #[clap(long, short, value_name = "PACKAGE")]
     pub name: PackageName,
     /// The version of the package being yanked.
+    #[clap(long, short, value_name = "VERSION", required_unless_present = "range")]
+    pub version: Option<Version>,
+    /// Yank every released version matching the range instead of a single version.
+    #[clap(long, value_name = "RANGE", conflicts_with = "version")]
+    pub range: Option<VersionReq>,
+    /// Whether to wait for the publish to complete.
+    #[clap(long)]
+    pub no_wait: bool,
+}
+
+impl PublishYankCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config)?;
+        let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
+        let signing_key = self.common.signing_key(registry_domain.as_ref()).await?;
+
+        let (entry, yanked) = match (self.version, self.range) {
+            (Some(version), _) => (
+                PublishEntry::Yank {
+                    version: version.clone(),
+                },
+                format!("version {version}"),
+            ),
+            (None, Some(range)) => (
+                PublishEntry::YankRange {
+                    range: range.clone(),
+                },
+                format!("versions matching `{range}`"),
+            ),
+            (None, None) => bail!("either a version or a range to yank is required"),
+        };
+
+        match enqueue(&client, &self.name, move |_| async move { Ok(entry) }).await? {
+            Some(entry) => {
+                let record_id = client
+                    .publish_with_info(
+                        &signing_key,
+                        PublishInfo {
+                            name: self.name.clone(),
+                            head: None,
+                            entries: vec![entry],
+                        },
+                    )
+                    .await?;
+
+                if self.no_wait {
+                    println!("submitted record `{record_id}` for publishing");
+                } else {
+                    client
+                        .wait_for_publish(&self.name, &record_id, DEFAULT_WAIT_INTERVAL)
+                        .await?;
+
+                    println!("yanked {yanked} of package `{name}`", name = self.name);
+                }
+            }
+            None => {
+                println!(
+                    "added yank of {yanked} for package `{name}` to pending publish",
+                    name = self.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Point a channel tag of a package at a release.
+#[derive(Args)]
+#[clap(disable_version_flag = true)]
+pub struct PublishTagCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package name being tagged.
+    #[clap(long, short, value_name = "PACKAGE")]
+    pub name: PackageName,
+    /// The name of the tag, such as `latest` or `stable`.
+    #[clap(long, short, value_name = "TAG")]
+    pub tag: String,
+    /// The version of the package the tag points at.
     #[clap(long, short, value_name = "VERSION")]
     pub version: Version,
     /// Whether to wait for the publish to complete.
@@ -272,7 +517,7 @@ pub struct PublishYankCommand {
     pub no_wait: bool,
 }
 
-impl PublishYankCommand {
+impl PublishTagCommand {
     /// Executes the command.
     pub async fn exec(self) -> Result<()> {
         let config = self.common.read_config()?;
@@ -280,9 +525,10 @@ impl PublishYankCommand {
         let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
         let signing_key = self.common.signing_key(registry_domain.as_ref()).await?;
 
+        let tag = self.tag.clone();
         let version = self.version.clone();
         match enqueue(&client, &self.name, move |_| async move {
-            Ok(PublishEntry::Yank { version })
+            Ok(PublishEntry::Tag { tag, version })
         })
         .await?
         {
@@ -306,15 +552,17 @@ impl PublishYankCommand {
                         .await?;
 
                     println!(
-                        "yanked version {version} of package `{name}`",
+                        "tagged version {version} of package `{name}` as `{tag}`",
                         version = self.version,
-                        name = self.name
+                        name = self.name,
+                        tag = self.tag
                     );
                 }
             }
             None => {
                 println!(
-                    "added yank of version {version} for package `{name}` to pending publish",
+                    "added tag `{tag}` of version {version} for package `{name}` to pending publish",
+                    tag = self.tag,
                     version = self.version,
                     name = self.name
                 );
@@ -336,8 +584,8 @@ pub struct PublishGrantCommand {
     #[clap(long, short, value_name = "PACKAGE")]
     pub name: PackageName,
     /// The public key to grant permissions to.
-    #[clap(value_name = "PUBLIC_KEY")]
-    pub public_key: PublicKey,
+    #[clap(value_name = "PUBLIC_KEY", required_unless_present = "request")]
+    pub public_key: Option<PublicKey>,
     /// The permission(s) to grant.
     #[clap(
         long = "permission",
@@ -345,6 +593,17 @@ pub struct PublishGrantCommand {
         default_value = "release,yank"
     )]
     pub permissions: Vec<Permission>,
+    /// A key request file, exported with `warg key request`, to grant the
+    /// requested permissions to the requesting key.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["public_key", "permissions"]
+    )]
+    pub request: Option<PathBuf>,
+    /// Grant the request without asking for confirmation.
+    #[clap(long, short, requires = "request")]
+    pub yes: bool,
     /// Whether to wait for the publish to complete.
     #[clap(long)]
     pub no_wait: bool,
@@ -352,7 +611,35 @@ pub struct PublishGrantCommand {
 
 impl PublishGrantCommand {
     /// Executes the command.
-    pub async fn exec(self) -> Result<()> {
+    pub async fn exec(mut self) -> Result<()> {
+        if let Some(path) = &self.request {
+            let request = read_key_request(path, &self.name)?;
+            if !self.yes
+                && !Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!(
+                        "grant ({permissions_str}) for package `{name}` to key ID `{key_id}`",
+                        permissions_str = request.permissions.iter().join(","),
+                        name = self.name,
+                        key_id = request.key.fingerprint(),
+                    ))
+                    .interact()?
+            {
+                println!(
+                    "skipping grant of key request `{path}`",
+                    path = path.display()
+                );
+                return Ok(());
+            }
+
+            self.public_key = Some(request.key);
+            self.permissions = request.permissions;
+        }
+
+        let public_key = self
+            .public_key
+            .clone()
+            .context("a public key or key request is required")?;
+
         let config = self.common.read_config()?;
         let client = self.common.create_client(&config)?;
         let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
@@ -360,7 +647,7 @@ impl PublishGrantCommand {
 
         match enqueue(&client, &self.name, |_| async {
             Ok(PublishEntry::Grant {
-                key: self.public_key.clone(),
+                key: public_key.clone(),
                 permissions: self.permissions.clone(),
             })
         })
@@ -388,7 +675,7 @@ impl PublishGrantCommand {
                     println!(
                         "granted ({permissions_str}) to key ID `{key_id}` for package `{name}`",
                         permissions_str = self.permissions.iter().join(","),
-                        key_id = self.public_key.fingerprint(),
+                        key_id = public_key.fingerprint(),
                         name = self.name
                     );
                 }
@@ -397,7 +684,7 @@ impl PublishGrantCommand {
                 println!(
                     "added grant of ({permissions_str}) to key ID `{key_id}` for package `{name}` to pending publish",
                     permissions_str = self.permissions.iter().join(","),
-                    key_id = self.public_key.fingerprint(),
+                    key_id = public_key.fingerprint(),
                     name = self.name
                 );
             }
@@ -407,6 +694,51 @@ impl PublishGrantCommand {
     }
 }
 
+/// Issue a token authorizing another key to publish releases.
+#[derive(Args)]
+pub struct PublishTokenCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+    /// The package the token authorizes releases of.
+    ///
+    /// Registries only accept tokens scoped to a single package.
+    #[clap(long, short, value_name = "PACKAGE")]
+    pub name: PackageName,
+    /// The public key to authorize, such as one held by a CI system.
+    #[clap(value_name = "PUBLIC_KEY")]
+    pub public_key: PublicKey,
+    /// The number of seconds until the token expires.
+    #[clap(long, value_name = "SECONDS", default_value = "3600")]
+    pub expires_in: u64,
+}
+
+impl PublishTokenCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config)?;
+        let registry_domain = client.get_warg_registry(self.name.namespace()).await?;
+        let signing_key = self.common.signing_key(registry_domain.as_ref()).await?;
+
+        let token = PublishToken::issue(
+            &signing_key,
+            self.public_key.clone(),
+            [self.name.clone()],
+            SystemTime::now() + Duration::from_secs(self.expires_in),
+        )?;
+
+        eprintln!(
+            "issued token authorizing key ID `{key_id}` to release `{name}` for {secs} seconds",
+            key_id = self.public_key.fingerprint(),
+            name = self.name,
+            secs = self.expires_in
+        );
+        println!("{token}");
+        Ok(())
+    }
+}
+
 /// Publish a package to a warg registry.
 #[derive(Args)]
 #[clap(disable_version_flag = true)]
@@ -555,12 +887,40 @@ impl PublishListCommand {
                         PublishEntry::Init => {
                             println!("initialize package");
                         }
-                        PublishEntry::Release { version, content } => {
-                            println!("release {version} with content digest `{content}`")
+                        PublishEntry::Release {
+                            version,
+                            content,
+                            encryption,
+                            manifest,
+                        } => {
+                            match encryption {
+                                Some(encryption) => print!(
+                                    "release {version} with content digest `{content}` (encrypted for {count} recipient(s))",
+                                    count = encryption.recipients.len()
+                                ),
+                                None => print!("release {version} with content digest `{content}`"),
+                            }
+                            match manifest {
+                                Some(manifest) => println!(
+                                    " and artifact(s) {names}",
+                                    names = manifest
+                                        .artifacts
+                                        .iter()
+                                        .map(|a| format!("`{name}`", name = a.name))
+                                        .join(", ")
+                                ),
+                                None => println!(),
+                            }
                         }
                         PublishEntry::Yank { version } => {
                             println!("yank {version}")
                         }
+                        PublishEntry::YankRange { range } => {
+                            println!("yank versions matching `{range}`")
+                        }
+                        PublishEntry::Tag { tag, version } => {
+                            println!("tag {version} as `{tag}`")
+                        }
                         PublishEntry::Grant { key, permissions } => println!(
                             "grant ({permissions_str}) to `{key_id}`",
                             permissions_str = permissions.iter().join(","),
@@ -660,6 +1020,12 @@ impl PublishSubmitCommand {
                             PublishEntry::Yank { version } => {
                                 println!("yanked version {version} of package `{name}`")
                             }
+                            PublishEntry::YankRange { range } => {
+                                println!("yanked versions matching `{range}` of package `{name}`")
+                            }
+                            PublishEntry::Tag { tag, version } => {
+                                println!("tagged version {version} of package `{name}` as `{tag}`")
+                            }
                             PublishEntry::Grant { key, permissions } => {
                                 println!(
                                     "granted ({permissions_str}) to `{key_id}`",
@@ -0,0 +1,45 @@
+use super::CommonOptions;
+use anyhow::{Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use warg_client::sbom::SbomFormat;
+use warg_protocol::registry::PackageName;
+
+/// Generate a software bill of materials for a package and its resolved dependencies.
+#[derive(Args)]
+pub struct SbomCommand {
+    /// The common command options.
+    #[clap(flatten)]
+    pub common: CommonOptions,
+
+    /// The package to generate the bill of materials for.
+    #[clap(value_name = "PACKAGE")]
+    pub package: PackageName,
+
+    /// The format of the bill of materials: `spdx` or `cyclonedx`.
+    #[clap(long, value_name = "FORMAT", default_value_t)]
+    pub format: SbomFormat,
+
+    /// The path to write the bill of materials to; defaults to stdout.
+    #[clap(long, short, value_name = "OUTPUT")]
+    pub output: Option<PathBuf>,
+}
+
+impl SbomCommand {
+    /// Executes the command.
+    pub async fn exec(self) -> Result<()> {
+        let config = self.common.read_config()?;
+        let client = self.common.create_client(&config)?;
+
+        let info = client.package(&self.package).await?;
+        let sbom = client.sbom(&info).await?;
+        let json = serde_json::to_string_pretty(&sbom.render(self.format))?;
+        match &self.output {
+            Some(path) => std::fs::write(path, json + "\n")
+                .with_context(|| format!("failed to write `{path}`", path = path.display()))?,
+            None => println!("{json}"),
+        }
+
+        Ok(())
+    }
+}
@@ -65,7 +65,9 @@ async fn client_incrementally_fetches() -> Result<()> {
                     head: Some(head),
                     entries: vec![PublishEntry::Release {
                         version: format!("0.{i}.0").parse().unwrap(),
-                        content: digest.clone(),
+                        content: digest,
+                        encryption: None,
+                        manifest: None,
                     }],
                 },
             )
@@ -106,7 +108,7 @@ async fn client_incrementally_fetches() -> Result<()> {
     );
 
     let mut count = 0;
-    for release in package.state.releases() {
+    for release in package.state.stored_releases() {
         assert_eq!(release.content(), Some(&digest));
         count += 1;
     }
@@ -115,3 +117,492 @@ async fn client_incrementally_fetches() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_detects_misbehaving_mock_registry() -> Result<()> {
+    use std::time::SystemTime;
+    use warg_client::{
+        api,
+        testing::{Misbehavior, MockRegistry, MOCK_REGISTRY_URL},
+        ClientError,
+    };
+    use warg_crypto::hash::HashAlgorithm;
+    use warg_protocol::{
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::RecordId,
+        ProtoEnvelope,
+    };
+
+    let root = root().await?;
+    let registry = MockRegistry::new(support::test_operator_key());
+    let config = Config {
+        home_url: Some(MOCK_REGISTRY_URL.to_string()),
+        registries_dir: Some(root.join("registries")),
+        content_dir: Some(root.join("content")),
+        namespace_map_path: Some(root.join("namespaces")),
+        disable_interactive: true,
+        ..Default::default()
+    };
+    let client = create_client(&config)?.with_transport(registry.clone());
+
+    let name = PackageName::new("test:mock")?;
+    let signing_key = support::test_signing_key();
+    let mut prev = None;
+    let mut release = |version: &str, init: bool| -> Result<()> {
+        let content = registry.add_content(format!("content of {version}"));
+        let mut entries = Vec::new();
+        if init {
+            entries.push(PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            });
+        }
+        entries.push(PackageEntry::Release {
+            version: version.parse()?,
+            content,
+            encryption: None,
+            manifest: None,
+        });
+
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: prev.clone(),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries,
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?;
+        prev = Some(RecordId::package_record::<warg_crypto::hash::Sha256>(
+            &record,
+        ));
+        registry.append_package_record(&name, record);
+        Ok(())
+    };
+
+    // A well-behaved registry serves verifiable logs and content
+    release("1.0.0", true)?;
+    let download = client
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, b"content of 1.0.0");
+
+    release("1.1.0", false)?;
+    client.update().await?;
+
+    // A registry serving a checkpoint older than one already seen is detected
+    registry.misbehave(Misbehavior::StaleCheckpoint);
+    assert!(client.update().await.is_err());
+    registry.behave();
+
+    release("1.2.0", false)?;
+    registry.misbehave(Misbehavior::ForgedCheckpoint);
+    assert!(matches!(
+        client.update().await,
+        Err(ClientError::InvalidCheckpointSignature)
+    ));
+    registry.behave();
+
+    registry.misbehave(Misbehavior::BadInclusionProof);
+    assert!(matches!(
+        client.update().await,
+        Err(ClientError::Api(api::ClientError::Proof(_)))
+    ));
+    registry.behave();
+
+    registry.misbehave(Misbehavior::BadConsistencyProof);
+    assert!(matches!(
+        client.update().await,
+        Err(ClientError::Api(
+            api::ClientError::IncorrectConsistencyProof { .. }
+        ))
+    ));
+    registry.behave();
+
+    client.update().await?;
+    let download = client
+        .download(&name, &"1.2.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, b"content of 1.2.0");
+
+    // A registry without streamed fetch logs responses is fetched from instead
+    release("1.3.0", false)?;
+    registry.misbehave(Misbehavior::NoStreaming);
+    client.update().await?;
+    let download = client
+        .download(&name, &"1.3.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, b"content of 1.3.0");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_verifies_namespace_migrations() -> Result<()> {
+    use std::time::SystemTime;
+    use warg_client::{
+        testing::{MockRegistry, MOCK_REGISTRY_URL},
+        ClientError,
+    };
+    use warg_crypto::{hash::HashAlgorithm, signing::generate_p256_pair};
+    use warg_protocol::{
+        operator::{Migration, OperatorEntry, OperatorRecord, OPERATOR_RECORD_VERSION},
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::Checkpoint,
+        ProtoEnvelope,
+    };
+
+    let root = root().await?;
+    let operator_key = support::test_operator_key();
+    let registry = MockRegistry::new(operator_key.clone());
+    let config = Config {
+        home_url: Some(MOCK_REGISTRY_URL.to_string()),
+        registries_dir: Some(root.join("registries")),
+        content_dir: Some(root.join("content")),
+        namespace_map_path: Some(root.join("namespaces")),
+        disable_interactive: true,
+        ..Default::default()
+    };
+    let client = || -> Result<_> { Ok(create_client(&config)?.with_transport(registry.clone())) };
+
+    // The registry migrates a namespace from a source registry, whose
+    // operator key signs the migration
+    let (source_key, source_priv) = generate_p256_pair();
+    let head = client()?
+        .update_operator(None)
+        .await?
+        .state
+        .head()
+        .clone()
+        .context("operator log is empty")?;
+    let migration = Migration::sign(
+        &source_priv,
+        &operator_key.public_key().fingerprint(),
+        "source.example.com",
+        Checkpoint {
+            log_length: 1,
+            log_root: HashAlgorithm::Sha256.digest(b"log"),
+            map_root: HashAlgorithm::Sha256.digest(b"map"),
+        },
+        ["migrated".to_string()],
+    )?;
+    registry.append_operator_record(ProtoEnvelope::signed_contents(
+        &operator_key,
+        OperatorRecord {
+            prev: Some(head.digest),
+            version: OPERATOR_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::Migrate(migration)],
+        },
+    )?);
+
+    let name = PackageName::new("migrated:package")?;
+    let signing_key = support::test_signing_key();
+    let content = registry.add_content("migrated content");
+    registry.append_package_record(
+        &name,
+        ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![
+                    PackageEntry::Init {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        key: signing_key.public_key(),
+                    },
+                    PackageEntry::Release {
+                        version: "1.0.0".parse()?,
+                        content,
+                        encryption: None,
+                        manifest: None,
+                    },
+                ],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?,
+    );
+
+    // The migration is not followed unless its key is an operator key of the source
+    let (other_key, _) = generate_p256_pair();
+    match client()?
+        .with_migration_source_key("source.example.com", other_key)
+        .download(&name, &"1.0.0".parse()?)
+        .await
+    {
+        Err(ClientError::UntrustedMigration {
+            namespace,
+            registry,
+            key_id,
+        }) => {
+            assert_eq!(namespace, "migrated");
+            assert_eq!(registry, "source.example.com");
+            assert_eq!(key_id, source_key.fingerprint());
+        }
+        other => bail!("expected an untrusted migration, got {other:?}"),
+    }
+
+    let download = client()?
+        .with_migration_source_key("source.example.com", source_key)
+        .download(&name, &"1.0.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, b"migrated content");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn source_verification_is_transport_agnostic() -> Result<()> {
+    use std::time::SystemTime;
+    use warg_client::{
+        api,
+        source::{self, RegistrySource, SourceError},
+        testing::{Misbehavior, MockRegistry, MOCK_REGISTRY_URL},
+    };
+    use warg_crypto::hash::{HashAlgorithm, Sha256};
+    use warg_protocol::{
+        package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+        registry::RecordId,
+        ProtoEnvelope,
+    };
+
+    let registry = MockRegistry::new(support::test_operator_key());
+    let name = PackageName::new("test:source")?;
+    let signing_key = support::test_signing_key();
+    let mut prev = None;
+    for (version, init) in [("1.0.0", true), ("1.1.0", false), ("2.0.0", false)] {
+        let content = registry.add_content(format!("content of {version}"));
+        let mut entries = Vec::new();
+        if init {
+            entries.push(PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            });
+        }
+        entries.push(PackageEntry::Release {
+            version: version.parse()?,
+            content,
+            encryption: None,
+            manifest: None,
+        });
+
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: prev.clone(),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries,
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?;
+        prev = Some(RecordId::package_record::<Sha256>(&record));
+        registry.append_package_record(&name, record);
+    }
+
+    // The same verification applies to the registry in memory and over its API
+    let api = api::Client::new(MOCK_REGISTRY_URL, None)?.with_transport(registry.clone());
+    let sources: [&dyn RegistrySource; 2] = [&registry, &api];
+    for source in sources {
+        let snapshot = source::verify_operator(source).await?;
+        assert_eq!(snapshot.checkpoint.checkpoint.log_length, 4);
+
+        let resolved = source::resolve(source, &snapshot, &name, &"^1".parse()?)
+            .await?
+            .context("failed to resolve package")?;
+        assert_eq!(resolved.version.to_string(), "1.1.0");
+        assert_eq!(resolved.content.as_ref(), b"content of 1.1.0");
+        assert!(source::resolve(source, &snapshot, &name, &"^3".parse()?)
+            .await?
+            .is_none());
+
+        registry.misbehave(Misbehavior::BadInclusionProof);
+        assert!(matches!(
+            source::verify_package(source, &snapshot, &name).await,
+            Err(SourceError::InclusionProof { .. })
+        ));
+        registry.behave();
+
+        registry.misbehave(Misbehavior::ForgedCheckpoint);
+        assert!(matches!(
+            source::verify_operator(source).await,
+            Err(SourceError::InvalidCheckpointSignature)
+        ));
+        registry.behave();
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_bootstraps_operator_log() -> Result<()> {
+    use warg_protocol::operator::{AdminCommand, NamespaceState};
+
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let client = create_client(&config)?;
+
+    // The operator log is validated and cached at the latest checkpoint
+    let operator = client.update_operator(None).await?;
+    assert_eq!(
+        operator.state.namespace_state("test"),
+        Some(&NamespaceState::Defined)
+    );
+    let checkpoint = client
+        .registry()
+        .load_checkpoint(None)
+        .await?
+        .context("checkpoint was not stored")?;
+    assert_eq!(
+        operator.checkpoint.as_ref(),
+        Some(&checkpoint.as_ref().checkpoint)
+    );
+    assert_eq!(
+        client
+            .registry()
+            .load_operator(None)
+            .await?
+            .and_then(|o| o.checkpoint),
+        operator.checkpoint
+    );
+
+    // A newer checkpoint refreshes the cached operator state
+    let name = PackageName::new("test:frozen")?;
+    client
+        .admin(
+            &support::test_operator_key(),
+            [AdminCommand::FreezePackage { name: name.clone() }],
+        )
+        .await?;
+    let refreshed = client.update_operator(None).await?;
+    assert!(refreshed.head_registry_index > operator.head_registry_index);
+    assert!(refreshed.state.package_frozen(&name));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_fetches_at_pinned_checkpoint() -> Result<()> {
+    use warg_client::ClientError;
+    use warg_protocol::registry::Checkpoint;
+
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let client = create_client(&config)?;
+    let signing_key = support::test_signing_key();
+    let name = PackageName::new("test:pinned")?;
+
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    client.update().await?;
+    let pinned = client
+        .registry()
+        .load_checkpoint(None)
+        .await?
+        .context("checkpoint was not stored")?
+        .as_ref()
+        .checkpoint
+        .clone();
+
+    publish_component(&client, &name, "1.1.0", "(component)", false, &signing_key).await?;
+    client.update().await?;
+    let latest = client
+        .registry()
+        .load_checkpoint(None)
+        .await?
+        .context("checkpoint was not stored")?;
+    assert!(latest.as_ref().checkpoint.log_length > pinned.log_length);
+    drop(client);
+
+    // Fetches are evaluated against the pinned checkpoint rather than the latest
+    let client = create_client(&config)?.with_pinned_checkpoint(None, pinned.clone());
+    client.update().await?;
+    let package = client.package(&name).await?;
+    assert_eq!(package.checkpoint.as_ref(), Some(&pinned));
+    let versions = package
+        .state
+        .stored_releases()
+        .map(|r| r.version.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["1.0.0"]);
+
+    // The stored checkpoint is never rewound to the pinned checkpoint
+    assert_eq!(
+        client.registry().load_checkpoint(None).await?.as_ref(),
+        Some(&latest)
+    );
+    drop(client);
+
+    // A pinned checkpoint with different roots is rejected
+    let client = create_client(&config)?.with_pinned_checkpoint(
+        None,
+        Checkpoint {
+            map_root: pinned.log_root,
+            ..pinned.clone()
+        },
+    );
+    match client.update_operator(None).await {
+        Err(ClientError::PinnedCheckpointMismatch { log_length }) => {
+            assert_eq!(log_length, pinned.log_length)
+        }
+        other => bail!("expected a pinned checkpoint mismatch, got {other:?}"),
+    }
+    drop(client);
+
+    // Unpinned clients fetch from the latest checkpoint again
+    let client = create_client(&config)?;
+    client.update().await?;
+    let package = client.package(&name).await?;
+    assert_eq!(package.state.releases().count(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn client_replays_states_stored_by_older_clients() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let client = create_client(&config)?;
+    let signing_key = support::test_signing_key();
+    let name = PackageName::new("test:replayed")?;
+
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    publish_component(&client, &name, "1.1.0", "(component)", false, &signing_key).await?;
+    client.update().await?;
+    let package = client.package(&name).await?;
+    let stats = package.state.stats();
+    let history = package.state.permissions().history;
+    assert_eq!((stats.records, stats.releases), (2, 2));
+    assert!(!history.is_empty());
+
+    // Store the state as clients from before log statistics or the
+    // permission history would have
+    for field in ["counts", "permissionHistory"] {
+        let mut package = client.package(&name).await?;
+        let mut state = serde_json::to_value(&package.state)?;
+        state
+            .as_object_mut()
+            .context("state is not an object")?
+            .remove(field);
+        package.state = serde_json::from_value(state)?;
+        assert!(package.state.needs_replay());
+        client
+            .registry()
+            .store_package(package.registry.as_ref(), &package)
+            .await?;
+
+        // The log is replayed from its first record on the next update
+        client.update().await?;
+        let package = client.package(&name).await?;
+        assert!(!package.state.needs_replay());
+        assert_eq!(package.state.stats(), stats);
+        assert_eq!(package.state.permissions().history, history);
+    }
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ use self::support::*;
 use anyhow::{Context, Result};
 use std::time::Duration;
 use warg_client::{
+    sbom::SbomFormat,
     storage::{
         ContentStorage, FileSystemContentStorage, FileSystemNamespaceMapStorage,
         FileSystemRegistryStorage, PublishEntry, PublishInfo, RegistryStorage,
@@ -101,6 +102,23 @@ async fn depsolve() -> Result<()> {
         wasmprinter::print_bytes(&locked_bytes)?,
         wasmprinter::print_bytes(expected_locked)?
     );
+
+    let sbom = client.sbom(&info).await?;
+    assert_eq!(sbom.package.name.to_string(), "test:meet");
+    let mut dependencies = sbom
+        .dependencies
+        .iter()
+        .map(|p| p.name.to_string())
+        .collect::<Vec<_>>();
+    dependencies.sort();
+    assert_eq!(dependencies, ["test:add", "test:five", "test:inc"]);
+    assert!(sbom
+        .dependencies
+        .iter()
+        .all(|p| p.release.content.is_some()));
+    let spdx = sbom.render(SbomFormat::Spdx);
+    assert_eq!(spdx["packages"].as_array().map(Vec::len), Some(4));
+
     let bundled_bytes = client.bundle_component(&info).await?;
     let expected_bundled = wat::parse_file("tests/components/meet_bundled.wat")?;
     assert_eq!(
@@ -150,7 +168,9 @@ async fn publish_package(
                 head: Some(head),
                 entries: vec![PublishEntry::Release {
                     version: "1.0.0".to_string().parse().unwrap(),
-                    content: add_digest.clone(),
+                    content: add_digest,
+                    encryption: None,
+                    manifest: None,
                 }],
             },
         )
@@ -2,7 +2,51 @@
 
 use super::{support::*, *};
 use anyhow::Result;
-use warg_client::api;
+use indexmap::IndexSet;
+use warg_api::v1::{
+    admin::AdminError,
+    fetch::FetchError,
+    package::{PackageError, PackageRecordState, UploadEndpoint},
+    proof::{DeltaRequest, ProofError},
+};
+use warg_client::{
+    api,
+    archive::{ArchiveClient, ArchiveError},
+    auth::BearerToken,
+    monitor,
+    static_site::{StaticSiteClient, StaticSiteError},
+    storage::{ContentStorage, RegistryStorage},
+};
+use warg_crypto::{
+    encryption::{ContentEncryption, EncryptionError},
+    hash::{AnyHash, Hash, StorageLayout},
+    signing::{generate_p256_pair, PublicKey},
+};
+use warg_protocol::{
+    discovery::OperatorKeys,
+    operator::{AdminCommand, Advisory, IdentityClaim, OperatorEntry, OperatorRecord},
+    package::{
+        PackageArchive, PackageArchiveError, Permission, PublishToken, ReleaseArtifact,
+        ReleaseManifest, ValidationRules, YankPolicy,
+    },
+    registry::{LogId, LogLeaf, RecordId},
+    CoPublication, CoPublicationStatus, CoPublishedRecord, Countersignature, SerdeEnvelope,
+};
+use warg_server::{
+    archive::LogArchiver,
+    auth::{Access, BearerTokenAuthenticator},
+    datastore::{DataStore, DataStoreError, MemoryDataStore, RecordStatus},
+    events::{Event, EventBus},
+    export::{ExportError, StaticSiteExporter},
+    import::{Dump, DumpFormat, PackageImporter},
+    local::LocalRegistry,
+    policy::staging::StagingPolicy,
+    recover::{ArchivedRecord, Recoverer},
+    services::{
+        AnomalyMonitor, CoreService, CoreServiceError, EntryKind, FileLease, GrantReleaseBurst,
+        LeaseError, Quarantine,
+    },
+};
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn it_starts_with_initial_checkpoint() -> Result<()> {
@@ -25,6 +69,9 @@ async fn it_publishes_a_component() -> Result<()> {
     );
 
     test_fetch_package_names(&config).await?;
+    test_search(&config).await?;
+    test_key_records(&config).await?;
+    test_conditional_fetch(&config).await?;
 
     Ok(())
 }
@@ -97,6 +144,12 @@ async fn it_rejects_invalid_signature() -> Result<()> {
     test_invalid_signature(&config).await
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_untagged_records() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    test_untagged_record(&config).await
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn it_formats_custom_content_urls() -> Result<()> {
     let (_server, config) = spawn_server(
@@ -114,3 +167,3261 @@ async fn it_get_ledger() -> Result<()> {
     let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
     test_get_ledger(&config).await
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_exports_a_static_site() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+
+    let name = PackageName::new("test:static")?;
+    let client = create_client(&config)?;
+    let digest = publish_component(
+        &client,
+        &name,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    // Export from a second core service sharing the server's data store
+    let (core, handle) = CoreService::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store),
+        Duration::from_secs(60),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    let site = root.join("site");
+    let summary = StaticSiteExporter::new(&core, root.join("server").join("files"))
+        .export(&site)
+        .await?;
+    assert_eq!(summary.log_length, 2);
+    assert_eq!(summary.packages, 1);
+    assert_eq!(summary.records, 2);
+    assert_eq!(summary.content, 1);
+
+    // Exporting again produces identical output
+    let checkpoint = std::fs::read(site.join("checkpoint.json"))?;
+    StaticSiteExporter::new(&core, root.join("server").join("files"))
+        .export(&site)
+        .await?;
+    assert_eq!(std::fs::read(site.join("checkpoint.json"))?, checkpoint);
+    drop(core);
+    handle.await?;
+
+    let client = StaticSiteClient::new(Url::from_directory_path(&site).unwrap());
+    let snapshot = client.snapshot().await?;
+    assert_eq!(snapshot.checkpoint.checkpoint.log_length, 2);
+
+    let state = client.package(&snapshot, &name).await?;
+    let release = state
+        .release(&"1.0.0".parse()?)
+        .context("release should exist")?;
+    assert_eq!(release.content(), Some(&digest));
+    client.download(&digest).await?;
+
+    // The exported site is also a verifiable registry source
+    let source_snapshot = warg_client::source::verify_operator(&client).await?;
+    let resolved = warg_client::source::resolve(&client, &source_snapshot, &name, &"^1".parse()?)
+        .await?
+        .context("release should resolve")?;
+    assert_eq!(resolved.digest, digest);
+
+    assert!(matches!(
+        client
+            .package(&snapshot, &PackageName::new("test:missing")?)
+            .await,
+        Err(StaticSiteError::PackageNotFound(_))
+    ));
+
+    // Records published before envelope contents were tagged are still read
+    for entry in std::fs::read_dir(site.join("logs"))? {
+        let path = entry?.path();
+        let mut log: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        for record in log["records"].as_array_mut().context("records")? {
+            record
+                .as_object_mut()
+                .context("record")?
+                .remove("contentType")
+                .context("records should be tagged")?;
+        }
+        std::fs::write(&path, serde_json::to_vec(&log)?)?;
+    }
+    let source_snapshot = warg_client::source::verify_operator(&client).await?;
+    let resolved = warg_client::source::resolve(&client, &source_snapshot, &name, &"^1".parse()?)
+        .await?
+        .context("untagged release should resolve")?;
+    assert_eq!(resolved.digest, digest);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_exports_a_verifiable_package_archive() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+
+    let name = PackageName::new("test:archived")?;
+    let client = create_client(&config)?;
+    let signing_key = test_signing_key();
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    publish_component(&client, &name, "1.1.0", "(component)", false, &signing_key).await?;
+    publish_component(
+        &client,
+        &PackageName::new("test:other")?,
+        "1.0.0",
+        "(component)",
+        true,
+        &signing_key,
+    )
+    .await?;
+
+    let (core, handle) = CoreService::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store),
+        Duration::from_secs(60),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    let exporter = StaticSiteExporter::new(&core, root.join("server").join("files"));
+    let archive = exporter.export_package(&name).await?;
+    assert!(matches!(
+        exporter
+            .export_package(&PackageName::new("test:missing")?)
+            .await,
+        Err(ExportError::PackageNotFound(_))
+    ));
+    drop(exporter);
+    drop(core);
+    handle.await?;
+
+    assert_eq!(archive.records.len(), 2);
+    assert_eq!(archive.checkpoint.as_ref().checkpoint.log_length, 4);
+
+    // The archive round-trips through JSON and verifies with the operator key
+    let archive: PackageArchive = serde_json::from_slice(&serde_json::to_vec(&archive)?)?;
+    let operator_key = test_operator_key().public_key();
+    let state = archive.verify(&operator_key)?;
+    assert!(state.release(&"1.1.0".parse()?).is_some());
+
+    // Verification fails with another operator key
+    let (other_key, _) = generate_p256_pair();
+    assert!(matches!(
+        archive.verify(&other_key),
+        Err(PackageArchiveError::InvalidSignature)
+    ));
+
+    // Verification fails if the head is withheld
+    let mut truncated = archive.clone();
+    truncated.records.pop();
+    assert!(matches!(
+        truncated.verify(&operator_key),
+        Err(PackageArchiveError::IncorrectProof { .. })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_archives_the_registry_log() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+
+    let client = create_client(&config)?;
+    for i in 0..4 {
+        publish_component(
+            &client,
+            &PackageName::new(format!("test:archived{i}"))?,
+            "1.0.0",
+            "(component)",
+            true,
+            &test_signing_key(),
+        )
+        .await?;
+    }
+    client.update().await?;
+    let operator = client
+        .registry()
+        .load_operator(None)
+        .await?
+        .context("operator should be stored")?
+        .state;
+
+    // Archive from a second core service sharing the server's data store
+    let (core, handle) = CoreService::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store),
+        Duration::from_secs(60),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    let output = root.join("archive");
+    let operator_key = test_operator_key();
+    let summary = LogArchiver::new(&core, &operator_key)
+        .with_segment_len(2)
+        .archive(&output)
+        .await?;
+    assert_eq!(summary.log_length, 5);
+    assert_eq!(summary.segments, 2);
+    assert_eq!(summary.records, 4);
+    drop(core);
+    handle.await?;
+
+    let archive = ArchiveClient::new(Url::from_directory_path(&output).unwrap());
+    let manifest = archive.manifest(&operator).await?;
+    assert_eq!(manifest.checkpoint.log_length, 5);
+
+    let records = archive.fetch_range(&manifest, 1..4).await?;
+    assert_eq!(
+        records
+            .iter()
+            .map(|r| r.record.registry_index)
+            .collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        records[0].log_id,
+        LogId::package_log::<Sha256>(&PackageName::new("test:archived0")?)
+    );
+
+    // The last record is not in a complete segment
+    assert!(matches!(
+        archive.fetch_range(&manifest, 3..5).await,
+        Err(ArchiveError::NotArchived { start: 3, end: 5 })
+    ));
+
+    // Tampering with a segment is detected
+    let path = output.join("segments/2-4.json");
+    let segment = std::fs::read_to_string(&path)?;
+    std::fs::write(
+        &path,
+        segment.replacen("\"registryIndex\": 2", "\"registryIndex\": 3", 1),
+    )?;
+    assert!(matches!(
+        archive.fetch_range(&manifest, 2..4).await,
+        Err(ArchiveError::SegmentDigestMismatch {
+            start: 2,
+            end: 4,
+            ..
+        })
+    ));
+    archive.fetch_range(&manifest, 0..2).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_imports_a_registry_dump() -> Result<()> {
+    let digest = HashAlgorithm::Sha256.digest(b"tarball");
+    let cksum = digest
+        .to_string()
+        .strip_prefix("sha256:")
+        .unwrap()
+        .to_string();
+    let index = (0..5)
+        .map(|i| {
+            format!(
+                r#"{{"name":"serde_json","vers":"1.0.{i}","cksum":"{cksum}","yanked":{}}}"#,
+                i == 4
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let dump = Dump::parse(DumpFormat::CratesIo, index.as_bytes(), "test")?;
+    assert!(dump.skipped.is_empty());
+
+    let store = MemoryDataStore::default();
+    let (core, handle) = CoreService::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store.clone()),
+        Duration::from_millis(100),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+
+    let summary = PackageImporter::new(&core, test_signing_key())
+        .with_max_entries_per_record(4)
+        .with_throttle(Duration::ZERO)
+        .import(&dump.packages)
+        .await?;
+    assert_eq!(summary.packages, 1);
+    assert_eq!(summary.records, 2);
+
+    // Wait for both records to be included in a checkpoint
+    let log_id = LogId::package_log::<Sha256>(&dump.packages[0].name);
+    let mut log_length = 0;
+    for _ in 0..50 {
+        log_length = core
+            .store()
+            .get_latest_checkpoint()
+            .await?
+            .as_ref()
+            .checkpoint
+            .log_length;
+        if log_length == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(log_length, 3);
+
+    let records = core
+        .store()
+        .get_package_records(&log_id, log_length, None, 10)
+        .await?;
+    let mut state = warg_protocol::package::LogState::new();
+    for record in &records {
+        state = state.validate(&record.envelope)?;
+    }
+    assert_eq!(state.releases().count(), 5);
+    assert!(state.release(&"1.0.4".parse()?).unwrap().yanked());
+
+    // Importing again skips the existing package
+    let summary = PackageImporter::new(&core, test_signing_key())
+        .import(&dump.packages)
+        .await?;
+    assert_eq!(summary.packages, 0);
+    assert_eq!(summary.existing, [dump.packages[0].name.clone()]);
+
+    drop(core);
+    handle.await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_stages_records_until_countersigned() -> Result<()> {
+    let root = root().await?;
+    let config = server_config(&root)
+        .with_staging_policy(StagingPolicy::new().with_reserved_package("test:reserved")?);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let name = PackageName::new("test:reserved")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let signing_key = test_signing_key();
+    let record = ProtoEnvelope::signed_contents(
+        &signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        },
+    )?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+
+    // Initializing a reserved package is staged rather than processed
+    let published = client
+        .publish_package_record(
+            None,
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(ProtoEnvelopeBody::from(record.clone())),
+                content_sources: Default::default(),
+                expected_head: None,
+                copublication: None,
+            },
+        )
+        .await?;
+    assert!(matches!(published.state, PackageRecordState::Staged));
+    let fetched = client.get_package_record(None, &log_id, &record_id).await?;
+    assert!(matches!(fetched.state, PackageRecordState::Staged));
+
+    // A countersignature by a key other than the operator's is rejected
+    let forged = Countersignature::sign(&signing_key, record.content_bytes())?;
+    match client
+        .countersign_package_record(None, &log_id, &record_id, &forged)
+        .await
+    {
+        Err(api::ClientError::Package(PackageError::Unauthorized(_))) => {}
+        Err(e) => panic!("unexpected countersign error: {e}"),
+        Ok(_) => panic!("expected countersign to fail"),
+    }
+
+    let countersignature = Countersignature::sign(&test_operator_key(), record.content_bytes())?;
+    let countersigned = client
+        .countersign_package_record(None, &log_id, &record_id, &countersignature)
+        .await?;
+    assert!(matches!(
+        countersigned.state,
+        PackageRecordState::Processing
+    ));
+
+    let mut state = countersigned.state;
+    for _ in 0..50 {
+        state = client
+            .get_package_record(None, &log_id, &record_id)
+            .await?
+            .state;
+        if matches!(state, PackageRecordState::Published { .. }) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(matches!(state, PackageRecordState::Published { .. }));
+
+    // The record is no longer staged, so it cannot be countersigned again
+    match client
+        .countersign_package_record(None, &log_id, &record_id, &countersignature)
+        .await
+    {
+        Err(api::ClientError::Package(PackageError::Message { status: 409, .. })) => {}
+        Err(e) => panic!("unexpected countersign error: {e}"),
+        Ok(_) => panic!("expected countersign to fail"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_stores_content_in_a_layout() -> Result<()> {
+    let root = root().await?;
+    let config = server_config(&root).with_content_layout(StorageLayout::HashPrefix);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = create_client(&config)?;
+    let name = PackageName::new("test:layout")?;
+    let digest = publish_component(
+        &client,
+        &name,
+        "0.1.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    let files_dir = root.join("server").join("files");
+    let hex = digest.to_string().replace("sha256:", "");
+    let arranged = files_dir
+        .join("sha256")
+        .join(&hex[..2])
+        .join(&hex[2..4])
+        .join(&hex);
+    assert!(arranged.is_file());
+
+    let download = || async {
+        let download = client
+            .download(&name, &"0.1.0".parse()?)
+            .await?
+            .context("failed to resolve package")?;
+        assert_eq!(download.digest, digest);
+        fs::remove_file(&download.path)?;
+        anyhow::Ok(())
+    };
+    download().await?;
+
+    // Content stored before the layout was set is still served
+    fs::rename(
+        &arranged,
+        files_dir.join(digest.to_string().replace(':', "-")),
+    )?;
+    download().await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_keeps_content_of_staged_records_when_collecting_garbage() -> Result<()> {
+    let root = root().await?;
+    let config = server_config(&root)
+        .with_staging_policy(StagingPolicy::new().with_reserved_package("test:reserved")?);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let name = PackageName::new("test:reserved")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let signing_key = test_signing_key();
+    let content = wat::parse_str("(component)")?;
+    let digest: AnyHash = Hash::<Sha256>::of(content.as_slice()).into();
+    let record = ProtoEnvelope::signed_contents(
+        &signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![
+                PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: signing_key.public_key(),
+                },
+                PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content: digest,
+                    encryption: None,
+                    manifest: None,
+                },
+            ],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        },
+    )?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+
+    let published = client
+        .publish_package_record(
+            None,
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(ProtoEnvelopeBody::from(record.clone())),
+                content_sources: Default::default(),
+                expected_head: None,
+                copublication: None,
+            },
+        )
+        .await?;
+    let PackageRecordState::Sourcing { missing_content } = published.state else {
+        panic!("expected the record to be missing content");
+    };
+    let UploadEndpoint::Http {
+        method,
+        url,
+        headers,
+    } = &missing_content[&digest].upload[0];
+    let body = bytes::Bytes::from(content);
+    client
+        .upload_content(
+            method,
+            url,
+            headers,
+            futures::stream::once(async move { Ok(body) }),
+        )
+        .await?;
+    let fetched = client.get_package_record(None, &log_id, &record_id).await?;
+    assert!(matches!(fetched.state, PackageRecordState::Staged));
+
+    // The content of the staged record outlives the grace period of the collector
+    let path = root
+        .join("server")
+        .join("files")
+        .join(digest.to_string().replace(':', "-"));
+    fs::File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(SystemTime::now() - Duration::from_secs(2 * 60 * 60))?;
+    let response = create_client(&config)?
+        .admin(&test_operator_key(), [AdminCommand::CollectGarbage])
+        .await?;
+    assert_eq!(response.results, ["removed 0 unreferenced file(s)"]);
+    assert!(path.is_file());
+
+    // The record can still be countersigned and published
+    let countersignature = Countersignature::sign(&test_operator_key(), record.content_bytes())?;
+    let countersigned = client
+        .countersign_package_record(None, &log_id, &record_id, &countersignature)
+        .await?;
+    assert!(matches!(
+        countersigned.state,
+        PackageRecordState::Processing
+    ));
+
+    let mut state = countersigned.state;
+    for _ in 0..50 {
+        state = client
+            .get_package_record(None, &log_id, &record_id)
+            .await?
+            .state;
+        if matches!(state, PackageRecordState::Published { .. }) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(matches!(state, PackageRecordState::Published { .. }));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_uncountersigned_records_when_sequencing() -> Result<()> {
+    let store = MemoryDataStore::default();
+    let (core, handle) = CoreService::<Sha256>::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store.clone()),
+        Duration::from_millis(100),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    core.enforce_staging(std::sync::Arc::new(
+        StagingPolicy::new().with_reserved_package("test:reserved")?,
+    ));
+
+    let name = PackageName::new("test:reserved")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let signing_key = test_signing_key();
+    let record = ProtoEnvelope::signed_contents(
+        &signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        },
+    )?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+
+    // A staged record submitted without its countersignature is rejected
+    store
+        .store_package_record(&log_id, &name, &record_id, &record, &IndexSet::new())
+        .await?;
+    core.submit_package_record(log_id.clone(), record_id.clone())
+        .await;
+
+    let mut status = store.get_package_record(&log_id, &record_id).await?.status;
+    for _ in 0..50 {
+        if !matches!(status, RecordStatus::Pending) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        status = store.get_package_record(&log_id, &record_id).await?.status;
+    }
+    assert!(
+        matches!(&status, RecordStatus::Rejected(reason) if reason.contains("countersignature")),
+        "unexpected record status {status:?}"
+    );
+
+    drop(core);
+    handle.await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_initializes_packages_with_operator_approval() -> Result<()> {
+    let root = root().await?;
+    let config = server_config(&root).with_staging_policy(
+        StagingPolicy::new()
+            .with_reserved_package("test:unapproved")?
+            .with_reserved_package("test:approved")?,
+    );
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = create_client(&config)?;
+    let signing_key = test_signing_key();
+
+    // Without operator approval, the initialization remains staged
+    let name = PackageName::new("test:unapproved")?;
+    let unapproved = client
+        .init_package(name.clone(), &signing_key, None)
+        .await?;
+    assert_eq!(unapproved.name(), &name);
+    assert!(unapproved.is_staged());
+
+    // With operator approval, the init record is countersigned and published
+    let name = PackageName::new("test:approved")?;
+    let approved = client
+        .init_package(name.clone(), &signing_key, Some(&test_operator_key()))
+        .await?;
+    assert!(!approved.is_staged());
+    client
+        .wait_for_publish(&name, approved.head(), Duration::from_millis(100))
+        .await?;
+    let package = client.package(&name).await?;
+    assert_eq!(
+        package.state.head().as_ref().map(|h| &h.digest),
+        Some(approved.head())
+    );
+
+    // The package cannot be initialized again
+    match client.init_package(name, &signing_key, None).await {
+        Err(ClientError::CannotInitializePackage { .. }) => {}
+        Err(e) => panic!("unexpected init error: {e}"),
+        Ok(_) => panic!("expected init to fail"),
+    }
+
+    let info = approved.publish_info(Vec::new());
+    assert_eq!(info.head.as_ref(), Some(approved.head()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_verifies_discovered_operator_keys() -> Result<()> {
+    let (root_public, root_private) = generate_p256_pair();
+    let operator_keys = |key: PublicKey| -> Result<_> {
+        Ok(SerdeEnvelope::signed_contents(
+            &root_private,
+            OperatorKeys::now(vec![key], Duration::from_secs(3600))?,
+        )?)
+    };
+
+    // A server refuses to serve a document not listing its operator key
+    let dir = root().await?;
+    let config = server_config(&dir).with_operator_keys(operator_keys(generate_p256_pair().0)?);
+    assert!(spawn_server_with_config(&dir, config).await.is_err());
+
+    let dir = root().await?;
+    let config =
+        server_config(&dir).with_operator_keys(operator_keys(test_operator_key().public_key())?);
+    let (_server, config) = spawn_server_with_config(&dir, config).await?;
+    let client = create_client(&config)?;
+    let name = PackageName::new("test:discovered")?;
+    publish_component(
+        &client,
+        &name,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    client.reset_registry().await?;
+    drop(client);
+
+    // A document not signed by the client's root of trust is rejected
+    let client = create_client(&config)?.with_root_of_trust(generate_p256_pair().0);
+    match client.fetch_package(&name).await {
+        Err(ClientError::InvalidOperatorKeysSignature) => {}
+        Err(e) => panic!("unexpected update error: {e}"),
+        Ok(_) => panic!("expected fetch to fail"),
+    }
+    drop(client);
+
+    // The document is verified and cached
+    let client = create_client(&config)?.with_root_of_trust(root_public);
+    client.fetch_package(&name).await?;
+    assert!(client.registry().load_operator_keys(None).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_enforces_the_yank_window() -> Result<()> {
+    let root = root().await?;
+    let config =
+        server_config(&root).with_yank_policy(YankPolicy::new(0).with_advisory_prefix("CVE-"));
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+
+    let name = PackageName::new("test:immutable")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    let head = client.package(&name).await?.state.head().clone().unwrap();
+    drop(client);
+
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let publish = |reason: &str| {
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: Some(head.digest.clone()),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::yank("1.0.0", Some(reason.to_string())).unwrap()],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )
+        .unwrap();
+        client.publish_package_record(
+            None,
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(ProtoEnvelopeBody::from(record)),
+                content_sources: Default::default(),
+                expected_head: None,
+                copublication: None,
+            },
+        )
+    };
+
+    // The window has elapsed, so the yank must be accompanied by an advisory
+    match publish("broken").await {
+        Err(api::ClientError::Package(PackageError::Message {
+            status: 400,
+            message,
+        })) => {
+            assert!(message.contains("without an advisory"), "{message}")
+        }
+        Err(e) => panic!("unexpected publish error: {e}"),
+        Ok(_) => panic!("expected publish to fail"),
+    }
+
+    let published = publish("CVE-2024-12345: broken").await?;
+    assert!(matches!(published.state, PackageRecordState::Processing));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_requires_grants_to_prove_key_possession() -> Result<()> {
+    let root = root().await?;
+    let rules = ValidationRules::new().with_key_possession(true);
+    let config = server_config(&root).with_validation_rules(rules);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+
+    let name = PackageName::new("test:possession")?;
+    let signing_key = test_signing_key();
+    let (maintainer_pub, maintainer_priv) = generate_p256_pair();
+    let client = create_client(&config)?.with_validation_rules(rules);
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+
+    // A grant without a proof of possession is rejected when sequenced
+    let result = async {
+        let record_id = client
+            .publish_with_info(
+                &signing_key,
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![PublishEntry::Grant {
+                        key: maintainer_pub.clone(),
+                        permissions: vec![Permission::Release],
+                    }],
+                },
+            )
+            .await?;
+        client
+            .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+            .await
+    }
+    .await;
+    match result {
+        Err(ClientError::PublishRejected { reason, .. }) => {
+            assert!(reason.contains("does not prove possession"), "{reason}")
+        }
+        other => panic!("expected the grant to be rejected: {other:?}"),
+    }
+
+    // A grant proving possession is published and validates under the rules
+    let head = client.package(&name).await?.state.head().clone().unwrap();
+    let mut record = PackageRecord {
+        prev: Some(head.digest),
+        version: PACKAGE_RECORD_VERSION,
+        timestamp: SystemTime::now(),
+        entries: vec![PackageEntry::grant(maintainer_pub, [Permission::Release])?],
+        entry_signatures: Vec::new(),
+        publish_token: None,
+    };
+    record.prove_key_possession(0, &maintainer_priv)?;
+    let record = ProtoEnvelope::signed_contents(&signing_key, record)?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+    api::Client::new(config.home_url.as_ref().unwrap(), None)?
+        .publish_package_record(
+            None,
+            &LogId::package_log::<Sha256>(&name),
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(ProtoEnvelopeBody::from(record)),
+                content_sources: Default::default(),
+                expected_head: None,
+                copublication: None,
+            },
+        )
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    client.fetch_package(&name).await?;
+    assert_eq!(
+        client
+            .package(&name)
+            .await?
+            .state
+            .head()
+            .clone()
+            .unwrap()
+            .digest,
+        record_id
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_yanks_version_ranges() -> Result<()> {
+    let root = root().await?;
+    let events = EventBus::default();
+    let mut receiver = events.subscribe();
+    let config = server_config(&root).with_event_bus(events);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+
+    let name = PackageName::new("test:vulnerable")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    for (index, version) in ["1.0.0", "1.1.0", "2.0.0"].into_iter().enumerate() {
+        publish_component(
+            &client,
+            &name,
+            version,
+            "(component)",
+            index == 0,
+            &signing_key,
+        )
+        .await?;
+    }
+
+    let yank = |range: &str| {
+        client.publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::YankRange {
+                    range: range.parse().unwrap(),
+                }],
+            },
+        )
+    };
+
+    let record_id = yank("<2").await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    assert!(client.download(&name, &"<2".parse()?).await?.is_none());
+    assert!(client.download(&name, &"^2".parse()?).await?.is_some());
+
+    // An event is published for each yanked version
+    let mut yanked = Vec::new();
+    while yanked.len() < 2 {
+        if let Event::VersionYanked { version, .. } =
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await??
+        {
+            yanked.push(version.to_string());
+        }
+    }
+    assert_eq!(yanked, ["1.0.0", "1.1.0"]);
+
+    // A range matching no unyanked release is rejected
+    let record_id = yank("<2").await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_detects_release_bursts_after_grants() -> Result<()> {
+    let root = root().await?;
+    let events = EventBus::default();
+    let mut receiver = events.subscribe();
+    let monitor = AnomalyMonitor::new()
+        .with_detector(GrantReleaseBurst::new(Duration::from_secs(60 * 60), 2));
+    let config = server_config(&root)
+        .with_event_bus(events)
+        .with_anomaly_monitor(monitor.clone());
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+
+    let name = PackageName::new("test:burst")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+
+    let record_id = client
+        .publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Grant {
+                    key: generate_p256_pair().0,
+                    permissions: vec![Permission::Release],
+                }],
+            },
+        )
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    for version in ["1.1.0", "1.2.0"] {
+        publish_component(&client, &name, version, "(component)", false, &signing_key).await?;
+    }
+
+    // The second release after the grant crosses the threshold
+    let version = loop {
+        if let Event::AnomalyDetected {
+            name: anomalous,
+            detector,
+            record_id,
+            ..
+        } = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await??
+        {
+            assert_eq!(anomalous.as_ref(), Some(&name));
+            assert_eq!(detector, "grant-release-burst");
+            break client
+                .package(&name)
+                .await?
+                .state
+                .stored_releases()
+                .find(|r| r.record_id == record_id)
+                .map(|r| r.version.to_string());
+        }
+    };
+    assert_eq!(version.as_deref(), Some("1.2.0"));
+
+    let baseline = monitor
+        .baseline(&LogId::package_log::<Sha256>(&name))
+        .await
+        .context("package has no baseline")?;
+    assert_eq!(baseline.records(), 4);
+    assert_eq!(baseline.entries(EntryKind::Grant), 1);
+    assert_eq!(baseline.entries(EntryKind::Release), 3);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_downloads_a_verified_artifact() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) = spawn_server(&root, None, None, None).await?;
+
+    let name = PackageName::new("test:artifact")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    let mut digests = Vec::new();
+    for (index, version) in ["1.0.0", "1.1.0"].into_iter().enumerate() {
+        digests.push(
+            publish_component(
+                &client,
+                &name,
+                version,
+                &format!("(component (core module (func (export \"v{index}\"))))"),
+                index == 0,
+                &signing_key,
+            )
+            .await?,
+        );
+    }
+
+    let record_id = client
+        .publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Yank {
+                    version: "1.1.0".parse()?,
+                }],
+            },
+        )
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+
+    // Content is fetched from the registry rather than client storage
+    client.clear_content_cache().await?;
+
+    let dest = root.join("artifact.wasm");
+    let artifact = client
+        .download_to(&name, &"^1".parse()?, &dest)
+        .await?
+        .context("a version should be resolved")?;
+    assert_eq!(artifact.version.to_string(), "1.0.0");
+    assert_eq!(artifact.digest, digests[0]);
+    assert_eq!(artifact.path, dest);
+    assert_eq!(artifact.size, std::fs::metadata(&dest)?.len());
+    assert!(artifact.checkpoint.is_some());
+    assert_eq!(
+        warg_crypto::hash::HashAlgorithm::Sha256.digest(&std::fs::read(&dest)?),
+        digests[0]
+    );
+
+    assert!(client
+        .download_to(&name, &"^2".parse()?, root.join("missing.wasm"))
+        .await?
+        .is_none());
+    assert!(!root.join("missing.wasm").exists());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_resolves_channel_tags() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+
+    let name = PackageName::new("test:tagged")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    for (index, version) in ["1.0.0", "1.1.0"].into_iter().enumerate() {
+        publish_component(
+            &client,
+            &name,
+            version,
+            "(component)",
+            index == 0,
+            &signing_key,
+        )
+        .await?;
+    }
+
+    let tag = |version: &str| {
+        client.publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Tag {
+                    tag: "stable".to_string(),
+                    version: version.parse().unwrap(),
+                }],
+            },
+        )
+    };
+
+    let record_id = tag("1.0.0").await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    let download = client.download_tag(&name, "stable").await?.unwrap();
+    assert_eq!(download.version.to_string(), "1.0.0");
+    assert!(client.download_tag(&name, "nightly").await?.is_none());
+
+    // Moving the tag is resolved through the updated log
+    let record_id = tag("1.1.0").await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    let download = client.download_tag(&name, "stable").await?.unwrap();
+    assert_eq!(download.version.to_string(), "1.1.0");
+
+    // A tag of an unreleased version is rejected
+    let record_id = tag("2.0.0").await?;
+    assert!(client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_release_manifests() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+
+    let name = PackageName::new("test:manifest")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    let store = |bytes: &'static [u8]| {
+        client.content().store_content(
+            Box::pin(futures::stream::once(async move { Ok(bytes.into()) })),
+            None,
+        )
+    };
+
+    let component = store(&wat::parse_str("(component)")?.leak()[..]).await?;
+    let docs = store(b"# test:manifest").await?;
+    let release = |version: &str, docs: &AnyHash, size| {
+        client.publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: [
+                    (version == "1.0.0").then_some(PublishEntry::Init),
+                    Some(PublishEntry::Release {
+                        version: version.parse().unwrap(),
+                        content: component,
+                        encryption: None,
+                        manifest: Some(ReleaseManifest {
+                            artifacts: vec![ReleaseArtifact {
+                                name: "docs".to_string(),
+                                content: *docs,
+                                size,
+                                media_type: "text/markdown".to_string(),
+                            }],
+                        }),
+                    }),
+                ]
+                .into_iter()
+                .flatten()
+                .collect(),
+            },
+        )
+    };
+
+    // Artifacts are not components, so the content policy does not apply
+    let record_id = release("1.0.0", &docs, 15).await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    let path = client
+        .download_artifact(&name, &"1.0.0".parse()?, "docs")
+        .await?;
+    assert_eq!(std::fs::read(path)?, b"# test:manifest");
+    assert!(matches!(
+        client
+            .download_artifact(&name, &"1.0.0".parse()?, "debug-info")
+            .await,
+        Err(ClientError::PackageArtifactDoesNotExist { .. })
+    ));
+
+    // Artifacts that do not match their declared size are rejected, whether
+    // already present or uploaded
+    assert!(release("1.1.0", &docs, 16).await.is_err());
+    let changelog = store(b"- initial release").await?;
+    assert!(matches!(
+        release("1.1.0", &changelog, 16).await,
+        Err(ClientError::PublishRejected { .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_verifies_checkpoint_freshness() -> Result<()> {
+    let root = root().await?;
+    let config = server_config(&root).with_freshness_interval(Duration::from_secs(600));
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+
+    let name = PackageName::new("test:fresh")?;
+    let client = create_client(&config)?;
+    publish_component(
+        &client,
+        &name,
+        "0.1.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    drop(client);
+
+    // The new checkpoint was asserted fresh when it was created
+    create_client(&config)?
+        .with_freshness_window(Duration::from_secs(60))
+        .update()
+        .await?;
+
+    // Without new checkpoints or a re-signing, the assertion ages past the window
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    match create_client(&config)?
+        .with_freshness_window(Duration::from_secs(1))
+        .update()
+        .await
+    {
+        Err(ClientError::StaleCheckpoint { age, window }) => {
+            assert!(age > window);
+        }
+        Err(e) => panic!("unexpected update error: {e}"),
+        Ok(()) => panic!("expected update to fail"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_a_package_filter() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+
+    let name = PackageName::new("test:filtered")?;
+    let missing = PackageName::new("test:missing")?;
+    let client = create_client(&config)?;
+    assert!(!client.package_may_exist(&name).await?);
+
+    publish_component(
+        &client,
+        &name,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    // The filter is re-signed with the checkpoint that sequenced the release
+    assert!(client.package_may_exist(&name).await?);
+    assert!(!client.package_may_exist(&missing).await?);
+    assert!(client.release_may_exist(&name, &"1.0.0".parse()?).await?);
+    assert!(!client.release_may_exist(&name, &"2.0.0".parse()?).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_proves_checkpoint_deltas() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let first = PackageName::new("test:delta-first")?;
+    let second = PackageName::new("test:delta-second")?;
+    let client = create_client(&config)?;
+    publish_component(
+        &client,
+        &first,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    let from = api
+        .latest_checkpoint(None)
+        .await?
+        .into_contents()
+        .checkpoint;
+
+    publish_component(
+        &client,
+        &second,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    publish_component(
+        &client,
+        &first,
+        "1.1.0",
+        "(component)",
+        false,
+        &test_signing_key(),
+    )
+    .await?;
+    let to = api
+        .latest_checkpoint(None)
+        .await?
+        .into_contents()
+        .checkpoint;
+
+    let mut heads = Vec::new();
+    for name in [&second, &first] {
+        let info = client.fetch_package(name).await?;
+        heads.push(LogLeaf {
+            log_id: LogId::package_log::<Sha256>(name),
+            record_id: info.state.head().as_ref().unwrap().digest.clone(),
+        });
+    }
+
+    // Only the entries changed since the earlier checkpoint are included
+    let changes = api
+        .prove_delta(
+            None,
+            DeltaRequest {
+                from: from.log_length,
+                to: to.log_length,
+            },
+            &to,
+        )
+        .await?;
+    assert_eq!(changes, heads);
+
+    // The proofs don't verify against a different checkpoint
+    assert!(api
+        .prove_delta(
+            None,
+            DeltaRequest {
+                from: from.log_length,
+                to: to.log_length,
+            },
+            &from,
+        )
+        .await
+        .is_err());
+
+    // A delta from the empty log includes every entry
+    let changes = api
+        .prove_delta(
+            None,
+            DeltaRequest {
+                from: 0,
+                to: to.log_length,
+            },
+            &to,
+        )
+        .await?;
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].log_id, LogId::operator_log::<Sha256>());
+
+    match api
+        .prove_delta(
+            None,
+            DeltaRequest {
+                from: to.log_length,
+                to: from.log_length,
+            },
+            &from,
+        )
+        .await
+    {
+        Err(api::ClientError::Proof(ProofError::Message { status: 400, .. })) => {}
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_lists_changes_between_checkpoints() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let first = PackageName::new("test:changes-first")?;
+    let second = PackageName::new("test:changes-second")?;
+    let client = create_client(&config)?;
+    publish_component(
+        &client,
+        &first,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    let from = api
+        .latest_checkpoint(None)
+        .await?
+        .into_contents()
+        .checkpoint;
+
+    publish_component(
+        &client,
+        &second,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    publish_component(
+        &client,
+        &first,
+        "1.1.0",
+        "(component)",
+        false,
+        &test_signing_key(),
+    )
+    .await?;
+    let to = api
+        .latest_checkpoint(None)
+        .await?
+        .into_contents()
+        .checkpoint;
+
+    // Compute changes from a second core service sharing the server's data store
+    let (core, handle) = CoreService::<Sha256>::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store),
+        Duration::from_secs(60),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+
+    let changes = core.changes_between(from.log_length, to.log_length).await?;
+    assert_eq!(changes.added, [second]);
+    assert_eq!(changes.updated, [first]);
+    assert!(!changes.policy_changed);
+
+    // Changes from the empty log include the operator log
+    let changes = core.changes_between(0, to.log_length).await?;
+    assert_eq!(changes.added.len(), 2);
+    assert!(changes.updated.is_empty());
+    assert!(changes.policy_changed);
+
+    assert!(core
+        .changes_between(to.log_length, to.log_length)
+        .await?
+        .added
+        .is_empty());
+    assert!(core
+        .changes_between(to.log_length, from.log_length)
+        .await
+        .is_err());
+
+    drop(core);
+    handle.await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_serves_an_activity_feed() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+
+    let name = PackageName::new("test:feed")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    let record_id = client
+        .publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Yank {
+                    version: "1.0.0".parse()?,
+                }],
+            },
+        )
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+
+    let url = Url::parse(config.home_url.as_ref().unwrap())?
+        .join(paths::feed())
+        .unwrap();
+    let response = reqwest::get(url.clone()).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[reqwest::header::CONTENT_TYPE],
+        "application/atom+xml"
+    );
+
+    // Entries are newest first
+    let feed = response.text().await?;
+    assert!(feed.contains(&format!("<id>{url}</id>")));
+    let titles = feed
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("<title>"))
+        .filter_map(|line| line.strip_suffix("</title>"))
+        .skip(1)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        titles,
+        [
+            "test:feed 1.0.0 yanked",
+            "test:feed 1.0.0 released",
+            "test:feed created"
+        ]
+    );
+
+    // The limit counts records
+    let feed = reqwest::get(format!("{url}?limit=1")).await?.text().await?;
+    assert_eq!(feed.matches("<entry>").count(), 1);
+
+    let response = reqwest::get(format!("{url}?limit=0")).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_checkpoints_batches_and_flushes() -> Result<()> {
+    // A batch size of one sequences records without waiting for the interval
+    let root = root().await?;
+    let config = server_config(&root)
+        .with_checkpoint_interval(Duration::from_secs(3600))
+        .with_checkpoint_batch_size(1);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = create_client(&config)?;
+    publish_component(
+        &client,
+        &PackageName::new("test:batched")?,
+        "1.0.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    // Flushing checkpoints records appended since the last checkpoint
+    let store = MemoryDataStore::default();
+    let (core, handle) = CoreService::<Sha256>::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(store.clone()),
+        Duration::from_secs(3600),
+        None,
+        Duration::from_secs(3600),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    let (_, denied) = generate_p256_pair();
+    core.deny_keys([denied.public_key().fingerprint()]).await?;
+    let latest = || async {
+        anyhow::Ok(
+            store
+                .get_latest_checkpoint()
+                .await?
+                .into_contents()
+                .checkpoint
+                .log_length,
+        )
+    };
+    assert_eq!(latest().await?, 1);
+
+    let checkpoint = core.flush_now().await?;
+    assert_eq!(checkpoint.log_length, 2);
+    assert_eq!(latest().await?, 2);
+    assert_eq!(core.flush_now().await?, checkpoint);
+
+    drop(core);
+    handle.await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_sequences_with_a_single_writer() -> Result<()> {
+    let root = root().await?;
+    let lock = root.join("sequencer.lock");
+    let store = MemoryDataStore::default();
+    let start = |holder: &str| {
+        CoreService::<Sha256>::start(
+            test_operator_key(),
+            test_namespaces(),
+            Box::new(store.clone()),
+            Duration::from_secs(3600),
+            None,
+            Duration::from_secs(3600),
+            EventBus::default(),
+            0,
+            Some(std::sync::Arc::new(FileLease::with_holder(
+                &lock,
+                Duration::from_secs(3600),
+                holder,
+            ))),
+        )
+    };
+
+    // Only one sequencer may hold the lease at a time
+    let (core, handle) = start("first").await?;
+    assert!(matches!(
+        start("second").await,
+        Err(CoreServiceError::LeaseUnavailable(LeaseError::Held { holder })) if holder == "first"
+    ));
+    assert_eq!(core.flush_now().await?.log_length, 1);
+
+    // A sequencer that has lost its lease stops signing checkpoints
+    std::fs::write(&lock, "second")?;
+    assert!(matches!(
+        core.flush_now().await,
+        Err(CoreServiceError::LeaseLost(err)) if matches!(&*err, LeaseError::Lost { holder } if holder == "second")
+    ));
+    drop(core);
+    handle.await?;
+
+    // The lease is released on shutdown
+    std::fs::remove_file(&lock)?;
+    let (core, handle) = start("first").await?;
+    drop(core);
+    handle.await?;
+    assert!(!lock.exists());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_round_trips_in_process() -> Result<()> {
+    let root = root().await?;
+    let (server, config) = spawn_in_process_server(&root).await?;
+
+    // Publish and download a component without any sockets
+    let name = PackageName::new("test:in-process")?;
+    let client = create_in_process_client(&server, &config)?;
+    let digest = publish_component(
+        &client,
+        &name,
+        "0.1.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+
+    let download = client
+        .download(&name, &"0.1.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(download.digest, digest);
+    assert_eq!(fs::read(&download.path)?, wat::parse_str("(component)")?);
+
+    // The checkpoint includes the published record
+    let api = api::Client::new(server.url().as_str(), None)?.with_transport(server.transport());
+    let checkpoint = api.latest_checkpoint(None).await?;
+    assert_eq!(checkpoint.as_ref().checkpoint.log_length, 2);
+
+    drop(client);
+    drop(api);
+    server.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_persists_a_local_registry() -> Result<()> {
+    let root = root().await?;
+    let dir = root.join("local");
+    let client_config = |name: &str, url: &Url| warg_client::Config {
+        home_url: Some(url.to_string()),
+        registries_dir: Some(root.join(name).join("registries")),
+        content_dir: Some(root.join(name).join("content")),
+        namespace_map_path: Some(root.join(name).join("namespaces")),
+        keys: IndexSet::new(),
+        keyring_auth: false,
+        ignore_federation_hints: false,
+        auto_accept_federation_hints: false,
+        disable_interactive: true,
+        storage_layout: Default::default(),
+    };
+
+    // Publish to the registry and close it
+    let name = PackageName::new("test:local")?;
+    let registry = LocalRegistry::open(&dir, server_config(&root)).await?;
+    let config = client_config("publisher", registry.url());
+    let client = create_client(&config)?.with_transport(registry.transport());
+    publish_component(
+        &client,
+        &name,
+        "0.1.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    drop(client);
+    registry.close().await?;
+
+    // A fresh client of the reopened registry resolves the release
+    let registry = LocalRegistry::open(&dir, server_config(&root)).await?;
+    let client = create_client(&client_config("resolver", registry.url()))?
+        .with_transport(registry.transport());
+    let download = client
+        .download(&name, &"0.1.0".parse()?)
+        .await?
+        .context("failed to resolve package")?;
+    assert_eq!(fs::read(&download.path)?, wat::parse_str("(component)")?);
+    drop(client);
+
+    // The original client continues from its stored checkpoint
+    let client = create_client(&config)?.with_transport(registry.transport());
+    publish_component(
+        &client,
+        &name,
+        "0.2.0",
+        "(component)",
+        false,
+        &test_signing_key(),
+    )
+    .await?;
+    drop(client);
+    registry.close().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rebases_onto_a_moved_head() -> Result<()> {
+    let root = root().await?;
+    let (server, config) = spawn_in_process_server(&root).await?;
+    let signing_key = test_signing_key();
+
+    let name = PackageName::new("test:rebase")?;
+    let client = create_in_process_client(&server, &config)?;
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+    let stale_head = client
+        .fetch_package(&name)
+        .await?
+        .state
+        .head()
+        .as_ref()
+        .map(|h| h.digest.clone());
+    publish_component(&client, &name, "0.2.0", "(component)", false, &signing_key).await?;
+    let current_head = client
+        .fetch_package(&name)
+        .await?
+        .state
+        .head()
+        .as_ref()
+        .map(|h| h.digest.clone());
+
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move {
+                Ok(wat::parse_str("(component)")?.into())
+            })),
+            None,
+        )
+        .await?;
+    let info = |version: &str| PublishInfo {
+        name: name.clone(),
+        head: stale_head.clone(),
+        entries: vec![PublishEntry::Release {
+            version: version.parse().unwrap(),
+            content: digest,
+            encryption: None,
+            manifest: None,
+        }],
+    };
+
+    // A record built against a stale head is rejected with the current head
+    match client.publish_with_info(&signing_key, info("0.3.0")).await {
+        Err(ClientError::HeadMismatch {
+            current_head: head, ..
+        }) => assert_eq!(head, current_head),
+        Err(e) => panic!("unexpected publish error: {e}"),
+        Ok(_) => panic!("expected publish to fail"),
+    }
+
+    // Entries that conflict with the current head are not rebased
+    drop(client);
+    let client = create_in_process_client(&server, &config)?.with_auto_rebase(true);
+    match client.publish_with_info(&signing_key, info("0.2.0")).await {
+        Err(ClientError::RebaseConflict { head, .. }) => assert_eq!(Some(head), current_head),
+        Err(e) => panic!("unexpected publish error: {e}"),
+        Ok(_) => panic!("expected publish to fail"),
+    }
+
+    // Other entries are rebased onto the current head and re-signed
+    let record_id = client
+        .publish_with_info(&signing_key, info("0.3.0"))
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+    let package = client.fetch_package(&name).await?;
+    assert_eq!(
+        package.state.head().as_ref().map(|h| &h.digest),
+        Some(&record_id)
+    );
+    assert!(package.state.release(&"0.3.0".parse()?).is_some());
+
+    drop(client);
+    server.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_reports_duplicate_releases() -> Result<()> {
+    let root = root().await?;
+    let (server, config) = spawn_in_process_server(&root).await?;
+    let signing_key = test_signing_key();
+
+    let name = PackageName::new("test:duplicate")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let client = create_in_process_client(&server, &config)?;
+    let content =
+        publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+    let head = client
+        .fetch_package(&name)
+        .await?
+        .state
+        .head()
+        .as_ref()
+        .map(|h| h.digest.clone());
+
+    // Two records race to release the same version against the same head
+    let api = api::Client::new(server.url().as_str(), None)?.with_transport(server.transport());
+    let mut record_ids = Vec::new();
+    for _ in 0..2 {
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: head.clone(),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::Release {
+                    version: "1.0.0".parse()?,
+                    content,
+                    encryption: None,
+                    manifest: None,
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?;
+        record_ids.push(RecordId::package_record::<Sha256>(&record));
+        api.publish_package_record(
+            None,
+            &log_id,
+            PublishRecordRequest {
+                package_name: Cow::Borrowed(&name),
+                record: Cow::Owned(ProtoEnvelopeBody::from(record)),
+                content_sources: Default::default(),
+                expected_head: None,
+                copublication: None,
+            },
+        )
+        .await?;
+    }
+
+    // One record wins and the client reports it as the reason the other was rejected
+    let mut published = Vec::new();
+    let mut rejected = Vec::new();
+    for record_id in &record_ids {
+        match client
+            .wait_for_publish(&name, record_id, Duration::from_millis(100))
+            .await
+        {
+            Ok(()) => published.push(record_id.clone()),
+            Err(ClientError::DuplicateRelease {
+                version,
+                released_by,
+                ..
+            }) => {
+                assert_eq!(version.to_string(), "1.0.0");
+                rejected.push((record_id.clone(), released_by));
+            }
+            Err(e) => panic!("unexpected publish error: {e}"),
+        }
+    }
+    assert_eq!(published.len(), 1);
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].1, published[0]);
+
+    drop(client);
+    drop(api);
+    server.shutdown().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_only_reports_duplicate_releases_for_duplicates() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let name = PackageName::new("test:rejected-duplicate")?;
+    let client = create_client(&config)?;
+    let signing_key = test_signing_key();
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+
+    // Releasing the version again with content rejected by policy reports the
+    // policy rejection rather than a conflict with the existing release
+    match publish(&client, &name, "1.0.0", Vec::new(), false, &signing_key)
+        .await
+        .expect_err("expected publish to fail")
+        .downcast::<ClientError>()
+    {
+        Ok(ClientError::PublishRejected { record_id, .. }) => {
+            match client
+                .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+                .await
+            {
+                Err(ClientError::PublishRejected { reason, .. }) => {
+                    assert!(reason.contains("rejected by policy"), "{reason}");
+                }
+                Err(e) => panic!("unexpected publish error: {e}"),
+                Ok(()) => panic!("expected publish to fail"),
+            }
+        }
+        other => panic!("expected a content policy rejection error: {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_compares_exported_registry_states() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (_server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+
+    let name = PackageName::new("test:monitor")?;
+    let client = create_client(&config)?;
+    let signing_key = test_signing_key();
+
+    // Export the registry state before and after a second publish
+    let mut sites = Vec::new();
+    for (version, init) in [("1.0.0", true), ("2.0.0", false)] {
+        publish_component(&client, &name, version, "(component)", init, &signing_key).await?;
+
+        let (core, handle) = CoreService::start(
+            test_operator_key(),
+            test_namespaces(),
+            Box::new(store.clone()),
+            Duration::from_secs(60),
+            None,
+            Duration::from_secs(60),
+            EventBus::default(),
+            0,
+            None,
+        )
+        .await?;
+        let site = root.join(format!("site-{version}"));
+        StaticSiteExporter::new(&core, root.join("server").join("files"))
+            .export(&site)
+            .await?;
+        drop(core);
+        handle.await?;
+
+        sites.push(StaticSiteClient::new(
+            Url::from_directory_path(&site).unwrap(),
+        ));
+    }
+
+    let report = monitor::compare(&sites[0], &sites[0]).await?;
+    assert!(report.is_consistent());
+
+    // The first divergent record is the second release
+    let report = monitor::compare(&sites[0], &sites[1]).await?;
+    assert!(!report.is_consistent());
+    assert_eq!(report.a.log_length, 2);
+    assert_eq!(report.b.log_length, 3);
+    let record = report
+        .record
+        .as_ref()
+        .context("expected a divergent record")?;
+    assert_eq!(record.registry_index, 2);
+    assert!(record.a.is_none());
+    let leaf = record.b.as_ref().context("expected a leaf")?;
+    assert_eq!(leaf.log_id, LogId::package_log::<Sha256>(&name));
+
+    let map_entry = report
+        .map_entry
+        .as_ref()
+        .context("expected a divergent map entry")?;
+    assert_eq!(map_entry.log_id, leaf.log_id);
+    assert_eq!(map_entry.b.as_ref(), Some(&leaf.record_id));
+
+    // The report is serializable
+    let json = serde_json::to_string(&report)?;
+    assert_eq!(
+        serde_json::from_str::<monitor::DivergenceReport>(&json)?,
+        report
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_records_signed_by_denied_keys() -> Result<()> {
+    let root = root().await?;
+    let store = MemoryDataStore::default();
+    let (server, config) = spawn_server(&root, None, Some(Box::new(store.clone())), None).await?;
+    let signing_key = test_signing_key();
+
+    let name = PackageName::new("test:compromised")?;
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+    drop(client);
+    drop(server);
+
+    // Restart the registry, declaring the signing key compromised
+    let key_id = signing_key.public_key().fingerprint();
+    let (_server, config) = spawn_server_with_config(
+        &root,
+        server_config(&root)
+            .with_boxed_data_store(Box::new(store))
+            .with_denied_key(key_id.clone()),
+    )
+    .await?;
+
+    let client = create_client(&config)?;
+    match publish_component(&client, &name, "0.2.0", "(component)", false, &signing_key)
+        .await
+        .unwrap_err()
+        .downcast::<ClientError>()?
+    {
+        ClientError::PublishRejected { reason, .. } => {
+            assert!(
+                reason.contains("compromised"),
+                "unexpected reason: {reason}"
+            )
+        }
+        e => panic!("unexpected publish error: {e}"),
+    }
+
+    // The release published before the key was declared compromised is surfaced
+    let info = client
+        .fetch_packages([&name])
+        .await?
+        .pop()
+        .context("expected package info")?;
+    let compromised = client.compromised_releases(&info).await?;
+    assert_eq!(compromised.len(), 1);
+    assert_eq!(compromised[0].version.to_string(), "0.1.0");
+    assert_eq!(compromised[0].by, key_id);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_retroactive_key_denials() -> Result<()> {
+    let (core, handle) = CoreService::<Sha256>::start(
+        test_operator_key(),
+        test_namespaces(),
+        Box::new(MemoryDataStore::default()),
+        Duration::from_secs(3600),
+        None,
+        Duration::from_secs(3600),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    let (_, denied) = generate_p256_pair();
+    let head = core
+        .deny_keys([denied.public_key().fingerprint()])
+        .await?
+        .context("expected a deny record")?;
+
+    // Denying a key as of a length the registry log has passed is rejected
+    let (_, retroactive) = generate_p256_pair();
+    let record = ProtoEnvelope::signed_contents(
+        &test_operator_key(),
+        OperatorRecord {
+            prev: Some(head),
+            version: 0,
+            timestamp: SystemTime::now(),
+            entries: vec![OperatorEntry::DenyKey {
+                key_id: retroactive.public_key().fingerprint(),
+                log_length: 0,
+            }],
+        },
+    )?;
+    match core.append_operator_record(&record).await {
+        Err(CoreServiceError::DataStore(DataStoreError::OperatorValidationFailed(e))) => {
+            assert!(e.to_string().contains("cannot be denied"), "{e}");
+        }
+        other => panic!("expected a retroactive denial to be rejected: {other:?}"),
+    }
+
+    drop(core);
+    handle.await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_encrypted_content() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) =
+        spawn_server_with_config(&root, server_config(&root).with_encrypted_content(true)).await?;
+    let signing_key = test_signing_key();
+    let (recipient_pub, recipient) = generate_p256_pair();
+
+    // Encrypted content is exempt from the Wasm content policy when allowed
+    let content = b"proprietary component".to_vec();
+    let (ciphertext, encryption) = ContentEncryption::encrypt(&content, [&recipient_pub])?;
+
+    let name = PackageName::new("test:encrypted")?;
+    let client = create_client(&config)?;
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(ciphertext.into()) })),
+            None,
+        )
+        .await?;
+    let record_id = client
+        .publish_with_info(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![
+                    PublishEntry::Init,
+                    PublishEntry::Release {
+                        version: "1.0.0".parse().unwrap(),
+                        content: digest,
+                        encryption: Some(encryption.clone()),
+                        manifest: None,
+                    },
+                ],
+            },
+        )
+        .await?;
+    client
+        .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+        .await?;
+
+    client.clear_content_cache().await?;
+    let download = client
+        .download_exact(&name, &"1.0.0".parse().unwrap())
+        .await?;
+    assert_eq!(download.digest, digest);
+    assert_eq!(download.encryption, Some(encryption));
+    assert_eq!(download.decrypt(&recipient).await?, content);
+
+    match download.decrypt(&signing_key).await.unwrap_err() {
+        ClientError::ContentDecryptionFailed {
+            source: EncryptionError::NotRecipient(key_id),
+            ..
+        } => assert_eq!(key_id, signing_key.public_key().fingerprint()),
+        e => panic!("unexpected decryption error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_applies_the_content_policy_to_encrypted_content_by_default() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let signing_key = test_signing_key();
+    let (recipient_pub, _) = generate_p256_pair();
+    let (ciphertext, encryption) =
+        ContentEncryption::encrypt(b"proprietary component", [&recipient_pub])?;
+
+    let name = PackageName::new("test:encrypted")?;
+    let client = create_client(&config)?;
+    let digest = client
+        .content()
+        .store_content(
+            Box::pin(futures::stream::once(async move { Ok(ciphertext.into()) })),
+            None,
+        )
+        .await?;
+    let result = async {
+        let record_id = client
+            .publish_with_info(
+                &signing_key,
+                PublishInfo {
+                    name: name.clone(),
+                    head: None,
+                    entries: vec![
+                        PublishEntry::Init,
+                        PublishEntry::Release {
+                            version: "1.0.0".parse().unwrap(),
+                            content: digest,
+                            encryption: Some(encryption),
+                            manifest: None,
+                        },
+                    ],
+                },
+            )
+            .await?;
+        client
+            .wait_for_publish(&name, &record_id, Duration::from_millis(100))
+            .await
+    }
+    .await;
+
+    match result {
+        Err(ClientError::PublishRejected { reason, .. }) => {
+            assert!(reason.contains("not valid WebAssembly"), "{reason}")
+        }
+        other => panic!("expected the content policy to reject the content: {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_authenticates_requests() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) = spawn_server_with_config(
+        &root,
+        server_config(&root).with_authenticator(
+            BearerTokenAuthenticator::new()
+                .with_token(Access::Read, "reader")
+                .with_token(Access::Publish, "publisher"),
+        ),
+    )
+    .await?;
+    let url = config.home_url.as_ref().unwrap();
+
+    // Requests without a token are rejected
+    match api::Client::new(url, None)?
+        .latest_checkpoint(None)
+        .await
+        .unwrap_err()
+    {
+        api::ClientError::Fetch(FetchError::Message { status, .. }) => assert_eq!(status, 401),
+        e => panic!("unexpected error: {e}"),
+    }
+
+    // A read token may fetch, but not publish
+    api::Client::new(url, None)?
+        .with_auth_provider(BearerToken::new("reader"))
+        .latest_checkpoint(None)
+        .await?;
+
+    let client = create_client(&config)?.with_auth_provider(BearerToken::new("reader"));
+
+    let name = PackageName::new("test:auth")?;
+    let signing_key = test_signing_key();
+    match publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key)
+        .await
+        .unwrap_err()
+        .downcast::<ClientError>()?
+    {
+        ClientError::Api(api::ClientError::Package(PackageError::Message { status, .. })) => {
+            assert_eq!(status, 403)
+        }
+        e => panic!("unexpected publish error: {e}"),
+    }
+    drop(client);
+
+    // A publish token may publish and download the content
+    let client = create_client(&config)?.with_auth_provider(BearerToken::new("publisher"));
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+    client.clear_content_cache().await?;
+    client
+        .download_exact(&name, &"0.1.0".parse().unwrap())
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_with_a_publish_token() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let signing_key = test_signing_key();
+    let (ci_pub, ci_key) = generate_p256_pair();
+
+    let name = PackageName::new("test:token")?;
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+
+    // The CI key may not release the package without a token
+    let other = PackageName::new("test:other")?;
+    publish_component(&client, &other, "0.1.0", "(component)", true, &signing_key).await?;
+    match publish_component(&client, &name, "0.2.0", "(component)", false, &ci_key)
+        .await
+        .unwrap_err()
+        .downcast::<ClientError>()?
+    {
+        ClientError::Unauthorized(_) => {}
+        e => panic!("unexpected publish error: {e}"),
+    }
+
+    let token = PublishToken::issue(
+        &signing_key,
+        ci_pub.clone(),
+        [name.clone()],
+        SystemTime::now() + Duration::from_secs(3600),
+    )?;
+    let token: PublishToken = token.to_string().parse()?;
+    let client = client.with_publish_token(token);
+
+    publish_component(&client, &name, "0.2.0", "(component)", false, &ci_key).await?;
+    let package = client.package(&name).await?;
+    let release = package
+        .state
+        .release(&"0.2.0".parse().unwrap())
+        .expect("release should exist");
+    assert_eq!(release.by, signing_key.public_key().fingerprint());
+
+    // The token does not authorize releases of other packages
+    match publish_component(&client, &other, "0.2.0", "(component)", false, &ci_key)
+        .await
+        .unwrap_err()
+        .downcast::<ClientError>()?
+    {
+        ClientError::Unauthorized(message) => assert!(message.contains("test:other")),
+        e => panic!("unexpected publish error: {e}"),
+    }
+
+    // A token scoped to more than one package is not accepted for any of them
+    let token = PublishToken::issue(
+        &signing_key,
+        ci_pub.clone(),
+        [name.clone(), other.clone()],
+        SystemTime::now() + Duration::from_secs(3600),
+    )?;
+    let client = client.with_publish_token(token);
+    match publish_component(&client, &name, "0.3.0", "(component)", false, &ci_key)
+        .await
+        .unwrap_err()
+        .downcast::<ClientError>()?
+    {
+        ClientError::Unauthorized(message) => assert!(message.contains("alone"), "{message}"),
+        e => panic!("unexpected publish error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_replayed_publish_requests() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let name = PackageName::new("test:replayed")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let signing_key = test_signing_key();
+    let record = ProtoEnvelope::signed_contents(
+        &signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        },
+    )?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+    let request = || PublishRecordRequest {
+        package_name: Cow::Borrowed(&name),
+        record: Cow::Owned(ProtoEnvelopeBody::from(record.clone())),
+        content_sources: Default::default(),
+        expected_head: None,
+        copublication: None,
+    };
+
+    client
+        .publish_package_record(None, &log_id, request())
+        .await?;
+
+    // Replaying the captured request is rejected by its record id
+    match client
+        .publish_package_record(None, &log_id, request())
+        .await
+    {
+        Err(api::ClientError::Package(PackageError::RecordAlreadySubmitted(id))) => {
+            assert_eq!(id, record_id)
+        }
+        Err(e) => panic!("unexpected publish error: {e}"),
+        Ok(_) => panic!("expected publish to fail"),
+    }
+
+    // A replay racing the original request is rejected by the data store
+    let name = PackageName::new("test:raced")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let record = ProtoEnvelope::signed_contents(
+        &signing_key,
+        PackageRecord {
+            prev: None,
+            version: PACKAGE_RECORD_VERSION,
+            timestamp: SystemTime::now(),
+            entries: vec![PackageEntry::Init {
+                hash_algorithm: HashAlgorithm::Sha256,
+                key: signing_key.public_key(),
+            }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
+        },
+    )?;
+    let record_id = RecordId::package_record::<Sha256>(&record);
+    let request = || PublishRecordRequest {
+        package_name: Cow::Borrowed(&name),
+        record: Cow::Owned(ProtoEnvelopeBody::from(record.clone())),
+        content_sources: Default::default(),
+        expected_head: None,
+        copublication: None,
+    };
+    let (first, second) = tokio::join!(
+        client.publish_package_record(None, &log_id, request()),
+        client.publish_package_record(None, &log_id, request())
+    );
+    let rejected = match (first, second) {
+        (Ok(_), Err(e)) | (Err(e), Ok(_)) => e,
+        (first, second) => panic!(
+            "expected exactly one publish to succeed: {:?}, {:?}",
+            first.err(),
+            second.err()
+        ),
+    };
+    assert!(matches!(
+        rejected,
+        api::ClientError::Package(PackageError::RecordAlreadySubmitted(id)) if id == record_id
+    ));
+
+    let store = MemoryDataStore::default();
+    store
+        .store_package_record(&log_id, &name, &record_id, &record, &IndexSet::new())
+        .await?;
+    assert!(matches!(
+        store
+            .store_package_record(&log_id, &name, &record_id, &record, &IndexSet::new())
+            .await,
+        Err(DataStoreError::RecordExists(id)) if id == record_id
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_rejects_records_outside_the_time_window() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+
+    let name = PackageName::new("test:timestamped")?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let signing_key = test_signing_key();
+    let now = SystemTime::now();
+    for timestamp in [
+        now + Duration::from_secs(60 * 60),
+        now - Duration::from_secs(2 * 24 * 60 * 60),
+    ] {
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp,
+                entries: vec![PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: signing_key.public_key(),
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?;
+
+        match client
+            .publish_package_record(
+                None,
+                &log_id,
+                PublishRecordRequest {
+                    package_name: Cow::Borrowed(&name),
+                    record: Cow::Owned(ProtoEnvelopeBody::from(record)),
+                    content_sources: Default::default(),
+                    expected_head: None,
+                    copublication: None,
+                },
+            )
+            .await
+        {
+            Err(api::ClientError::Package(PackageError::Message { status, message })) => {
+                assert_eq!(status, 400);
+                assert!(message.contains("invalid record timestamp"), "{message}");
+            }
+            Err(e) => panic!("unexpected publish error: {e}"),
+            Ok(_) => panic!("expected publish to fail"),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_recovers_from_a_record_archive() -> Result<()> {
+    let store = MemoryDataStore::default();
+    let (_server, config) =
+        spawn_server(&root().await?, None, Some(Box::new(store.clone())), None).await?;
+
+    let client = create_client(&config)?;
+    let signing_key = test_signing_key();
+    for name in ["test:recovered", "test:also-recovered"] {
+        publish_component(
+            &client,
+            &PackageName::new(name)?,
+            "1.0.0",
+            "(component)",
+            true,
+            &signing_key,
+        )
+        .await?;
+    }
+
+    // Archive the registry's records in registry order
+    let checkpoint = store
+        .get_latest_checkpoint()
+        .await?
+        .into_contents()
+        .checkpoint;
+    let leafs = store
+        .get_log_leafs_starting_with_registry_index(0, 100)
+        .await?;
+    assert_eq!(leafs.len(), checkpoint.log_length);
+    let operator_log_id = LogId::operator_log::<Sha256>();
+    let package_log_ids = leafs
+        .iter()
+        .map(|(_, leaf)| leaf.log_id.clone())
+        .filter(|id| *id != operator_log_id)
+        .collect::<Vec<_>>();
+    let names = store.get_package_names(&package_log_ids).await?;
+    let mut archive = Vec::new();
+    for (_, LogLeaf { log_id, record_id }) in leafs {
+        archive.push(match names.get(&log_id).cloned().flatten() {
+            Some(name) => ArchivedRecord::Package {
+                name,
+                envelope: store
+                    .get_package_record(&log_id, &record_id)
+                    .await?
+                    .envelope,
+            },
+            None => ArchivedRecord::Operator(
+                store
+                    .get_operator_record(&log_id, &record_id)
+                    .await?
+                    .envelope,
+            ),
+        });
+    }
+
+    // Add records that do not validate
+    archive.insert(
+        1,
+        ArchivedRecord::Operator(ProtoEnvelope::signed_contents(
+            &signing_key,
+            OperatorRecord {
+                prev: None,
+                version: 0,
+                timestamp: SystemTime::now(),
+                entries: vec![OperatorEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: signing_key.public_key(),
+                }],
+            },
+        )?),
+    );
+    archive.push(ArchivedRecord::Package {
+        name: PackageName::new("undefined:package")?,
+        envelope: ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::Init {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    key: signing_key.public_key(),
+                }],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?,
+    });
+
+    let operator_key = test_operator_key();
+    let recovery = Recoverer::new(operator_key.clone(), [operator_key.public_key()])
+        .with_checkpoint_interval(2)
+        .recover(archive)?;
+    let report = recovery.report();
+    assert_eq!(report.records, checkpoint.log_length);
+    assert_eq!(report.packages, 2);
+    assert_eq!(
+        report
+            .rejected
+            .iter()
+            .map(|r| (r.index, r.log_id.clone()))
+            .collect::<Vec<_>>(),
+        [
+            (1, operator_log_id),
+            (
+                checkpoint.log_length + 1,
+                LogId::package_log::<Sha256>(&PackageName::new("undefined:package")?)
+            )
+        ]
+    );
+    assert!(matches!(
+        report.rejected[1].error,
+        DataStoreError::PackageNamespaceNotDefined(_)
+    ));
+
+    // The fresh checkpoint chain ends with the registry's latest checkpoint
+    let lengths = recovery
+        .checkpoints()
+        .iter()
+        .map(|c| c.as_ref().checkpoint.log_length)
+        .collect::<Vec<_>>();
+    assert_eq!(lengths, [2, 3]);
+    assert_eq!(
+        recovery.checkpoints().last().unwrap().as_ref().checkpoint,
+        checkpoint
+    );
+
+    // A registry restored from the recovery starts with the recovered state
+    let restored = MemoryDataStore::default();
+    recovery.restore(&restored).await?;
+    let (core, handle) = CoreService::<Sha256>::start(
+        operator_key,
+        test_namespaces(),
+        Box::new(restored.clone()),
+        Duration::from_secs(60),
+        None,
+        Duration::from_secs(60),
+        EventBus::default(),
+        0,
+        None,
+    )
+    .await?;
+    assert_eq!(
+        restored
+            .get_latest_checkpoint()
+            .await?
+            .into_contents()
+            .checkpoint,
+        checkpoint
+    );
+    drop(core);
+    handle.await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_executes_signed_admin_commands() -> Result<()> {
+    let root = root().await?;
+    let (next_pub, next_priv) = generate_p256_pair();
+    let (_server, config) = spawn_server_with_config(
+        &root,
+        server_config(&root).with_next_operator_key(next_priv),
+    )
+    .await?;
+
+    let name = PackageName::new("test:admin")?;
+    let signing_key = test_signing_key();
+    let operator_key = test_operator_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+
+    // Commands signed by a key without the admin permission are rejected
+    match client
+        .admin(
+            &signing_key,
+            [AdminCommand::FreezePackage { name: name.clone() }],
+        )
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api(api::ClientError::Admin(AdminError::Unauthorized(_))) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    // A frozen package rejects new records
+    let response = client
+        .admin(
+            &operator_key,
+            [AdminCommand::FreezePackage { name: name.clone() }],
+        )
+        .await?;
+    assert_eq!(response.results, ["package `test:admin` is frozen"]);
+    let error = publish_component(&client, &name, "0.2.0", "(component)", false, &signing_key)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("frozen"), "{error}");
+
+    // Commands build on the operator log, where the package is now frozen
+    let response = client
+        .admin(
+            &operator_key,
+            [
+                AdminCommand::UnfreezePackage { name: name.clone() },
+                AdminCommand::RevalidatePackage { name: name.clone() },
+                AdminCommand::CollectGarbage,
+            ],
+        )
+        .await?;
+    assert_eq!(
+        response.results,
+        [
+            "package `test:admin` is unfrozen",
+            "validated 1 record(s) of package `test:admin`",
+            "removed 0 unreferenced file(s)",
+        ]
+    );
+    publish_component(&client, &name, "0.2.0", "(component)", false, &signing_key).await?;
+
+    // The checkpoint key can only be rotated to the configured next key
+    let (other_pub, _) = generate_p256_pair();
+    match client
+        .admin(
+            &operator_key,
+            [AdminCommand::RotateCheckpointKey { key: other_pub }],
+        )
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api(api::ClientError::Admin(AdminError::NotSupported(_))) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    client
+        .admin(
+            &operator_key,
+            [AdminCommand::RotateCheckpointKey {
+                key: next_pub.clone(),
+            }],
+        )
+        .await?;
+    publish_component(&client, &name, "0.3.0", "(component)", false, &signing_key).await?;
+    let ts_checkpoint = api::Client::new(config.home_url.as_ref().unwrap(), None)?
+        .latest_checkpoint(None)
+        .await?;
+    assert_eq!(ts_checkpoint.key_id(), &next_pub.fingerprint());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_runs_client_middleware() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+    use warg_client::transport::{self, Middleware, Next, TransportRequest};
+
+    // Records the path of each request and tags it with a header
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    #[async_trait::async_trait]
+    impl Middleware for Recorder {
+        async fn handle(
+            &self,
+            mut request: TransportRequest,
+            next: Next<'_>,
+        ) -> Result<reqwest::Response, api::ClientError> {
+            self.0.lock().unwrap().push(request.url.path().to_string());
+            request
+                .headers
+                .insert("x-test-middleware", "recorded".parse().unwrap());
+            next.run(request).await
+        }
+    }
+
+    // Answers checkpoint requests without contacting the registry
+    struct Unavailable;
+
+    #[async_trait::async_trait]
+    impl Middleware for Unavailable {
+        async fn handle(
+            &self,
+            request: TransportRequest,
+            next: Next<'_>,
+        ) -> Result<reqwest::Response, api::ClientError> {
+            if request.url.path().ends_with(paths::fetch_checkpoint()) {
+                return Ok(transport::response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Default::default(),
+                    "{\"status\":503,\"message\":\"unavailable\"}".into(),
+                ));
+            }
+
+            next.run(request).await
+        }
+    }
+
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let paths = Arc::new(Mutex::new(Vec::new()));
+
+    let client = create_client(&config)?.with_middleware(Recorder(paths.clone()));
+    let name = PackageName::new("test:middleware")?;
+    publish_component(
+        &client,
+        &name,
+        "0.1.0",
+        "(component)",
+        true,
+        &test_signing_key(),
+    )
+    .await?;
+    assert!(paths
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|path| path.ends_with(paths::fetch_checkpoint())));
+
+    // Middleware added later runs closer to the transport
+    let recorded = paths.lock().unwrap().len();
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?
+        .with_middleware(Recorder(paths.clone()))
+        .with_middleware(Unavailable);
+    assert!(api.latest_checkpoint(None).await.is_err());
+    assert_eq!(paths.lock().unwrap().len(), recorded + 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_injects_faults() -> Result<()> {
+    use std::num::NonZeroU64;
+    use warg_server::faults::Faults;
+
+    let root = root().await?;
+    let config = server_config(&root).with_faults(
+        Faults::new()
+            .with_checkpoint_delay(Duration::from_millis(10))
+            .with_corrupted_proofs(NonZeroU64::new(1).unwrap()),
+    );
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = create_client(&config)?;
+
+    // The client detects the corrupted inclusion proof of its own record
+    let name = PackageName::new("test:fault")?;
+    let signing_key = test_signing_key();
+    let err = publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ClientError>(),
+        Some(ClientError::Api(api::ClientError::Proof(_)))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_advisories() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) = spawn_server(&root, None, None, None).await?;
+
+    let name = PackageName::new("test:advised")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    publish_component(&client, &name, "2.0.0", "(component)", false, &signing_key).await?;
+
+    let advisory = Advisory {
+        id: "ADV-2024-0001".to_string(),
+        package: name.clone(),
+        affected: "<2.0.0".parse()?,
+        summary: "a vulnerability".to_string(),
+    };
+
+    // Advisories signed by a key without the admin permission are rejected
+    match client
+        .publish_advisories(&signing_key, [advisory.clone()])
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api(api::ClientError::Admin(AdminError::Unauthorized(_))) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    let response = client
+        .publish_advisories(&test_operator_key(), [advisory.clone()])
+        .await?;
+    assert_eq!(
+        response.results,
+        ["published advisory `ADV-2024-0001` for package `test:advised`"]
+    );
+
+    assert_eq!(
+        client.advisories_for(&name, &"1.0.0".parse()?).await?,
+        std::slice::from_ref(&advisory)
+    );
+    assert!(client
+        .advisories_for(&name, &"2.0.0".parse()?)
+        .await?
+        .is_empty());
+
+    // An advisory identifier cannot be published twice
+    match client
+        .publish_advisories(&test_operator_key(), [advisory])
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api(api::ClientError::Admin(AdminError::Rejection(_))) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_publishes_identity_claims() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) = spawn_server(&root, None, None, None).await?;
+
+    let name = PackageName::new("test:claimed")?;
+    let signing_key = test_signing_key();
+    let key_id = signing_key.public_key().fingerprint();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+    assert!(client.identities_for(&name, &key_id).await?.is_empty());
+
+    let claim = IdentityClaim::sign(&signing_key, "alice@example.com")?;
+    let response = client
+        .publish_identity_claims(&test_operator_key(), [claim.clone()])
+        .await?;
+    assert_eq!(
+        response.results,
+        [format!(
+            "key `{key_id}` is bound to identity `alice@example.com`"
+        )]
+    );
+
+    // The release can be attributed to the verified identity of its signer
+    let info = client.package(&name).await?;
+    let release = info.state.release(&"1.0.0".parse()?).unwrap();
+    assert_eq!(
+        client.identities_for(&name, &release.by).await?,
+        ["alice@example.com"]
+    );
+
+    // Claims not signed by the key they are for are rejected
+    let (other_key, _) = generate_p256_pair();
+    let forged = IdentityClaim {
+        key: other_key,
+        ..claim
+    };
+    match client
+        .publish_identity_claims(&test_operator_key(), [forged])
+        .await
+        .unwrap_err()
+    {
+        ClientError::Api(api::ClientError::Admin(AdminError::Rejection(_))) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_quarantines_rejected_records() -> Result<()> {
+    let root = root().await?;
+    let (_server, config) = spawn_server_with_config(
+        &root,
+        server_config(&root).with_quarantine(Quarantine::new(10)),
+    )
+    .await?;
+
+    let name = PackageName::new("test:quarantine")?;
+    let signing_key = test_signing_key();
+    let operator_key = test_operator_key();
+    let client = create_client(&config)?;
+    publish_component(&client, &name, "0.1.0", "(component)", true, &signing_key).await?;
+
+    // A record rejected on publish is quarantined
+    client
+        .admin(
+            &operator_key,
+            [AdminCommand::FreezePackage { name: name.clone() }],
+        )
+        .await?;
+    publish_component(&client, &name, "0.2.0", "(component)", false, &signing_key)
+        .await
+        .unwrap_err();
+
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let records = api.quarantined_records().await?;
+    assert_eq!(records.len(), 1);
+    let quarantined = &records[0];
+    assert_eq!(quarantined.package_name, name);
+    assert_eq!(quarantined.log_id, LogId::package_log::<Sha256>(&name));
+    assert_eq!(
+        quarantined.submitter,
+        signing_key.public_key().fingerprint()
+    );
+    assert!(
+        quarantined.reason.contains("frozen"),
+        "{}",
+        quarantined.reason
+    );
+    assert_eq!(
+        api.quarantined_record(quarantined.id).await?.record_id,
+        quarantined.record_id
+    );
+
+    // Requeuing a record that is still rejected keeps it quarantined
+    match api.requeue_quarantined_record(quarantined.id).await {
+        Err(api::ClientError::Admin(AdminError::Rejection(reason))) => {
+            assert!(reason.contains("frozen"), "{reason}")
+        }
+        Err(e) => panic!("unexpected admin error: {e}"),
+        Ok(_) => panic!("expected the requeued record to be rejected"),
+    }
+    assert_eq!(api.quarantined_records().await?.len(), 1);
+
+    // Once the cause is fixed, a requeued record is accepted and released
+    client
+        .admin(
+            &operator_key,
+            [AdminCommand::UnfreezePackage { name: name.clone() }],
+        )
+        .await?;
+    let record = api.requeue_quarantined_record(quarantined.id).await?;
+    assert_eq!(record.record_id, quarantined.record_id);
+    assert!(api.quarantined_records().await?.is_empty());
+    match api.quarantined_record(quarantined.id).await.unwrap_err() {
+        api::ClientError::Admin(AdminError::Message { status: 404, .. }) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_does_not_quarantine_without_a_quarantine() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    match api.quarantined_records().await.unwrap_err() {
+        api::ClientError::Admin(AdminError::NotSupported(_)) => {}
+        e => panic!("unexpected admin error: {e}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_returns_record_receipts() -> Result<()> {
+    let (_server, config) = spawn_server(&root().await?, None, None, None).await?;
+    let name = PackageName::new("test:receipt")?;
+    let signing_key = test_signing_key();
+    let client = create_client(&config)?;
+    let digest =
+        publish_component(&client, &name, "1.0.0", "(component)", true, &signing_key).await?;
+
+    let receipt = client
+        .publish_with_receipt(
+            &signing_key,
+            PublishInfo {
+                name: name.clone(),
+                head: None,
+                entries: vec![PublishEntry::Release {
+                    version: "1.1.0".parse()?,
+                    content: digest,
+                    encryption: None,
+                    manifest: None,
+                }],
+            },
+            Duration::from_millis(100),
+        )
+        .await?;
+
+    // The receipt is for the head of the package log
+    let package = client
+        .registry()
+        .load_package(None, &name)
+        .await?
+        .context("package does not exist in client storage")?;
+    let receipt_contents = receipt.as_ref();
+    assert_eq!(receipt_contents.log_id, LogId::package_log::<Sha256>(&name));
+    assert_eq!(
+        Some(&receipt_contents.record_id),
+        package.state.head().as_ref().map(|h| &h.digest)
+    );
+    assert_eq!(
+        Some(receipt_contents.registry_index),
+        package.head_registry_index
+    );
+    assert!(receipt_contents.registry_index < receipt_contents.checkpoint.log_length);
+
+    // The verified receipt is stored
+    let stored = client
+        .registry()
+        .load_receipt(None, &receipt_contents.record_id)
+        .await?
+        .context("receipt was not stored")?;
+    assert_eq!(stored.as_ref(), receipt_contents);
+    assert_eq!(stored.signature(), receipt.signature());
+
+    // Records that are not published have no receipt
+    let unknown = RecordId::from(AnyHash::from(warg_crypto::hash::Hash::<Sha256>::of(
+        "unknown",
+    )));
+    assert!(client.record_receipt(&name, &unknown).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn it_sequences_copublications_together() -> Result<()> {
+    // A batch size of one would otherwise sequence each record on its own
+    let root = root().await?;
+    let config = server_config(&root).with_checkpoint_batch_size(1);
+    let (_server, config) = spawn_server_with_config(&root, config).await?;
+    let client = create_client(&config)?;
+    let api = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let signing_key = test_signing_key();
+    let foo = PackageName::new("test:copublished-foo")?;
+    let bar = PackageName::new("test:copublished-bar")?;
+    let digest =
+        publish_component(&client, &foo, "1.0.0", "(component)", true, &signing_key).await?;
+    publish_component(&client, &bar, "1.0.0", "(component)", true, &signing_key).await?;
+
+    let release = |name: &PackageName| PublishInfo {
+        name: name.clone(),
+        head: None,
+        entries: vec![PublishEntry::Release {
+            version: "1.1.0".parse().unwrap(),
+            content: digest,
+            encryption: None,
+            manifest: None,
+        }],
+    };
+    let copublication = client
+        .publish_copublication(&signing_key, vec![release(&foo), release(&bar)])
+        .await?;
+    for part in &copublication.as_ref().parts {
+        client
+            .wait_for_publish(&part.package, &part.record_id, Duration::from_millis(100))
+            .await?;
+    }
+    assert_eq!(
+        client.check_copublication(&copublication).await?,
+        CoPublicationStatus::Complete
+    );
+
+    // A part is held until every part of the co-publication is published
+    let mut records = Vec::new();
+    for name in [&foo, &bar] {
+        let package = client.fetch_package(name).await?;
+        let record = ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: package.state.head().as_ref().map(|h| h.digest.clone()),
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::release("2.0.0", digest)?],
+                entry_signatures: Vec::new(),
+                publish_token: None,
+            },
+        )?;
+        records.push((name, RecordId::package_record::<Sha256>(&record), record));
+    }
+    let copublication = CoPublication::new(
+        signing_key.public_key(),
+        records
+            .iter()
+            .map(|(name, record_id, _)| CoPublishedRecord {
+                package: (*name).clone(),
+                record_id: record_id.clone(),
+            }),
+        SystemTime::now(),
+    )?
+    .sign(&signing_key)?;
+    let publish = |(name, _, record): &(&PackageName, RecordId, ProtoEnvelope<PackageRecord>),
+                   copublication: SerdeEnvelope<CoPublication>| {
+        let log_id = LogId::package_log::<Sha256>(name);
+        let request = PublishRecordRequest {
+            package_name: Cow::Owned((*name).clone()),
+            record: Cow::Owned(ProtoEnvelopeBody::from(record.clone())),
+            content_sources: Default::default(),
+            expected_head: None,
+            copublication: Some(copublication),
+        };
+        let api = &api;
+        async move { api.publish_package_record(None, &log_id, request).await }
+    };
+
+    // The co-publication must include the record it is published with
+    let (_, other_key) = generate_p256_pair();
+    let other = CoPublication::new(
+        other_key.public_key(),
+        copublication.as_ref().parts.clone(),
+        SystemTime::now(),
+    )?
+    .sign(&other_key)?;
+    match publish(&records[0], other).await {
+        Err(api::ClientError::Package(PackageError::Message { status: 400, .. })) => {}
+        Err(e) => panic!("unexpected publish error: {e}"),
+        Ok(_) => panic!("expected publish to fail"),
+    }
+
+    publish(&records[0], copublication.clone()).await?;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let held = api
+        .get_package_record(None, &LogId::package_log::<Sha256>(&foo), &records[0].1)
+        .await?;
+    assert!(matches!(held.state, PackageRecordState::Processing));
+    assert_eq!(
+        client.check_copublication(&copublication).await?,
+        CoPublicationStatus::Pending
+    );
+
+    publish(&records[1], copublication.clone()).await?;
+    for (name, record_id, _) in &records {
+        client
+            .wait_for_publish(name, record_id, Duration::from_millis(100))
+            .await?;
+    }
+    assert_eq!(
+        client.check_copublication(&copublication).await?,
+        CoPublicationStatus::Complete
+    );
+
+    Ok(())
+}
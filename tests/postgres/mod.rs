@@ -53,7 +53,11 @@ async fn it_works_with_postgres() -> TestResult {
     // allows any signing key
     //test_unknown_signing_key(&config).await?;
     test_invalid_signature(&config).await?;
+    test_untagged_record(&config).await?;
     test_fetch_package_names(&config).await?;
+    test_search(&config).await?;
+    test_key_records(&config).await?;
+    test_conditional_fetch(&config).await?;
     test_get_ledger(&config).await?;
 
     let mut packages = vec![
@@ -10,7 +10,8 @@ use std::{
 use url::Url;
 use warg_api::v1::{
     content::{ContentSource, ContentSourcesResponse},
-    fetch::{FetchPackageNamesRequest, FetchPackageNamesResponse},
+    fetch::{FetchLogsRequest, FetchPackageNamesRequest, FetchPackageNamesResponse},
+    key::KeyRecordsQuery,
     ledger::{LedgerSource, LedgerSourceContentType, LedgerSourcesResponse},
     package::PublishRecordRequest,
     paths,
@@ -26,7 +27,7 @@ use warg_crypto::{
     Encode, Signable,
 };
 use warg_protocol::{
-    package::{PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
+    package::{EntrySignature, PackageEntry, PackageRecord, PACKAGE_RECORD_VERSION},
     registry::{LogId, PackageName},
     ProtoEnvelope, ProtoEnvelopeBody, Version,
 };
@@ -340,6 +341,77 @@ async fn test_invalid_signature(config: &Config) -> Result<()> {
         .join(&paths::publish_package_record(&log_id))
         .unwrap();
 
+    let signing_key = test_signing_key();
+    let record = |entry_signatures| {
+        ProtoEnvelope::signed_contents(
+            &signing_key,
+            PackageRecord {
+                prev: None,
+                version: PACKAGE_RECORD_VERSION,
+                timestamp: SystemTime::now(),
+                entries: vec![PackageEntry::Init {
+                    hash_algorithm: warg_crypto::hash::HashAlgorithm::Sha256,
+                    key: signing_key.public_key(),
+                }],
+                entry_signatures,
+                publish_token: None,
+            },
+        )
+    };
+    let body = |record| PublishRecordRequest {
+        package_name: Cow::Borrowed(&name),
+        record: Cow::Owned(ProtoEnvelopeBody::from(record)),
+        content_sources: Default::default(),
+        expected_head: None,
+        copublication: None,
+    };
+    let publish = |body: serde_json::Value| {
+        let url = url.clone();
+        async move {
+            let client = reqwest::Client::new();
+            let response = client.post(url).json(&body).send().await?;
+            let status = response.status();
+            let body = response.text().await?;
+            assert_eq!(
+                status,
+                StatusCode::UNAUTHORIZED,
+                "unexpected response from server: {status}\n{body}",
+            );
+            assert!(
+                body.contains("verification failed"),
+                "unexpected response body: {body}"
+            );
+            Ok::<_, anyhow::Error>(())
+        }
+    };
+
+    // Update the signature to one that does not match the contents
+    let mut invalid = serde_json::to_value(body(record(Vec::new())?)).unwrap();
+    invalid["record"]["signature"] = serde_json::Value::String("ecdsa-p256:MEUCIQCzWZBW6ux9LecP66Y+hjmLZTP/hZVz7puzlPTXcRT2wwIgQZO7nxP0nugtw18MwHZ26ROFWcJmgCtKOguK031Y1D0=".to_string());
+    publish(invalid).await?;
+
+    // An entry signature that does not match the entry is rejected along with
+    // the envelope signature
+    let entry_signature = EntrySignature {
+        entry: 0,
+        key_id: signing_key.public_key().fingerprint(),
+        signature: signing_key.sign(b"not the entry")?,
+    };
+    publish(serde_json::to_value(body(record(vec![entry_signature])?)).unwrap()).await?;
+
+    Ok(())
+}
+
+async fn test_untagged_record(config: &Config) -> Result<()> {
+    const PACKAGE_NAME: &str = "test:untagged-record";
+
+    // Use a reqwest client directly here as the client always tags records
+    let name = PackageName::new(PACKAGE_NAME)?;
+    let log_id = LogId::package_log::<Sha256>(&name);
+    let url = Url::parse(config.home_url.as_ref().unwrap())?
+        .join(&paths::publish_package_record(&log_id))
+        .unwrap();
+
     let signing_key = test_signing_key();
     let record = ProtoEnvelope::signed_contents(
         &signing_key,
@@ -351,35 +423,34 @@ async fn test_invalid_signature(config: &Config) -> Result<()> {
                 hash_algorithm: warg_crypto::hash::HashAlgorithm::Sha256,
                 key: signing_key.public_key(),
             }],
+            entry_signatures: Vec::new(),
+            publish_token: None,
         },
     )?;
-
-    let body = PublishRecordRequest {
+    let mut body = serde_json::to_value(PublishRecordRequest {
         package_name: Cow::Borrowed(&name),
         record: Cow::Owned(ProtoEnvelopeBody::from(record)),
         content_sources: Default::default(),
-    };
-
-    // Update the signature to one that does not match the contents
-    let mut body = serde_json::to_value(&body).unwrap();
-    body["record"]["signature"] = serde_json::Value::String("ecdsa-p256:MEUCIQCzWZBW6ux9LecP66Y+hjmLZTP/hZVz7puzlPTXcRT2wwIgQZO7nxP0nugtw18MwHZ26ROFWcJmgCtKOguK031Y1D0=".to_string());
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post(url)
-        .json(&serde_json::to_value(&body).unwrap())
-        .send()
-        .await?;
-
+        expected_head: None,
+        copublication: None,
+    })?;
+
+    // Envelopes encoded before contents were tagged may not be submitted
+    body["record"]
+        .as_object_mut()
+        .unwrap()
+        .remove("contentType")
+        .expect("record should be tagged");
+    let response = reqwest::Client::new().post(url).json(&body).send().await?;
     let status = response.status();
     let body = response.text().await?;
     assert_eq!(
         status,
-        StatusCode::UNAUTHORIZED,
+        StatusCode::BAD_REQUEST,
         "unexpected response from server: {status}\n{body}",
     );
     assert!(
-        body.contains("verification failed"),
+        body.contains("not tagged"),
         "unexpected response body: {body}"
     );
 
@@ -470,6 +541,145 @@ async fn test_fetch_package_names(config: &Config) -> Result<()> {
     Ok(())
 }
 
+async fn test_search(config: &Config) -> Result<()> {
+    let client = create_client(config)?;
+    let name = PackageName::new("test:component")?;
+
+    assert_eq!(client.search("comp", None).await?, vec![name.clone()]);
+    assert_eq!(client.search("test component", Some(1)).await?, vec![name]);
+    assert!(client.search("missing", None).await?.is_empty());
+
+    Ok(())
+}
+
+async fn test_key_records(config: &Config) -> Result<()> {
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let key_id = test_signing_key().public_key().fingerprint();
+    let name = PackageName::new("test:component")?;
+
+    let response = client
+        .key_records(None, &key_id, KeyRecordsQuery::default())
+        .await?;
+    assert!(!response.more);
+    assert!(response
+        .records
+        .windows(2)
+        .all(|w| w[0].registry_index < w[1].registry_index));
+    let record = response
+        .records
+        .iter()
+        .find(|r| r.name.as_ref() == Some(&name))
+        .context("expected a record for `test:component`")?;
+    assert_eq!(record.log_id, LogId::package_log::<Sha256>(&name));
+
+    // Page through the records one at a time
+    let mut since = None;
+    let mut paged = Vec::new();
+    loop {
+        let response = client
+            .key_records(
+                None,
+                &key_id,
+                KeyRecordsQuery {
+                    since,
+                    limit: Some(1),
+                },
+            )
+            .await?;
+        since = response.records.last().map(|r| r.registry_index);
+        paged.extend(response.records);
+        if !response.more {
+            break;
+        }
+    }
+    assert_eq!(paged, response.records);
+
+    let unknown_key = PrivateKey::from(p256::ecdsa::SigningKey::random(&mut OsRng));
+    let response = client
+        .key_records(
+            None,
+            &unknown_key.public_key().fingerprint(),
+            KeyRecordsQuery::default(),
+        )
+        .await?;
+    assert!(response.records.is_empty());
+
+    Ok(())
+}
+
+async fn test_conditional_fetch(config: &Config) -> Result<()> {
+    let url = Url::parse(config.home_url.as_ref().unwrap())?
+        .join(paths::fetch_checkpoint())
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let response = client.get(url.clone()).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .context("expected the checkpoint to have an entity tag")?
+        .clone();
+
+    // An unchanged checkpoint is not sent again
+    let response = client
+        .get(url.clone())
+        .header(reqwest::header::IF_NONE_MATCH, &etag)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert!(response.bytes().await?.is_empty());
+
+    let response = client
+        .get(url)
+        .header(reqwest::header::IF_NONE_MATCH, "\"sha256:stale\"")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The API client revalidates its cached checkpoint
+    let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
+    let checkpoint = client.latest_checkpoint(None).await?;
+    assert_eq!(client.latest_checkpoint(None).await?, checkpoint);
+
+    let log_id = LogId::package_log::<Sha256>(&PackageName::new("test:component")?);
+    let request = || FetchLogsRequest {
+        log_length: checkpoint.as_ref().checkpoint.log_length,
+        limit: None,
+        operator: None,
+        packages: Cow::Owned([(log_id.clone(), None)].into_iter().collect()),
+    };
+
+    let etag = match client
+        .fetch_logs_if_none_match(None, request(), None)
+        .await?
+    {
+        api::Conditional::Modified { value, etag } => {
+            assert!(!value.operator.is_empty());
+            etag.context("expected the log page to have an entity tag")?
+        }
+        api::Conditional::NotModified => panic!("expected the log page to be returned"),
+    };
+    assert!(matches!(
+        client
+            .fetch_logs_if_none_match(None, request(), Some(&etag))
+            .await?,
+        api::Conditional::NotModified
+    ));
+
+    // A different page has a different entity tag
+    let mut different = request();
+    different.limit = Some(1);
+    assert!(matches!(
+        client
+            .fetch_logs_if_none_match(None, different, Some(&etag))
+            .await?,
+        api::Conditional::Modified { .. }
+    ));
+
+    Ok(())
+}
+
 async fn test_get_ledger(config: &Config) -> Result<()> {
     let client = api::Client::new(config.home_url.as_ref().unwrap(), None)?;
 
@@ -21,7 +21,9 @@ use warg_crypto::{
 use warg_protocol::{operator, registry::PackageName};
 use warg_server::{
     datastore::DataStore,
+    in_process::InProcessServer,
     policy::{content::WasmContentPolicy, record::AuthorizedKeyPolicy},
+    services::{KeyIndex, SearchIndex},
     Config, Server,
 };
 use wit_parser::{Resolve, UnresolvedPackage};
@@ -111,6 +113,15 @@ fn thread_test_logging() -> DefaultGuard {
     tracing::subscriber::set_default(subscriber)
 }
 
+/// Creates the configuration of a test server rooted at the given directory.
+pub fn server_config(root: &Path) -> Config {
+    Config::new(test_operator_key(), test_namespaces(), root.join("server"))
+        .with_checkpoint_interval(Duration::from_millis(100))
+        .with_content_policy(WasmContentPolicy::default()) // For the tests, we assume only wasm content is allowed.
+        .with_search_index(SearchIndex::default())
+        .with_key_index(KeyIndex::default())
+}
+
 /// Spawns a server as a background task.
 pub async fn spawn_server(
     root: &Path,
@@ -118,14 +129,7 @@ pub async fn spawn_server(
     data_store: Option<Box<dyn DataStore>>,
     authorized_keys: Option<Vec<(String, KeyID)>>,
 ) -> Result<(ServerInstance, warg_client::Config)> {
-    let _subscriber_guard = thread_test_logging();
-
-    let shutdown = CancellationToken::new();
-    let mut config = Config::new(test_operator_key(), test_namespaces(), root.join("server"))
-        .with_addr(([127, 0, 0, 1], 0))
-        .with_shutdown(shutdown.clone().cancelled_owned())
-        .with_checkpoint_interval(Duration::from_millis(100))
-        .with_content_policy(WasmContentPolicy::default()); // For the tests, we assume only wasm content is allowed.
+    let mut config = server_config(root);
 
     if let Some(content_url) = content_base_url {
         config = config.with_content_base_url(content_url);
@@ -144,6 +148,20 @@ pub async fn spawn_server(
         config = config.with_boxed_data_store(store);
     }
 
+    spawn_server_with_config(root, config).await
+}
+
+pub async fn spawn_server_with_config(
+    root: &Path,
+    config: Config,
+) -> Result<(ServerInstance, warg_client::Config)> {
+    let _subscriber_guard = thread_test_logging();
+
+    let shutdown = CancellationToken::new();
+    let config = config
+        .with_addr(([127, 0, 0, 1], 0))
+        .with_shutdown(shutdown.clone().cancelled_owned());
+
     let server = Server::new(config).initialize().await?;
 
     let addr = server.local_addr()?;
@@ -170,11 +188,43 @@ pub async fn spawn_server(
         ignore_federation_hints: false,
         auto_accept_federation_hints: false,
         disable_interactive: true,
+        storage_layout: Default::default(),
     };
 
     Ok((instance, config))
 }
 
+/// Starts a server in-process, without a listening socket.
+///
+/// Clients must use the server's transport with [`create_in_process_client`].
+pub async fn spawn_in_process_server(
+    root: &Path,
+) -> Result<(InProcessServer, warg_client::Config)> {
+    let server = Server::new(server_config(root)).in_process().await?;
+
+    let config = warg_client::Config {
+        home_url: Some(server.url().to_string()),
+        registries_dir: Some(root.join("registries")),
+        content_dir: Some(root.join("content")),
+        namespace_map_path: Some(root.join("namespaces")),
+        keys: IndexSet::new(),
+        keyring_auth: false,
+        ignore_federation_hints: false,
+        auto_accept_federation_hints: false,
+        disable_interactive: true,
+        storage_layout: Default::default(),
+    };
+
+    Ok((server, config))
+}
+
+pub fn create_in_process_client(
+    server: &InProcessServer,
+    config: &warg_client::Config,
+) -> Result<FileSystemClient> {
+    Ok(create_client(config)?.with_transport(server.transport()))
+}
+
 pub async fn publish(
     client: &FileSystemClient,
     name: &PackageName,
@@ -197,7 +247,9 @@ pub async fn publish(
     }
     entries.push(PublishEntry::Release {
         version: version.parse().unwrap(),
-        content: digest.clone(),
+        content: digest,
+        encryption: None,
+        manifest: None,
     });
 
     let record_id = client